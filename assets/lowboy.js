@@ -0,0 +1,229 @@
+// Lowboy browser client.
+//
+// Small helpers for lowboy's realtime and form conventions, so apps don't need to write their own
+// glue code:
+//
+//   const events = Lowboy.events();
+//   events.on("NewPost", (data, event) => { ... });
+//
+//   await Lowboy.fetch("/post", { method: "POST", body: new FormData(form) });
+//
+// It also drives lowboy's optional layout snippets (see `lowboy::view::ProgressBar`,
+// `lowboy::view::ConnectionBanner` and `lowboy::view::Announcements`) when they're on the page.
+(function (global) {
+  "use strict";
+
+  const VERSION = "__LOWBOY_CLIENT_VERSION__";
+  const DEFAULT_EVENTS_URL = "/events";
+  const ANNOUNCEMENTS_URL = "/announcements";
+  // Navigations quicker than this don't show the progress bar, to avoid flickering.
+  const PROGRESS_DELAY = 150;
+
+  // A `fetch` wrapper that includes cookies and marks the request as coming from script.
+  function lowboyFetch(input, init) {
+    init = Object.assign({ credentials: "same-origin" }, init || {});
+
+    const headers = new Headers(init.headers || {});
+    if (!headers.has("X-Requested-With")) {
+      headers.set("X-Requested-With", "XMLHttpRequest");
+    }
+    init.headers = headers;
+
+    return global.fetch(input, init);
+  }
+
+  // A reconnecting `EventSource` wrapper for the lowboy events endpoint.
+  //
+  // Events sent while the connection was down aren't replayed, so pages that need to stay in sync
+  // should reload what they show on `open` after a `reconnect`.
+  function LowboyEvents(url, options) {
+    this.url = url || DEFAULT_EVENTS_URL;
+    this.options = Object.assign({ minBackoff: 1000, maxBackoff: 30000 }, options || {});
+    this.handlers = {};
+    this.listeners = { open: [], error: [], reconnect: [] };
+    this.backoff = this.options.minBackoff;
+    this.source = null;
+    this.closed = false;
+    this.connect();
+    this.on("announcement", refreshAnnouncements);
+  }
+
+  LowboyEvents.prototype.connect = function () {
+    const source = new EventSource(this.url, { withCredentials: true });
+    this.source = source;
+
+    source.onopen = () => {
+      this.backoff = this.options.minBackoff;
+      setConnected(true);
+      this.emit("open");
+    };
+
+    source.onerror = (error) => {
+      this.emit("error", error);
+      if (source.readyState === EventSource.CLOSED && !this.closed) {
+        setConnected(false);
+        this.scheduleReconnect();
+      }
+    };
+
+    Object.keys(this.handlers).forEach((name) => this.bind(name));
+  };
+
+  LowboyEvents.prototype.scheduleReconnect = function () {
+    const delay = this.backoff;
+    this.backoff = Math.min(this.backoff * 2, this.options.maxBackoff);
+    this.emit("reconnect", delay);
+    setTimeout(() => {
+      if (!this.closed) {
+        this.connect();
+      }
+    }, delay);
+  };
+
+  LowboyEvents.prototype.bind = function (name) {
+    this.source.addEventListener(name, (event) => {
+      let data = event.data;
+      try {
+        data = JSON.parse(event.data);
+      } catch (_) {
+        // Not JSON (e.g. an HTML fragment), pass it through as-is.
+      }
+
+      (this.handlers[name] || []).forEach((handler) => handler(data, event));
+    });
+  };
+
+  // Listen for a named server event.
+  LowboyEvents.prototype.on = function (name, handler) {
+    if (name in this.listeners) {
+      this.listeners[name].push(handler);
+      return this;
+    }
+
+    if (!this.handlers[name]) {
+      this.handlers[name] = [];
+      if (this.source) {
+        this.bind(name);
+      }
+    }
+    this.handlers[name].push(handler);
+
+    return this;
+  };
+
+  // Listen for patches to one model, e.g. `events.onPatch("post", 5, (patch) => { ... })`, to keep
+  // a collaborative form up to date (see `lowboy::collab`). Patches only say which version the
+  // model is at now, so the handler reloads it. Patches older than `version`, and later ones that
+  // were already seen, are ignored.
+  LowboyEvents.prototype.onPatch = function (model, id, handler, version) {
+    let current = version || 0;
+
+    return this.on("patch", (patch, event) => {
+      if (patch.model !== model || patch.id !== id || patch.version <= current) {
+        return;
+      }
+      current = patch.version;
+      handler(patch, event);
+    });
+  };
+
+  LowboyEvents.prototype.emit = function (name, value) {
+    (this.listeners[name] || []).forEach((handler) => handler(value));
+  };
+
+  LowboyEvents.prototype.close = function () {
+    this.closed = true;
+    if (this.source) {
+      this.source.close();
+    }
+  };
+
+  // Show (or hide) the connection lost banner, if the page has one.
+  function setConnected(connected) {
+    const banner = document.querySelector("[data-lowboy-connection]");
+    if (banner) {
+      banner.hidden = connected;
+    }
+  }
+
+  // Reload the announcements, if the page has them, when a new one becomes active. The server
+  // only says that one did, since it may not be for this user.
+  function refreshAnnouncements() {
+    const announcements = document.querySelector("[data-lowboy-announcements]");
+    if (!announcements) {
+      return;
+    }
+
+    lowboyFetch(ANNOUNCEMENTS_URL)
+      .then((response) => (response.ok ? response.text() : null))
+      .then((html) => {
+        if (html !== null) {
+          announcements.outerHTML = html;
+        }
+      })
+      .catch(() => {});
+  }
+
+  // Show the progress bar while htmx navigates, i.e. for boosted links and other GET requests.
+  // Other requests (e.g. autosaving a draft) happen in the background, so they don't show it.
+  function watchNavigation() {
+    let pending = 0;
+    let timer = null;
+
+    function isNavigation(event) {
+      const detail = event.detail || {};
+      return detail.boosted || (detail.requestConfig && detail.requestConfig.verb === "get");
+    }
+
+    document.addEventListener("htmx:beforeRequest", (event) => {
+      const bar = document.querySelector("[data-lowboy-progress]");
+      if (!bar || !isNavigation(event)) {
+        return;
+      }
+
+      pending += 1;
+      if (pending === 1) {
+        timer = setTimeout(() => {
+          bar.removeAttribute("data-done");
+          bar.setAttribute("data-active", "");
+        }, PROGRESS_DELAY);
+      }
+    });
+
+    document.addEventListener("htmx:afterRequest", (event) => {
+      const bar = document.querySelector("[data-lowboy-progress]");
+      if (!bar || !isNavigation(event) || pending === 0) {
+        return;
+      }
+
+      pending -= 1;
+      if (pending === 0) {
+        clearTimeout(timer);
+        if (bar.hasAttribute("data-active")) {
+          bar.removeAttribute("data-active");
+          bar.setAttribute("data-done", "");
+        }
+      }
+    });
+  }
+
+  // The htmx SSE extension manages its own connection, so follow its events as well.
+  function watchConnection() {
+    document.addEventListener("htmx:sseOpen", () => setConnected(true));
+    document.addEventListener("htmx:sseError", () => setConnected(false));
+    global.addEventListener("offline", () => setConnected(false));
+    global.addEventListener("online", () => setConnected(true));
+  }
+
+  watchNavigation();
+  watchConnection();
+
+  global.Lowboy = {
+    version: VERSION,
+    fetch: lowboyFetch,
+    events: function (url, options) {
+      return new LowboyEvents(url, options);
+    },
+    setConnected: setConnected,
+  };
+})(window);
@@ -0,0 +1,107 @@
+//! Benchmarks for the model loading paths used on every authenticated request.
+//!
+//! Run with `cargo bench --bench model`.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
+use diesel_async::AsyncConnection as _;
+use diesel_migrations::MigrationHarness as _;
+use lowboy::clock::SystemClock;
+use lowboy::model::{Email, Model as _, User, UserModel as _, UserRecord};
+use lowboy::secret::SequentialSecretGenerator;
+use lowboy::{Connection, MIGRATIONS};
+use tokio::runtime::Runtime;
+use tokio::sync::Mutex;
+
+const SEEDED_USERS: i32 = 1_000;
+
+async fn seeded_connection() -> Connection {
+    let mut conn = SyncConnectionWrapper::establish(":memory:")
+        .await
+        .expect("should be able to open an in-memory database");
+
+    conn.spawn_blocking(|conn| {
+        conn.run_pending_migrations(MIGRATIONS)
+            .expect("migrations should run");
+        Ok(())
+    })
+    .await
+    .expect("migrations should run");
+
+    let secrets = SequentialSecretGenerator::new("bench");
+    for n in 0..SEEDED_USERS {
+        User::new(
+            &format!("bench-{n}"),
+            &format!("bench-{n}@example.com"),
+            Some("not-a-real-password-hash"),
+            None,
+            &SystemClock,
+            &secrets,
+            &mut conn,
+        )
+        .await
+        .expect("should be able to seed users");
+    }
+
+    conn
+}
+
+/// Load the user by first reading its record, then reading its email in a second query.
+async fn load_from_record(id: i32, conn: &mut Connection) -> User {
+    let record = UserRecord::read(id, conn).await.expect("user should exist");
+    let email = Email::find_by_user_id(id, conn)
+        .await
+        .expect("email query should succeed")
+        .expect("email should exist");
+
+    User {
+        id: record.id,
+        username: record.username,
+        email,
+        password: record.password,
+        access_token: record.access_token,
+        service_account: record.service_account,
+        roles: None,
+        permissions: None,
+    }
+}
+
+fn user_loading(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("should be able to start a tokio runtime");
+    let conn = Mutex::new(runtime.block_on(seeded_connection()));
+    let id = SEEDED_USERS / 2;
+
+    let mut group = c.benchmark_group("user");
+
+    group.bench_with_input(BenchmarkId::new("load_joined", id), &id, |b, &id| {
+        b.to_async(&runtime).iter(|| async {
+            User::load(id, &mut *conn.lock().await)
+                .await
+                .expect("user should exist")
+        })
+    });
+
+    group.bench_with_input(BenchmarkId::new("load_from_record", id), &id, |b, &id| {
+        b.to_async(&runtime)
+            .iter(|| async { load_from_record(id, &mut *conn.lock().await).await })
+    });
+
+    group.bench_with_input(
+        BenchmarkId::new("load_with_roles_and_permissions", id),
+        &id,
+        |b, &id| {
+            b.to_async(&runtime).iter(|| async {
+                let conn = &mut *conn.lock().await;
+                let mut user = User::load(id, conn).await.expect("user should exist");
+                user.with_roles_and_permissions(conn)
+                    .await
+                    .expect("roles and permissions should load");
+                user
+            })
+        },
+    );
+
+    group.finish();
+}
+
+criterion_group!(benches, user_loading);
+criterion_main!(benches);
@@ -1,9 +1,11 @@
 use anyhow::Result;
-use vergen_gitcl::{Emitter, GitclBuilder};
+use vergen_gitcl::{BuildBuilder, Emitter, GitclBuilder, RustcBuilder};
 
 fn main() -> Result<()> {
     Emitter::default()
+        .add_instructions(&BuildBuilder::all_build()?)?
         .add_instructions(&GitclBuilder::all_git()?)?
+        .add_instructions(&RustcBuilder::all_rustc()?)?
         .emit()?;
 
     Ok(())
@@ -1,13 +1,13 @@
 use anyhow::Context as _;
-use axum::routing::{get, post};
+use axum::routing::{delete, get, post};
 use axum::Router;
-use axum_login::login_required;
 use diesel_async::pooled_connection::deadpool::Pool;
-use lettre::{AsyncSmtpTransport, Tokio1Executor};
 use lowboy::auth::{LowboyLoginForm, RegistrationDetails};
-use lowboy::model::User as LowboyUser;
-use lowboy::{context, App, AppContext, Connection, Context, Events, LowboyAuth};
-use tokio_cron_scheduler::JobScheduler;
+use lowboy::controller::BuildInfo;
+use lowboy::jobs::Scheduler;
+use lowboy::mailer::Mailer;
+use lowboy::model::{PermissionDef, User as LowboyUser};
+use lowboy::{context, App, AppContext, Connection, Context, Events};
 
 use crate::controller;
 use crate::form::RegisterForm;
@@ -19,8 +19,8 @@ use crate::view::{self, Layout};
 pub struct DemoContext {
     pub database: Pool<Connection>,
     pub events: Events,
-    pub scheduler: JobScheduler,
-    pub mailer: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    pub scheduler: Scheduler,
+    pub mailer: Option<Box<dyn Mailer>>,
     #[allow(dead_code)]
     pub my_custom_thing: Vec<String>,
 }
@@ -30,8 +30,8 @@ impl AppContext for DemoContext {
     fn create(
         database: Pool<Connection>,
         events: Events,
-        scheduler: JobScheduler,
-        mailer: Option<AsyncSmtpTransport<Tokio1Executor>>,
+        scheduler: Scheduler,
+        mailer: Option<Box<dyn Mailer>>,
     ) -> Result<Self, context::Error> {
         Ok(Self {
             database,
@@ -95,12 +95,12 @@ impl Context for DemoContext {
         &self.events
     }
 
-    fn scheduler(&self) -> &JobScheduler {
+    fn scheduler(&self) -> &Scheduler {
         &self.scheduler
     }
 
-    fn mailer(&self) -> Option<&AsyncSmtpTransport<Tokio1Executor>> {
-        self.mailer.as_ref()
+    fn mailer(&self) -> Option<&dyn Mailer> {
+        self.mailer.as_deref()
     }
 }
 
@@ -124,12 +124,24 @@ impl App<DemoContext> for Demo {
         "Demo App"
     }
 
+    fn build_info() -> BuildInfo {
+        lowboy::build_info!()
+    }
+
+    fn permissions() -> &'static [PermissionDef] {
+        &[PermissionDef::new(
+            controller::post::DELETE_ANY_POST,
+            "Delete posts written by other users.",
+        )]
+    }
+
     fn routes() -> Router<DemoContext> {
         Router::new()
             .route("/", get(controller::home))
             .route("/post", post(controller::post::create))
+            .route("/post/:id", delete(controller::post::delete))
             // Previous routes require authentication.
-            .route_layer(login_required!(LowboyAuth, login_url = "/login"))
+            .route_layer(lowboy::login_required!())
     }
 }
 
@@ -145,6 +157,6 @@ impl App<DemoContext> for Demo {
 //             .route("/post", post(controller::post::create))
 //             .route("/", get(controller::home))
 //             // Previous routes require authentication.
-//             .route_layer(login_required!(LowboyAuth, login_url = "/login"))
+//             .route_layer(lowboy::login_required!())
 //     }
 // }
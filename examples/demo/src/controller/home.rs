@@ -1,22 +1,71 @@
+use axum::extract::{Query, State};
 use axum::response::IntoResponse;
-use lowboy::error::LowboyError;
-use lowboy::extract::{DatabaseConnection, EnsureAppUser};
-use lowboy::lowboy_view;
-use lowboy::model::UserModel;
+use chrono::TimeDelta;
+use lowboy::cache::{cache_fragment, FragmentKey};
+use lowboy::model::{Draft, PageQuery};
+use lowboy::prelude::*;
+use lowboy::view::Pagination;
 
 use crate::app::{Demo, DemoContext};
+use crate::controller::post::{can_delete, PostCreateForm, DELETE_ANY_POST, DRAFT_FORM};
 use crate::model::Post;
-use crate::view::Home;
+use crate::view::{self, Home, PostList};
+
+/// How many posts are on each page of the home page.
+const POSTS_PER_PAGE: i64 = 5;
 
 #[axum::debug_handler]
 pub async fn home(
+    State(context): State<DemoContext>,
     EnsureAppUser(user): EnsureAppUser<Demo, DemoContext>,
     DatabaseConnection(mut conn): DatabaseConnection,
+    Query(query): Query<PageQuery>,
 ) -> Result<impl IntoResponse, LowboyError> {
-    let posts = Post::list(&mut conn, Some(5)).await?;
+    let draft = Draft::restore::<PostCreateForm>(
+        user.id(),
+        DRAFT_FORM,
+        context.clock().now(),
+        &mut conn,
+    )
+    .await?
+    .map(|form| form.message)
+    .unwrap_or_default();
+
+    // Each page of posts is the same for everyone who can delete the same posts, until a post is
+    // created or deleted.
+    let viewer = if user.has_permission(DELETE_ANY_POST) {
+        "any".to_string()
+    } else {
+        user.id().to_string()
+    };
+    let user = &user;
+    let conn = &mut conn;
+    let page = query.page();
+    let posts = cache_fragment(
+        &context,
+        FragmentKey::new(format!("home:posts:{page}:{viewer}")).depends_on("post"),
+        TimeDelta::minutes(5),
+        move || async move {
+            let posts = Post::list(page, POSTS_PER_PAGE, conn).await?;
+            let list = PostList {
+                pagination: Pagination::new(page as u32, posts.pages() as u32, "/"),
+                posts: posts
+                    .items
+                    .into_iter()
+                    .map(|post| view::Post {
+                        can_delete: can_delete(user, &post),
+                        post,
+                    })
+                    .collect(),
+            };
+            Ok::<_, LowboyError>(list.to_string())
+        },
+    )
+    .await?;
 
     let template = Home {
         show_post_form: user.is_authenticated(),
+        draft,
         posts,
     };
 
@@ -1,20 +1,26 @@
-use axum::extract::Form;
+use axum::extract::{Form, State};
 use axum::response::IntoResponse;
-use lowboy::error::LowboyError;
-use lowboy::extract::{DatabaseConnection, EnsureAppUser};
-use lowboy::model::{Model as _, UserModel};
+use lowboy::model::Draft;
+use lowboy::prelude::*;
 use serde::Deserialize;
 
 use crate::app::{Demo, DemoContext};
 use crate::model::Post;
 use crate::view;
 
+/// The name the post form's drafts are saved under.
+pub const DRAFT_FORM: &str = "post";
+
+/// The permission to delete posts written by other users.
+pub const DELETE_ANY_POST: &str = "delete any post";
+
 #[derive(Debug, Deserialize)]
 pub struct PostCreateForm {
-    message: String,
+    pub message: String,
 }
 
 pub async fn create(
+    State(context): State<DemoContext>,
     EnsureAppUser(author): EnsureAppUser<Demo, DemoContext>,
     DatabaseConnection(mut conn): DatabaseConnection,
     Form(input): Form<PostCreateForm>,
@@ -27,9 +33,36 @@ pub async fn create(
         .save(&mut conn)
         .await?;
     let post = Post::load(record.id, &mut conn).await?;
+    Draft::discard(author.id(), DRAFT_FORM, &mut conn).await?;
+    context
+        .on_model_event(&ModelEvent::created("post", post.id))
+        .await?;
 
-    let form = view::PostForm {};
-    let post = view::Post { post };
+    let form = view::PostForm::default();
+    let can_delete = can_delete(&author, &post);
+    let post = view::Post { post, can_delete };
 
     Ok(format!("{form}{post}"))
 }
+
+pub async fn delete(
+    State(context): State<DemoContext>,
+    EnsureAppUser(user): EnsureAppUser<Demo, DemoContext>,
+    LoadPath(post): LoadPath<Post>,
+    DatabaseConnection(mut conn): DatabaseConnection,
+) -> Result<impl IntoResponse, LowboyError> {
+    lowboy::authorize_owner!(user, &post, bypass = DELETE_ANY_POST)?;
+
+    let id = post.id;
+    post.delete_record(&mut conn).await?;
+    context
+        .on_model_event(&ModelEvent::deleted("post", id))
+        .await?;
+
+    Ok(String::new())
+}
+
+/// Whether `user` can delete `post`, so whether it's shown a delete button.
+pub fn can_delete(user: &impl UserModel, post: &Post) -> bool {
+    lowboy::auth::authorize_owner(user, post, &[DELETE_ANY_POST]).is_ok()
+}
@@ -2,7 +2,7 @@ use diesel::dsl::{AsSelect, Select, SqlTypeOf};
 use diesel::prelude::*;
 use diesel::sqlite::Sqlite;
 use diesel_async::RunQueryDsl;
-use lowboy::model::{Model, UserModel, UserRecord};
+use lowboy::model::{Model, Owned, Paginate, Paginated, UserModel, UserRecord};
 use lowboy::Connection;
 
 use crate::model::User;
@@ -16,7 +16,12 @@ pub struct Post {
 }
 
 impl Post {
-    pub async fn list(conn: &mut Connection, limit: Option<i64>) -> QueryResult<Vec<Self>> {
+    /// The `page`th page of posts, newest first.
+    pub async fn list(
+        page: i64,
+        per_page: i64,
+        conn: &mut Connection,
+    ) -> QueryResult<Paginated<Self>> {
         // @TODO this isn't very nice that we have to use .assume_null_is_not_found() on anything
         // that touches the user model. This is because of how we're loading roles/permissions via
         // json_object/json_group_array. If no users are found in query, it returns a row of nulls
@@ -33,13 +38,19 @@ impl Post {
         // caching solution for models now, and ensuring that cache can be invalidated e.g. when a
         // new role is added to a user or a new permission is added to a role.
         Post::query()
-            .limit(limit.unwrap_or(100))
             .order_by(post::id.desc())
-            .load(conn)
+            .paginate(page, per_page)
+            .load_page(conn)
             .await
     }
 }
 
+impl Owned for Post {
+    fn owner_id(&self) -> i32 {
+        self.user.id()
+    }
+}
+
 #[diesel::dsl::auto_type]
 fn post_from_clause() -> _ {
     let user_from_clause: <User as Model>::FromClause = <User as Model>::from_clause();
@@ -62,6 +73,8 @@ impl Model for Post {
     type FromClause = post_from_clause;
     type Query = Select<Self::FromClause, Self::SelectClause>;
 
+    const TABLE_NAME: &'static str = "post";
+
     fn query() -> Self::Query {
         Self::from_clause().select(Self::select_clause())
     }
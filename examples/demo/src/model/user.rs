@@ -60,6 +60,8 @@ impl Model for User {
     type FromClause = user_from_clause;
     type Query = Select<Self::FromClause, Self::SelectClause>;
 
+    const TABLE_NAME: &'static str = "user";
+
     fn query() -> Self::Query {
         Self::from_clause().select(Self::select_clause())
     }
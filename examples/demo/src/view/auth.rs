@@ -3,7 +3,10 @@ use lowboy::{
         LoginForm, LowboyEmailVerificationView, LowboyLoginView, LowboyRegisterView,
         RegistrationForm,
     },
+    bot::BotFields,
+    form::FormErrors,
     model::unverified_email,
+    view::ProviderButtons,
 };
 use rinja::Template;
 
@@ -13,6 +16,9 @@ use crate::form::DemoRegistrationForm;
 #[template(path = "pages/auth/login.html")]
 pub struct Login<T: LoginForm> {
     pub form: T,
+    pub errors: FormErrors,
+    pub bot_fields: BotFields,
+    pub providers: ProviderButtons,
 }
 
 impl<T: LoginForm + Clone + Default> LowboyLoginView<T> for Login<T> {
@@ -20,12 +26,30 @@ impl<T: LoginForm + Clone + Default> LowboyLoginView<T> for Login<T> {
         self.form = form;
         self
     }
+
+    fn set_errors(&mut self, errors: FormErrors) -> &mut Self {
+        self.errors = errors;
+        self
+    }
+
+    fn set_bot_fields(&mut self, bot_fields: BotFields) -> &mut Self {
+        self.bot_fields = bot_fields;
+        self
+    }
+
+    fn set_providers(&mut self, providers: ProviderButtons) -> &mut Self {
+        self.providers = providers;
+        self
+    }
 }
 
 #[derive(Clone, Template, Default)]
 #[template(path = "pages/auth/register.html")]
 pub struct Register<T: RegistrationForm + DemoRegistrationForm> {
     pub form: T,
+    pub errors: FormErrors,
+    pub bot_fields: BotFields,
+    pub providers: ProviderButtons,
 }
 
 impl<T: RegistrationForm + DemoRegistrationForm + Clone + Default> LowboyRegisterView<T>
@@ -35,6 +59,21 @@ impl<T: RegistrationForm + DemoRegistrationForm + Clone + Default> LowboyRegiste
         self.form = form;
         self
     }
+
+    fn set_errors(&mut self, errors: FormErrors) -> &mut Self {
+        self.errors = errors;
+        self
+    }
+
+    fn set_bot_fields(&mut self, bot_fields: BotFields) -> &mut Self {
+        self.bot_fields = bot_fields;
+        self
+    }
+
+    fn set_providers(&mut self, providers: ProviderButtons) -> &mut Self {
+        self.providers = providers;
+        self
+    }
 }
 
 #[derive(Clone, Template, Default)]
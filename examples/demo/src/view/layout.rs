@@ -1,6 +1,6 @@
 use axum_messages::Message;
 use lowboy::model::UserModel;
-use lowboy::view::{LayoutContext, LowboyLayout};
+use lowboy::view::{Announcements, LayoutContext, LowboyLayout};
 use rinja::Template;
 
 use crate::model::DemoUser;
@@ -13,6 +13,7 @@ pub struct Layout<T: UserModel + DemoUser> {
     pub content: String,
     pub user: Option<T>,
     pub context: LayoutContext,
+    pub announcements: Announcements,
 }
 
 impl<T: UserModel + DemoUser> LowboyLayout<T> for Layout<T> {
@@ -35,4 +36,9 @@ impl<T: UserModel + DemoUser> LowboyLayout<T> for Layout<T> {
         self.user = user;
         self
     }
+
+    fn set_announcements(&mut self, announcements: Announcements) -> &mut Self {
+        self.announcements = announcements;
+        self
+    }
 }
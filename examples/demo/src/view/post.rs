@@ -1,3 +1,4 @@
+use lowboy::view::Pagination;
 use rinja::Template;
 
 use crate::model;
@@ -7,4 +8,14 @@ use crate::model::DemoUser as _;
 #[template(path = "components/post.html")]
 pub struct Post {
     pub post: model::Post,
+    /// Whether the viewer can delete the post, so it's shown a delete button.
+    pub can_delete: bool,
+}
+
+/// A page of posts, and links to the other pages.
+#[derive(Clone, Template)]
+#[template(path = "components/post-list.html")]
+pub struct PostList {
+    pub posts: Vec<Post>,
+    pub pagination: Pagination,
 }
@@ -2,4 +2,7 @@ use rinja::Template;
 
 #[derive(Clone, Default, Template)]
 #[template(path = "components/post-form.html")]
-pub struct PostForm {}
+pub struct PostForm {
+    /// The restored draft of the post, if any.
+    pub draft: String,
+}
@@ -2,6 +2,11 @@ import type { Config } from "tailwindcss";
 
 export default {
   content: ["./templates/**/*.html"],
+  // Follow the system theme unless the user picked one, which sets a class on <html>.
+  darkMode: ["variant", [
+    "@media (prefers-color-scheme: dark) { &:not(.light *) }",
+    "&:is(.dark *)",
+  ]],
   theme: {
     extend: {
       colors: {
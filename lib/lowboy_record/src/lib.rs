@@ -1,4 +1,6 @@
 #[doc(hidden)]
+pub use async_trait::async_trait;
+#[doc(hidden)]
 pub use defile::defile;
 #[doc = "Apply a `macro_rules!` macro using an `#[apply(macro_name!)]` attribute (provided by `macro_rules_attribute` crate)"]
 pub use macro_rules_attribute::apply;
@@ -6,7 +8,7 @@ pub use macro_rules_attribute::apply;
 pub use paste::paste;
 
 pub mod prelude {
-    pub use crate::{apply, lowboy_record, HasOne, Related};
+    pub use crate::{apply, lowboy_record, HasOne, Operation, Related};
 }
 
 /// A marker to designate a field as being a related model.
@@ -15,8 +17,78 @@ pub struct Related<T>(T);
 /// A marker to designate a field as being a one-to-one relationship.
 pub struct HasOne<T>(T);
 
+/// What an upsert, such as a generated `create_or_update_by_*` method, did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    /// No row conflicted, so a new one was inserted.
+    Created,
+    /// A row conflicted, so it was updated instead.
+    Updated,
+}
+
 /// Generate record boilerplate for a model.
 ///
+/// # Field attributes
+///
+/// Fields can be given `#[lowboy(...)]` attributes, which must be their first attributes, for
+/// columns that don't map directly onto them:
+///
+/// - `column = "name"` stores the field in the column `name`, rather than the one named after it.
+/// - `type = Type` reads and writes the column as `Type`, converting it to and from the field's
+///   type with `From`/`Into`, e.g. for JSON stored as text.
+/// - `unique` generates a `find_by_<field>` method on the model, which returns the model with the
+///   given value, if there is one, and a `create_or_update_by_<field>` method on the new record,
+///   which updates the row with the same value instead of failing if there is one, returning the
+///   record along with the [`Operation`] performed. It goes in its own attribute, before any other
+///   `#[lowboy(...)]` attribute, e.g. `#[lowboy(unique)] #[lowboy(column = "name")]`.
+/// - `soft_delete` marks the field, an `Option` of a timestamp, as the model's `deleted_at`
+///   column, and implements lowboy's `SoftDelete` for the model, which must be in scope along with
+///   its `Model` implementation. The record must derive `Selectable`. The model's `delete_record`
+///   trashes it instead of deleting it, taking when it was trashed. Like `unique`, it goes in its
+///   own attribute.
+/// - `published_at` and `publish_at` mark the fields, both an `Option` of a timestamp, for when
+///   the model was published and when it's scheduled to be, and implement lowboy's `Publish` for
+///   the model, which must be in scope along with its `Model` implementation. A model needs both
+///   or neither, each in its own attribute.
+///
+/// ```ignore
+/// pub struct Setting {
+///     id: i32,
+///     #[lowboy(column = "name")]
+///     key: String,
+///     #[lowboy(column = "value_json", type = String)]
+///     value: Tags,
+/// }
+/// ```
+///
+/// # Updating and deleting
+///
+/// Models get an `update_record` method, which saves the model's fields to its row, and a
+/// `delete_record` method, which deletes its row (or trashes it, see `soft_delete`).
+///
+/// # Versioning
+///
+/// A `#[lowboy(versioned)]` attribute on the struct, before any other attribute, implements
+/// lowboy's `Versioned` for the model, which must be in scope along with its `Model`
+/// implementation. The model derives serde's `Serialize` and `Deserialize`, as do any models it's
+/// related to, and its record must derive `Insertable`. Its `update_record` and `delete_record`
+/// take the time of the change, and snapshot the model as it was before changing it, in the same
+/// transaction as the change.
+///
+/// ```ignore
+/// #[apply(lowboy_record!)]
+/// #[lowboy(versioned)]
+/// #[derive(Debug, Default, Queryable, Identifiable, Selectable, Insertable)]
+/// #[diesel(table_name = crate::schema::page)]
+/// pub struct Page {
+///     id: i32,
+///     title: String,
+/// }
+///
+/// page.title = input.title;
+/// page.update_record(now, &mut conn).await?;
+/// ```
+///
 /// # Example
 ///
 /// ```
@@ -114,6 +186,19 @@ pub struct HasOne<T>(T);
 /// ```
 #[macro_export(local_inner_macros)]
 macro_rules! lowboy_record {
+    // Versioned models.
+    (
+        #[lowboy(versioned $(,)?)]
+        $(#[$attr:meta])*
+        $pub:vis struct $model:ident {
+            $($fields:tt)*
+        }
+    ) => {
+        internal_record!($(#[$attr])* $pub $model ($($fields)*));
+        internal_model!($pub $model [::serde::Serialize, ::serde::Deserialize] ($($fields)*));
+        internal_impl!($model [versioned] ($($fields)*));
+    };
+
     // Main entrypoint.
     (
         $(#[$attr:meta])*
@@ -125,28 +210,36 @@ macro_rules! lowboy_record {
         // NewModelRecord
         internal_record!($(#[$attr])* $pub $model ($($fields)*));
         // Model
-        internal_model!($pub $model ($($fields)*));
+        internal_model!($pub $model [] ($($fields)*));
         // impl Model
-        internal_impl!($model ($($fields)*));
+        internal_impl!($model [] ($($fields)*));
     };
 }
 
 #[macro_export(local_inner_macros)]
 #[doc(hidden)]
+#[allow(clippy::crate_in_macro_def)]
 macro_rules! internal_record {
     // Done, generate struct.
     (@record
         ()
-        -> { $(#[$attr:meta])* $pub:vis $model:ident $(($field_vis:vis $field:ident : $type:ty))* }
+        -> { $(#[$attr:meta])* $pub:vis $model:ident $(($(#[$field_attr:meta])* $field_vis:vis $field:ident : $type:ty))* }
         [$(($from:ident : $from_type:ty))*]
-        [$(($from_related: ident : $from_related_model:ty))*]
+        [$(($from_related:ident : $from_related_field:ident))*]
     ) => {
         paste! {
             // ModelRecord
             $(#[$attr])*
             #[doc = "A `" $model "` record"]
             $pub struct [<$model Record>] {
-                $($field_vis $field : $type ,)*
+                $($(#[$field_attr])* $field_vis $field : $type ,)*
+            }
+
+            // impl ModelRecord
+            impl [<$model Record>] {
+                // ModelRecord::TABLE_NAME
+                #[doc = "The `" [<$model:snake>] "` table, for the model's `Model::TABLE_NAME`"]
+                pub const TABLE_NAME: &'static str = ::core::stringify!([<$model:snake>]);
             }
 
             // impl From<Model> for <ModelRecord>
@@ -154,7 +247,7 @@ macro_rules! internal_record {
             impl From<$model> for [<$model Record>] {
                 fn from(value: $model) -> Self {
                 $(
-                    let $from_related = value.[<$from_related_model:snake>].id;
+                    let $from_related = value.$from_related_field.id;
                 )*
 
                     Self {
@@ -163,14 +256,111 @@ macro_rules! internal_record {
                     }
                 }
             }
+
+            // UpdateModelRecord
+            #[derive(diesel::AsChangeset)]
+            #[diesel(table_name = crate::schema::[<$model:snake>])]
+            #[diesel(treat_none_as_null = true)]
+            #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+            #[doc = "The changes saved by `" $model "::update_record`"]
+            $pub struct [<Update $model Record>] {
+                $($(#[$field_attr])* $field_vis $field : $type ,)*
+            }
+
+            // impl From<ModelRecord> for UpdateModelRecord
+            #[doc = "Convert from a `" [<$model Record>] "` into `" [<Update $model Record>] "`"]
+            impl From<[<$model Record>]> for [<Update $model Record>] {
+                fn from(value: [<$model Record>]) -> Self {
+                    Self {
+                        $($field : value.$field ,)*
+                    }
+                }
+            }
         }
 
-        internal_new_record!($pub $model ($($field_vis $field : $type ,)*));
+        internal_new_record!($pub $model ($($(#[$field_attr])* $field_vis $field : $type ,)*));
+    };
+
+    // Strip out `#[lowboy(unique)]`, it's only used to generate the model's find_by_* methods.
+    (@record
+        (#[lowboy(unique $(,)?)] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [$($from:tt)*]
+        [$($from_related:tt)*]
+    ) => {
+        internal_record!(@record ($($rest)*) -> { $($output)* } [$($from)*] [$($from_related)*]);
+    };
+
+    // Strip out `#[lowboy(soft_delete)]`, it's only used to implement `SoftDelete` for the model.
+    (@record
+        (#[lowboy(soft_delete $(,)?)] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [$($from:tt)*]
+        [$($from_related:tt)*]
+    ) => {
+        internal_record!(@record ($($rest)*) -> { $($output)* } [$($from)*] [$($from_related)*]);
+    };
+
+    // Strip out `#[lowboy(published_at)]` and `#[lowboy(publish_at)]`, they're only used to
+    // implement `Publish` for the model.
+    (@record
+        (#[lowboy(published_at $(,)?)] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [$($from:tt)*]
+        [$($from_related:tt)*]
+    ) => {
+        internal_record!(@record ($($rest)*) -> { $($output)* } [$($from)*] [$($from_related)*]);
+    };
+
+    (@record
+        (#[lowboy(publish_at $(,)?)] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [$($from:tt)*]
+        [$($from_related:tt)*]
+    ) => {
+        internal_record!(@record ($($rest)*) -> { $($output)* } [$($from)*] [$($from_related)*]);
+    };
+
+    // Convert `#[lowboy(...)]` field attributes into the diesel attributes they stand for.
+    (@record
+        (#[lowboy(column = $column:literal $(,)?)] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [$($from:tt)*]
+        [$($from_related:tt)*]
+    ) => {
+        internal_record!(@record (#[diesel(column_name = $column)] $($rest)*) -> { $($output)* } [$($from)*] [$($from_related)*]);
+    };
+
+    (@record
+        (#[lowboy(type = $sql_type:ty $(,)?)] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [$($from:tt)*]
+        [$($from_related:tt)*]
+    ) => {
+        internal_record!(@record (#[diesel(serialize_as = $sql_type, deserialize_as = $sql_type)] $($rest)*) -> { $($output)* } [$($from)*] [$($from_related)*]);
+    };
+
+    (@record
+        (#[lowboy(column = $column:literal, type = $sql_type:ty $(,)?)] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [$($from:tt)*]
+        [$($from_related:tt)*]
+    ) => {
+        internal_record!(@record (#[diesel(column_name = $column, serialize_as = $sql_type, deserialize_as = $sql_type)] $($rest)*) -> { $($output)* } [$($from)*] [$($from_related)*]);
+    };
+
+    (@record
+        (#[lowboy(type = $sql_type:ty, column = $column:literal $(,)?)] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [$($from:tt)*]
+        [$($from_related:tt)*]
+    ) => {
+        internal_record!(@record (#[diesel(column_name = $column, serialize_as = $sql_type, deserialize_as = $sql_type)] $($rest)*) -> { $($output)* } [$($from)*] [$($from_related)*]);
     };
 
     // Strip out HasOne fields. These fields are "virtual" and used for one-to-one relations.
     (@record
-        ($pub:vis $field:ident : HasOne<$type:ty> $(, $($rest:tt)*)?)
+        ($(#[$field_attr:meta])* $pub:vis $field:ident : HasOne<$type:ty> $(, $($rest:tt)*)?)
         -> { $($output:tt)* }
         [$($from:tt)*]
         [$($from_related:tt)*]
@@ -182,7 +372,7 @@ macro_rules! internal_record {
 
     // Strip out vec relation fields. These fields are "virtual" and used for one-to-many relations.
     (@record
-        ($pub:vis $field:ident : Related<Vec<$type:ty>> $(, $($rest:tt)*)?)
+        ($(#[$field_attr:meta])* $pub:vis $field:ident : Related<Vec<$type:ty>> $(, $($rest:tt)*)?)
         -> { $($output:tt)* }
         [$($from:tt)*]
         [$($from_related:tt)*]
@@ -194,13 +384,13 @@ macro_rules! internal_record {
 
     // Replace relation fields with foreign key.
     (@record
-        ($pub:vis $field:ident : Related<$type:ty> $(, $($rest:tt)*)?)
+        ($(#[$field_attr:meta])* $pub:vis $field:ident : Related<$type:ty> $(, $($rest:tt)*)?)
         -> { $($output:tt)* }
         [$($from:tt)*]
         [$($from_related:tt)*]
     ) => {
         paste! {
-            internal_record!(@record ($($($rest)*)?) -> { $($output)* ($pub [<$field _id>] : i32) } [$($from)*] [$($from_related)* ([<$field _id>] : $type)]);
+            internal_record!(@record ($($($rest)*)?) -> { $($output)* ($(#[$field_attr])* $pub [<$field _id>] : i32) } [$($from)*] [$($from_related)* ([<$field _id>] : $field)]);
         }
     };
 
@@ -208,15 +398,15 @@ macro_rules! internal_record {
     (@record
         // Remove the first field/type from the list of Model fields to process into ModelRecord
         // fields.
-        ($pub:vis $field:ident : $type:ty $(, $($rest:tt)*)?)
+        ($(#[$field_attr:meta])* $pub:vis $field:ident : $type:ty $(, $($rest:tt)*)?)
         // Accumulator of ModelRecord output (attrs, visibility, model name, (record fields)).
         -> { $($output:tt)* }
         // Accumulator of non-related fields to copy 1-to-1 from Model to ModelRecord.
         [$($from:tt)*]
-        // Accumulator of related fields to copy ids from related Model to ModelRecord.
+        // Accumulator of foreign keys to copy from the ids of the related Model's fields.
         [$($from_related:tt)*]
     ) => {
-        internal_record!(@record ($($($rest)*)?) -> { $($output)* ($pub $field : $type) } [$($from)* ($field : $type)] [$($from_related)*]);
+        internal_record!(@record ($($($rest)*)?) -> { $($output)* ($(#[$field_attr])* $pub $field : $type) } [$($from)* ($field : $type)] [$($from_related)*]);
     };
 
     // Entrypoint.
@@ -232,8 +422,8 @@ macro_rules! internal_new_record {
     // Done, generate struct and generate new_record associated function for model.
     (@new_record
         ()
-        -> { $pub:vis $model:ident $(($field_vis:vis $field:ident : $type:ty))* }
-        [ $(($option_vis:vis $option:ident : $option_type:ty))* ]
+        -> { $pub:vis $model:ident $(($(#[$field_attr:meta])* $field_vis:vis $field:ident : $type:ty))* }
+        [ $(($(#[$option_attr:meta])* $option_vis:vis $option:ident : $option_type:ty))* ]
     ) => {
         paste! {
             // NewModelRecord
@@ -241,8 +431,8 @@ macro_rules! internal_new_record {
             #[diesel(table_name = crate::schema::[<$model:snake>])]
             #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
             $pub struct [<New $model Record>]<'a> {
-                $($field_vis $field : $type ,)*
-                $($option_vis $option : $option_type ,)*
+                $($(#[$field_attr])* $field_vis $field : $type ,)*
+                $($(#[$option_attr])* $option_vis $option : $option_type ,)*
             }
 
             // impl NewModelRecord
@@ -270,8 +460,9 @@ macro_rules! internal_new_record {
                 // NewModelRecord::create
                 #[doc = "Create a new `" [<$model:snake>] "` in the database"]
                 pub async fn create(&self, conn: &mut Connection) -> QueryResult<[<$model Record>]> {
+                    // Fields with a custom `type` can only be inserted by value.
                     diesel::insert_into(crate::schema::[<$model:snake>]::table)
-                        .values(self)
+                        .values(self.clone())
                         .returning(crate::schema::[<$model:snake>]::table::all_columns())
                         .get_result(conn)
                         .await
@@ -293,40 +484,40 @@ macro_rules! internal_new_record {
 
     // Convert Option<String> fields to Option<&'a str>, and put them in the optionial accumulator.
     (@new_record
-        ($pub:vis $field:ident : Option<String> $(, $($rest:tt)*)?)
+        ($(#[$field_attr:meta])* $pub:vis $field:ident : Option<String> $(, $($rest:tt)*)?)
         -> { $($output:tt)* }
         [ $($optional:tt)* ]
     ) => {
         defile! {
-            internal_new_record!(@@new_record ($($(@$rest)*)?) -> { $($output)* } [ $($optional)* ($pub $field : Option<&'a str>) ]);
+            internal_new_record!(@@new_record ($($(@$rest)*)?) -> { $($output)* } [ $($optional)* ($(#[$field_attr])* $pub $field : Option<&'a str>) ]);
         }
     };
 
     // Put optional fields in a separate optional accumulator.
     (@new_record
-        ($pub:vis $field:ident : Option<$type:ty> $(, $($rest:tt)*)?)
+        ($(#[$field_attr:meta])* $pub:vis $field:ident : Option<$type:ty> $(, $($rest:tt)*)?)
         -> { $($output:tt)* }
         [ $($optional:tt)* ]
     ) => {
         defile! {
-            internal_new_record!(@@new_record ($($(@$rest)*)?) -> { $($output)* } [ $($optional)* ($pub $field : Option<$type>) ]);
+            internal_new_record!(@@new_record ($($(@$rest)*)?) -> { $($output)* } [ $($optional)* ($(#[$field_attr])* $pub $field : Option<$type>) ]);
         }
     };
 
     // Convert String fields to &'a str.
     (@new_record
-        ($pub:vis $field:ident : String $(, $($rest:tt)*)?)
+        ($(#[$field_attr:meta])* $pub:vis $field:ident : String $(, $($rest:tt)*)?)
         -> { $($output:tt)* }
         [ $($optional:tt)* ]
     ) => {
         defile! {
-            internal_new_record!(@@new_record ($($(@$rest)*)?) -> { $($output)* ($pub $field : &'a str) } [ $($optional)* ]);
+            internal_new_record!(@@new_record ($($(@$rest)*)?) -> { $($output)* ($(#[$field_attr])* $pub $field : &'a str) } [ $($optional)* ]);
         }
     };
 
     // Remove id field.
     (@new_record
-        ($pub:vis id : $type:ty $(, $($rest:tt)*)?)
+        ($(#[$field_attr:meta])* $pub:vis id : $type:ty $(, $($rest:tt)*)?)
         -> { $($output:tt)* }
         [ $($optional:tt)* ]
     ) => {
@@ -339,14 +530,14 @@ macro_rules! internal_new_record {
     (@new_record
         // Remove the first field/type from the list of Model fields to process into NewModelRecord
         // fields.
-        ($pub:vis $field:ident : $type:ty $(, $($rest:tt)*)?)
+        ($(#[$field_attr:meta])* $pub:vis $field:ident : $type:ty $(, $($rest:tt)*)?)
         // Accumulator of NewModelRecord output (attrs, visibility, model name, (record fields)).
         -> { $($output:tt)* }
         // Accumulator of optional NewModelRecord fields.
         [ $($optional:tt)* ]
     ) => {
         defile! {
-            internal_new_record!(@@new_record ($($(@$rest)*)?) -> { $($output)* ($pub $field : $type) } [ $($optional)* ]);
+            internal_new_record!(@@new_record ($($(@$rest)*)?) -> { $($output)* ($(#[$field_attr])* $pub $field : $type) } [ $($optional)* ]);
         }
     };
 
@@ -362,11 +553,11 @@ macro_rules! internal_model {
     // Done, generate struct.
     (@model
         ()
-        -> { $pub:vis $model:ident $(($field_vis:vis $field:ident : $type:ty))* }
+        -> { [$($derive:path),*] $pub:vis $model:ident $(($field_vis:vis $field:ident : $type:ty))* }
     ) => {
         paste! {
             // Model
-            #[derive(Debug, Default, Clone)]
+            #[derive(Debug, Default, Clone $(, $derive)*)]
             #[doc = "A `" $model "` model"]
             $pub struct $model {
                 $($field_vis $field : $type ,)*
@@ -374,6 +565,14 @@ macro_rules! internal_model {
         }
     };
 
+    // Strip out field attributes, they only apply to records.
+    (@model
+        (#[$($field_attr:tt)*] $($rest:tt)*)
+        -> { $($output:tt)* }
+    ) => {
+        internal_model!(@model ($($rest)*) -> { $($output)* });
+    };
+
     // Strip out HasOne marker.
     (@model
         ($pub:vis $field:ident : HasOne<$type:ty> $(, $($rest:tt)*)?)
@@ -400,9 +599,9 @@ macro_rules! internal_model {
         internal_model!(@model ($($($rest)*)?) -> { $($output)* ($pub $field : $type) });
     };
 
-    // Entrypoint.
-    ($pub:vis $model:ident ($($rest:tt)*)) => {
-        internal_model!(@model ($($rest)*) -> { $pub $model });
+    // Entrypoint, with any extra derives for the model.
+    ($pub:vis $model:ident [$($derive:path),*] ($($rest:tt)*)) => {
+        internal_model!(@model ($($rest)*) -> { [$($derive),*] $pub $model });
     };
 }
 
@@ -413,10 +612,13 @@ macro_rules! internal_impl {
     // Done, generate Model impl.
     (@impl
         ()
-        -> { $model:ident $(($field_vis:vis $field:ident : $type:ty))* }
+        -> { $model:ident [$($versioned:ident)?] $(($field_vis:vis $field:ident : $type:ty))* }
         [ $(($key:ident ; $foreign_vis:vis $foreign_key:ident : $foreign_model:ty))* ]
         [ $(($many_vis:vis $many:ident : $many_model:ty))* ]
         [ $(($has_one_vis:vis $has_one:ident : $has_one_model:ty))* ]
+        [ $(($unique:ident ; $unique_column:ident : $unique_type:ty))* ]
+        [ $(($soft_delete:ident ; $soft_delete_column:ident : $deleted_at:ty))* ]
+        [ $($publish:tt)* ]
     ) => {
         // impl Model
         impl $model {
@@ -466,6 +668,23 @@ macro_rules! internal_impl {
                     Ok(models)
                 }
 
+            $(
+                // Model::find_by_$unique
+                #[doc = "Find the `" $model "` with the given `" $unique "`"]
+                pub async fn [<find_by_ $unique>]($unique: $unique_type, conn: &mut Connection) -> QueryResult<Option<Self>> {
+                    let record: Option<[<$model Record>]> = crate::schema::[<$model:snake>]::table
+                        .filter(crate::schema::[<$model:snake>]::$unique_column.eq($unique))
+                        .first(conn)
+                        .await
+                        .optional()?;
+
+                    match record {
+                        Some(record) => Ok(Some(Self::from_record(&record, conn).await?)),
+                        None => Ok(None),
+                    }
+                }
+            )*
+
             $(
                 // Model::with_$many
                 #[doc = "Load `" $many "` models into the `" [<$model>] "` object"]
@@ -490,6 +709,374 @@ macro_rules! internal_impl {
 
             }
         }
+
+        paste! {
+            // impl NewModelRecord
+            impl<'a> [<New $model Record>]<'a> {
+            $(
+                // NewModelRecord::create_or_update_by_$unique
+                #[doc = "Create a new `" [<$model:snake>] "` in the database, or update the one with the same `" $unique "`"]
+                pub async fn [<create_or_update_by_ $unique>](&self, conn: &mut Connection) -> QueryResult<([<$model Record>], $crate::Operation)> {
+                    // Fields with a custom `type` can only be inserted by value.
+                    let record = self.clone();
+                    diesel_async::AsyncConnection::transaction(conn, |conn| {
+                        diesel_async::scoped_futures::ScopedFutureExt::scope_boxed(async move {
+                            // SQLite doesn't report whether an upsert inserted or updated, so check
+                            // first, in the same transaction.
+                            let exists: bool = diesel::select(diesel::dsl::exists(
+                                crate::schema::[<$model:snake>]::table
+                                    .filter(crate::schema::[<$model:snake>]::$unique_column.eq(record.$unique.clone())),
+                            ))
+                            .get_result(conn)
+                            .await?;
+
+                            let record = diesel::insert_into(crate::schema::[<$model:snake>]::table)
+                                .values(record.clone())
+                                .on_conflict(crate::schema::[<$model:snake>]::$unique_column)
+                                .do_update()
+                                .set(record)
+                                .returning(crate::schema::[<$model:snake>]::table::all_columns())
+                                .get_result(conn)
+                                .await?;
+
+                            let operation = if exists {
+                                $crate::Operation::Updated
+                            } else {
+                                $crate::Operation::Created
+                            };
+
+                            Ok((record, operation))
+                        })
+                    })
+                    .await
+                }
+            )*
+            }
+        }
+
+        paste! {
+        $(
+            // impl SoftDelete for Model
+            #[$crate::async_trait]
+            impl SoftDelete for $model {
+                fn deleted_at(&self) -> Option<$deleted_at> {
+                    self.$soft_delete
+                }
+
+                type WithTrashed = diesel::dsl::Select<
+                    crate::schema::[<$model:snake>]::table,
+                    diesel::dsl::AsSelect<[<$model Record>], diesel::sqlite::Sqlite>,
+                >;
+                type OnlyTrashed = diesel::dsl::Select<
+                    diesel::dsl::Filter<
+                        crate::schema::[<$model:snake>]::table,
+                        diesel::dsl::IsNotNull<crate::schema::[<$model:snake>]::$soft_delete_column>,
+                    >,
+                    diesel::dsl::AsSelect<[<$model Record>], diesel::sqlite::Sqlite>,
+                >;
+
+                fn with_trashed() -> Self::WithTrashed {
+                    crate::schema::[<$model:snake>]::table.select([<$model Record>]::as_select())
+                }
+
+                fn only_trashed() -> Self::OnlyTrashed {
+                    crate::schema::[<$model:snake>]::table
+                        .filter(crate::schema::[<$model:snake>]::$soft_delete_column.is_not_null())
+                        .select([<$model Record>]::as_select())
+                }
+
+                async fn load_with_trashed(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+                    let record: [<$model Record>] = Self::with_trashed()
+                        .filter(crate::schema::[<$model:snake>]::id.eq(id))
+                        .first(conn)
+                        .await?;
+
+                    Self::from_record(&record, conn).await
+                }
+
+                async fn trashed(conn: &mut Connection) -> QueryResult<Vec<Self>> {
+                    let records: Vec<[<$model Record>]> = Self::only_trashed().load(conn).await?;
+
+                    Self::from_records(&records, conn).await
+                }
+
+                async fn trash(id: i32, now: $deleted_at, conn: &mut Connection) -> QueryResult<usize> {
+                    diesel::update(
+                        crate::schema::[<$model:snake>]::table
+                            .find(id)
+                            .filter(crate::schema::[<$model:snake>]::$soft_delete_column.is_null()),
+                    )
+                    .set(crate::schema::[<$model:snake>]::$soft_delete_column.eq(Some(now)))
+                    .execute(conn)
+                    .await
+                }
+
+                async fn restore(id: i32, conn: &mut Connection) -> QueryResult<usize> {
+                    diesel::update(
+                        crate::schema::[<$model:snake>]::table
+                            .find(id)
+                            .filter(crate::schema::[<$model:snake>]::$soft_delete_column.is_not_null()),
+                    )
+                    .set(crate::schema::[<$model:snake>]::$soft_delete_column.eq(None::<$deleted_at>))
+                    .execute(conn)
+                    .await
+                }
+
+                async fn restore_since(id: i32, since: $deleted_at, conn: &mut Connection) -> QueryResult<usize> {
+                    diesel::update(
+                        crate::schema::[<$model:snake>]::table
+                            .find(id)
+                            .filter(crate::schema::[<$model:snake>]::$soft_delete_column.gt(Some(since))),
+                    )
+                    .set(crate::schema::[<$model:snake>]::$soft_delete_column.eq(None::<$deleted_at>))
+                    .execute(conn)
+                    .await
+                }
+
+                async fn purge_trashed(trashed_before: $deleted_at, conn: &mut Connection) -> QueryResult<Vec<i32>> {
+                    diesel::delete(
+                        crate::schema::[<$model:snake>]::table
+                            .filter(crate::schema::[<$model:snake>]::$soft_delete_column.le(Some(trashed_before))),
+                    )
+                    .returning(crate::schema::[<$model:snake>]::id)
+                    .get_results(conn)
+                    .await
+                }
+
+                async fn delete_trashed(id: i32, conn: &mut Connection) -> QueryResult<usize> {
+                    diesel::delete(
+                        crate::schema::[<$model:snake>]::table
+                            .find(id)
+                            .filter(crate::schema::[<$model:snake>]::$soft_delete_column.is_not_null()),
+                    )
+                    .execute(conn)
+                    .await
+                }
+
+                async fn force_delete(id: i32, conn: &mut Connection) -> QueryResult<usize> {
+                    diesel::delete(crate::schema::[<$model:snake>]::table.find(id))
+                        .execute(conn)
+                        .await
+                }
+            }
+        )*
+        }
+
+        // Model::update_record
+        internal_update!($model [$($versioned)?]);
+
+        // Model::delete_record
+        internal_delete!($model [$($versioned)?] $(($soft_delete ; $soft_delete_column : $deleted_at))*);
+
+        // impl Versioned for Model
+        internal_versioned!($model $($versioned)?);
+
+        // impl Publish for Model
+        internal_publish!($model $($publish)*);
+    };
+
+    // Mark unique fields, along with their column. `#[lowboy(unique)]` must come before the
+    // field's `#[lowboy(column = ...)]` attribute, if it has one.
+    (@impl
+        (#[lowboy(unique $(,)?)] #[lowboy(column = $column:literal $(, $($args:tt)*)?)] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        paste! {
+            internal_impl!(@impl (@unique [<$column>] $($rest)*) -> { $($output)* } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
+        }
+    };
+
+    (@impl
+        (#[lowboy(unique $(,)?)] #[lowboy(type = $sql_type:ty, column = $column:literal $(,)?)] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        paste! {
+            internal_impl!(@impl (@unique [<$column>] $($rest)*) -> { $($output)* } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
+        }
+    };
+
+    (@impl
+        (#[lowboy(unique $(,)?)] $(#[$($field_attr:tt)*])* $pub:vis $field:ident : $($rest:tt)*)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        internal_impl!(@impl (@unique $field $pub $field : $($rest)*) -> { $($output)* } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
+    };
+
+    // Put unique fields in a separate accumulator, looking them up by `&str` rather than `String`.
+    (@impl
+        (@unique $column:ident $(#[$($field_attr:tt)*])* $pub:vis $field:ident : String $(, $($rest:tt)*)?)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        internal_impl!(@impl ($($($rest)*)?) -> { $($output)* ($pub $field : String) } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ($field ; $column : &str) ] [ $($soft_delete)* ] [ $($publish)* ]);
+    };
+
+    (@impl
+        (@unique $column:ident $(#[$($field_attr:tt)*])* $pub:vis $field:ident : $type:ty $(, $($rest:tt)*)?)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        internal_impl!(@impl ($($($rest)*)?) -> { $($output)* ($pub $field : $type) } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ($field ; $column : $type) ] [ $($soft_delete)* ] [ $($publish)* ]);
+    };
+
+    // Mark the soft delete field, along with its column. Like `#[lowboy(unique)]`,
+    // `#[lowboy(soft_delete)]` must come before the field's `#[lowboy(column = ...)]` attribute.
+    (@impl
+        (#[lowboy(soft_delete $(,)?)] #[lowboy(column = $column:literal $(,)?)] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        paste! {
+            internal_impl!(@impl (@soft_delete [<$column>] $($rest)*) -> { $($output)* } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
+        }
+    };
+
+    (@impl
+        (#[lowboy(soft_delete $(,)?)] $(#[$($field_attr:tt)*])* $pub:vis $field:ident : $($rest:tt)*)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        internal_impl!(@impl (@soft_delete $field $pub $field : $($rest)*) -> { $($output)* } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
+    };
+
+    // Put the soft delete field in its own accumulator, along with the timestamp type it's an
+    // `Option` of.
+    (@impl
+        (@soft_delete $column:ident $(#[$($field_attr:tt)*])* $pub:vis $field:ident : Option<$type:ty> $(, $($rest:tt)*)?)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        internal_impl!(@impl ($($($rest)*)?) -> { $($output)* ($pub $field : Option<$type>) } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ($field ; $column : $type) ] [ $($publish)* ]);
+    };
+
+    // Mark the publication fields, along with their columns. Like `#[lowboy(unique)]`, they must
+    // come before the field's `#[lowboy(column = ...)]` attribute.
+    (@impl
+        (#[lowboy(published_at $(,)?)] #[lowboy(column = $column:literal $(,)?)] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        paste! {
+            internal_impl!(@impl (@publish published_at [<$column>] $($rest)*) -> { $($output)* } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
+        }
+    };
+
+    (@impl
+        (#[lowboy(published_at $(,)?)] $(#[$($field_attr:tt)*])* $pub:vis $field:ident : $($rest:tt)*)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        internal_impl!(@impl (@publish published_at $field $pub $field : $($rest)*) -> { $($output)* } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
+    };
+
+    (@impl
+        (#[lowboy(publish_at $(,)?)] #[lowboy(column = $column:literal $(,)?)] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        paste! {
+            internal_impl!(@impl (@publish publish_at [<$column>] $($rest)*) -> { $($output)* } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
+        }
+    };
+
+    (@impl
+        (#[lowboy(publish_at $(,)?)] $(#[$($field_attr:tt)*])* $pub:vis $field:ident : $($rest:tt)*)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        internal_impl!(@impl (@publish publish_at $field $pub $field : $($rest)*) -> { $($output)* } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
+    };
+
+    // Put the publication fields in their own accumulator, along with the timestamp type they're an
+    // `Option` of.
+    (@impl
+        (@publish $role:ident $column:ident $(#[$($field_attr:tt)*])* $pub:vis $field:ident : Option<$type:ty> $(, $($rest:tt)*)?)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        internal_impl!(@impl ($($($rest)*)?) -> { $($output)* ($pub $field : Option<$type>) } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ($role $field ; $column : $type) ]);
+    };
+
+    // Strip out field attributes, they only apply to records.
+    (@impl
+        (#[$($field_attr:tt)*] $($rest:tt)*)
+        -> { $($output:tt)* }
+        [ $($relations:tt)* ]
+        [ $($many:tt)* ]
+        [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
+    ) => {
+        internal_impl!(@impl ($($rest)*) -> { $($output)* } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
     };
 
     // Put vec relation fields in a separate one-to-many accumulator.
@@ -499,9 +1086,12 @@ macro_rules! internal_impl {
         [ $($relations:tt)* ]
         [ $($many:tt)* ]
         [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
     ) => {
         paste! {
-            internal_impl!(@impl ($($($rest)*)?) -> { $($output)* } [ $($relations)* ] [ $($many)* ($pub $field : $type) ] [ $($has_one)* ]);
+            internal_impl!(@impl ($($($rest)*)?) -> { $($output)* } [ $($relations)* ] [ $($many)* ($pub $field : $type) ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
         }
     };
 
@@ -512,9 +1102,12 @@ macro_rules! internal_impl {
         [ $($relations:tt)* ]
         [ $($many:tt)* ]
         [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
     ) => {
         paste! {
-            internal_impl!(@impl ($($($rest)*)?) -> { $($output)* } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ($pub $field : $type) ]);
+            internal_impl!(@impl ($($($rest)*)?) -> { $($output)* } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ($pub $field : $type) ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
         }
     };
 
@@ -525,9 +1118,12 @@ macro_rules! internal_impl {
         [ $($relations:tt)* ]
         [ $($many:tt)* ]
         [ $($has_one:tt)* ]
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
     ) => {
         paste! {
-            internal_impl!(@impl ($($($rest)*)?) -> { $($output)* } [ $($relations)* ($field ; $pub [<$field _id>] : $type) ] [ $($many)* ] [ $($has_one)* ]);
+            internal_impl!(@impl ($($($rest)*)?) -> { $($output)* } [ $($relations)* ($field ; $pub [<$field _id>] : $type) ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
         }
     };
 
@@ -543,12 +1139,253 @@ macro_rules! internal_impl {
         [ $($many:tt)* ]
         // Accumulator of model has-one children.
         [ $($has_one:tt)* ]
+        // Accumulator of unique fields to generate find_by_* methods for.
+        [ $($unique:tt)* ]
+        [ $($soft_delete:tt)* ]
+        [ $($publish:tt)* ]
     ) => {
-        internal_impl!(@impl ($($($rest)*)?) -> { $($output)* ($pub $field : $type) } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ]);
+        internal_impl!(@impl ($($($rest)*)?) -> { $($output)* ($pub $field : $type) } [ $($relations)* ] [ $($many)* ] [ $($has_one)* ] [ $($unique)* ] [ $($soft_delete)* ] [ $($publish)* ]);
     };
 
     // Entrypoint.
-    ($model:ident ($($rest:tt)*)) => {
-        internal_impl!(@impl ($($rest)*) -> { $model } [] [] []);
+    ($model:ident [$($versioned:ident)?] ($($rest:tt)*)) => {
+        internal_impl!(@impl ($($rest)*) -> { $model [$($versioned)?] } [] [] [] [] [] []);
+    };
+}
+
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+#[allow(clippy::crate_in_macro_def)]
+macro_rules! internal_update {
+    // Update the row.
+    ($model:ident []) => {
+        paste! {
+            impl $model {
+                #[doc = "Save the `" $model "` model's fields to its `" [<$model:snake>] "`"]
+                pub async fn update_record(&self, conn: &mut Connection) -> QueryResult<[<$model Record>]> {
+                    let changes = [<Update $model Record>]::from([<$model Record>]::from(self.clone()));
+
+                    diesel::update(crate::schema::[<$model:snake>]::table.find(self.id))
+                        .set(changes)
+                        .returning(crate::schema::[<$model:snake>]::table::all_columns())
+                        .get_result(conn)
+                        .await
+                }
+            }
+        }
+    };
+
+    // Snapshot versioned models first, in the same transaction.
+    ($model:ident [versioned]) => {
+        paste! {
+            impl $model {
+                #[doc = "Save the `" $model "` model's fields to its `" [<$model:snake>] "`, after snapshotting it as it was at `now`"]
+                pub async fn update_record(&self, now: chrono::DateTime<chrono::Utc>, conn: &mut Connection) -> QueryResult<[<$model Record>]> {
+                    let id = self.id;
+                    let changes = [<Update $model Record>]::from([<$model Record>]::from(self.clone()));
+
+                    diesel_async::AsyncConnection::transaction(conn, |conn| {
+                        diesel_async::scoped_futures::ScopedFutureExt::scope_boxed(async move {
+                            <Self as Versioned>::before_update(id, now, conn).await?;
+
+                            diesel::update(crate::schema::[<$model:snake>]::table.find(id))
+                                .set(changes)
+                                .returning(crate::schema::[<$model:snake>]::table::all_columns())
+                                .get_result(conn)
+                                .await
+                        })
+                    })
+                    .await
+                }
+            }
+        }
+    };
+}
+
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+#[allow(clippy::crate_in_macro_def)]
+macro_rules! internal_delete {
+    // Delete the row.
+    ($model:ident []) => {
+        paste! {
+            impl $model {
+                #[doc = "Delete the `" [<$model:snake>] "` from the database"]
+                pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+                    diesel::delete(crate::schema::[<$model:snake>]::table.find(self.id))
+                        .execute(conn)
+                        .await
+                }
+            }
+        }
+    };
+
+    // Trash soft deleted models instead.
+    ($model:ident [] ($soft_delete:ident ; $soft_delete_column:ident : $deleted_at:ty)) => {
+        paste! {
+            impl $model {
+                #[doc = "Trash the `" [<$model:snake>] "`, rather than deleting it from the database"]
+                pub async fn delete_record(self, now: $deleted_at, conn: &mut Connection) -> QueryResult<usize> {
+                    <Self as SoftDelete>::trash(self.id, now, conn).await
+                }
+            }
+        }
+    };
+
+    // Snapshot versioned models first, in the same transaction.
+    ($model:ident [versioned]) => {
+        paste! {
+            impl $model {
+                #[doc = "Delete the `" [<$model:snake>] "` from the database, after snapshotting it as it was at `now`"]
+                pub async fn delete_record(self, now: chrono::DateTime<chrono::Utc>, conn: &mut Connection) -> QueryResult<usize> {
+                    let id = self.id;
+
+                    diesel_async::AsyncConnection::transaction(conn, |conn| {
+                        diesel_async::scoped_futures::ScopedFutureExt::scope_boxed(async move {
+                            <Self as Versioned>::before_delete(id, now, conn).await?;
+
+                            diesel::delete(crate::schema::[<$model:snake>]::table.find(id))
+                                .execute(conn)
+                                .await
+                        })
+                    })
+                    .await
+                }
+            }
+        }
+    };
+
+    ($model:ident [versioned] ($soft_delete:ident ; $soft_delete_column:ident : $deleted_at:ty)) => {
+        paste! {
+            impl $model {
+                #[doc = "Trash the `" [<$model:snake>] "`, rather than deleting it from the database, after snapshotting it as it was at `now`"]
+                pub async fn delete_record(self, now: $deleted_at, conn: &mut Connection) -> QueryResult<usize> {
+                    let id = self.id;
+
+                    diesel_async::AsyncConnection::transaction(conn, |conn| {
+                        diesel_async::scoped_futures::ScopedFutureExt::scope_boxed(async move {
+                            <Self as Versioned>::before_delete(id, now, conn).await?;
+                            <Self as SoftDelete>::trash(id, now, conn).await
+                        })
+                    })
+                    .await
+                }
+            }
+        }
+    };
+
+    ($model:ident [$($versioned:ident)?] $($soft_delete:tt)*) => {
+        ::core::compile_error!("a model can only have one `#[lowboy(soft_delete)]` field");
+    };
+}
+
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+#[allow(clippy::crate_in_macro_def)]
+macro_rules! internal_versioned {
+    // Not versioned.
+    ($model:ident) => {};
+
+    ($model:ident versioned) => {
+        paste! {
+            // impl Versioned for Model
+            #[$crate::async_trait]
+            impl Versioned for $model {
+                async fn write_version(model: Self, conn: &mut Connection) -> QueryResult<Self> {
+                    let changes = [<Update $model Record>]::from([<$model Record>]::from(model.clone()));
+
+                    let record: [<$model Record>] = diesel::insert_into(crate::schema::[<$model:snake>]::table)
+                        .values([<$model Record>]::from(model))
+                        .on_conflict(crate::schema::[<$model:snake>]::id)
+                        .do_update()
+                        .set(changes)
+                        .returning(crate::schema::[<$model:snake>]::table::all_columns())
+                        .get_result(conn)
+                        .await?;
+
+                    Self::from_record(&record, conn).await
+                }
+            }
+        }
+    };
+}
+
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+#[allow(clippy::crate_in_macro_def)]
+macro_rules! internal_publish {
+    // No publication fields.
+    ($model:ident) => {};
+
+    // Put the fields in order.
+    ($model:ident (publish_at $($publish_at:tt)*) (published_at $($published_at:tt)*)) => {
+        internal_publish!($model (published_at $($published_at)*) (publish_at $($publish_at)*));
+    };
+
+    ($model:ident
+        (published_at $published_at:ident ; $published_at_column:ident : $published_at_type:ty)
+        (publish_at $publish_at:ident ; $publish_at_column:ident : $publish_at_type:ty)
+    ) => {
+        paste! {
+            #[$crate::async_trait]
+            impl Publish for $model {
+                fn published_at(&self) -> Option<$published_at_type> {
+                    self.$published_at
+                }
+
+                fn publish_at(&self) -> Option<$publish_at_type> {
+                    self.$publish_at
+                }
+
+                async fn publish(id: i32, now: $published_at_type, conn: &mut Connection) -> QueryResult<usize> {
+                    diesel::update(crate::schema::[<$model:snake>]::table.find(id))
+                        .set((
+                            crate::schema::[<$model:snake>]::$published_at_column.eq(Some(now)),
+                            crate::schema::[<$model:snake>]::$publish_at_column.eq(None::<$publish_at_type>),
+                        ))
+                        .execute(conn)
+                        .await
+                }
+
+                async fn schedule(id: i32, publish_at: $publish_at_type, conn: &mut Connection) -> QueryResult<usize> {
+                    diesel::update(crate::schema::[<$model:snake>]::table.find(id))
+                        .set((
+                            crate::schema::[<$model:snake>]::$published_at_column.eq(None::<$published_at_type>),
+                            crate::schema::[<$model:snake>]::$publish_at_column.eq(Some(publish_at)),
+                        ))
+                        .execute(conn)
+                        .await
+                }
+
+                async fn unpublish(id: i32, conn: &mut Connection) -> QueryResult<usize> {
+                    diesel::update(crate::schema::[<$model:snake>]::table.find(id))
+                        .set((
+                            crate::schema::[<$model:snake>]::$published_at_column.eq(None::<$published_at_type>),
+                            crate::schema::[<$model:snake>]::$publish_at_column.eq(None::<$publish_at_type>),
+                        ))
+                        .execute(conn)
+                        .await
+                }
+
+                async fn publish_due(now: $publish_at_type, conn: &mut Connection) -> QueryResult<Vec<i32>> {
+                    diesel::update(
+                        crate::schema::[<$model:snake>]::table
+                            .filter(crate::schema::[<$model:snake>]::$publish_at_column.le(Some(now)))
+                            .filter(crate::schema::[<$model:snake>]::$published_at_column.is_null()),
+                    )
+                    .set((
+                        crate::schema::[<$model:snake>]::$published_at_column.eq(crate::schema::[<$model:snake>]::$publish_at_column),
+                        crate::schema::[<$model:snake>]::$publish_at_column.eq(None::<$publish_at_type>),
+                    ))
+                    .returning(crate::schema::[<$model:snake>]::id)
+                    .get_results(conn)
+                    .await
+                }
+            }
+        }
+    };
+
+    ($model:ident $($fields:tt)*) => {
+        ::core::compile_error!("a model needs exactly one `#[lowboy(published_at)]` field and one `#[lowboy(publish_at)]` field to implement `Publish`");
     };
 }
@@ -4,7 +4,7 @@
 use diesel::prelude::*;
 use diesel::sqlite::SqliteConnection;
 use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
-use diesel_async::RunQueryDsl;
+use diesel_async::{AsyncConnection, RunQueryDsl};
 use lowboy_record::prelude::*;
 
 pub type Connection = SyncConnectionWrapper<SqliteConnection>;
@@ -43,6 +43,23 @@ pub mod schema {
             content -> Text,
         }
     }
+
+    table! {
+        setting (id) {
+            id -> Integer,
+            name -> Text,
+            tag_list -> Text,
+        }
+    }
+
+    table! {
+        user_data_revision (id) {
+            id -> Integer,
+            current_data_id -> Integer,
+            editor_id -> Integer,
+            avatar -> Nullable<Text>,
+        }
+    }
 }
 
 #[test]
@@ -95,3 +112,155 @@ fn lowboy_record_works() {
     assert_eq!(record.user_id, 123);
     assert_eq!(record.content, "some content");
 }
+
+#[test]
+fn lowboy_record_uses_related_field_names() {
+    #[apply(lowboy_record!)]
+    #[derive(Debug, Default, Queryable, Identifiable, Associations)]
+    #[diesel(belongs_to(UserRecord, foreign_key = user_id))]
+    #[diesel(table_name = crate::schema::user_data)]
+    #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+    pub struct UserData {
+        pub id: i32,
+        pub user_id: i32,
+        pub avatar: Option<String>,
+    }
+
+    #[apply(lowboy_record!)]
+    #[derive(Debug, Default, Queryable, Identifiable, Selectable, Insertable)]
+    #[diesel(table_name = crate::schema::user)]
+    pub struct User {
+        pub id: i32,
+        pub name: String,
+        pub data: HasOne<UserData>,
+    }
+
+    // A multi-word model, related to a multi-word model, through fields not named after the
+    // related models.
+    #[apply(lowboy_record!)]
+    #[derive(Debug, Default, Queryable, Identifiable)]
+    #[diesel(table_name = crate::schema::user_data_revision)]
+    #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+    pub struct UserDataRevision {
+        pub id: i32,
+        pub current_data: Related<UserData>,
+        pub editor: Related<User>,
+        pub avatar: Option<String>,
+    }
+
+    let revision = UserDataRevision {
+        id: 1,
+        current_data: UserData {
+            id: 2,
+            user_id: 3,
+            avatar: None,
+        },
+        editor: User {
+            id: 3,
+            name: "editor".to_string(),
+            data: UserData::default(),
+        },
+        avatar: Some("avatar.png".to_string()),
+    };
+
+    let record = UserDataRevisionRecord::from(revision);
+
+    assert_eq!(record.id, 1);
+    assert_eq!(record.current_data_id, 2);
+    assert_eq!(record.editor_id, 3);
+    assert_eq!(record.avatar.as_deref(), Some("avatar.png"));
+
+    let record = UserDataRevision::new_record(2, 3).with_avatar(Some("avatar.png"));
+
+    assert_eq!(record.current_data_id, 2);
+    assert_eq!(record.editor_id, 3);
+    assert_eq!(record.avatar, Some("avatar.png"));
+}
+
+#[tokio::test]
+async fn lowboy_record_field_attributes() {
+    /// Tags stored as a comma separated list.
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct Tags(Vec<String>);
+
+    impl From<String> for Tags {
+        fn from(value: String) -> Self {
+            Self(value.split(',').map(str::to_string).collect())
+        }
+    }
+
+    impl From<Tags> for String {
+        fn from(value: Tags) -> Self {
+            value.0.join(",")
+        }
+    }
+
+    #[apply(lowboy_record!)]
+    #[derive(Debug, Default, Queryable, Identifiable, Selectable)]
+    #[diesel(table_name = crate::schema::setting)]
+    #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+    pub struct Setting {
+        pub id: i32,
+        #[lowboy(unique)]
+        #[lowboy(column = "name")]
+        pub key: String,
+        #[lowboy(column = "tag_list", type = String)]
+        pub tags: Tags,
+    }
+
+    let tags = Tags(vec!["a".to_string(), "b".to_string()]);
+    let record = Setting::new_record("theme", tags.clone());
+
+    assert_eq!(record.key, "theme");
+    assert_eq!(record.tags, tags);
+
+    let record = SettingRecord::from(Setting {
+        id: 1,
+        key: "theme".to_string(),
+        tags: tags.clone(),
+    });
+
+    assert_eq!(record.key, "theme");
+    assert_eq!(record.tags, tags);
+
+    // Unique fields can be upserted on, and looked up.
+    let mut conn = Connection::establish(":memory:").await.unwrap();
+    diesel::sql_query(
+        "CREATE TABLE setting (
+            id INTEGER PRIMARY KEY NOT NULL,
+            name TEXT NOT NULL UNIQUE,
+            tag_list TEXT NOT NULL
+        )",
+    )
+    .execute(&mut conn)
+    .await
+    .unwrap();
+
+    let (created, operation) = Setting::new_record("theme", tags.clone())
+        .create_or_update_by_key(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(operation, Operation::Created);
+    assert_eq!(created.key, "theme");
+    assert_eq!(created.tags, tags);
+
+    let dark = Tags(vec!["dark".to_string()]);
+    let (updated, operation) = Setting::new_record("theme", dark.clone())
+        .create_or_update_by_key(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(operation, Operation::Updated);
+    assert_eq!(updated.id, created.id);
+    assert_eq!(updated.tags, dark);
+
+    let setting = Setting::find_by_key("theme".to_string(), &mut conn)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(setting.id, created.id);
+    assert_eq!(setting.key, "theme");
+    assert_eq!(setting.tags, dark);
+
+    let missing = Setting::find_by_key("missing".to_string(), &mut conn).await;
+    assert!(missing.unwrap().is_none());
+}
@@ -0,0 +1,2 @@
+-- Drop mailbox_message table.
+DROP TABLE mailbox_message;
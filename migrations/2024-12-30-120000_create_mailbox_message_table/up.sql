@@ -0,0 +1,10 @@
+-- Create mailbox_message table.
+CREATE TABLE IF NOT EXISTS mailbox_message (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    sender TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    text TEXT NOT NULL,
+    html TEXT,
+    created_at DATETIME NOT NULL
+);
@@ -0,0 +1,5 @@
+-- Drop known_device table.
+DROP TABLE known_device;
+
+-- Drop notification_preferences table.
+DROP TABLE notification_preferences;
@@ -0,0 +1,18 @@
+-- Create notification_preferences table.
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id INTEGER NOT NULL PRIMARY KEY REFERENCES user(id),
+    new_device_login BOOLEAN NOT NULL DEFAULT true,
+    failed_login_streak BOOLEAN NOT NULL DEFAULT true,
+    password_changed BOOLEAN NOT NULL DEFAULT true
+);
+
+-- Create known_device table.
+CREATE TABLE IF NOT EXISTS known_device (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES user(id),
+    user_agent TEXT NOT NULL,
+    ip TEXT,
+    created_at DATETIME NOT NULL,
+    last_seen_at DATETIME NOT NULL,
+    UNIQUE (user_id, user_agent)
+);
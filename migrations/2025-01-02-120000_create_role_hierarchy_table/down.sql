@@ -0,0 +1,2 @@
+-- Drop role_hierarchy table.
+DROP TABLE IF EXISTS role_hierarchy;
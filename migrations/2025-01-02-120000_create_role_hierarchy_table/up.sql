@@ -0,0 +1,14 @@
+-- Create role_hierarchy table.
+CREATE TABLE IF NOT EXISTS role_hierarchy (
+    parent_id INTEGER NOT NULL REFERENCES role(id),
+    child_id INTEGER NOT NULL REFERENCES role(id),
+    PRIMARY KEY (parent_id, child_id),
+    CHECK (parent_id != child_id)
+);
+
+-- Administrators are authenticated users.
+INSERT INTO role_hierarchy (parent_id, child_id)
+VALUES (
+    (SELECT id FROM role WHERE name = 'administrator'),
+    (SELECT id FROM role WHERE name = 'authenticated')
+);
@@ -0,0 +1,11 @@
+-- Remove administer site permission.
+DELETE FROM role_permission
+WHERE permission_id = (SELECT id FROM permission WHERE name = 'administer site');
+DELETE FROM permission WHERE name = 'administer site';
+
+-- Drop description column from permission table.
+ALTER TABLE permission DROP COLUMN description;
+
+-- Drop description and system columns from role table.
+ALTER TABLE role DROP COLUMN system;
+ALTER TABLE role DROP COLUMN description;
@@ -0,0 +1,23 @@
+-- Add description and system columns to role table.
+ALTER TABLE role ADD COLUMN description TEXT;
+ALTER TABLE role ADD COLUMN system BOOLEAN NOT NULL DEFAULT false;
+
+-- Add description column to permission table.
+ALTER TABLE permission ADD COLUMN description TEXT;
+
+-- Mark the built-in roles as system roles, which can't be deleted.
+UPDATE role SET system = true, description = 'Visitors who are not logged in.' WHERE name = 'anonymous';
+UPDATE role SET system = true, description = 'Users who have not verified their email address.' WHERE name = 'unverified';
+UPDATE role SET system = true, description = 'Users who are logged in.' WHERE name = 'authenticated';
+UPDATE role SET system = true, description = 'Users who can administer the site.' WHERE name = 'administrator';
+
+-- Add administer site permission.
+INSERT INTO permission (name, description)
+VALUES ('administer site', 'Manage roles, permissions, and users.');
+
+-- Add administer site permission to administrator role.
+INSERT INTO role_permission (role_id, permission_id)
+VALUES (
+    (SELECT id FROM role WHERE name = 'administrator'),
+    (SELECT id FROM permission WHERE name = 'administer site')
+);
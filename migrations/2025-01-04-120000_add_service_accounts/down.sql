@@ -0,0 +1,5 @@
+-- Drop service account token index.
+DROP INDEX user_service_account_token;
+
+-- Drop service_account column from user table.
+ALTER TABLE user DROP COLUMN service_account;
@@ -0,0 +1,5 @@
+-- Add service_account column to user table.
+ALTER TABLE user ADD COLUMN service_account BOOLEAN NOT NULL DEFAULT false;
+
+-- Service accounts authenticate by their access token, so look them up by it.
+CREATE UNIQUE INDEX user_service_account_token ON user (access_token) WHERE service_account;
@@ -0,0 +1,13 @@
+-- Create audit_log table.
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    request_id TEXT NOT NULL,
+    real_user_id INTEGER REFERENCES user(id),
+    effective_user_id INTEGER REFERENCES user(id),
+    action TEXT NOT NULL,
+    subject TEXT,
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX audit_log_real_user ON audit_log (real_user_id);
+CREATE INDEX audit_log_effective_user ON audit_log (effective_user_id);
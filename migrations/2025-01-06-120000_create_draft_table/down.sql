@@ -0,0 +1,2 @@
+-- Drop draft table.
+DROP TABLE draft;
@@ -0,0 +1,10 @@
+-- Create draft table.
+CREATE TABLE IF NOT EXISTS draft (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES user(id),
+    form TEXT NOT NULL,
+    content TEXT NOT NULL,
+    updated_at DATETIME NOT NULL,
+    expires_at DATETIME NOT NULL,
+    UNIQUE (user_id, form)
+);
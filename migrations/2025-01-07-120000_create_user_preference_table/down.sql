@@ -0,0 +1,2 @@
+-- Drop user_preference table.
+DROP TABLE user_preference;
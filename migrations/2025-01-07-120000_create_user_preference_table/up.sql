@@ -0,0 +1,8 @@
+-- Create user_preference table.
+CREATE TABLE IF NOT EXISTS user_preference (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES user(id),
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    UNIQUE (user_id, key)
+);
@@ -0,0 +1,2 @@
+-- Drop purpose column from token table.
+ALTER TABLE token DROP COLUMN purpose;
@@ -0,0 +1,2 @@
+-- Add purpose column to token table.
+ALTER TABLE token ADD COLUMN purpose TEXT NOT NULL DEFAULT 'email_verification';
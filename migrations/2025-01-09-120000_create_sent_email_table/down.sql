@@ -0,0 +1,2 @@
+-- Drop sent_email table.
+DROP TABLE sent_email;
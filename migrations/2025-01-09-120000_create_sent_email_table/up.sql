@@ -0,0 +1,14 @@
+-- Create sent_email table.
+CREATE TABLE IF NOT EXISTS sent_email (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    recipient TEXT NOT NULL,
+    template TEXT,
+    subject TEXT NOT NULL,
+    status TEXT NOT NULL,
+    provider_message_id TEXT,
+    error TEXT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL
+);
+
+CREATE INDEX sent_email_recipient ON sent_email (recipient);
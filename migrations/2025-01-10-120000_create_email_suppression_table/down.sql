@@ -0,0 +1,2 @@
+-- Drop email_suppression table.
+DROP TABLE email_suppression;
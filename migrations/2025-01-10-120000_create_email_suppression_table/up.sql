@@ -0,0 +1,8 @@
+-- Create email_suppression table.
+CREATE TABLE IF NOT EXISTS email_suppression (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    address TEXT NOT NULL UNIQUE,
+    reason TEXT NOT NULL,
+    detail TEXT,
+    created_at DATETIME NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Drop digest_opt_out table.
+DROP TABLE digest_opt_out;
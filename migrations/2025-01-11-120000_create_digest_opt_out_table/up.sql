@@ -0,0 +1,6 @@
+-- Create digest_opt_out table.
+CREATE TABLE IF NOT EXISTS digest_opt_out (
+    user_id INTEGER NOT NULL REFERENCES user(id),
+    digest TEXT NOT NULL,
+    PRIMARY KEY (user_id, digest)
+);
@@ -0,0 +1,2 @@
+-- Drop dead_letter table.
+DROP TABLE dead_letter;
@@ -0,0 +1,8 @@
+-- Create dead_letter table.
+CREATE TABLE IF NOT EXISTS dead_letter (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    job TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    error TEXT NOT NULL,
+    created_at DATETIME NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Drop queued_job table.
+DROP TABLE queued_job;
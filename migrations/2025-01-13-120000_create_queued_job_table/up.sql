@@ -0,0 +1,11 @@
+-- Create queued_job table.
+CREATE TABLE IF NOT EXISTS queued_job (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    job TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    unique_key TEXT UNIQUE,
+    run_at DATETIME NOT NULL,
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX queued_job_run_at ON queued_job (run_at);
@@ -0,0 +1,4 @@
+-- Drop retry columns from queued_job table.
+ALTER TABLE queued_job DROP COLUMN locked_until;
+ALTER TABLE queued_job DROP COLUMN last_error;
+ALTER TABLE queued_job DROP COLUMN attempts;
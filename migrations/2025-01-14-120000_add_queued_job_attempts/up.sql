@@ -0,0 +1,4 @@
+-- Add retry columns to queued_job table.
+ALTER TABLE queued_job ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE queued_job ADD COLUMN last_error TEXT;
+ALTER TABLE queued_job ADD COLUMN locked_until DATETIME;
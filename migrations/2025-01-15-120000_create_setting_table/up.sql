@@ -0,0 +1,7 @@
+-- Create setting table.
+CREATE TABLE IF NOT EXISTS setting (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    key TEXT NOT NULL UNIQUE,
+    value TEXT NOT NULL,
+    updated_at DATETIME NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Drop kv_entry table.
+DROP TABLE kv_entry;
@@ -0,0 +1,10 @@
+-- Create kv_entry table.
+CREATE TABLE IF NOT EXISTS kv_entry (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    key TEXT NOT NULL UNIQUE,
+    value TEXT NOT NULL,
+    expires_at DATETIME,
+    updated_at DATETIME NOT NULL
+);
+
+CREATE INDEX kv_entry_expires_at ON kv_entry (expires_at);
@@ -0,0 +1,2 @@
+-- Drop announcement table.
+DROP TABLE announcement;
@@ -0,0 +1,13 @@
+-- Create announcement table.
+CREATE TABLE IF NOT EXISTS announcement (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    message TEXT NOT NULL,
+    level TEXT NOT NULL,
+    starts_at DATETIME NOT NULL,
+    ends_at DATETIME,
+    role_id INTEGER REFERENCES role(id) ON DELETE CASCADE,
+    announced_at DATETIME,
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX announcement_starts_at ON announcement (starts_at);
@@ -0,0 +1,2 @@
+-- Drop model_version table.
+DROP TABLE model_version;
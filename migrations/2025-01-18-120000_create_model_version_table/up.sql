@@ -0,0 +1,12 @@
+-- Create model_version table.
+CREATE TABLE IF NOT EXISTS model_version (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    model TEXT NOT NULL,
+    record_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    data TEXT NOT NULL,
+    user_id INTEGER REFERENCES user(id) ON DELETE SET NULL,
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX model_version_record ON model_version (model, record_id);
@@ -0,0 +1,14 @@
+-- Create attachment table.
+CREATE TABLE IF NOT EXISTS attachment (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER REFERENCES user(id) ON DELETE SET NULL,
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    path TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    scan_state TEXT NOT NULL,
+    scan_reason TEXT,
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX attachment_user_id ON attachment (user_id);
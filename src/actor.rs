@@ -0,0 +1,216 @@
+use std::future::Future;
+
+use anyhow::anyhow;
+use axum::extract::{FromRequestParts, Request};
+use axum::http::request::Parts;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use tracing::Instrument;
+use uuid::Uuid;
+
+use crate::error::LowboyError;
+use crate::model::{Model as _, User, UserModel as _};
+use crate::{metrics, AppContext, AuthSession};
+
+/// Requests are identified by this header, which is generated unless the client (or a proxy in
+/// front of the app) already set it.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The id of the user impersonating the logged in user, if any.
+pub(crate) const IMPERSONATOR_KEY: &str = "lowboy.impersonator";
+
+tokio::task_local! {
+    static CURRENT_ACTOR: RequestActor;
+}
+
+/// The id of the current request, added to the request extensions by [`request_id`].
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+/// Who is making a request (or running a job), so writes can be attributed to them.
+///
+/// The real user is the one actually making the request, and the effective user is the one it's
+/// being made as. They're the same unless an administrator is impersonating someone (see
+/// [`impersonate`]), or a job is acting on a user's behalf (see [`RequestActor::on_behalf_of`]).
+///
+/// Actors can be serialized into job payloads, so the job's writes are attributed to the request
+/// that enqueued it. The actor of the request being handled is also available with
+/// [`RequestActor::current`], which is how [`crate::model::ModelEvent`]s are attributed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestActor {
+    pub request_id: String,
+    pub real_user_id: Option<i32>,
+    pub effective_user_id: Option<i32>,
+}
+
+impl RequestActor {
+    /// An actor for work that isn't done by or for any user, such as scheduled maintenance.
+    pub fn system() -> Self {
+        Self {
+            request_id: new_request_id(),
+            real_user_id: None,
+            effective_user_id: None,
+        }
+    }
+
+    /// An actor for a job acting on `user_id`'s behalf, which no user is making directly.
+    pub fn on_behalf_of(user_id: i32) -> Self {
+        Self {
+            effective_user_id: Some(user_id),
+            ..Self::system()
+        }
+    }
+
+    pub fn is_impersonating(&self) -> bool {
+        self.real_user_id.is_some() && self.real_user_id != self.effective_user_id
+    }
+
+    /// The actor of the request, or the job scoped with [`RequestActor::scope`], being handled.
+    pub fn current() -> Option<Self> {
+        CURRENT_ACTOR.try_with(Clone::clone).ok()
+    }
+
+    /// Run `future` as this actor, e.g. a job acting on behalf of the request that enqueued it.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT_ACTOR.scope(self, future).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RequestActor {
+    type Rejection = LowboyError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(actor) = parts.extensions.get::<Self>() {
+            return Ok(actor.clone());
+        }
+
+        let request_id = parts
+            .extensions
+            .get::<RequestId>()
+            .map(|RequestId(id)| id.clone())
+            .unwrap_or_else(new_request_id);
+
+        let effective_user_id = parts
+            .extensions
+            .get::<AuthSession>()
+            .and_then(|auth_session| auth_session.user.as_ref())
+            .map(|user| user.id);
+
+        let impersonator = match parts.extensions.get::<Session>() {
+            Some(session) if effective_user_id.is_some() => {
+                session.get::<i32>(IMPERSONATOR_KEY).await?
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            request_id,
+            real_user_id: impersonator.or(effective_user_id),
+            effective_user_id,
+        })
+    }
+}
+
+fn new_request_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// Give each request an id, echoed back in the [`REQUEST_ID_HEADER`] response header and
+/// included in the request's log span.
+pub async fn request_id(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 128)
+        .map(str::to_string)
+        .unwrap_or_else(new_request_id);
+
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let span = tracing::info_span!("request", request_id = %id);
+    let mut response = next.run(request).instrument(span).await;
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    response
+}
+
+/// Make the request's [`RequestActor`] available to the code handling it, as an extension and
+/// with [`RequestActor::current`]. It goes inside the auth layer, which logs the user in.
+pub(crate) async fn track_actor(request: Request, next: Next) -> Response {
+    let (mut parts, body) = request.into_parts();
+    let actor = RequestActor::from_request_parts(&mut parts, &()).await;
+    let mut request = Request::from_parts(parts, body);
+
+    match actor {
+        Ok(actor) => {
+            request.extensions_mut().insert(actor.clone());
+            actor.scope(next.run(request)).await
+        }
+        Err(_) => next.run(request).await,
+    }
+}
+
+/// Log in as `target` on behalf of the `actor`'s real user, e.g. so an administrator can see what
+/// a user sees. Requests made while impersonating are attributed to both users.
+pub async fn impersonate<AC: AppContext>(
+    context: &AC,
+    auth_session: &mut AuthSession,
+    session: &Session,
+    actor: &RequestActor,
+    target: &User,
+) -> Result<(), LowboyError> {
+    let real_user_id = actor.real_user_id.ok_or(LowboyError::Unauthorized)?;
+
+    auth_session
+        .login(target)
+        .await
+        .map_err(|e| anyhow!("Error impersonating user({}): {e}", target.id))?;
+    session.insert(IMPERSONATOR_KEY, real_user_id).await?;
+
+    let impersonation = RequestActor {
+        effective_user_id: Some(target.id),
+        ..actor.clone()
+    };
+    context
+        .audit(&impersonation, "impersonation.start", Some(&target.username))
+        .await?;
+
+    Ok(())
+}
+
+/// Stop impersonating, logging back in as the real user.
+pub async fn stop_impersonating<AC: AppContext>(
+    context: &AC,
+    auth_session: &mut AuthSession,
+    session: &Session,
+    actor: &RequestActor,
+) -> Result<(), LowboyError> {
+    let Some(real_user_id) = session.remove::<i32>(IMPERSONATOR_KEY).await? else {
+        return Err(LowboyError::BadRequest);
+    };
+
+    let mut conn = metrics::checkout(context.database()).await?;
+    let real_user = User::load(real_user_id, &mut conn)
+        .await?
+        .with_roles_and_permissions(&mut conn)
+        .await?
+        .to_owned();
+    drop(conn);
+
+    context.audit(actor, "impersonation.stop", None).await?;
+
+    auth_session
+        .login(&real_user)
+        .await
+        .map_err(|e| anyhow!("Error ending impersonation of user({real_user_id}): {e}"))?;
+
+    Ok(())
+}
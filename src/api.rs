@@ -0,0 +1,193 @@
+//! Versioned JSON APIs, so apps can change their API without breaking clients.
+//!
+//! Each version is a router nested at its own prefix, e.g. `/api/v1` and `/api/v2` when returned
+//! from [`crate::app::App::api_routes`]. Versions can share handlers, which can tell which version
+//! they were called through with the [`RequestedVersion`] extension:
+//!
+//! ```ignore
+//! fn api_routes() -> Router<AC> {
+//!     let v1 = Router::new()
+//!         .route("/posts", get(list_posts))
+//!         .route("/posts/:id", get(show_post));
+//!     // v2 changes how posts are listed, and keeps the rest of v1.
+//!     let v2 = v1.clone().route("/posts", get(list_posts_v2));
+//!
+//!     ApiVersions::new()
+//!         .version(
+//!             ApiVersion::new("v1", v1)
+//!                 .deprecated(deprecated_at, Some(sunset))
+//!                 .deprecation_link("https://example.com/changelog#v2"),
+//!         )
+//!         .version(ApiVersion::new("v2", v2).openapi(v2_openapi()))
+//!         .into_router()
+//! }
+//! ```
+//!
+//! Deprecated versions respond with `Deprecation` and `Sunset` headers (RFC 9745 and RFC 8594),
+//! so clients can warn about them before they're removed.
+
+use axum::extract::Request;
+use axum::http::header::LINK;
+use axum::http::{HeaderName, HeaderValue};
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use chrono::{DateTime, Utc};
+
+const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+const SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+/// The API version a request was routed through, e.g. `v1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RequestedVersion(pub &'static str);
+
+#[derive(Clone, Debug)]
+struct Deprecation {
+    at: DateTime<Utc>,
+    sunset: Option<DateTime<Utc>>,
+    link: Option<String>,
+}
+
+/// A version of the API, see the [module docs](self).
+pub struct ApiVersion<S> {
+    name: &'static str,
+    router: Router<S>,
+    deprecation: Option<Deprecation>,
+    openapi: Option<serde_json::Value>,
+}
+
+impl<S: Clone + Send + Sync + 'static> ApiVersion<S> {
+    /// The version `name`, e.g. `v1`, which is also its path prefix.
+    pub fn new(name: &'static str, router: Router<S>) -> Self {
+        Self {
+            name,
+            router,
+            deprecation: None,
+            openapi: None,
+        }
+    }
+
+    /// Mark the version as deprecated as of `at`, to be removed at `sunset` if there's a date for
+    /// it.
+    pub fn deprecated(self, at: DateTime<Utc>, sunset: Option<DateTime<Utc>>) -> Self {
+        let link = None;
+        Self {
+            deprecation: Some(Deprecation { at, sunset, link }),
+            ..self
+        }
+    }
+
+    /// Where clients can read about the deprecation, e.g. a migration guide. Only used for
+    /// deprecated versions.
+    pub fn deprecation_link(mut self, link: impl Into<String>) -> Self {
+        if let Some(ref mut deprecation) = self.deprecation {
+            deprecation.link = Some(link.into());
+        }
+        self
+    }
+
+    /// Serve the version's OpenAPI document at `/<version>/openapi.json`.
+    pub fn openapi(self, document: serde_json::Value) -> Self {
+        Self {
+            openapi: Some(document),
+            ..self
+        }
+    }
+
+    fn into_router(self) -> Router<S> {
+        let mut router = self.router;
+
+        if let Some(document) = self.openapi {
+            router = router.route("/openapi.json", get(|| async move { Json(document) }));
+        }
+
+        if let Some(deprecation) = self.deprecation {
+            router = router.layer(middleware::from_fn(move |request: Request, next: Next| {
+                let deprecation = deprecation.clone();
+                async move { deprecate(&deprecation, next.run(request).await) }
+            }));
+        }
+
+        router.layer(Extension(RequestedVersion(self.name)))
+    }
+}
+
+fn deprecate(deprecation: &Deprecation, mut response: Response) -> Response {
+    let headers = response.headers_mut();
+
+    // RFC 9745's structured field date, e.g. `@1688169599`.
+    let at = format!("@{}", deprecation.at.timestamp());
+    if let Ok(at) = HeaderValue::from_str(&at) {
+        headers.insert(DEPRECATION, at);
+    }
+    if let Some(sunset) = deprecation.sunset {
+        let sunset = sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        if let Ok(sunset) = HeaderValue::from_str(&sunset) {
+            headers.insert(SUNSET, sunset);
+        }
+    }
+    if let Some(ref link) = deprecation.link {
+        let link = format!(r#"<{link}>; rel="deprecation""#);
+        if let Ok(link) = HeaderValue::from_str(&link) {
+            headers.append(LINK, link);
+        }
+    }
+
+    response
+}
+
+/// The versions of an API, each nested at its name.
+pub struct ApiVersions<S> {
+    versions: Vec<ApiVersion<S>>,
+}
+
+impl<S: Clone + Send + Sync + 'static> Default for ApiVersions<S> {
+    fn default() -> Self {
+        Self { versions: vec![] }
+    }
+}
+
+impl<S: Clone + Send + Sync + 'static> ApiVersions<S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn version(mut self, version: ApiVersion<S>) -> Self {
+        self.versions.push(version);
+        self
+    }
+
+    /// The router with every version nested at its name, e.g. `/v1`.
+    pub fn into_router(self) -> Router<S> {
+        self.versions
+            .into_iter()
+            .fold(Router::new(), |router, version| {
+                let path = format!("/{}", version.name);
+                router.nest(&path, version.into_router())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::response::IntoResponse;
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn deprecated_responses_say_when_and_until_when() {
+        let deprecation = Deprecation {
+            at: Utc.with_ymd_and_hms(2023, 6, 30, 23, 59, 59).unwrap(),
+            sunset: Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+            link: Some("https://example.com/changelog".to_string()),
+        };
+
+        let response = deprecate(&deprecation, ().into_response());
+        let headers = response.headers();
+        assert_eq!(headers[DEPRECATION], "@1688169599");
+        assert_eq!(headers[SUNSET], "Mon, 01 Jan 2024 00:00:00 GMT");
+        assert_eq!(headers[LINK], r#"<https://example.com/changelog>; rel="deprecation""#);
+    }
+}
@@ -1,18 +1,34 @@
+use std::future::Future;
+
 use axum::Router;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "grpc")]
+use tonic::service::Routes;
 
 use crate::auth::{
     LoginForm, LowboyEmailVerificationView, LowboyLoginView, LowboyRegisterView, RegistrationForm,
 };
+use crate::bot::BotCheck;
 use crate::context::CloneableAppContext;
 use crate::controller;
+use crate::controller::icons::WebManifest;
+use crate::controller::BuildInfo;
+use crate::digest::Digest;
 use crate::error::{LowboyError, LowboyErrorView};
-use crate::model::UserModel;
-use crate::view::LowboyLayout;
+use crate::form::UniqueConstraints;
+use crate::guest::GuestSession;
+use crate::jobs::Jobs;
+use crate::mailer::MailTemplates;
+use crate::model::{PermissionDef, User, UserModel};
+use crate::publish::Publishables;
+use crate::settings::SettingDefinitions;
+use crate::trash::Trash;
+use crate::versioning::VersionedModels;
+use crate::view::{Components, LowboyLayout};
 
 #[allow(unused_variables)]
 pub trait App<AC: CloneableAppContext>: Send + 'static {
-    type User: UserModel + Send + Clone;
+    type User: UserModel + Send + Sync + Clone + 'static;
     type Layout: LowboyLayout<Self::User>;
     type ErrorView: LowboyErrorView;
     type RegistrationForm: RegistrationForm
@@ -31,6 +47,17 @@ pub trait App<AC: CloneableAppContext>: Send + 'static {
         Self::name()
     }
 
+    /// What was built, served at `/__version`, in the version header and logged at startup. Fill
+    /// it in from the app crate with [`crate::build_info!`].
+    fn build_info() -> BuildInfo {
+        BuildInfo::unknown(Self::name())
+    }
+
+    /// The web app manifest, served at [`crate::controller::icons::MANIFEST_PATH`].
+    fn web_manifest() -> WebManifest {
+        WebManifest::new(Self::app_title())
+    }
+
     fn layout(context: &AC) -> Self::Layout {
         Self::Layout::default()
     }
@@ -51,8 +78,92 @@ pub trait App<AC: CloneableAppContext>: Send + 'static {
         Self::ErrorView::default()
     }
 
+    /// Migrate the data attached to a guest session to the account the guest just registered or
+    /// logged in as.
+    ///
+    /// The guest session is only ended once this succeeds. If it fails, the error is logged and
+    /// the guest session is handed over again the next time the user logs in, so the migration
+    /// should be safe to retry.
+    fn on_guest_upgrade(
+        context: &AC,
+        user: &User,
+        guest: GuestSession,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send {
+        async { Ok(()) }
+    }
+
+    /// The permissions the app checks for. They're added to the database at boot if missing, and
+    /// their descriptions are shown in the admin UI.
+    fn permissions() -> &'static [PermissionDef] {
+        &[]
+    }
+
+    /// Checks registration and login submissions have to pass, in addition to the built-in
+    /// honeypot, time trap, registration limit and captcha.
+    fn bot_checks() -> Vec<Box<dyn BotCheck>> {
+        vec![]
+    }
+
+    /// Register the app's components, or replace lowboy's, so templates can render them with
+    /// [`crate::view::component`].
+    fn components(components: &mut Components) {}
+
+    /// Register the unique constraints of the app's models, so violations of them can be reported
+    /// as form errors with [`crate::form::FormErrors::from_unique_violation`].
+    fn unique_constraints(constraints: &mut UniqueConstraints) {}
+
+    /// Register the app's mail templates, or replace lowboy's (e.g. `verify_email`), keeping the
+    /// props. Templates with a preview are shown in the dev mailbox.
+    fn mail_templates(templates: &mut MailTemplates) {}
+
+    /// Register the app's background jobs, so they can be queued with
+    /// [`crate::jobs::JobQueue::enqueue_in`].
+    fn jobs(jobs: &mut Jobs) {}
+
+    /// Register the app's settings, so they're editable in the admin alongside lowboy's, see
+    /// [`crate::settings`].
+    fn settings(settings: &mut SettingDefinitions) {}
+
+    /// Register the app's models with scheduled publishing, so they're published when they're due,
+    /// see [`crate::publish`].
+    fn publishables(publishables: &mut Publishables) {}
+
+    /// Register the app's versioned models, so their old versions are pruned, see
+    /// [`crate::versioning`].
+    fn versioned_models(models: &mut VersionedModels) {}
+
+    /// Register the app's soft deleted models, so they're listed in the admin's trash and purged
+    /// once they expire, see [`crate::trash`].
+    fn trash(trash: &mut Trash) {}
+
+    /// The app's digest emails, each sent on its schedule to the users who haven't opted out.
+    fn digests() -> Vec<Box<dyn Digest>> {
+        vec![]
+    }
+
     fn routes() -> Router<AC>;
 
+    /// The app's JSON API, nested under `/api`. Its routes are treated as [API
+    /// requests](crate::auth::ApiRequest), and service accounts can call them with their tokens.
+    /// See [`crate::api::ApiVersions`] for versioning them.
+    fn api_routes() -> Router<AC> {
+        Router::new()
+    }
+
+    /// The app's gRPC services, served alongside the web app, see [`crate::grpc`]. They bypass the
+    /// auth and permission layers, so they have to check calls themselves.
+    #[cfg(feature = "grpc")]
+    fn grpc(context: &AC) -> Option<Routes> {
+        None
+    }
+
+    /// Add layers to every route, e.g. custom authentication or tenant resolution. They run inside
+    /// lowboy's session, authentication, locale and error page layers, so the user, their locale
+    /// and [`crate::AuthSession`] are available, and errors are rendered as usual.
+    fn middleware(router: Router<AC>) -> Router<AC> {
+        router
+    }
+
     fn auth_routes<App: self::App<AC>>() -> Router<AC> {
         controller::auth::routes::<App, AC>()
     }
@@ -0,0 +1,326 @@
+//! Storing files users upload as [`Attachment`]s.
+//!
+//! [`Attachments::store`] writes each upload to the attachment directory and records it as
+//! pending, then scans it, moving it into quarantine if it's flagged (see [`crate::scan`]). With
+//! the `media` feature, images are processed before they're written, stripping their metadata
+//! unless the upload is given other [`ImageOptions`] with [`Upload::with_image_options`].
+//! Attachments are served with [`Attachment::download`], which refuses to serve those which
+//! haven't been scanned and found clean:
+//!
+//! ```ignore
+//! let attachments = Attachments::new("uploads").with_scanner(ClamAv::new());
+//!
+//! let upload = Upload::new(&file_name, &content_type, &bytes).with_user_id(Some(user.id));
+//! let attachment = attachments.store(upload, now, &mut conn).await?;
+//!
+//! // In the download handler, once the user is allowed to see it:
+//! Ok(Attachment::load(id, &mut conn).await?.download().await?)
+//! ```
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::http::HeaderValue;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::error::LowboyError;
+#[cfg(feature = "media")]
+use crate::media::{self, ImageOptions};
+use crate::model::Attachment;
+use crate::scan::{self, NoopScanner, Scanner};
+use crate::Connection;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[cfg(feature = "media")]
+    #[error(transparent)]
+    Image(#[from] media::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[cfg(feature = "media")]
+    #[error(transparent)]
+    Join(#[from] tokio::task::JoinError),
+
+    #[error(transparent)]
+    Scan(#[from] scan::Error),
+}
+
+/// A file being uploaded, to be stored with [`Attachments::store`].
+#[derive(Clone, Copy, Debug)]
+pub struct Upload<'a> {
+    /// The user uploading the file, if it's uploaded by one.
+    pub user_id: Option<i32>,
+    pub file_name: &'a str,
+    pub content_type: &'a str,
+    pub bytes: &'a [u8],
+    /// How the file is processed if it's an image.
+    #[cfg(feature = "media")]
+    pub image_options: ImageOptions,
+}
+
+impl<'a> Upload<'a> {
+    pub fn new(file_name: &'a str, content_type: &'a str, bytes: &'a [u8]) -> Self {
+        Self {
+            user_id: None,
+            file_name,
+            content_type,
+            bytes,
+            #[cfg(feature = "media")]
+            image_options: ImageOptions::default(),
+        }
+    }
+
+    pub fn with_user_id(self, user_id: Option<i32>) -> Self {
+        Self { user_id, ..self }
+    }
+
+    #[cfg(feature = "media")]
+    pub fn with_image_options(self, image_options: ImageOptions) -> Self {
+        Self {
+            image_options,
+            ..self
+        }
+    }
+
+    /// The contents to store, which for images the `image` crate can decode is the image processed
+    /// according to [`Upload::image_options`].
+    #[cfg(feature = "media")]
+    async fn contents(&self) -> Result<Cow<'a, [u8]>> {
+        if image::ImageFormat::from_mime_type(self.content_type).is_none() {
+            return Ok(Cow::Borrowed(self.bytes));
+        }
+
+        let bytes = self.bytes.to_vec();
+        let options = self.image_options;
+        let processed =
+            tokio::task::spawn_blocking(move || media::process_image(&bytes, options)).await??;
+
+        Ok(Cow::Owned(processed))
+    }
+
+    #[cfg(not(feature = "media"))]
+    async fn contents(&self) -> Result<Cow<'a, [u8]>> {
+        Ok(Cow::Borrowed(self.bytes))
+    }
+}
+
+/// Where attachments are stored, and what scans them, see the [module docs](self).
+#[derive(Clone)]
+pub struct Attachments {
+    dir: PathBuf,
+    quarantine: PathBuf,
+    scanner: Arc<dyn Scanner>,
+}
+
+impl Attachments {
+    /// Store attachments in `dir`, and quarantine flagged ones in its `quarantine` directory.
+    /// Every upload is treated as clean until a scanner is given with
+    /// [`Attachments::with_scanner`].
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+
+        Self {
+            quarantine: dir.join("quarantine"),
+            dir,
+            scanner: Arc::new(NoopScanner),
+        }
+    }
+
+    pub fn with_scanner(self, scanner: impl Scanner + 'static) -> Self {
+        Self {
+            scanner: Arc::new(scanner),
+            ..self
+        }
+    }
+
+    pub fn with_quarantine(self, quarantine: impl Into<PathBuf>) -> Self {
+        Self {
+            quarantine: quarantine.into(),
+            ..self
+        }
+    }
+
+    /// Process `upload` if it's an image, then store and scan it. If scanning fails the error is
+    /// returned, and the attachment is kept pending, so it isn't served.
+    pub async fn store(
+        &self,
+        upload: Upload<'_>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> Result<Attachment> {
+        let contents = upload.contents().await?;
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+        // Stored under a name of our own, so uploads can't overwrite each other, or be written
+        // outside of the directory.
+        let path = self.dir.join(Uuid::new_v4().to_string());
+        tokio::fs::write(&path, &contents).await?;
+
+        let stored_at = path.to_string_lossy();
+        let size = contents.len() as i64;
+        let record = Attachment::create_record(
+            upload.file_name,
+            upload.content_type,
+            &stored_at,
+            size,
+            now,
+        )
+        .with_user_id(upload.user_id)
+        .save(conn)
+        .await?;
+        let mut attachment = Attachment::from(record);
+
+        let scanned = scan::scan_upload(self.scanner.as_ref(), &path, &self.quarantine).await?;
+        attachment.set_scanned(&scanned, conn).await?;
+
+        Ok(attachment)
+    }
+}
+
+impl Attachment {
+    /// Respond with the attachment as a download, unless it hasn't been scanned and found clean.
+    pub async fn download(&self) -> std::result::Result<Response, LowboyError> {
+        self.scan_state.ensure_downloadable()?;
+
+        let bytes = tokio::fs::read(&self.path)
+            .await
+            .map_err(|e| anyhow!("couldn't read attachment({}): {e}", self.id))?;
+        let content_type = HeaderValue::from_str(&self.content_type)
+            .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+
+        Ok((
+            [
+                (CONTENT_TYPE, content_type),
+                (CONTENT_DISPOSITION, content_disposition(&self.file_name)),
+            ],
+            bytes,
+        )
+            .into_response())
+    }
+}
+
+/// Download `file_name`, with the characters which can't be in a quoted header value replaced.
+fn content_disposition(file_name: &str) -> HeaderValue {
+    let file_name: String = file_name
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c == ' ' || c.is_ascii_graphic() => c,
+            _ => '_',
+        })
+        .collect();
+
+    HeaderValue::from_str(&format!("attachment; filename=\"{file_name}\""))
+        .unwrap_or(HeaderValue::from_static("attachment"))
+}
+
+impl From<Error> for LowboyError {
+    fn from(value: Error) -> Self {
+        Self::Internal(anyhow!("attachment error: {value}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use axum::http::StatusCode;
+
+    use super::*;
+    use crate::model::Model;
+    use crate::scan::{ScanState, Verdict};
+
+    /// Flags files whose contents contain `EICAR`.
+    struct ContentScanner;
+
+    #[async_trait::async_trait]
+    impl Scanner for ContentScanner {
+        async fn scan(&self, path: &Path) -> std::result::Result<Verdict, scan::Error> {
+            let contents = tokio::fs::read_to_string(path).await?;
+
+            if contents.contains("EICAR") {
+                return Ok(Verdict::Flagged("Eicar-Test-Signature".to_string()));
+            }
+
+            Ok(Verdict::Clean)
+        }
+    }
+
+    fn attachments() -> Attachments {
+        let dir = std::env::temp_dir().join(format!("lowboy-attachments-{}", Uuid::new_v4()));
+
+        Attachments::new(dir).with_scanner(ContentScanner)
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn clean_uploads_are_stored_and_downloadable() {
+        let database = crate::test::database().await;
+        let mut conn = database.get().await.unwrap();
+        let upload = Upload::new("notes \"final\".txt", "text/plain", b"hello");
+
+        let attachment = attachments().store(upload, now(), &mut conn).await.unwrap();
+        assert_eq!(attachment.scan_state, ScanState::Clean);
+        assert_eq!(attachment.size, 5);
+
+        let attachment = Attachment::load(attachment.id, &mut conn).await.unwrap();
+        assert_eq!(attachment.scan_state, ScanState::Clean);
+        let response = attachment.download().await.unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/plain");
+        assert_eq!(
+            response.headers()[CONTENT_DISPOSITION],
+            "attachment; filename=\"notes _final_.txt\""
+        );
+    }
+
+    #[tokio::test]
+    async fn flagged_uploads_are_quarantined_and_refused() {
+        let database = crate::test::database().await;
+        let mut conn = database.get().await.unwrap();
+        let attachments = attachments();
+        let upload = Upload::new("virus.txt", "text/plain", b"EICAR");
+
+        let attachment = attachments.store(upload, now(), &mut conn).await.unwrap();
+        let attachment = Attachment::load(attachment.id, &mut conn).await.unwrap();
+        assert_eq!(attachment.scan_state, ScanState::Quarantined);
+        assert_eq!(attachment.scan_reason.as_deref(), Some("Eicar-Test-Signature"));
+        assert!(Path::new(&attachment.path).starts_with(&attachments.quarantine));
+
+        let refused = attachment.download().await;
+        assert!(matches!(
+            refused,
+            Err(LowboyError::Rejected { status: StatusCode::FORBIDDEN, .. })
+        ));
+    }
+
+    #[cfg(feature = "media")]
+    #[tokio::test]
+    async fn images_are_processed_before_they_are_stored() {
+        let database = crate::test::database().await;
+        let mut conn = database.get().await.unwrap();
+        let attachments = attachments();
+        let upload = Upload::new("photo.png", "image/png", b"not a png");
+
+        let processed = attachments.store(upload, now(), &mut conn).await;
+        assert!(matches!(processed, Err(Error::Image(_))));
+
+        let upload = upload.with_image_options(ImageOptions::original());
+        let attachment = attachments.store(upload, now(), &mut conn).await.unwrap();
+        assert_eq!(tokio::fs::read(&attachment.path).await.unwrap(), b"not a png");
+    }
+}
@@ -2,38 +2,58 @@
 use std::collections::{HashMap, HashSet};
 
 use async_trait::async_trait;
+use axum::extract::Request;
+use axum::http::header::ACCEPT;
+use axum::http::{Extensions, HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Redirect, Response};
 use axum_login::{AuthnBackend, AuthzBackend};
 use derive_masked::DebugMasked;
 use derive_more::derive::Display;
 use dyn_clone::DynClone;
 use mopa::mopafy;
+#[cfg(feature = "oauth")]
 use oauth2::basic::{BasicClient, BasicRequestTokenError};
+#[cfg(feature = "oauth")]
 use oauth2::http::header::{AUTHORIZATION, USER_AGENT};
+#[cfg(feature = "oauth")]
 use oauth2::reqwest::{async_http_client, AsyncHttpClientError};
+#[cfg(feature = "oauth")]
 use oauth2::url::Url;
+#[cfg(feature = "oauth")]
 use oauth2::{
     AccessToken, AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, RedirectUrl, Scope,
     TokenResponse, TokenUrl,
 };
-use password_auth::verify_password;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
-use crate::model::{CredentialKind, Credentials, Model as _, Permission, User, UserModel};
-use crate::view::LowboyView;
-use crate::AppContext;
+use crate::bot::BotFields;
+use crate::error::{problem, problem_response};
+use crate::form::FormErrors;
+use crate::model::{
+    ApiTokenCredentials, CredentialKind, Credentials, Model as _, ModelCache, Owned, Permission,
+    User, UserModel, WithRolesAndPermissions,
+};
+#[cfg(feature = "oauth")]
+use crate::settings::RegistrationOpen;
+use crate::view::{LowboyView, ProviderButton, ProviderButtons};
+use crate::{metrics, AppContext};
 
 pub type AuthSession = axum_login::AuthSession<LowboyAuth>;
 type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum Error {
     #[error(transparent)]
     Reqwest(reqwest::Error),
 
+    #[cfg(feature = "oauth")]
     #[error(transparent)]
     OAuth2(BasicRequestTokenError<AsyncHttpClientError>),
 
+    #[cfg(feature = "oauth")]
     #[error(transparent)]
     OAuth2Url(#[from] oauth2::url::ParseError),
 
@@ -125,6 +145,19 @@ impl RegistrationForm for LowboyRegisterForm {
 
 pub trait LowboyRegisterView<T: RegistrationForm + Default>: LowboyView + Clone + Default {
     fn set_form(&mut self, form: T) -> &mut Self;
+    fn set_errors(&mut self, errors: FormErrors) -> &mut Self;
+
+    /// Views render the bot check fields (e.g. the honeypot and captcha widget) inside the form,
+    /// unescaped.
+    fn set_bot_fields(&mut self, _fields: BotFields) -> &mut Self {
+        self
+    }
+
+    /// Views render the sign in buttons of the configured OAuth providers inside the form,
+    /// unescaped.
+    fn set_providers(&mut self, _providers: ProviderButtons) -> &mut Self {
+        self
+    }
 }
 
 pub trait LowboyEmailVerificationView: LowboyView + Clone + Default {
@@ -186,6 +219,19 @@ impl LoginForm for LowboyLoginForm {
 
 pub trait LowboyLoginView<T: LoginForm + Default>: LowboyView + Clone + Default {
     fn set_form(&mut self, form: T) -> &mut Self;
+    fn set_errors(&mut self, errors: FormErrors) -> &mut Self;
+
+    /// Views render the bot check fields (e.g. the honeypot and captcha widget) inside the form,
+    /// unescaped.
+    fn set_bot_fields(&mut self, _fields: BotFields) -> &mut Self {
+        self
+    }
+
+    /// Views render the sign in buttons of the configured OAuth providers inside the form,
+    /// unescaped.
+    fn set_providers(&mut self, _providers: ProviderButtons) -> &mut Self {
+        self
+    }
 }
 
 #[derive(Clone)]
@@ -202,11 +248,17 @@ pub struct IdentityProviderConfig {
     pub client_secret: String,
     pub auth_url: String,
     pub token_url: String,
+    /// Override the provider's user info endpoint, e.g. to point it at a mock provider in tests.
+    #[serde(default)]
+    pub userinfo_url: Option<String>,
     pub intermediary_redirect: bool,
     #[serde(default)]
-    pub scopes: Vec<Scope>,
+    pub scopes: Vec<String>,
     #[serde(default)]
     pub extra_params: HashMap<String, String>,
+    /// The name shown on the provider's sign in button, instead of the provider's own.
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
 impl IdentityProviderConfig {
@@ -223,9 +275,23 @@ impl IdentityProviderConfig {
             client_secret: client_secret.into(),
             auth_url: auth_url.into(),
             token_url: token_url.into(),
+            userinfo_url: None,
             intermediary_redirect: false,
             scopes: vec![],
             extra_params: HashMap::new(),
+            label: None,
+        }
+    }
+
+    /// The provider's sign in button.
+    pub fn button(&self) -> ProviderButton {
+        ProviderButton {
+            name: self.kind.to_string(),
+            label: self
+                .label
+                .clone()
+                .unwrap_or_else(|| self.kind.label().to_string()),
+            icon: self.kind.to_string(),
         }
     }
 }
@@ -239,16 +305,39 @@ pub enum IdentityProvider {
 }
 
 impl IdentityProvider {
+    /// The provider's name, as shown to users.
+    pub fn label(&self) -> &'static str {
+        use IdentityProvider::*;
+
+        match *self {
+            GitHub => "GitHub",
+            Discord => "Discord",
+        }
+    }
+
+    pub fn userinfo_url(&self) -> &'static str {
+        use IdentityProvider::*;
+
+        match *self {
+            GitHub => "https://api.github.com/user",
+            Discord => "https://discord.com/api/users/@me",
+        }
+    }
+
+    #[cfg(feature = "oauth")]
     pub async fn fetch_registration_details(
         &self,
         token: &AccessToken,
+        userinfo_url: Option<&str>,
     ) -> Result<RegistrationDetails> {
         use IdentityProvider::*;
 
+        let userinfo_url = userinfo_url.unwrap_or(self.userinfo_url());
+
         match *self {
             GitHub => {
                 let details = reqwest::Client::new()
-                    .get("https://api.github.com/user")
+                    .get(userinfo_url)
                     .header(USER_AGENT.as_str(), "lowboy")
                     .header(AUTHORIZATION.as_str(), format!("Bearer {}", token.secret()))
                     .send()
@@ -263,7 +352,7 @@ impl IdentityProvider {
 
             Discord => {
                 let details = reqwest::Client::new()
-                    .get("https://discord.com/api/users/@me")
+                    .get(userinfo_url)
                     .header(USER_AGENT.as_str(), "lowboy")
                     .header(AUTHORIZATION.as_str(), format!("Bearer {}", token.secret()))
                     .send()
@@ -279,16 +368,41 @@ impl IdentityProvider {
     }
 }
 
-#[derive(Clone, Default)]
+#[cfg(feature = "oauth")]
+#[derive(Clone)]
 pub struct OAuthClientManager {
+    /// The URL the app is reached at, which the providers redirect back to.
+    public_url: String,
     clients: HashMap<IdentityProvider, (BasicClient, IdentityProviderConfig)>,
+    /// The providers in the order they're configured, for showing their buttons in that order.
+    order: Vec<IdentityProvider>,
 }
 
+#[cfg(feature = "oauth")]
 impl OAuthClientManager {
+    pub fn new(public_url: impl Into<String>) -> Self {
+        Self {
+            public_url: public_url.into(),
+            clients: HashMap::new(),
+            order: vec![],
+        }
+    }
+
     pub fn get(&self, idp: &IdentityProvider) -> Option<&(BasicClient, IdentityProviderConfig)> {
         self.clients.get(idp)
     }
 
+    /// The sign in buttons of the configured providers.
+    pub fn buttons(&self) -> ProviderButtons {
+        ProviderButtons::new(
+            self.order
+                .iter()
+                .filter_map(|provider| self.clients.get(provider))
+                .map(|(_, config)| config.button())
+                .collect(),
+        )
+    }
+
     pub fn insert(&mut self, config: IdentityProviderConfig) -> Result<&mut Self> {
         let provider = config.kind.clone();
         let intermediary_redirect = config.intermediary_redirect;
@@ -298,11 +412,14 @@ impl OAuthClientManager {
             AuthUrl::new(config.auth_url.to_string())?,
             Some(TokenUrl::new(config.token_url.to_string())?),
         )
-        // @TODO
         .set_redirect_uri(RedirectUrl::new(format!(
-            "http://localhost:3000/login/oauth/{provider}/callback?intermediary_redirect={intermediary_redirect}"
+            "{}/login/oauth/{provider}/callback?intermediary_redirect={intermediary_redirect}",
+            self.public_url
         ))?);
 
+        if !self.order.contains(&provider) {
+            self.order.push(provider.clone());
+        }
         self.clients.insert(provider, (client, config));
         Ok(self)
     }
@@ -310,30 +427,74 @@ impl OAuthClientManager {
 
 #[derive(Clone)]
 pub struct LowboyAuth {
-    pub oauth: OAuthClientManager,
+    // Private, so apps can't build the backend with a struct literal that the `oauth` feature
+    // would break.
+    #[cfg(feature = "oauth")]
+    oauth: OAuthClientManager,
     pub context: Box<dyn AppContext>,
 }
 
 impl LowboyAuth {
+    /// Lowboy's auth backend, signing in with `providers` as well as passwords if it's built with
+    /// the `oauth` feature.
     pub fn new(
         context: Box<dyn AppContext>,
         providers: Vec<IdentityProviderConfig>,
+        public_url: &str,
     ) -> Result<Self> {
-        let mut oauth = OAuthClientManager::default();
-
-        for provider in providers.into_iter() {
-            oauth.insert(provider)?;
+        #[cfg(feature = "oauth")]
+        let oauth = {
+            let mut oauth = OAuthClientManager::new(public_url);
+            for provider in providers.into_iter() {
+                oauth.insert(provider)?;
+            }
+            oauth
+        };
+
+        #[cfg(not(feature = "oauth"))]
+        {
+            // The providers would be redirected back to the public URL.
+            let _ = public_url;
+            if !providers.is_empty() {
+                tracing::warn!(
+                    "ignoring the configured oauth providers, lowboy was built without the `oauth` \
+                     feature"
+                );
+            }
         }
 
-        Ok(Self { oauth, context })
+        Ok(Self {
+            #[cfg(feature = "oauth")]
+            oauth,
+            context,
+        })
+    }
+
+    /// The OAuth clients of the configured providers.
+    #[cfg(feature = "oauth")]
+    pub fn oauth(&self) -> &OAuthClientManager {
+        &self.oauth
+    }
+
+    /// The sign in buttons of the configured providers, for the login and register views.
+    #[cfg(feature = "oauth")]
+    pub fn provider_buttons(&self) -> ProviderButtons {
+        self.oauth.buttons()
     }
 
+    /// Without the `oauth` feature there are no providers to sign in with.
+    #[cfg(not(feature = "oauth"))]
+    pub fn provider_buttons(&self) -> ProviderButtons {
+        ProviderButtons::default()
+    }
+
+    #[cfg(feature = "oauth")]
     pub fn authorize_url(&self, idp: &IdentityProvider) -> Option<(Url, CsrfToken)> {
         let (client, config) = self.oauth.get(idp)?;
 
         let mut auth_url = client
             .authorize_url(CsrfToken::new_random)
-            .add_scopes(config.scopes.clone());
+            .add_scopes(config.scopes.iter().cloned().map(Scope::new));
 
         for (name, value) in &config.extra_params {
             auth_url = auth_url.add_extra_param(name, value);
@@ -370,7 +531,7 @@ impl AuthnBackend for LowboyAuth {
         &self,
         credentials: Self::Credentials,
     ) -> std::result::Result<Option<Self::User>, Self::Error> {
-        let mut conn = self.context.database().get().await?;
+        let mut conn = metrics::checkout(self.context.database()).await?;
 
         // @TODO confirm the user has a verified email before being able to authenticate
         match credentials.kind {
@@ -385,24 +546,40 @@ impl AuthnBackend for LowboyAuth {
                     return Ok(None);
                 };
 
-                tokio::task::spawn_blocking(|| {
-                    Ok(verify_password(
-                        credentials.password,
-                        user.password.as_ref().expect("checked in query"),
-                    )
-                    .is_ok()
-                    .then_some(user))
-                })
-                .await?
+                let hash = user.password.clone().expect("checked in query");
+                let verified = self
+                    .context
+                    .password_hasher()
+                    .verify(credentials.password, hash)
+                    .await?;
+
+                Ok(verified.then_some(user))
+            }
+            CredentialKind::ApiToken => {
+                let credentials = credentials
+                    .api_token
+                    .ok_or(Error::MissingCredential("api token"))?;
+                let Some(mut user) =
+                    User::find_service_account_by_token(&credentials.token, &mut conn).await?
+                else {
+                    return Ok(None);
+                };
+
+                // Token authenticated requests don't have a session for `get_user` to be called
+                // with, so load the roles and permissions here instead.
+                user.with_roles_and_permissions(&mut conn).await?;
+
+                Ok(Some(user))
             }
+            #[cfg(feature = "oauth")]
             CredentialKind::OAuth(provider) => {
                 let credentials = credentials.oauth.ok_or(Error::MissingCredential("oauth"))?;
                 // Ensure the CSRF state has not been tampered with.
-                if credentials.old_state.secret() != credentials.new_state.secret() {
+                if credentials.old_state != credentials.new_state {
                     return Ok(None);
                 };
 
-                let (client, _) =
+                let (client, config) =
                     self.oauth
                         .get(&provider)
                         .ok_or(Error::OAuthClientManager(format!(
@@ -416,7 +593,9 @@ impl AuthnBackend for LowboyAuth {
                     .map_err(Self::Error::OAuth2)?;
 
                 let token = token_res.access_token();
-                let registration_details = provider.fetch_registration_details(token).await?;
+                let registration_details = provider
+                    .fetch_registration_details(token, config.userinfo_url.as_deref())
+                    .await?;
 
                 let (username, email) = match registration_details {
                     RegistrationDetails::GitHub(ref info) => (&info.login, &info.email),
@@ -435,6 +614,11 @@ impl AuthnBackend for LowboyAuth {
                 let access_token = token.secret();
                 let user =
                     if let Some(mut user) = User::find_by_username(username, &mut conn).await? {
+                        // Service accounts can only authenticate with their API token.
+                        if user.service_account {
+                            return Ok(None);
+                        }
+
                         // @note this caused some pain trying to figure out why i can't log back in
                         // after logging out. we're returning the user model with the old token. leaving
                         // this commented out here to figure out a better design later (never?? :D)
@@ -448,15 +632,33 @@ impl AuthnBackend for LowboyAuth {
                         user.update_record().save(&mut conn).await?;
                         user
                     } else {
-                        let user =
-                            User::new(username, email, None, Some(access_token), &mut conn).await?;
+                        let RegistrationOpen(open) = self
+                            .context
+                            .settings()
+                            .get::<RegistrationOpen>()
+                            .await
+                            .map_err(|e| Error::AppError(e.to_string()))?;
+                        if !open {
+                            return Ok(None);
+                        }
+
+                        let user = User::new(
+                            username,
+                            email,
+                            None,
+                            Some(access_token),
+                            self.context.clock(),
+                            self.context.secret_generator(),
+                            &mut conn,
+                        )
+                        .await?;
 
                         self.context
-                            .on_new_user(&user, registration_details)
+                            .complete_new_user(&user, registration_details)
                             .await
                             .map_err(|e| {
                                 Error::AppError(format!(
-                                    "there was an error executing on_new_user: {e}"
+                                    "there was an error completing the new user: {e}"
                                 ))
                             })?;
 
@@ -465,6 +667,9 @@ impl AuthnBackend for LowboyAuth {
 
                 Ok(Some(user))
             }
+            // There are no OAuth clients to exchange the code with.
+            #[cfg(not(feature = "oauth"))]
+            CredentialKind::OAuth(_) => Ok(None),
         }
     }
 
@@ -472,12 +677,22 @@ impl AuthnBackend for LowboyAuth {
         &self,
         user_id: &axum_login::UserId<Self>,
     ) -> std::result::Result<Option<Self::User>, Self::Error> {
-        let mut conn = self.context.database().get().await?;
-        let user = User::load(*user_id, &mut conn)
-            .await?
-            .with_roles_and_permissions(&mut conn)
-            .await?
-            .to_owned();
+        let load = async {
+            let mut conn = metrics::checkout(self.context.database()).await?;
+            let user = User::load(*user_id, &mut conn)
+                .await?
+                .with_roles_and_permissions(&mut conn)
+                .await?
+                .to_owned();
+
+            Ok::<_, Self::Error>(WithRolesAndPermissions(user))
+        };
+
+        // Shared with the app user extractors when the app's user is lowboy's.
+        let WithRolesAndPermissions(user) = match ModelCache::current() {
+            Some(cache) => cache.get_or_load(*user_id, load).await?,
+            None => load.await?,
+        };
 
         Ok(Some(user))
     }
@@ -494,3 +709,220 @@ impl AuthzBackend for LowboyAuth {
         Ok(user.permissions().cloned().unwrap_or_default())
     }
 }
+
+/// Marks requests as coming from an API client, so authentication failures respond with a status
+/// code and JSON body instead of redirecting to the HTML login page.
+///
+/// Add it to a router with `.layer(Extension(ApiRequest))` to mark every route in it, `Accept:
+/// application/json` and `X-Requested-With: XMLHttpRequest` requests are detected automatically.
+#[derive(Clone, Copy, Debug)]
+pub struct ApiRequest;
+
+/// Whether the request expects a status code rather than a redirect to an HTML page.
+pub fn is_api_request(request: &Request) -> bool {
+    is_api(request.headers(), request.extensions())
+}
+
+/// [`is_api_request`], for extractors which only have the request's parts.
+pub(crate) fn is_api(headers: &HeaderMap, extensions: &Extensions) -> bool {
+    let accepts_json = headers
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json") && !accept.contains("text/html"));
+    let is_xhr = headers
+        .get("x-requested-with")
+        .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"xmlhttprequest"));
+
+    accepts_json || is_xhr || extensions.get::<ApiRequest>().is_some()
+}
+
+/// Authenticate service accounts sending an `Authorization: Bearer <token>` header.
+///
+/// The user is only attached to the request's [`AuthSession`], no session is created for it, and
+/// the request is treated as an [API request](is_api_request). Invalid tokens are rejected with
+/// `401 Unauthorized` rather than falling back to anonymous access.
+pub(crate) async fn authenticate_api_token(mut request: Request, next: Next) -> Response {
+    let Some(token) = request
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+    else {
+        return next.run(request).await;
+    };
+
+    let Some(mut auth_session) = request.extensions().get::<AuthSession>().cloned() else {
+        return next.run(request).await;
+    };
+
+    let credentials = Credentials {
+        kind: CredentialKind::ApiToken,
+        password: None,
+        oauth: None,
+        api_token: Some(ApiTokenCredentials { token }),
+    };
+
+    match auth_session.authenticate(credentials).await {
+        Ok(Some(user)) => {
+            auth_session.user = Some(user);
+            request.extensions_mut().insert(auth_session);
+            request.extensions_mut().insert(ApiRequest);
+
+            next.run(request).await
+        }
+        Ok(None) => api_error(StatusCode::UNAUTHORIZED),
+        Err(e) => {
+            tracing::error!("error authenticating api token: {e}");
+            api_error(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// A problem details response to an API request, see [`crate::error::problem_details`].
+pub(crate) fn api_error(status: StatusCode) -> Response {
+    problem_response(status, problem(status))
+}
+
+/// Require an authenticated user, see [`crate::login_required!`].
+#[doc(hidden)]
+pub async fn require_login(
+    auth_session: AuthSession,
+    login_url: &str,
+    request: Request,
+    next: Next,
+) -> Response {
+    if auth_session.user.is_some() {
+        return next.run(request).await;
+    }
+
+    if is_api_request(&request) {
+        return api_error(StatusCode::UNAUTHORIZED);
+    }
+
+    let next_url = request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+    let next_url: String = form_urlencoded::byte_serialize(next_url.as_bytes()).collect();
+
+    Redirect::temporary(&format!("{login_url}?next={next_url}")).into_response()
+}
+
+/// Require an authenticated user with all of `permissions`, see [`crate::permission_required!`].
+#[doc(hidden)]
+pub async fn require_permissions(
+    auth_session: AuthSession,
+    login_url: &str,
+    permissions: &[&str],
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(ref user) = auth_session.user else {
+        return require_login(auth_session, login_url, request, next).await;
+    };
+
+    if permissions
+        .iter()
+        .all(|permission| user.has_permission(permission))
+    {
+        return next.run(request).await;
+    }
+
+    if is_api_request(&request) {
+        api_error(StatusCode::FORBIDDEN)
+    } else {
+        crate::error::LowboyError::Forbidden.into_response()
+    }
+}
+
+/// Allow `user` to act on `resource` if they own it, or have any of the `bypass` permissions
+/// (e.g. moderators who can edit anyone's posts), responding with `403 Forbidden` otherwise.
+///
+/// Prefer the [`authorize_owner!`](crate::authorize_owner) macro in handlers.
+pub fn authorize_owner<U: UserModel>(
+    user: &U,
+    resource: &impl Owned,
+    bypass: &[&str],
+) -> Result<(), crate::error::LowboyError> {
+    if resource.owner_id() == user.id()
+        || bypass.iter().any(|permission| user.has_permission(permission))
+    {
+        return Ok(());
+    }
+
+    Err(crate::error::LowboyError::Forbidden)
+}
+
+/// Redirect unauthenticated users to the login page, or respond with `401 Unauthorized` for API
+/// requests (see [`is_api_request`]).
+///
+/// ```ignore
+/// Router::new()
+///     .route("/", get(home))
+///     .route_layer(lowboy::login_required!())
+/// ```
+#[macro_export]
+macro_rules! login_required {
+    () => {
+        $crate::login_required!(login_url = "/login")
+    };
+    (login_url = $login_url:expr) => {
+        ::axum::middleware::from_fn(
+            |auth_session: $crate::AuthSession,
+             request: ::axum::extract::Request,
+             next: ::axum::middleware::Next| async move {
+                $crate::auth::require_login(auth_session, $login_url, request, next).await
+            },
+        )
+    };
+}
+
+/// Like [`login_required!`], but also requires the user to have all of the given permissions,
+/// responding with `403 Forbidden` otherwise.
+///
+/// ```ignore
+/// Router::new()
+///     .route("/admin", get(admin))
+///     .route_layer(lowboy::permission_required!("administer site"))
+/// ```
+#[macro_export]
+macro_rules! permission_required {
+    (login_url = $login_url:expr, $($permission:expr),+ $(,)?) => {
+        ::axum::middleware::from_fn(
+            |auth_session: $crate::AuthSession,
+             request: ::axum::extract::Request,
+             next: ::axum::middleware::Next| async move {
+                $crate::auth::require_permissions(
+                    auth_session,
+                    $login_url,
+                    &[$($permission),+],
+                    request,
+                    next,
+                )
+                .await
+            },
+        )
+    };
+    ($($permission:expr),+ $(,)?) => {
+        $crate::permission_required!(login_url = "/login", $($permission),+)
+    };
+}
+
+/// Ensure a user owns a resource, or has one of the given bypass permissions, before acting on
+/// it. Evaluates to a `Result<(), LowboyError>`, which is `Forbidden` for anyone else.
+///
+/// ```ignore
+/// lowboy::authorize_owner!(user, &post)?;
+/// lowboy::authorize_owner!(user, &post, bypass = "edit any post", "administer site")?;
+/// ```
+#[macro_export]
+macro_rules! authorize_owner {
+    ($user:expr, $resource:expr, bypass = $($permission:expr),+ $(,)?) => {
+        $crate::auth::authorize_owner(&$user, $resource, &[$($permission),+])
+    };
+    ($user:expr, $resource:expr $(,)?) => {
+        $crate::auth::authorize_owner(&$user, $resource, &[])
+    };
+}
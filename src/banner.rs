@@ -0,0 +1,282 @@
+//! The summary logged when the app starts serving, so misconfiguration is obvious at a glance.
+//!
+//! It covers the build, where the app is listening and serves static files from, the database and
+//! its migrations, what's enabled, and the resolved config, with its keys, passwords and secrets
+//! redacted. Disable it with `startup_banner: false`.
+
+use std::fmt;
+
+use diesel::migration::{MigrationVersion, Result as MigrationResult};
+use diesel::sqlite::Sqlite;
+use diesel_migrations::MigrationHarness;
+use serde_json::Value;
+use tracing::info;
+
+use crate::context::CloneableAppContext;
+use crate::controller::BuildInfo;
+use crate::serve::ServeOptions;
+use crate::{app, model, settings, Config, MIGRATIONS};
+
+const REDACTED: &str = "[redacted]";
+
+/// How many of lowboy's migrations have run.
+#[derive(Clone, Debug)]
+pub struct MigrationStatus {
+    pub applied: usize,
+    pub pending: usize,
+    /// The version of the latest migration that has run.
+    pub latest: Option<String>,
+}
+
+impl MigrationStatus {
+    pub fn of(conn: &mut impl MigrationHarness<Sqlite>) -> MigrationResult<Self> {
+        let applied = conn.applied_migrations()?;
+        let pending = conn.pending_migrations(MIGRATIONS)?;
+
+        Ok(Self {
+            applied: applied.len(),
+            pending: pending.len(),
+            latest: applied.iter().max().map(MigrationVersion::to_string),
+        })
+    }
+}
+
+/// What's logged at startup, see the [module docs](self).
+pub struct StartupSummary {
+    build: BuildInfo,
+    listening: String,
+    grpc: Option<String>,
+    static_dir: String,
+    database: String,
+    attached: Vec<String>,
+    migrations: MigrationStatus,
+    providers: Vec<String>,
+    mailer: String,
+    captcha: String,
+    features: Vec<&'static str>,
+    disabled_layers: Vec<&'static str>,
+    permissions: usize,
+    settings: usize,
+    config: Vec<(String, String)>,
+}
+
+impl StartupSummary {
+    pub fn new<App: app::App<AC>, AC: CloneableAppContext>(
+        config: &Config,
+        options: &ServeOptions,
+        grpc: bool,
+        migrations: MigrationStatus,
+    ) -> Self {
+        let scheme = if config.tls.is_some() { "https" } else { "http" };
+        let listening = format!("{scheme}://{}:{}", config.listen_addr, config.listen_port);
+        let grpc = grpc.then(|| match config.grpc_port {
+            Some(port) => format!("{}:{port}", config.listen_addr),
+            None => "sharing the web app's port".to_string(),
+        });
+
+        let static_dir = match config.static_dir() {
+            Ok(dir) => dir.display().to_string(),
+            Err(_) => "not found".to_string(),
+        };
+
+        let database = match database_size(&config.database_url) {
+            Some(size) => format!(
+                "{} ({}, {} journal)",
+                config.database_url,
+                human_size(size),
+                config.database_journal_mode
+            ),
+            None => format!("{} (size unknown)", config.database_url),
+        };
+
+        let mailer = match config.mailer {
+            Some(ref mailer) => mailer.transport.to_string(),
+            None => "disabled".to_string(),
+        };
+        let captcha = match config.captcha {
+            Some(ref captcha) => format!("{:?}", captcha.provider).to_lowercase(),
+            None => "disabled".to_string(),
+        };
+
+        let features = [
+            (config.metrics, "metrics"),
+            (config.version_endpoint, "version endpoint"),
+            (config.version_header, "version header"),
+            (config.minify_html, "html minification"),
+            (config.database_warm_up, "database warm up"),
+            (config.telemetry.is_some(), "telemetry"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, feature)| enabled.then_some(feature))
+        .collect();
+
+        let disabled_layers = [
+            (options.messages, "messages"),
+            (options.error_page, "error page"),
+            (options.catch_panic, "catch panic"),
+            (options.api_tokens, "api tokens"),
+            (options.locale_detection, "locale detection"),
+            (options.session_tracking, "session tracking"),
+            (options.livereload, "livereload"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, layer)| (!enabled).then_some(layer))
+        .collect();
+
+        Self {
+            build: App::build_info(),
+            listening,
+            grpc,
+            static_dir,
+            database,
+            attached: config
+                .database_attach
+                .iter()
+                .map(|database| database.name.clone())
+                .collect(),
+            migrations,
+            providers: config
+                .oauth_providers
+                .iter()
+                .map(|provider| provider.kind.to_string())
+                .collect(),
+            mailer,
+            captcha,
+            features,
+            disabled_layers,
+            permissions: model::LOWBOY_PERMISSIONS.len() + App::permissions().len(),
+            settings: settings::SettingDefinitions::global().iter().count(),
+            config: redacted_config(config),
+        }
+    }
+
+    /// Log the summary, a line at a time.
+    pub fn log(&self) {
+        for line in self.to_string().lines() {
+            info!("{line}");
+        }
+    }
+}
+
+impl fmt::Display for StartupSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list<T: AsRef<str>>(items: &[T], empty: &str) -> String {
+            if items.is_empty() {
+                return empty.to_string();
+            }
+
+            items
+                .iter()
+                .map(AsRef::as_ref)
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+
+        writeln!(
+            f,
+            "starting {} ({}, built {} with rustc {})",
+            self.build.app, self.build.git_sha, self.build.build_timestamp, self.build.rustc_version
+        )?;
+        writeln!(f, "  listening:   {}", self.listening)?;
+        if let Some(ref grpc) = self.grpc {
+            writeln!(f, "  grpc:        {grpc}")?;
+        }
+        writeln!(f, "  static:      {}", self.static_dir)?;
+        writeln!(f, "  database:    {}", self.database)?;
+        if !self.attached.is_empty() {
+            writeln!(f, "  attached:    {}", list(&self.attached, ""))?;
+        }
+        writeln!(
+            f,
+            "  migrations:  {} applied, {} pending, latest {}",
+            self.migrations.applied,
+            self.migrations.pending,
+            self.migrations.latest.as_deref().unwrap_or("none")
+        )?;
+        writeln!(f, "  providers:   {}", list(&self.providers, "none"))?;
+        writeln!(f, "  mailer:      {}", self.mailer)?;
+        writeln!(f, "  captcha:     {}", self.captcha)?;
+        writeln!(f, "  features:    {}", list(&self.features, "none"))?;
+        writeln!(f, "  disabled:    {}", list(&self.disabled_layers, "none"))?;
+        writeln!(
+            f,
+            "  registered:  {} permissions, {} settings",
+            self.permissions, self.settings
+        )?;
+        writeln!(f, "  config:")?;
+        for (key, value) in &self.config {
+            writeln!(f, "    {key} = {value}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The size of the database file and its write-ahead log, if it's a file.
+fn database_size(url: &str) -> Option<u64> {
+    let path = url
+        .strip_prefix("sqlite://")
+        .or_else(|| url.strip_prefix("file:"))
+        .unwrap_or(url);
+    let path = path.split('?').next()?;
+
+    let size = std::fs::metadata(path).ok()?.len();
+    let wal_size = std::fs::metadata(format!("{path}-wal")).map_or(0, |wal| wal.len());
+
+    Some(size + wal_size)
+}
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+
+    format!("{size:.1} {unit}")
+}
+
+/// Whether the config field `key` holds a secret.
+fn is_secret(key: &str) -> bool {
+    key.contains("secret") || key.contains("password") || matches!(key, "session_key" | "api_key")
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields.iter_mut() {
+                if is_secret(key) && !value.is_null() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => (),
+    }
+}
+
+/// Each of the config's fields, as JSON, with secrets redacted.
+fn redacted_config(config: &Config) -> Vec<(String, String)> {
+    let mut config = serde_json::to_value(config).unwrap_or_default();
+    redact(&mut config);
+
+    match config {
+        Value::Object(fields) => fields
+            .into_iter()
+            .map(|(key, value)| (key, value.to_string()))
+            .collect(),
+        _ => vec![],
+    }
+}
@@ -0,0 +1,288 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context as _};
+use reqwest::header::{COOKIE, LOCATION, SET_COOKIE};
+use reqwest::redirect::Policy;
+use reqwest::{Client, Response};
+use tokio::process::{Child, Command};
+use uuid::Uuid;
+
+const PASSWORD: &str = "lowboy-bench-password";
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Base url of the app to benchmark.
+    #[arg(long, default_value = "http://127.0.0.1:3000")]
+    url: String,
+
+    /// Path to request.
+    #[arg(long, default_value = "/")]
+    path: String,
+
+    /// Total number of requests to make.
+    #[arg(short = 'n', long, default_value_t = 10_000)]
+    requests: usize,
+
+    /// Number of requests to make concurrently.
+    #[arg(short, long, default_value_t = 64)]
+    concurrency: usize,
+
+    /// Number of users to register before benchmarking. Requests are made as the first one.
+    #[arg(long, default_value_t = 10)]
+    users: usize,
+
+    /// Additional registration form fields required by the app, e.g. `--field name=Bench`.
+    #[arg(long = "field", value_parser = parse_field)]
+    fields: Vec<(String, String)>,
+
+    /// Request the path anonymously instead of as a seeded user.
+    #[arg(long)]
+    anonymous: bool,
+
+    /// Boot the demo app with `cargo run --release -p demo` before benchmarking.
+    #[arg(long)]
+    boot_demo: bool,
+
+    /// Generate load with `oha` instead of the built-in load generator.
+    #[arg(long)]
+    oha: bool,
+}
+
+fn parse_field(field: &str) -> anyhow::Result<(String, String)> {
+    let (name, value) = field
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected a field in the form name=value"))?;
+
+    Ok((name.to_string(), value.to_string()))
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    let client = Client::builder().redirect(Policy::none()).build()?;
+
+    // Keep the demo alive until the benchmark is done, it's killed when dropped.
+    let _demo = if args.boot_demo {
+        Some(boot_demo(&client, &args.url).await?)
+    } else {
+        None
+    };
+
+    let usernames = seed_users(&client, &args).await?;
+    let cookie = match usernames.first() {
+        Some(username) if !args.anonymous => Some(login(&client, &args.url, username).await?),
+        _ => None,
+    };
+
+    let url = format!("{}{}", args.url, args.path);
+    if args.oha {
+        let mut oha = Command::new("oha");
+        oha.arg("--no-tui")
+            .args(["-n", &args.requests.to_string()])
+            .args(["-c", &args.concurrency.to_string()]);
+        if let Some(cookie) = cookie {
+            oha.args(["-H", &format!("Cookie: {cookie}")]);
+        }
+
+        let status = oha
+            .arg(url)
+            .status()
+            .await
+            .context("failed to run oha, is it installed?")?;
+        if !status.success() {
+            bail!("oha exited with {status}");
+        }
+
+        return Ok(());
+    }
+
+    let report = drive_load(&client, url, cookie, args.requests, args.concurrency).await?;
+    report.print();
+
+    Ok(())
+}
+
+async fn boot_demo(client: &Client, url: &str) -> anyhow::Result<Child> {
+    println!("booting demo...");
+
+    let mut demo = Command::new("cargo")
+        .args(["run", "--release", "-p", "demo"])
+        .kill_on_drop(true)
+        .spawn()
+        .context("failed to boot the demo")?;
+
+    // Give the demo plenty of time to compile before giving up.
+    let deadline = Instant::now() + Duration::from_secs(600);
+    while Instant::now() < deadline {
+        if let Some(status) = demo.try_wait()? {
+            bail!("demo exited early with {status}");
+        }
+
+        if client.get(format!("{url}/login")).send().await.is_ok() {
+            return Ok(demo);
+        }
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    bail!("timed out waiting for the demo to start")
+}
+
+/// Register `args.users` users through the app's registration form, so any app specific
+/// registration logic runs as well.
+///
+/// The form is submitted without being shown first, so the app's `bot_min_fill_time` has to be 0.
+async fn seed_users(client: &Client, args: &Args) -> anyhow::Result<Vec<String>> {
+    let prefix = &Uuid::new_v4().simple().to_string()[..8];
+    let mut usernames = Vec::with_capacity(args.users);
+
+    for n in 0..args.users {
+        let username = format!("bench-{prefix}-{n}");
+        let email = format!("{username}@example.com");
+        let mut form = vec![
+            ("username".to_string(), username.clone()),
+            ("email".to_string(), email),
+            ("password".to_string(), PASSWORD.to_string()),
+        ];
+        form.extend(args.fields.iter().cloned());
+
+        let response = client
+            .post(format!("{}/register", args.url))
+            .form(&form)
+            .send()
+            .await?;
+        if !redirected_to(&response, "/login") {
+            bail!("failed to register {username}: {}", response.status());
+        }
+
+        usernames.push(username);
+    }
+
+    println!("seeded {} users", usernames.len());
+
+    Ok(usernames)
+}
+
+/// Log in as `username`, returning the session cookie.
+async fn login(client: &Client, url: &str, username: &str) -> anyhow::Result<String> {
+    let response = client
+        .post(format!("{url}/login"))
+        .form(&[("username", username), ("password", PASSWORD)])
+        .send()
+        .await?;
+
+    if !response.status().is_redirection() || redirected_to(&response, "/login") {
+        bail!("failed to log in as {username}: {}", response.status());
+    }
+
+    let cookie = response
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|cookie| cookie.to_str().ok())
+        .filter_map(|cookie| cookie.split(';').next())
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    Ok(cookie)
+}
+
+fn redirected_to(response: &Response, path: &str) -> bool {
+    response.status().is_redirection()
+        && response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .is_some_and(|location| location.starts_with(path))
+}
+
+struct Report {
+    elapsed: Duration,
+    latencies: Vec<Duration>,
+    errors: usize,
+}
+
+impl Report {
+    fn percentile(&self, percentile: f64) -> Duration {
+        let rank = (self.latencies.len() as f64 * percentile).ceil() as usize;
+        self.latencies[rank.saturating_sub(1).min(self.latencies.len() - 1)]
+    }
+
+    fn print(&self) {
+        let requests = self.latencies.len() + self.errors;
+
+        println!("requests:  {requests}");
+        println!("errors:    {}", self.errors);
+        println!("elapsed:   {:.2?}", self.elapsed);
+        println!(
+            "req/s:     {:.0}",
+            requests as f64 / self.elapsed.as_secs_f64()
+        );
+
+        if self.latencies.is_empty() {
+            return;
+        }
+
+        println!("p50:       {:.2?}", self.percentile(0.50));
+        println!("p99:       {:.2?}", self.percentile(0.99));
+        println!("max:       {:.2?}", self.percentile(1.0));
+    }
+}
+
+async fn drive_load(
+    client: &Client,
+    url: String,
+    cookie: Option<String>,
+    requests: usize,
+    concurrency: usize,
+) -> anyhow::Result<Report> {
+    let next = Arc::new(AtomicUsize::new(0));
+    let started = Instant::now();
+
+    let workers = (0..concurrency.max(1))
+        .map(|_| {
+            let client = client.clone();
+            let url = url.clone();
+            let cookie = cookie.clone();
+            let next = next.clone();
+
+            tokio::spawn(async move {
+                let mut latencies = Vec::new();
+                let mut errors = 0;
+
+                while next.fetch_add(1, Ordering::Relaxed) < requests {
+                    let mut request = client.get(&url);
+                    if let Some(ref cookie) = cookie {
+                        request = request.header(COOKIE, cookie);
+                    }
+
+                    let start = Instant::now();
+                    match request.send().await {
+                        Ok(response) if response.status().is_success() => {
+                            let _ = response.bytes().await;
+                            latencies.push(start.elapsed());
+                        }
+                        _ => errors += 1,
+                    }
+                }
+
+                (latencies, errors)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut report = Report {
+        elapsed: Duration::ZERO,
+        latencies: Vec::with_capacity(requests),
+        errors: 0,
+    };
+    for worker in workers {
+        let (latencies, errors) = worker.await?;
+        report.latencies.extend(latencies);
+        report.errors += errors;
+    }
+    report.elapsed = started.elapsed();
+    report.latencies.sort();
+
+    Ok(report)
+}
@@ -0,0 +1,61 @@
+use anyhow::Context as _;
+use chrono::Utc;
+use diesel::sqlite::SqliteConnection;
+use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
+use diesel_async::AsyncConnection;
+use lowboy::jobs::{ScheduledJob, BUILT_IN};
+use lowboy::model::QueuedJob;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+
+    /// The app's database url, i.e. its `database_url` config.
+    #[arg(long)]
+    database: String,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// List lowboy's scheduled jobs with their next runs, and the queued jobs. The app's digests
+    /// are listed in the admin at `/admin/jobs`.
+    List {
+        /// How many upcoming runs of each scheduled job to list.
+        #[arg(long, default_value_t = 3)]
+        runs: usize,
+    },
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    let mut conn = SyncConnectionWrapper::<SqliteConnection>::establish(&args.database)
+        .await
+        .with_context(|| format!("couldn't open the database at {}", args.database))?;
+
+    match args.command {
+        Command::List { runs } => {
+            let now = Utc::now();
+
+            println!("Scheduled:");
+            for job in BUILT_IN {
+                let job = ScheduledJob::new(job.name, job.schedule)?;
+                let next_runs = job
+                    .next_runs(runs, now)
+                    .iter()
+                    .map(|run| run.to_rfc3339())
+                    .collect::<Vec<_>>();
+                println!("  {} ({}): {}", job.name, job.schedule, next_runs.join(", "));
+            }
+
+            println!("Queued:");
+            for job in QueuedJob::list(&mut conn, None).await? {
+                match job.unique_key {
+                    Some(key) => println!("  {} [{key}]: {}", job.job, job.run_at.to_rfc3339()),
+                    None => println!("  {}: {}", job.job, job.run_at.to_rfc3339()),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
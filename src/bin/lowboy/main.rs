@@ -0,0 +1,39 @@
+use clap::{Parser, Subcommand};
+
+mod bench;
+mod jobs;
+mod new;
+mod service_account;
+mod user;
+
+#[derive(Debug, Parser)]
+#[command(name = "lowboy", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Drive load against a running app and report throughput and latency.
+    Bench(bench::Args),
+    /// Inspect the scheduled and queued background jobs.
+    Jobs(jobs::Args),
+    /// Create a new app, scaffolded from the demo.
+    New(new::Args),
+    /// Manage service accounts, which authenticate with an API token instead of a password.
+    ServiceAccount(service_account::Args),
+    /// Manage users.
+    User(user::Args),
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    match Cli::parse().command {
+        Command::Bench(args) => bench::run(args).await,
+        Command::Jobs(args) => jobs::run(args).await,
+        Command::New(args) => new::run(args),
+        Command::ServiceAccount(args) => service_account::run(args).await,
+        Command::User(args) => user::run(args).await,
+    }
+}
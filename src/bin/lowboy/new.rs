@@ -0,0 +1,239 @@
+//! `lowboy new`, which scaffolds a new app from the demo.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _};
+
+/// Where the scaffolded app depends on lowboy from, in place of the demo's path dependencies.
+const LOWBOY_GIT: &str = "https://github.com/marcaddeo/lowboy";
+
+/// The lowboy commit this binary was built from, which the scaffolded app is pinned to so it
+/// matches the demo it was made from.
+const LOWBOY_REV: &str = env!("VERGEN_GIT_SHA");
+
+macro_rules! demo_file {
+    ($path:literal) => {
+        ($path, include_str!(concat!("../../../examples/demo/", $path)))
+    };
+}
+
+/// The demo's files, by their path in the app.
+const FILES: &[(&str, &str)] = &[
+    demo_file!(".editorconfig"),
+    demo_file!(".env.template"),
+    demo_file!(".gitattributes"),
+    demo_file!(".gitignore"),
+    demo_file!("Cargo.toml"),
+    demo_file!("css/main.css"),
+    demo_file!("diesel.toml"),
+    demo_file!("esbuild.mjs"),
+    demo_file!("justfile"),
+    demo_file!("migrations/2024-11-17-010618_create_user_profile_table/down.sql"),
+    demo_file!("migrations/2024-11-17-010618_create_user_profile_table/up.sql"),
+    demo_file!("migrations/2024-11-17-010622_create_post_table/down.sql"),
+    demo_file!("migrations/2024-11-17-010622_create_post_table/up.sql"),
+    demo_file!("package.json"),
+    demo_file!("src/app.rs"),
+    demo_file!("src/controller/home.rs"),
+    demo_file!("src/controller/mod.rs"),
+    demo_file!("src/controller/post.rs"),
+    demo_file!("src/form/mod.rs"),
+    demo_file!("src/form/register.rs"),
+    demo_file!("src/main.rs"),
+    demo_file!("src/model/mod.rs"),
+    demo_file!("src/model/post.rs"),
+    demo_file!("src/model/user.rs"),
+    demo_file!("src/model/user_profile.rs"),
+    demo_file!("src/schema.rs"),
+    demo_file!("src/view/auth.rs"),
+    demo_file!("src/view/error.rs"),
+    demo_file!("src/view/home.rs"),
+    demo_file!("src/view/layout.rs"),
+    demo_file!("src/view/mod.rs"),
+    demo_file!("src/view/post.rs"),
+    demo_file!("src/view/post_form.rs"),
+    demo_file!("static/.gitkeep"),
+    demo_file!("tailwind.config.ts"),
+    demo_file!("templates/components/alert-error.html"),
+    demo_file!("templates/components/alert-info.html"),
+    demo_file!("templates/components/alert-success.html"),
+    demo_file!("templates/components/alert-warning.html"),
+    demo_file!("templates/components/alerts.html"),
+    demo_file!("templates/components/footer.html"),
+    demo_file!("templates/components/header.html"),
+    demo_file!("templates/components/post-form.html"),
+    demo_file!("templates/components/post-list.html"),
+    demo_file!("templates/components/post.html"),
+    demo_file!("templates/layout.html"),
+    demo_file!("templates/pages/auth/login.html"),
+    demo_file!("templates/pages/auth/register.html"),
+    demo_file!("templates/pages/auth/verify-email.html"),
+    demo_file!("templates/pages/error/index.html"),
+    demo_file!("templates/pages/home.html"),
+    demo_file!("ts/htmx.ts"),
+    demo_file!("ts/main.ts"),
+    demo_file!("ts/types/global.d.ts"),
+    demo_file!("tsconfig.json"),
+];
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// The app's crate name, e.g. `my-blog`.
+    name: String,
+
+    /// The directory to create the app in. Defaults to the app's name.
+    #[arg(long)]
+    path: Option<PathBuf>,
+}
+
+/// The ways the app's name is written in its code.
+struct Names {
+    /// e.g. `my-blog`, the crate name.
+    krate: String,
+    /// e.g. `my_blog`, the name the app reports itself as.
+    snake: String,
+    /// e.g. `MyBlog`, for the app's types.
+    pascal: String,
+    /// e.g. `My Blog`, the app's title.
+    title: String,
+}
+
+impl Names {
+    fn new(name: &str) -> anyhow::Result<Self> {
+        let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !valid {
+            bail!(
+                "`{name}` isn't a valid app name, use lowercase letters, digits, `-` and `_`, \
+                starting with a letter"
+            );
+        }
+
+        let words: Vec<&str> = name.split(['-', '_']).filter(|w| !w.is_empty()).collect();
+        let capitalized: Vec<String> = words
+            .iter()
+            .map(|word| {
+                let (first, rest) = word.split_at(1);
+                format!("{}{rest}", first.to_ascii_uppercase())
+            })
+            .collect();
+
+        Ok(Self {
+            krate: name.to_string(),
+            snake: words.join("_"),
+            pascal: capitalized.concat(),
+            title: capitalized.join(" "),
+        })
+    }
+
+    /// The demo's `contents`, renamed for the app.
+    fn substitute(&self, path: &str, contents: &str) -> String {
+        let contents = match path {
+            "Cargo.toml" => contents
+                .replace(r#"name = "demo""#, &format!(r#"name = "{}""#, self.krate))
+                .replace(
+                    r#"lowboy = { path = "../../" }"#,
+                    &format!(r#"lowboy = {{ git = "{LOWBOY_GIT}", rev = "{LOWBOY_REV}" }}"#),
+                )
+                .replace(
+                    r#"lowboy_record = { path = "../../lib/lowboy_record" }"#,
+                    &format!(
+                        r#"lowboy_record = {{ git = "{LOWBOY_GIT}", rev = "{LOWBOY_REV}" }}"#
+                    ),
+                ),
+            _ => contents.to_string(),
+        };
+
+        contents
+            .replace("Demo App", &self.title)
+            .replace("Demo", &self.pascal)
+            .replace(r#""demo""#, &format!(r#""{}""#, self.snake))
+    }
+}
+
+pub fn run(args: Args) -> anyhow::Result<()> {
+    let names = Names::new(&args.name)?;
+    let root = args.path.unwrap_or_else(|| PathBuf::from(&args.name));
+
+    if root.exists() && root.read_dir()?.next().is_some() {
+        bail!("{} already exists and isn't empty", root.display());
+    }
+
+    for (path, contents) in FILES {
+        write(&root.join(path), &names.substitute(path, contents))?;
+    }
+    write(&root.join("config.yml"), &lowboy::get_config_template())?;
+
+    println!("Created {} in {}", names.title, root.display());
+    println!();
+    println!("Fill in config.yml, then copy it to where lowboy reads its config from:");
+    println!("  {}", lowboy::get_config_path(None)?.display());
+    println!();
+    println!("And start the app with:");
+    println!("  cd {}", root.display());
+    println!("  just build && cargo run");
+
+    Ok(())
+}
+
+fn write(path: &Path, contents: &str) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("couldn't create {}", parent.display()))?;
+    }
+
+    fs::write(path, contents).with_context(|| format!("couldn't write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_derived_from_the_crate_name() {
+        let names = Names::new("my-blog_2").unwrap();
+
+        assert_eq!(names.krate, "my-blog_2");
+        assert_eq!(names.snake, "my_blog_2");
+        assert_eq!(names.pascal, "MyBlog2");
+        assert_eq!(names.title, "My Blog 2");
+    }
+
+    #[test]
+    fn invalid_names_are_refused() {
+        for name in ["", "MyBlog", "2blog", "-blog", "my blog", "my.blog"] {
+            assert!(Names::new(name).is_err(), "`{name}` should be refused");
+        }
+    }
+
+    #[test]
+    fn substitute_renames_the_demo() {
+        let names = Names::new("my-blog").unwrap();
+
+        let app = names.substitute(
+            "src/app.rs",
+            r#"pub struct Demo; // "demo", the Demo App"#,
+        );
+        assert_eq!(app, r#"pub struct MyBlog; // "my_blog", the My Blog"#);
+    }
+
+    #[test]
+    fn substitute_pins_lowboy_to_the_commit_it_was_built_from() {
+        let names = Names::new("my-blog").unwrap();
+        let cargo_toml = concat!(
+            "name = \"demo\"\n",
+            "lowboy = { path = \"../../\" }\n",
+            "lowboy_record = { path = \"../../lib/lowboy_record\" }\n",
+        );
+
+        let cargo_toml = names.substitute("Cargo.toml", cargo_toml);
+        assert!(cargo_toml.contains(r#"name = "my-blog""#));
+        let rev = format!(r#"{{ git = "{LOWBOY_GIT}", rev = "{LOWBOY_REV}" }}"#);
+        assert!(cargo_toml.contains(&format!("lowboy = {rev}")));
+        assert!(cargo_toml.contains(&format!("lowboy_record = {rev}")));
+        assert!(!cargo_toml.contains("path ="));
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::Context as _;
+use diesel::sqlite::SqliteConnection;
+use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
+use diesel_async::AsyncConnection;
+use lowboy::model::{Model as _, User, UserModel as _};
+use lowboy::secret::UuidSecretGenerator;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+
+    /// The app's database url, i.e. its `database_url` config.
+    #[arg(long)]
+    database: String,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Create a service account and print its API token.
+    Create { username: String },
+    /// Replace a service account's API token, revoking the old one, and print the new one.
+    Rotate { username: String },
+    /// List the service accounts.
+    List,
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    let mut conn = SyncConnectionWrapper::<SqliteConnection>::establish(&args.database)
+        .await
+        .with_context(|| format!("couldn't open the database at {}", args.database))?;
+
+    match args.command {
+        Command::Create { username } => {
+            if User::find_by_username(&username, &mut conn).await?.is_some() {
+                anyhow::bail!("the username `{username}` is taken");
+            }
+
+            let (_, token) =
+                User::new_service_account(&username, &UuidSecretGenerator, &mut conn).await?;
+            println!("{token}");
+        }
+        Command::Rotate { username } => {
+            let mut user = User::find_by_username(&username, &mut conn)
+                .await?
+                .filter(|user| user.service_account)
+                .with_context(|| format!("there's no service account named `{username}`"))?;
+
+            let token = user
+                .rotate_api_token(&UuidSecretGenerator, &mut conn)
+                .await?;
+            println!("{token}");
+        }
+        Command::List => {
+            for user in User::list_service_accounts(&mut conn).await? {
+                println!("{}", user.username);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use diesel::sqlite::SqliteConnection;
+use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
+use diesel_async::AsyncConnection;
+use lowboy::clock::SystemClock;
+use lowboy::password::PasswordHasher;
+use lowboy::provision::{provision_users, NewUser};
+use lowboy::secret::UuidSecretGenerator;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+
+    /// The app's database url, i.e. its `database_url` config.
+    #[arg(long)]
+    database: String,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Create the users in a file with one JSON object per line, e.g.
+    /// `{"username": "marc", "email": "marc@example.com", "verified": true, "roles": ["admin"]}`.
+    ///
+    /// `password`, `verified` and `roles` are optional. Unverified users aren't sent a
+    /// verification email.
+    Import { file: PathBuf },
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    let mut conn = SyncConnectionWrapper::<SqliteConnection>::establish(&args.database)
+        .await
+        .with_context(|| format!("couldn't open the database at {}", args.database))?;
+
+    match args.command {
+        Command::Import { file } => {
+            let contents = std::fs::read_to_string(&file)
+                .with_context(|| format!("couldn't read {}", file.display()))?;
+            let users = parse_users(&contents)?;
+
+            let results = provision_users(
+                users,
+                &SystemClock,
+                &UuidSecretGenerator,
+                PasswordHasher::global(),
+                &mut conn,
+            )
+            .await?;
+
+            let mut failed = 0;
+            for result in results {
+                match result {
+                    Ok(user) => println!("created {}", user.username),
+                    Err(e) => {
+                        failed += 1;
+                        eprintln!("{e}");
+                    }
+                }
+            }
+
+            if failed > 0 {
+                anyhow::bail!("{failed} user(s) couldn't be created");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse one user per (non-blank) line.
+fn parse_users(contents: &str) -> anyhow::Result<Vec<NewUser>> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("line {}: invalid user", index + 1))
+        })
+        .collect()
+}
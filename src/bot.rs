@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use tracing::warn;
+
+use crate::error::LowboyError;
+
+/// Bots fill in every field they find, so a form submitted with this (visually hidden) field
+/// filled in is rejected.
+pub const HONEYPOT_FIELD: &str = "website_url";
+
+/// More registrations than this from the same address within [`REGISTRATION_WINDOW`] are rejected.
+pub const REGISTRATION_LIMIT: u32 = 10;
+pub const REGISTRATION_WINDOW: TimeDelta = TimeDelta::hours(1);
+
+/// Forms protected by the [`BotGuard`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GuardedForm {
+    Register,
+    Login,
+}
+
+impl GuardedForm {
+    fn rendered_at_key(self) -> &'static str {
+        match self {
+            Self::Register => "bot.register-rendered-at",
+            Self::Login => "bot.login-rendered-at",
+        }
+    }
+}
+
+/// A submission of a [`GuardedForm`], to be checked by each [`BotCheck`].
+#[derive(Debug)]
+pub struct Submission<'a> {
+    pub form: GuardedForm,
+    /// Every submitted field, including ones the form type doesn't know about (e.g. the captcha
+    /// response).
+    pub fields: &'a HashMap<String, String>,
+    pub ip: Option<IpAddr>,
+    /// How long the form was shown before being submitted, if it was shown at all.
+    pub elapsed: Option<TimeDelta>,
+    pub now: DateTime<Utc>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BotRejection {
+    #[error("the honeypot field was filled in")]
+    Honeypot,
+
+    #[error("the form was submitted too quickly")]
+    TooFast,
+
+    #[error("too many registrations from {0}")]
+    RateLimited(IpAddr),
+
+    #[error("captcha verification failed: {0}")]
+    Captcha(String),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+/// A check a [`GuardedForm`] submission has to pass before it's handled.
+///
+/// Apps can add their own checks with [`crate::App::bot_checks`].
+#[async_trait::async_trait]
+pub trait BotCheck: Send + Sync {
+    async fn check(&self, submission: &Submission<'_>) -> Result<(), BotRejection>;
+
+    /// Markup to render inside the form, e.g. a captcha widget.
+    fn fields(&self) -> String {
+        String::new()
+    }
+}
+
+pub struct Honeypot;
+
+#[async_trait::async_trait]
+impl BotCheck for Honeypot {
+    async fn check(&self, submission: &Submission<'_>) -> Result<(), BotRejection> {
+        match submission.fields.get(HONEYPOT_FIELD) {
+            Some(value) if !value.is_empty() => Err(BotRejection::Honeypot),
+            _ => Ok(()),
+        }
+    }
+
+    fn fields(&self) -> String {
+        format!(
+            r#"<div aria-hidden="true" style="position: absolute; left: -10000px;"><input type="text" name="{HONEYPOT_FIELD}" tabindex="-1" autocomplete="off" value="" /></div>"#
+        )
+    }
+}
+
+/// Rejects forms submitted sooner than a person could fill them in, or without being shown first.
+pub struct TimeTrap {
+    pub min_fill_time: TimeDelta,
+}
+
+#[async_trait::async_trait]
+impl BotCheck for TimeTrap {
+    async fn check(&self, submission: &Submission<'_>) -> Result<(), BotRejection> {
+        match submission.elapsed {
+            Some(elapsed) if elapsed >= self.min_fill_time => Ok(()),
+            _ => Err(BotRejection::TooFast),
+        }
+    }
+}
+
+/// A soft limit on registrations per address, to slow down mass account creation.
+#[derive(Default)]
+pub struct RegistrationLimit(Mutex<HashMap<IpAddr, (u32, DateTime<Utc>)>>);
+
+#[async_trait::async_trait]
+impl BotCheck for RegistrationLimit {
+    async fn check(&self, submission: &Submission<'_>) -> Result<(), BotRejection> {
+        let (GuardedForm::Register, Some(ip)) = (submission.form, submission.ip) else {
+            return Ok(());
+        };
+
+        // Local development (and benchmarking) registers lots of users from the same machine.
+        if ip.is_loopback() {
+            return Ok(());
+        }
+
+        let mut attempts = self
+            .0
+            .lock()
+            .expect("registration limit lock should not be poisoned");
+        attempts.retain(|_, (_, started)| submission.now - *started < REGISTRATION_WINDOW);
+
+        let (count, _) = attempts.entry(ip).or_insert((0, submission.now));
+        *count += 1;
+
+        if *count > REGISTRATION_LIMIT {
+            return Err(BotRejection::RateLimited(ip));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptchaProvider {
+    HCaptcha,
+    Turnstile,
+}
+
+impl CaptchaProvider {
+    fn verify_url(self) -> &'static str {
+        match self {
+            Self::HCaptcha => "https://api.hcaptcha.com/siteverify",
+            Self::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+        }
+    }
+
+    fn response_field(self) -> &'static str {
+        match self {
+            Self::HCaptcha => "h-captcha-response",
+            Self::Turnstile => "cf-turnstile-response",
+        }
+    }
+
+    fn widget(self, site_key: &str) -> String {
+        match self {
+            Self::HCaptcha => format!(
+                r#"<script src="https://js.hcaptcha.com/1/api.js" async defer></script><div class="h-captcha" data-sitekey="{site_key}"></div>"#
+            ),
+            Self::Turnstile => format!(
+                r#"<script src="https://challenges.cloudflare.com/turnstile/v0/api.js" async defer></script><div class="cf-turnstile" data-sitekey="{site_key}"></div>"#
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CaptchaConfig {
+    pub provider: CaptchaProvider,
+    pub site_key: String,
+    pub secret_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CaptchaVerification {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+/// Verifies the response of a captcha widget with the provider.
+pub struct Captcha {
+    config: CaptchaConfig,
+    client: reqwest::Client,
+}
+
+impl Captcha {
+    pub fn new(config: CaptchaConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BotCheck for Captcha {
+    async fn check(&self, submission: &Submission<'_>) -> Result<(), BotRejection> {
+        let provider = self.config.provider;
+        let Some(response) = submission
+            .fields
+            .get(provider.response_field())
+            .filter(|response| !response.is_empty())
+        else {
+            return Err(BotRejection::Captcha("no captcha response".into()));
+        };
+
+        let mut params = vec![
+            ("secret", self.config.secret_key.clone()),
+            ("response", response.clone()),
+        ];
+        if let Some(ip) = submission.ip {
+            params.push(("remoteip", ip.to_string()));
+        }
+
+        let verification: CaptchaVerification = self
+            .client
+            .post(provider.verify_url())
+            .form(&params)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| BotRejection::Captcha(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| BotRejection::Captcha(e.to_string()))?;
+
+        if !verification.success {
+            return Err(BotRejection::Captcha(verification.error_codes.join(", ")));
+        }
+
+        Ok(())
+    }
+
+    fn fields(&self) -> String {
+        self.config.provider.widget(&self.config.site_key)
+    }
+}
+
+/// The markup the [`BotGuard`]'s checks need rendered inside a guarded form.
+#[derive(Clone, Debug, Default)]
+pub struct BotFields(String);
+
+impl fmt::Display for BotFields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Runs every [`BotCheck`] against registration and login submissions.
+pub struct BotGuard {
+    checks: Vec<Box<dyn BotCheck>>,
+}
+
+impl BotGuard {
+    /// A guard with the built-in checks: the honeypot, the registration limit, a time trap unless
+    /// `min_fill_time` is zero, and a captcha if one is configured.
+    pub fn new(min_fill_time: TimeDelta, captcha: Option<CaptchaConfig>) -> Self {
+        let mut checks: Vec<Box<dyn BotCheck>> =
+            vec![Box::new(Honeypot), Box::new(RegistrationLimit::default())];
+
+        if min_fill_time > TimeDelta::zero() {
+            checks.push(Box::new(TimeTrap { min_fill_time }));
+        }
+
+        if let Some(captcha) = captcha {
+            checks.push(Box::new(Captcha::new(captcha)));
+        }
+
+        Self { checks }
+    }
+
+    pub fn with_checks(mut self, checks: Vec<Box<dyn BotCheck>>) -> Self {
+        self.checks.extend(checks);
+        self
+    }
+
+    pub fn fields(&self) -> BotFields {
+        BotFields(self.checks.iter().map(|check| check.fields()).collect())
+    }
+
+    /// Remember when `form` was shown, for the time trap.
+    pub async fn start(
+        &self,
+        session: &Session,
+        form: GuardedForm,
+        now: DateTime<Utc>,
+    ) -> Result<(), LowboyError> {
+        session.insert(form.rendered_at_key(), now).await?;
+        Ok(())
+    }
+
+    /// Run every check against a submission of `form`, returning whether it passed.
+    pub async fn check(
+        &self,
+        session: &Session,
+        form: GuardedForm,
+        fields: &HashMap<String, String>,
+        ip: Option<IpAddr>,
+        now: DateTime<Utc>,
+    ) -> Result<bool, LowboyError> {
+        let rendered_at = session
+            .get::<DateTime<Utc>>(form.rendered_at_key())
+            .await?;
+        let submission = Submission {
+            form,
+            fields,
+            ip,
+            elapsed: rendered_at.map(|rendered_at| now - rendered_at),
+            now,
+        };
+
+        for check in &self.checks {
+            if let Err(rejection) = check.check(&submission).await {
+                warn!(?form, ?ip, "rejected a likely bot submission: {rejection}");
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,310 @@
+//! Bulk actions, which apply an action such as delete, publish or assign role to the records
+//! selected on a list page.
+//!
+//! The list page's form posts the chosen `action`, the selected `ids` (one field per checkbox) and
+//! the [`BulkFields`] token, which [`BulkRequest`] checks against the session so other sites can't
+//! submit it:
+//!
+//! ```html
+//! <form method="post" action="/posts/bulk" hx-post="/posts/bulk" hx-target="#lowboy-bulk-results">
+//!   {{ bulk_fields|safe }}
+//!   <select name="action">
+//!     {% for (name, label) in actions %}
+//!     <option value="{{ name }}">{{ label }}</option>
+//!     {% endfor %}
+//!   </select>
+//!   {% for post in posts %}<input type="checkbox" name="ids" value="{{ post.id }}">{% endfor %}
+//!   <button type="submit">Apply</button>
+//! </form>
+//! <div id="lowboy-bulk-results"></div>
+//! ```
+//!
+//! Each selected record is checked with [`BulkAction::authorize`], then the action is applied to it
+//! in its own transaction, so a failure rolls back only that record. The [`BulkResults`] report
+//! which records failed and why, as the `bulk_results` component for htmx requests, or a flash
+//! message and a redirect back to the list otherwise:
+//!
+//! ```ignore
+//! struct DeletePost;
+//!
+//! #[async_trait::async_trait]
+//! impl BulkAction for DeletePost {
+//!     type User = User;
+//!
+//!     async fn authorize(&self, user: &User, id: i32, conn: &mut Connection) -> Result<()> {
+//!         lowboy::authorize_owner!(user, &Post::load(id, conn).await?, bypass = "delete any post")
+//!     }
+//!
+//!     async fn apply(&self, _user: &User, id: i32, conn: &mut Connection) -> Result<()> {
+//!         Post::load(id, conn).await?.delete_record(conn).await?;
+//!         Ok(())
+//!     }
+//! }
+//!
+//! async fn bulk(
+//!     RequiredUser(user): RequiredUser<App, AppContext>,
+//!     DatabaseConnection(mut conn): DatabaseConnection,
+//!     hx: HxRequest,
+//!     messages: Flash,
+//!     request: BulkRequest,
+//! ) -> Result<Response, LowboyError> {
+//!     let actions = BulkActions::new().with("delete", "Delete", DeletePost);
+//!     let results = actions.apply(&request, &user, &mut conn).await?;
+//!
+//!     Ok(results.respond(&hx, &messages, "/posts"))
+//! }
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+
+use axum::extract::{FromRequest, FromRequestParts, Request};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use constant_time_eq::constant_time_eq;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::AsyncConnection;
+use tower_sessions::Session;
+use tracing::warn;
+
+use crate::error::LowboyError;
+use crate::extract::{Flash, HxRequest};
+use crate::secret::SecretGenerator;
+use crate::view::{self, BulkFailure, BulkResults};
+use crate::Connection;
+
+type Result<T, E = LowboyError> = std::result::Result<T, E>;
+
+/// The form field holding the token.
+pub const TOKEN_FIELD: &str = "_token";
+
+/// The most records a bulk action can be applied to at once.
+pub const MAX_ITEMS: usize = 500;
+
+/// The largest bulk action form accepted, in bytes.
+const BODY_LIMIT: usize = 64 * 1024;
+
+/// The session key of the token bulk action forms are submitted with.
+const TOKEN_KEY: &str = "lowboy.bulk.token";
+
+/// The hidden token field bulk action forms are submitted with.
+#[derive(Clone, Debug, Default)]
+pub struct BulkFields(String);
+
+impl BulkFields {
+    /// The fields for the session, creating its token with `secrets` if it doesn't have one yet.
+    pub async fn new(session: &Session, secrets: &dyn SecretGenerator) -> Result<Self> {
+        let token = match session.get::<String>(TOKEN_KEY).await? {
+            Some(token) => token,
+            None => {
+                let token = secrets.generate();
+                session.insert(TOKEN_KEY, &token).await?;
+                token
+            }
+        };
+
+        Ok(Self(format!(
+            r#"<input type="hidden" name="{TOKEN_FIELD}" value="{token}">"#
+        )))
+    }
+}
+
+impl fmt::Display for BulkFields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A submitted bulk action form: the action's name and the selected ids, in the order they were
+/// selected without duplicates.
+///
+/// Forms without the session's token are `403 Forbidden`, and forms without an action, with ids
+/// that aren't numbers or with more than [`MAX_ITEMS`] ids are `400 Bad Request`.
+#[derive(Clone, Debug)]
+pub struct BulkRequest {
+    pub action: String,
+    pub ids: Vec<i32>,
+}
+
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequest<S> for BulkRequest {
+    type Rejection = LowboyError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let (mut parts, body) = request.into_parts();
+        let session = Session::from_request_parts(&mut parts, state)
+            .await
+            .map_err(|(_, e)| anyhow::anyhow!(e))?;
+        let Ok(body) = axum::body::to_bytes(body, BODY_LIMIT).await else {
+            return Err(LowboyError::Rejected {
+                status: StatusCode::PAYLOAD_TOO_LARGE,
+                detail: format!("The form is larger than {BODY_LIMIT} bytes"),
+            });
+        };
+
+        let mut token = None;
+        let mut action = None;
+        let mut ids = vec![];
+        let mut seen = HashSet::new();
+        for (name, value) in form_urlencoded::parse(&body) {
+            match &*name {
+                TOKEN_FIELD => token = Some(value.into_owned()),
+                "action" => action = Some(value.into_owned()),
+                "ids" | "ids[]" => {
+                    let id = value.parse().map_err(|_| LowboyError::BadRequest)?;
+                    if seen.insert(id) {
+                        ids.push(id);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let expected = session.get::<String>(TOKEN_KEY).await?;
+        let valid = match (token, expected) {
+            (Some(token), Some(expected)) => {
+                constant_time_eq(token.as_bytes(), expected.as_bytes())
+            }
+            _ => false,
+        };
+        if !valid {
+            return Err(LowboyError::Forbidden);
+        }
+
+        let Some(action) = action.filter(|action| !action.is_empty()) else {
+            return Err(LowboyError::BadRequest);
+        };
+        if ids.len() > MAX_ITEMS {
+            return Err(LowboyError::BadRequest);
+        }
+
+        Ok(Self { action, ids })
+    }
+}
+
+/// An action applied to each record selected on a list page, see [`crate::bulk`].
+#[async_trait::async_trait]
+pub trait BulkAction: Send + Sync {
+    /// Who the action is applied on behalf of, usually the app's user.
+    type User: Send + Sync;
+
+    /// Check that `user` may apply the action to the record `id`, e.g. with
+    /// [`crate::authorize_owner!`]. Records which aren't allowed are reported as failures, and
+    /// the action isn't applied to them.
+    async fn authorize(&self, user: &Self::User, id: i32, conn: &mut Connection) -> Result<()>;
+
+    /// Apply the action to the record `id`, inside a transaction which is rolled back if it fails.
+    async fn apply(&self, user: &Self::User, id: i32, conn: &mut Connection) -> Result<()>;
+}
+
+/// The actions a list page offers, by name.
+pub struct BulkActions<U> {
+    actions: Vec<(&'static str, &'static str, Box<dyn BulkAction<User = U>>)>,
+}
+
+impl<U> Default for BulkActions<U> {
+    fn default() -> Self {
+        Self { actions: vec![] }
+    }
+}
+
+impl<U: Send + Sync> BulkActions<U> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Offer `action` as `name`, shown as `label`.
+    pub fn with(
+        mut self,
+        name: &'static str,
+        label: &'static str,
+        action: impl BulkAction<User = U> + 'static,
+    ) -> Self {
+        self.actions.push((name, label, Box::new(action)));
+        self
+    }
+
+    /// The names and labels of the actions, for the form's action select.
+    pub fn options(&self) -> Vec<(&'static str, &'static str)> {
+        self.actions
+            .iter()
+            .map(|&(name, label, _)| (name, label))
+            .collect()
+    }
+
+    /// Apply the requested action to each of the selected records on behalf of `user`. Unknown
+    /// actions are `400 Bad Request`.
+    pub async fn apply(
+        &self,
+        request: &BulkRequest,
+        user: &U,
+        conn: &mut Connection,
+    ) -> Result<BulkResults> {
+        let Some((_, label, action)) = self
+            .actions
+            .iter()
+            .find(|(name, _, _)| *name == request.action)
+        else {
+            return Err(LowboyError::BadRequest);
+        };
+
+        let mut results = BulkResults::new(*label);
+        for &id in &request.ids {
+            let applied = match action.authorize(user, id, conn).await {
+                Ok(()) => {
+                    conn.transaction(|conn| action.apply(user, id, conn).scope_boxed())
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+
+            match applied {
+                Ok(()) => results.succeeded.push(id),
+                Err(e) => results.failed.push(BulkFailure {
+                    id,
+                    reason: failure_reason(id, e),
+                }),
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+/// Why applying an action to the record `id` failed, for the user. Internal errors are logged
+/// rather than shown.
+fn failure_reason(id: i32, error: LowboyError) -> String {
+    match error {
+        LowboyError::Unauthorized | LowboyError::Forbidden => {
+            "You aren't allowed to do that".to_string()
+        }
+        LowboyError::NotFound => "It no longer exists".to_string(),
+        LowboyError::Conflict => "It was changed by someone else".to_string(),
+        LowboyError::Invalid(errors) => errors.to_string(),
+        LowboyError::Rejected { detail, .. } => detail,
+        LowboyError::BadRequest => "It can't be changed that way".to_string(),
+        LowboyError::Internal(e) => {
+            warn!("couldn't apply a bulk action to {id}: {e}");
+            "Something went wrong".to_string()
+        }
+    }
+}
+
+impl BulkResults {
+    /// Respond with the results: the `bulk_results` component for htmx requests, to be swapped
+    /// into the list page, or a flash message and a redirect to `list_path` otherwise.
+    pub fn respond(&self, hx: &HxRequest, messages: &Flash, list_path: &str) -> Response {
+        if hx.enabled {
+            return Html(view::component("bulk_results", self)).into_response();
+        }
+
+        let messages = messages.clone();
+        if self.failed.is_empty() {
+            messages.success(self.summary());
+        } else {
+            messages.error(self.summary());
+        }
+
+        hx.redirect(list_path)
+    }
+}
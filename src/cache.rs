@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::model::ModelEvent;
+use crate::Context;
+
+static CACHE: LazyLock<Cache> = LazyLock::new(Cache::default);
+
+/// Entries are only added while the cache holds fewer than this many unexpired entries.
+const MAX_ENTRIES: usize = 10_000;
+
+struct Entry {
+    value: String,
+    expires_at: DateTime<Utc>,
+    tags: Vec<String>,
+}
+
+/// An in-memory cache of rendered strings, such as template fragments.
+///
+/// Entries expire after their TTL, and can be invalidated early by key or by the tags they were
+/// stored with (see [`FragmentKey::depends_on`]).
+#[derive(Default)]
+pub struct Cache(Mutex<HashMap<String, Entry>>);
+
+impl Cache {
+    /// The cache used by [`Context::cache`] unless the app provides its own.
+    pub fn global() -> &'static Self {
+        &CACHE
+    }
+
+    pub fn get(&self, key: &str, now: DateTime<Utc>) -> Option<String> {
+        self.entries()
+            .get(key)
+            .filter(|entry| entry.expires_at > now)
+            .map(|entry| entry.value.clone())
+    }
+
+    pub fn insert(
+        &self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+        ttl: TimeDelta,
+        tags: Vec<String>,
+        now: DateTime<Utc>,
+    ) {
+        let mut entries = self.entries();
+
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, entry| entry.expires_at > now);
+        }
+        if entries.len() >= MAX_ENTRIES {
+            tracing::debug!("fragment cache is full, not caching");
+            return;
+        }
+
+        entries.insert(
+            key.into(),
+            Entry {
+                value: value.into(),
+                expires_at: now + ttl,
+                tags,
+            },
+        );
+    }
+
+    pub fn invalidate(&self, key: &str) {
+        self.entries().remove(key);
+    }
+
+    /// Invalidate every entry stored with `tag`.
+    pub fn invalidate_tag(&self, tag: &str) {
+        self.entries()
+            .retain(|_, entry| !entry.tags.iter().any(|t| t == tag));
+    }
+
+    /// Invalidate the entries depending on the model `event` is about.
+    pub fn invalidate_model(&self, event: &ModelEvent) {
+        self.invalidate_tag(event.model);
+        if let Some(id) = event.id {
+            self.invalidate_tag(&model_tag(event.model, id));
+        }
+    }
+
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<String, Entry>> {
+        self.0.lock().expect("cache lock should not be poisoned")
+    }
+}
+
+fn model_tag(model: &str, id: i32) -> String {
+    format!("{model}:{id}")
+}
+
+/// The key a fragment is cached under, and the models it's rendered from.
+#[derive(Clone, Debug)]
+pub struct FragmentKey {
+    key: String,
+    tags: Vec<String>,
+}
+
+impl FragmentKey {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            tags: vec![],
+        }
+    }
+
+    /// Invalidate the fragment whenever any `model` (e.g. `post`) is created, updated or deleted.
+    pub fn depends_on(mut self, model: &str) -> Self {
+        self.tags.push(model.to_string());
+        self
+    }
+
+    /// Invalidate the fragment whenever the `model` with `id` is updated or deleted.
+    pub fn depends_on_id(mut self, model: &str, id: i32) -> Self {
+        self.tags.push(model_tag(model, id));
+        self
+    }
+}
+
+impl From<&str> for FragmentKey {
+    fn from(key: &str) -> Self {
+        Self::new(key)
+    }
+}
+
+/// Reuse the fragment cached under `key` if it was rendered within the last `ttl`, otherwise
+/// render it with `render` and cache it.
+///
+/// ```ignore
+/// let posts = cache_fragment(
+///     &context,
+///     FragmentKey::new("home:posts").depends_on("post"),
+///     TimeDelta::minutes(5),
+///     || async { render_posts(&mut conn).await },
+/// )
+/// .await?;
+/// ```
+pub async fn cache_fragment<C, F, Fut, E>(
+    context: &C,
+    key: impl Into<FragmentKey>,
+    ttl: TimeDelta,
+    render: F,
+) -> Result<String, E>
+where
+    C: Context + ?Sized,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<String, E>>,
+{
+    let FragmentKey { key, tags } = key.into();
+    let cache = context.cache();
+
+    if let Some(fragment) = cache.get(&key, context.clock().now()) {
+        return Ok(fragment);
+    }
+
+    let fragment = render().await?;
+    cache.insert(key, fragment.clone(), ttl, tags, context.clock().now());
+
+    Ok(fragment)
+}
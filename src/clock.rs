@@ -0,0 +1,56 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use dyn_clone::DynClone;
+
+/// A source of the current time.
+///
+/// Token expiry, session expiry, and scheduled work ask the context's clock for the current time
+/// rather than calling `Utc::now()` directly, so tests can control time with a [`MockClock`].
+pub trait Clock: Debug + DynClone + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+dyn_clone::clone_trait_object!(Clock);
+
+/// The system clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to.
+///
+/// Clones share the same time, so a test can keep a handle to the clock it gave to the context.
+#[derive(Clone, Debug)]
+pub struct MockClock(Arc<Mutex<DateTime<Utc>>>);
+
+impl MockClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self(Arc::new(Mutex::new(now)))
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.0.lock().expect("mock clock lock should not be poisoned") = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.0.lock().expect("mock clock lock should not be poisoned") += duration;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().expect("mock clock lock should not be poisoned")
+    }
+}
@@ -0,0 +1,209 @@
+//! Primitives for simple collaborative editing, without a full CRDT stack.
+//!
+//! Models that can be edited by several users at once keep a `version` column, which is sent
+//! along with the edit form. Saving the edit is an optimistic update that only applies to the
+//! version the form was based on:
+//!
+//! ```ignore
+//! let rows = diesel::update(post::table.find(id).filter(post::version.eq(form.version)))
+//!     .set((post::body.eq(&form.body), post::version.eq(post::version + 1)))
+//!     .execute(&mut conn)
+//!     .await?;
+//! let version = collab::ensure_updated(rows, "post", id, form.version)?;
+//!
+//! Patch::new("post", id, version, &form)
+//!     .by(user.id)
+//!     .broadcast(context.events())
+//!     .await?;
+//! ```
+//!
+//! A stale submission is rejected with [`StaleVersion`], a `409 Conflict`. The other editors are
+//! told about the [`Patch`] with a `patch` event so they can update their forms (see `onPatch` in
+//! the browser client). Every `/events` subscriber gets the event, so it only carries the
+//! [`PatchNotice`], and not the changes: editors reload the model, which checks they can see it.
+
+use axum::response::sse::Event;
+use serde::{Deserialize, Serialize};
+
+use crate::Events;
+
+/// A change to a model, and the version of the model it produced.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Patch<T> {
+    /// The model's name, e.g. `post`.
+    pub model: String,
+    pub id: i32,
+    pub version: i64,
+    /// The user who made the change, so their own client can ignore it.
+    pub author_id: Option<i32>,
+    /// The changed fields.
+    pub changes: T,
+}
+
+impl<T> Patch<T> {
+    pub fn new(model: impl Into<String>, id: i32, version: i64, changes: T) -> Self {
+        Self {
+            model: model.into(),
+            id,
+            version,
+            author_id: None,
+            changes,
+        }
+    }
+
+    pub fn by(self, author_id: i32) -> Self {
+        Self {
+            author_id: Some(author_id),
+            ..self
+        }
+    }
+
+    /// Which model the patch changed and the version it produced, without the changes.
+    pub fn notice(&self) -> PatchNotice {
+        PatchNotice {
+            model: self.model.clone(),
+            id: self.id,
+            version: self.version,
+            author_id: self.author_id,
+        }
+    }
+
+    /// The patch's notice as a `patch` event.
+    pub fn to_event(&self) -> serde_json::Result<Event> {
+        Ok(Event::default()
+            .event("patch")
+            .data(serde_json::to_string(&self.notice())?))
+    }
+
+    /// Send the patch's notice to the clients connected to the event bus.
+    pub async fn broadcast(&self, events: &Events) -> serde_json::Result<()> {
+        events.send(self.to_event()?).await;
+        Ok(())
+    }
+}
+
+/// What's broadcast about a [`Patch`]. It's sent to every client connected to the event bus, who
+/// may not be allowed to see the model, so it doesn't include the changes.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct PatchNotice {
+    pub model: String,
+    pub id: i32,
+    pub version: i64,
+    pub author_id: Option<i32>,
+}
+
+/// A submission was based on an outdated version of a model, because someone else changed it
+/// since.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{model}({id}) was changed by someone else since version {submitted}")]
+pub struct StaleVersion {
+    pub model: String,
+    pub id: i32,
+    /// The version the submission was based on.
+    pub submitted: i64,
+    /// The model's current version, if it's known.
+    pub current: Option<i64>,
+}
+
+/// Check a submission based on version `submitted` of a model can be applied to its `current`
+/// version.
+pub fn check_version(
+    model: &str,
+    id: i32,
+    submitted: i64,
+    current: i64,
+) -> Result<(), StaleVersion> {
+    if submitted == current {
+        return Ok(());
+    }
+
+    Err(StaleVersion {
+        model: model.to_string(),
+        id,
+        submitted,
+        current: Some(current),
+    })
+}
+
+/// Finish an optimistic update of a model, which only applied to version `submitted`. No `rows`
+/// being updated means the submission was stale. Returns the model's new version.
+pub fn ensure_updated(
+    rows: usize,
+    model: &str,
+    id: i32,
+    submitted: i64,
+) -> Result<i64, StaleVersion> {
+    if rows == 0 {
+        return Err(StaleVersion {
+            model: model.to_string(),
+            id,
+            submitted,
+            current: None,
+        });
+    }
+
+    Ok(submitted + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::EventOverflow;
+
+    #[derive(Serialize)]
+    struct Changes {
+        body: &'static str,
+    }
+
+    fn patch() -> Patch<Changes> {
+        Patch::new("post", 5, 3, Changes { body: "unpublished draft" }).by(1)
+    }
+
+    #[test]
+    fn notice_leaves_out_the_changes() {
+        let notice = serde_json::to_value(patch().notice()).unwrap();
+
+        assert_eq!(notice, json!({ "model": "post", "id": 5, "version": 3, "author_id": 1 }));
+    }
+
+    #[tokio::test]
+    async fn broadcast_only_sends_the_notice() {
+        let events = Events::new(8, EventOverflow::DropNew, Duration::from_millis(10));
+        let subscription = events.subscribe();
+
+        patch().broadcast(&events).await.unwrap();
+
+        let event = format!("{:?}", subscription.recv().await.unwrap());
+        assert!(event.contains("patch"));
+        assert!(event.contains("version"));
+        assert!(!event.contains("unpublished draft"));
+    }
+
+    #[test]
+    fn check_version_accepts_the_current_version() {
+        assert_eq!(check_version("post", 5, 3, 3), Ok(()));
+    }
+
+    #[test]
+    fn check_version_rejects_a_stale_version() {
+        assert_eq!(
+            check_version("post", 5, 2, 3),
+            Err(StaleVersion {
+                model: "post".to_string(),
+                id: 5,
+                submitted: 2,
+                current: Some(3),
+            })
+        );
+    }
+
+    #[test]
+    fn ensure_updated_bumps_the_version() {
+        assert_eq!(ensure_updated(1, "post", 5, 3), Ok(4));
+        assert_eq!(ensure_updated(0, "post", 5, 3).unwrap_err().current, None);
+    }
+}
@@ -1,12 +1,14 @@
 #![allow(dead_code)]
-use std::path::PathBuf;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 
 use confique::yaml::FormatOptions;
 use confique::Config as _;
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
 
 use crate::auth::IdentityProviderConfig;
-use crate::mailer;
+use crate::{bot, mailer, telemetry};
 type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, thiserror::Error)]
@@ -22,10 +24,37 @@ pub enum Error {
 
     #[error(transparent)]
     Xdg(#[from] xdg::BaseDirectoriesError),
+
+    #[error("static files directory not found in {looked_in}, set `static_dir` to its path")]
+    StaticDirNotFound { looked_in: String },
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, confique::Config)]
 pub struct Config {
+    /// Address to listen on
+    #[config(default = "127.0.0.1")]
+    pub listen_addr: IpAddr,
+
+    /// Port to listen on
+    #[config(default = 3000)]
+    pub listen_port: u16,
+
+    /// URL the app is reached at, e.g. `https://example.com`, used to build links like OAuth
+    /// redirect URLs. Defaults to localhost on `listen_port`
+    pub public_url: Option<String>,
+
+    /// Serve HTTPS with this certificate, instead of HTTP
+    pub tls: Option<TlsConfig>,
+
+    /// Port to serve the app's gRPC services on. Without it, they share `listen_port` with the
+    /// web app. Either way, gRPC calls bypass the auth and permission layers, see `lowboy::grpc`.
+    pub grpc_port: Option<u16>,
+
+    /// Directory of the static files served at `/static`. Relative paths are looked up in the
+    /// app's crate when it's run with cargo, wherever from, then in the working directory
+    #[config(default = "static")]
+    pub static_dir: PathBuf,
+
     /// Database url
     pub database_url: String,
 
@@ -33,15 +62,296 @@ pub struct Config {
     #[config(default = 16)]
     pub database_pool_size: usize,
 
+    /// Milliseconds to wait for a database connection before giving up
+    pub database_pool_wait_timeout: Option<u64>,
+
+    /// Milliseconds to wait for a new database connection to be established
+    pub database_pool_create_timeout: Option<u64>,
+
+    /// Milliseconds to wait for a database connection to be recycled
+    pub database_pool_recycle_timeout: Option<u64>,
+
+    /// How database connections are checked before being reused
+    #[config(default = "verified")]
+    pub database_pool_recycling: PoolRecycling,
+
+    /// Cache prepared statements on each database connection
+    #[config(default = true)]
+    pub database_statement_cache: bool,
+
+    /// Open every pooled database connection at boot and prepare the hot queries on them
+    #[config(default = false)]
+    pub database_warm_up: bool,
+
+    /// Log database connection checkouts waiting at least this many milliseconds
+    #[config(default = 100)]
+    pub database_pool_slow_checkout: u64,
+
+    /// SQLite journal mode
+    #[config(env = "LOWBOY_DATABASE_JOURNAL_MODE", default = "wal")]
+    pub database_journal_mode: JournalMode,
+
+    /// How often SQLite waits for writes to reach the disk
+    #[config(env = "LOWBOY_DATABASE_SYNCHRONOUS", default = "normal")]
+    pub database_synchronous: Synchronous,
+
+    /// Milliseconds to wait for a locked database before giving up
+    #[config(env = "LOWBOY_DATABASE_BUSY_TIMEOUT", default = 30000)]
+    pub database_busy_timeout: u64,
+
+    /// Page cache size of each database connection, in pages when positive or KiB when negative.
+    /// Defaults to SQLite's
+    #[config(env = "LOWBOY_DATABASE_CACHE_SIZE")]
+    pub database_cache_size: Option<i64>,
+
+    /// Where SQLite stores temporary tables and indices
+    #[config(env = "LOWBOY_DATABASE_TEMP_STORE", default = "default")]
+    pub database_temp_store: TempStore,
+
+    /// Bytes of the database file to memory map. Defaults to SQLite's
+    #[config(env = "LOWBOY_DATABASE_MMAP_SIZE")]
+    pub database_mmap_size: Option<u64>,
+
+    /// Additional SQLite databases attached to every database connection
+    #[config(default = [])]
+    pub database_attach: Vec<AttachedDatabase>,
+
+    /// What to do when the database schema has drifted from lowboy's after running migrations
+    #[config(default = "warn")]
+    pub database_schema_check: SchemaCheck,
+
+    /// Hash at most this many passwords at once. Defaults to the number of CPUs
+    pub password_hash_parallelism: Option<usize>,
+
+    /// Locale used when the user, their session and their browser don't specify one, e.g. `en-US`
+    pub default_locale: Option<String>,
+
+    /// Timezone used when the user and their session don't specify one
+    #[config(default = "UTC")]
+    pub default_timezone: String,
+
+    /// Collapse whitespace in rendered HTML
+    #[config(env = "LOWBOY_MINIFY_HTML", default = false)]
+    pub minify_html: bool,
+
+    /// Most /events connections a user can have open at once. Opening another closes their
+    /// oldest one. 0 disables the limit
+    #[config(default = 5)]
+    pub events_max_connections: usize,
+
+    /// Close /events connections which haven't been sent an event for this many seconds. Browsers
+    /// reconnect on their own. 0 disables the timeout
+    #[config(default = 3600)]
+    pub events_idle_timeout: u64,
+
+    /// How many events are buffered for each /events connection
+    #[config(default = 32)]
+    pub events_capacity: usize,
+
+    /// What to do when an /events connection's buffer is full
+    #[config(default = "drop_oldest")]
+    pub events_overflow: EventOverflow,
+
+    /// Milliseconds to wait for a full buffer with the `block` overflow policy before dropping
+    /// the event
+    #[config(default = 1000)]
+    pub events_send_timeout: u64,
+
+    /// How many background jobs run at once
+    #[config(default = 4)]
+    pub jobs_workers: usize,
+
+    /// Milliseconds idle job workers wait between checking for due jobs
+    #[config(default = 1000)]
+    pub jobs_poll_interval: u64,
+
+    /// Largest JSON request body, in bytes, `lowboy::extract::Json` accepts
+    #[config(default = 1048576)]
+    pub json_body_limit: usize,
+
+    /// Log requests still running after this many seconds, with their route and user, to find
+    /// hangs. 0 disables the watchdog
+    #[config(default = 30)]
+    pub request_watchdog: u64,
+
+    /// Let users with the `profile site` permission capture CPU profiles at /debug/pprof (debug
+    /// builds on unix only)
+    #[config(default = false)]
+    pub profiling: bool,
+
+    /// Expose metrics at /metrics
+    #[config(default = false)]
+    pub metrics: bool,
+
+    /// Expose the app's name, git SHA, build time and rustc version at /__version
+    #[config(default = false)]
+    pub version_endpoint: bool,
+
+    /// Add the git SHA to every response in an `X-Lowboy-Version` header
+    #[config(default = false)]
+    pub version_header: bool,
+
+    /// Log a summary of the build, database, enabled features and config when the app starts
+    #[config(default = true)]
+    pub startup_banner: bool,
+
+    /// Sessions whose data is larger than this many bytes are logged, and handled according to
+    /// `session_oversized`
+    #[config(default = 65536)]
+    pub session_max_size: usize,
+
+    /// What to do with sessions larger than `session_max_size`
+    #[config(default = "truncate")]
+    pub session_oversized: OversizedSession,
+
     /// Base64 encoded session key
     #[config(env = "LOWBOY_SESSION_KEY")]
     pub session_key: String,
 
+    /// Domain the session cookie is sent to, e.g. `example.com` to share sessions between
+    /// `app.example.com` and `admin.example.com`. Without it, sessions are isolated to the host
+    /// that started them. The OAuth state is kept in the session, so it follows the cookie
+    #[config(env = "LOWBOY_SESSION_COOKIE_DOMAIN")]
+    pub session_cookie_domain: Option<String>,
+
+    /// Path the session cookie is sent to, e.g. `/app` to isolate the sessions of apps sharing a
+    /// host
+    #[config(default = "/")]
+    pub session_cookie_path: String,
+
+    /// Reject registration and login forms submitted within this many seconds of being shown, as
+    /// people can't fill them in that quickly. 0 disables the check
+    #[config(default = 2)]
+    pub bot_min_fill_time: u64,
+
+    /// Captcha shown on the registration and login forms
+    pub captcha: Option<bot::CaptchaConfig>,
+
     /// OAuth Provider Configuration
     pub oauth_providers: Vec<IdentityProviderConfig>,
 
     /// Mailer configuration
     pub mailer: Option<mailer::Config>,
+
+    /// Report panics in lowboy's code and failed migrations, without any user data, to this
+    /// endpoint. Off unless set, see `lowboy::telemetry`
+    pub telemetry: Option<telemetry::Config>,
+}
+
+/// The certificate to serve HTTPS with.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TlsConfig {
+    /// PEM encoded certificate chain.
+    pub cert: PathBuf,
+    /// PEM encoded private key.
+    pub key: PathBuf,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PoolRecycling {
+    /// Reuse connections without checking them.
+    Fast,
+    /// Run a test query before reusing a connection.
+    #[default]
+    Verified,
+}
+
+/// A database file attached alongside the main database, e.g. to keep analytics or large blobs
+/// out of it.
+///
+/// Its tables are accessed with the schema name, which diesel supports in `table!`:
+///
+/// ```ignore
+/// diesel::table! {
+///     analytics.page_view (id) {
+///         id -> Integer,
+///         path -> Text,
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AttachedDatabase {
+    /// The schema name the database is attached as.
+    pub name: String,
+    pub url: String,
+    /// Directory of diesel migrations to run against the database at boot.
+    #[serde(default)]
+    pub migrations: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, strum::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum JournalMode {
+    Delete,
+    Truncate,
+    Persist,
+    Memory,
+    #[default]
+    Wal,
+    Off,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, strum::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum Synchronous {
+    Off,
+    /// Safe from corruption in WAL mode, though the last transactions may be lost on power loss.
+    #[default]
+    Normal,
+    Full,
+    Extra,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, strum::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum TempStore {
+    /// Use SQLite's compile time default.
+    #[default]
+    Default,
+    File,
+    Memory,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaCheck {
+    /// Don't check the schema.
+    Off,
+    /// Log every difference as an error, and boot anyway.
+    #[default]
+    Warn,
+    /// Refuse to boot.
+    Abort,
+}
+
+/// What the event bus does when a subscriber's buffer is full.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventOverflow {
+    /// Drop the oldest buffered event to make room.
+    #[default]
+    DropOldest,
+    /// Drop the new event.
+    DropNew,
+    /// Wait for room, up to `events_send_timeout`, then drop the new event.
+    Block,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OversizedSession {
+    /// Only log a warning.
+    Warn,
+    /// Drop the largest values from the session until it fits, keeping the login and device
+    /// tracking data.
+    #[default]
+    Truncate,
+    /// Refuse to save the session.
+    Reject,
 }
 
 impl Config {
@@ -51,6 +361,58 @@ impl Config {
 
         Ok(config)
     }
+
+    /// The URL the app is reached at, without a trailing slash.
+    pub fn public_url(&self) -> String {
+        match self.public_url {
+            Some(ref url) => url.trim_end_matches('/').to_string(),
+            None => {
+                let scheme = if self.tls.is_some() { "https" } else { "http" };
+                format!("{scheme}://localhost:{}", self.listen_port)
+            }
+        }
+    }
+
+    /// Whether browsers accept the session cookie from `public_url`, which they only do when
+    /// `session_cookie_domain` is its host or a parent of it.
+    pub fn session_cookie_domain_matches(&self) -> bool {
+        let Some(ref domain) = self.session_cookie_domain else {
+            return true;
+        };
+        let Some(host) = Url::parse(&self.public_url())
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_lowercase))
+        else {
+            return false;
+        };
+
+        let domain = domain.trim_start_matches('.').to_lowercase();
+        host == domain || host.ends_with(&format!(".{domain}"))
+    }
+
+    /// The directory of static files, see `static_dir`.
+    pub fn static_dir(&self) -> Result<PathBuf> {
+        let mut candidates = vec![];
+        // `cargo run` and `cargo test` set this to the app's crate, even from the workspace root.
+        if let Some(manifest_dir) = std::env::var_os("CARGO_MANIFEST_DIR") {
+            candidates.push(Path::new(&manifest_dir).join(&self.static_dir));
+        }
+        if !candidates.contains(&self.static_dir) {
+            candidates.push(self.static_dir.clone());
+        }
+
+        candidates
+            .iter()
+            .find(|dir| dir.is_dir())
+            .cloned()
+            .ok_or_else(|| Error::StaticDirNotFound {
+                looked_in: candidates
+                    .iter()
+                    .map(|dir| format!("`{}`", dir.display()))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })
+    }
 }
 
 pub fn get_config_template() -> String {
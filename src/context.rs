@@ -1,23 +1,43 @@
-use axum::response::sse::Event;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use diesel::connection::CacheSize;
 use diesel::sqlite::SqliteConnection;
-use diesel::ConnectionError;
+use diesel::{Connection as _, ConnectionError};
 use diesel_async::pooled_connection::deadpool::Pool;
-use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
+use diesel_async::pooled_connection::{
+    AsyncDieselConnectionManager, ManagerConfig, RecyclingMethod,
+};
+use diesel_async::scoped_futures::ScopedFutureExt;
 use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
 use diesel_async::{AsyncConnection, SimpleAsyncConnection};
 use dyn_clone::DynClone;
-use flume::{Receiver, Sender};
 use futures::FutureExt;
-use lettre::message::{header, MultiPart, SinglePart};
-use lettre::transport::smtp::authentication::Credentials;
-use lettre::{AsyncSmtpTransport, AsyncTransport as _, Message, Tokio1Executor};
-use tokio_cron_scheduler::JobScheduler;
 
+use crate::actor::RequestActor;
 use crate::auth::RegistrationDetails;
-use crate::config::Config;
+use crate::cache::Cache;
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Config, PoolRecycling};
+use crate::database::{self, Pragmas};
+use crate::jobs::{self, Job, Jobs, Scheduler};
+use crate::kv::Kv;
+use crate::mailer::template::{SecurityNotificationEmail, VerifyEmail};
+use crate::mailer::{self, Mail, Mailer};
+use crate::metrics::{self, PoolMetrics};
 use crate::model::unverified_email::UnverifiedEmail;
-use crate::model::{User, UserModel};
-use crate::{Connection, Events};
+use crate::mailer::webhook::DeliveryFailure;
+use crate::model::{
+    AuditLog, DeadLetter, Email, EmailSuppression, Model as _, ModelEvent, NotificationPreferences,
+    QueuedJob, SentEmail, SentEmailStatus, SuppressionReason, User, UserModel,
+};
+use crate::password::PasswordHasher;
+use crate::presence::Presence;
+use crate::provision::{self, NewUser, ProvisionResult};
+use crate::secret::{SecretGenerator, UuidSecretGenerator};
+use crate::security::SecurityNotification;
+use crate::settings::{Settings, SettingsCache};
+use crate::{diesel_sqlite_session_store, Connection, Events};
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -38,16 +58,10 @@ pub enum Error {
     ),
 
     #[error(transparent)]
-    JobScheduler(#[from] tokio_cron_scheduler::JobSchedulerError),
-
-    #[error(transparent)]
-    LettreSmtp(#[from] lettre::transport::smtp::Error),
+    Scheduler(#[from] jobs::SchedulerError),
 
     #[error(transparent)]
-    LettreAddress(#[from] lettre::address::AddressError),
-
-    #[error(transparent)]
-    LettreError(#[from] lettre::error::Error),
+    Mailer(#[from] mailer::Error),
 
     #[error(transparent)]
     App(#[from] anyhow::Error),
@@ -63,11 +77,66 @@ impl From<Error> for ConnectionError {
     }
 }
 
+static SYSTEM_CLOCK: SystemClock = SystemClock;
+static UUID_SECRET_GENERATOR: UuidSecretGenerator = UuidSecretGenerator;
+/// The app's `public_url` config, set by [`create_context`].
+static PUBLIC_URL: RwLock<String> = RwLock::new(String::new());
+/// The mailer's `from` config, set by [`create_context`].
+static MAIL_FROM: RwLock<String> = RwLock::new(String::new());
+
 pub trait Context: Send + Sync + 'static {
     fn database(&self) -> &Pool<Connection>;
     fn events(&self) -> &Events;
-    fn scheduler(&self) -> &JobScheduler;
-    fn mailer(&self) -> Option<&AsyncSmtpTransport<Tokio1Executor>>;
+    fn scheduler(&self) -> &Scheduler;
+    fn mailer(&self) -> Option<&dyn Mailer>;
+
+    /// The url the app is served from, i.e. its `public_url` config, for links in emails and other
+    /// places there's no request to build them from.
+    fn public_url(&self) -> String {
+        PUBLIC_URL.read().expect("lock should not be poisoned").clone()
+    }
+
+    /// The address emails are sent from, i.e. the mailer's `from` config.
+    fn mail_from(&self) -> String {
+        MAIL_FROM.read().expect("lock should not be poisoned").clone()
+    }
+
+    /// The source of the current time. Override this with a [`crate::clock::MockClock`] in tests
+    /// that depend on time passing.
+    fn clock(&self) -> &dyn Clock {
+        &SYSTEM_CLOCK
+    }
+
+    /// The source of secrets such as verification tokens. Override this with a
+    /// [`crate::secret::SequentialSecretGenerator`] in tests that need predictable values.
+    fn secret_generator(&self) -> &dyn SecretGenerator {
+        &UUID_SECRET_GENERATOR
+    }
+
+    /// The cache used for rendered fragments, see [`crate::cache::cache_fragment`].
+    fn cache(&self) -> &Cache {
+        Cache::global()
+    }
+
+    /// Hashes and verifies passwords off the async executor.
+    fn password_hasher(&self) -> &PasswordHasher {
+        PasswordHasher::global()
+    }
+
+    /// The users connected to the `/events` stream.
+    fn presence(&self) -> &Presence {
+        Presence::global()
+    }
+
+    /// The site-wide settings stored in the database, see [`crate::settings`].
+    fn settings(&self) -> Settings<'_> {
+        Settings::new(self.database(), SettingsCache::global(), self.clock())
+    }
+
+    /// The key-value store for ephemeral data, see [`crate::kv`].
+    fn kv(&self) -> Kv<'_> {
+        Kv::new(self.database(), self.clock(), self.secret_generator())
+    }
 }
 
 #[allow(unused_variables)]
@@ -76,8 +145,8 @@ pub trait AppContext: Context + DynClone {
     fn create(
         database: Pool<Connection>,
         events: Events,
-        scheduler: JobScheduler,
-        mailer: Option<AsyncSmtpTransport<Tokio1Executor>>,
+        scheduler: Scheduler,
+        mailer: Option<Box<dyn Mailer>>,
     ) -> Result<Self>
     where
         Self: Sized;
@@ -87,49 +156,265 @@ pub trait AppContext: Context + DynClone {
         Ok(())
     }
 
+    /// Undo the registration of `user` after [`AppContext::on_new_user`] failed with `error`, so
+    /// they aren't left half set up and can register again.
+    ///
+    /// By default the user is deleted with [`User::discard`]. Apps whose `on_new_user` may have
+    /// created rows for the user before failing should override this to delete them first.
+    async fn on_new_user_failed(&self, user: &User, error: &Error) -> Result<()> {
+        let mut conn = metrics::checkout(self.database()).await?;
+        user.clone().discard(&mut conn).await?;
+        Ok(())
+    }
+
+    /// Run [`AppContext::on_new_user`] for a newly created `user`, undoing the registration with
+    /// [`AppContext::on_new_user_failed`] if it fails.
+    async fn complete_new_user(&self, user: &User, details: RegistrationDetails) -> Result<()> {
+        let Err(error) = self.on_new_user(user, details).await else {
+            return Ok(());
+        };
+
+        tracing::error!("on_new_user failed for {}, undoing registration: {error}", user.username);
+        if let Err(e) = self.on_new_user_failed(user, &error).await {
+            tracing::error!("couldn't undo the registration of {}: {e}", user.username);
+        }
+
+        Err(error)
+    }
+
     async fn send_verification_email(&self, user: &User) -> Result<()> {
         if !user.email.verified {
             tracing::info!(
                 "Sending new user verification email to: {email}",
                 email = user.email
             );
-            let mut conn = self.database().get().await?;
+            let mut conn = metrics::checkout(self.database()).await?;
             let unverified_email =
                 UnverifiedEmail::find_by_address(&user.email().address, &mut conn)
                     .await?
                     .expect("should be able to load the unverified email");
 
             let verification_url = format!(
-                "http://localhost:3000/email/{email}/verify/{token}",
+                "{public_url}/email/{email}/verify/{token}",
+                public_url = self.public_url(),
                 email = unverified_email.address,
                 token = unverified_email.token.secret,
             );
 
-            let verification_email = Message::builder()
-                .from("Lowboy <no-reply@marc.cx>".parse()?)
-                .to(format!("<{}>", user.email()).parse()?)
-                .subject("Email Verification")
-                .multipart(
-                    MultiPart::alternative()
-                        .singlepart(
-                            SinglePart::builder()
-                                .header(header::ContentType::TEXT_PLAIN)
-                                .body(format!("Go here to verify your email: {verification_url}")),
-                        )
-                        .singlepart(
-                            SinglePart::builder()
-                                .header(header::ContentType::TEXT_HTML)
-                                .body(format!(r#"Click here to verify your email: <a href="{verification_url}">{verification_url}</a>"#)),
-                        ),
-                )?;
+            let verification_email = Mail::from_template(
+                self.mail_from(),
+                format!("<{}>", user.email()),
+                "verify_email",
+                &VerifyEmail {
+                    username: user.username.clone(),
+                    verification_url,
+                },
+            )?;
 
             if let Some(mailer) = self.mailer() {
-                mailer.send(verification_email).await?;
+                mailer.send(&verification_email).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the email sent for a security `notification`, from the `security_notification` mail
+    /// template. Override this to customize more than the template.
+    fn security_notification_mail(
+        &self,
+        user: &User,
+        notification: &SecurityNotification,
+    ) -> Result<Mail> {
+        let props = SecurityNotificationEmail {
+            username: user.username.clone(),
+            notification: notification.clone(),
+        };
+
+        Ok(Mail::from_template(
+            self.mail_from(),
+            format!("<{}>", user.email()),
+            "security_notification",
+            &props,
+        )?)
+    }
+
+    /// Email `user` about a security `notification`, unless they've opted out of it.
+    async fn send_security_notification(
+        &self,
+        user: &User,
+        notification: SecurityNotification,
+    ) -> Result<()> {
+        // Service accounts don't have a real email address to notify.
+        if user.service_account {
+            return Ok(());
+        }
+
+        let mut conn = metrics::checkout(self.database()).await?;
+        let preferences = NotificationPreferences::for_user(user.id, &mut conn).await?;
+        if !preferences.allows(&notification) {
+            return Ok(());
+        }
+
+        tracing::info!(
+            "Sending security notification ({subject}) to: {email}",
+            subject = notification.subject(),
+            email = user.email
+        );
+
+        if let Some(mailer) = self.mailer() {
+            mailer
+                .send(&self.security_notification_mail(user, &notification)?)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Called after the mail provider reported that email to `user` bounced or was marked as spam,
+    /// so no more is sent to them. Override this to tell them in-app to update their address.
+    async fn on_email_undeliverable(&self, user: &User, failure: &DeliveryFailure) -> Result<()> {
+        Ok(())
+    }
+
+    /// Record a bounce or complaint reported by the mail provider: suppress the address, mark the
+    /// sent email it was about, and call [`AppContext::on_email_undeliverable`] for its user.
+    async fn record_delivery_failure(&self, failure: &DeliveryFailure) -> Result<()> {
+        tracing::warn!(
+            "email to {address} is undeliverable ({reason}), suppressing it",
+            address = failure.address,
+            reason = failure.reason
+        );
+
+        let mut conn = metrics::checkout(self.database()).await?;
+        let now = self.clock().now();
+        let detail = failure.detail.as_deref();
+
+        EmailSuppression::suppress(&failure.address, failure.reason, detail, now, &mut conn).await?;
+
+        if let Some(id) = &failure.provider_message_id {
+            let status = match failure.reason {
+                SuppressionReason::Bounce => SentEmailStatus::Bounced,
+                SuppressionReason::Complaint => SentEmailStatus::Complained,
+            };
+            SentEmail::mark_by_provider_message_id(id, status, detail, now, &mut conn).await?;
+        }
+
+        let user = match Email::find_by_address(&failure.address, &mut conn).await? {
+            Some(email) => Some(User::load(email.user_id, &mut conn).await?),
+            None => None,
+        };
+        drop(conn);
+
+        if let Some(user) = user {
+            self.on_email_undeliverable(&user, failure).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Retry the work of a `dead_letter`, deleting it. Lowboy retries `send_email` dead letters
+    /// and registered [`Job`]s, override this to retry the app's own.
+    ///
+    /// Email is sent straight away, and its dead letter is only deleted once it's sent. If it
+    /// fails again the dead letter is kept, unless the mailer recorded the failure as a new one,
+    /// as [`mailer::TrackedMailer`] does. Jobs are queued again, and deleted in the same
+    /// transaction.
+    async fn retry_dead_letter(&self, dead_letter: DeadLetter) -> Result<()> {
+        let registered = Jobs::global().contains(&dead_letter.job);
+        match dead_letter.job.as_str() {
+            mailer::SEND_EMAIL_JOB => {
+                let mail: Mail = dead_letter.payload().map_err(anyhow::Error::from)?;
+                let mailer = self
+                    .mailer()
+                    .ok_or_else(|| anyhow::anyhow!("there's no mailer to send the email with"))?;
+                let sent = mailer.send(&mail).await;
+
+                let mut conn = metrics::checkout(self.database()).await?;
+                if sent.is_ok() || dead_letter.superseded(&mut conn).await? {
+                    dead_letter.delete_record(&mut conn).await?;
+                }
+                sent?;
             }
+            job if registered => {
+                let job = job.to_string();
+                let now = self.clock().now();
+                let mut conn = metrics::checkout(self.database()).await?;
+                conn.transaction(|conn| {
+                    async move {
+                        QueuedJob::enqueue(&job, &dead_letter.payload, None, now, now, conn).await?;
+                        dead_letter.delete_record(conn).await
+                    }
+                    .scope_boxed()
+                })
+                .await?;
+            }
+            job => return Err(anyhow::anyhow!("don't know how to retry `{job}` jobs").into()),
         }
 
         Ok(())
     }
+
+    /// Create users in bulk, e.g. when importing them, returning a result for each user in the
+    /// same order. Unlike registration, [`AppContext::on_new_user`] isn't called so no verification
+    /// emails are sent.
+    async fn provision_users(&self, users: Vec<NewUser>) -> Result<Vec<ProvisionResult>> {
+        let mut conn = metrics::checkout(self.database()).await?;
+
+        Ok(provision::provision_users(
+            users,
+            self.clock(),
+            self.secret_generator(),
+            self.password_hasher(),
+            &mut conn,
+        )
+        .await?)
+    }
+
+    /// Record that `actor` performed `action`, optionally on `subject`, in the audit log. Override
+    /// this to send audit events somewhere else as well.
+    async fn audit(&self, actor: &RequestActor, action: &str, subject: Option<&str>) -> Result<()> {
+        let mut conn = metrics::checkout(self.database()).await?;
+        AuditLog::record(actor, action, subject, self.clock().now(), &mut conn).await?;
+
+        Ok(())
+    }
+
+    /// Called when a model is created, updated, deleted, published or restored. Invalidates the
+    /// cached fragments that depend on it.
+    async fn on_model_event(&self, event: &ModelEvent) -> Result<()> {
+        self.cache().invalidate_model(event);
+        Ok(())
+    }
+
+    /// Called by [`AppContext::change_password`] when `user`'s password was changed. Notifies them,
+    /// so they hear about changes they didn't make.
+    async fn on_password_changed(&self, user: &User) -> Result<()> {
+        self.send_security_notification(user, SecurityNotification::PasswordChanged)
+            .await
+    }
+
+    /// Set `user`'s password to `password`, returning the updated user. Password change and reset
+    /// flows should use this, so [`AppContext::on_password_changed`] is called.
+    ///
+    /// The user's sessions are tied to their password, so log them back in with the returned user.
+    async fn change_password(&self, user: &User, password: &str) -> Result<User> {
+        let hash = self
+            .password_hasher()
+            .hash(password.to_owned())
+            .await
+            .map_err(anyhow::Error::from)?;
+
+        let mut conn = metrics::checkout(self.database()).await?;
+        let mut user = user.clone();
+        user.password = Some(hash);
+        user.update_record().save(&mut conn).await?;
+        drop(conn);
+
+        self.on_password_changed(&user).await?;
+
+        Ok(user)
+    }
 }
 dyn_clone::clone_trait_object!(AppContext);
 
@@ -139,10 +424,10 @@ impl<T: AppContext + Clone> CloneableAppContext for T {}
 #[derive(Clone)]
 pub struct LowboyContext {
     pub database: Pool<SyncConnectionWrapper<SqliteConnection>>,
-    pub events: (Sender<Event>, Receiver<Event>),
+    pub events: Events,
     #[allow(dead_code)]
-    pub scheduler: JobScheduler,
-    pub mailer: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    pub scheduler: Scheduler,
+    pub mailer: Option<Box<dyn Mailer>>,
 }
 
 impl Context for LowboyContext {
@@ -154,12 +439,12 @@ impl Context for LowboyContext {
         &self.events
     }
 
-    fn scheduler(&self) -> &JobScheduler {
+    fn scheduler(&self) -> &Scheduler {
         &self.scheduler
     }
 
-    fn mailer(&self) -> Option<&AsyncSmtpTransport<Tokio1Executor>> {
-        self.mailer.as_ref()
+    fn mailer(&self) -> Option<&dyn Mailer> {
+        self.mailer.as_deref()
     }
 }
 
@@ -167,8 +452,8 @@ impl AppContext for LowboyContext {
     fn create(
         database: Pool<Connection>,
         events: Events,
-        scheduler: JobScheduler,
-        mailer: Option<AsyncSmtpTransport<Tokio1Executor>>,
+        scheduler: Scheduler,
+        mailer: Option<Box<dyn Mailer>>,
     ) -> Result<Self> {
         Ok(Self {
             database,
@@ -190,11 +475,11 @@ impl Context for () {
         unreachable!()
     }
 
-    fn scheduler(&self) -> &JobScheduler {
+    fn scheduler(&self) -> &Scheduler {
         unreachable!()
     }
 
-    fn mailer(&self) -> Option<&AsyncSmtpTransport<Tokio1Executor>> {
+    fn mailer(&self) -> Option<&dyn Mailer> {
         unreachable!()
     }
 }
@@ -203,8 +488,8 @@ impl AppContext for () {
     fn create(
         _database: Pool<Connection>,
         _events: Events,
-        _scheduler: JobScheduler,
-        _mailer: Option<AsyncSmtpTransport<Tokio1Executor>>,
+        _scheduler: Scheduler,
+        _mailer: Option<Box<dyn Mailer>>,
     ) -> Result<Self>
     where
         Self: Sized,
@@ -213,25 +498,53 @@ impl AppContext for () {
     }
 }
 
-pub async fn create_context<AC: AppContext>(config: &Config) -> Result<AC> {
+pub(crate) async fn create_context<AC: AppContext>(config: &Config) -> Result<AC> {
     diesel::connection::set_default_instrumentation(|| {
         Some(Box::new(diesel_tracing::TracingInstrumentation::new(true)))
     })?;
 
     let mut manager_config = ManagerConfig::default();
-    manager_config.custom_setup = Box::new(|url| {
-        async {
+    manager_config.recycling_method = match config.database_pool_recycling {
+        PoolRecycling::Fast => RecyclingMethod::Fast,
+        PoolRecycling::Verified => RecyclingMethod::Verified,
+    };
+    let statement_cache = if config.database_statement_cache {
+        CacheSize::Unbounded
+    } else {
+        CacheSize::Disabled
+    };
+    let warm_up = config.database_warm_up;
+    let pragmas = Pragmas::from_config(config);
+    let attach = config.database_attach.clone();
+    manager_config.custom_setup = Box::new(move |url| {
+        let pragmas = pragmas.clone();
+        let attach = attach.clone();
+        async move {
             let mut conn = SyncConnectionWrapper::<SqliteConnection>::establish(url)
                 .await
                 .map_err(Error::DieselConnection)?;
 
-            let query = "
-            PRAGMA journal_mode = WAL;
-            PRAGMA synchronous = NORMAL;
-            PRAGMA foreign_keys = ON;
-            PRAGMA busy_timeout = 30000;
-            ";
-            conn.batch_execute(query).await.map_err(Error::Diesel)?;
+            conn.batch_execute(&pragmas.to_sql())
+                .await
+                .map_err(Error::Diesel)?;
+            for database in &attach {
+                database::attach(&mut conn, database, &pragmas)
+                    .await
+                    .map_err(Error::Diesel)?;
+            }
+
+            conn.spawn_blocking(move |conn| {
+                conn.set_prepared_statement_cache_size(statement_cache);
+                Ok(())
+            })
+            .await
+            .map_err(Error::Diesel)?;
+
+            // Errors are ignored here, the tables may not exist yet if migrations haven't run.
+            if warm_up {
+                User::warm_up(&mut conn).await;
+                diesel_sqlite_session_store::warm_up(&mut conn).await;
+            }
 
             Ok(conn)
         }
@@ -246,23 +559,47 @@ pub async fn create_context<AC: AppContext>(config: &Config) -> Result<AC> {
 
     let database = Pool::builder(manager)
         .max_size(config.database_pool_size)
+        .wait_timeout(config.database_pool_wait_timeout.map(Duration::from_millis))
+        .create_timeout(config.database_pool_create_timeout.map(Duration::from_millis))
+        .recycle_timeout(config.database_pool_recycle_timeout.map(Duration::from_millis))
+        .runtime(deadpool::Runtime::Tokio1)
         .build()?;
 
-    let events = flume::bounded::<Event>(32);
-
-    let scheduler = JobScheduler::new().await?;
-    scheduler.start().await?;
-
-    let mailer: Option<AsyncSmtpTransport<Tokio1Executor>> = if let Some(conf) = &config.mailer {
-        Some(
-            AsyncSmtpTransport::<Tokio1Executor>::relay(&conf.smtp_relay)?
-                .credentials(Credentials::new(
-                    conf.smtp_username.to_string(),
-                    conf.smtp_password.to_string(),
-                ))
-                .build(),
-        )
-    } else {
+    *PUBLIC_URL.write().expect("lock should not be poisoned") = config.public_url();
+    if let Some(mailer) = &config.mailer {
+        *MAIL_FROM.write().expect("lock should not be poisoned") = mailer.from.clone();
+    }
+    PoolMetrics::global()
+        .set_slow_checkout_threshold(Duration::from_millis(config.database_pool_slow_checkout));
+    if let Some(parallelism) = config.password_hash_parallelism {
+        PasswordHasher::global().set_parallelism(parallelism);
+    }
+    Presence::global().set_limits(
+        config.events_max_connections,
+        (config.events_idle_timeout > 0).then(|| Duration::from_secs(config.events_idle_timeout)),
+    );
+
+    let events = Events::new(
+        config.events_capacity,
+        config.events_overflow,
+        Duration::from_millis(config.events_send_timeout),
+    );
+
+    let scheduler = Scheduler::start().await?;
+
+    #[cfg(feature = "mailer")]
+    let mailer = config
+        .mailer
+        .as_ref()
+        .map(|conf| mailer::create_mailer(conf, &database))
+        .transpose()?;
+    #[cfg(not(feature = "mailer"))]
+    let mailer = {
+        if config.mailer.is_some() {
+            tracing::warn!(
+                "ignoring the mailer config, lowboy was built without the `mailer` feature"
+            );
+        }
         None
     };
 
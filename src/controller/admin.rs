@@ -0,0 +1,1461 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{Extension, Form, Json, Router};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use diesel::result::OptionalExtension as _;
+use diesel::QueryResult;
+use diesel_async::scoped_futures::{ScopedBoxFuture, ScopedFutureExt};
+use diesel_async::AsyncConnection;
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+
+use crate::actor::{self, RequestActor};
+use crate::auth::{api_error, ApiRequest};
+use crate::context::CloneableAppContext;
+use crate::controller::announcements;
+use crate::error::LowboyError;
+use crate::extract::{DatabaseConnection, Flash};
+use crate::form::parse_datetime_local;
+use crate::jobs::ScheduledJobs;
+use crate::locale::RequestLocale;
+use crate::model::{
+    Announcement, AnnouncementLevel, DeadLetter, Model as _, Permission, QueuedJob, Role,
+    RoleError, User, UserModel as _,
+};
+use crate::settings::{SettingDefinition, SettingDefinitions};
+use crate::trash::{self, Trash};
+use crate::view::admin::{
+    AdminAnnouncements, AdminDeadLetters, AdminJobs, AdminRole, AdminRoles, AdminScheduledJob,
+    AdminServiceAccounts, AdminSetting, AdminSettings, AdminTrash,
+};
+use crate::{lowboy_view, AuthSession, Connection};
+
+/// The permission required to access the admin routes, see [`crate::model::LOWBOY_PERMISSIONS`].
+pub const ADMINISTER_SITE: &str = "administer site";
+
+/// Routes for administering roles, their permissions, and who they're assigned to, service
+/// accounts, dead letters, settings, and announcements.
+pub fn routes<AC: CloneableAppContext>() -> Router<AC> {
+    let pages = Router::new()
+        .route("/admin/roles", get(list_roles).post(create_role::<AC>))
+        .route("/admin/roles/:id", get(show_role))
+        .route("/admin/roles/:id/delete", post(delete_role::<AC>))
+        .route(
+            "/admin/roles/:id/permissions/:permission_id/attach",
+            post(attach_permission::<AC>),
+        )
+        .route(
+            "/admin/roles/:id/permissions/:permission_id/detach",
+            post(detach_permission::<AC>),
+        )
+        .route("/admin/roles/:id/users", post(assign_users::<AC>))
+        .route(
+            "/admin/roles/:id/users/:user_id/remove",
+            post(remove_user::<AC>),
+        )
+        .route(
+            "/admin/service-accounts",
+            get(list_service_accounts).post(create_service_account::<AC>),
+        )
+        .route(
+            "/admin/service-accounts/:id/rotate",
+            post(rotate_service_account::<AC>),
+        )
+        .route("/admin/jobs", get(list_jobs::<AC>))
+        .route("/admin/dead-letters", get(list_dead_letters))
+        .route("/admin/dead-letters/purge", post(purge_dead_letters::<AC>))
+        .route(
+            "/admin/dead-letters/:id/retry",
+            post(retry_dead_letter::<AC>),
+        )
+        .route(
+            "/admin/dead-letters/:id/purge",
+            post(purge_dead_letter::<AC>),
+        )
+        .route("/admin/settings", get(list_settings::<AC>))
+        .route("/admin/settings/:key", post(update_setting::<AC>))
+        .route("/admin/settings/:key/reset", post(reset_setting::<AC>))
+        .route(
+            "/admin/announcements",
+            get(list_announcements::<AC>).post(create_announcement::<AC>),
+        )
+        .route("/admin/announcements/:id/end", post(end_announcement::<AC>))
+        .route(
+            "/admin/announcements/:id/delete",
+            post(delete_announcement::<AC>),
+        )
+        .route("/admin/trash", get(list_trash))
+        .route(
+            "/admin/trash/:model/:id/restore",
+            post(restore_trashed::<AC>),
+        )
+        .route("/admin/trash/:model/:id/delete", post(delete_trashed::<AC>))
+        .route("/admin/users/:id/impersonate", post(impersonate_user::<AC>));
+
+    let api = Router::new()
+        .route(
+            "/api/admin/roles",
+            get(api::list_roles).post(api::create_role::<AC>),
+        )
+        .route("/api/admin/roles/:id", delete(api::delete_role::<AC>))
+        .route(
+            "/api/admin/roles/:id/permissions/:permission_id",
+            put(api::attach_permission::<AC>).delete(api::detach_permission::<AC>),
+        )
+        .route("/api/admin/roles/:id/users", post(api::assign_users::<AC>))
+        .route(
+            "/api/admin/roles/:id/users/:user_id",
+            delete(api::remove_user::<AC>),
+        )
+        .route(
+            "/api/admin/service-accounts",
+            post(api::create_service_account::<AC>),
+        )
+        .route("/api/admin/jobs", get(api::list_jobs::<AC>))
+        .route("/api/admin/dead-letters", get(api::list_dead_letters))
+        .route(
+            "/api/admin/dead-letters/:id",
+            delete(api::purge_dead_letter::<AC>),
+        )
+        .route(
+            "/api/admin/dead-letters/:id/retry",
+            post(api::retry_dead_letter::<AC>),
+        )
+        .route("/api/admin/settings", get(api::list_settings::<AC>))
+        .route(
+            "/api/admin/settings/:key",
+            put(api::update_setting::<AC>).delete(api::reset_setting::<AC>),
+        )
+        .route(
+            "/api/admin/announcements",
+            get(api::list_announcements).post(api::create_announcement::<AC>),
+        )
+        .route(
+            "/api/admin/announcements/:id",
+            delete(api::delete_announcement::<AC>),
+        )
+        .route(
+            "/api/admin/announcements/:id/end",
+            post(api::end_announcement::<AC>),
+        )
+        .route("/api/admin/trash", get(api::list_trash))
+        .route(
+            "/api/admin/trash/:model/:id",
+            delete(api::delete_trashed::<AC>),
+        )
+        .route(
+            "/api/admin/trash/:model/:id/restore",
+            post(api::restore_trashed::<AC>),
+        )
+        .layer(Extension(ApiRequest));
+
+    pages
+        .merge(api)
+        .route_layer(crate::permission_required!(ADMINISTER_SITE))
+        // The impersonated user usually can't administer the site, but has to be able to stop.
+        .route(
+            "/admin/impersonation/stop",
+            post(stop_impersonating::<AC>),
+        )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleForm {
+    name: String,
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceAccountForm {
+    username: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignUsersForm {
+    /// Usernames separated by commas or whitespace.
+    usernames: String,
+}
+
+async fn load_role(id: i32, conn: &mut Connection) -> Result<Role, LowboyError> {
+    Role::load(id, conn)
+        .await
+        .optional()?
+        .ok_or(LowboyError::NotFound)
+}
+
+async fn load_announcement(id: i32, conn: &mut Connection) -> Result<Announcement, LowboyError> {
+    Announcement::load(id, conn)
+        .await
+        .optional()?
+        .ok_or(LowboyError::NotFound)
+}
+
+async fn load_dead_letter(id: i32, conn: &mut Connection) -> Result<DeadLetter, LowboyError> {
+    DeadLetter::load(id, conn)
+        .await
+        .optional()?
+        .ok_or(LowboyError::NotFound)
+}
+
+/// How many upcoming runs of each scheduled job are listed.
+const NEXT_RUNS: usize = 5;
+
+/// The scheduled jobs, with their next runs.
+fn scheduled_jobs(now: DateTime<Utc>) -> Vec<AdminScheduledJob> {
+    ScheduledJobs::global()
+        .list()
+        .iter()
+        .map(|job| AdminScheduledJob {
+            name: job.name.clone(),
+            schedule: job.schedule.clone(),
+            next_runs: job.next_runs(NEXT_RUNS, now),
+        })
+        .collect()
+}
+
+/// The audit log subject for a dead letter.
+fn dead_letter_subject(dead_letter: &DeadLetter) -> String {
+    format!("{}({})", dead_letter.job, dead_letter.id)
+}
+
+async fn load_permission(id: i32, conn: &mut Connection) -> Result<Permission, LowboyError> {
+    Permission::load(id, conn)
+        .await
+        .optional()?
+        .ok_or(LowboyError::NotFound)
+}
+
+/// Apply `change`, unless it would take away the `actor`'s own ability to administer the site.
+///
+/// The change is made in a transaction and rolled back if the actor loses access, in which case
+/// `Ok(false)` is returned.
+async fn without_lockout<'a, F>(
+    actor: &User,
+    conn: &mut Connection,
+    change: F,
+) -> Result<bool, LowboyError>
+where
+    F: for<'r> FnOnce(&'r mut Connection) -> ScopedBoxFuture<'a, 'r, QueryResult<()>> + Send + 'a,
+{
+    let actor_id = actor.id;
+    let result = conn
+        .transaction(|conn| {
+            async move {
+                change(conn).await?;
+
+                let mut actor = User::load(actor_id, conn).await?;
+                actor.with_roles_and_permissions(conn).await?;
+
+                if actor.has_permission(ADMINISTER_SITE) {
+                    Ok(())
+                } else {
+                    Err(diesel::result::Error::RollbackTransaction)
+                }
+            }
+            .scope_boxed()
+        })
+        .await;
+
+    match result {
+        Ok(()) => Ok(true),
+        Err(diesel::result::Error::RollbackTransaction) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Remove a role as part of a [`without_lockout`] change. System roles must be ruled out first.
+async fn remove_role(role: Role, conn: &mut Connection) -> QueryResult<()> {
+    match role.remove(conn).await {
+        Ok(_) => Ok(()),
+        Err(RoleError::Query(e)) => Err(e),
+        Err(e) => unreachable!("{e}"),
+    }
+}
+
+const LOCKOUT_MESSAGE: &str = "You can't remove your own access to administer the site";
+
+fn role_path(role_id: i32) -> String {
+    format!("/admin/roles/{role_id}")
+}
+
+/// The audit log subject for a change to a role's permissions.
+fn role_permission(role: &Role, permission: &Permission) -> String {
+    format!("{}: {}", role.name, permission.name)
+}
+
+/// The audit log subject for a change to who a role is assigned to.
+fn role_user(role: &Role, user_id: i32) -> String {
+    format!("{}: user({user_id})", role.name)
+}
+
+fn usernames(input: &str) -> Vec<&str> {
+    input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|username| !username.is_empty())
+        .collect()
+}
+
+pub async fn list_roles(
+    DatabaseConnection(mut conn): DatabaseConnection,
+) -> Result<impl IntoResponse, LowboyError> {
+    let roles = Role::list(&mut conn).await?;
+
+    Ok(lowboy_view!(AdminRoles { roles }, {
+        "title" => "Roles",
+    }))
+}
+
+pub async fn create_role<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    messages: Flash,
+    Form(input): Form<RoleForm>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let name = input.name.trim();
+    if name.is_empty() {
+        messages.error("A role name is required");
+        return Ok(Redirect::to("/admin/roles"));
+    }
+
+    if Role::find_by_name(name, &mut conn).await?.is_some() {
+        messages.error(format!("The role `{name}` already exists"));
+        return Ok(Redirect::to("/admin/roles"));
+    }
+
+    let mut record = Role::create_record(name);
+    if let Some(description) = input.description.as_deref().filter(|d| !d.trim().is_empty()) {
+        record = record.with_description(description.trim());
+    }
+    let role = record.save(&mut conn).await?;
+    context
+        .audit(&request_actor, "role.create", Some(&role.name))
+        .await?;
+
+    messages.success(format!("The role `{}` has been created.", role.name));
+
+    Ok(Redirect::to(&role_path(role.id)))
+}
+
+pub async fn show_role(
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let role = load_role(id, &mut conn).await?;
+    let granted = role.permissions(&mut conn).await?;
+    let permissions = Permission::list(&mut conn)
+        .await?
+        .into_iter()
+        .map(|permission| {
+            let is_granted = granted.contains(&permission);
+            (permission, is_granted)
+        })
+        .collect();
+    let users = role.users(&mut conn).await?;
+
+    Ok(lowboy_view!(AdminRole { role, permissions, users }, {
+        "title" => "Role",
+    }))
+}
+
+pub async fn delete_role<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    AuthSession { user, .. }: AuthSession,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    messages: Flash,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let actor = user.ok_or(LowboyError::Unauthorized)?;
+    let role = load_role(id, &mut conn).await?;
+
+    if role.system {
+        messages.error(RoleError::System(role.name).to_string());
+        return Ok(Redirect::to(&role_path(id)));
+    }
+
+    let name = role.name.clone();
+    let removed =
+        without_lockout(&actor, &mut conn, |conn| remove_role(role, conn).scope_boxed()).await?;
+
+    if !removed {
+        messages.error(LOCKOUT_MESSAGE);
+        return Ok(Redirect::to(&role_path(id)));
+    }
+
+    context
+        .audit(&request_actor, "role.delete", Some(&name))
+        .await?;
+
+    messages.success(format!("The role `{name}` has been deleted."));
+
+    Ok(Redirect::to("/admin/roles"))
+}
+
+pub async fn attach_permission<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path((id, permission_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let role = load_role(id, &mut conn).await?;
+    let permission = load_permission(permission_id, &mut conn).await?;
+
+    role.attach(&permission, &mut conn).await?;
+    context
+        .audit(
+            &request_actor,
+            "role.attach_permission",
+            Some(&role_permission(&role, &permission)),
+        )
+        .await?;
+
+    Ok(Redirect::to(&role_path(id)))
+}
+
+pub async fn detach_permission<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    AuthSession { user, .. }: AuthSession,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    messages: Flash,
+    Path((id, permission_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let actor = user.ok_or(LowboyError::Unauthorized)?;
+    let role = load_role(id, &mut conn).await?;
+    let permission = load_permission(permission_id, &mut conn).await?;
+    let subject = role_permission(&role, &permission);
+
+    let detached = without_lockout(&actor, &mut conn, |conn| {
+        async move {
+            role.detach(&permission, conn).await?;
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await?;
+
+    if detached {
+        context
+            .audit(&request_actor, "role.detach_permission", Some(&subject))
+            .await?;
+    } else {
+        messages.error(LOCKOUT_MESSAGE);
+    }
+
+    Ok(Redirect::to(&role_path(id)))
+}
+
+pub async fn assign_users<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    messages: Flash,
+    Path(id): Path<i32>,
+    Form(input): Form<AssignUsersForm>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let role = load_role(id, &mut conn).await?;
+    let usernames = usernames(&input.usernames);
+    let user_ids = User::find_ids_by_username(&usernames, &mut conn).await?;
+
+    if user_ids.len() < usernames.len() {
+        messages.warning(format!(
+            "{} of the usernames didn't match a user.",
+            usernames.len() - user_ids.len()
+        ));
+    }
+
+    let assigned = role.assign_many(&user_ids, &mut conn).await?;
+    context
+        .audit(
+            &request_actor,
+            "role.assign",
+            Some(&format!("{}: {}", role.name, usernames.join(", "))),
+        )
+        .await?;
+
+    messages.success(format!("Assigned `{}` to {assigned} user(s).", role.name));
+
+    Ok(Redirect::to(&role_path(id)))
+}
+
+pub async fn remove_user<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    AuthSession { user, .. }: AuthSession,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    messages: Flash,
+    Path((id, user_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let actor = user.ok_or(LowboyError::Unauthorized)?;
+    let role = load_role(id, &mut conn).await?;
+    let subject = role_user(&role, user_id);
+
+    let removed = without_lockout(&actor, &mut conn, |conn| {
+        async move {
+            role.unassign(user_id, conn).await?;
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await?;
+
+    if removed {
+        context
+            .audit(&request_actor, "role.unassign", Some(&subject))
+            .await?;
+    } else {
+        messages.error(LOCKOUT_MESSAGE);
+    }
+
+    Ok(Redirect::to(&role_path(id)))
+}
+
+pub async fn impersonate_user<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    mut auth_session: AuthSession,
+    session: Session,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, LowboyError> {
+    if request_actor.is_impersonating() || request_actor.real_user_id == Some(id) {
+        return Err(LowboyError::BadRequest);
+    }
+
+    let target = User::load(id, &mut conn)
+        .await
+        .optional()?
+        .ok_or(LowboyError::NotFound)?
+        .with_roles_and_permissions(&mut conn)
+        .await?
+        .to_owned();
+    drop(conn);
+
+    actor::impersonate(&context, &mut auth_session, &session, &request_actor, &target).await?;
+
+    Ok(Redirect::to("/"))
+}
+
+pub async fn stop_impersonating<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    mut auth_session: AuthSession,
+    session: Session,
+) -> Result<impl IntoResponse, LowboyError> {
+    actor::stop_impersonating(&context, &mut auth_session, &session, &request_actor).await?;
+
+    Ok(Redirect::to("/admin/roles"))
+}
+
+pub async fn list_service_accounts(
+    DatabaseConnection(mut conn): DatabaseConnection,
+) -> Result<impl IntoResponse, LowboyError> {
+    let service_accounts = User::list_service_accounts(&mut conn).await?;
+
+    Ok(lowboy_view!(AdminServiceAccounts { service_accounts, token: None }, {
+        "title" => "Service Accounts",
+    }))
+}
+
+/// Create a service account, showing its API token. The token isn't shown again after this.
+pub async fn create_service_account<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    messages: Flash,
+    Form(input): Form<ServiceAccountForm>,
+) -> Result<Response, LowboyError> {
+    let username = input.username.trim();
+    if username.is_empty() {
+        messages.error("A username is required");
+        return Ok(Redirect::to("/admin/service-accounts").into_response());
+    }
+
+    if User::find_by_username(username, &mut conn).await?.is_some() {
+        messages.error(format!("The username `{username}` is taken"));
+        return Ok(Redirect::to("/admin/service-accounts").into_response());
+    }
+
+    let (user, token) =
+        User::new_service_account(username, context.secret_generator(), &mut conn).await?;
+    context
+        .audit(&request_actor, "service_account.create", Some(&user.username))
+        .await?;
+    let service_accounts = User::list_service_accounts(&mut conn).await?;
+
+    Ok(lowboy_view!(AdminServiceAccounts {
+        service_accounts,
+        token: Some((user.username, token)),
+    }, {
+        "title" => "Service Accounts",
+    })
+    .into_response())
+}
+
+/// Replace a service account's API token, revoking the old one.
+pub async fn rotate_service_account<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+) -> Result<Response, LowboyError> {
+    let mut user = User::load(id, &mut conn)
+        .await
+        .optional()?
+        .filter(|user| user.service_account)
+        .ok_or(LowboyError::NotFound)?;
+
+    let token = user
+        .rotate_api_token(context.secret_generator(), &mut conn)
+        .await?;
+    context
+        .audit(&request_actor, "service_account.rotate_token", Some(&user.username))
+        .await?;
+    let service_accounts = User::list_service_accounts(&mut conn).await?;
+
+    Ok(lowboy_view!(AdminServiceAccounts {
+        service_accounts,
+        token: Some((user.username, token)),
+    }, {
+        "title" => "Service Accounts",
+    })
+    .into_response())
+}
+
+pub async fn list_jobs<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    DatabaseConnection(mut conn): DatabaseConnection,
+) -> Result<impl IntoResponse, LowboyError> {
+    let scheduled = scheduled_jobs(context.clock().now());
+    let queued = QueuedJob::list(&mut conn, None).await?;
+
+    Ok(lowboy_view!(AdminJobs { scheduled, queued }, {
+        "title" => "Jobs",
+    }))
+}
+
+pub async fn list_dead_letters(
+    DatabaseConnection(mut conn): DatabaseConnection,
+) -> Result<impl IntoResponse, LowboyError> {
+    let dead_letters = DeadLetter::list(&mut conn, None).await?;
+
+    Ok(lowboy_view!(AdminDeadLetters { dead_letters }, {
+        "title" => "Dead Letters",
+    }))
+}
+
+/// Retry the work of a dead letter. If it fails again, it's recorded as a new dead letter.
+pub async fn retry_dead_letter<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    messages: Flash,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let dead_letter = load_dead_letter(id, &mut conn).await?;
+    let subject = dead_letter_subject(&dead_letter);
+    drop(conn);
+
+    context
+        .audit(&request_actor, "dead_letter.retry", Some(&subject))
+        .await?;
+    match context.retry_dead_letter(dead_letter).await {
+        Ok(()) => messages.success(format!("Retried `{subject}`.")),
+        Err(e) => messages.error(format!("Retrying `{subject}` failed: {e}")),
+    };
+
+    Ok(Redirect::to("/admin/dead-letters"))
+}
+
+pub async fn purge_dead_letter<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    messages: Flash,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let dead_letter = load_dead_letter(id, &mut conn).await?;
+    let subject = dead_letter_subject(&dead_letter);
+
+    dead_letter.delete_record(&mut conn).await?;
+    context
+        .audit(&request_actor, "dead_letter.purge", Some(&subject))
+        .await?;
+
+    messages.success(format!("Purged `{subject}`."));
+
+    Ok(Redirect::to("/admin/dead-letters"))
+}
+
+pub async fn purge_dead_letters<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    messages: Flash,
+) -> Result<impl IntoResponse, LowboyError> {
+    let purged = DeadLetter::purge_all(&mut conn).await?;
+    context
+        .audit(&request_actor, "dead_letter.purge_all", None)
+        .await?;
+
+    messages.success(format!("Purged {purged} dead letter(s)."));
+
+    Ok(Redirect::to("/admin/dead-letters"))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SettingForm {
+    value: String,
+}
+
+/// The registered settings, with their current values.
+async fn admin_settings<AC: CloneableAppContext>(
+    context: &AC,
+) -> Result<Vec<AdminSetting>, LowboyError> {
+    // Copied out, so the registry isn't locked while loading the values.
+    let definitions: Vec<SettingDefinition> =
+        SettingDefinitions::global().iter().copied().collect();
+    let settings = context.settings();
+
+    let mut admin_settings = Vec::with_capacity(definitions.len());
+    for definition in definitions {
+        let value = settings.raw(definition.key).await?;
+        admin_settings.push(AdminSetting {
+            key: definition.key,
+            label: definition.label,
+            description: definition.description,
+            value: definition.display(value.as_deref()),
+            is_bool: definition.is_bool(),
+            is_set: value.is_some(),
+        });
+    }
+
+    Ok(admin_settings)
+}
+
+fn load_setting_definition(key: &str) -> Result<SettingDefinition, LowboyError> {
+    SettingDefinitions::global()
+        .get(key)
+        .ok_or(LowboyError::NotFound)
+}
+
+pub async fn list_settings<AC: CloneableAppContext>(
+    State(context): State<AC>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let settings = admin_settings(&context).await?;
+
+    Ok(lowboy_view!(AdminSettings { settings }, {
+        "title" => "Settings",
+    }))
+}
+
+pub async fn update_setting<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    messages: Flash,
+    Path(key): Path<String>,
+    Form(input): Form<SettingForm>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let definition = load_setting_definition(&key)?;
+
+    match definition.parse(&input.value) {
+        Ok(value) => {
+            context.settings().set_raw(definition.key, &value).await?;
+            context
+                .audit(&request_actor, "setting.update", Some(definition.key))
+                .await?;
+            messages.success(format!("Saved {}.", definition.label));
+        }
+        Err(e) => {
+            messages.error(format!("{} is invalid: {e}", definition.label));
+        }
+    }
+
+    Ok(Redirect::to("/admin/settings"))
+}
+
+pub async fn reset_setting<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    messages: Flash,
+    Path(key): Path<String>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let definition = load_setting_definition(&key)?;
+
+    context.settings().reset_raw(definition.key).await?;
+    context
+        .audit(&request_actor, "setting.reset", Some(definition.key))
+        .await?;
+
+    messages.success(format!("Reset {}.", definition.label));
+
+    Ok(Redirect::to("/admin/settings"))
+}
+
+const ANNOUNCEMENTS_PATH: &str = "/admin/announcements";
+
+#[derive(Debug, Deserialize)]
+pub struct AnnouncementForm {
+    message: String,
+    level: AnnouncementLevel,
+    /// `datetime-local` inputs, in the admin's timezone. Without a start the announcement starts
+    /// now, and without an end it's shown until it's ended.
+    starts_at: Option<String>,
+    ends_at: Option<String>,
+    /// The role the announcement is shown to, or empty for everyone.
+    role_id: Option<String>,
+}
+
+/// The audit log subject for an announcement.
+fn announcement_subject(announcement: &Announcement) -> String {
+    format!("announcement({})", announcement.id)
+}
+
+/// Parse an optional `datetime-local` field, or `None` if it isn't a valid time.
+fn datetime_field(input: Option<&str>, timezone: Tz) -> Option<Option<DateTime<Utc>>> {
+    match input.map(str::trim).filter(|input| !input.is_empty()) {
+        Some(input) => parse_datetime_local(input, timezone).map(Some),
+        None => Some(None),
+    }
+}
+
+pub async fn list_announcements<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    DatabaseConnection(mut conn): DatabaseConnection,
+) -> Result<impl IntoResponse, LowboyError> {
+    let announcements = Announcement::list(&mut conn, None).await?;
+    let roles = Role::list(&mut conn).await?;
+
+    Ok(lowboy_view!(AdminAnnouncements {
+        announcements,
+        roles,
+        now: context.clock().now(),
+    }, {
+        "title" => "Announcements",
+    }))
+}
+
+/// Create an announcement, telling connected clients about it if it's active already.
+pub async fn create_announcement<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    RequestLocale { timezone, .. }: RequestLocale,
+    messages: Flash,
+    Form(input): Form<AnnouncementForm>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let message = input.message.trim();
+    if message.is_empty() {
+        messages.error("A message is required");
+        return Ok(Redirect::to(ANNOUNCEMENTS_PATH));
+    }
+
+    let (Some(starts_at), Some(ends_at)) = (
+        datetime_field(input.starts_at.as_deref(), timezone),
+        datetime_field(input.ends_at.as_deref(), timezone),
+    ) else {
+        messages.error("The start and end must be valid times");
+        return Ok(Redirect::to(ANNOUNCEMENTS_PATH));
+    };
+    let now = context.clock().now();
+    let starts_at = starts_at.unwrap_or(now);
+    if ends_at.is_some_and(|ends_at| ends_at <= starts_at) {
+        messages.error("An announcement must end after it starts");
+        return Ok(Redirect::to(ANNOUNCEMENTS_PATH));
+    }
+
+    let role_id = match input.role_id.as_deref().filter(|id| !id.is_empty()) {
+        Some(id) => {
+            let id = id.parse().map_err(|_| LowboyError::BadRequest)?;
+            Some(load_role(id, &mut conn).await?.id)
+        }
+        None => None,
+    };
+
+    let announcement = Announcement::from(
+        Announcement::create_record(message, input.level, starts_at, now)
+            .with_ends_at(ends_at)
+            .with_role_id(role_id)
+            .save(&mut conn)
+            .await?,
+    );
+    context
+        .audit(
+            &request_actor,
+            "announcement.create",
+            Some(&announcement_subject(&announcement)),
+        )
+        .await?;
+    announcements::push(context.events(), now, &mut conn).await?;
+
+    messages.success("The announcement has been created.");
+
+    Ok(Redirect::to(ANNOUNCEMENTS_PATH))
+}
+
+/// Stop showing an announcement.
+pub async fn end_announcement<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    messages: Flash,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let mut announcement = load_announcement(id, &mut conn).await?;
+
+    announcement.end(context.clock().now(), &mut conn).await?;
+    context
+        .audit(
+            &request_actor,
+            "announcement.end",
+            Some(&announcement_subject(&announcement)),
+        )
+        .await?;
+
+    messages.success("The announcement has been ended.");
+
+    Ok(Redirect::to(ANNOUNCEMENTS_PATH))
+}
+
+pub async fn delete_announcement<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    messages: Flash,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let announcement = load_announcement(id, &mut conn).await?;
+    let subject = announcement_subject(&announcement);
+
+    announcement.delete_record(&mut conn).await?;
+    context
+        .audit(&request_actor, "announcement.delete", Some(&subject))
+        .await?;
+
+    messages.success("The announcement has been deleted.");
+
+    Ok(Redirect::to(ANNOUNCEMENTS_PATH))
+}
+
+const TRASH_PATH: &str = "/admin/trash";
+
+/// The audit log subject for a trashed model, erroring if its model isn't kept in the trash.
+fn trashed_subject(model: &str, id: i32) -> Result<String, LowboyError> {
+    if Trash::global().retention(model).is_none() {
+        return Err(LowboyError::NotFound);
+    }
+
+    Ok(format!("{model}({id})"))
+}
+
+pub async fn list_trash(
+    DatabaseConnection(mut conn): DatabaseConnection,
+) -> Result<impl IntoResponse, LowboyError> {
+    let items = trash::list(&mut conn).await?;
+
+    Ok(lowboy_view!(AdminTrash { items }, {
+        "title" => "Trash",
+    }))
+}
+
+/// Restore a trashed model, if it hasn't expired.
+pub async fn restore_trashed<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    messages: Flash,
+    Path((model, id)): Path<(String, i32)>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let subject = trashed_subject(&model, id)?;
+
+    if trash::restore(&context, &model, id).await? {
+        context
+            .audit(&request_actor, "trash.restore", Some(&subject))
+            .await?;
+        messages.success(format!("Restored `{subject}`."));
+    } else {
+        messages.error(format!("`{subject}` is no longer in the trash."));
+    }
+
+    Ok(Redirect::to(TRASH_PATH))
+}
+
+/// Remove a trashed model for good, without waiting for it to expire.
+pub async fn delete_trashed<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    request_actor: RequestActor,
+    messages: Flash,
+    Path((model, id)): Path<(String, i32)>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let subject = trashed_subject(&model, id)?;
+
+    if trash::delete(&context, &model, id).await? {
+        context
+            .audit(&request_actor, "trash.delete", Some(&subject))
+            .await?;
+        messages.success(format!("Deleted `{subject}` for good."));
+    } else {
+        messages.error(format!("`{subject}` is no longer in the trash."));
+    }
+
+    Ok(Redirect::to(TRASH_PATH))
+}
+
+/// JSON equivalents of the admin pages, for building custom admin interfaces.
+mod api {
+    use super::*;
+
+    #[derive(Debug, Serialize)]
+    pub struct RoleWithPermissions {
+        #[serde(flatten)]
+        role: Role,
+        permissions: Vec<Permission>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct AssignUsers {
+        user_ids: Vec<i32>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct CreatedServiceAccount {
+        id: i32,
+        username: String,
+        token: String,
+    }
+
+    fn lockout() -> Response {
+        api_error(StatusCode::CONFLICT)
+    }
+
+    pub async fn list_roles(
+        DatabaseConnection(mut conn): DatabaseConnection,
+    ) -> Result<impl IntoResponse, LowboyError> {
+        let mut roles = vec![];
+        for role in Role::list(&mut conn).await? {
+            let permissions = role.permissions(&mut conn).await?;
+            roles.push(RoleWithPermissions { role, permissions });
+        }
+
+        Ok(Json(roles))
+    }
+
+    pub async fn create_role<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        DatabaseConnection(mut conn): DatabaseConnection,
+        Json(input): Json<RoleForm>,
+    ) -> Result<Response, LowboyError> {
+        let name = input.name.trim();
+        if name.is_empty() || Role::find_by_name(name, &mut conn).await?.is_some() {
+            return Ok(api_error(StatusCode::UNPROCESSABLE_ENTITY));
+        }
+
+        let mut record = Role::create_record(name);
+        if let Some(ref description) = input.description {
+            record = record.with_description(description);
+        }
+        let role = Role::from(record.save(&mut conn).await?);
+        context
+            .audit(&request_actor, "role.create", Some(&role.name))
+            .await?;
+
+        Ok((StatusCode::CREATED, Json(role)).into_response())
+    }
+
+    pub async fn delete_role<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        AuthSession { user, .. }: AuthSession,
+        DatabaseConnection(mut conn): DatabaseConnection,
+        Path(id): Path<i32>,
+    ) -> Result<Response, LowboyError> {
+        let actor = user.ok_or(LowboyError::Unauthorized)?;
+        let role = load_role(id, &mut conn).await?;
+
+        if role.system {
+            return Ok(api_error(StatusCode::UNPROCESSABLE_ENTITY));
+        }
+
+        let name = role.name.clone();
+        let removed =
+            without_lockout(&actor, &mut conn, |conn| remove_role(role, conn).scope_boxed())
+                .await?;
+
+        Ok(if removed {
+            context
+                .audit(&request_actor, "role.delete", Some(&name))
+                .await?;
+            StatusCode::NO_CONTENT.into_response()
+        } else {
+            lockout()
+        })
+    }
+
+    pub async fn attach_permission<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        DatabaseConnection(mut conn): DatabaseConnection,
+        Path((id, permission_id)): Path<(i32, i32)>,
+    ) -> Result<Response, LowboyError> {
+        let role = load_role(id, &mut conn).await?;
+        let permission = load_permission(permission_id, &mut conn).await?;
+
+        role.attach(&permission, &mut conn).await?;
+        context
+            .audit(
+                &request_actor,
+                "role.attach_permission",
+                Some(&role_permission(&role, &permission)),
+            )
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    pub async fn detach_permission<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        AuthSession { user, .. }: AuthSession,
+        DatabaseConnection(mut conn): DatabaseConnection,
+        Path((id, permission_id)): Path<(i32, i32)>,
+    ) -> Result<Response, LowboyError> {
+        let actor = user.ok_or(LowboyError::Unauthorized)?;
+        let role = load_role(id, &mut conn).await?;
+        let permission = load_permission(permission_id, &mut conn).await?;
+        let subject = role_permission(&role, &permission);
+
+        let detached = without_lockout(&actor, &mut conn, |conn| {
+            async move {
+                role.detach(&permission, conn).await?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+        Ok(if detached {
+            context
+                .audit(&request_actor, "role.detach_permission", Some(&subject))
+                .await?;
+            StatusCode::NO_CONTENT.into_response()
+        } else {
+            lockout()
+        })
+    }
+
+    pub async fn assign_users<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        DatabaseConnection(mut conn): DatabaseConnection,
+        Path(id): Path<i32>,
+        Json(input): Json<AssignUsers>,
+    ) -> Result<Response, LowboyError> {
+        let role = load_role(id, &mut conn).await?;
+        let assigned = role.assign_many(&input.user_ids, &mut conn).await?;
+        for user_id in &input.user_ids {
+            context
+                .audit(&request_actor, "role.assign", Some(&role_user(&role, *user_id)))
+                .await?;
+        }
+
+        Ok(Json(serde_json::json!({ "assigned": assigned })).into_response())
+    }
+
+    pub async fn remove_user<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        AuthSession { user, .. }: AuthSession,
+        DatabaseConnection(mut conn): DatabaseConnection,
+        Path((id, user_id)): Path<(i32, i32)>,
+    ) -> Result<Response, LowboyError> {
+        let actor = user.ok_or(LowboyError::Unauthorized)?;
+        let role = load_role(id, &mut conn).await?;
+        let subject = role_user(&role, user_id);
+
+        let removed = without_lockout(&actor, &mut conn, |conn| {
+            async move {
+                role.unassign(user_id, conn).await?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+        Ok(if removed {
+            context
+                .audit(&request_actor, "role.unassign", Some(&subject))
+                .await?;
+            StatusCode::NO_CONTENT.into_response()
+        } else {
+            lockout()
+        })
+    }
+
+    pub async fn create_service_account<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        DatabaseConnection(mut conn): DatabaseConnection,
+        Json(input): Json<ServiceAccountForm>,
+    ) -> Result<Response, LowboyError> {
+        let username = input.username.trim();
+        if username.is_empty() || User::find_by_username(username, &mut conn).await?.is_some() {
+            return Ok(api_error(StatusCode::UNPROCESSABLE_ENTITY));
+        }
+
+        let (user, token) =
+            User::new_service_account(username, context.secret_generator(), &mut conn).await?;
+        context
+            .audit(&request_actor, "service_account.create", Some(&user.username))
+            .await?;
+
+        Ok((
+            StatusCode::CREATED,
+            Json(CreatedServiceAccount {
+                id: user.id,
+                username: user.username,
+                token,
+            }),
+        )
+            .into_response())
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct Jobs {
+        scheduled: Vec<AdminScheduledJob>,
+        queued: Vec<QueuedJob>,
+    }
+
+    pub async fn list_jobs<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        DatabaseConnection(mut conn): DatabaseConnection,
+    ) -> Result<impl IntoResponse, LowboyError> {
+        Ok(Json(Jobs {
+            scheduled: scheduled_jobs(context.clock().now()),
+            queued: QueuedJob::list(&mut conn, None).await?,
+        }))
+    }
+
+    pub async fn list_dead_letters(
+        DatabaseConnection(mut conn): DatabaseConnection,
+    ) -> Result<impl IntoResponse, LowboyError> {
+        Ok(Json(DeadLetter::list(&mut conn, None).await?))
+    }
+
+    pub async fn retry_dead_letter<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        DatabaseConnection(mut conn): DatabaseConnection,
+        Path(id): Path<i32>,
+    ) -> Result<Response, LowboyError> {
+        let dead_letter = load_dead_letter(id, &mut conn).await?;
+        let subject = dead_letter_subject(&dead_letter);
+        drop(conn);
+
+        context
+            .audit(&request_actor, "dead_letter.retry", Some(&subject))
+            .await?;
+
+        Ok(match context.retry_dead_letter(dead_letter).await {
+            Ok(()) => StatusCode::NO_CONTENT.into_response(),
+            Err(_) => api_error(StatusCode::BAD_GATEWAY),
+        })
+    }
+
+    pub async fn purge_dead_letter<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        DatabaseConnection(mut conn): DatabaseConnection,
+        Path(id): Path<i32>,
+    ) -> Result<Response, LowboyError> {
+        let dead_letter = load_dead_letter(id, &mut conn).await?;
+        let subject = dead_letter_subject(&dead_letter);
+
+        dead_letter.delete_record(&mut conn).await?;
+        context
+            .audit(&request_actor, "dead_letter.purge", Some(&subject))
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    pub async fn list_settings<AC: CloneableAppContext>(
+        State(context): State<AC>,
+    ) -> Result<impl IntoResponse, LowboyError> {
+        Ok(Json(admin_settings(&context).await?))
+    }
+
+    /// Set a setting to the JSON body. Strings are parsed like the admin form's input, e.g. a
+    /// boolean setting accepts `true` or `"true"`.
+    pub async fn update_setting<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        Path(key): Path<String>,
+        Json(input): Json<serde_json::Value>,
+    ) -> Result<Response, LowboyError> {
+        let definition = load_setting_definition(&key)?;
+        let input = match input {
+            serde_json::Value::String(input) => input,
+            input => input.to_string(),
+        };
+        let Ok(value) = definition.parse(&input) else {
+            return Ok(api_error(StatusCode::UNPROCESSABLE_ENTITY));
+        };
+
+        context.settings().set_raw(definition.key, &value).await?;
+        context
+            .audit(&request_actor, "setting.update", Some(definition.key))
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    pub async fn reset_setting<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        Path(key): Path<String>,
+    ) -> Result<Response, LowboyError> {
+        let definition = load_setting_definition(&key)?;
+
+        context.settings().reset_raw(definition.key).await?;
+        context
+            .audit(&request_actor, "setting.reset", Some(definition.key))
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct CreateAnnouncement {
+        message: String,
+        #[serde(default)]
+        level: AnnouncementLevel,
+        /// When the announcement starts, or now if there isn't one.
+        starts_at: Option<DateTime<Utc>>,
+        ends_at: Option<DateTime<Utc>>,
+        role_id: Option<i32>,
+    }
+
+    pub async fn list_announcements(
+        DatabaseConnection(mut conn): DatabaseConnection,
+    ) -> Result<impl IntoResponse, LowboyError> {
+        Ok(Json(Announcement::list(&mut conn, None).await?))
+    }
+
+    pub async fn create_announcement<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        DatabaseConnection(mut conn): DatabaseConnection,
+        Json(input): Json<CreateAnnouncement>,
+    ) -> Result<Response, LowboyError> {
+        let now = context.clock().now();
+        let message = input.message.trim();
+        let starts_at = input.starts_at.unwrap_or(now);
+        if message.is_empty() || input.ends_at.is_some_and(|ends_at| ends_at <= starts_at) {
+            return Ok(api_error(StatusCode::UNPROCESSABLE_ENTITY));
+        }
+        if let Some(role_id) = input.role_id {
+            if Role::load(role_id, &mut conn).await.optional()?.is_none() {
+                return Ok(api_error(StatusCode::UNPROCESSABLE_ENTITY));
+            }
+        }
+
+        let announcement = Announcement::from(
+            Announcement::create_record(message, input.level, starts_at, now)
+                .with_ends_at(input.ends_at)
+                .with_role_id(input.role_id)
+                .save(&mut conn)
+                .await?,
+        );
+        context
+            .audit(
+                &request_actor,
+                "announcement.create",
+                Some(&announcement_subject(&announcement)),
+            )
+            .await?;
+        announcements::push(context.events(), now, &mut conn).await?;
+
+        Ok((StatusCode::CREATED, Json(announcement)).into_response())
+    }
+
+    pub async fn end_announcement<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        DatabaseConnection(mut conn): DatabaseConnection,
+        Path(id): Path<i32>,
+    ) -> Result<Response, LowboyError> {
+        let mut announcement = load_announcement(id, &mut conn).await?;
+
+        announcement.end(context.clock().now(), &mut conn).await?;
+        context
+            .audit(
+                &request_actor,
+                "announcement.end",
+                Some(&announcement_subject(&announcement)),
+            )
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    pub async fn delete_announcement<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        DatabaseConnection(mut conn): DatabaseConnection,
+        Path(id): Path<i32>,
+    ) -> Result<Response, LowboyError> {
+        let announcement = load_announcement(id, &mut conn).await?;
+        let subject = announcement_subject(&announcement);
+
+        announcement.delete_record(&mut conn).await?;
+        context
+            .audit(&request_actor, "announcement.delete", Some(&subject))
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    pub async fn list_trash(
+        DatabaseConnection(mut conn): DatabaseConnection,
+    ) -> Result<impl IntoResponse, LowboyError> {
+        Ok(Json(trash::list(&mut conn).await?))
+    }
+
+    pub async fn restore_trashed<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        Path((model, id)): Path<(String, i32)>,
+    ) -> Result<Response, LowboyError> {
+        let subject = trashed_subject(&model, id)?;
+
+        if !trash::restore(&context, &model, id).await? {
+            return Err(LowboyError::NotFound);
+        }
+        context
+            .audit(&request_actor, "trash.restore", Some(&subject))
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    pub async fn delete_trashed<AC: CloneableAppContext>(
+        State(context): State<AC>,
+        request_actor: RequestActor,
+        Path((model, id)): Path<(String, i32)>,
+    ) -> Result<Response, LowboyError> {
+        let subject = trashed_subject(&model, id)?;
+
+        if !trash::delete(&context, &model, id).await? {
+            return Err(LowboyError::NotFound);
+        }
+        context
+            .audit(&request_actor, "trash.delete", Some(&subject))
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+}
@@ -0,0 +1,90 @@
+use axum::extract::State;
+use axum::response::sse::Event;
+use axum::response::{Html, IntoResponse};
+use axum::routing::get;
+use axum::Router;
+use chrono::{DateTime, Utc};
+use diesel::QueryResult;
+use diesel_async::pooled_connection::deadpool::Pool;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::context::CloneableAppContext;
+use crate::error::LowboyError;
+use crate::events::Events;
+use crate::extract::DatabaseConnection;
+use crate::model::Announcement;
+use crate::view::{self, Announcements};
+use crate::{jobs, metrics, AuthSession, Connection};
+
+/// Routes for the current visitor's announcements, see [`Announcement`].
+pub fn routes<AC: CloneableAppContext>() -> Router<AC> {
+    Router::new().route("/announcements", get(show::<AC>))
+}
+
+/// The active announcements for the current visitor, rendered with the `announcements` component
+/// so it can replace itself with them.
+pub async fn show<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    AuthSession { user, .. }: AuthSession,
+    DatabaseConnection(mut conn): DatabaseConnection,
+) -> Result<impl IntoResponse, LowboyError> {
+    let announcements =
+        Announcement::active_for(user.map(|user| user.id), context.clock().now(), &mut conn)
+            .await?;
+
+    Ok(Html(view::component("announcements", &Announcements::new(announcements))))
+}
+
+/// The `announcement` event sent when an announcement becomes active. Only its id is sent, since
+/// it may be for a role; clients reload `/announcements` to show it.
+#[derive(Clone, Debug, Serialize)]
+struct AnnouncementEvent {
+    id: i32,
+}
+
+impl AnnouncementEvent {
+    fn to_event(&self) -> Event {
+        Event::default()
+            .event("announcement")
+            .json_data(self)
+            .expect("an announcement event serializes to JSON")
+    }
+}
+
+/// Send an `announcement` event for each announcement which has become active since the last
+/// push, returning how many were sent.
+pub(crate) async fn push(
+    events: &Events,
+    now: DateTime<Utc>,
+    conn: &mut Connection,
+) -> QueryResult<usize> {
+    let ids: Vec<i32> = Announcement::unannounced(now, conn)
+        .await?
+        .iter()
+        .map(|announcement| announcement.id)
+        .collect();
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    Announcement::mark_announced(&ids, now, conn).await?;
+    for &id in &ids {
+        events.send(AnnouncementEvent { id }.to_event()).await;
+    }
+
+    Ok(ids.len())
+}
+
+pub(crate) async fn push_active(database: &Pool<Connection>, events: &Events, now: DateTime<Utc>) {
+    let pushed = async {
+        let mut conn = metrics::checkout(database).await?;
+        Ok::<_, anyhow::Error>(push(events, now, &mut conn).await?)
+    };
+
+    match metrics::track_job(jobs::PUSH_ANNOUNCEMENTS.name, pushed).await {
+        Ok(0) => (),
+        Ok(pushed) => info!("pushed {pushed} announcements"),
+        Err(e) => warn!("couldn't push announcements: {e}"),
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::Path;
+use std::sync::LazyLock;
+
+use axum::extract::Request;
+use axum::http::header::{ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_TYPE, VARY};
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use tower_http::services::ServeDir;
+
+use crate::auth::{api_error, is_api_request};
+use crate::error::LowboyError;
+
+/// Path the versioned lowboy browser client is served from.
+pub const CLIENT_PATH: &str = concat!("/lowboy/lowboy-", env!("CARGO_PKG_VERSION"), ".js");
+
+/// Path the versioned stylesheet for lowboy's layout components is served from.
+pub const STYLES_PATH: &str = concat!("/lowboy/lowboy-", env!("CARGO_PKG_VERSION"), ".css");
+
+static CLIENT: LazyLock<String> = LazyLock::new(|| {
+    include_str!("../../assets/lowboy.js")
+        .replace("__LOWBOY_CLIENT_VERSION__", env!("CARGO_PKG_VERSION"))
+});
+
+pub async fn client() -> impl IntoResponse {
+    (
+        [
+            (CONTENT_TYPE, "text/javascript; charset=utf-8"),
+            // The path is versioned, so the client can be cached forever.
+            (CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        CLIENT.as_str(),
+    )
+}
+
+pub async fn styles() -> impl IntoResponse {
+    (
+        [
+            (CONTENT_TYPE, "text/css; charset=utf-8"),
+            (CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        include_str!("../../assets/lowboy.css"),
+    )
+}
+
+/// Serve the files in `directory`, along with their `.br`/`.gz` precompressed variants to clients
+/// accepting them. Range requests are supported, for larger media files.
+///
+/// Fingerprinted files, e.g. `app-5HQXK3LS.js`, can be cached forever. Other files must be
+/// revalidated, which is cheap as they're served with a `Last-Modified` date.
+///
+/// Missing files are [`LowboyError::NotFound`] errors, rendered by the error page layer if it's
+/// added to the router, or a JSON error for [API requests](is_api_request).
+pub fn static_files(directory: impl AsRef<Path>) -> Router {
+    let files = ServeDir::new(directory)
+        .precompressed_br()
+        .precompressed_gzip();
+
+    Router::new()
+        .fallback_service(files)
+        .layer(middleware::from_fn(static_cache_headers))
+        .layer(middleware::from_fn(static_not_found))
+}
+
+async fn static_not_found(request: Request, next: Next) -> Response {
+    let api_request = is_api_request(&request);
+    let response = next.run(request).await;
+
+    match response.status() {
+        StatusCode::NOT_FOUND if api_request => api_error(StatusCode::NOT_FOUND),
+        StatusCode::NOT_FOUND => LowboyError::NotFound.into_response(),
+        _ => response,
+    }
+}
+
+async fn static_cache_headers(request: Request, next: Next) -> Response {
+    let fingerprinted = is_fingerprinted(request.uri().path());
+    let mut response = next.run(request).await;
+
+    if response.status().is_success() {
+        let cache_control = if fingerprinted {
+            "public, max-age=31536000, immutable"
+        } else {
+            "public, no-cache"
+        };
+        let headers = response.headers_mut();
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static(cache_control));
+        // Responses differ by encoding, now that precompressed variants are served.
+        if !headers.contains_key(VARY) {
+            headers.insert(VARY, HeaderValue::from_name(ACCEPT_ENCODING));
+        }
+    }
+
+    response
+}
+
+/// Whether the file at `path` has a content hash in its name, so its contents never change. Only
+/// esbuild's `[name]-[hash]` names are recognized, where the hash is 8 base32 characters, e.g.
+/// `app-5HQXK3LS.js`.
+///
+/// Hashes have to mix letters and digits, so names like `app-SETTINGS.js` aren't cached forever.
+/// The few fingerprinted files that don't are revalidated instead, which is only slower.
+fn is_fingerprinted(path: &str) -> bool {
+    let Some(stem) = Path::new(path).file_stem().and_then(|stem| stem.to_str()) else {
+        return false;
+    };
+    let Some((_, hash)) = stem.rsplit_once('-') else {
+        return false;
+    };
+    let is_base32 = |c: char| c.is_ascii_uppercase() || ('2'..='7').contains(&c);
+
+    hash.len() == 8
+        && hash.chars().all(is_base32)
+        && hash.chars().any(|c| c.is_ascii_digit())
+        && hash.chars().any(|c| c.is_ascii_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn esbuild_names_are_fingerprinted() {
+        assert!(is_fingerprinted("/static/app-5HQXK3LS.js"));
+        assert!(is_fingerprinted("/static/chunks/chunk-2ABCDEFG.css"));
+        assert!(is_fingerprinted("/static/my-app-7QW3ERTY.js"));
+    }
+
+    #[test]
+    fn other_names_are_not_fingerprinted() {
+        assert!(!is_fingerprinted("/static/app.js"));
+        assert!(!is_fingerprinted("/static/logo.3f2a9c1d.png"));
+        assert!(!is_fingerprinted("/static/app-5hqxk3ls.js"));
+        assert!(!is_fingerprinted("/static/app-5HQXK3L.js"));
+        assert!(!is_fingerprinted("/static/app-5HQXK3LSX.js"));
+        assert!(!is_fingerprinted("/static/app-SETTINGS.js"));
+        assert!(!is_fingerprinted("/static/release-20240101.txt"));
+    }
+}
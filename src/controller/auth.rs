@@ -1,47 +1,77 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
 use anyhow::anyhow;
-use axum::extract::{Path, Query, State};
-use axum::response::{IntoResponse, Redirect};
+#[cfg(feature = "oauth")]
+use axum::extract::Query;
+use axum::extract::{ConnectInfo, Path, RawForm, State};
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderValue, StatusCode};
+#[cfg(feature = "oauth")]
+use axum::response::Redirect;
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
-use axum::{Form, Router};
-use axum_messages::Messages;
+use axum::{Extension, Form, Router};
+use axum_extra::headers::UserAgent;
+use axum_extra::TypedHeader;
+use chrono::TimeDelta;
 use diesel::result::DatabaseErrorKind;
 use diesel::result::Error::DatabaseError;
-use oauth2::CsrfToken;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use tower_sessions::Session;
 use tracing::warn;
-use validator::{Validate, ValidationErrorsKind};
+use validator::Validate;
 
+#[cfg(feature = "oauth")]
+use crate::auth::IdentityProvider;
 use crate::auth::{
-    IdentityProvider, LoginForm as _, LowboyEmailVerificationView as _, LowboyLoginView as _,
+    api_error, LoginForm as _, LowboyEmailVerificationView as _, LowboyLoginView as _,
     LowboyRegisterView as _, RegistrationDetails, RegistrationForm as _,
 };
+use crate::bot::{BotGuard, GuardedForm};
 use crate::context::CloneableAppContext;
 use crate::error::LowboyError;
-use crate::extract::DatabaseConnection;
+use crate::extract::{ApiClient, DatabaseConnection, Flash, HxRequest, ValidatedQuery};
+use crate::form::FormErrors;
+use crate::guest::GuestSession;
+#[cfg(feature = "oauth")]
+use crate::model::OAuthCredentials;
 use crate::model::{
-    unverified_email::Error as VerificationError, CredentialKind, Credentials, OAuthCredentials,
-    PasswordCredentials, UnverifiedEmail, User,
+    unverified_email::Error as VerificationError, CredentialKind, Credentials, KnownDevice,
+    PasswordCredentials, UnverifiedEmail, User, UserModel as _,
 };
-use crate::{app, lowboy_view, AuthSession};
+use crate::security::{LoginAttempts, SecurityNotification, FAILED_LOGIN_THRESHOLD, LOCKOUT};
+use crate::settings::RegistrationOpen;
+use crate::{app, lowboy_view, metrics, AuthSession};
 
 const NEXT_URL_KEY: &str = "auth.next-url";
+#[cfg(feature = "oauth")]
 const CSRF_STATE_KEY: &str = "oauth.csrf-state";
 const REGISTRATION_FORM_KEY: &str = "auth.registration-form";
+const REGISTRATION_ERRORS_KEY: &str = "auth.registration-errors";
 const LOGIN_FORM_KEY: &str = "auth.login-form";
+const LOGIN_ERRORS_KEY: &str = "auth.login-errors";
+const REGISTRATION_CLOSED: &str = "Registration is closed";
 
 pub fn routes<App: app::App<AC>, AC: CloneableAppContext>() -> Router<AC> {
-    Router::new()
+    let router = Router::new()
         .route("/register", get(register_form::<App, AC>))
         .route("/register", post(register::<App, AC>))
         .route("/login", get(login_form::<App, AC>))
-        .route("/login", post(login::<App, AC>))
+        .route("/login", post(login::<App, AC>));
+
+    #[cfg(feature = "oauth")]
+    let router = router
         .route("/login/oauth/:provider", post(oauth_init::<App, AC>))
         .route("/login/oauth/:provider/callback", get(oauth_callback))
         .route(
             "/login/oauth/:provider/authenticate",
-            get(oauth_authenticate),
-        )
+            get(oauth_authenticate::<App, AC>),
+        );
+
+    router
         .route("/logout", get(logout))
         .route(
             "/email/:address/verify/:token",
@@ -49,11 +79,132 @@ pub fn routes<App: app::App<AC>, AC: CloneableAppContext>() -> Router<AC> {
         )
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct NextUrl {
+    #[validate(length(max = 2048))]
+    next: Option<String>,
+}
+
+/// Only allow local, absolute paths as a "return to" destination to avoid open redirects.
+fn sanitize_next(next: Option<String>) -> Option<String> {
+    next.filter(|next| next.starts_with('/') && !next.starts_with("//") && !next.contains('\\'))
+}
+
+/// Build a redirect to `path`, carrying the "return to" destination along as `?next=`.
+fn redirect_with_next(hx: &HxRequest, path: &str, next: Option<&String>) -> Response {
+    match next {
+        Some(next) => {
+            let next: String = form_urlencoded::byte_serialize(next.as_bytes()).collect();
+            hx.redirect(&format!("{path}?next={next}"))
+        }
+        None => hx.redirect(path),
+    }
+}
+
+/// Resolve the "return to" destination, preferring the explicitly provided value and falling back
+/// to the one remembered in the session. The resolved value is remembered for the next step of the
+/// auth flow (e.g. register → verify → login).
+async fn remember_next(
+    session: &Session,
+    next: Option<String>,
+) -> Result<Option<String>, LowboyError> {
+    let next = match sanitize_next(next) {
+        Some(next) => Some(next),
+        None => sanitize_next(session.get::<Option<String>>(NEXT_URL_KEY).await?.flatten()),
+    };
+
+    session.insert(NEXT_URL_KEY, &next).await?;
+
+    Ok(next)
+}
+
+/// Resolve the "return to" destination and forget it, as the auth flow is complete.
+async fn take_next(
+    session: &Session,
     next: Option<String>,
+) -> Result<Option<String>, LowboyError> {
+    let remembered = session
+        .remove::<Option<String>>(NEXT_URL_KEY)
+        .await?
+        .flatten();
+
+    Ok(sanitize_next(next).or(sanitize_next(remembered)))
 }
 
+/// Parse the body of a form guarded by the [`BotGuard`], keeping every submitted field for its
+/// checks.
+fn parse_guarded_form<T: DeserializeOwned>(
+    body: &[u8],
+) -> Result<(T, HashMap<String, String>), LowboyError> {
+    let input = serde_urlencoded::from_bytes(body).map_err(|_| LowboyError::BadRequest)?;
+    let fields = form_urlencoded::parse(body).into_owned().collect();
+
+    Ok((input, fields))
+}
+
+/// Hand the guest session (if any) over to the app, now that the guest has an account.
+///
+/// The guest session is kept if the app fails to migrate it, so it's retried at the next login
+/// rather than lost along with whatever the guest had.
+async fn upgrade_guest<App: app::App<AC>, AC: CloneableAppContext>(
+    context: &AC,
+    session: &Session,
+    user: &User,
+) -> Result<(), LowboyError> {
+    let Some(guest) = GuestSession::current(session).await? else {
+        return Ok(());
+    };
+
+    if !guest.is_empty() {
+        if let Err(e) = App::on_guest_upgrade(context, user, guest).await {
+            warn!("couldn't upgrade the guest session of user({}), keeping it: {e}", user.id);
+            return Ok(());
+        }
+    }
+
+    GuestSession::end(session).await
+}
+
+/// Remember the device `user` signed in from, notifying them if it's a new one.
+async fn remember_device<AC: CloneableAppContext>(
+    context: &AC,
+    user: &User,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+) -> Result<(), LowboyError> {
+    let Some(TypedHeader(user_agent)) = user_agent else {
+        return Ok(());
+    };
+    let ip = connect_info.map(|ConnectInfo(addr)| addr.ip().to_string());
+
+    let mut conn = metrics::checkout(context.database()).await?;
+    let new_device = KnownDevice::remember(
+        user.id,
+        user_agent.as_str(),
+        ip.as_deref(),
+        context.clock().now(),
+        &mut conn,
+    )
+    .await?;
+    drop(conn);
+
+    if new_device {
+        let notification = SecurityNotification::NewDeviceLogin {
+            user_agent: user_agent.to_string(),
+            ip,
+        };
+        if let Err(e) = context.send_security_notification(user, notification).await {
+            warn!(
+                "couldn't send new device notification to user({}): {e}",
+                user.id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "oauth")]
 #[derive(Clone, Debug, Deserialize)]
 pub struct CallbackResp {
     intermediary_redirect: bool,
@@ -61,147 +212,278 @@ pub struct CallbackResp {
     state: String,
 }
 
+#[cfg(feature = "oauth")]
 #[derive(Clone, Debug, Deserialize)]
 pub struct AuthzResp {
     code: String,
-    state: CsrfToken,
+    state: String,
 }
 
 pub async fn register_form<App: app::App<AC>, AC: CloneableAppContext>(
     State(context): State<AC>,
-    AuthSession { user, .. }: AuthSession,
+    AuthSession { user, backend, .. }: AuthSession,
     session: Session,
-    Query(NextUrl { next }): Query<NextUrl>,
+    messages: Flash,
+    hx: HxRequest,
+    Extension(bot_guard): Extension<Arc<BotGuard>>,
+    ValidatedQuery(NextUrl { next }): ValidatedQuery<NextUrl>,
 ) -> Result<impl IntoResponse, LowboyError> {
     if user.is_some() {
-        return Ok(Redirect::to(&next.unwrap_or("/".into())).into_response());
+        let next = take_next(&session, next).await?;
+        return Ok(hx.redirect(&next.unwrap_or("/".into())));
+    }
+
+    let next = remember_next(&session, next).await?;
+
+    let RegistrationOpen(open) = context.settings().get::<RegistrationOpen>().await?;
+    if !open {
+        messages.error(REGISTRATION_CLOSED);
+        return Ok(redirect_with_next(&hx, "/login", next.as_ref()));
     }
 
     let mut form = session
         .remove(REGISTRATION_FORM_KEY)
         .await?
         .unwrap_or(App::RegistrationForm::empty());
+    let errors = session
+        .remove(REGISTRATION_ERRORS_KEY)
+        .await?
+        .unwrap_or_default();
 
     form.set_next(next);
 
-    Ok(
-        lowboy_view!(App::register_view(&context).set_form(form).clone(), {
-            "title" => "Register",
-        })
-        .into_response(),
-    )
+    bot_guard
+        .start(&session, GuardedForm::Register, context.clock().now())
+        .await?;
+
+    let view = App::register_view(&context)
+        .set_form(form)
+        .set_errors(errors)
+        .set_bot_fields(bot_guard.fields())
+        .set_providers(backend.provider_buttons())
+        .clone();
+
+    Ok(lowboy_view!(view, {
+        "title" => "Register",
+    })
+    .into_response())
 }
 
 pub async fn register<App: app::App<AC>, AC: CloneableAppContext>(
     State(context): State<AC>,
     AuthSession { user, .. }: AuthSession,
     session: Session,
-    mut messages: Messages,
-    Form(input): Form<App::RegistrationForm>,
+    messages: Flash,
+    hx: HxRequest,
+    Extension(bot_guard): Extension<Arc<BotGuard>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    RawForm(body): RawForm,
 ) -> Result<impl IntoResponse, LowboyError> {
+    let (input, fields) = parse_guarded_form::<App::RegistrationForm>(&body)?;
+
     if user.is_some() {
-        return Ok(Redirect::to(&input.next().to_owned().unwrap_or("/".into())).into_response());
+        let next = take_next(&session, input.next().to_owned()).await?;
+        return Ok(hx.redirect(&next.unwrap_or("/".into())));
     }
 
-    if let Err(validation) = input.validate() {
-        for (_, info) in validation.into_errors() {
-            if let ValidationErrorsKind::Field(errors) = info {
-                for error in errors {
-                    messages = messages.error(error.to_string());
-                }
-            }
-        }
+    let next = remember_next(&session, input.next().to_owned()).await?;
+
+    let RegistrationOpen(open) = context.settings().get::<RegistrationOpen>().await?;
+    if !open {
+        messages.error(REGISTRATION_CLOSED);
+        return Ok(redirect_with_next(&hx, "/login", next.as_ref()));
+    }
 
+    let ip = connect_info.map(|ConnectInfo(addr)| addr.ip());
+    let now = context.clock().now();
+    if !bot_guard
+        .check(&session, GuardedForm::Register, &fields, ip, now)
+        .await?
+    {
+        messages.error("We couldn't verify that you're not a bot, please try again");
         session.insert(REGISTRATION_FORM_KEY, input.clone()).await?;
-        return Ok(if let Some(next) = input.next().to_owned() {
-            Redirect::to(&format!("/register?next={next}"))
-        } else {
-            Redirect::to("/register")
-        }
-        .into_response());
+        return Ok(redirect_with_next(&hx, "/register", next.as_ref()));
+    }
+
+    if let Err(validation) = input.validate() {
+        session
+            .insert(REGISTRATION_ERRORS_KEY, FormErrors::from(validation))
+            .await?;
+        session.insert(REGISTRATION_FORM_KEY, input.clone()).await?;
+        return Ok(redirect_with_next(&hx, "/register", next.as_ref()));
     };
 
-    let mut conn = context.database().get().await?;
+    let mut conn = metrics::checkout(context.database()).await?;
 
-    let password = password_auth::generate_hash(input.password());
+    let password = context
+        .password_hasher()
+        .hash(input.password().to_owned())
+        .await?;
     let user = User::new(
         input.username(),
         input.email(),
         Some(&password),
         None,
+        context.clock(),
+        context.secret_generator(),
         &mut conn,
     )
     .await;
 
     match user {
         Ok(user) => {
-            messages.success("Registration successful! You can now log in.");
-
             context
-                .on_new_user(&user, RegistrationDetails::Local(Box::new(input.clone())))
+                .complete_new_user(&user, RegistrationDetails::Local(Box::new(input.clone())))
                 .await?;
 
-            let redirect = Redirect::to(&input.next().to_owned().unwrap_or("/login".into()));
+            messages.success("Registration successful! You can now log in.");
+
+            upgrade_guest::<App, AC>(&context, &session, &user).await?;
 
-            return Ok(redirect.into_response());
+            // The "return to" destination is remembered in the session, so it survives the email
+            // verification step as well.
+            return Ok(redirect_with_next(&hx, "/login", next.as_ref()));
         }
-        Err(DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
-            messages.error("A user with the same username or email already exists")
+        Err(ref e @ DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            match FormErrors::from_unique_violation(e) {
+                Some(errors) => session.insert(REGISTRATION_ERRORS_KEY, errors).await?,
+                None => {
+                    messages.error("A user with the same username or email already exists");
+                }
+            }
+        }
+        Err(_) => {
+            messages.error("An unknown error occurred");
         }
-        Err(_) => messages.error("An unknown error occurred"),
     };
 
     session.insert(REGISTRATION_FORM_KEY, input.clone()).await?;
-    let redirect = if let Some(next) = input.next().to_owned() {
-        Redirect::to(&format!("/register?next={next}"))
+
+    Ok(redirect_with_next(&hx, "/register", next.as_ref()))
+}
+
+/// Refuse a login to a locked out account. API and htmx clients get a `429 Too Many Requests`,
+/// browsers are redirected back to the login form to show the error.
+async fn locked_out(
+    session: &Session,
+    hx: &HxRequest,
+    api: bool,
+    next: Option<&String>,
+    retry_after: TimeDelta,
+) -> Result<Response, LowboyError> {
+    let message = match (retry_after.num_seconds() + 59) / 60 {
+        1 => "Too many failed login attempts, please try again in a minute".to_string(),
+        minutes => {
+            format!("Too many failed login attempts, please try again in {minutes} minutes")
+        }
+    };
+
+    let mut errors = FormErrors::new();
+    errors
+        .add("username", message.clone())
+        .set_retry_after(retry_after);
+
+    let mut response = if api {
+        api_error(StatusCode::TOO_MANY_REQUESTS)
+    } else if hx.enabled {
+        (StatusCode::TOO_MANY_REQUESTS, message).into_response()
     } else {
-        Redirect::to("/register")
+        session.insert(LOGIN_ERRORS_KEY, &errors).await?;
+        redirect_with_next(hx, "/login", next)
     };
+    if let Some(seconds) = errors.retry_after() {
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(seconds));
+    }
 
-    Ok(redirect.into_response())
+    Ok(response)
 }
 
 pub async fn login_form<App: app::App<AC>, AC: CloneableAppContext>(
     State(context): State<AC>,
+    AuthSession { backend, .. }: AuthSession,
     session: Session,
-    Query(NextUrl { next }): Query<NextUrl>,
+    Extension(bot_guard): Extension<Arc<BotGuard>>,
+    ValidatedQuery(NextUrl { next }): ValidatedQuery<NextUrl>,
 ) -> Result<impl IntoResponse, LowboyError> {
+    let next = remember_next(&session, next).await?;
+
     let mut form = session
         .remove(LOGIN_FORM_KEY)
         .await?
         .unwrap_or(App::LoginForm::empty());
+    let errors: FormErrors = session.remove(LOGIN_ERRORS_KEY).await?.unwrap_or_default();
+    let retry_after = errors
+        .retry_after()
+        .map(|seconds| [(RETRY_AFTER, HeaderValue::from(seconds))]);
 
     form.set_next(next);
 
-    Ok(
-        lowboy_view!(App::login_view(&context).set_form(form).clone(), {
+    bot_guard
+        .start(&session, GuardedForm::Login, context.clock().now())
+        .await?;
+
+    let view = App::login_view(&context)
+        .set_form(form)
+        .set_errors(errors)
+        .set_bot_fields(bot_guard.fields())
+        .set_providers(backend.provider_buttons())
+        .clone();
+
+    Ok((
+        retry_after,
+        lowboy_view!(view, {
             "title" => "Login",
         }),
-    )
+    ))
 }
 
 pub async fn login<App: app::App<AC>, AC: CloneableAppContext>(
+    State(context): State<AC>,
     mut auth_session: AuthSession,
     session: Session,
-    mut messages: Messages,
-    Form(input): Form<App::LoginForm>,
+    messages: Flash,
+    hx: HxRequest,
+    ApiClient(api): ApiClient,
+    Extension(bot_guard): Extension<Arc<BotGuard>>,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    RawForm(body): RawForm,
 ) -> Result<impl IntoResponse, LowboyError> {
+    let (input, fields) = parse_guarded_form::<App::LoginForm>(&body)?;
+
     session.insert(LOGIN_FORM_KEY, input.clone()).await?;
 
+    let next = remember_next(&session, input.next().to_owned()).await?;
+
     if let Err(validation) = input.validate() {
-        for (_, info) in validation.into_errors() {
-            if let ValidationErrorsKind::Field(errors) = info {
-                for error in errors {
-                    messages = messages.error(error.to_string());
-                }
-            }
-        }
-        return Ok(if let Some(next) = input.next().to_owned() {
-            Redirect::to(&format!("/login?next={next}"))
-        } else {
-            Redirect::to("/login")
+        session
+            .insert(LOGIN_ERRORS_KEY, FormErrors::from(validation))
+            .await?;
+        return Ok(redirect_with_next(&hx, "/login", next.as_ref()));
+    }
+
+    let now = context.clock().now();
+    let ip = connect_info.as_ref().map(|ConnectInfo(addr)| addr.ip());
+    if !bot_guard
+        .check(&session, GuardedForm::Login, &fields, ip, now)
+        .await?
+    {
+        messages.error("We couldn't verify that you're not a bot, please try again");
+        return Ok(redirect_with_next(&hx, "/login", next.as_ref()));
+    }
+
+    let attempts = LoginAttempts::global();
+    let account = {
+        let mut conn = metrics::checkout(context.database()).await?;
+        User::find_by_username(input.username(), &mut conn).await?
+    };
+
+    if let Some(ref account) = account {
+        if let Some(retry_after) = attempts.locked_for(account.id, now) {
+            return locked_out(&session, &hx, api, next.as_ref(), retry_after).await;
         }
-        .into_response());
     }
 
     let creds = Credentials {
@@ -211,19 +493,33 @@ pub async fn login<App: app::App<AC>, AC: CloneableAppContext>(
             password: input.password().clone(),
         }),
         oauth: None,
+        api_token: None,
     };
 
     let user = match auth_session.authenticate(creds).await {
         Ok(Some(user)) => user,
         Ok(None) => {
+            if let Some(account) = account {
+                let streak = attempts.record_failure(account.id, now);
+                if streak == FAILED_LOGIN_THRESHOLD {
+                    let notification = SecurityNotification::FailedLoginStreak { attempts: streak };
+                    if let Err(e) = context
+                        .send_security_notification(&account, notification)
+                        .await
+                    {
+                        warn!(
+                            "couldn't send failed login notification to user({}): {e}",
+                            account.id
+                        );
+                    }
+
+                    return locked_out(&session, &hx, api, next.as_ref(), LOCKOUT).await;
+                }
+            }
+
             messages.error("Invalid credentials");
 
-            return Ok(if let Some(next) = input.next().to_owned() {
-                Redirect::to(&format!("/login?next={next}"))
-            } else {
-                Redirect::to("/login")
-            }
-            .into_response());
+            return Ok(redirect_with_next(&hx, "/login", next.as_ref()));
         }
         Err(e) => {
             return Err(anyhow!(
@@ -240,12 +536,20 @@ pub async fn login<App: app::App<AC>, AC: CloneableAppContext>(
         }
     }
 
-    Ok(Redirect::to(&input.next().to_owned().unwrap_or("/".into())).into_response())
+    attempts.clear(user.id);
+    remember_device(&context, &user, user_agent, connect_info).await?;
+    upgrade_guest::<App, AC>(&context, &session, &user).await?;
+
+    let next = take_next(&session, next).await?;
+
+    Ok(hx.redirect(&next.unwrap_or("/".into())))
 }
 
+#[cfg(feature = "oauth")]
 pub async fn oauth_init<App: app::App<AC>, AC: CloneableAppContext>(
     auth_session: AuthSession,
     session: Session,
+    hx: HxRequest,
     Path(provider): Path<IdentityProvider>,
     Form(input): Form<App::LoginForm>,
 ) -> Result<impl IntoResponse, LowboyError> {
@@ -256,11 +560,12 @@ pub async fn oauth_init<App: app::App<AC>, AC: CloneableAppContext>(
     };
 
     session.insert(CSRF_STATE_KEY, csrf_state.secret()).await?;
-    session.insert(NEXT_URL_KEY, input.next()).await?;
+    remember_next(&session, input.next().to_owned()).await?;
 
-    Ok(Redirect::to(auth_url.as_str()).into_response())
+    Ok(hx.redirect(auth_url.as_str()))
 }
 
+#[cfg(feature = "oauth")]
 pub async fn oauth_callback(
     Path(provider): Path<IdentityProvider>,
     Query(CallbackResp {
@@ -291,10 +596,15 @@ pub async fn oauth_callback(
     }
 }
 
-pub async fn oauth_authenticate(
+#[cfg(feature = "oauth")]
+pub async fn oauth_authenticate<App: app::App<AC>, AC: CloneableAppContext>(
+    State(context): State<AC>,
     mut auth_session: AuthSession,
-    messages: Messages,
+    messages: Flash,
     session: Session,
+    hx: HxRequest,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     Path(provider): Path<IdentityProvider>,
     Query(AuthzResp {
         code,
@@ -305,11 +615,6 @@ pub async fn oauth_authenticate(
         return Err(LowboyError::BadRequest);
     };
 
-    let next = session
-        .get::<Option<String>>(NEXT_URL_KEY)
-        .await?
-        .unwrap_or(None);
-
     let credentials = Credentials {
         kind: CredentialKind::OAuth(provider),
         password: None,
@@ -318,6 +623,7 @@ pub async fn oauth_authenticate(
             old_state,
             new_state,
         }),
+        api_token: None,
     };
 
     let user = match auth_session.authenticate(credentials).await {
@@ -325,12 +631,8 @@ pub async fn oauth_authenticate(
         Ok(None) => {
             messages.error("Invalid CSRF state");
 
-            return Ok(if let Some(next) = next.to_owned() {
-                Redirect::to(&format!("/login?next={next}"))
-            } else {
-                Redirect::to("/login")
-            }
-            .into_response());
+            let next = remember_next(&session, None).await?;
+            return Ok(redirect_with_next(&hx, "/login", next.as_ref()));
         }
         Err(e) => {
             return Err(anyhow!("Error during oauth authenticate: {e}"))?;
@@ -341,22 +643,33 @@ pub async fn oauth_authenticate(
         return Err(anyhow!("Error during oauth login: {e}"))?;
     }
 
-    Ok(Redirect::to(&next.to_owned().unwrap_or("/".into())).into_response())
+    remember_device(&context, &user, user_agent, connect_info).await?;
+    upgrade_guest::<App, AC>(&context, &session, &user).await?;
+
+    let next = take_next(&session, None).await?;
+
+    Ok(hx.redirect(&next.unwrap_or("/".into())))
 }
 
-pub async fn logout(mut session: AuthSession) -> Result<impl IntoResponse, LowboyError> {
+pub async fn logout(
+    mut session: AuthSession,
+    hx: HxRequest,
+) -> Result<impl IntoResponse, LowboyError> {
     match session.logout().await {
-        Ok(_) => Ok(Redirect::to("/").into_response()),
+        Ok(_) => Ok(hx.redirect("/")),
         Err(e) => Err(anyhow!("Error logging out user: {e}"))?,
     }
 }
 
-// @todo support ?next
 pub async fn verify_email<App: app::App<AC>, AC: CloneableAppContext>(
     State(context): State<AC>,
+    AuthSession { user, .. }: AuthSession,
     DatabaseConnection(mut conn): DatabaseConnection,
-    messages: Messages,
+    session: Session,
+    messages: Flash,
+    hx: HxRequest,
     Path((address, token)): Path<(String, String)>,
+    ValidatedQuery(NextUrl { next }): ValidatedQuery<NextUrl>,
 ) -> Result<impl IntoResponse, LowboyError> {
     fn email_verification_view<App: app::App<AC>, AC: CloneableAppContext>(
         context: &AC,
@@ -382,10 +695,19 @@ pub async fn verify_email<App: app::App<AC>, AC: CloneableAppContext>(
         .into_response());
     };
 
-    match email.verify(&token, &mut conn).await {
+    match email.verify(&token, context.clock(), &mut conn).await {
         Ok(_) => {
             messages.success("Your email address has been verified.");
-            Ok(Redirect::to("/").into_response())
+
+            // Logged in users can go straight to where they were headed, everyone else needs to
+            // log in first.
+            if user.is_some() {
+                let next = take_next(&session, next).await?;
+                Ok(hx.redirect(&next.unwrap_or("/".into())))
+            } else {
+                let next = remember_next(&session, next).await?;
+                Ok(redirect_with_next(&hx, "/login", next.as_ref()))
+            }
         }
         Err(error) => {
             warn!("couldn't verify email {address}: {error}");
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::put;
+use axum::{Form, Json, Router};
+use chrono::{DateTime, Utc};
+use diesel_async::pooled_connection::deadpool::Pool;
+use tracing::{info, warn};
+
+use crate::context::CloneableAppContext;
+use crate::error::LowboyError;
+use crate::extract::DatabaseConnection;
+use crate::model::Draft;
+use crate::{jobs, metrics, AuthSession, Connection};
+
+/// Drafts larger than this many bytes aren't saved.
+const MAX_DRAFT_SIZE: usize = 64 * 1024;
+
+/// Routes for autosaving drafts of forms, see [`Draft`].
+///
+/// Forms autosave by sending their fields to `/drafts/:form`, e.g. with htmx:
+///
+/// ```html
+/// <form hx-put="/drafts/post" hx-trigger="input delay:2s" hx-swap="none">
+/// ```
+pub fn routes<AC: CloneableAppContext>() -> Router<AC> {
+    Router::new().route(
+        "/drafts/:form",
+        put(save::<AC>).get(show::<AC>).delete(discard),
+    )
+}
+
+pub async fn save<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    AuthSession { user, .. }: AuthSession,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(form): Path<String>,
+    Form(fields): Form<HashMap<String, String>>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let user = user.ok_or(LowboyError::Unauthorized)?;
+
+    let size: usize = fields.iter().map(|(name, value)| name.len() + value.len()).sum();
+    if size > MAX_DRAFT_SIZE {
+        return Ok(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    Draft::save(user.id, &form, &fields, context.clock().now(), &mut conn).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn show<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    AuthSession { user, .. }: AuthSession,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(form): Path<String>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let user = user.ok_or(LowboyError::Unauthorized)?;
+
+    let draft = Draft::find(user.id, &form, context.clock().now(), &mut conn)
+        .await?
+        .ok_or(LowboyError::NotFound)?;
+
+    Ok(Json(draft.fields()))
+}
+
+pub async fn discard(
+    AuthSession { user, .. }: AuthSession,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(form): Path<String>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let user = user.ok_or(LowboyError::Unauthorized)?;
+
+    Draft::discard(user.id, &form, &mut conn).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Delete the drafts which expired before `now`, run periodically by [`crate::Lowboy::serve`].
+pub(crate) async fn delete_expired(database: &Pool<Connection>, now: DateTime<Utc>) {
+    let deleted = async {
+        let mut conn = metrics::checkout(database).await?;
+        Ok::<_, anyhow::Error>(Draft::delete_expired(now, &mut conn).await?)
+    };
+
+    match metrics::track_job(jobs::DELETE_EXPIRED_DRAFTS.name, deleted).await {
+        Ok(0) => (),
+        Ok(deleted) => info!("deleted {deleted} expired drafts"),
+        Err(e) => warn!("couldn't delete expired drafts: {e}"),
+    }
+}
@@ -1,29 +1,105 @@
 use std::convert::Infallible;
 use std::time::Duration;
 
-use axum::extract::State;
+use axum::extract::{Query, State};
 use axum::response::sse::{Event, Sse};
 use axum_extra::{headers, TypedHeader};
 use futures::{Stream, StreamExt as _};
+use serde::Deserialize;
+use tokio::time::Instant;
 use tracing::info;
 
-use crate::{shutdown_signal, AppContext};
+use crate::clock::Clock;
+use crate::error::LowboyError;
+use crate::metrics::{CloseReason, EventMetrics};
+use crate::presence::PresenceGuard;
+use crate::{shutdown_signal, AppContext, AuthSession};
+
+/// How often an open stream marks its connection as still present.
+const HEARTBEAT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default, Deserialize)]
+pub struct EventsQuery {
+    /// The page the client is on, for presence.
+    page: Option<String>,
+}
 
 pub async fn events<T: AppContext>(
     State(context): State<T>,
+    AuthSession { user, .. }: AuthSession,
     TypedHeader(user_agent): TypedHeader<headers::UserAgent>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    Query(query): Query<EventsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, LowboyError> {
+    let user = user.ok_or(LowboyError::Unauthorized)?;
     info!("`{}` connected", user_agent.as_str());
 
-    let (_, rx) = context.events().clone();
-    let stream = rx.into_stream().map(Ok);
+    let events = context.events();
+    let subscription = events.subscribe();
+    let presence = context
+        .presence()
+        .connect(&user, query.page, events.clone(), context.clock().now());
+    let stream = subscription.into_stream().map(Ok);
+    let idle_timeout = context.presence().idle_timeout();
+    let stream = with_presence(
+        stream,
+        presence,
+        idle_timeout,
+        dyn_clone::clone_box(context.clock()),
+    );
     let stream = or_until_shutdown(stream);
 
-    Sse::new(stream).keep_alive(
+    Ok(Sse::new(stream).keep_alive(
         axum::response::sse::KeepAlive::new()
             .interval(Duration::from_secs(1))
             .text("keep-alive-text"),
-    )
+    ))
+}
+
+/// Keep `presence` while `stream` is open, touching it every [`HEARTBEAT`] so it isn't expired.
+///
+/// The stream ends when presence closes the connection, or when nothing was sent on it for
+/// `idle_timeout`.
+fn with_presence<S>(
+    stream: S,
+    presence: PresenceGuard,
+    idle_timeout: Option<Duration>,
+    clock: Box<dyn Clock>,
+) -> impl Stream<Item = S::Item>
+where
+    S: Stream,
+{
+    async_stream::stream! {
+        futures::pin_mut!(stream);
+
+        let mut heartbeat = tokio::time::interval(HEARTBEAT);
+        let mut idle_deadline = idle_timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            tokio::select! {
+                item = stream.next() => match item {
+                    Some(item) => {
+                        idle_deadline = idle_timeout.map(|timeout| Instant::now() + timeout);
+                        yield item
+                    }
+                    None => break,
+                },
+                _ = heartbeat.tick() => presence.touch(clock.now()),
+                _ = presence.closed() => break,
+                _ = idle(idle_deadline) => {
+                    EventMetrics::global().record_closed(CloseReason::Idle);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Wait until `deadline`, or forever if there isn't one.
+async fn idle(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
 }
 
 fn or_until_shutdown<S>(stream: S) -> impl Stream<Item = S::Item>
@@ -0,0 +1,6 @@
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+
+pub async fn healthz() -> impl IntoResponse {
+    (StatusCode::OK, "ok")
+}
@@ -0,0 +1,162 @@
+use std::path::{Path, PathBuf};
+
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Extension, Router};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::app;
+use crate::context::CloneableAppContext;
+
+/// Path the web app manifest is served from.
+pub const MANIFEST_PATH: &str = "/manifest.webmanifest";
+
+/// The directory icons are served from, the app's static directory.
+#[derive(Clone, Debug)]
+pub struct IconsDir(pub PathBuf);
+
+/// Icons aren't fingerprinted, so they're only cached for a day.
+const ICON_CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// The web app manifest, describing how the app looks when installed.
+#[derive(Clone, Debug, Serialize)]
+pub struct WebManifest {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub short_name: Option<String>,
+    pub start_url: String,
+    pub display: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme_color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+    pub icons: Vec<ManifestIcon>,
+}
+
+impl WebManifest {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            short_name: None,
+            start_url: "/".to_string(),
+            display: "browser".to_string(),
+            theme_color: None,
+            background_color: None,
+            icons: vec![],
+        }
+    }
+
+    pub fn with_short_name(self, short_name: impl Into<String>) -> Self {
+        Self {
+            short_name: Some(short_name.into()),
+            ..self
+        }
+    }
+
+    /// e.g. `standalone`, to open the app without the browser's UI once installed.
+    pub fn with_display(self, display: impl Into<String>) -> Self {
+        Self {
+            display: display.into(),
+            ..self
+        }
+    }
+
+    pub fn with_theme_color(self, theme_color: impl Into<String>) -> Self {
+        Self {
+            theme_color: Some(theme_color.into()),
+            ..self
+        }
+    }
+
+    pub fn with_background_color(self, background_color: impl Into<String>) -> Self {
+        Self {
+            background_color: Some(background_color.into()),
+            ..self
+        }
+    }
+
+    /// Add an icon, e.g. `("/static/icon-512.png", "512x512", "image/png")`.
+    pub fn with_icon(
+        mut self,
+        src: impl Into<String>,
+        sizes: impl Into<String>,
+        kind: impl Into<String>,
+    ) -> Self {
+        self.icons.push(ManifestIcon {
+            src: src.into(),
+            sizes: sizes.into(),
+            kind: kind.into(),
+        });
+        self
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ManifestIcon {
+    pub src: String,
+    pub sizes: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+/// Routes for the favicon, apple touch icons and web app manifest, which browsers request on
+/// their own.
+///
+/// The icons are served from `favicon.ico` and `apple-touch-icon.png` in `static_dir`. Apps
+/// without them get an empty response rather than a 404.
+pub fn routes<App: app::App<AC>, AC: CloneableAppContext>(static_dir: &Path) -> Router<AC> {
+    Router::new()
+        .route("/favicon.ico", get(favicon))
+        .route("/apple-touch-icon.png", get(apple_touch_icon))
+        .route("/apple-touch-icon-precomposed.png", get(apple_touch_icon))
+        .route(MANIFEST_PATH, get(manifest::<App, AC>))
+        .layer(Extension(IconsDir(static_dir.to_path_buf())))
+}
+
+pub async fn favicon(Extension(dir): Extension<IconsDir>) -> Response {
+    icon(&dir, "favicon.ico", "image/x-icon").await
+}
+
+pub async fn apple_touch_icon(Extension(dir): Extension<IconsDir>) -> Response {
+    icon(&dir, "apple-touch-icon.png", "image/png").await
+}
+
+pub async fn manifest<App: app::App<AC>, AC: CloneableAppContext>() -> impl IntoResponse {
+    match serde_json::to_string(&App::web_manifest()) {
+        Ok(manifest) => (
+            [
+                (CONTENT_TYPE, "application/manifest+json"),
+                (CACHE_CONTROL, ICON_CACHE_CONTROL),
+            ],
+            manifest,
+        )
+            .into_response(),
+        Err(e) => {
+            warn!("couldn't serialize the web manifest: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn icon(IconsDir(dir): &IconsDir, name: &str, content_type: &'static str) -> Response {
+    match tokio::fs::read(dir.join(name)).await {
+        Ok(icon) => (
+            [
+                (CONTENT_TYPE, content_type),
+                (CACHE_CONTROL, ICON_CACHE_CONTROL),
+            ],
+            icon,
+        )
+            .into_response(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            ([(CACHE_CONTROL, ICON_CACHE_CONTROL)], StatusCode::NO_CONTENT).into_response()
+        }
+        Err(e) => {
+            warn!("couldn't read icon {name}: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
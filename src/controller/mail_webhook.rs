@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Extension, Router};
+use constant_time_eq::constant_time_eq;
+
+use crate::context::CloneableAppContext;
+use crate::error::LowboyError;
+use crate::mailer::webhook::{self, SesNotification};
+use crate::mailer::Transport;
+
+#[derive(Clone)]
+struct WebhookSecret(Arc<str>);
+
+/// Routes for the bounce and complaint notifications of mail providers, authenticated by the
+/// `secret` in their path.
+pub fn routes<AC: CloneableAppContext>(secret: &str) -> Router<AC> {
+    Router::new()
+        .route("/webhooks/mail/:transport/:secret", post(receive::<AC>))
+        .layer(Extension(WebhookSecret(Arc::from(secret))))
+}
+
+pub async fn receive<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    Extension(WebhookSecret(expected)): Extension<WebhookSecret>,
+    Path((transport, secret)): Path<(Transport, String)>,
+    body: Bytes,
+) -> Result<StatusCode, LowboyError> {
+    if !constant_time_eq(secret.as_bytes(), expected.as_bytes()) {
+        return Err(LowboyError::NotFound);
+    }
+
+    let failures = match transport {
+        Transport::Ses => match webhook::parse_ses(&body).map_err(|_| LowboyError::BadRequest)? {
+            SesNotification::SubscriptionConfirmation { subscribe_url } => {
+                webhook::confirm_ses_subscription(&subscribe_url).await?;
+                return Ok(StatusCode::NO_CONTENT);
+            }
+            SesNotification::Failures(failures) => failures,
+        },
+        Transport::SendGrid => webhook::parse_sendgrid(&body).map_err(|_| LowboyError::BadRequest)?,
+        Transport::Mailgun => webhook::parse_mailgun(&body).map_err(|_| LowboyError::BadRequest)?,
+        Transport::Smtp | Transport::Mailbox => return Err(LowboyError::NotFound),
+    };
+
+    for failure in &failures {
+        context.record_delivery_failure(failure).await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,61 @@
+use axum::extract::Path;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use diesel::result::OptionalExtension as _;
+
+use crate::context::CloneableAppContext;
+use crate::error::LowboyError;
+use crate::extract::DatabaseConnection;
+use crate::lowboy_view;
+use crate::mailer::MailTemplates;
+use crate::model::{MailboxMessage, Model as _};
+use crate::view::mailbox::{Mailbox, MailboxMessageView, MailPreview};
+
+/// Routes for viewing email captured by the [`crate::mailer::Mailbox`] transport, and previews of
+/// the mail templates.
+pub fn routes<AC: CloneableAppContext>() -> Router<AC> {
+    Router::new()
+        .route("/dev/mailbox", get(list))
+        .route("/dev/mailbox/:id", get(show))
+        .route("/dev/mailbox/preview/:name", get(preview))
+}
+
+pub async fn list(
+    DatabaseConnection(mut conn): DatabaseConnection,
+) -> Result<impl IntoResponse, LowboyError> {
+    let messages = MailboxMessage::list(&mut conn, None).await?;
+    let previews = MailTemplates::global()
+        .previews()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+
+    Ok(lowboy_view!(Mailbox { messages, previews }, {
+        "title" => "Mailbox",
+    }))
+}
+
+pub async fn show(
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let Some(message) = MailboxMessage::load(id, &mut conn).await.optional()? else {
+        return Err(LowboyError::NotFound);
+    };
+
+    Ok(lowboy_view!(MailboxMessageView { message }, {
+        "title" => "Mailbox",
+    }))
+}
+
+pub async fn preview(Path(name): Path<String>) -> Result<impl IntoResponse, LowboyError> {
+    let Some(mail) = MailTemplates::global().render_preview(&name) else {
+        return Err(LowboyError::NotFound);
+    };
+    let mail = mail?;
+
+    Ok(lowboy_view!(MailPreview { name, mail }, {
+        "title" => "Mailbox",
+    }))
+}
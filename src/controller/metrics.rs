@@ -0,0 +1,12 @@
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+
+use crate::context::CloneableAppContext;
+
+pub async fn metrics<AC: CloneableAppContext>(State(context): State<AC>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        crate::metrics::render(context.database()),
+    )
+}
@@ -1,4 +1,27 @@
+#[cfg(feature = "admin")]
+pub mod admin;
+pub mod announcements;
+mod assets;
 pub mod auth;
+pub mod draft;
+#[cfg(feature = "sse")]
 mod events;
+mod health;
+pub mod icons;
+pub mod mail_webhook;
+pub mod mailbox;
+mod metrics;
+pub mod preferences;
+#[cfg(all(unix, feature = "profiling"))]
+pub mod profile;
+pub mod session;
+mod version;
 
+pub use assets::{CLIENT_PATH, STYLES_PATH};
+pub use version::{BuildInfo, VERSION_HEADER};
+pub(crate) use assets::*;
+#[cfg(feature = "sse")]
 pub(crate) use events::*;
+pub(crate) use health::*;
+pub(crate) use metrics::*;
+pub(crate) use version::*;
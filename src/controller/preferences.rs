@@ -0,0 +1,146 @@
+use anyhow::anyhow;
+use axum::extract::State;
+use axum::response::{IntoResponse, Redirect};
+use axum::routing::{get, post};
+use axum::{Form, Router};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::context::CloneableAppContext;
+use crate::error::LowboyError;
+use crate::extract::{DatabaseConnection, Flash};
+use crate::lowboy_view;
+use crate::model::{
+    NotificationPreferences, Preferences, Theme, UserPreference, LOCALE_PREFERENCE,
+    THEME_PREFERENCE, TIMEZONE_PREFERENCE,
+};
+use crate::view::preferences::AccountPreferences;
+use crate::AuthSession;
+
+/// Passwords shorter than this are refused.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Routes for users to set their display and notification preferences, and change their password.
+pub fn routes<AC: CloneableAppContext>() -> Router<AC> {
+    Router::new()
+        .route("/account/preferences", get(edit).post(update))
+        .route("/account/password", post(change_password::<AC>))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreferencesForm {
+    theme: Theme,
+    #[serde(default)]
+    locale: String,
+    timezone: String,
+    // Checkboxes are only submitted when they're checked.
+    new_device_login: Option<String>,
+    failed_login_streak: Option<String>,
+    password_changed: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordForm {
+    #[serde(default)]
+    current_password: String,
+    password: String,
+    password_confirmation: String,
+}
+
+pub async fn edit(
+    AuthSession { user, .. }: AuthSession,
+    DatabaseConnection(mut conn): DatabaseConnection,
+) -> Result<impl IntoResponse, LowboyError> {
+    let user = user.ok_or(LowboyError::Unauthorized)?;
+    let preferences = Preferences::for_user(user.id, &mut conn).await?;
+    let notifications = NotificationPreferences::for_user(user.id, &mut conn).await?;
+
+    let view = AccountPreferences {
+        theme: preferences.theme(),
+        locale: preferences.locale().unwrap_or_default().to_string(),
+        timezone: preferences.timezone(),
+        notifications,
+        has_password: user.password.is_some(),
+    };
+
+    Ok(lowboy_view!(view, {
+        "title" => "Preferences",
+    }))
+}
+
+pub async fn update(
+    AuthSession { user, .. }: AuthSession,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    messages: Flash,
+    Form(input): Form<PreferencesForm>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let user = user.ok_or(LowboyError::Unauthorized)?;
+
+    let Ok(timezone) = input.timezone.parse::<Tz>() else {
+        messages.error("Unknown timezone");
+        return Ok(Redirect::to("/account/preferences"));
+    };
+
+    UserPreference::set(user.id, THEME_PREFERENCE, &input.theme.to_string(), &mut conn).await?;
+    UserPreference::set(user.id, TIMEZONE_PREFERENCE, timezone.name(), &mut conn).await?;
+
+    let locale = input.locale.trim();
+    if locale.is_empty() {
+        UserPreference::unset(user.id, LOCALE_PREFERENCE, &mut conn).await?;
+    } else {
+        UserPreference::set(user.id, LOCALE_PREFERENCE, locale, &mut conn).await?;
+    }
+
+    NotificationPreferences {
+        user_id: user.id,
+        new_device_login: input.new_device_login.is_some(),
+        failed_login_streak: input.failed_login_streak.is_some(),
+        password_changed: input.password_changed.is_some(),
+    }
+    .save(&mut conn)
+    .await?;
+
+    messages.success("Your preferences have been saved.");
+
+    Ok(Redirect::to("/account/preferences"))
+}
+
+pub async fn change_password<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    mut auth_session: AuthSession,
+    messages: Flash,
+    Form(input): Form<PasswordForm>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let user = auth_session.user.clone().ok_or(LowboyError::Unauthorized)?;
+    if user.service_account {
+        return Err(LowboyError::BadRequest);
+    }
+
+    if let Some(hash) = user.password.clone() {
+        let current = input.current_password;
+        if !context.password_hasher().verify(current, hash).await? {
+            messages.error("Your current password is incorrect.");
+            return Ok(Redirect::to("/account/preferences"));
+        }
+    }
+
+    if input.password.chars().count() < MIN_PASSWORD_LENGTH {
+        messages.error(format!("Your password must be at least {MIN_PASSWORD_LENGTH} characters."));
+        return Ok(Redirect::to("/account/preferences"));
+    }
+
+    if input.password != input.password_confirmation {
+        messages.error("The passwords don't match.");
+        return Ok(Redirect::to("/account/preferences"));
+    }
+
+    let user = context.change_password(&user, &input.password).await?;
+    auth_session
+        .login(&user)
+        .await
+        .map_err(|e| anyhow!("Error logging in user({}): {e}", user.id))?;
+
+    messages.success("Your password has been changed.");
+
+    Ok(Redirect::to("/account/preferences"))
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use axum::extract::Query;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use serde::Deserialize;
+
+use crate::context::CloneableAppContext;
+use crate::error::LowboyError;
+
+/// The permission required to profile the app, see [`crate::model::LOWBOY_PERMISSIONS`].
+pub const PROFILE_SITE: &str = "profile site";
+
+/// Profiles are captured for at most this long.
+const MAX_SECONDS: u64 = 60;
+
+/// Routes for profiling the app while investigating its performance, e.g. with a load test running
+/// against it. Only served by debug builds with `profiling` enabled, to users with
+/// [`PROFILE_SITE`].
+///
+/// `/debug/pprof/profile?seconds=10` samples the CPU for `seconds`, and responds with a
+/// flamegraph of where the whole process spent its time.
+pub fn routes<AC: CloneableAppContext>() -> Router<AC> {
+    Router::new()
+        .route("/debug/pprof/profile", get(profile))
+        .route_layer(crate::permission_required!(PROFILE_SITE))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProfileQuery {
+    #[serde(default = "ProfileQuery::default_seconds")]
+    seconds: u64,
+    /// Samples per second.
+    #[serde(default = "ProfileQuery::default_frequency")]
+    frequency: i32,
+}
+
+impl ProfileQuery {
+    fn default_seconds() -> u64 {
+        10
+    }
+
+    fn default_frequency() -> i32 {
+        99
+    }
+}
+
+pub async fn profile(
+    Query(query): Query<ProfileQuery>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let seconds = query.seconds.clamp(1, MAX_SECONDS);
+    let frequency = query.frequency.clamp(1, 1000);
+
+    // Sampled on a thread of its own, as the profiler can't be held across awaits.
+    let flamegraph = tokio::task::spawn_blocking(move || capture(seconds, frequency)).await??;
+
+    Ok(([(CONTENT_TYPE, "image/svg+xml")], flamegraph))
+}
+
+fn capture(seconds: u64, frequency: i32) -> Result<Vec<u8>, LowboyError> {
+    // Only one profile can be captured at a time.
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(frequency)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()
+        .map_err(|_| LowboyError::Conflict)?;
+
+    std::thread::sleep(Duration::from_secs(seconds));
+
+    let report = guard
+        .report()
+        .build()
+        .map_err(|e| LowboyError::Internal(anyhow!("couldn't build profile: {e}")))?;
+    drop(guard);
+
+    let mut flamegraph = Vec::new();
+    report
+        .flamegraph(&mut flamegraph)
+        .map_err(|e| LowboyError::Internal(anyhow!("couldn't render flamegraph: {e}")))?;
+
+    Ok(flamegraph)
+}
@@ -0,0 +1,56 @@
+use axum::extract::{Path, State};
+use axum::response::{IntoResponse, Redirect};
+use axum::routing::{get, post};
+use axum::Router;
+use tower_sessions::Session;
+
+use crate::context::CloneableAppContext;
+use crate::error::LowboyError;
+use crate::extract::{DatabaseConnection, Flash};
+use crate::lowboy_view;
+use crate::session::ActiveSession;
+use crate::view::session::ActiveSessions;
+use crate::AuthSession;
+
+/// Routes for users to review, and log out of, the sessions they're logged in to.
+pub fn routes<AC: CloneableAppContext>() -> Router<AC> {
+    Router::new()
+        .route("/account/sessions", get(list::<AC>))
+        .route("/account/sessions/:id/revoke", post(revoke))
+}
+
+pub async fn list<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    AuthSession { user, .. }: AuthSession,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    session: Session,
+) -> Result<impl IntoResponse, LowboyError> {
+    let user = user.ok_or(LowboyError::Unauthorized)?;
+    let sessions = ActiveSession::list_for_user(user.id, context.clock().now(), &mut conn).await?;
+
+    let view = ActiveSessions {
+        sessions,
+        current: session.id().map(|id| id.to_string()),
+    };
+
+    Ok(lowboy_view!(view, {
+        "title" => "Active Sessions",
+    }))
+}
+
+pub async fn revoke(
+    AuthSession { user, .. }: AuthSession,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    messages: Flash,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, LowboyError> {
+    let user = user.ok_or(LowboyError::Unauthorized)?;
+
+    if !ActiveSession::revoke(&id, user.id, &mut conn).await? {
+        return Err(LowboyError::NotFound);
+    }
+
+    messages.success("The session has been logged out.");
+
+    Ok(Redirect::to("/account/sessions"))
+}
@@ -0,0 +1,72 @@
+use axum::http::HeaderValue;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+
+use crate::app;
+use crate::context::CloneableAppContext;
+
+/// The header [`version_header`] adds the app's git SHA to responses in.
+pub const VERSION_HEADER: &str = "x-lowboy-version";
+
+/// What was deployed, to check a deploy went out. Apps provide theirs with
+/// [`crate::App::build_info`].
+#[derive(Clone, Debug, Serialize)]
+pub struct BuildInfo {
+    pub app: &'static str,
+    pub git_sha: &'static str,
+    pub build_timestamp: &'static str,
+    pub rustc_version: &'static str,
+}
+
+impl BuildInfo {
+    /// The build info of an app that doesn't provide it.
+    pub fn unknown(app: &'static str) -> Self {
+        Self {
+            app,
+            git_sha: "unknown",
+            build_timestamp: "unknown",
+            rustc_version: "unknown",
+        }
+    }
+}
+
+/// The [`BuildInfo`] of the crate this is expanded in, read from the variables its build script
+/// emits with [vergen](https://docs.rs/vergen-gitcl), i.e. `VERGEN_GIT_SHA`,
+/// `VERGEN_BUILD_TIMESTAMP` and `VERGEN_RUSTC_SEMVER`. The ones it doesn't emit are `unknown`.
+///
+/// ```ignore
+/// fn build_info() -> BuildInfo {
+///     lowboy::build_info!()
+/// }
+/// ```
+#[macro_export]
+macro_rules! build_info {
+    () => {
+        $crate::controller::BuildInfo {
+            app: ::core::env!("CARGO_PKG_NAME"),
+            git_sha: $crate::build_info!(@env "VERGEN_GIT_SHA"),
+            build_timestamp: $crate::build_info!(@env "VERGEN_BUILD_TIMESTAMP"),
+            rustc_version: $crate::build_info!(@env "VERGEN_RUSTC_SEMVER"),
+        }
+    };
+    (@env $name:literal) => {
+        match ::core::option_env!($name) {
+            Some(value) => value,
+            None => "unknown",
+        }
+    };
+}
+
+pub async fn version<App: app::App<AC>, AC: CloneableAppContext>() -> impl IntoResponse {
+    Json(App::build_info())
+}
+
+pub async fn version_header<App: app::App<AC>, AC: CloneableAppContext>(
+    mut response: Response,
+) -> Response {
+    if let Ok(sha) = HeaderValue::from_str(App::build_info().git_sha) {
+        response.headers_mut().insert(VERSION_HEADER, sha);
+    }
+    response
+}
@@ -0,0 +1,368 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use diesel::sql_types::Text;
+use diesel::sqlite::SqliteConnection;
+use diesel::{QueryResult, QueryableByName};
+use diesel_async::scoped_futures::ScopedBoxFuture;
+use diesel_async::{AsyncConnection, RunQueryDsl, SimpleAsyncConnection};
+use futures::future::BoxFuture;
+
+use crate::{AttachedDatabase, Config, Connection, JournalMode, Synchronous, TempStore};
+
+/// The pragmas applied to every database connection when it's opened.
+#[derive(Clone, Debug)]
+pub struct Pragmas {
+    pub journal_mode: JournalMode,
+    pub synchronous: Synchronous,
+    /// Milliseconds to wait for a locked database.
+    pub busy_timeout: u64,
+    pub cache_size: Option<i64>,
+    pub temp_store: TempStore,
+    pub mmap_size: Option<u64>,
+    /// Refuse to write to the database.
+    pub query_only: bool,
+}
+
+impl Pragmas {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            journal_mode: config.database_journal_mode,
+            synchronous: config.database_synchronous,
+            busy_timeout: config.database_busy_timeout,
+            cache_size: config.database_cache_size,
+            temp_store: config.database_temp_store,
+            mmap_size: config.database_mmap_size,
+            query_only: false,
+        }
+    }
+
+    /// Pragmas for connections which only read, such as those to a replica.
+    ///
+    /// The journal mode is left as it is, since changing it writes to the database.
+    pub fn read_only(self) -> Self {
+        Self {
+            query_only: true,
+            ..self
+        }
+    }
+
+    /// The statements setting these pragmas, to run with `batch_execute`.
+    pub fn to_sql(&self) -> String {
+        let mut sql = String::new();
+
+        if !self.query_only {
+            sql += &format!("PRAGMA journal_mode = {};\n", self.journal_mode);
+        }
+        sql += &format!("PRAGMA synchronous = {};\n", self.synchronous);
+        sql += "PRAGMA foreign_keys = ON;\n";
+        sql += &format!("PRAGMA busy_timeout = {};\n", self.busy_timeout);
+        if let Some(cache_size) = self.cache_size {
+            sql += &format!("PRAGMA cache_size = {cache_size};\n");
+        }
+        sql += &format!("PRAGMA temp_store = {};\n", self.temp_store);
+        if let Some(mmap_size) = self.mmap_size {
+            sql += &format!("PRAGMA mmap_size = {mmap_size};\n");
+        }
+        if self.query_only {
+            sql += "PRAGMA query_only = ON;\n";
+        }
+
+        sql
+    }
+}
+
+/// Atomic scopes which nest inside an existing transaction.
+pub trait SavepointExt {
+    /// Run `callback` in a savepoint when already in a transaction, or in a transaction of its own
+    /// otherwise.
+    ///
+    /// If `callback` fails, only its changes are rolled back and the error is returned, leaving
+    /// the enclosing transaction usable, so callers can recover from it (or propagate it to roll
+    /// back everything). If it succeeds, its changes are released into the enclosing transaction
+    /// and are committed (or rolled back) along with it.
+    ///
+    /// ```ignore
+    /// conn.savepoint(|conn| {
+    ///     async move { CreateUserRecord::new(username).save(conn).await }.scope_boxed()
+    /// })
+    /// .await?;
+    /// ```
+    fn savepoint<'a, 'conn, R, E, F>(
+        &'conn mut self,
+        callback: F,
+    ) -> BoxFuture<'conn, Result<R, E>>
+    where
+        F: for<'r> FnOnce(&'r mut Connection) -> ScopedBoxFuture<'a, 'r, Result<R, E>>
+            + Send
+            + 'a,
+        E: From<diesel::result::Error> + Send + 'a,
+        R: Send + 'a,
+        'a: 'conn;
+}
+
+impl SavepointExt for Connection {
+    fn savepoint<'a, 'conn, R, E, F>(
+        &'conn mut self,
+        callback: F,
+    ) -> BoxFuture<'conn, Result<R, E>>
+    where
+        F: for<'r> FnOnce(&'r mut Connection) -> ScopedBoxFuture<'a, 'r, Result<R, E>>
+            + Send
+            + 'a,
+        E: From<diesel::result::Error> + Send + 'a,
+        R: Send + 'a,
+        'a: 'conn,
+    {
+        // Diesel's transaction manager uses a savepoint for transactions begun within another.
+        self.transaction(callback)
+    }
+}
+
+/// Attach `database` to `conn`, setting its journal mode to match the main database's.
+pub async fn attach(
+    conn: &mut Connection,
+    database: &AttachedDatabase,
+    pragmas: &Pragmas,
+) -> QueryResult<()> {
+    diesel::sql_query("ATTACH DATABASE ? AS ?")
+        .bind::<Text, _>(&database.url)
+        .bind::<Text, _>(&database.name)
+        .execute(conn)
+        .await?;
+
+    // The journal mode is set per database, the other pragmas apply to the whole connection.
+    if !pragmas.query_only {
+        let name = database.name.replace('"', "\"\"");
+        conn.batch_execute(&format!(
+            "PRAGMA \"{name}\".journal_mode = {};",
+            pragmas.journal_mode
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// List the tables and columns lowboy expects, referencing every column through
+/// [`crate::schema`] so the list can't name a column that doesn't exist there.
+macro_rules! expected_schema {
+    ($($table:ident($($column:ident),+ $(,)?)),+ $(,)?) => {
+        &[$((
+            stringify!($table),
+            &[$(<crate::schema::$table::$column as diesel::Column>::NAME),+],
+        )),+]
+    };
+}
+
+/// The tables and columns lowboy's queries expect.
+const EXPECTED_SCHEMA: &[(&str, &[&str])] = expected_schema! {
+    user(id, username, password, access_token, service_account),
+    email(id, user_id, address, verified),
+    token(id, user_id, secret, expiration, purpose),
+    permission(id, name, description),
+    role(id, name, description, system),
+    role_hierarchy(parent_id, child_id),
+    role_permission(role_id, permission_id),
+    user_role(user_id, role_id),
+    mailbox_message(id, sender, recipient, subject, text, html, created_at),
+    notification_preferences(user_id, new_device_login, failed_login_streak, password_changed),
+    known_device(id, user_id, user_agent, ip, created_at, last_seen_at),
+    audit_log(id, request_id, real_user_id, effective_user_id, action, subject, created_at),
+    draft(id, user_id, form, content, updated_at, expires_at),
+    user_preference(id, user_id, key, value),
+    sent_email(
+        id,
+        recipient,
+        template,
+        subject,
+        status,
+        provider_message_id,
+        error,
+        created_at,
+        updated_at
+    ),
+    email_suppression(id, address, reason, detail, created_at),
+    digest_opt_out(user_id, digest),
+    dead_letter(id, job, payload, error, created_at),
+    queued_job(
+        id,
+        job,
+        payload,
+        unique_key,
+        run_at,
+        created_at,
+        attempts,
+        last_error,
+        locked_until
+    ),
+    setting(id, key, value, updated_at),
+    kv_entry(id, key, value, expires_at, updated_at),
+    announcement(id, message, level, starts_at, ends_at, role_id, announced_at, created_at),
+};
+
+/// A difference between the live database and the schema lowboy expects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchemaDrift {
+    MissingTable(&'static str),
+    MissingColumn {
+        table: &'static str,
+        column: &'static str,
+    },
+}
+
+impl fmt::Display for SchemaDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingTable(table) => write!(f, "table `{table}` is missing"),
+            Self::MissingColumn { table, column } => {
+                write!(f, "column `{column}` is missing from table `{table}`")
+            }
+        }
+    }
+}
+
+/// The database schema drifted, and [`SchemaCheck::Abort`](crate::SchemaCheck::Abort) is set.
+#[derive(Debug, thiserror::Error)]
+#[error("database schema has drifted from lowboy's schema ({} differences)", .0.len())]
+pub struct SchemaDriftError(pub Vec<SchemaDrift>);
+
+#[derive(QueryableByName)]
+struct TableColumn {
+    #[diesel(sql_type = Text)]
+    name: String,
+}
+
+/// Compare the live database against the tables and columns lowboy expects, catching ones that
+/// were dropped or renamed out-of-band instead of through a migration.
+///
+/// Only presence is checked, so tables and columns added by the app are ignored.
+pub fn schema_drift(conn: &mut SqliteConnection) -> QueryResult<Vec<SchemaDrift>> {
+    let mut drift = vec![];
+
+    for &(table, columns) in EXPECTED_SCHEMA {
+        let query =
+            diesel::sql_query("SELECT name FROM pragma_table_info(?)").bind::<Text, _>(table);
+        // Spelled out, as this runs on a plain connection while diesel-async's `RunQueryDsl` is
+        // in scope.
+        let live: HashSet<String> = diesel::RunQueryDsl::load::<TableColumn>(query, conn)?
+            .into_iter()
+            .map(|column| column.name)
+            .collect();
+
+        // `pragma_table_info` has no rows for tables that don't exist.
+        if live.is_empty() {
+            drift.push(SchemaDrift::MissingTable(table));
+            continue;
+        }
+
+        drift.extend(
+            columns
+                .iter()
+                .filter(|column| !live.contains(**column))
+                .map(|&column| SchemaDrift::MissingColumn { table, column }),
+        );
+    }
+
+    Ok(drift)
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::result::Error;
+    use diesel_async::scoped_futures::ScopedFutureExt;
+
+    use super::*;
+
+    async fn connection() -> Connection {
+        let mut conn = Connection::establish(":memory:").await.unwrap();
+        conn.batch_execute("CREATE TABLE item (name TEXT NOT NULL)")
+            .await
+            .unwrap();
+
+        conn
+    }
+
+    async fn insert(name: &str, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::sql_query("INSERT INTO item (name) VALUES (?)")
+            .bind::<Text, _>(name)
+            .execute(conn)
+            .await
+    }
+
+    #[derive(QueryableByName)]
+    struct Item {
+        #[diesel(sql_type = Text)]
+        name: String,
+    }
+
+    async fn names(conn: &mut Connection) -> Vec<String> {
+        diesel::sql_query("SELECT name FROM item ORDER BY rowid")
+            .load::<Item>(conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.name)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn a_failed_savepoint_only_rolls_back_its_own_changes() {
+        let mut conn = connection().await;
+
+        conn.transaction::<_, Error, _>(|conn| {
+            async move {
+                insert("outer", conn).await?;
+                let nested = conn
+                    .savepoint::<(), Error, _>(|conn| {
+                        async move {
+                            insert("nested", conn).await?;
+                            Err(Error::RollbackTransaction)
+                        }
+                        .scope_boxed()
+                    })
+                    .await;
+                assert!(matches!(nested, Err(Error::RollbackTransaction)));
+
+                // The enclosing transaction is still usable.
+                insert("after", conn).await?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(names(&mut conn).await, ["outer", "after"]);
+    }
+
+    #[tokio::test]
+    async fn a_released_savepoint_is_rolled_back_with_its_transaction() {
+        let mut conn = connection().await;
+
+        let result = conn
+            .transaction::<(), Error, _>(|conn| {
+                async move {
+                    conn.savepoint::<_, Error, _>(|conn| insert("nested", conn).scope_boxed())
+                        .await?;
+                    Err(Error::RollbackTransaction)
+                }
+                .scope_boxed()
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert!(names(&mut conn).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_savepoint_outside_a_transaction_commits_on_its_own() {
+        let mut conn = connection().await;
+
+        conn.savepoint::<_, Error, _>(|conn| insert("alone", conn).scope_boxed())
+            .await
+            .unwrap();
+
+        assert_eq!(names(&mut conn).await, ["alone"]);
+    }
+}
@@ -8,6 +8,12 @@ use diesel::{sql_query, table, Selectable, SqliteConnection};
 use diesel_async::pooled_connection::deadpool::Pool;
 use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
 use diesel_async::RunQueryDsl;
+use tracing::warn;
+
+use crate::actor::IMPERSONATOR_KEY;
+use crate::clock::{Clock, SystemClock};
+use crate::config::OversizedSession;
+use crate::session::{SessionDevice, SESSION_DEVICE_KEY};
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -33,6 +39,10 @@ pub enum Error {
     /// A variant to map `rmp_serde` decode errors.
     #[error(transparent)]
     Decode(#[from] rmp_serde::decode::Error),
+
+    /// The session data is larger than the configured maximum size.
+    #[error("session data is {size} bytes, exceeding the limit of {max_size} bytes")]
+    TooLarge { size: usize, max_size: usize },
 }
 
 impl From<Error> for session_store::Error {
@@ -43,6 +53,7 @@ impl From<Error> for session_store::Error {
             Error::Interact(inner) => session_store::Error::Backend(inner.to_string()),
             Error::Decode(inner) => session_store::Error::Decode(inner.to_string()),
             Error::Encode(inner) => session_store::Error::Encode(inner.to_string()),
+            Error::TooLarge { .. } => session_store::Error::Encode(err.to_string()),
         }
     }
 }
@@ -52,6 +63,10 @@ table! {
         id -> Text,
         data -> Binary,
         expiry_date -> BigInt,
+        user_id -> Nullable<Integer>,
+        user_agent -> Nullable<Text>,
+        ip -> Nullable<Text>,
+        last_seen -> Nullable<BigInt>,
     }
 }
 
@@ -59,20 +74,146 @@ table! {
 #[diesel(table_name = tower_sessions)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct TowerSession {
-    id: String,
+    pub(crate) id: String,
     data: Vec<u8>,
-    expiry_date: i64,
+    pub(crate) expiry_date: i64,
+    pub(crate) user_id: Option<i32>,
+    pub(crate) user_agent: Option<String>,
+    pub(crate) ip: Option<String>,
+    pub(crate) last_seen: Option<i64>,
+}
+
+/// Session keys that are never dropped when truncating an oversized session, since losing them
+/// would log the user out or lose track of their device.
+const PROTECTED_KEYS: &[&str] = &["axum-login.data", SESSION_DEVICE_KEY, IMPERSONATOR_KEY];
+
+/// The maximum size of session data, and what to do with sessions exceeding it.
+#[derive(Clone, Copy, Debug)]
+struct SizeLimit {
+    max_size: usize,
+    oversized: OversizedSession,
+}
+
+impl Default for SizeLimit {
+    fn default() -> Self {
+        Self {
+            max_size: 64 * 1024,
+            oversized: OversizedSession::default(),
+        }
+    }
+}
+
+impl SizeLimit {
+    /// Encode `record`, applying the limit to it.
+    fn encode(&self, record: &Record) -> Result<Vec<u8>> {
+        let data = rmp_serde::to_vec(record)?;
+        if data.len() <= self.max_size {
+            return Ok(data);
+        }
+
+        let mut sizes: Vec<(&String, usize)> = record
+            .data
+            .iter()
+            .map(|(key, value)| (key, rmp_serde::to_vec(value).map_or(0, |value| value.len())))
+            .collect();
+        sizes.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+        warn!(
+            session_id = %record.id,
+            size = data.len(),
+            max_size = self.max_size,
+            largest = ?sizes.iter().take(3).collect::<Vec<_>>(),
+            "session data exceeds the maximum size"
+        );
+
+        match self.oversized {
+            OversizedSession::Warn => Ok(data),
+            OversizedSession::Reject => Err(Error::TooLarge {
+                size: data.len(),
+                max_size: self.max_size,
+            }),
+            OversizedSession::Truncate => {
+                let mut truncated = record.clone();
+                let mut data = data;
+
+                for (key, _) in sizes {
+                    if data.len() <= self.max_size {
+                        break;
+                    }
+
+                    if PROTECTED_KEYS.contains(&key.as_str()) {
+                        continue;
+                    }
+
+                    warn!(session_id = %record.id, key = %key, "dropping value from oversized session");
+                    truncated.data.remove(key);
+                    data = rmp_serde::to_vec(&truncated)?;
+                }
+
+                Ok(data)
+            }
+        }
+    }
+}
+
+impl TowerSession {
+    fn from_record(record: &Record, limit: &SizeLimit) -> Result<Self> {
+        // The device is tracked in the session data by `crate::session::track`, and copied into
+        // its own columns so sessions can be listed without decoding them.
+        let device = record
+            .data
+            .get(SESSION_DEVICE_KEY)
+            .and_then(|device| serde_json::from_value::<SessionDevice>(device.clone()).ok());
+
+        Ok(Self {
+            id: record.id.to_string(),
+            data: limit.encode(record)?,
+            expiry_date: record.expiry_date.unix_timestamp(),
+            user_id: device.as_ref().and_then(|device| device.user_id),
+            user_agent: device.as_ref().and_then(|device| device.user_agent.clone()),
+            ip: device.as_ref().and_then(|device| device.ip.clone()),
+            last_seen: device.map(|device| device.last_seen),
+        })
+    }
+}
+
+#[derive(QueryableByName)]
+struct TableColumn {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    name: String,
 }
 
 #[derive(derive_more::Debug, Clone)]
 pub struct DieselSqliteSessionStore {
     #[debug(skip)]
     database: Pool<SyncConnectionWrapper<SqliteConnection>>,
+    clock: Box<dyn Clock>,
+    limit: SizeLimit,
 }
 
 impl DieselSqliteSessionStore {
     pub fn new(database: Pool<SyncConnectionWrapper<SqliteConnection>>) -> Self {
-        Self { database }
+        Self {
+            database,
+            clock: Box::new(SystemClock),
+            limit: SizeLimit::default(),
+        }
+    }
+
+    /// Use `clock` to determine whether sessions have expired.
+    pub fn with_clock(self, clock: Box<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// Limit session data to `max_size` bytes, handling larger sessions according to `oversized`.
+    pub fn with_max_size(self, max_size: usize, oversized: OversizedSession) -> Self {
+        Self {
+            limit: SizeLimit {
+                max_size,
+                oversized,
+            },
+            ..self
+        }
     }
 
     /// Migrate the session schema.
@@ -92,6 +233,29 @@ impl DieselSqliteSessionStore {
             .await
             .map_err(Error::Diesel)?;
 
+        // Add the device tracking columns to session tables created before they existed.
+        let columns: Vec<String> = sql_query("select name from pragma_table_info('tower_sessions')")
+            .load::<TableColumn>(&mut conn)
+            .await
+            .map_err(Error::Diesel)?
+            .into_iter()
+            .map(|column| column.name)
+            .collect();
+
+        for (column, kind) in [
+            ("user_id", "integer"),
+            ("user_agent", "text"),
+            ("ip", "text"),
+            ("last_seen", "integer"),
+        ] {
+            if !columns.iter().any(|name| name == column) {
+                sql_query(format!("alter table tower_sessions add column {column} {kind}"))
+                    .execute(&mut conn)
+                    .await
+                    .map_err(Error::Diesel)?;
+            }
+        }
+
         Ok(())
     }
 }
@@ -101,7 +265,7 @@ impl ExpiredDeletion for DieselSqliteSessionStore {
     async fn delete_expired(&self) -> session_store::Result<()> {
         let mut conn = self.database.get().await.map_err(Error::Pool)?;
         diesel::delete(tower_sessions::table)
-            .filter(tower_sessions::expiry_date.lt(chrono::Utc::now().timestamp()))
+            .filter(tower_sessions::expiry_date.lt(self.clock.now().timestamp()))
             .execute(&mut conn)
             .await
             .map_err(Error::Diesel)?;
@@ -115,12 +279,9 @@ impl SessionStore for DieselSqliteSessionStore {
         async fn try_create_with_conn(
             conn: &mut SyncConnectionWrapper<SqliteConnection>,
             record: &Record,
+            limit: &SizeLimit,
         ) -> Result<bool> {
-            let new_session = TowerSession {
-                id: record.id.to_string(),
-                data: rmp_serde::to_vec(&record)?,
-                expiry_date: record.expiry_date.unix_timestamp(),
-            };
+            let new_session = TowerSession::from_record(record, limit)?;
             let res = diesel::insert_into(tower_sessions::table)
                 .values(&new_session)
                 .execute(conn)
@@ -141,7 +302,7 @@ impl SessionStore for DieselSqliteSessionStore {
 
         let mut conn = self.database.get().await.map_err(Error::Pool)?;
 
-        while !try_create_with_conn(&mut conn, record).await? {
+        while !try_create_with_conn(&mut conn, record, &self.limit).await? {
             record.id = Id::default(); // Generate a new ID
         }
 
@@ -152,12 +313,9 @@ impl SessionStore for DieselSqliteSessionStore {
         async fn save_with_conn(
             conn: &mut SyncConnectionWrapper<SqliteConnection>,
             record: &Record,
+            limit: &SizeLimit,
         ) -> Result<()> {
-            let new_session = TowerSession {
-                id: record.id.to_string(),
-                data: rmp_serde::to_vec(&record)?,
-                expiry_date: record.expiry_date.unix_timestamp(),
-            };
+            let new_session = TowerSession::from_record(record, limit)?;
             diesel::insert_into(tower_sessions::table)
                 .values(&new_session)
                 .on_conflict(tower_sessions::id)
@@ -165,6 +323,10 @@ impl SessionStore for DieselSqliteSessionStore {
                 .set((
                     tower_sessions::expiry_date.eq(new_session.expiry_date),
                     tower_sessions::data.eq(new_session.data.clone()),
+                    tower_sessions::user_id.eq(new_session.user_id),
+                    tower_sessions::user_agent.eq(new_session.user_agent.clone()),
+                    tower_sessions::ip.eq(new_session.ip.clone()),
+                    tower_sessions::last_seen.eq(new_session.last_seen),
                 ))
                 .execute(conn)
                 .await
@@ -174,7 +336,7 @@ impl SessionStore for DieselSqliteSessionStore {
         }
         let mut conn = self.database.get().await.map_err(Error::Pool)?;
 
-        save_with_conn(&mut conn, record).await?;
+        save_with_conn(&mut conn, record, &self.limit).await?;
 
         Ok(())
     }
@@ -182,11 +344,12 @@ impl SessionStore for DieselSqliteSessionStore {
     async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
         let mut conn = self.database.get().await.map_err(Error::Pool)?;
 
-        let session = tower_sessions::dsl::tower_sessions
-            .filter(tower_sessions::id.eq(session_id.to_string()))
-            .filter(tower_sessions::expiry_date.gt(chrono::Utc::now().timestamp()))
-            .get_result::<TowerSession>(&mut conn)
-            .await;
+        let session = load_session(
+            &session_id.to_string(),
+            self.clock.now().timestamp(),
+            &mut conn,
+        )
+        .await;
 
         if let Ok(session) = session {
             Ok(Some(
@@ -209,3 +372,20 @@ impl SessionStore for DieselSqliteSessionStore {
         Ok(())
     }
 }
+
+async fn load_session(
+    session_id: &str,
+    now: i64,
+    conn: &mut SyncConnectionWrapper<SqliteConnection>,
+) -> QueryResult<TowerSession> {
+    tower_sessions::dsl::tower_sessions
+        .filter(tower_sessions::id.eq(session_id))
+        .filter(tower_sessions::expiry_date.gt(now))
+        .get_result::<TowerSession>(conn)
+        .await
+}
+
+/// Prepare the statement used to load sessions, so it's cached on the connection.
+pub(crate) async fn warm_up(conn: &mut SyncConnectionWrapper<SqliteConnection>) {
+    let _ = load_session("", 0, conn).await;
+}
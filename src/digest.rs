@@ -0,0 +1,200 @@
+//! Periodic email digests, e.g. a weekly summary of activity.
+//!
+//! Apps register their [`Digest`]s with [`crate::App::digests`], and [`crate::Lowboy::serve`]
+//! schedules each one. When it runs, a [`SendDigest`] job is queued for every user with a
+//! verified email, unless they opted out with [`NotificationPreferences::set_digest`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use anyhow::Context as _;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::context::{AppContext, Context};
+use crate::jobs::{Job, JobQueue};
+use crate::mailer::{Mail, RenderedMail};
+use crate::metrics;
+use crate::model::{Model, NotificationPreferences, User, UserModel};
+
+/// How many users are loaded at a time while sending a digest.
+pub const BATCH_SIZE: i64 = 100;
+
+/// The app's digests, by name, so [`SendDigest`] jobs can find theirs.
+static DIGESTS: LazyLock<RwLock<HashMap<&'static str, Arc<dyn Digest>>>> =
+    LazyLock::new(Default::default);
+
+/// How often a digest is sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DigestPeriod {
+    /// Every day at 08:00 UTC.
+    Daily,
+    /// Every Monday at 08:00 UTC.
+    Weekly,
+}
+
+impl DigestPeriod {
+    /// The cron schedule the digest is sent on.
+    pub fn cron(&self) -> &'static str {
+        match self {
+            Self::Daily => "0 0 8 * * *",
+            Self::Weekly => "0 0 8 * * Mon",
+        }
+    }
+
+    /// The time a digest covers.
+    pub fn duration(&self) -> TimeDelta {
+        match self {
+            Self::Daily => TimeDelta::days(1),
+            Self::Weekly => TimeDelta::weeks(1),
+        }
+    }
+}
+
+/// A digest email, built for each user when it's sent.
+#[async_trait::async_trait]
+pub trait Digest: Send + Sync + 'static {
+    /// The digest's name, e.g. `weekly_activity`. Opt-outs are stored by name, and sent digests
+    /// are tracked as being rendered from a template with this name.
+    fn name(&self) -> &'static str;
+
+    fn period(&self) -> DigestPeriod;
+
+    /// The cron schedule the digest is sent on. Defaults to the period's.
+    fn schedule(&self) -> &'static str {
+        self.period().cron()
+    }
+
+    /// Build `user`'s digest of what happened since `since`, usually rendered with
+    /// [`crate::mailer::render_mail`]. Returning `None` skips the user, e.g. when there's nothing
+    /// to tell them about.
+    async fn build(
+        &self,
+        context: &dyn AppContext,
+        user: &User,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<Option<RenderedMail>>;
+}
+
+/// How queueing a digest went.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DigestReport {
+    pub queued: usize,
+    /// Users who opted out, or whose digest for the period was already queued.
+    pub skipped: usize,
+}
+
+/// Sends one user their digest, queued by [`send`]. It's unique per digest, user and period, so
+/// sending a digest again before its jobs have run doesn't email anyone twice.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SendDigest {
+    pub digest: String,
+    pub user_id: i32,
+    pub since: DateTime<Utc>,
+}
+
+#[async_trait::async_trait]
+impl Job for SendDigest {
+    const NAME: &'static str = "send_digest";
+
+    fn unique_key(&self) -> Option<String> {
+        Some(format!("{}:{}:{}:{}", Self::NAME, self.digest, self.user_id, self.since.date_naive()))
+    }
+
+    async fn run(&self, context: &dyn AppContext) -> anyhow::Result<()> {
+        let Some(mailer) = context.mailer() else {
+            return Ok(());
+        };
+        let digest = DIGESTS
+            .read()
+            .expect("digests lock should not be poisoned")
+            .get(self.digest.as_str())
+            .cloned()
+            .with_context(|| format!("no digest is registered as `{}`", self.digest))?;
+        let user = {
+            let mut conn = metrics::checkout(context.database()).await?;
+            User::load(self.user_id, &mut conn).await?
+        };
+
+        let Some(rendered) = digest.build(context, &user, self.since).await? else {
+            return Ok(());
+        };
+        let mail = Mail {
+            template: Some(digest.name().to_string()),
+            ..Mail::new(context.mail_from(), format!("<{}>", user.email()), rendered)
+        };
+        mailer.send(&mail).await?;
+
+        Ok(())
+    }
+}
+
+/// Set the digests [`SendDigest`] jobs can send, i.e. the app's [`crate::App::digests`].
+pub(crate) fn register(digests: &[Arc<dyn Digest>]) {
+    let mut registered = DIGESTS.write().expect("digests lock should not be poisoned");
+    *registered = digests
+        .iter()
+        .map(|digest| (digest.name(), digest.clone()))
+        .collect();
+}
+
+/// Queue a [`SendDigest`] job for every user who hasn't opted out of `digest`.
+///
+/// Building and sending each user's digest happens in their job, so a failure is retried for
+/// just that user.
+pub async fn send(
+    context: &dyn AppContext,
+    digest: &dyn Digest,
+) -> anyhow::Result<DigestReport> {
+    if context.mailer().is_none() {
+        return Ok(DigestReport::default());
+    }
+
+    let since = context.clock().now() - digest.period().duration();
+    let mut report = DigestReport::default();
+    let mut after_id = 0;
+
+    loop {
+        let users = {
+            let mut conn = metrics::checkout(context.database()).await?;
+            User::list_verified_after(after_id, BATCH_SIZE, &mut conn).await?
+        };
+        let Some(last) = users.last() else {
+            break;
+        };
+        after_id = last.id;
+
+        for user in &users {
+            let allowed = {
+                let mut conn = metrics::checkout(context.database()).await?;
+                NotificationPreferences::allows_digest(user.id, digest.name(), &mut conn).await?
+            };
+            let job = SendDigest {
+                digest: digest.name().to_string(),
+                user_id: user.id,
+                since,
+            };
+            if allowed && context.enqueue(job).await? {
+                report.queued += 1;
+            } else {
+                report.skipped += 1;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Queue `digest`, logging how it went. This is what the scheduled job runs.
+pub async fn run(context: &dyn AppContext, digest: &dyn Digest) {
+    match metrics::track_job(digest.name(), send(context, digest)).await {
+        Ok(report) => info!(
+            digest = digest.name(),
+            queued = report.queued,
+            skipped = report.skipped,
+            "queued digest"
+        ),
+        Err(e) => warn!("couldn't queue the {} digest: {e}", digest.name()),
+    }
+}
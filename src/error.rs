@@ -1,10 +1,13 @@
+use std::any::Any;
 use std::sync::Arc;
 
 use anyhow::anyhow;
+use axum::http::header::CONTENT_TYPE;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 
-use crate::context;
+use crate::form::FormErrors;
+use crate::{collab, context, kv, mailer, settings};
 use crate::view::LowboyView;
 
 #[derive(Debug, thiserror::Error)]
@@ -12,6 +15,10 @@ pub enum LowboyError {
     #[error("Bad Request")]
     BadRequest,
 
+    /// Input which failed validation, e.g. query parameters, with what's wrong with each field.
+    #[error("Bad Request: {0}")]
+    Invalid(FormErrors),
+
     #[error("Unauthorized")]
     Unauthorized,
 
@@ -21,6 +28,13 @@ pub enum LowboyError {
     #[error("Not Found")]
     NotFound,
 
+    #[error("Conflict")]
+    Conflict,
+
+    /// A request which can't be handled, e.g. a body which is too large, with why.
+    #[error("{detail}")]
+    Rejected { status: StatusCode, detail: String },
+
     #[error("Internal Server Error: {0}")]
     Internal(#[from] anyhow::Error),
 }
@@ -47,12 +61,42 @@ impl From<tower_sessions::session::Error> for LowboyError {
     }
 }
 
+impl From<tokio::task::JoinError> for LowboyError {
+    fn from(value: tokio::task::JoinError) -> Self {
+        Self::Internal(anyhow!("task error: {value}"))
+    }
+}
+
 impl From<context::Error> for LowboyError {
     fn from(value: context::Error) -> Self {
         Self::Internal(anyhow!("context error: {value}"))
     }
 }
 
+impl From<mailer::Error> for LowboyError {
+    fn from(value: mailer::Error) -> Self {
+        Self::Internal(anyhow!("mailer error: {value}"))
+    }
+}
+
+impl From<settings::Error> for LowboyError {
+    fn from(value: settings::Error) -> Self {
+        Self::Internal(anyhow!("settings error: {value}"))
+    }
+}
+
+impl From<kv::Error> for LowboyError {
+    fn from(value: kv::Error) -> Self {
+        Self::Internal(anyhow!("key-value store error: {value}"))
+    }
+}
+
+impl From<collab::StaleVersion> for LowboyError {
+    fn from(_: collab::StaleVersion) -> Self {
+        Self::Conflict
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct ErrorWrapper(pub Arc<LowboyError>);
 
@@ -61,10 +105,12 @@ impl IntoResponse for LowboyError {
         use LowboyError::*;
 
         let code = match self {
-            BadRequest => StatusCode::BAD_REQUEST,
+            BadRequest | Invalid(_) => StatusCode::BAD_REQUEST,
             Unauthorized => StatusCode::UNAUTHORIZED,
             Forbidden => StatusCode::FORBIDDEN,
             NotFound => StatusCode::NOT_FOUND,
+            Conflict => StatusCode::CONFLICT,
+            Rejected { status, .. } => status,
             Internal(ref inner) => {
                 tracing::error!("{inner}");
                 StatusCode::INTERNAL_SERVER_ERROR
@@ -80,6 +126,51 @@ impl IntoResponse for LowboyError {
     }
 }
 
+/// Convert a panic while handling a request into an internal server error, so it's logged and the
+/// error page is shown instead of the connection being dropped.
+pub(crate) fn panic_response(panic: Box<dyn Any + Send + 'static>) -> axum::response::Response {
+    let message = if let Some(message) = panic.downcast_ref::<String>() {
+        message.as_str()
+    } else if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else {
+        "unknown panic"
+    };
+
+    LowboyError::Internal(anyhow!("handler panicked: {message}")).into_response()
+}
+
+/// An RFC 9457 `application/problem+json` response, the error format of lowboy's JSON APIs.
+pub fn problem_details(status: StatusCode, detail: impl Into<String>) -> axum::response::Response {
+    let mut problem = problem(status);
+    problem["detail"] = detail.into().into();
+
+    problem_response(status, problem)
+}
+
+/// The members of a problem details response for `status`, to add a `detail` or extension members
+/// to before responding with [`problem_response`].
+pub(crate) fn problem(status: StatusCode) -> serde_json::Value {
+    serde_json::json!({
+        "type": "about:blank",
+        "title": status.canonical_reason().unwrap_or_default(),
+        "status": status.as_u16(),
+    })
+}
+
+/// Respond with the members of a `problem`, see [`problem_details`].
+pub(crate) fn problem_response(
+    status: StatusCode,
+    problem: serde_json::Value,
+) -> axum::response::Response {
+    (
+        status,
+        [(CONTENT_TYPE, "application/problem+json")],
+        problem.to_string(),
+    )
+        .into_response()
+}
+
 pub trait LowboyErrorView: LowboyView + Clone + Default {
     fn message(&self) -> &String;
     fn set_message(&mut self, message: &str) -> &mut Self;
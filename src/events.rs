@@ -0,0 +1,175 @@
+//! The event bus behind the `/events` stream.
+//!
+//! Every subscriber, usually an open `/events` connection, gets its own buffer of events so a slow
+//! client can't stall the others. What happens when a subscriber's buffer is full is up to the
+//! [`EventOverflow`] policy.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use axum::response::sse::Event;
+use flume::{Receiver, Sender, TrySendError};
+#[cfg(feature = "sse")]
+use futures::Stream;
+
+use crate::config::EventOverflow;
+use crate::metrics::EventMetrics;
+
+struct Subscriber {
+    sender: Sender<Event>,
+    /// Kept so the oldest event can be dropped when the buffer is full.
+    receiver: Receiver<Event>,
+}
+
+/// Broadcasts events to every subscriber.
+#[derive(Clone)]
+pub struct Events {
+    capacity: usize,
+    overflow: EventOverflow,
+    send_timeout: Duration,
+    next_id: Arc<AtomicU64>,
+    subscribers: Arc<Mutex<HashMap<u64, Subscriber>>>,
+}
+
+impl Events {
+    /// An event bus buffering up to `capacity` events per subscriber. `send_timeout` is how long
+    /// [`EventOverflow::Block`] waits for a full buffer.
+    pub fn new(capacity: usize, overflow: EventOverflow, send_timeout: Duration) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            overflow,
+            send_timeout,
+            next_id: Default::default(),
+            subscribers: Default::default(),
+        }
+    }
+
+    /// Subscribe to the events sent from now on.
+    pub fn subscribe(&self) -> Subscription {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = flume::bounded(self.capacity);
+
+        self.subscribers().insert(
+            id,
+            Subscriber {
+                sender,
+                receiver: receiver.clone(),
+            },
+        );
+
+        Subscription {
+            events: self.clone(),
+            id,
+            receiver,
+        }
+    }
+
+    /// How many subscribers there are.
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers().len()
+    }
+
+    /// Send `event` to every subscriber, waiting for full buffers if the overflow policy is
+    /// [`EventOverflow::Block`].
+    pub async fn send(&self, event: Event) {
+        if self.overflow != EventOverflow::Block {
+            return self.try_send(event);
+        }
+
+        // Concurrently, so full buffers hold the send up for one `send_timeout` rather than one
+        // each.
+        let sends = self.senders().into_iter().map(|(sender, _)| {
+            let event = event.clone();
+            async move {
+                let sent = tokio::time::timeout(self.send_timeout, sender.send_async(event));
+                if let Err(_elapsed) = sent.await {
+                    EventMetrics::global().record_dropped();
+                }
+            }
+        });
+        futures::future::join_all(sends).await;
+    }
+
+    /// Send `event` to every subscriber without waiting. Full buffers are handled as
+    /// [`EventOverflow::DropNew`] if the policy is [`EventOverflow::Block`], so this is what's
+    /// used where awaiting isn't possible, e.g. in `Drop`.
+    pub fn try_send(&self, event: Event) {
+        for (sender, receiver) in self.senders() {
+            let Err(TrySendError::Full(event)) = sender.try_send(event.clone()) else {
+                continue;
+            };
+            EventMetrics::global().record_dropped();
+
+            if self.overflow == EventOverflow::DropOldest {
+                let _ = receiver.try_recv();
+                let _ = sender.try_send(event);
+            }
+        }
+    }
+
+    fn senders(&self) -> Vec<(Sender<Event>, Receiver<Event>)> {
+        self.subscribers()
+            .values()
+            .map(|subscriber| (subscriber.sender.clone(), subscriber.receiver.clone()))
+            .collect()
+    }
+
+    fn subscribers(&self) -> MutexGuard<'_, HashMap<u64, Subscriber>> {
+        self.subscribers
+            .lock()
+            .expect("event bus lock should not be poisoned")
+    }
+}
+
+/// A subscription to the event bus, which unsubscribes when dropped.
+pub struct Subscription {
+    events: Events,
+    id: u64,
+    receiver: Receiver<Event>,
+}
+
+impl Subscription {
+    /// Wait for the next event.
+    pub async fn recv(&self) -> Option<Event> {
+        self.receiver.recv_async().await.ok()
+    }
+
+    #[cfg(feature = "sse")]
+    pub fn into_stream(self) -> impl Stream<Item = Event> {
+        async_stream::stream! {
+            while let Some(event) = self.recv().await {
+                yield event;
+            }
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.events.subscribers().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::Instant;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn blocking_sends_wait_for_full_buffers_concurrently() {
+        let timeout = Duration::from_millis(200);
+        let events = Events::new(1, EventOverflow::Block, timeout);
+        let _full = [events.subscribe(), events.subscribe(), events.subscribe()];
+        events.try_send(Event::default().data("first"));
+        let open = events.subscribe();
+
+        let started = Instant::now();
+        events.send(Event::default().data("second")).await;
+
+        assert!(started.elapsed() < timeout * 2);
+        assert!(open.recv().await.is_some());
+    }
+}
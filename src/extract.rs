@@ -1,11 +1,30 @@
-use axum::extract::{FromRef, FromRequestParts};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use axum::extract::{FromRef, FromRequest, FromRequestParts, Path, Query, Request};
 use axum::http::request::Parts;
+use axum::http::header::{ACCEPT, CONTENT_TYPE};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum_messages::Messages;
 use diesel_async::pooled_connection::deadpool::{Object, Pool};
+use diesel::OptionalExtension as _;
+use diesel_async::{AsyncConnection, TransactionManager};
+use http_body_util::LengthLimitError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use validator::Validate;
 
 use crate::context::CloneableAppContext;
-use crate::error::LowboyError;
-use crate::model::{Model, UserModel};
-use crate::{app, AppContext, AuthSession, Connection};
+use crate::error::{problem, problem_details, problem_response, LowboyError};
+use crate::form::FormErrors;
+use crate::jobs::Scheduler;
+use crate::model::{LoadBySlug, Model, ModelCache, UserModel, WithRolesAndPermissions};
+use crate::{app, auth, metrics, AppContext, AuthSession, Connection};
 
 pub struct DatabaseConnection(pub Object<Connection>);
 
@@ -17,14 +36,19 @@ where
 {
     type Rejection = LowboyError;
 
-    async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let DatabasePool(pool) = DatabasePool::from_ref(state);
-        let conn = pool.get().await?;
+        let conn = metrics::checkout(&pool).await?;
+        parts.extensions.insert(ConnectionHeld);
 
         Ok(Self(conn))
     }
 }
 
+/// Marks a request whose handler holds a [`DatabaseConnection`], see [`load_connection`].
+#[derive(Clone, Copy)]
+struct ConnectionHeld;
+
 struct DatabasePool(Pool<Connection>);
 
 impl<T: AppContext> FromRef<T> for DatabasePool {
@@ -33,7 +57,111 @@ impl<T: AppContext> FromRef<T> for DatabasePool {
     }
 }
 
-pub struct JobScheduler(pub tokio_cron_scheduler::JobScheduler);
+type ConnectionTransactionManager = <Connection as AsyncConnection>::TransactionManager;
+
+/// The connection of the request's transaction, checked out (and the transaction begun) by the
+/// first [`TransactionalConnection`] the request extracts.
+#[derive(Clone, Default)]
+struct RequestTransaction(Arc<Mutex<Option<Object<Connection>>>>);
+
+/// Run each request in a database transaction, which is committed if the response is a success or
+/// a redirect, and rolled back otherwise.
+///
+/// The transaction is only begun when a handler extracts a [`TransactionalConnection`], so the
+/// middleware can wrap routes which don't need it. It's opt-in, add it to the app's routes:
+///
+/// ```ignore
+/// Router::new()
+///     .route("/register", post(register))
+///     .layer(axum::middleware::from_fn(lowboy::extract::transaction))
+/// ```
+pub async fn transaction(mut request: Request, next: Next) -> Response {
+    let transaction = RequestTransaction::default();
+    request.extensions_mut().insert(transaction.clone());
+
+    let response = next.run(request).await;
+
+    let Some(mut conn) = transaction.0.lock().await.take() else {
+        return response;
+    };
+
+    let status = response.status();
+    if status.is_success() || status.is_redirection() {
+        match ConnectionTransactionManager::commit_transaction(&mut *conn).await {
+            Ok(()) => response,
+            Err(e) => {
+                // Don't return a connection to the pool that could still be in the transaction.
+                let _ = Object::take(conn);
+                LowboyError::from(e).into_response()
+            }
+        }
+    } else {
+        if let Err(e) = ConnectionTransactionManager::rollback_transaction(&mut *conn).await {
+            tracing::error!("couldn't roll back the request's transaction: {e}");
+            let _ = Object::take(conn);
+        }
+
+        response
+    }
+}
+
+/// A database connection within the request's transaction, see [`transaction`].
+///
+/// Everything the request does through it is committed or rolled back together, so handlers
+/// making several changes don't need their own transaction. Extracting it in a route without the
+/// [`transaction`] middleware is an error.
+pub struct TransactionalConnection(OwnedMutexGuard<Option<Object<Connection>>>);
+
+#[async_trait::async_trait]
+impl<S> FromRequestParts<S> for TransactionalConnection
+where
+    S: Send + Sync + AppContext,
+    DatabasePool: FromRef<S>,
+{
+    type Rejection = LowboyError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let RequestTransaction(transaction) = parts
+            .extensions
+            .get::<RequestTransaction>()
+            .cloned()
+            .ok_or_else(|| {
+                anyhow::anyhow!("TransactionalConnection requires the transaction middleware")
+            })?;
+        let mut conn = transaction.try_lock_owned().map_err(|_| {
+            anyhow::anyhow!("the request's transactional connection is already in use")
+        })?;
+
+        if conn.is_none() {
+            let DatabasePool(pool) = DatabasePool::from_ref(state);
+            let mut checked_out = metrics::checkout(&pool).await?;
+            ConnectionTransactionManager::begin_transaction(&mut *checked_out).await?;
+            *conn = Some(checked_out);
+        }
+
+        Ok(Self(conn))
+    }
+}
+
+impl Deref for TransactionalConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+            .as_ref()
+            .expect("transactional connection should be checked out")
+    }
+}
+
+impl DerefMut for TransactionalConnection {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0
+            .as_mut()
+            .expect("transactional connection should be checked out")
+    }
+}
+
+pub struct JobScheduler(pub Scheduler);
 
 #[async_trait::async_trait]
 impl<S> FromRequestParts<S> for JobScheduler
@@ -49,7 +177,7 @@ where
     }
 }
 
-struct JobSchedulerInstance(tokio_cron_scheduler::JobScheduler);
+struct JobSchedulerInstance(Scheduler);
 
 impl<T: AppContext> FromRef<T> for JobSchedulerInstance {
     fn from_ref(input: &T) -> Self {
@@ -57,6 +185,34 @@ impl<T: AppContext> FromRef<T> for JobSchedulerInstance {
     }
 }
 
+/// Load the app user with its roles and permissions, memoized in the request's [`ModelCache`].
+pub(crate) async fn load_app_user<App: app::App<AC>, AC: CloneableAppContext>(
+    user_id: Option<i32>,
+    database: &Pool<Connection>,
+) -> Result<Option<App::User>, LowboyError> {
+    let Some(user_id) = user_id else {
+        return Ok(None);
+    };
+
+    let load = async {
+        let mut conn = metrics::checkout(database).await?;
+        let user = <App::User as Model>::load(user_id, &mut conn)
+            .await?
+            .with_roles_and_permissions(&mut conn)
+            .await?
+            .to_owned();
+
+        Ok::<_, LowboyError>(WithRolesAndPermissions(user))
+    };
+
+    let WithRolesAndPermissions(user) = match ModelCache::current() {
+        Some(cache) => cache.get_or_load(user_id, load).await?,
+        None => load.await?,
+    };
+
+    Ok(Some(user))
+}
+
 pub struct AppUser<App: app::App<AC>, AC: CloneableAppContext>(pub Option<App::User>);
 
 #[async_trait::async_trait]
@@ -69,22 +225,14 @@ where
     type Rejection = LowboyError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let DatabaseConnection(mut conn) =
-            DatabaseConnection::from_request_parts(parts, state).await?;
         let auth_session: AuthSession = axum_login::AuthSession::from_request_parts(parts, state)
             .await
             .map_err(|e| anyhow::anyhow!("{e:?}"))?;
-        let Some(user) = auth_session.user else {
-            return Ok(Self(None));
-        };
-        // @TODO is this necessary?
-        let user = <App::User as Model>::load(user.id, &mut conn)
-            .await?
-            .with_roles_and_permissions(&mut conn)
-            .await?
-            .to_owned();
+        let user =
+            load_app_user::<App, AC>(auth_session.user.map(|user| user.id), state.database())
+                .await?;
 
-        Ok(Self(Some(user)))
+        Ok(Self(user))
     }
 }
 
@@ -108,3 +256,438 @@ where
         Ok(Self(user))
     }
 }
+
+/// The model whose id is the route's `:id` parameter, loaded with [`Model::load_cached`].
+///
+/// Responds with `404 Not Found` when there's no such model, or the id isn't a number, which
+/// saves handlers loading and matching on the model themselves:
+///
+/// ```ignore
+/// // DELETE /posts/:id
+/// pub async fn delete(LoadPath(post): LoadPath<Post>) -> Result<impl IntoResponse, LowboyError> {
+///     // ...
+/// }
+/// ```
+///
+/// Models with slugs can be loaded from a `:slug` parameter with [`LoadSlug`].
+///
+/// The model is loaded in the request's transaction if it has one (see [`transaction`]),
+/// otherwise with a connection that's returned to the pool straight after. Either way, extract it
+/// before [`DatabaseConnection`] or [`TransactionalConnection`], which hold their connection for
+/// the rest of the request. Extracting it after them is an error, rather than the request waiting
+/// on a second connection a small pool may never free up.
+#[derive(Clone, Debug)]
+pub struct LoadPath<T>(pub T);
+
+#[async_trait::async_trait]
+impl<S, T> FromRequestParts<S> for LoadPath<T>
+where
+    S: Send + Sync + AppContext,
+    T: Model + Clone + Send + Sync + 'static,
+{
+    type Rejection = LowboyError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Ok(id) = path_param(parts, state, "id").await?.parse::<i32>() else {
+            return Err(LowboyError::NotFound);
+        };
+
+        let mut conn = load_connection(parts, state).await?;
+        let model = T::load_cached(id, &mut conn)
+            .await
+            .optional()?
+            .ok_or(LowboyError::NotFound)?;
+
+        Ok(Self(model))
+    }
+}
+
+/// The model whose slug is the route's `:slug` parameter, see [`LoadPath`]. Like it, extract it
+/// before [`DatabaseConnection`] or [`TransactionalConnection`].
+#[derive(Clone, Debug)]
+pub struct LoadSlug<T>(pub T);
+
+#[async_trait::async_trait]
+impl<S, T> FromRequestParts<S> for LoadSlug<T>
+where
+    S: Send + Sync + AppContext,
+    T: LoadBySlug + Send,
+{
+    type Rejection = LowboyError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let slug = path_param(parts, state, "slug").await?;
+
+        let mut conn = load_connection(parts, state).await?;
+        let model = T::load_by_slug(&slug, &mut conn)
+            .await
+            .optional()?
+            .ok_or(LowboyError::NotFound)?;
+
+        Ok(Self(model))
+    }
+}
+
+/// The connection [`LoadPath`] and [`LoadSlug`] load their model with.
+enum LoadConnection {
+    Transaction(TransactionalConnection),
+    Pooled(Object<Connection>),
+}
+
+impl Deref for LoadConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Transaction(conn) => conn,
+            Self::Pooled(conn) => conn,
+        }
+    }
+}
+
+impl DerefMut for LoadConnection {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Transaction(conn) => conn,
+            Self::Pooled(conn) => conn,
+        }
+    }
+}
+
+/// The request's transactional connection if it has one, otherwise a connection from the pool.
+/// Fails if the handler already holds a connection, since checking out another could wait forever
+/// on a pool the request itself has exhausted.
+async fn load_connection<S>(parts: &mut Parts, state: &S) -> Result<LoadConnection, LowboyError>
+where
+    S: Send + Sync + AppContext,
+{
+    if parts.extensions.get::<ConnectionHeld>().is_some() {
+        return Err(anyhow::anyhow!(
+            "LoadPath and LoadSlug must be extracted before DatabaseConnection"
+        ))?;
+    }
+
+    if parts.extensions.get::<RequestTransaction>().is_some() {
+        let conn = TransactionalConnection::from_request_parts(parts, state).await?;
+        return Ok(LoadConnection::Transaction(conn));
+    }
+
+    Ok(LoadConnection::Pooled(metrics::checkout(state.database()).await?))
+}
+
+/// The route's `name` parameter, which it's a bug for the route not to have.
+async fn path_param<S: Send + Sync>(
+    parts: &mut Parts,
+    state: &S,
+    name: &str,
+) -> Result<String, LowboyError> {
+    let Path(mut params) = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+        .await
+        .map_err(|e| anyhow::anyhow!("couldn't read path parameters: {e}"))?;
+
+    params
+        .remove(name)
+        .ok_or_else(|| anyhow::anyhow!("route has no `:{name}` parameter").into())
+}
+
+/// The largest JSON body [`Json`] accepts, in bytes, from the `json_body_limit` config.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct JsonLimit(pub usize);
+
+impl Default for JsonLimit {
+    fn default() -> Self {
+        Self(1024 * 1024)
+    }
+}
+
+/// A JSON request body deserialized into `T`, or a JSON response.
+///
+/// Unlike axum's `Json`, bodies larger than the `json_body_limit` config are refused, the content
+/// type must be `application/json` (or `application/*+json`), and rejections are rendered like
+/// lowboy's other errors: `application/problem+json` (see [`crate::error::problem_details`]) with
+/// where the body went wrong, or the error page for requests that accept HTML.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json<T>(pub T);
+
+#[async_trait::async_trait]
+impl<S, T> FromRequest<S> for Json<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, body) = request.into_parts();
+
+        if !is_json(&parts.headers) {
+            let detail = "Expected a request body with the `Content-Type: application/json` header";
+            return Err(reject(&parts, StatusCode::UNSUPPORTED_MEDIA_TYPE, detail));
+        }
+
+        let JsonLimit(limit) = parts.extensions.get().copied().unwrap_or_default();
+        let bytes = match axum::body::to_bytes(body, limit).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.into_inner().is::<LengthLimitError>() => {
+                let detail = format!("The request body is larger than {limit} bytes");
+                return Err(reject(&parts, StatusCode::PAYLOAD_TOO_LARGE, detail));
+            }
+            Err(_) => {
+                let detail = "The request body couldn't be read";
+                return Err(reject(&parts, StatusCode::BAD_REQUEST, detail));
+            }
+        };
+
+        match axum::Json::<T>::from_bytes(&bytes) {
+            Ok(axum::Json(value)) => Ok(Self(value)),
+            Err(rejection) => Err(reject(&parts, rejection.status(), rejection.body_text())),
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+/// Whether the request's body is JSON, going by its content type.
+fn is_json(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    essence == "application/json"
+        || (essence.starts_with("application/") && essence.ends_with("+json"))
+}
+
+/// Reject a JSON request, with the error page if it accepts HTML and problem details otherwise.
+fn reject(parts: &Parts, status: StatusCode, detail: impl Into<String>) -> Response {
+    let accepts_html = parts
+        .headers
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+
+    if accepts_html && !auth::is_api(&parts.headers, &parts.extensions) {
+        let detail = detail.into();
+        return LowboyError::Rejected { status, detail }.into_response();
+    }
+
+    problem_details(status, detail)
+}
+
+const HX_REQUEST: &str = "hx-request";
+const HX_CURRENT_URL: &str = "hx-current-url";
+const HX_REDIRECT: &str = "hx-redirect";
+const HX_REFRESH: &str = "hx-refresh";
+
+/// Details of a request made by htmx, which swaps responses into the page rather than navigating
+/// to them, so it can't follow redirects to full pages.
+#[derive(Clone, Debug, Default)]
+pub struct HxRequest {
+    /// Whether the request was made by htmx.
+    pub enabled: bool,
+    /// The path (and query) of the page htmx made the request from.
+    pub current_url: Option<String>,
+}
+
+impl HxRequest {
+    /// Redirect to `location`.
+    ///
+    /// Requests made by htmx get an `HX-Redirect` header (or `HX-Refresh` when `location` is the
+    /// page they were made from) instead of a 303, so the browser navigates to the page instead of
+    /// htmx swapping it into the current one.
+    pub fn redirect(&self, location: &str) -> Response {
+        if !self.enabled {
+            return Redirect::to(location).into_response();
+        }
+
+        if self.current_url.as_deref() == Some(location) {
+            return [(HX_REFRESH, "true")].into_response();
+        }
+
+        match HeaderValue::from_str(location) {
+            Ok(location) => [(HX_REDIRECT, location)].into_response(),
+            Err(_) => Redirect::to(location).into_response(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for HxRequest {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let enabled = parts
+            .headers
+            .get(HX_REQUEST)
+            .is_some_and(|value| value == "true");
+
+        let current_url = parts
+            .headers
+            .get(HX_CURRENT_URL)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<Uri>().ok())
+            .and_then(|uri| uri.path_and_query().map(|path| path.as_str().to_string()));
+
+        Ok(Self {
+            enabled,
+            current_url,
+        })
+    }
+}
+
+/// Whether the request came from an API client, which expects a status code rather than a
+/// redirect to an HTML page, see [`crate::auth::is_api_request`].
+#[derive(Clone, Copy, Debug)]
+pub struct ApiClient(pub bool);
+
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ApiClient {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(auth::is_api(&parts.headers, &parts.extensions)))
+    }
+}
+
+/// Flash messages for the next page, see [`axum_messages`].
+///
+/// Apps can turn the messages layer off with [`crate::serve::ServeOptions::messages`], so
+/// lowboy's own handlers take this rather than [`axum_messages::Messages`], which fails to extract
+/// without it. Without the layer, messages are dropped.
+#[derive(Clone)]
+pub struct Flash(pub Option<Messages>);
+
+impl Flash {
+    pub fn info(self, message: impl Into<String>) -> Self {
+        Self(self.0.map(|messages| messages.info(message)))
+    }
+
+    pub fn success(self, message: impl Into<String>) -> Self {
+        Self(self.0.map(|messages| messages.success(message)))
+    }
+
+    pub fn warning(self, message: impl Into<String>) -> Self {
+        Self(self.0.map(|messages| messages.warning(message)))
+    }
+
+    pub fn error(self, message: impl Into<String>) -> Self {
+        Self(self.0.map(|messages| messages.error(message)))
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Flash {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(Messages::from_request_parts(parts, state).await.ok()))
+    }
+}
+
+/// Query parameters deserialized into `T` and validated, like a form.
+///
+/// Invalid parameters are rejected with `400 Bad Request`, listed on the error page, or as
+/// [problem details](crate::error::problem_details) with a `fields` member for
+/// [API requests](crate::auth::is_api_request):
+///
+/// ```ignore
+/// #[derive(Deserialize, Validate)]
+/// pub struct Search {
+///     #[validate(length(min = 1, max = 100))]
+///     q: String,
+///     #[validate(range(min = 1))]
+///     page: Option<i64>,
+/// }
+///
+/// pub async fn search(ValidatedQuery(search): ValidatedQuery<Search>) -> impl IntoResponse {
+///     // ...
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ValidatedQuery<T>(pub T);
+
+#[async_trait::async_trait]
+impl<S, T> FromRequestParts<S> for ValidatedQuery<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let errors = match Query::<T>::from_request_parts(parts, state).await {
+            Ok(Query(value)) => match value.validate() {
+                Ok(()) => return Ok(Self(value)),
+                Err(validation) => FormErrors::from(validation),
+            },
+            // The query string couldn't be deserialized at all, e.g. a required parameter is
+            // missing or a number isn't one.
+            Err(rejection) => {
+                let mut errors = FormErrors::new();
+                errors.add("query", rejection.body_text());
+                errors
+            }
+        };
+
+        Err(invalid_input(parts, errors))
+    }
+}
+
+/// Reject input which failed validation, as JSON for API requests and with the error page
+/// otherwise.
+pub(crate) fn invalid_input(parts: &Parts, errors: FormErrors) -> Response {
+    if auth::is_api(&parts.headers, &parts.extensions) {
+        let mut problem = problem(StatusCode::BAD_REQUEST);
+        problem["fields"] = serde_json::json!(errors);
+        return problem_response(StatusCode::BAD_REQUEST, problem);
+    }
+
+    LowboyError::Invalid(errors).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{Body, Bytes};
+
+    use super::*;
+
+    async fn rejection(body: Body) -> Response {
+        let request = Request::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .extension(JsonLimit(8))
+            .body(body)
+            .unwrap();
+
+        match Json::<serde_json::Value>::from_request(request, &()).await {
+            Ok(_) => panic!("the body should be rejected"),
+            Err(response) => response,
+        }
+    }
+
+    #[tokio::test]
+    async fn json_rejects_bodies_over_the_limit_as_too_large() {
+        let response = rejection(Body::from(r#"{"too": "large"}"#)).await;
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/problem+json");
+    }
+
+    #[tokio::test]
+    async fn json_rejects_bodies_that_fail_to_read_as_bad_requests() {
+        let error = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset");
+        let stream = futures::stream::iter([Err::<Bytes, _>(error)]);
+        let response = rejection(Body::from_stream(stream)).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/problem+json");
+    }
+}
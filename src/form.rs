@@ -0,0 +1,317 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{LazyLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use chrono::{DateTime, NaiveDateTime, TimeDelta, TimeZone as _, Utc};
+use chrono_tz::Tz;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use rinja::Template;
+use serde::{Deserialize, Serialize};
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+use crate::model::{Email, Model, User};
+
+static UNIQUE_CONSTRAINTS: LazyLock<RwLock<UniqueConstraints>> = LazyLock::new(Default::default);
+
+/// Per-field validation error messages, exposed to templates.
+///
+/// ```html
+/// {% if let Some(error) = errors.field("email") %}
+///   <p class="error">{{ error }}</p>
+/// {% endif %}
+/// ```
+///
+/// Forms which were throttled, e.g. after too many failed logins, also say when they can be
+/// submitted again:
+///
+/// ```html
+/// {% if let Some(seconds) = errors.retry_after() %}
+///   <p class="error">Try again in {{ seconds }} seconds</p>
+/// {% endif %}
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FormErrors {
+    #[serde(flatten)]
+    messages: BTreeMap<String, Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retry_after: Option<i64>,
+}
+
+impl FormErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, field: impl Into<String>, message: impl Into<String>) -> &mut Self {
+        self.messages
+            .entry(field.into())
+            .or_default()
+            .push(message.into());
+        self
+    }
+
+    /// The first error message for `field`, if any.
+    pub fn field(&self, field: &str) -> Option<&String> {
+        self.messages
+            .get(field)
+            .and_then(|messages| messages.first())
+    }
+
+    /// All error messages for `field`.
+    pub fn messages(&self, field: &str) -> &[String] {
+        self.messages
+            .get(field)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn has(&self, field: &str) -> bool {
+        self.messages.contains_key(field)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Vec<String>)> {
+        self.messages.iter()
+    }
+
+    /// Mark the form as throttled, so it can't be submitted again until `retry_after` has passed.
+    pub fn set_retry_after(&mut self, retry_after: TimeDelta) -> &mut Self {
+        self.retry_after = Some(retry_after.num_seconds().max(1));
+        self
+    }
+
+    /// Seconds until a throttled form can be submitted again, see [`Self::set_retry_after`].
+    pub fn retry_after(&self) -> Option<i64> {
+        self.retry_after
+    }
+
+    /// Translate a violation of a registered unique constraint into an error on the form field it
+    /// belongs to, see [`UniqueConstraints`].
+    pub fn from_unique_violation(error: &DieselError) -> Option<Self> {
+        UniqueConstraints::global().translate(error)
+    }
+}
+
+/// Every error, e.g. "`email` must be a valid email address", for showing them all at once.
+impl fmt::Display for FormErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors = self
+            .messages
+            .iter()
+            .flat_map(|(field, messages)| messages.iter().map(move |message| (field, message)));
+
+        for (i, (field, message)) in errors.enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "`{field}` {message}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<ValidationErrors> for FormErrors {
+    fn from(value: ValidationErrors) -> Self {
+        let mut errors = Self::new();
+
+        for (field, info) in value.into_errors() {
+            if let ValidationErrorsKind::Field(field_errors) = info {
+                for error in field_errors {
+                    errors.add(field.to_string(), error.to_string());
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+/// A unique constraint of a model, and the form field violations of it are reported on.
+#[derive(Clone, Copy, Debug)]
+pub struct UniqueConstraint {
+    /// The constrained columns, e.g. `["address"]`.
+    pub columns: &'static [&'static str],
+    pub field: &'static str,
+    pub message: &'static str,
+}
+
+impl UniqueConstraint {
+    pub const fn new(
+        columns: &'static [&'static str],
+        field: &'static str,
+        message: &'static str,
+    ) -> Self {
+        Self {
+            columns,
+            field,
+            message,
+        }
+    }
+}
+
+/// The unique constraints of every registered model, so violations can be reported as friendly
+/// form errors instead of guessing which constraint failed.
+///
+/// Models list their constraints in [`Model::UNIQUE_CONSTRAINTS`]. Lowboy registers its own models,
+/// and apps register theirs in [`crate::App::unique_constraints`].
+#[derive(Clone, Debug)]
+pub struct UniqueConstraints(Vec<(&'static str, UniqueConstraint)>);
+
+impl Default for UniqueConstraints {
+    fn default() -> Self {
+        let mut constraints = Self(vec![]);
+        constraints.register::<User>().register::<Email>();
+        constraints
+    }
+}
+
+impl UniqueConstraints {
+    /// The constraints used by [`FormErrors::from_unique_violation`].
+    pub fn global() -> RwLockReadGuard<'static, Self> {
+        UNIQUE_CONSTRAINTS
+            .read()
+            .expect("unique constraints lock should not be poisoned")
+    }
+
+    pub fn global_mut() -> RwLockWriteGuard<'static, Self> {
+        UNIQUE_CONSTRAINTS
+            .write()
+            .expect("unique constraints lock should not be poisoned")
+    }
+
+    /// Register the unique constraints of `M`.
+    pub fn register<M: Model>(&mut self) -> &mut Self {
+        self.0.extend(
+            M::UNIQUE_CONSTRAINTS
+                .iter()
+                .map(|constraint| (M::TABLE_NAME, *constraint)),
+        );
+        self
+    }
+
+    /// The form errors for `error`, if it's a violation of a registered constraint.
+    pub fn translate(&self, error: &DieselError) -> Option<FormErrors> {
+        let DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) = error else {
+            return None;
+        };
+
+        // SQLite doesn't report the constraint's name, only the columns, e.g.
+        // `UNIQUE constraint failed: user_preference.user_id, user_preference.key`.
+        let columns: Vec<(&str, &str)> = info
+            .message()
+            .strip_prefix("UNIQUE constraint failed: ")?
+            .split(", ")
+            .filter_map(|column| column.split_once('.'))
+            .collect();
+
+        let (_, constraint) = self.0.iter().find(|(table, constraint)| {
+            constraint.columns.len() == columns.len()
+                && columns.iter().all(|(column_table, column)| {
+                    column_table == table && constraint.columns.contains(column)
+                })
+        })?;
+
+        let mut errors = FormErrors::new();
+        errors.add(constraint.field, constraint.message);
+        Some(errors)
+    }
+}
+
+/// Default partial for a form input with its label and error messages.
+///
+/// ```html
+/// {{ Input::new("email", errors).with_label("Email").with_kind("email").with_value(form.email())|safe }}
+/// ```
+#[derive(Clone, Template)]
+#[template(path = "form/input.html")]
+pub struct Input<'a> {
+    pub name: &'a str,
+    pub label: Option<&'a str>,
+    pub kind: &'a str,
+    pub value: &'a str,
+    pub placeholder: Option<&'a str>,
+    pub autocomplete: Option<&'a str>,
+    pub required: bool,
+    pub errors: &'a [String],
+}
+
+impl<'a> Input<'a> {
+    pub fn new(name: &'a str, errors: &'a FormErrors) -> Self {
+        Self {
+            name,
+            label: None,
+            kind: "text",
+            value: "",
+            placeholder: None,
+            autocomplete: None,
+            required: false,
+            errors: errors.messages(name),
+        }
+    }
+
+    pub fn with_label(self, label: &'a str) -> Self {
+        Self {
+            label: Some(label),
+            ..self
+        }
+    }
+
+    pub fn with_kind(self, kind: &'a str) -> Self {
+        Self { kind, ..self }
+    }
+
+    pub fn with_value(self, value: &'a str) -> Self {
+        Self { value, ..self }
+    }
+
+    pub fn with_placeholder(self, placeholder: &'a str) -> Self {
+        Self {
+            placeholder: Some(placeholder),
+            ..self
+        }
+    }
+
+    pub fn with_autocomplete(self, autocomplete: &'a str) -> Self {
+        Self {
+            autocomplete: Some(autocomplete),
+            ..self
+        }
+    }
+
+    pub fn with_required(self, required: bool) -> Self {
+        Self { required, ..self }
+    }
+}
+
+/// The format of `<input type="datetime-local">` values, to the minute.
+const DATETIME_LOCAL_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+/// Parse the value of an `<input type="datetime-local">`, e.g. `2025-01-17T09:30`, as a time in
+/// `timezone`, since the input doesn't include one. Use the user's timezone, from
+/// [`crate::locale::RequestLocale`], so it's the time they meant.
+///
+/// Times skipped by a daylight saving change aren't valid, and times repeated by one are taken as
+/// the earlier of the two.
+pub fn parse_datetime_local(input: &str, timezone: Tz) -> Option<DateTime<Utc>> {
+    let datetime = NaiveDateTime::parse_from_str(input, DATETIME_LOCAL_FORMAT)
+        .or_else(|_| NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M:%S"))
+        .ok()?;
+
+    timezone
+        .from_local_datetime(&datetime)
+        .earliest()
+        .map(|datetime| datetime.with_timezone(&Utc))
+}
+
+/// Format `datetime` as the value of an `<input type="datetime-local">` in `timezone`, e.g. to
+/// fill in when a post is scheduled to be published.
+pub fn format_datetime_local(datetime: &DateTime<Utc>, timezone: Tz) -> String {
+    datetime
+        .with_timezone(&timezone)
+        .format(DATETIME_LOCAL_FORMAT)
+        .to_string()
+}
@@ -0,0 +1,81 @@
+//! gRPC services alongside the web app, for internal RPC.
+//!
+//! Apps hand lowboy their [tonic] services from [`crate::App::grpc`], built with the app's
+//! context:
+//!
+//! ```ignore
+//! fn grpc(context: &AppContext) -> Option<Routes> {
+//!     Some(Routes::new(InventoryServer::new(Inventory::new(context.clone()))))
+//! }
+//! ```
+//!
+//! They're served on `grpc_port` when it's configured, otherwise they share the web app's port,
+//! where requests with a gRPC content type are routed to them. Either way, they're shut down
+//! gracefully with the web app, and a `grpc_port` that can't be bound fails the boot.
+//!
+//! gRPC calls don't go through any of the web app's layers, on either port. There's no session,
+//! so [`crate::login_required!`] and [`crate::permission_required!`] don't apply to them, and
+//! services have to authenticate and authorize calls themselves, e.g. with a tonic interceptor.
+
+use std::net::SocketAddr;
+
+use axum::extract::Request;
+use axum::http::header::CONTENT_TYPE;
+use axum::middleware::{self, Next};
+use axum::Router;
+use tonic::service::Routes;
+use tonic::transport::server::TcpIncoming;
+use tower::ServiceExt as _;
+use tracing::info;
+
+/// Whether `request` is a gRPC call, i.e. has an `application/grpc` content type.
+pub fn is_grpc(request: &Request) -> bool {
+    request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/grpc"))
+}
+
+/// Route gRPC calls to `services`, ahead of every other layer, and everything else to `router`.
+/// The calls skip the auth and permission layers, see the [module docs](self).
+pub(crate) fn share_port<S>(router: Router<S>, services: Routes) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let services = services.into_axum_router();
+
+    router.layer(middleware::from_fn(move |request: Request, next: Next| {
+        let services = services.clone();
+        async move {
+            if !is_grpc(&request) {
+                return next.run(request).await;
+            }
+
+            match services.oneshot(request).await {
+                Ok(response) => response,
+                Err(infallible) => match infallible {},
+            }
+        }
+    }))
+}
+
+/// Listen for gRPC calls on `addr`, before [`serve`] is spawned, so a port that can't be bound
+/// fails the boot rather than the spawned server.
+pub(crate) async fn bind(addr: SocketAddr) -> std::io::Result<TcpIncoming> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("serving gRPC on {}", listener.local_addr()?);
+
+    TcpIncoming::from_listener(listener, true, None).map_err(std::io::Error::other)
+}
+
+/// Serve `services` on their own port, until the app shuts down.
+pub(crate) async fn serve(
+    services: Routes,
+    incoming: TcpIncoming,
+) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_routes(services)
+        .serve_with_incoming_shutdown(incoming, crate::shutdown_signal(None))
+        .await
+}
@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use uuid::Uuid;
+
+use crate::error::LowboyError;
+
+const GUEST_KEY: &str = "lowboy.guest";
+
+/// A stable anonymous identity, and the data attached to it (e.g. a cart or drafts).
+///
+/// The guest session lives in the user's session until they register or log in, at which point
+/// it's handed to [`crate::App::on_guest_upgrade`] to be migrated to their account.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GuestSession {
+    id: String,
+    data: BTreeMap<String, serde_json::Value>,
+}
+
+impl GuestSession {
+    fn new() -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            data: BTreeMap::new(),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.data
+            .get(key)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.data.keys()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// The guest session in `session`, if there is one, without creating it.
+    pub(crate) async fn current(session: &Session) -> Result<Option<Self>, LowboyError> {
+        Ok(session.get::<Self>(GUEST_KEY).await?)
+    }
+
+    /// Remove the guest session from `session`, ending the anonymous identity.
+    pub(crate) async fn end(session: &Session) -> Result<(), LowboyError> {
+        session.remove::<Self>(GUEST_KEY).await?;
+        Ok(())
+    }
+}
+
+/// Extracts the current [`GuestSession`], creating it if needed.
+pub struct Guest {
+    session: Session,
+    guest: GuestSession,
+}
+
+impl Guest {
+    pub fn id(&self) -> &str {
+        self.guest.id()
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.guest.get(key)
+    }
+
+    pub async fn insert(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Serialize,
+    ) -> Result<(), LowboyError> {
+        let value = serde_json::to_value(value).map_err(|e| anyhow!(e))?;
+        self.guest.data.insert(key.into(), value);
+        self.save().await
+    }
+
+    pub async fn remove<T: DeserializeOwned>(&mut self, key: &str) -> Result<Option<T>, LowboyError> {
+        let value = self
+            .guest
+            .data
+            .remove(key)
+            .and_then(|value| serde_json::from_value(value).ok());
+        self.save().await?;
+
+        Ok(value)
+    }
+
+    async fn save(&self) -> Result<(), LowboyError> {
+        Ok(self.session.insert(GUEST_KEY, &self.guest).await?)
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Guest {
+    type Rejection = LowboyError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let session = Session::from_request_parts(parts, state)
+            .await
+            .map_err(|(_, e)| anyhow!(e))?;
+
+        let guest = match session.get::<GuestSession>(GUEST_KEY).await? {
+            Some(guest) => guest,
+            None => {
+                let guest = GuestSession::new();
+                session.insert(GUEST_KEY, &guest).await?;
+                guest
+            }
+        };
+
+        Ok(Self { session, guest })
+    }
+}
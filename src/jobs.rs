@@ -0,0 +1,590 @@
+//! Background jobs.
+//!
+//! One-off jobs are queued in the `queued_job` table so they survive restarts. Apps implement
+//! [`Job`], register it with [`crate::App::jobs`], and queue it from a handler, or another job,
+//! with [`JobQueue::enqueue`] or [`JobQueue::enqueue_in`]. [`crate::Lowboy::serve`]
+//! starts a pool of [`Workers`] which run jobs as they come due. Failed jobs are retried according
+//! to their [`RetryPolicy`]; jobs out of attempts are kept as [`DeadLetter`]s, where they can be
+//! retried from the admin.
+//!
+//! Recurring jobs, lowboy's own and the app's digests, run on cron schedules. They're listed with
+//! their next runs in [`ScheduledJobs`].
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, LazyLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use croner::errors::CronError;
+use croner::Cron;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::context::{AppContext, CloneableAppContext, Context};
+use crate::metrics;
+use crate::model::{DeadLetter, QueuedJob};
+use crate::{shutdown_signal, Connection};
+
+static JOBS: LazyLock<RwLock<Jobs>> = LazyLock::new(Default::default);
+static SCHEDULED_JOBS: LazyLock<RwLock<ScheduledJobs>> = LazyLock::new(Default::default);
+/// Wakes idle workers when a job is queued to run now.
+static QUEUED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// A recurring job lowboy schedules itself.
+#[derive(Clone, Copy, Debug)]
+pub struct BuiltInJob {
+    pub name: &'static str,
+    pub schedule: &'static str,
+}
+
+/// Reports the largest sessions, see [`crate::session::report_largest`].
+pub const REPORT_LARGEST_SESSIONS: BuiltInJob = BuiltInJob {
+    name: "report_largest_sessions",
+    schedule: "0 0 * * * *",
+};
+
+pub const DELETE_EXPIRED_DRAFTS: BuiltInJob = BuiltInJob {
+    name: "delete_expired_drafts",
+    schedule: "0 30 * * * *",
+};
+
+/// Expires stale presence, see [`crate::presence::Presence::expire`].
+pub const EXPIRE_PRESENCE: BuiltInJob = BuiltInJob {
+    name: "expire_presence",
+    schedule: "0 * * * * *",
+};
+
+/// Deletes expired entries from the key-value store, see [`crate::kv`].
+pub const DELETE_EXPIRED_KV_ENTRIES: BuiltInJob = BuiltInJob {
+    name: "delete_expired_kv_entries",
+    schedule: "0 45 * * * *",
+};
+
+/// Tells clients about announcements as they become active, see [`crate::model::Announcement`].
+pub const PUSH_ANNOUNCEMENTS: BuiltInJob = BuiltInJob {
+    name: "push_announcements",
+    schedule: "30 * * * * *",
+};
+
+/// Publishes scheduled models once they're due, see [`crate::publish`].
+pub const PUBLISH_SCHEDULED: BuiltInJob = BuiltInJob {
+    name: "publish_scheduled",
+    schedule: "15 * * * * *",
+};
+
+/// Deletes old versions of models, see [`crate::versioning`].
+pub const PRUNE_VERSIONS: BuiltInJob = BuiltInJob {
+    name: "prune_versions",
+    schedule: "0 15 3 * * *",
+};
+
+/// Removes expired models from the trash for good, see [`crate::trash`].
+pub const PURGE_TRASH: BuiltInJob = BuiltInJob {
+    name: "purge_trash",
+    schedule: "0 0 4 * * *",
+};
+
+pub const BUILT_IN: &[BuiltInJob] = &[
+    REPORT_LARGEST_SESSIONS,
+    DELETE_EXPIRED_DRAFTS,
+    EXPIRE_PRESENCE,
+    DELETE_EXPIRED_KV_ENTRIES,
+    PUSH_ANNOUNCEMENTS,
+    PUBLISH_SCHEDULED,
+    PRUNE_VERSIONS,
+    PURGE_TRASH,
+];
+
+/// How long a worker has to run a job before it's considered dead, and the job runs again.
+pub const LEASE: TimeDelta = TimeDelta::minutes(15);
+
+/// How long to wait between retries of a failed job.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backoff {
+    Fixed(TimeDelta),
+    /// Double the delay after each attempt, starting from `base`, up to `max`.
+    Exponential { base: TimeDelta, max: TimeDelta },
+}
+
+/// How a failed job is retried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How many times the job is run before it's kept as a dead letter, including the first.
+    pub max_attempts: i32,
+    pub backoff: Backoff,
+}
+
+impl RetryPolicy {
+    /// Don't retry the job.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff: Backoff::Fixed(TimeDelta::zero()),
+        }
+    }
+
+    /// The delay before retrying a job that failed its `attempt`th run, starting from 1.
+    pub fn delay(&self, attempt: i32) -> TimeDelta {
+        match self.backoff {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { base, max } => {
+                let factor = 2_i32.saturating_pow(attempt.saturating_sub(1).clamp(0, 30) as u32);
+                base.checked_mul(factor).map_or(max, |delay| delay.min(max))
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// 5 attempts, waiting 30 seconds after the first and doubling up to an hour.
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            backoff: Backoff::Exponential {
+                base: TimeDelta::seconds(30),
+                max: TimeDelta::hours(1),
+            },
+        }
+    }
+}
+
+/// A unit of background work, e.g. sending one user their digest.
+#[async_trait::async_trait]
+pub trait Job: Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// The name the job is registered as, e.g. `send_digest`.
+    const NAME: &'static str;
+
+    /// Only one job with this key is queued at a time, e.g. `send_digest:5` so user 5's digest
+    /// isn't queued twice. Defaults to `None`, so the job can be queued any number of times.
+    fn unique_key(&self) -> Option<String> {
+        None
+    }
+
+    /// How the job is retried when it fails. Defaults to [`RetryPolicy::default`].
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    async fn run(&self, context: &dyn AppContext) -> anyhow::Result<()>;
+}
+
+#[async_trait::async_trait]
+trait AnyJob: Send + Sync {
+    fn retry_policy(&self, payload: &str) -> RetryPolicy;
+
+    async fn run(&self, payload: &str, context: &dyn AppContext) -> anyhow::Result<()>;
+}
+
+struct JobRunner<J>(PhantomData<fn() -> J>);
+
+#[async_trait::async_trait]
+impl<J: Job> AnyJob for JobRunner<J> {
+    fn retry_policy(&self, payload: &str) -> RetryPolicy {
+        match serde_json::from_str::<J>(payload) {
+            Ok(job) => job.retry_policy(),
+            // A payload that doesn't deserialize won't the next time either.
+            Err(_) => RetryPolicy::none(),
+        }
+    }
+
+    async fn run(&self, payload: &str, context: &dyn AppContext) -> anyhow::Result<()> {
+        let job: J = serde_json::from_str(payload)?;
+        job.run(context).await
+    }
+}
+
+/// The registry of jobs, by name, so queued jobs can be deserialized and run.
+#[derive(Clone, Default)]
+pub struct Jobs {
+    runners: HashMap<&'static str, Arc<dyn AnyJob>>,
+}
+
+impl Jobs {
+    pub fn global() -> RwLockReadGuard<'static, Self> {
+        JOBS.read().expect("jobs lock should not be poisoned")
+    }
+
+    pub fn global_mut() -> RwLockWriteGuard<'static, Self> {
+        JOBS.write().expect("jobs lock should not be poisoned")
+    }
+
+    /// Register the job `J`, so it can be queued.
+    pub fn register<J: Job>(&mut self) -> &mut Self {
+        self.runners
+            .insert(J::NAME, Arc::new(JobRunner::<J>(PhantomData)));
+        self
+    }
+
+    /// Whether a job is registered as `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.runners.contains_key(name)
+    }
+
+    fn runner(&self, name: &str) -> Option<Arc<dyn AnyJob>> {
+        self.runners.get(name).cloned()
+    }
+}
+
+/// Queueing jobs with any context, including the `&dyn AppContext` jobs run with.
+#[async_trait::async_trait]
+pub trait JobQueue: Context {
+    /// Queue `job` to run as soon as a worker is free. Returns `false`, without queueing it, if
+    /// the job has a [`Job::unique_key`] and a job with the same key is already queued.
+    async fn enqueue<J: Job>(&self, job: J) -> anyhow::Result<bool> {
+        self.enqueue_in(TimeDelta::zero(), job).await
+    }
+
+    /// Queue `job` to run after `delay`. Returns `false`, without queueing it, if the job has a
+    /// [`Job::unique_key`] and a job with the same key is already queued.
+    async fn enqueue_in<J: Job>(&self, delay: TimeDelta, job: J) -> anyhow::Result<bool> {
+        let mut conn = metrics::checkout(self.database()).await?;
+        enqueue_in(&job, delay, self.clock().now(), &mut conn).await
+    }
+}
+
+impl<C: Context + ?Sized> JobQueue for C {}
+
+/// Queue `job` to run after `delay`, returning `false` if it's unique and already queued.
+pub async fn enqueue_in<J: Job>(
+    job: &J,
+    delay: TimeDelta,
+    now: DateTime<Utc>,
+    conn: &mut Connection,
+) -> anyhow::Result<bool> {
+    let payload = serde_json::to_string(job)?;
+    let unique_key = job.unique_key();
+
+    let queued =
+        QueuedJob::enqueue(J::NAME, &payload, unique_key.as_deref(), now + delay, now, conn).await?;
+    if queued.is_some() && delay <= TimeDelta::zero() {
+        QUEUED.notify_waiters();
+    }
+
+    Ok(queued.is_some())
+}
+
+/// Run the next due job, returning `false` if there wasn't one.
+///
+/// A job that fails is rescheduled according to its [`RetryPolicy`], or kept as a dead letter if
+/// it's out of attempts.
+pub async fn run_next(context: &dyn AppContext) -> anyhow::Result<bool> {
+    let claimed = {
+        let mut conn = metrics::checkout(context.database()).await?;
+        let now = context.clock().now();
+        let Some(job) = QueuedJob::due(now, 1, &mut conn).await?.pop() else {
+            return Ok(false);
+        };
+        QueuedJob::claim(job.id, LEASE, now, &mut conn).await?
+    };
+    // Another worker claimed it first, there may be more due.
+    let Some(job) = claimed else {
+        return Ok(true);
+    };
+
+    let runner = Jobs::global().runner(&job.job);
+    let (result, policy) = match runner {
+        Some(runner) => (
+            metrics::track_job(&job.job, runner.run(&job.payload, context)).await,
+            runner.retry_policy(&job.payload),
+        ),
+        None => (
+            Err(anyhow::anyhow!("no job is registered as `{}`", job.job)),
+            RetryPolicy::none(),
+        ),
+    };
+
+    let mut conn = metrics::checkout(context.database()).await?;
+    let Err(e) = result else {
+        job.delete_record(&mut conn).await?;
+        return Ok(true);
+    };
+
+    let error = e.to_string();
+    let now = context.clock().now();
+    if job.attempts < policy.max_attempts {
+        let run_at = now + policy.delay(job.attempts);
+        warn!(
+            "job {name}({id}) failed, retrying at {run_at}: {error}",
+            name = job.job,
+            id = job.id
+        );
+        job.reschedule(run_at, &error, &mut conn).await?;
+    } else {
+        warn!("job {name}({id}) failed for good: {error}", name = job.job, id = job.id);
+        DeadLetter::record(&job.job, &job.payload, &error, now, &mut conn).await?;
+        job.delete_record(&mut conn).await?;
+    }
+
+    Ok(true)
+}
+
+/// Run the jobs that are due until there are none, returning how many ran.
+pub async fn run_due(context: &dyn AppContext) -> anyhow::Result<usize> {
+    let mut ran = 0;
+    while run_next(context).await? {
+        ran += 1;
+    }
+
+    Ok(ran)
+}
+
+/// A pool of workers running queued jobs on the tokio runtime.
+pub struct Workers {
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl Workers {
+    /// Start `count` workers. Idle workers check for due jobs every `poll_interval`, or as soon as
+    /// a job is queued to run now. Workers stop at shutdown, once they finish their current job.
+    pub fn spawn<AC: CloneableAppContext>(
+        context: AC,
+        count: usize,
+        poll_interval: Duration,
+    ) -> Self {
+        let handles = (0..count.max(1))
+            .map(|_| tokio::spawn(work(context.clone(), poll_interval)))
+            .collect();
+        info!("started {count} job workers");
+
+        Self { handles }
+    }
+
+    /// Stop the workers, abandoning the jobs they're running. The jobs run again once their lease
+    /// is over.
+    pub fn abort(&self) {
+        for handle in &self.handles {
+            handle.abort();
+        }
+    }
+}
+
+async fn work<AC: CloneableAppContext>(context: AC, poll_interval: Duration) {
+    let shutdown = shutdown_signal(None);
+    futures::pin_mut!(shutdown);
+
+    loop {
+        match run_next(&context).await {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(e) => warn!("couldn't run queued jobs: {e}"),
+        }
+
+        tokio::select! {
+            _ = QUEUED.notified() => {}
+            _ = tokio::time::sleep(poll_interval) => {}
+            _ = &mut shutdown => break,
+        }
+    }
+}
+
+/// A recurring job's schedule isn't a valid cron expression.
+#[derive(Debug, thiserror::Error)]
+#[error("the schedule of the `{name}` job, `{schedule}`, isn't a valid cron expression: {source}")]
+pub struct ScheduleError {
+    pub name: String,
+    pub schedule: String,
+    pub source: CronError,
+}
+
+/// A recurring job, and the cron schedule it runs on.
+#[derive(Clone, Debug)]
+pub struct ScheduledJob {
+    pub name: String,
+    /// The cron expression, with an optional leading seconds field, e.g. `0 30 * * * *`.
+    pub schedule: String,
+    cron: Cron,
+}
+
+impl ScheduledJob {
+    pub fn new(name: &str, schedule: &str) -> Result<Self, ScheduleError> {
+        let cron = Cron::new(schedule)
+            .with_seconds_optional()
+            .parse()
+            .map_err(|source| ScheduleError {
+                name: name.to_string(),
+                schedule: schedule.to_string(),
+                source,
+            })?;
+
+        Ok(Self {
+            name: name.to_string(),
+            schedule: schedule.to_string(),
+            cron,
+        })
+    }
+
+    /// The next `n` times the job will run after `after`.
+    pub fn next_runs(&self, n: usize, after: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        self.cron.iter_after(after).take(n).collect()
+    }
+}
+
+/// The recurring jobs [`crate::Lowboy::serve`] scheduled.
+#[derive(Clone, Debug, Default)]
+pub struct ScheduledJobs {
+    jobs: Vec<ScheduledJob>,
+}
+
+impl ScheduledJobs {
+    pub fn global() -> RwLockReadGuard<'static, Self> {
+        SCHEDULED_JOBS
+            .read()
+            .expect("scheduled jobs lock should not be poisoned")
+    }
+
+    pub fn global_mut() -> RwLockWriteGuard<'static, Self> {
+        SCHEDULED_JOBS
+            .write()
+            .expect("scheduled jobs lock should not be poisoned")
+    }
+
+    /// Check `schedule` is a valid cron expression and add the job `name` running on it.
+    pub fn add(&mut self, name: &str, schedule: &str) -> Result<&mut Self, ScheduleError> {
+        self.jobs.push(ScheduledJob::new(name, schedule)?);
+        Ok(self)
+    }
+
+    pub fn list(&self) -> &[ScheduledJob] {
+        &self.jobs
+    }
+}
+
+/// The job scheduler couldn't be started, or a job couldn't be added to it.
+#[derive(Debug, thiserror::Error)]
+#[error("job scheduler error: {0}")]
+pub struct SchedulerError(String);
+
+#[cfg(feature = "scheduler")]
+impl From<tokio_cron_scheduler::JobSchedulerError> for SchedulerError {
+    fn from(e: tokio_cron_scheduler::JobSchedulerError) -> Self {
+        Self(e.to_string())
+    }
+}
+
+/// Runs recurring jobs on their cron schedules.
+///
+/// Without the `scheduler` feature the handle still exists, so app contexts are the same with or
+/// without it, but jobs added to it never run.
+#[derive(Clone)]
+pub struct Scheduler {
+    #[cfg(feature = "scheduler")]
+    inner: tokio_cron_scheduler::JobScheduler,
+}
+
+impl Scheduler {
+    #[cfg(feature = "scheduler")]
+    pub async fn start() -> Result<Self, SchedulerError> {
+        let inner = tokio_cron_scheduler::JobScheduler::new().await?;
+        inner.start().await?;
+        Ok(Self { inner })
+    }
+
+    #[cfg(not(feature = "scheduler"))]
+    pub async fn start() -> Result<Self, SchedulerError> {
+        info!("lowboy was built without the `scheduler` feature, recurring jobs won't run");
+        Ok(Self {})
+    }
+
+    /// Run `job` on the cron `schedule`.
+    #[cfg(feature = "scheduler")]
+    pub async fn add<F, Fut>(&self, schedule: &str, job: F) -> Result<(), SchedulerError>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let job = tokio_cron_scheduler::Job::new_async(schedule, move |_, _| Box::pin(job()))?;
+        self.inner.add(job).await?;
+        Ok(())
+    }
+
+    /// Run `job` on the cron `schedule`.
+    #[cfg(not(feature = "scheduler"))]
+    pub async fn add<F, Fut>(&self, _schedule: &str, _job: F) -> Result<(), SchedulerError>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        Ok(())
+    }
+
+    /// The underlying `tokio-cron-scheduler` scheduler.
+    #[cfg(feature = "scheduler")]
+    pub fn inner(&self) -> &tokio_cron_scheduler::JobScheduler {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Serialize, Deserialize)]
+    struct CountRuns;
+
+    #[async_trait::async_trait]
+    impl Job for CountRuns {
+        const NAME: &'static str = "test_count_runs";
+
+        async fn run(&self, _context: &dyn AppContext) -> anyhow::Result<()> {
+            RUNS.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn two_workers_run_a_job_once() {
+        Jobs::global_mut().register::<CountRuns>();
+        let context = crate::test::context().await;
+        assert!(context.enqueue(CountRuns).await.unwrap());
+
+        let (first, second) = tokio::join!(run_next(&context), run_next(&context));
+        first.unwrap();
+        second.unwrap();
+
+        assert_eq!(RUNS.load(Ordering::SeqCst), 1);
+        assert!(!run_next(&context).await.unwrap());
+    }
+
+    async fn due(now: DateTime<Utc>, conn: &mut Connection) -> usize {
+        QueuedJob::due(now, 10, conn).await.unwrap().len()
+    }
+
+    async fn claim(id: i32, now: DateTime<Utc>, conn: &mut Connection) -> Option<QueuedJob> {
+        QueuedJob::claim(id, LEASE, now, conn).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn a_job_can_be_claimed_again_once_its_lease_expires() {
+        let database = crate::test::database().await;
+        let mut conn = database.get().await.unwrap();
+        let now = Utc::now();
+        let job = QueuedJob::enqueue("test", "{}", None, now, now, &mut conn)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let claimed = claim(job.id, now, &mut conn).await;
+        assert_eq!(claimed.map(|job| job.attempts), Some(1));
+
+        // Other workers can't run it while the lease is held...
+        let leased = now + LEASE - TimeDelta::seconds(1);
+        assert_eq!(due(leased, &mut conn).await, 0);
+        assert!(claim(job.id, leased, &mut conn).await.is_none());
+
+        // ...but can once it expires, e.g. because the worker running it died.
+        let expired = now + LEASE;
+        assert_eq!(due(expired, &mut conn).await, 1);
+        let claimed = claim(job.id, expired, &mut conn).await;
+        assert_eq!(claimed.map(|job| job.attempts), Some(2));
+    }
+}
@@ -0,0 +1,198 @@
+//! A small key-value store in the database, for ephemeral data when there's no Redis, e.g.
+//! rate-limit counters, locks and feature flag overrides.
+//!
+//! Values are stored as JSON, and can expire after a TTL. Expired entries are ignored, and deleted
+//! hourly by the [`crate::jobs::DELETE_EXPIRED_KV_ENTRIES`] job:
+//!
+//! ```ignore
+//! let kv = context.kv();
+//!
+//! // Allow 10 attempts a minute.
+//! let attempts = kv.increment(&format!("attempts:{ip}"), 1, Some(TimeDelta::minutes(1))).await?;
+//! if attempts > 10 {
+//!     return Ok(StatusCode::TOO_MANY_REQUESTS.into_response());
+//! }
+//!
+//! // Only send the report from one process.
+//! if let Some(lock) = kv.try_lock("report", TimeDelta::minutes(5)).await? {
+//!     send_report().await?;
+//!     kv.unlock("report", &lock).await?;
+//! }
+//! ```
+
+use chrono::{DateTime, TimeDelta, Utc};
+use diesel_async::pooled_connection::deadpool::Pool;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::clock::Clock;
+use crate::model::KvEntry;
+use crate::secret::SecretGenerator;
+use crate::{jobs, metrics, Connection};
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    Pool(#[from] deadpool::managed::PoolError<diesel_async::pooled_connection::PoolError>),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// Reads and writes the key-value store, see the [module docs](self).
+pub struct Kv<'a> {
+    database: &'a Pool<Connection>,
+    clock: &'a dyn Clock,
+    secrets: &'a dyn SecretGenerator,
+}
+
+/// Proof of holding a lock taken with [`Kv::try_lock`], needed to unlock it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LockToken(String);
+
+impl<'a> Kv<'a> {
+    /// The store in `database`. `secrets` generates the tokens of locks.
+    pub fn new(
+        database: &'a Pool<Connection>,
+        clock: &'a dyn Clock,
+        secrets: &'a dyn SecretGenerator,
+    ) -> Self {
+        Self {
+            database,
+            clock,
+            secrets,
+        }
+    }
+
+    /// The value stored under `key`, unless it has expired.
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        let mut conn = metrics::checkout(self.database).await?;
+
+        KvEntry::find(key, self.clock.now(), &mut conn)
+            .await?
+            .map(|entry| serde_json::from_str(&entry.value))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    /// Store `value` under `key`, expiring after `ttl` if there is one.
+    pub async fn set<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Option<TimeDelta>,
+    ) -> Result<()> {
+        let value = serde_json::to_string(value)?;
+        let now = self.clock.now();
+
+        let mut conn = metrics::checkout(self.database).await?;
+        KvEntry::set(key, &value, expires_at(now, ttl), now, &mut conn).await?;
+
+        Ok(())
+    }
+
+    /// Remove `key`, returning whether it was stored.
+    pub async fn delete(&self, key: &str) -> Result<bool> {
+        let mut conn = metrics::checkout(self.database).await?;
+
+        Ok(KvEntry::unset(key, &mut conn).await? > 0)
+    }
+
+    /// Add `by` to the counter `key`, returning its new value. Counters start from zero, and
+    /// expire `ttl` after they start, so they count over fixed windows.
+    pub async fn increment(&self, key: &str, by: i64, ttl: Option<TimeDelta>) -> Result<i64> {
+        let now = self.clock.now();
+
+        let mut conn = metrics::checkout(self.database).await?;
+        Ok(KvEntry::increment(key, by, expires_at(now, ttl), now, &mut conn).await?)
+    }
+
+    /// Take the lock `key` for `ttl`, returning its token if it was free. Locks are released
+    /// when they're unlocked or expire, so a lock isn't held forever by a process which died
+    /// holding it.
+    pub async fn try_lock(&self, key: &str, ttl: TimeDelta) -> Result<Option<LockToken>> {
+        let token = self.secrets.generate();
+        let value = serde_json::to_string(&token)?;
+        let now = self.clock.now();
+
+        let mut conn = metrics::checkout(self.database).await?;
+        let locked = KvEntry::set_if_absent(key, &value, Some(now + ttl), now, &mut conn).await?;
+
+        Ok(locked.then_some(LockToken(token)))
+    }
+
+    /// Release the lock `key` taken with `token`, returning whether it was still held. A lock
+    /// which expired and was taken by someone else is left alone.
+    pub async fn unlock(&self, key: &str, token: &LockToken) -> Result<bool> {
+        let value = serde_json::to_string(&token.0)?;
+
+        let mut conn = metrics::checkout(self.database).await?;
+        Ok(KvEntry::unset_if(key, &value, &mut conn).await?)
+    }
+}
+
+fn expires_at(now: DateTime<Utc>, ttl: Option<TimeDelta>) -> Option<DateTime<Utc>> {
+    ttl.map(|ttl| now + ttl)
+}
+
+pub(crate) async fn delete_expired(database: &Pool<Connection>, now: DateTime<Utc>) {
+    let deleted = async {
+        let mut conn = metrics::checkout(database).await?;
+        Ok::<_, anyhow::Error>(KvEntry::delete_expired(now, &mut conn).await?)
+    };
+
+    match metrics::track_job(jobs::DELETE_EXPIRED_KV_ENTRIES.name, deleted).await {
+        Ok(0) => (),
+        Ok(deleted) => info!("deleted {deleted} expired key-value entries"),
+        Err(e) => warn!("couldn't delete expired key-value entries: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::secret::SequentialSecretGenerator;
+
+    #[tokio::test]
+    async fn lock_is_exclusive_until_unlocked() {
+        let database = crate::test::database().await;
+        let clock = MockClock::default();
+        let secrets = SequentialSecretGenerator::default();
+        let kv = Kv::new(&database, &clock, &secrets);
+
+        let lock = kv.try_lock("report", TimeDelta::minutes(5)).await.unwrap();
+        let lock = lock.expect("the lock should be free");
+        assert_eq!(kv.try_lock("report", TimeDelta::minutes(5)).await.unwrap(), None);
+
+        assert!(kv.unlock("report", &lock).await.unwrap());
+        assert!(kv.try_lock("report", TimeDelta::minutes(5)).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn expired_lock_can_be_taken_over() {
+        let database = crate::test::database().await;
+        let clock = MockClock::default();
+        let secrets = SequentialSecretGenerator::default();
+        let kv = Kv::new(&database, &clock, &secrets);
+
+        let first = kv.try_lock("report", TimeDelta::minutes(5)).await.unwrap();
+        let first = first.expect("the lock should be free");
+        clock.advance(TimeDelta::minutes(5));
+        let second = kv.try_lock("report", TimeDelta::minutes(5)).await.unwrap();
+        let second = second.expect("the expired lock should be taken over");
+
+        // The first holder can't release the lock that was taken over from it.
+        assert!(!kv.unlock("report", &first).await.unwrap());
+        assert_eq!(kv.try_lock("report", TimeDelta::minutes(5)).await.unwrap(), None);
+
+        assert!(kv.unlock("report", &second).await.unwrap());
+        assert!(kv.try_lock("report", TimeDelta::minutes(5)).await.unwrap().is_some());
+    }
+}
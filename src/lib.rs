@@ -1,53 +1,100 @@
 use std::io::LineWriter;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 
-use axum::response::sse::Event;
 use axum::routing::get;
-use axum::{middleware, Router};
+use axum::{middleware, Extension, Router};
 use axum_login::tower_sessions::{ExpiredDeletion, Expiry, SessionManagerLayer};
-use axum_login::{login_required, AuthManagerLayerBuilder};
+use axum_login::AuthManagerLayerBuilder;
 use axum_messages::MessagesManagerLayer;
+use axum_server::tls_rustls::RustlsConfig;
 use base64::prelude::*;
-use config::Config;
+use chrono::TimeDelta;
 use context::{create_context, CloneableAppContext};
 use diesel::sqlite::{Sqlite, SqliteConnection};
+use diesel::Connection as _;
 use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
 use diesel_migrations::{
-    embed_migrations, EmbeddedMigrations, HarnessWithOutput, MigrationHarness,
+    embed_migrations, EmbeddedMigrations, FileBasedMigrations, HarnessWithOutput, MigrationHarness,
 };
 use diesel_sqlite_session_store::DieselSqliteSessionStore;
 use error::LowboyError;
-use flume::{Receiver, Sender};
 use tokio::signal;
 use tokio::task::AbortHandle;
-use tower_http::services::ServeDir;
+use tower_http::catch_panic::CatchPanicLayer;
 use tower_sessions::cookie::{self, Key};
 use tracing::info;
 
+pub mod actor;
+pub mod api;
 mod app;
+pub mod attachment;
 pub mod auth;
+mod banner;
+pub mod bot;
+pub mod bulk;
+pub mod cache;
+pub mod clock;
+pub mod collab;
 mod config;
 pub mod context;
 pub mod controller;
+pub mod database;
+pub mod digest;
 mod diesel_sqlite_session_store;
 pub mod error;
+pub mod events;
 pub mod extract;
-mod mailer;
+pub mod form;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod guest;
+pub mod jobs;
+pub mod kv;
+pub mod locale;
+pub mod mailer;
+#[cfg(feature = "media")]
+pub mod media;
+pub mod metrics;
 pub mod model;
+pub mod password;
+pub mod prelude;
+pub mod presence;
+pub mod provision;
+pub mod publish;
+pub mod scan;
 pub mod schema;
+pub mod secret;
+pub mod security;
+pub mod serve;
+pub mod session;
+pub mod settings;
+pub mod telemetry;
+pub mod test;
+pub mod trash;
+pub mod versioning;
 pub mod view;
+pub mod watchdog;
+pub mod wizard;
 
 pub use app::App;
 pub use auth::{AuthSession, LowboyAuth};
+pub use config::{
+    get_config_path, get_config_template, AttachedDatabase, Config, EventOverflow, JournalMode,
+    OversizedSession, PoolRecycling, SchemaCheck, Synchronous, TempStore, TlsConfig,
+};
 pub use context::{AppContext, Context, LowboyContext};
+pub use events::Events;
+pub use serve::ServeOptions;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
 pub type Connection = SyncConnectionWrapper<SqliteConnection>;
-pub type Events = (Sender<Event>, Receiver<Event>);
 type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum Error {
     #[error(transparent)]
     Config(#[from] crate::config::Error),
@@ -76,9 +123,26 @@ pub enum Error {
     #[error(transparent)]
     TokioJoin(#[from] tokio::task::JoinError),
 
+    #[cfg(feature = "livereload")]
     #[error(transparent)]
     Notify(#[from] notify::Error),
 
+    #[cfg(feature = "grpc")]
+    #[error(transparent)]
+    Grpc(#[from] tonic::transport::Error),
+
+    #[error(transparent)]
+    Scheduler(#[from] jobs::SchedulerError),
+
+    #[error("unknown timezone `{0}`")]
+    UnknownTimezone(String),
+
+    #[error(transparent)]
+    SchemaDrift(#[from] database::SchemaDriftError),
+
+    #[error(transparent)]
+    Schedule(#[from] jobs::ScheduleError),
+
     #[error(transparent)]
     Migration(#[from] Box<dyn std::error::Error + Send + Sync>),
 }
@@ -109,12 +173,42 @@ impl std::io::Write for MigrationWriter {
 
 impl<AC: CloneableAppContext> Lowboy<AC> {
     pub async fn boot() -> Result<Self> {
-        let config = Config::load(None)?;
+        Self::boot_with_config(Config::load(None)?).await
+    }
+
+    /// Boot using the provided configuration instead of loading it from the config file.
+    pub async fn boot_with_config(config: Config) -> Result<Self> {
+        telemetry::install(config.telemetry.clone());
         let context = create_context::<AC>(&config).await?;
 
         let mut conn = context.database().get().await?;
-        conn.spawn_blocking(|conn| Ok(Self::run_migrations(conn)))
-            .await??;
+        let migrated = conn
+            .spawn_blocking(|conn| Ok(Self::run_migrations(conn)))
+            .await?;
+        if let Err(ref e) = migrated {
+            telemetry::send(telemetry::Report::migration_failure(e)).await;
+        }
+        migrated?;
+        for database in config.database_attach.iter().cloned() {
+            tokio::task::spawn_blocking(move || Self::run_attached_migrations(&database))
+                .await??;
+        }
+        if config.database_schema_check != SchemaCheck::Off {
+            let drift = conn.spawn_blocking(database::schema_drift).await?;
+            Self::report_schema_drift(drift, config.database_schema_check)?;
+        }
+        drop(conn);
+
+        if config.database_warm_up {
+            // Open every pooled connection up front, so the first requests after a deploy don't
+            // pay for establishing connections and preparing statements.
+            let database = context.database();
+            let conns = futures::future::try_join_all(
+                (0..config.database_pool_size).map(|_| database.get()),
+            )
+            .await?;
+            info!("warmed up {} database connections", conns.len());
+        }
 
         Ok(Self { config, context })
     }
@@ -125,67 +219,535 @@ impl<AC: CloneableAppContext> Lowboy<AC> {
         Ok(())
     }
 
-    pub async fn serve<App: app::App<AC>>(self) -> Result<()> {
-        let session_store = DieselSqliteSessionStore::new(self.context.database().clone());
-        session_store.migrate().await?;
+    /// Run an attached database's migrations on a connection of its own, so they're tracked in
+    /// that database rather than the main one.
+    fn run_attached_migrations(database: &AttachedDatabase) -> Result<()> {
+        let Some(ref migrations) = database.migrations else {
+            return Ok(());
+        };
+
+        info!("running migrations for attached database {}", database.name);
+        let migrations =
+            FileBasedMigrations::from_path(migrations).map_err(|e| Error::Migration(e.into()))?;
+        let mut conn =
+            SqliteConnection::establish(&database.url).map_err(|e| Error::Migration(e.into()))?;
+
+        HarnessWithOutput::new(&mut conn, LineWriter::new(MigrationWriter))
+            .run_pending_migrations(migrations)?;
+        Ok(())
+    }
 
-        let deletion_task = tokio::task::spawn(
-            session_store
-                .clone()
-                .continuously_delete_expired(Duration::from_secs(60)),
+    fn report_schema_drift(drift: Vec<database::SchemaDrift>, check: SchemaCheck) -> Result<()> {
+        if drift.is_empty() {
+            return Ok(());
+        }
+
+        for difference in &drift {
+            tracing::error!("database schema drift: {difference}");
+        }
+        tracing::error!(
+            "the database schema doesn't match what lowboy expects, was it modified outside of \
+             migrations?"
         );
-        let session_key = BASE64_STANDARD.decode(self.config.session_key)?;
+
+        if check == SchemaCheck::Abort {
+            return Err(database::SchemaDriftError(drift).into());
+        }
+
+        Ok(())
+    }
+
+    pub fn context(&self) -> &AC {
+        &self.context
+    }
+
+    fn session_store(&self) -> DieselSqliteSessionStore {
+        DieselSqliteSessionStore::new(self.context.database().clone())
+            .with_clock(dyn_clone::clone_box(self.context.clock()))
+            .with_max_size(self.config.session_max_size, self.config.session_oversized)
+    }
+
+    /// Build the application router, including the auth routes and all of lowboy's layers.
+    pub async fn router<App: app::App<AC>>(&self) -> Result<Router<AC>> {
+        self.router_with::<App>(&ServeOptions::default()).await
+    }
+
+    /// Build the application router, including the auth routes and the layers enabled in
+    /// `options`.
+    pub async fn router_with<App: app::App<AC>>(
+        &self,
+        options: &ServeOptions,
+    ) -> Result<Router<AC>> {
+        let session_store = self.session_store();
+        session_store.migrate().await?;
+
+        let catalog: Vec<_> = model::LOWBOY_PERMISSIONS
+            .iter()
+            .chain(App::permissions())
+            .copied()
+            .collect();
+        let mut conn = self.context.database().get().await?;
+        model::Permission::sync(&catalog, &mut conn).await?;
+        drop(conn);
+
+        App::components(&mut view::Components::global_mut());
+        App::unique_constraints(&mut form::UniqueConstraints::global_mut());
+        App::mail_templates(&mut mailer::MailTemplates::global_mut());
+        jobs::Jobs::global_mut().register::<digest::SendDigest>();
+        App::jobs(&mut jobs::Jobs::global_mut());
+        App::settings(&mut settings::SettingDefinitions::global_mut());
+        App::publishables(&mut publish::Publishables::global_mut());
+        App::versioned_models(&mut versioning::VersionedModels::global_mut());
+        App::trash(&mut trash::Trash::global_mut());
+
+        let session_key = BASE64_STANDARD.decode(&self.config.session_key)?;
         let session_key = Key::from(session_key.as_slice());
 
-        let session_layer = SessionManagerLayer::new(session_store)
-            .with_secure(false) // @TODO
-            .with_expiry(Expiry::OnInactivity(cookie::time::Duration::days(1)))
+        let session_expiry = cookie::time::Duration::seconds(options.session.expiry.num_seconds());
+        let secure = options
+            .session
+            .secure
+            .unwrap_or_else(|| self.config.public_url().starts_with("https://"));
+        let mut session_layer = SessionManagerLayer::new(session_store)
+            .with_name(options.session.cookie_name.clone())
+            .with_secure(secure)
+            .with_path(self.config.session_cookie_path.clone())
+            .with_expiry(Expiry::OnInactivity(session_expiry))
             .with_signed(session_key);
-
-        let lowboy_auth =
-            LowboyAuth::new(Box::new(self.context.clone()), self.config.oauth_providers)?;
+        if let Some(ref domain) = self.config.session_cookie_domain {
+            if !self.config.session_cookie_domain_matches() {
+                tracing::warn!(
+                    "the session cookie domain `{domain}` doesn't cover {}, browsers will reject \
+                     the cookie and nobody will stay logged in",
+                    self.config.public_url()
+                );
+            }
+            session_layer = session_layer.with_domain(domain.clone());
+        }
+
+        let lowboy_auth = LowboyAuth::new(
+            Box::new(self.context.clone()),
+            self.config.oauth_providers.clone(),
+            &self.config.public_url(),
+        )?;
         let auth_layer = AuthManagerLayerBuilder::new(lowboy_auth, session_layer).build();
 
+        let bot_guard = bot::BotGuard::new(
+            TimeDelta::seconds(self.config.bot_min_fill_time as i64),
+            self.config.captcha.clone(),
+        )
+        .with_checks(App::bot_checks());
+
+        // Expose captured email for debug builds.
+        let mailbox_routes = match self.config.mailer {
+            Some(ref config)
+                if cfg!(debug_assertions)
+                    && config.mailbox_viewer
+                    && config.transport == mailer::Transport::Mailbox =>
+            {
+                controller::mailbox::routes()
+            }
+            _ => Router::new(),
+        };
+
+        // Expose profiling for debug builds.
+        #[cfg(all(unix, feature = "profiling"))]
+        let profile_routes = if cfg!(debug_assertions) && self.config.profiling {
+            controller::profile::routes()
+        } else {
+            Router::new()
+        };
+        #[cfg(not(all(unix, feature = "profiling")))]
+        let profile_routes = {
+            if self.config.profiling {
+                tracing::warn!(
+                    "ignoring `profiling`, it's only available on unix with the `profiling` feature"
+                );
+            }
+            Router::new()
+        };
+
+        let mail_webhook_routes = match self
+            .config
+            .mailer
+            .as_ref()
+            .and_then(|config| config.webhook_secret.as_deref())
+        {
+            Some(secret) => controller::mail_webhook::routes(secret),
+            None => Router::new(),
+        };
+
+        let metrics_routes = if self.config.metrics {
+            Router::new().route("/metrics", get(controller::metrics::<AC>))
+        } else {
+            Router::new()
+        };
+
+        let version_routes = if self.config.version_endpoint {
+            Router::new().route("/__version", get(controller::version::<App, AC>))
+        } else {
+            Router::new()
+        };
+
+        let locale_defaults = locale::LocaleDefaults {
+            locale: self.config.default_locale.clone(),
+            timezone: self
+                .config
+                .default_timezone
+                .parse()
+                .map_err(|_| Error::UnknownTimezone(self.config.default_timezone.clone()))?,
+        };
+
         let router = Router::new()
             .fallback(|| async { LowboyError::NotFound })
             // App routes.
-            .route("/events", get(controller::events::<AC>))
+            .merge(controller::session::routes())
+            .merge(controller::preferences::routes())
+            .merge(controller::draft::routes());
+
+        #[cfg(feature = "sse")]
+        let router = router.route("/events", get(controller::events::<AC>));
+
+        let router = router
             // Previous routes require authentication.
-            .route_layer(login_required!(LowboyAuth, login_url = "/login"))
-            // Static assets.
-            .nest_service("/static", ServeDir::new("static"))
+            .route_layer(crate::login_required!())
+            .merge(controller::announcements::routes())
             .merge(App::routes())
-            .merge(App::auth_routes::<App>())
-            .layer(middleware::map_response_with_state(
+            .merge(App::auth_routes::<App>());
+
+        #[cfg(feature = "admin")]
+        let router = router.merge(controller::admin::routes());
+
+        let router = router
+            .merge(mailbox_routes)
+            .merge(profile_routes)
+            .merge(metrics_routes)
+            .nest("/api", App::api_routes().layer(Extension(auth::ApiRequest)));
+
+        let mut router = App::middleware(router);
+        if options.locale_detection {
+            router = router.layer(middleware::from_fn_with_state(
+                (self.context.clone(), Arc::new(locale_defaults)),
+                locale::detect::<AC>,
+            ));
+        }
+        if options.session_tracking {
+            router = router.layer(middleware::from_fn_with_state(
                 self.context.clone(),
-                view::render_view::<App, AC>,
-            ))
-            .layer(middleware::map_response_with_state(
+                session::track::<AC>,
+            ));
+        }
+        if options.catch_panic {
+            router = router.layer(CatchPanicLayer::custom(error::panic_response));
+        }
+        router = router.layer(middleware::map_response_with_state(
+            self.context.clone(),
+            view::render_view::<App, AC>,
+        ));
+        if options.error_page {
+            router = router.layer(middleware::map_response_with_state(
                 self.context.clone(),
                 view::error_page::<App, AC>,
-            ))
-            .layer(MessagesManagerLayer)
-            .layer(auth_layer)
-            .layer(middleware::map_response_with_state(
+            ));
+        }
+        if options.api_tokens {
+            router = router.layer(middleware::from_fn(auth::authenticate_api_token));
+        }
+        router = router.layer(Extension(Arc::new(bot_guard)));
+        router = router.layer(Extension(extract::JsonLimit(self.config.json_body_limit)));
+        if options.messages {
+            router = router.layer(MessagesManagerLayer);
+        }
+        // Inside the auth layer, so the user making a slow request is logged with it.
+        if self.config.request_watchdog > 0 {
+            router = router.layer(middleware::from_fn_with_state(
+                Duration::from_secs(self.config.request_watchdog),
+                watchdog::watch,
+            ));
+        }
+        // Inside the auth layer, so the actor is the logged in user.
+        router = router.layer(middleware::from_fn(actor::track_actor));
+        router = router.layer(auth_layer);
+        // Errors from the session and auth layers are rendered too.
+        if options.error_page {
+            router = router.layer(middleware::map_response_with_state(
                 self.context.clone(),
                 view::error_page::<App, AC>,
             ));
+        }
+        let router = router
+            .layer(middleware::from_fn(actor::request_id))
+            // Outside the auth layer, so the user it loads is cached for the rest of the request.
+            .layer(middleware::from_fn(model::cache_models));
+
+        let router = if self.config.minify_html {
+            router.layer(middleware::map_response(view::minify))
+        } else {
+            router
+        };
+
+        // Missing static files are shown the error page too, without a user as there's no session.
+        let static_dir = self.config.static_dir()?;
+        let mut static_files = controller::static_files(&static_dir);
+        if options.error_page {
+            static_files = static_files.layer(middleware::map_response_with_state(
+                self.context.clone(),
+                view::error_page::<App, AC>,
+            ));
+        }
+
+        let router = router
+            // Static assets and health checks are merged after the layers above, so they skip the
+            // session and auth layers (and the database roundtrips they make) entirely.
+            .nest_service("/static", static_files)
+            .route(controller::CLIENT_PATH, get(controller::client))
+            .route(controller::STYLES_PATH, get(controller::styles))
+            .route("/healthz", get(controller::healthz))
+            .merge(version_routes)
+            .merge(controller::icons::routes::<App, AC>(&static_dir))
+            // Webhooks come from mail providers, not browsers.
+            .merge(mail_webhook_routes);
+
+        let router = if self.config.version_header {
+            router.layer(middleware::map_response(controller::version_header::<App, AC>))
+        } else {
+            router
+        };
+
+        Ok(router)
+    }
 
-        // Enable livereload for debug builds.
-        #[cfg(debug_assertions)]
-        let (router, _watcher) = livereload(router)?;
+    /// Schedule lowboy's built-in jobs and the app's digests.
+    async fn schedule_jobs<App: app::App<AC>>(&self) -> Result<()> {
+        // Check every schedule before scheduling anything, so a typo fails the boot with a clear
+        // error instead of a job that never runs.
+        let digests: Vec<Arc<dyn digest::Digest>> =
+            App::digests().into_iter().map(Arc::from).collect();
+        let mut scheduled_jobs = jobs::ScheduledJobs::default();
+        for job in jobs::BUILT_IN {
+            scheduled_jobs.add(job.name, job.schedule)?;
+        }
+        for digest in &digests {
+            scheduled_jobs.add(digest.name(), digest.schedule())?;
+        }
+        *jobs::ScheduledJobs::global_mut() = scheduled_jobs;
+        digest::register(&digests);
+
+        // Report the largest sessions hourly, to catch session data bloating over time.
+        let database = self.context.database().clone();
+        let max_size = self.config.session_max_size;
+        self.context
+            .scheduler()
+            .add(jobs::REPORT_LARGEST_SESSIONS.schedule, move || {
+                let database = database.clone();
+                async move { session::report_largest(&database, max_size).await }
+            })
+            .await?;
+
+        // Delete expired drafts hourly.
+        let database = self.context.database().clone();
+        let clock = dyn_clone::clone_box(self.context.clock());
+        self.context
+            .scheduler()
+            .add(jobs::DELETE_EXPIRED_DRAFTS.schedule, move || {
+                let database = database.clone();
+                let now = clock.now();
+                async move { controller::draft::delete_expired(&database, now).await }
+            })
+            .await?;
+
+        // Delete expired key-value entries hourly.
+        let database = self.context.database().clone();
+        let clock = dyn_clone::clone_box(self.context.clock());
+        self.context
+            .scheduler()
+            .add(jobs::DELETE_EXPIRED_KV_ENTRIES.schedule, move || {
+                let database = database.clone();
+                let now = clock.now();
+                async move { kv::delete_expired(&database, now).await }
+            })
+            .await?;
+
+        // Tell clients about announcements as they become active, every minute.
+        let database = self.context.database().clone();
+        let events = self.context.events().clone();
+        let clock = dyn_clone::clone_box(self.context.clock());
+        self.context
+            .scheduler()
+            .add(jobs::PUSH_ANNOUNCEMENTS.schedule, move || {
+                let database = database.clone();
+                let events = events.clone();
+                let now = clock.now();
+                async move {
+                    controller::announcements::push_active(&database, &events, now).await
+                }
+            })
+            .await?;
+
+        // Publish scheduled models as they come due, every minute.
+        let context = self.context.clone();
+        self.context
+            .scheduler()
+            .add(jobs::PUBLISH_SCHEDULED.schedule, move || {
+                let context = context.clone();
+                async move { publish::run(&context).await }
+            })
+            .await?;
+
+        // Prune old versions of models nightly.
+        let context = self.context.clone();
+        self.context
+            .scheduler()
+            .add(jobs::PRUNE_VERSIONS.schedule, move || {
+                let context = context.clone();
+                async move { versioning::run(&context).await }
+            })
+            .await?;
+
+        // Purge expired models from the trash nightly.
+        let context = self.context.clone();
+        self.context
+            .scheduler()
+            .add(jobs::PURGE_TRASH.schedule, move || {
+                let context = context.clone();
+                async move { trash::run(&context).await }
+            })
+            .await?;
+
+        // Expire the presence of connections that went away without closing.
+        let context = self.context.clone();
+        self.context
+            .scheduler()
+            .add(jobs::EXPIRE_PRESENCE.schedule, move || {
+                let context = context.clone();
+                async move {
+                    let expired = context
+                        .presence()
+                        .expire(context.events(), context.clock().now());
+                    if expired > 0 {
+                        info!("expired {expired} stale presence connections");
+                    }
+                }
+            })
+            .await?;
+
+        // Queue the app's digests on their schedules.
+        for digest in digests {
+            let context = self.context.clone();
+            self.context
+                .scheduler()
+                .add(digest.schedule(), move || {
+                    let digest = digest.clone();
+                    let context = context.clone();
+                    async move { digest::run(&context, digest.as_ref()).await }
+                })
+                .await?;
+        }
 
-        let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await?;
-        info!("listening on {}", listener.local_addr()?);
+        Ok(())
+    }
 
-        axum::serve(
-            listener,
-            router.with_state(self.context).into_make_service(),
-        )
-        .with_graceful_shutdown(shutdown_signal(Some(deletion_task.abort_handle())))
-        .await?;
+    pub async fn serve<App: app::App<AC>>(self) -> Result<()> {
+        self.serve_with::<App>(ServeOptions::default()).await
+    }
+
+    /// Serve the app, with the built-in layers enabled in `options`.
+    pub async fn serve_with<App: app::App<AC>>(self, options: ServeOptions) -> Result<()> {
+        let router = self.router_with::<App>(&options).await?;
+
+        let deletion_task = tokio::task::spawn(
+            self.session_store()
+                .continuously_delete_expired(Duration::from_secs(60)),
+        );
+
+        self.schedule_jobs::<App>().await?;
+
+        // Run queued background jobs as they come due.
+        let _workers = jobs::Workers::spawn(
+            self.context.clone(),
+            self.config.jobs_workers,
+            Duration::from_millis(self.config.jobs_poll_interval),
+        );
+
+        // Enable livereload for debug builds.
+        #[cfg(all(debug_assertions, feature = "livereload"))]
+        let (router, _watcher) = if options.livereload {
+            let (router, watcher) = livereload(router, &self.config.static_dir()?)?;
+            (router, Some(watcher))
+        } else {
+            (router, None)
+        };
+
+        // Serve the app's gRPC services on their own port, or alongside the web app.
+        #[cfg(feature = "grpc")]
+        let (router, grpc_enabled, grpc_server) = {
+            let grpc_services = App::grpc(&self.context);
+            let grpc_enabled = grpc_services.is_some();
+            let mut grpc_server = None;
+            let router = match (grpc_services, self.config.grpc_port) {
+                (Some(services), Some(port)) => {
+                    let addr = SocketAddr::new(self.config.listen_addr, port);
+                    let incoming = grpc::bind(addr).await?;
+                    grpc_server = Some(tokio::spawn(grpc::serve(services, incoming)));
+                    router
+                }
+                (Some(services), None) => grpc::share_port(router, services),
+                (None, _) => router,
+            };
+            (router, grpc_enabled, grpc_server)
+        };
+        #[cfg(not(feature = "grpc"))]
+        let grpc_enabled = false;
+
+        if self.config.startup_banner {
+            let mut conn = self.context.database().get().await?;
+            let migrations = conn
+                .spawn_blocking(|conn| Ok(banner::MigrationStatus::of(conn)))
+                .await??;
+            drop(conn);
+
+            banner::StartupSummary::new::<App, AC>(&self.config, &options, grpc_enabled, migrations)
+                .log();
+        }
+
+        let addr = SocketAddr::new(self.config.listen_addr, self.config.listen_port);
+        let service = router
+            .with_state(self.context)
+            .into_make_service_with_connect_info::<SocketAddr>();
+
+        match self.config.tls {
+            Some(ref tls) => {
+                let rustls = RustlsConfig::from_pem_file(&tls.cert, &tls.key).await?;
+                let handle = axum_server::Handle::new();
+
+                let shutdown = handle.clone();
+                let abort_handle = deletion_task.abort_handle();
+                tokio::spawn(async move {
+                    shutdown_signal(Some(abort_handle)).await;
+                    shutdown.graceful_shutdown(None);
+                });
+
+                info!("listening on https://{addr}");
+                axum_server::bind_rustls(addr, rustls)
+                    .handle(handle)
+                    .serve(service)
+                    .await?;
+            }
+            None => {
+                let listener = tokio::net::TcpListener::bind(addr).await?;
+                info!("listening on {}", listener.local_addr()?);
+
+                axum::serve(listener, service)
+                    .with_graceful_shutdown(shutdown_signal(Some(deletion_task.abort_handle())))
+                    .await?;
+            }
+        }
 
         deletion_task.await??;
+        #[cfg(feature = "grpc")]
+        if let Some(grpc_server) = grpc_server {
+            grpc_server.await??;
+        }
 
         Ok(())
     }
@@ -211,14 +773,15 @@ pub async fn shutdown_signal(abort_handle: Option<AbortHandle>) {
     }
 }
 
-#[cfg(debug_assertions)]
+#[cfg(all(debug_assertions, feature = "livereload"))]
 fn not_htmx_predicate(req: &axum::extract::Request) -> bool {
     !req.headers().contains_key("hx-request")
 }
 
-#[cfg(debug_assertions)]
+#[cfg(all(debug_assertions, feature = "livereload"))]
 fn livereload<AC: CloneableAppContext>(
     router: axum::Router<AC>,
+    static_dir: &std::path::Path,
 ) -> Result<(axum::Router<AC>, notify::FsEventWatcher)> {
     use notify::Watcher;
 
@@ -228,10 +791,7 @@ fn livereload<AC: CloneableAppContext>(
     let router = router.layer(livereload.request_predicate(not_htmx_predicate));
 
     let mut watcher = notify::recommended_watcher(move |_| reloader.reload())?;
-    watcher.watch(
-        std::path::Path::new("static"),
-        notify::RecursiveMode::Recursive,
-    )?;
+    watcher.watch(static_dir, notify::RecursiveMode::Recursive)?;
 
     Ok((router, watcher))
 }
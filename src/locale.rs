@@ -0,0 +1,148 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::header::ACCEPT_LANGUAGE;
+use axum::http::request::Parts;
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use tower_sessions::Session;
+use tracing::warn;
+
+use crate::context::CloneableAppContext;
+use crate::model::{Preferences, TIMEZONE_PREFERENCE};
+use crate::view::RenderContext;
+use crate::{metrics, AuthSession};
+
+/// Session key for the locale of visitors without a preference, e.g. chosen from a language menu.
+pub const LOCALE_SESSION_KEY: &str = "lowboy.locale";
+/// Session key for the timezone of visitors without a preference, e.g. detected by the browser.
+pub const TIMEZONE_SESSION_KEY: &str = "lowboy.timezone";
+
+/// The locale and timezone used when nothing more specific is known.
+#[derive(Clone, Debug)]
+pub struct LocaleDefaults {
+    pub locale: Option<String>,
+    pub timezone: Tz,
+}
+
+/// The locale and timezone of the current request, resolved by [`detect`].
+///
+/// Available as an extractor, and in the response extensions, where
+/// [`render_view`](crate::view::render_view) uses it for the date filters.
+#[derive(Clone, Debug)]
+pub struct RequestLocale {
+    /// e.g. `en-US`.
+    pub locale: Option<String>,
+    pub timezone: Tz,
+}
+
+impl Default for RequestLocale {
+    fn default() -> Self {
+        Self {
+            locale: None,
+            timezone: Tz::UTC,
+        }
+    }
+}
+
+impl RequestLocale {
+    /// A [`RenderContext`] for rendering views in this locale and timezone.
+    pub fn render_context(&self, now: DateTime<Utc>) -> RenderContext {
+        RenderContext {
+            timezone: self.timezone,
+            locale: self.locale.clone(),
+            now,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RequestLocale {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().cloned().unwrap_or_default())
+    }
+}
+
+/// Resolve the request's locale and timezone from the user's preferences, the session, the
+/// `Accept-Language` header (locale only) and the configured defaults, in that order.
+pub async fn detect<AC: CloneableAppContext>(
+    State((context, defaults)): State<(AC, Arc<LocaleDefaults>)>,
+    auth_session: AuthSession,
+    session: Session,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let preferences = match auth_session.user {
+        Some(user) => match metrics::checkout(context.database()).await {
+            Ok(mut conn) => Preferences::for_user(user.id, &mut conn)
+                .await
+                .unwrap_or_else(|e| {
+                    warn!("couldn't load the user's preferences: {e}");
+                    Preferences::default()
+                }),
+            Err(e) => {
+                warn!("couldn't load the user's preferences: {e}");
+                Preferences::default()
+            }
+        },
+        None => Preferences::default(),
+    };
+
+    let session_locale = session_value(&session, LOCALE_SESSION_KEY).await;
+    let session_timezone = session_value(&session, TIMEZONE_SESSION_KEY).await;
+
+    let locale = preferences
+        .locale()
+        .map(str::to_string)
+        .or(session_locale)
+        .or_else(|| accept_language(request.headers()))
+        .or_else(|| defaults.locale.clone());
+    let timezone = preferences
+        .get(TIMEZONE_PREFERENCE)
+        .or_else(|| session_timezone.and_then(|timezone| timezone.parse().ok()))
+        .unwrap_or(defaults.timezone);
+
+    let locale = RequestLocale { locale, timezone };
+    request.extensions_mut().insert(locale.clone());
+
+    let mut response = next.run(request).await;
+    response.extensions_mut().insert(locale);
+    response
+}
+
+async fn session_value(session: &Session, key: &str) -> Option<String> {
+    session.get::<String>(key).await.unwrap_or_else(|e| {
+        warn!("couldn't load `{key}` from the session: {e}");
+        None
+    })
+}
+
+/// The most preferred language in an `Accept-Language` header, e.g. `en-US` for
+/// `fr;q=0.8, en-US, *;q=0.5`.
+pub fn accept_language(headers: &HeaderMap) -> Option<String> {
+    let header = headers.get(ACCEPT_LANGUAGE)?.to_str().ok()?;
+
+    header
+        .split(',')
+        .filter_map(|language| {
+            let mut parts = language.trim().split(';');
+            let tag = parts.next()?.trim();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |quality| quality.parse::<f32>().ok())?;
+
+            (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((tag, quality))
+        })
+        // The first of the most preferred languages.
+        .fold(None, |best: Option<(&str, f32)>, (tag, quality)| match best {
+            Some((_, best_quality)) if best_quality >= quality => best,
+            _ => Some((tag, quality)),
+        })
+        .map(|(tag, _)| tag.to_string())
+}
@@ -0,0 +1,198 @@
+use std::fmt::Debug;
+
+use dyn_clone::DynClone;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "mailer")]
+pub mod provider;
+pub mod template;
+#[cfg(feature = "mailer")]
+mod transport;
+pub mod webhook;
+
+#[cfg(feature = "mailer")]
+pub use provider::{MailgunMailer, SendGridMailer, SesMailer};
+pub use template::{render_mail, MailTemplate, MailTemplates, RenderedMail};
+#[cfg(feature = "mailer")]
+pub use transport::*;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[cfg(feature = "mailer")]
+    #[error(transparent)]
+    LettreSmtp(#[from] lettre::transport::smtp::Error),
+
+    #[cfg(feature = "mailer")]
+    #[error(transparent)]
+    LettreAddress(#[from] lettre::address::AddressError),
+
+    #[cfg(feature = "mailer")]
+    #[error(transparent)]
+    LettreError(#[from] lettre::error::Error),
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    Pool(#[from] deadpool::managed::PoolError<diesel_async::pooled_connection::PoolError>),
+
+    #[error(transparent)]
+    Template(#[from] rinja::Error),
+
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+
+    #[error("{provider} rejected the email ({status}): {body}")]
+    Provider {
+        provider: &'static str,
+        status: u16,
+        body: String,
+    },
+
+    #[error("invalid webhook: {0}")]
+    Webhook(&'static str),
+
+    #[error("the `{0}` transport is used but isn't configured")]
+    MissingTransportConfig(Transport),
+
+    #[error("no mail template is registered as `{0}`")]
+    UnknownMailTemplate(String),
+
+    #[error("mail template `{0}` was rendered with the wrong props")]
+    MailTemplateProps(String),
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq, strum::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Transport {
+    /// Deliver email through the configured SMTP relay.
+    #[default]
+    Smtp,
+    /// Capture email in the database instead of delivering it. Intended for development and tests.
+    Mailbox,
+    /// Deliver email through the Amazon SES API, configured in `ses`.
+    Ses,
+    /// Deliver email through the SendGrid API, configured in `sendgrid`.
+    SendGrid,
+    /// Deliver email through the Mailgun API, configured in `mailgun`.
+    Mailgun,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Config {
+    #[serde(default)]
+    pub transport: Transport,
+    /// The address lowboy's emails are sent from, e.g. `Example <no-reply@example.com>`.
+    #[serde(default = "default_from")]
+    pub from: String,
+    /// Transports to try in order when `transport` fails to send an email, e.g. `[ses, smtp]`.
+    #[serde(default)]
+    pub failover: Vec<Transport>,
+    #[serde(default)]
+    pub smtp_relay: String,
+    #[serde(default)]
+    pub smtp_username: String,
+    #[serde(default)]
+    pub smtp_password: String,
+    #[serde(default)]
+    pub ses: Option<SesConfig>,
+    #[serde(default)]
+    pub sendgrid: Option<SendGridConfig>,
+    #[serde(default)]
+    pub mailgun: Option<MailgunConfig>,
+    /// Accept bounce and complaint notifications at `/webhooks/mail/<transport>/<secret>`, e.g.
+    /// `/webhooks/mail/ses/<secret>` for an SNS subscription.
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+    /// Expose captured email at `/dev/mailbox` (debug builds only).
+    #[serde(default)]
+    pub mailbox_viewer: bool,
+}
+
+fn default_from() -> String {
+    "Lowboy <no-reply@marc.cx>".to_string()
+}
+
+/// Settings for [`SesMailer`](provider::SesMailer).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SesConfig {
+    /// e.g. `us-east-1`.
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// The configuration set to send with, e.g. for event publishing.
+    #[serde(default)]
+    pub configuration_set: Option<String>,
+}
+
+/// Settings for [`SendGridMailer`](provider::SendGridMailer).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SendGridConfig {
+    pub api_key: String,
+}
+
+/// Settings for [`MailgunMailer`](provider::MailgunMailer).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MailgunConfig {
+    /// The sending domain, e.g. `mg.example.com`.
+    pub domain: String,
+    pub api_key: String,
+    /// Use Mailgun's EU region.
+    #[serde(default)]
+    pub eu: bool,
+}
+
+/// An outgoing email.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Mail {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+    /// The mail template the email was rendered from, for tracking its delivery.
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+impl Mail {
+    /// Address a `rendered` email.
+    pub fn new(from: impl Into<String>, to: impl Into<String>, rendered: RenderedMail) -> Self {
+        Self {
+            from: from.into(),
+            to: to.into(),
+            subject: rendered.subject,
+            text: rendered.text,
+            html: rendered.html,
+            template: None,
+        }
+    }
+
+    /// Render the mail template `name` with `props`, and address it.
+    pub fn from_template<P: 'static>(
+        from: impl Into<String>,
+        to: impl Into<String>,
+        name: &str,
+        props: &P,
+    ) -> Result<Self> {
+        Ok(Self {
+            template: Some(name.to_string()),
+            ..Self::new(from, to, render_mail(name, props)?)
+        })
+    }
+}
+
+#[async_trait::async_trait]
+pub trait Mailer: Debug + DynClone + Send + Sync {
+    /// Send `mail`, returning the transport's id for the message if it gave one.
+    async fn send(&self, mail: &Mail) -> Result<Option<String>>;
+}
+dyn_clone::clone_trait_object!(Mailer);
+
+/// The [`DeadLetter`](crate::model::DeadLetter) job of email that failed to send, with the
+/// [`Mail`] as its payload.
+pub const SEND_EMAIL_JOB: &str = "send_email";
@@ -0,0 +1,260 @@
+//! Mailers delivering email through the HTTP APIs of email providers, rather than SMTP.
+
+use hmac::{Hmac, Mac};
+use lettre::message::Mailbox as Address;
+use reqwest::{Client, RequestBuilder};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use super::{Error, Mail, MailgunConfig, Mailer, Result, SendGridConfig, SesConfig};
+use crate::clock::{Clock, SystemClock};
+
+/// Send `request`, failing with the provider's response if it wasn't successful.
+async fn send(provider: &'static str, request: RequestBuilder) -> Result<reqwest::Response> {
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        return Err(Error::Provider {
+            provider,
+            status: status.as_u16(),
+            body: response.text().await.unwrap_or_default(),
+        });
+    }
+
+    Ok(response)
+}
+
+/// Delivers email through the Amazon SES v2 API.
+#[derive(Clone, derive_more::Debug)]
+pub struct SesMailer {
+    region: String,
+    access_key_id: String,
+    #[debug(skip)]
+    secret_access_key: String,
+    configuration_set: Option<String>,
+    #[debug(skip)]
+    client: Client,
+    clock: Box<dyn Clock>,
+}
+
+impl SesMailer {
+    const SERVICE: &'static str = "ses";
+    const PATH: &'static str = "/v2/email/outbound-emails";
+
+    pub fn new(config: &SesConfig) -> Self {
+        Self {
+            region: config.region.clone(),
+            access_key_id: config.access_key_id.clone(),
+            secret_access_key: config.secret_access_key.clone(),
+            configuration_set: config.configuration_set.clone(),
+            client: Client::new(),
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock(self, clock: Box<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    fn host(&self) -> String {
+        format!("email.{}.amazonaws.com", self.region)
+    }
+
+    /// The `Authorization` header for `body`, signed with AWS Signature Version 4.
+    fn authorization(&self, body: &str, amz_date: &str) -> String {
+        let date = &amz_date[..8];
+        let scope = format!("{date}/{}/{}/aws4_request", self.region, Self::SERVICE);
+        let signed_headers = "content-type;host;x-amz-date";
+
+        let canonical_request = format!(
+            "POST\n{path}\n\ncontent-type:application/json\nhost:{host}\nx-amz-date:{amz_date}\n\n{signed_headers}\n{payload}",
+            path = Self::PATH,
+            host = self.host(),
+            payload = hex(&Sha256::digest(body)),
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex(&Sha256::digest(&canonical_request)),
+        );
+
+        let key = format!("AWS4{}", self.secret_access_key);
+        let key = hmac(key.as_bytes(), date);
+        let key = hmac(&key, &self.region);
+        let key = hmac(&key, Self::SERVICE);
+        let key = hmac(&key, "aws4_request");
+        let signature = hex(&hmac(&key, &string_to_sign));
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.access_key_id,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for SesMailer {
+    async fn send(&self, mail: &Mail) -> Result<Option<String>> {
+        let mut body = json!({
+            "FromEmailAddress": mail.from,
+            "Destination": { "ToAddresses": [mail.to] },
+            "Content": {
+                "Simple": {
+                    "Subject": { "Data": mail.subject, "Charset": "UTF-8" },
+                    "Body": {
+                        "Text": { "Data": mail.text, "Charset": "UTF-8" },
+                    },
+                },
+            },
+        });
+        if let Some(html) = &mail.html {
+            body["Content"]["Simple"]["Body"]["Html"] = json!({ "Data": html, "Charset": "UTF-8" });
+        }
+        if let Some(configuration_set) = &self.configuration_set {
+            body["ConfigurationSetName"] = json!(configuration_set);
+        }
+        let body = body.to_string();
+
+        let amz_date = self.clock.now().format("%Y%m%dT%H%M%SZ").to_string();
+        let request = self
+            .client
+            .post(format!("https://{}{}", self.host(), Self::PATH))
+            .header("content-type", "application/json")
+            .header("x-amz-date", &amz_date)
+            .header("authorization", self.authorization(&body, &amz_date))
+            .body(body);
+
+        #[derive(Deserialize)]
+        struct Response {
+            #[serde(rename = "MessageId")]
+            message_id: Option<String>,
+        }
+
+        let response: Response = send("ses", request).await?.json().await?;
+        Ok(response.message_id)
+    }
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Delivers email through the SendGrid v3 API.
+#[derive(Clone, derive_more::Debug)]
+pub struct SendGridMailer {
+    #[debug(skip)]
+    api_key: String,
+    #[debug(skip)]
+    client: Client,
+}
+
+impl SendGridMailer {
+    pub fn new(config: &SendGridConfig) -> Self {
+        Self {
+            api_key: config.api_key.clone(),
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for SendGridMailer {
+    async fn send(&self, mail: &Mail) -> Result<Option<String>> {
+        fn address(mailbox: &str) -> Result<serde_json::Value> {
+            let mailbox = mailbox.parse::<Address>()?;
+            Ok(match mailbox.name {
+                Some(name) => json!({ "email": mailbox.email.to_string(), "name": name }),
+                None => json!({ "email": mailbox.email.to_string() }),
+            })
+        }
+
+        let mut content = vec![json!({ "type": "text/plain", "value": mail.text })];
+        if let Some(html) = &mail.html {
+            content.push(json!({ "type": "text/html", "value": html }));
+        }
+
+        let request = self
+            .client
+            .post("https://api.sendgrid.com/v3/mail/send")
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "personalizations": [{ "to": [address(&mail.to)?] }],
+                "from": address(&mail.from)?,
+                "subject": mail.subject,
+                "content": content,
+            }));
+
+        let response = send("sendgrid", request).await?;
+        Ok(response
+            .headers()
+            .get("x-message-id")
+            .and_then(|id| id.to_str().ok())
+            .map(str::to_string))
+    }
+}
+
+/// Delivers email through the Mailgun API.
+#[derive(Clone, derive_more::Debug)]
+pub struct MailgunMailer {
+    domain: String,
+    #[debug(skip)]
+    api_key: String,
+    eu: bool,
+    #[debug(skip)]
+    client: Client,
+}
+
+impl MailgunMailer {
+    pub fn new(config: &MailgunConfig) -> Self {
+        Self {
+            domain: config.domain.clone(),
+            api_key: config.api_key.clone(),
+            eu: config.eu,
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for MailgunMailer {
+    async fn send(&self, mail: &Mail) -> Result<Option<String>> {
+        let host = if self.eu {
+            "api.eu.mailgun.net"
+        } else {
+            "api.mailgun.net"
+        };
+
+        let mut form = vec![
+            ("from", mail.from.as_str()),
+            ("to", mail.to.as_str()),
+            ("subject", mail.subject.as_str()),
+            ("text", mail.text.as_str()),
+        ];
+        if let Some(html) = &mail.html {
+            form.push(("html", html.as_str()));
+        }
+
+        let request = self
+            .client
+            .post(format!("https://{host}/v3/{}/messages", self.domain))
+            .basic_auth("api", Some(&self.api_key))
+            .form(&form);
+
+        #[derive(Deserialize)]
+        struct Response {
+            id: Option<String>,
+        }
+
+        let response: Response = send("mailgun", request).await?.json().await?;
+        // Webhooks refer to the message by its `Message-Id`, without the angle brackets.
+        Ok(response
+            .id
+            .map(|id| id.trim_matches(|c| c == '<' || c == '>').to_string()))
+    }
+}
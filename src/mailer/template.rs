@@ -0,0 +1,315 @@
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+use std::sync::{Arc, LazyLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use rinja::Template;
+
+use super::{Error, Result};
+use crate::security::{SecurityNotification, LOCKOUT};
+
+static MAIL_TEMPLATES: LazyLock<RwLock<MailTemplates>> = LazyLock::new(Default::default);
+
+/// The subject and parts of an email, before it's addressed.
+#[derive(Clone, Debug)]
+pub struct RenderedMail {
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+}
+
+/// An email rendered from typed props.
+///
+/// Mail templates are registered by name in [`MailTemplates`], so lowboy can send its emails
+/// without knowing which implementation the app chose.
+pub trait MailTemplate: Send + Sync + 'static {
+    type Props: 'static;
+
+    fn render(&self, props: &Self::Props) -> rinja::Result<RenderedMail>;
+}
+
+/// The body of an email, rendered as HTML inside the shared [`MailLayout`].
+pub trait MailBody: Template + 'static {
+    fn subject(&self) -> String;
+
+    /// The plain text part. Defaults to the HTML body converted to text, so most emails only need
+    /// the one template.
+    fn text(&self) -> rinja::Result<String> {
+        Ok(html_to_text(&self.render()?))
+    }
+}
+
+/// Renders props which are [`MailBody`]s within the layout, as lowboy's built-in emails do.
+pub struct LayoutMailTemplate<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for LayoutMailTemplate<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: MailBody> MailTemplate for LayoutMailTemplate<T> {
+    type Props = T;
+
+    fn render(&self, props: &T) -> rinja::Result<RenderedMail> {
+        let subject = props.subject();
+        let html = MailLayout {
+            subject: &subject,
+            body: &props.render()?,
+        }
+        .render()?;
+
+        Ok(RenderedMail {
+            text: props.text()?,
+            html: Some(html),
+            subject,
+        })
+    }
+}
+
+/// The layout every [`MailBody`] is rendered in.
+#[derive(Template)]
+#[template(path = "mail/layout.html")]
+pub struct MailLayout<'a> {
+    pub subject: &'a str,
+    pub body: &'a str,
+}
+
+trait AnyMailTemplate: Send + Sync {
+    /// Render the template, or `None` if `props` aren't the template's props.
+    fn render_any(&self, props: &dyn Any) -> Option<rinja::Result<RenderedMail>>;
+}
+
+impl<T: MailTemplate> AnyMailTemplate for T {
+    fn render_any(&self, props: &dyn Any) -> Option<rinja::Result<RenderedMail>> {
+        props
+            .downcast_ref::<T::Props>()
+            .map(|props| self.render(props))
+    }
+}
+
+/// The registry of mail templates, by name.
+///
+/// Lowboy registers `verify_email` and `security_notification`, with previews for the dev
+/// mailbox. Apps add their own (or replace lowboy's, keeping the props) in
+/// [`crate::App::mail_templates`].
+#[derive(Clone)]
+pub struct MailTemplates {
+    templates: HashMap<String, Arc<dyn AnyMailTemplate>>,
+    previews: BTreeMap<String, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Default for MailTemplates {
+    fn default() -> Self {
+        let mut templates = Self {
+            templates: HashMap::new(),
+            previews: BTreeMap::new(),
+        };
+        templates
+            .register("verify_email", LayoutMailTemplate::<VerifyEmail>::default())
+            .preview(
+                "verify_email",
+                VerifyEmail {
+                    username: "lowboy".to_string(),
+                    verification_url: "http://localhost:3000/email/lowboy@example.com/verify/token"
+                        .to_string(),
+                },
+            )
+            .register(
+                "security_notification",
+                LayoutMailTemplate::<SecurityNotificationEmail>::default(),
+            )
+            .preview(
+                "security_notification",
+                SecurityNotificationEmail {
+                    username: "lowboy".to_string(),
+                    notification: SecurityNotification::NewDeviceLogin {
+                        user_agent: "Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Firefox/131.0"
+                            .to_string(),
+                        ip: Some("127.0.0.1".to_string()),
+                    },
+                },
+            );
+        templates
+    }
+}
+
+impl MailTemplates {
+    /// The templates used by [`render_mail`].
+    pub fn global() -> RwLockReadGuard<'static, Self> {
+        MAIL_TEMPLATES
+            .read()
+            .expect("mail templates lock should not be poisoned")
+    }
+
+    pub fn global_mut() -> RwLockWriteGuard<'static, Self> {
+        MAIL_TEMPLATES
+            .write()
+            .expect("mail templates lock should not be poisoned")
+    }
+
+    /// Register `template` as `name`, replacing any template already registered with that name.
+    pub fn register(&mut self, name: impl Into<String>, template: impl MailTemplate) -> &mut Self {
+        self.templates.insert(name.into(), Arc::new(template));
+        self
+    }
+
+    /// Preview the template registered as `name` with the sample `props` in the dev mailbox.
+    pub fn preview<P: Send + Sync + 'static>(
+        &mut self,
+        name: impl Into<String>,
+        props: P,
+    ) -> &mut Self {
+        self.previews.insert(name.into(), Arc::new(props));
+        self
+    }
+
+    /// Render the template registered as `name`.
+    pub fn render<P: 'static>(&self, name: &str, props: &P) -> Result<RenderedMail> {
+        self.render_any(name, props)
+    }
+
+    /// The names of the templates with previews.
+    pub fn previews(&self) -> Vec<&str> {
+        self.previews.keys().map(String::as_str).collect()
+    }
+
+    /// Render the template registered as `name` with its preview props.
+    pub fn render_preview(&self, name: &str) -> Option<Result<RenderedMail>> {
+        let props = self.previews.get(name)?;
+        Some(self.render_any(name, props.as_ref()))
+    }
+
+    fn render_any(&self, name: &str, props: &dyn Any) -> Result<RenderedMail> {
+        let template = self
+            .templates
+            .get(name)
+            .ok_or_else(|| Error::UnknownMailTemplate(name.to_string()))?;
+
+        template
+            .render_any(props)
+            .ok_or_else(|| Error::MailTemplateProps(name.to_string()))?
+            .map_err(Error::from)
+    }
+}
+
+/// Render the globally registered mail template `name`.
+pub fn render_mail<P: 'static>(name: &str, props: &P) -> Result<RenderedMail> {
+    MailTemplates::global().render(name, props)
+}
+
+/// Convert an HTML email body to plain text, keeping the targets of links.
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut href: Option<String> = None;
+    let mut label_start = 0;
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        text.push_str(&decode_entities(&rest[..start]));
+
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let closing = tag.starts_with('/');
+
+        match (name.as_str(), closing) {
+            ("a", false) => {
+                href = attribute(tag, "href");
+                label_start = text.len();
+            }
+            ("a", true) => {
+                if let Some(href) = href.take() {
+                    if text[label_start..].trim() != href {
+                        text.push_str(": ");
+                        text.push_str(&href);
+                    }
+                }
+            }
+            ("br", _) => text.push('\n'),
+            ("li", false) => text.push_str("\n- "),
+            ("p" | "div" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "ul" | "ol" | "tr", _) => {
+                text.push_str("\n\n");
+            }
+            _ => {}
+        }
+    }
+    text.push_str(&decode_entities(rest));
+
+    // Collapse the whitespace of the template's indentation, keeping at most one blank line.
+    let mut lines = vec![];
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() && matches!(lines.last().map(String::as_str), None | Some("")) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+
+    lines.join("\n")
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!("{name}=\""))? + name.len() + 2;
+    let end = tag[start..].find('"')?;
+    Some(decode_entities(&tag[start..start + end]))
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#34;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&#x2f;", "/")
+        .replace("&amp;", "&")
+}
+
+/// The email asking a new user to verify their email address.
+#[derive(Clone, Debug, Template)]
+#[template(path = "mail/verify-email.html")]
+pub struct VerifyEmail {
+    pub username: String,
+    pub verification_url: String,
+}
+
+impl MailBody for VerifyEmail {
+    fn subject(&self) -> String {
+        "Email Verification".to_string()
+    }
+}
+
+/// The email about a [`SecurityNotification`].
+#[derive(Clone, Debug, Template)]
+#[template(path = "mail/security-notification.html")]
+pub struct SecurityNotificationEmail {
+    pub username: String,
+    pub notification: SecurityNotification,
+}
+
+impl SecurityNotificationEmail {
+    fn lockout_minutes(&self) -> i64 {
+        LOCKOUT.num_minutes()
+    }
+}
+
+impl MailBody for SecurityNotificationEmail {
+    fn subject(&self) -> String {
+        self.notification.subject().to_string()
+    }
+}
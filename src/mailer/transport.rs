@@ -0,0 +1,351 @@
+//! The transports delivering email, and the mailers wrapping them.
+
+use diesel_async::pooled_connection::deadpool::Pool;
+use lettre::message::{header, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::response::Response;
+use lettre::{AsyncSmtpTransport, AsyncTransport as _, Message, Tokio1Executor};
+
+use super::{
+    Config, Error, Mail, Mailer, MailgunMailer, Result, SendGridMailer, SesMailer, Transport,
+    SEND_EMAIL_JOB,
+};
+use crate::clock::{Clock, SystemClock};
+use crate::metrics::MailMetrics;
+use crate::model::{DeadLetter, EmailSuppression, MailboxMessage, SentEmail};
+use crate::Connection;
+
+impl Mail {
+    /// The bare address of the recipient, e.g. `user@example.com` for
+    /// `User <user@example.com>`.
+    pub fn recipient(&self) -> Result<String> {
+        Ok(self.to.parse::<lettre::message::Mailbox>()?.email.to_string())
+    }
+
+    pub fn to_message(&self) -> Result<Message> {
+        let builder = Message::builder()
+            .from(self.from.parse()?)
+            .to(self.to.parse()?)
+            .subject(&self.subject);
+
+        let text = SinglePart::builder()
+            .header(header::ContentType::TEXT_PLAIN)
+            .body(self.text.clone());
+
+        let message = match &self.html {
+            Some(html) => builder.multipart(
+                MultiPart::alternative().singlepart(text).singlepart(
+                    SinglePart::builder()
+                        .header(header::ContentType::TEXT_HTML)
+                        .body(html.clone()),
+                ),
+            )?,
+            None => builder.singlepart(text)?,
+        };
+
+        Ok(message)
+    }
+}
+
+pub fn create_mailer(config: &Config, database: &Pool<Connection>) -> Result<Box<dyn Mailer>> {
+    let primary = create_transport(&config.transport, config, database)?;
+
+    let transport: Box<dyn Mailer> = if config.failover.is_empty() {
+        primary
+    } else {
+        let mut mailers = vec![primary];
+        for transport in &config.failover {
+            mailers.push(create_transport(transport, config, database)?);
+        }
+        Box::new(FailoverMailer::new(mailers))
+    };
+
+    Ok(Box::new(TrackedMailer::new(transport, database.clone())))
+}
+
+fn create_transport(
+    transport: &Transport,
+    config: &Config,
+    database: &Pool<Connection>,
+) -> Result<Box<dyn Mailer>> {
+    let missing = || Error::MissingTransportConfig(transport.clone());
+
+    Ok(match transport {
+        Transport::Smtp => Box::new(SmtpMailer::new(config)?),
+        Transport::Mailbox => Box::new(Mailbox::new(database.clone())),
+        Transport::Ses => Box::new(SesMailer::new(config.ses.as_ref().ok_or_else(missing)?)),
+        Transport::SendGrid => Box::new(SendGridMailer::new(
+            config.sendgrid.as_ref().ok_or_else(missing)?,
+        )),
+        Transport::Mailgun => Box::new(MailgunMailer::new(
+            config.mailgun.as_ref().ok_or_else(missing)?,
+        )),
+    })
+}
+
+/// Tries each of its mailers in order until one sends the email, so delivery doesn't depend on a
+/// single provider.
+#[derive(Clone, Debug)]
+pub struct FailoverMailer {
+    mailers: Vec<Box<dyn Mailer>>,
+}
+
+impl FailoverMailer {
+    pub fn new(mailers: Vec<Box<dyn Mailer>>) -> Self {
+        Self { mailers }
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for FailoverMailer {
+    async fn send(&self, mail: &Mail) -> Result<Option<String>> {
+        let mut mailers = self.mailers.iter().peekable();
+
+        while let Some(mailer) = mailers.next() {
+            match mailer.send(mail).await {
+                Ok(message_id) => return Ok(message_id),
+                Err(e) if mailers.peek().is_some() => {
+                    tracing::warn!("{mailer:?} failed to send email, trying the next mailer: {e}");
+                    MailMetrics::global().record_retry();
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        unreachable!("a failover mailer always has at least one mailer")
+    }
+}
+
+#[derive(Clone, derive_more::Debug)]
+pub struct SmtpMailer {
+    #[debug(skip)]
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &Config) -> Result<Self> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_relay)?
+            .credentials(Credentials::new(
+                config.smtp_username.to_string(),
+                config.smtp_password.to_string(),
+            ))
+            .build();
+
+        Ok(Self { transport })
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, mail: &Mail) -> Result<Option<String>> {
+        let response = self.transport.send(mail.to_message()?).await?;
+        Ok(queue_id(&response))
+    }
+}
+
+/// The relay's id for an accepted message, e.g. `4XYZ` from `250 2.0.0 Ok: queued as 4XYZ`.
+fn queue_id(response: &Response) -> Option<String> {
+    response
+        .message()
+        .find_map(|line| line.split_once("queued as "))
+        .map(|(_, id)| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+}
+
+/// A transport that stores outgoing email in the `mailbox` table instead of delivering it.
+#[derive(Clone, derive_more::Debug)]
+pub struct Mailbox {
+    #[debug(skip)]
+    database: Pool<Connection>,
+    clock: Box<dyn Clock>,
+}
+
+impl Mailbox {
+    pub fn new(database: Pool<Connection>) -> Self {
+        Self {
+            database,
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock(self, clock: Box<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for Mailbox {
+    async fn send(&self, mail: &Mail) -> Result<Option<String>> {
+        // Make sure the mail would actually be deliverable.
+        mail.to_message()?;
+
+        let mut conn = self.database.get().await?;
+        let record = MailboxMessage::create_record(
+            &mail.from,
+            &mail.to,
+            &mail.subject,
+            &mail.text,
+            self.clock.now(),
+        );
+        let record = match &mail.html {
+            Some(html) => record.with_html(html),
+            None => record,
+        };
+        let message = record.save(&mut conn).await?;
+
+        tracing::info!(
+            "captured email to {to} in the mailbox: {subject}",
+            to = mail.to,
+            subject = mail.subject
+        );
+
+        Ok(Some(message.id.to_string()))
+    }
+}
+
+/// Records every email sent through another mailer in the `sent_email` table, with whether it
+/// was sent, so support staff can look it up later with [`SentEmail`]'s queries. Email that
+/// fails to send is kept as a [`DeadLetter`].
+///
+/// Email to addresses that bounced or complained (see [`EmailSuppression`]) isn't sent at all.
+#[derive(Clone, derive_more::Debug)]
+pub struct TrackedMailer {
+    inner: Box<dyn Mailer>,
+    #[debug(skip)]
+    database: Pool<Connection>,
+    clock: Box<dyn Clock>,
+}
+
+impl TrackedMailer {
+    pub fn new(inner: Box<dyn Mailer>, database: Pool<Connection>) -> Self {
+        Self {
+            inner,
+            database,
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock(self, clock: Box<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for TrackedMailer {
+    async fn send(&self, mail: &Mail) -> Result<Option<String>> {
+        let recipient = mail.recipient()?;
+
+        let mut sent_email = {
+            let mut conn = self.database.get().await?;
+
+            if let Some(suppression) =
+                EmailSuppression::find_by_address(&recipient, &mut conn).await?
+            {
+                tracing::info!(
+                    "not sending email to {recipient}, the address is suppressed ({reason})",
+                    reason = suppression.reason
+                );
+                MailMetrics::global().record_suppressed();
+                SentEmail::suppressed(
+                    &recipient,
+                    mail.template.as_deref(),
+                    &mail.subject,
+                    suppression.reason.as_str(),
+                    self.clock.now(),
+                    &mut conn,
+                )
+                .await?;
+
+                return Ok(None);
+            }
+
+            SentEmail::queue(
+                &recipient,
+                mail.template.as_deref(),
+                &mail.subject,
+                self.clock.now(),
+                &mut conn,
+            )
+            .await?
+        };
+
+        // Don't hold a connection while the email is being sent, the transport may need one.
+        let start = MailMetrics::global().start_send();
+        let result = self.inner.send(mail).await;
+        MailMetrics::global().finish_send(start, result.is_ok());
+
+        // The transport has the final say on whether the email was sent. Failing to record that
+        // is only logged, returning an error would have the caller send it again.
+        let recorded = async {
+            let mut conn = self.database.get().await?;
+            match &result {
+                Ok(message_id) => {
+                    sent_email
+                        .mark_sent(message_id.as_deref(), self.clock.now(), &mut conn)
+                        .await?;
+                }
+                Err(e) => {
+                    let error = e.to_string();
+                    sent_email
+                        .mark_failed(&error, self.clock.now(), &mut conn)
+                        .await?;
+
+                    // Keep the email, so it can be retried from the admin.
+                    let payload = serde_json::to_string(mail).expect("an email serializes to JSON");
+                    DeadLetter::record(
+                        SEND_EMAIL_JOB,
+                        &payload,
+                        &error,
+                        self.clock.now(),
+                        &mut conn,
+                    )
+                    .await?;
+                }
+            }
+
+            Ok::<_, Error>(())
+        };
+        if let Err(e) = recorded.await {
+            tracing::error!("couldn't record the email to {recipient}: {e}");
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel_async::SimpleAsyncConnection as _;
+
+    use super::*;
+    use crate::mailer::template::RenderedMail;
+
+    /// Accepts every email, but drops the `sent_email` table first so recording it fails.
+    #[derive(Clone, derive_more::Debug)]
+    struct BreaksTracking(#[debug(skip)] Pool<Connection>);
+
+    #[async_trait::async_trait]
+    impl Mailer for BreaksTracking {
+        async fn send(&self, _mail: &Mail) -> Result<Option<String>> {
+            let mut conn = self.0.get().await?;
+            conn.batch_execute("DROP TABLE sent_email").await?;
+            Ok(Some("message-id".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn sent_email_is_ok_when_tracking_fails() {
+        let database = crate::test::database().await;
+        let mailer = TrackedMailer::new(Box::new(BreaksTracking(database.clone())), database);
+        let rendered = RenderedMail {
+            subject: "Hello".to_string(),
+            text: "Hello!".to_string(),
+            html: None,
+        };
+        let mail = Mail::new("app@example.com", "user@example.com", rendered);
+
+        let sent = mailer.send(&mail).await.unwrap();
+
+        assert_eq!(sent.as_deref(), Some("message-id"));
+    }
+}
@@ -0,0 +1,155 @@
+//! Parsing the bounce and complaint notifications mail providers send to
+//! `/webhooks/mail/:transport/:secret`.
+//!
+//! The notifications are recorded with [`crate::context::AppContext::record_delivery_failure`].
+
+use reqwest::Url;
+use serde_json::Value;
+
+use super::{Error, Result};
+use crate::model::SuppressionReason;
+
+/// An address a provider reported as undeliverable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeliveryFailure {
+    pub address: String,
+    pub reason: SuppressionReason,
+    /// The provider's id for the email that bounced or was complained about.
+    pub provider_message_id: Option<String>,
+    /// What the provider said about it, e.g. the bounce's diagnostic code.
+    pub detail: Option<String>,
+}
+
+/// A notification Amazon SNS delivered for SES.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SesNotification {
+    /// SNS asks for new subscriptions to be confirmed by visiting `subscribe_url`.
+    SubscriptionConfirmation { subscribe_url: String },
+    Failures(Vec<DeliveryFailure>),
+}
+
+/// Parse an SNS notification about SES bounces and complaints. Transient bounces are ignored.
+pub fn parse_ses(body: &[u8]) -> serde_json::Result<SesNotification> {
+    let notification: Value = serde_json::from_slice(body)?;
+
+    let message = match notification["Type"].as_str() {
+        Some("SubscriptionConfirmation") => {
+            return Ok(SesNotification::SubscriptionConfirmation {
+                subscribe_url: string(&notification["SubscribeURL"]).unwrap_or_default(),
+            })
+        }
+        Some("Notification") => {
+            serde_json::from_str(notification["Message"].as_str().unwrap_or("{}"))?
+        }
+        // Raw message delivery.
+        _ => notification,
+    };
+
+    let provider_message_id = string(&message["mail"]["messageId"]);
+    let failures = |recipients: &Value, reason: SuppressionReason, detail: &str| {
+        recipients
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|recipient| {
+                Some(DeliveryFailure {
+                    address: string(&recipient["emailAddress"])?,
+                    reason,
+                    provider_message_id: provider_message_id.clone(),
+                    detail: string(&recipient[detail]),
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let kind = message["notificationType"]
+        .as_str()
+        .or(message["eventType"].as_str());
+    let bounce = &message["bounce"];
+    let complaint = &message["complaint"];
+
+    Ok(SesNotification::Failures(match kind {
+        Some("Bounce") if bounce["bounceType"] == "Permanent" => failures(
+            &bounce["bouncedRecipients"],
+            SuppressionReason::Bounce,
+            "diagnosticCode",
+        ),
+        Some("Complaint") => failures(
+            &complaint["complainedRecipients"],
+            SuppressionReason::Complaint,
+            "complaintFeedbackType",
+        ),
+        _ => vec![],
+    }))
+}
+
+/// Confirm an SNS subscription, after checking `subscribe_url` really is Amazon's.
+pub async fn confirm_ses_subscription(subscribe_url: &str) -> Result<()> {
+    let url = Url::parse(subscribe_url).map_err(|_| Error::Webhook("invalid SubscribeURL"))?;
+    let trusted = url.scheme() == "https"
+        && url
+            .host_str()
+            .is_some_and(|host| host.ends_with(".amazonaws.com"));
+    if !trusted {
+        return Err(Error::Webhook("SubscribeURL isn't an Amazon SNS URL"));
+    }
+
+    reqwest::get(url).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Parse a batch of SendGrid events. Blocked bounces, which are usually temporary, are ignored.
+pub fn parse_sendgrid(body: &[u8]) -> serde_json::Result<Vec<DeliveryFailure>> {
+    let events: Vec<Value> = serde_json::from_slice(body)?;
+
+    Ok(events
+        .iter()
+        .filter_map(|event| {
+            let reason = match event["event"].as_str()? {
+                "bounce" if event["type"] != "blocked" => SuppressionReason::Bounce,
+                "spamreport" => SuppressionReason::Complaint,
+                _ => return None,
+            };
+
+            Some(DeliveryFailure {
+                address: string(&event["email"])?,
+                reason,
+                // The `X-Message-Id` SendGrid responded with, followed by `.filter...`.
+                provider_message_id: event["sg_message_id"]
+                    .as_str()
+                    .and_then(|id| id.split(".filter").next())
+                    .map(str::to_string),
+                detail: string(&event["reason"]),
+            })
+        })
+        .collect())
+}
+
+/// Parse a Mailgun webhook. Temporary failures are ignored.
+pub fn parse_mailgun(body: &[u8]) -> serde_json::Result<Vec<DeliveryFailure>> {
+    let webhook: Value = serde_json::from_slice(body)?;
+    let event = &webhook["event-data"];
+
+    let reason = match event["event"].as_str() {
+        Some("failed") if event["severity"] == "permanent" => SuppressionReason::Bounce,
+        Some("complained") => SuppressionReason::Complaint,
+        _ => return Ok(vec![]),
+    };
+    let Some(address) = string(&event["recipient"]) else {
+        return Ok(vec![]);
+    };
+    let status = &event["delivery-status"];
+
+    Ok(vec![DeliveryFailure {
+        address,
+        reason,
+        provider_message_id: string(&event["message"]["headers"]["message-id"]),
+        detail: string(&status["description"])
+            .filter(|description| !description.is_empty())
+            .or_else(|| string(&status["message"])),
+    }])
+}
+
+fn string(value: &Value) -> Option<String> {
+    value.as_str().map(|value| value.trim().to_string())
+}
@@ -0,0 +1,73 @@
+//! Processing of uploaded images before they're stored.
+//!
+//! Photos carry EXIF metadata, which can include where they were taken, so it's stripped by
+//! default. Stripping it also drops the orientation phones record photos with, so the image is
+//! rotated to match first.
+//!
+//! [`crate::attachment::Attachments`] runs [`process_image`] on uploaded images before storing
+//! them, with the [`ImageOptions`] given for each kind of upload.
+
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageDecoder, ImageReader};
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("unrecognized image format")]
+    UnknownFormat,
+}
+
+/// How an uploaded image is processed.
+#[derive(Clone, Copy, Debug)]
+pub struct ImageOptions {
+    /// Strip the image's metadata, rotating it to its EXIF orientation first. The image is
+    /// re-encoded to do so, otherwise it's stored as uploaded.
+    pub strip_metadata: bool,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            strip_metadata: true,
+        }
+    }
+}
+
+impl ImageOptions {
+    /// Store images as uploaded, e.g. for a photographer's originals.
+    pub fn original() -> Self {
+        Self {
+            strip_metadata: false,
+        }
+    }
+}
+
+/// Process the uploaded image `bytes` according to `options`, returning the image to store in the
+/// same format.
+pub fn process_image(bytes: &[u8], options: ImageOptions) -> Result<Vec<u8>> {
+    if !options.strip_metadata {
+        return Ok(bytes.to_vec());
+    }
+
+    let reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+    let format = reader.format().ok_or(Error::UnknownFormat)?;
+
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    // Encoders don't write the metadata back out.
+    let mut output = Cursor::new(Vec::new());
+    image.write_to(&mut output, format)?;
+
+    Ok(output.into_inner())
+}
@@ -0,0 +1,479 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::future::Future;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use diesel_async::pooled_connection::deadpool::{Object, Pool, PoolError};
+use tracing::debug;
+
+use crate::Connection;
+
+static POOL_METRICS: LazyLock<PoolMetrics> = LazyLock::new(PoolMetrics::default);
+static MAIL_METRICS: LazyLock<MailMetrics> = LazyLock::new(MailMetrics::default);
+static JOB_METRICS: LazyLock<JobMetrics> = LazyLock::new(JobMetrics::default);
+static EVENT_METRICS: LazyLock<EventMetrics> = LazyLock::new(EventMetrics::default);
+
+/// The upper bounds of the duration histograms' buckets, in seconds.
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+/// Connection pool checkout statistics, in addition to those deadpool tracks itself.
+#[derive(Debug, Default)]
+pub struct PoolMetrics {
+    checkouts: AtomicU64,
+    slow_checkouts: AtomicU64,
+    wait_micros_total: AtomicU64,
+    wait_micros_max: AtomicU64,
+    slow_checkout_micros: AtomicU64,
+}
+
+impl PoolMetrics {
+    pub fn global() -> &'static Self {
+        &POOL_METRICS
+    }
+
+    /// Checkouts waiting at least this long are counted as slow and logged.
+    pub fn set_slow_checkout_threshold(&self, threshold: Duration) {
+        self.slow_checkout_micros
+            .store(threshold.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn record(&self, waited: Duration, status: deadpool::Status) {
+        let micros = waited.as_micros() as u64;
+
+        self.checkouts.fetch_add(1, Ordering::Relaxed);
+        self.wait_micros_total.fetch_add(micros, Ordering::Relaxed);
+        self.wait_micros_max.fetch_max(micros, Ordering::Relaxed);
+
+        let threshold = self.slow_checkout_micros.load(Ordering::Relaxed);
+        if threshold > 0 && micros >= threshold {
+            self.slow_checkouts.fetch_add(1, Ordering::Relaxed);
+            debug!(
+                waited = ?waited,
+                size = status.size,
+                available = status.available,
+                waiting = status.waiting,
+                "slow database connection checkout, is something holding a connection too long?"
+            );
+        }
+    }
+}
+
+/// Check a connection out of the pool, recording how long it took.
+pub async fn checkout(pool: &Pool<Connection>) -> Result<Object<Connection>, PoolError> {
+    let start = Instant::now();
+    let conn = pool.get().await;
+    PoolMetrics::global().record(start.elapsed(), pool.status());
+
+    conn
+}
+
+/// A histogram of durations, with the [`DURATION_BUCKETS`].
+#[derive(Debug)]
+pub struct Histogram {
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: DURATION_BUCKETS.iter().map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Histogram {
+    pub fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        for (bucket, le) in self.buckets.iter().zip(DURATION_BUCKETS) {
+            if secs <= *le {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, output: &mut String, name: &str, labels: &[(&str, &str)]) {
+        for (bucket, le) in self.buckets.iter().zip(DURATION_BUCKETS) {
+            let le = le.to_string();
+            let labels = [labels, &[("le", le.as_str())]].concat();
+            let _ = writeln!(
+                output,
+                "{name}_bucket{} {}",
+                format_labels(&labels),
+                bucket.load(Ordering::Relaxed)
+            );
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let inf = [labels, &[("le", "+Inf")]].concat();
+        let _ = writeln!(output, "{name}_bucket{} {count}", format_labels(&inf));
+        let _ = writeln!(
+            output,
+            "{name}_sum{} {}",
+            format_labels(labels),
+            self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0
+        );
+        let _ = writeln!(output, "{name}_count{} {count}", format_labels(labels));
+    }
+}
+
+/// Statistics of the email sent through [`crate::mailer::TrackedMailer`].
+#[derive(Debug, Default)]
+pub struct MailMetrics {
+    sent: AtomicU64,
+    failed: AtomicU64,
+    suppressed: AtomicU64,
+    retries: AtomicU64,
+    sending: AtomicI64,
+    send_duration: Histogram,
+}
+
+impl MailMetrics {
+    pub fn global() -> &'static Self {
+        &MAIL_METRICS
+    }
+
+    /// Record that an email is being handed to the transport, returning when it was.
+    pub fn start_send(&self) -> Instant {
+        self.sending.fetch_add(1, Ordering::Relaxed);
+        Instant::now()
+    }
+
+    /// Record that the transport finished sending the email started at `start`.
+    pub fn finish_send(&self, start: Instant, sent: bool) {
+        self.sending.fetch_sub(1, Ordering::Relaxed);
+        self.send_duration.observe(start.elapsed());
+
+        if sent {
+            self.sent.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record an email that wasn't sent because its recipient is suppressed.
+    pub fn record_suppressed(&self) {
+        self.suppressed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record an email being retried with the next mailer of a [`crate::mailer::FailoverMailer`].
+    pub fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Statistics of a scheduled job.
+#[derive(Debug, Default)]
+struct JobStats {
+    runs: AtomicU64,
+    failures: AtomicU64,
+    running: AtomicI64,
+    duration: Histogram,
+}
+
+/// Statistics of the scheduled jobs, by name.
+#[derive(Debug, Default)]
+pub struct JobMetrics {
+    jobs: Mutex<BTreeMap<String, Arc<JobStats>>>,
+}
+
+impl JobMetrics {
+    pub fn global() -> &'static Self {
+        &JOB_METRICS
+    }
+
+    fn stats(&self, name: &str) -> Arc<JobStats> {
+        self.jobs
+            .lock()
+            .expect("job metrics lock should not be poisoned")
+            .entry(name.to_string())
+            .or_default()
+            .clone()
+    }
+}
+
+/// Run the scheduled job `name`, recording how long it took and whether it failed.
+pub async fn track_job<T, E>(
+    name: &str,
+    job: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let stats = JobMetrics::global().stats(name);
+    stats.running.fetch_add(1, Ordering::Relaxed);
+    let start = Instant::now();
+
+    let result = job.await;
+
+    stats.running.fetch_sub(1, Ordering::Relaxed);
+    stats.duration.observe(start.elapsed());
+    stats.runs.fetch_add(1, Ordering::Relaxed);
+    if result.is_err() {
+        stats.failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    result
+}
+
+/// Why lowboy closed an `/events` connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CloseReason {
+    /// The user opened more connections than they're allowed.
+    Cap,
+    /// The connection wasn't sent an event for too long.
+    Idle,
+}
+
+/// Statistics of the `/events` connections.
+#[derive(Debug, Default)]
+pub struct EventMetrics {
+    opened: AtomicU64,
+    open: AtomicI64,
+    closed_cap: AtomicU64,
+    closed_idle: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl EventMetrics {
+    pub fn global() -> &'static Self {
+        &EVENT_METRICS
+    }
+
+    pub fn open(&self) {
+        self.opened.fetch_add(1, Ordering::Relaxed);
+        self.open.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn close(&self) {
+        self.open.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Record an event that wasn't delivered to a subscriber, because its buffer was full.
+    pub fn record_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that lowboy closed a connection, rather than the client.
+    pub fn record_closed(&self, reason: CloseReason) {
+        match reason {
+            CloseReason::Cap => self.closed_cap.fetch_add(1, Ordering::Relaxed),
+            CloseReason::Idle => self.closed_idle.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+}
+
+/// Render `labels` as `{name="value",...}`, or nothing if there are none.
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+
+    let labels = labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{name}=\"{value}\"")
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+    format!("{{{labels}}}")
+}
+
+fn header(output: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(output, "# HELP {name} {help}");
+    let _ = writeln!(output, "# TYPE {name} {kind}");
+}
+
+/// Render the metrics in the Prometheus text exposition format.
+pub fn render(pool: &Pool<Connection>) -> String {
+    let status = pool.status();
+    let metrics = PoolMetrics::global();
+    let micros_to_secs = |micros: &AtomicU64| micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+
+    let mut output = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+        header(&mut output, name, kind, help);
+        let _ = writeln!(output, "{name} {value}");
+    };
+
+    metric(
+        "lowboy_db_pool_max_size",
+        "gauge",
+        "Maximum number of database connections.",
+        status.max_size.to_string(),
+    );
+    metric(
+        "lowboy_db_pool_size",
+        "gauge",
+        "Number of open database connections.",
+        status.size.to_string(),
+    );
+    metric(
+        "lowboy_db_pool_available",
+        "gauge",
+        "Number of idle database connections.",
+        status.available.to_string(),
+    );
+    metric(
+        "lowboy_db_pool_waiting",
+        "gauge",
+        "Number of tasks waiting for a database connection.",
+        status.waiting.to_string(),
+    );
+    metric(
+        "lowboy_db_pool_checkouts_total",
+        "counter",
+        "Number of database connection checkouts.",
+        metrics.checkouts.load(Ordering::Relaxed).to_string(),
+    );
+    metric(
+        "lowboy_db_pool_slow_checkouts_total",
+        "counter",
+        "Number of database connection checkouts exceeding the slow checkout threshold.",
+        metrics.slow_checkouts.load(Ordering::Relaxed).to_string(),
+    );
+    metric(
+        "lowboy_db_pool_checkout_wait_seconds_total",
+        "counter",
+        "Total time spent waiting for database connections.",
+        micros_to_secs(&metrics.wait_micros_total).to_string(),
+    );
+    metric(
+        "lowboy_db_pool_checkout_wait_seconds_max",
+        "gauge",
+        "Longest time spent waiting for a database connection.",
+        micros_to_secs(&metrics.wait_micros_max).to_string(),
+    );
+
+    let mail = MailMetrics::global();
+    metric(
+        "lowboy_mail_sent_total",
+        "counter",
+        "Number of emails sent.",
+        mail.sent.load(Ordering::Relaxed).to_string(),
+    );
+    metric(
+        "lowboy_mail_failed_total",
+        "counter",
+        "Number of emails that failed to send.",
+        mail.failed.load(Ordering::Relaxed).to_string(),
+    );
+    metric(
+        "lowboy_mail_suppressed_total",
+        "counter",
+        "Number of emails not sent because the recipient is suppressed.",
+        mail.suppressed.load(Ordering::Relaxed).to_string(),
+    );
+    metric(
+        "lowboy_mail_retries_total",
+        "counter",
+        "Number of emails retried with the next failover mailer.",
+        mail.retries.load(Ordering::Relaxed).to_string(),
+    );
+    metric(
+        "lowboy_mail_queue_depth",
+        "gauge",
+        "Number of emails being sent.",
+        mail.sending.load(Ordering::Relaxed).to_string(),
+    );
+    header(
+        &mut output,
+        "lowboy_mail_send_duration_seconds",
+        "histogram",
+        "Time taken to send an email.",
+    );
+    mail.send_duration
+        .render(&mut output, "lowboy_mail_send_duration_seconds", &[]);
+
+    let jobs = JobMetrics::global()
+        .jobs
+        .lock()
+        .expect("job metrics lock should not be poisoned")
+        .clone();
+    let mut job_metric = |name: &str, kind: &str, help: &str, value: fn(&JobStats) -> i64| {
+        header(&mut output, name, kind, help);
+        for (job, stats) in &jobs {
+            let _ = writeln!(output, "{name}{} {}", format_labels(&[("job", job)]), value(stats));
+        }
+    };
+    job_metric(
+        "lowboy_job_runs_total",
+        "counter",
+        "Number of scheduled job runs.",
+        |stats| stats.runs.load(Ordering::Relaxed) as i64,
+    );
+    job_metric(
+        "lowboy_job_failures_total",
+        "counter",
+        "Number of scheduled job runs that failed.",
+        |stats| stats.failures.load(Ordering::Relaxed) as i64,
+    );
+    job_metric(
+        "lowboy_job_running",
+        "gauge",
+        "Number of scheduled jobs running.",
+        |stats| stats.running.load(Ordering::Relaxed),
+    );
+    header(
+        &mut output,
+        "lowboy_job_duration_seconds",
+        "histogram",
+        "Time taken by scheduled job runs.",
+    );
+    for (job, stats) in &jobs {
+        stats
+            .duration
+            .render(&mut output, "lowboy_job_duration_seconds", &[("job", job)]);
+    }
+
+    let events = EventMetrics::global();
+    let mut event_metric = |name: &str, kind: &str, help: &str, value: i64| {
+        header(&mut output, name, kind, help);
+        let _ = writeln!(output, "{name} {value}");
+    };
+    event_metric(
+        "lowboy_events_connections",
+        "gauge",
+        "Number of open event stream connections.",
+        events.open.load(Ordering::Relaxed),
+    );
+    event_metric(
+        "lowboy_events_connections_total",
+        "counter",
+        "Number of event stream connections opened.",
+        events.opened.load(Ordering::Relaxed) as i64,
+    );
+    event_metric(
+        "lowboy_events_dropped_total",
+        "counter",
+        "Number of events not delivered to a subscriber because its buffer was full.",
+        events.dropped.load(Ordering::Relaxed) as i64,
+    );
+    header(
+        &mut output,
+        "lowboy_events_connections_closed_total",
+        "counter",
+        "Number of event stream connections closed by the server.",
+    );
+    for (reason, closed) in [("cap", &events.closed_cap), ("idle", &events.closed_idle)] {
+        let _ = writeln!(
+            output,
+            "lowboy_events_connections_closed_total{} {}",
+            format_labels(&[("reason", reason)]),
+            closed.load(Ordering::Relaxed)
+        );
+    }
+
+    output
+}
@@ -0,0 +1,313 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+
+use crate::model::Model;
+use crate::schema::{announcement, user_role};
+use crate::Connection;
+
+crate::db_enum! {
+    /// How prominently an announcement is shown.
+    #[derive(Default)]
+    pub enum AnnouncementLevel {
+        #[default]
+        Info = "info",
+        Success = "success",
+        Warning = "warning",
+        Error = "error",
+    }
+}
+
+/// A message shown at the top of every page while it's active, e.g. planned maintenance.
+#[derive(Clone, Debug, Serialize)]
+pub struct Announcement {
+    pub id: i32,
+    pub message: String,
+    pub level: AnnouncementLevel,
+    pub starts_at: DateTime<Utc>,
+    /// When the announcement stops being shown, if it does.
+    pub ends_at: Option<DateTime<Utc>>,
+    /// The role the announcement is shown to, or everyone if there isn't one.
+    pub role_id: Option<i32>,
+    /// When clients were told the announcement became active.
+    pub announced_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Announcement {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at <= now && self.ends_at.is_none_or(|ends_at| ends_at > now)
+    }
+
+    /// Every announcement, latest first.
+    pub async fn list(conn: &mut Connection, limit: Option<i64>) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .limit(limit.unwrap_or(100))
+            .order_by(announcement::starts_at.desc())
+            .load(conn)
+            .await
+    }
+
+    /// The active announcements shown to the user `user_id`, i.e. those for everyone and for the
+    /// roles they're assigned, latest first. Visitors who aren't logged in only see announcements
+    /// for everyone.
+    pub async fn active_for(
+        user_id: Option<i32>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<Self>> {
+        let query = Self::query()
+            .filter(announcement::starts_at.le(now))
+            .filter(
+                announcement::ends_at
+                    .is_null()
+                    .or(announcement::ends_at.gt(now)),
+            )
+            .order_by(announcement::starts_at.desc());
+
+        match user_id {
+            Some(user_id) => {
+                let roles = user_role::table
+                    .filter(user_role::user_id.eq(user_id))
+                    .select(user_role::role_id.nullable());
+
+                query
+                    .filter(
+                        announcement::role_id
+                            .is_null()
+                            .or(announcement::role_id.eq_any(roles)),
+                    )
+                    .load(conn)
+                    .await
+            }
+            None => {
+                query
+                    .filter(announcement::role_id.is_null())
+                    .load(conn)
+                    .await
+            }
+        }
+    }
+
+    /// The active announcements clients haven't been told about yet.
+    pub async fn unannounced(now: DateTime<Utc>, conn: &mut Connection) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .filter(announcement::announced_at.is_null())
+            .filter(announcement::starts_at.le(now))
+            .filter(
+                announcement::ends_at
+                    .is_null()
+                    .or(announcement::ends_at.gt(now)),
+            )
+            .load(conn)
+            .await
+    }
+
+    /// Record that clients were told about the announcements `ids`.
+    pub async fn mark_announced(
+        ids: &[i32],
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<usize> {
+        diesel::update(announcement::table.filter(announcement::id.eq_any(ids)))
+            .set(announcement::announced_at.eq(now))
+            .execute(conn)
+            .await
+    }
+
+    /// Stop showing the announcement from `now`.
+    pub async fn end(&mut self, now: DateTime<Utc>, conn: &mut Connection) -> QueryResult<()> {
+        diesel::update(announcement::table.find(self.id))
+            .set(announcement::ends_at.eq(now))
+            .execute(conn)
+            .await?;
+
+        self.ends_at = Some(now);
+
+        Ok(())
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn announcement_from_clause() -> _ {
+    announcement::table
+}
+
+#[diesel::dsl::auto_type]
+fn announcement_select_clause() -> _ {
+    let as_select: AsSelect<AnnouncementRecord, Sqlite> = AnnouncementRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for Announcement {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = announcement_select_clause;
+    type FromClause = announcement_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "announcement";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        announcement_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        announcement_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query()
+            .filter(announcement::id.eq(id))
+            .first(conn)
+            .await
+    }
+}
+
+impl Selectable<Sqlite> for Announcement {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<Announcement as Model>::RowSqlType, Sqlite> for Announcement {
+    type Row = (AnnouncementRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<AnnouncementRecord> for Announcement {
+    fn from(value: AnnouncementRecord) -> Self {
+        Self {
+            id: value.id,
+            message: value.message,
+            level: value.level,
+            starts_at: value.starts_at,
+            ends_at: value.ends_at,
+            role_id: value.role_id,
+            announced_at: value.announced_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::announcement)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct AnnouncementRecord {
+    pub id: i32,
+    pub message: String,
+    pub level: AnnouncementLevel,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub role_id: Option<i32>,
+    pub announced_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AnnouncementRecord {
+    pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<AnnouncementRecord> {
+        announcement::table.find(id).get_result(conn).await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(announcement::table.find(self.id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `Announcement` model into `AnnouncementRecord`
+impl From<Announcement> for AnnouncementRecord {
+    fn from(value: Announcement) -> Self {
+        Self {
+            id: value.id,
+            message: value.message,
+            level: value.level,
+            starts_at: value.starts_at,
+            ends_at: value.ends_at,
+            role_id: value.role_id,
+            announced_at: value.announced_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::announcement)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CreateAnnouncementRecord<'a> {
+    pub message: &'a str,
+    pub level: AnnouncementLevel,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub role_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<'a> CreateAnnouncementRecord<'a> {
+    /// Create a new `CreateAnnouncementRecord` object
+    pub fn new(
+        message: &'a str,
+        level: AnnouncementLevel,
+        starts_at: DateTime<Utc>,
+        created_at: DateTime<Utc>,
+    ) -> CreateAnnouncementRecord<'a> {
+        Self {
+            message,
+            level,
+            starts_at,
+            ends_at: None,
+            role_id: None,
+            created_at,
+        }
+    }
+
+    pub fn with_ends_at(self, ends_at: Option<DateTime<Utc>>) -> Self {
+        Self { ends_at, ..self }
+    }
+
+    pub fn with_role_id(self, role_id: Option<i32>) -> Self {
+        Self { role_id, ..self }
+    }
+
+    /// Create a new `announcement` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<AnnouncementRecord> {
+        diesel::insert_into(crate::schema::announcement::table)
+            .values(self)
+            .returning(crate::schema::announcement::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+}
+
+impl Announcement {
+    pub fn create_record<'a>(
+        message: &'a str,
+        level: AnnouncementLevel,
+        starts_at: DateTime<Utc>,
+        created_at: DateTime<Utc>,
+    ) -> CreateAnnouncementRecord<'a> {
+        CreateAnnouncementRecord::new(message, level, starts_at, created_at)
+    }
+
+    pub async fn read_record(id: i32, conn: &mut Connection) -> QueryResult<AnnouncementRecord> {
+        AnnouncementRecord::read(id, conn).await
+    }
+
+    pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+        AnnouncementRecord::from(self).delete(conn).await
+    }
+}
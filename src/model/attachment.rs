@@ -0,0 +1,250 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+
+use crate::model::Model;
+use crate::scan::{ScanState, Scanned};
+use crate::schema::attachment;
+use crate::Connection;
+
+/// A file a user uploaded, stored by [`crate::attachment::Attachments`].
+#[derive(Clone, Debug, Serialize)]
+pub struct Attachment {
+    pub id: i32,
+    /// The user who uploaded the file, if it was uploaded by one.
+    pub user_id: Option<i32>,
+    /// The file's name, as uploaded.
+    pub file_name: String,
+    pub content_type: String,
+    /// Where the file is stored, in the quarantine directory if it was flagged by the scanner.
+    #[serde(skip)]
+    pub path: String,
+    /// The file's size in bytes.
+    pub size: i64,
+    pub scan_state: ScanState,
+    /// What the scanner flagged the file for.
+    pub scan_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Attachment {
+    /// The attachments uploaded by the user `user_id`, latest first.
+    pub async fn list_for_user(user_id: i32, conn: &mut Connection) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .filter(attachment::user_id.eq(user_id))
+            .order_by(attachment::id.desc())
+            .load(conn)
+            .await
+    }
+
+    /// Record what the scanner found in the attachment, and where the file is now.
+    pub async fn set_scanned(
+        &mut self,
+        scanned: &Scanned,
+        conn: &mut Connection,
+    ) -> QueryResult<()> {
+        let path = scanned.path.to_string_lossy().into_owned();
+
+        diesel::update(attachment::table.find(self.id))
+            .set((
+                attachment::scan_state.eq(scanned.state),
+                attachment::scan_reason.eq(scanned.reason.as_deref()),
+                attachment::path.eq(&path),
+            ))
+            .execute(conn)
+            .await?;
+
+        self.scan_state = scanned.state;
+        self.scan_reason = scanned.reason.clone();
+        self.path = path;
+
+        Ok(())
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn attachment_from_clause() -> _ {
+    attachment::table
+}
+
+#[diesel::dsl::auto_type]
+fn attachment_select_clause() -> _ {
+    let as_select: AsSelect<AttachmentRecord, Sqlite> = AttachmentRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for Attachment {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = attachment_select_clause;
+    type FromClause = attachment_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "attachment";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        attachment_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        attachment_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query()
+            .filter(attachment::id.eq(id))
+            .first(conn)
+            .await
+    }
+}
+
+impl Selectable<Sqlite> for Attachment {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<Attachment as Model>::RowSqlType, Sqlite> for Attachment {
+    type Row = (AttachmentRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<AttachmentRecord> for Attachment {
+    fn from(value: AttachmentRecord) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            file_name: value.file_name,
+            content_type: value.content_type,
+            path: value.path,
+            size: value.size,
+            scan_state: value.scan_state,
+            scan_reason: value.scan_reason,
+            created_at: value.created_at,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::attachment)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct AttachmentRecord {
+    pub id: i32,
+    pub user_id: Option<i32>,
+    pub file_name: String,
+    pub content_type: String,
+    pub path: String,
+    pub size: i64,
+    pub scan_state: ScanState,
+    pub scan_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AttachmentRecord {
+    pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<AttachmentRecord> {
+        attachment::table.find(id).get_result(conn).await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(attachment::table.find(self.id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `Attachment` model into `AttachmentRecord`
+impl From<Attachment> for AttachmentRecord {
+    fn from(value: Attachment) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            file_name: value.file_name,
+            content_type: value.content_type,
+            path: value.path,
+            size: value.size,
+            scan_state: value.scan_state,
+            scan_reason: value.scan_reason,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::attachment)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CreateAttachmentRecord<'a> {
+    pub user_id: Option<i32>,
+    pub file_name: &'a str,
+    pub content_type: &'a str,
+    pub path: &'a str,
+    pub size: i64,
+    pub scan_state: ScanState,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<'a> CreateAttachmentRecord<'a> {
+    /// Create a new `CreateAttachmentRecord` object, waiting to be scanned.
+    pub fn new(
+        file_name: &'a str,
+        content_type: &'a str,
+        path: &'a str,
+        size: i64,
+        created_at: DateTime<Utc>,
+    ) -> CreateAttachmentRecord<'a> {
+        Self {
+            user_id: None,
+            file_name,
+            content_type,
+            path,
+            size,
+            scan_state: ScanState::Pending,
+            created_at,
+        }
+    }
+
+    pub fn with_user_id(self, user_id: Option<i32>) -> Self {
+        Self { user_id, ..self }
+    }
+
+    /// Create a new `attachment` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<AttachmentRecord> {
+        diesel::insert_into(crate::schema::attachment::table)
+            .values(self)
+            .returning(crate::schema::attachment::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+}
+
+impl Attachment {
+    pub fn create_record<'a>(
+        file_name: &'a str,
+        content_type: &'a str,
+        path: &'a str,
+        size: i64,
+        created_at: DateTime<Utc>,
+    ) -> CreateAttachmentRecord<'a> {
+        CreateAttachmentRecord::new(file_name, content_type, path, size, created_at)
+    }
+
+    pub async fn read_record(id: i32, conn: &mut Connection) -> QueryResult<AttachmentRecord> {
+        AttachmentRecord::read(id, conn).await
+    }
+
+    pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+        AttachmentRecord::from(self).delete(conn).await
+    }
+}
@@ -0,0 +1,241 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel_async::RunQueryDsl;
+
+use crate::actor::RequestActor;
+use crate::model::Model;
+use crate::schema::audit_log;
+use crate::Connection;
+
+/// A write made by a [`RequestActor`], e.g. an administrator creating a role.
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    pub id: i32,
+    pub request_id: String,
+    /// The user who actually made the request, e.g. an administrator impersonating someone.
+    pub real_user_id: Option<i32>,
+    /// The user the request was made as.
+    pub effective_user_id: Option<i32>,
+    pub action: String,
+    pub subject: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditLog {
+    /// Record that `actor` performed `action`, optionally on `subject` (e.g. the name of a role).
+    pub async fn record(
+        actor: &RequestActor,
+        action: &str,
+        subject: Option<&str>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Self> {
+        let mut record = Self::create_record(&actor.request_id, action, now);
+        record.real_user_id = actor.real_user_id;
+        record.effective_user_id = actor.effective_user_id;
+        if let Some(subject) = subject {
+            record = record.with_subject(subject);
+        }
+
+        Ok(record.save(conn).await?.into())
+    }
+
+    /// Entries made by or as `user_id`, most recent first.
+    pub async fn list_for_user(user_id: i32, conn: &mut Connection) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .filter(
+                audit_log::real_user_id
+                    .eq(user_id)
+                    .or(audit_log::effective_user_id.eq(user_id)),
+            )
+            .order_by(audit_log::id.desc())
+            .load(conn)
+            .await
+    }
+
+    /// Entries made while handling the request `request_id`.
+    pub async fn list_for_request(
+        request_id: &str,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .filter(audit_log::request_id.eq(request_id))
+            .order_by(audit_log::id.asc())
+            .load(conn)
+            .await
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn audit_log_from_clause() -> _ {
+    audit_log::table
+}
+
+#[diesel::dsl::auto_type]
+fn audit_log_select_clause() -> _ {
+    let as_select: AsSelect<AuditLogRecord, Sqlite> = AuditLogRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for AuditLog {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = audit_log_select_clause;
+    type FromClause = audit_log_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "audit_log";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        audit_log_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        audit_log_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query().filter(audit_log::id.eq(id)).first(conn).await
+    }
+}
+
+impl Selectable<Sqlite> for AuditLog {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<AuditLog as Model>::RowSqlType, Sqlite> for AuditLog {
+    type Row = (AuditLogRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<AuditLogRecord> for AuditLog {
+    fn from(value: AuditLogRecord) -> Self {
+        Self {
+            id: value.id,
+            request_id: value.request_id,
+            real_user_id: value.real_user_id,
+            effective_user_id: value.effective_user_id,
+            action: value.action,
+            subject: value.subject,
+            created_at: value.created_at,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::audit_log)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct AuditLogRecord {
+    pub id: i32,
+    pub request_id: String,
+    pub real_user_id: Option<i32>,
+    pub effective_user_id: Option<i32>,
+    pub action: String,
+    pub subject: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditLogRecord {
+    pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<AuditLogRecord> {
+        audit_log::table.find(id).get_result(conn).await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(audit_log::table.find(self.id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `AuditLog` model into `AuditLogRecord`
+impl From<AuditLog> for AuditLogRecord {
+    fn from(value: AuditLog) -> Self {
+        Self {
+            id: value.id,
+            request_id: value.request_id,
+            real_user_id: value.real_user_id,
+            effective_user_id: value.effective_user_id,
+            action: value.action,
+            subject: value.subject,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::audit_log)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CreateAuditLogRecord<'a> {
+    pub request_id: &'a str,
+    pub real_user_id: Option<i32>,
+    pub effective_user_id: Option<i32>,
+    pub action: &'a str,
+    pub subject: Option<&'a str>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<'a> CreateAuditLogRecord<'a> {
+    /// Create a new `CreateAuditLogRecord` object
+    pub fn new(
+        request_id: &'a str,
+        action: &'a str,
+        created_at: DateTime<Utc>,
+    ) -> CreateAuditLogRecord<'a> {
+        Self {
+            request_id,
+            real_user_id: None,
+            effective_user_id: None,
+            action,
+            subject: None,
+            created_at,
+        }
+    }
+
+    pub fn with_subject(self, subject: &'a str) -> CreateAuditLogRecord<'a> {
+        Self {
+            subject: Some(subject),
+            ..self
+        }
+    }
+
+    /// Create a new `audit_log` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<AuditLogRecord> {
+        diesel::insert_into(crate::schema::audit_log::table)
+            .values(self)
+            .returning(crate::schema::audit_log::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+}
+
+impl AuditLog {
+    pub fn create_record<'a>(
+        request_id: &'a str,
+        action: &'a str,
+        created_at: DateTime<Utc>,
+    ) -> CreateAuditLogRecord<'a> {
+        CreateAuditLogRecord::new(request_id, action, created_at)
+    }
+
+    pub async fn read_record(id: i32, conn: &mut Connection) -> QueryResult<AuditLogRecord> {
+        AuditLogRecord::read(id, conn).await
+    }
+
+    pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+        AuditLogRecord::from(self).delete(conn).await
+    }
+}
@@ -1,4 +1,3 @@
-use oauth2::CsrfToken;
 use serde::Deserialize;
 
 use crate::auth::IdentityProvider;
@@ -6,6 +5,7 @@ use crate::auth::IdentityProvider;
 #[derive(Debug, Clone, Deserialize)]
 pub enum CredentialKind {
     Password,
+    ApiToken,
     #[serde(untagged)]
     OAuth(IdentityProvider),
 }
@@ -17,6 +17,8 @@ pub struct Credentials {
     pub password: Option<PasswordCredentials>,
     #[serde(flatten)]
     pub oauth: Option<OAuthCredentials>,
+    #[serde(flatten)]
+    pub api_token: Option<ApiTokenCredentials>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -25,9 +27,17 @@ pub struct PasswordCredentials {
     pub password: String,
 }
 
+/// The API token of a service account, see [`crate::model::User::new_service_account`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiTokenCredentials {
+    pub token: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct OAuthCredentials {
     pub code: String,
-    pub old_state: CsrfToken,
-    pub new_state: CsrfToken,
+    /// The CSRF state stored in the session when the user was sent to the provider.
+    pub old_state: String,
+    /// The CSRF state the provider redirected back with.
+    pub new_state: String,
 }
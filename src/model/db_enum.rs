@@ -0,0 +1,137 @@
+/// A value stored in a [`db_enum!`] column which isn't one of the enum's variants.
+#[derive(Debug, thiserror::Error)]
+#[error("`{value}` isn't a valid {enum_name}")]
+pub struct UnknownVariant {
+    pub enum_name: &'static str,
+    pub value: String,
+}
+
+/// Define an enum stored as text, since SQLite doesn't have enums.
+///
+/// Each variant is given the value it's stored as. The enum can be used in diesel queries and
+/// models as `Text`, is (de)serialized by serde as its value, and converts to and from its value
+/// with [`Display`](std::fmt::Display) and [`FromStr`](std::str::FromStr). Values that aren't a
+/// variant are rejected with [`UnknownVariant`], whether they're loaded or parsed.
+///
+/// The generated code uses `diesel` and `serde`, which need to be dependencies of the crate using
+/// the macro.
+///
+/// ```ignore
+/// lowboy::db_enum! {
+///     /// How a post is shown.
+///     #[derive(Default)]
+///     pub enum Visibility {
+///         #[default]
+///         Public = "public",
+///         Unlisted = "unlisted",
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! db_enum {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$variant_attr:meta])*
+                $variant:ident = $value:literal
+            ),+ $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(
+            Clone,
+            Copy,
+            Debug,
+            PartialEq,
+            Eq,
+            Hash,
+            ::diesel::AsExpression,
+            ::diesel::FromSqlRow,
+        )]
+        #[diesel(sql_type = ::diesel::sql_types::Text)]
+        $vis enum $name {
+            $(
+                $(#[$variant_attr])*
+                $variant,
+            )+
+        }
+
+        impl $name {
+            /// Every variant, in the order they're declared.
+            pub const ALL: &'static [Self] = &[$(Self::$variant),+];
+
+            /// The value the variant is stored as.
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $value,)+
+                }
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl ::std::str::FromStr for $name {
+            type Err = $crate::model::UnknownVariant;
+
+            fn from_str(value: &str) -> ::std::result::Result<Self, Self::Err> {
+                match value {
+                    $($value => Ok(Self::$variant),)+
+                    _ => Err($crate::model::UnknownVariant {
+                        enum_name: stringify!($name),
+                        value: value.to_string(),
+                    }),
+                }
+            }
+        }
+
+        impl ::diesel::serialize::ToSql<::diesel::sql_types::Text, ::diesel::sqlite::Sqlite>
+            for $name
+        {
+            fn to_sql<'b>(
+                &'b self,
+                out: &mut ::diesel::serialize::Output<'b, '_, ::diesel::sqlite::Sqlite>,
+            ) -> ::diesel::serialize::Result {
+                out.set_value(self.as_str());
+                Ok(::diesel::serialize::IsNull::No)
+            }
+        }
+
+        impl ::diesel::deserialize::FromSql<::diesel::sql_types::Text, ::diesel::sqlite::Sqlite>
+            for $name
+        {
+            fn from_sql(
+                value: ::diesel::sqlite::SqliteValue<'_, '_, '_>,
+            ) -> ::diesel::deserialize::Result<Self> {
+                let value = <String as ::diesel::deserialize::FromSql<
+                    ::diesel::sql_types::Text,
+                    ::diesel::sqlite::Sqlite,
+                >>::from_sql(value)?;
+
+                Ok(value.parse()?)
+            }
+        }
+
+        impl ::serde::Serialize for $name {
+            fn serialize<S: ::serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> ::std::result::Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> ::serde::Deserialize<'de> for $name {
+            fn deserialize<D: ::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> ::std::result::Result<Self, D::Error> {
+                let value = <String as ::serde::Deserialize>::deserialize(deserializer)?;
+                value.parse().map_err(::serde::de::Error::custom)
+            }
+        }
+    };
+}
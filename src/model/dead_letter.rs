@@ -0,0 +1,233 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+
+use crate::model::Model;
+use crate::schema::dead_letter;
+use crate::Connection;
+
+/// Background work that failed for good, kept so it doesn't silently vanish.
+///
+/// Dead letters are listed in the admin, where they can be retried with
+/// [`crate::context::AppContext::retry_dead_letter`] or purged.
+#[derive(Clone, Debug, Serialize)]
+pub struct DeadLetter {
+    pub id: i32,
+    /// What the work was, e.g. `send_email`.
+    pub job: String,
+    /// The JSON needed to retry the work, e.g. the email that wasn't sent.
+    pub payload: String,
+    /// Why the work failed, the last time it was tried.
+    pub error: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl DeadLetter {
+    /// Record that `job` failed with `error`.
+    pub async fn record(
+        job: &str,
+        payload: &str,
+        error: &str,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Self> {
+        Ok(Self::create_record(job, payload, error, now)
+            .save(conn)
+            .await?
+            .into())
+    }
+
+    /// Dead letters, most recent first.
+    pub async fn list(conn: &mut Connection, limit: Option<i64>) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .limit(limit.unwrap_or(100))
+            .order_by(dead_letter::id.desc())
+            .load(conn)
+            .await
+    }
+
+    /// Delete every dead letter, returning how many there were.
+    pub async fn purge_all(conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(dead_letter::table).execute(conn).await
+    }
+
+    /// Whether the same work failed again since, and was recorded as a newer dead letter.
+    pub async fn superseded(&self, conn: &mut Connection) -> QueryResult<bool> {
+        diesel::select(diesel::dsl::exists(
+            dead_letter::table
+                .filter(dead_letter::job.eq(&self.job))
+                .filter(dead_letter::payload.eq(&self.payload))
+                .filter(dead_letter::id.gt(self.id)),
+        ))
+        .get_result(conn)
+        .await
+    }
+
+    /// The payload, deserialized.
+    pub fn payload<T: serde::de::DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_str(&self.payload)
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn dead_letter_from_clause() -> _ {
+    dead_letter::table
+}
+
+#[diesel::dsl::auto_type]
+fn dead_letter_select_clause() -> _ {
+    let as_select: AsSelect<DeadLetterRecord, Sqlite> = DeadLetterRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for DeadLetter {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = dead_letter_select_clause;
+    type FromClause = dead_letter_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "dead_letter";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        dead_letter_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        dead_letter_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query()
+            .filter(dead_letter::id.eq(id))
+            .first(conn)
+            .await
+    }
+}
+
+impl Selectable<Sqlite> for DeadLetter {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<DeadLetter as Model>::RowSqlType, Sqlite> for DeadLetter {
+    type Row = (DeadLetterRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<DeadLetterRecord> for DeadLetter {
+    fn from(value: DeadLetterRecord) -> Self {
+        Self {
+            id: value.id,
+            job: value.job,
+            payload: value.payload,
+            error: value.error,
+            created_at: value.created_at,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::dead_letter)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct DeadLetterRecord {
+    pub id: i32,
+    pub job: String,
+    pub payload: String,
+    pub error: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl DeadLetterRecord {
+    pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<DeadLetterRecord> {
+        dead_letter::table.find(id).get_result(conn).await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(dead_letter::table.find(self.id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `DeadLetter` model into `DeadLetterRecord`
+impl From<DeadLetter> for DeadLetterRecord {
+    fn from(value: DeadLetter) -> Self {
+        Self {
+            id: value.id,
+            job: value.job,
+            payload: value.payload,
+            error: value.error,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::dead_letter)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CreateDeadLetterRecord<'a> {
+    pub job: &'a str,
+    pub payload: &'a str,
+    pub error: &'a str,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<'a> CreateDeadLetterRecord<'a> {
+    /// Create a new `CreateDeadLetterRecord` object
+    pub fn new(
+        job: &'a str,
+        payload: &'a str,
+        error: &'a str,
+        created_at: DateTime<Utc>,
+    ) -> CreateDeadLetterRecord<'a> {
+        Self {
+            job,
+            payload,
+            error,
+            created_at,
+        }
+    }
+
+    /// Create a new `dead_letter` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<DeadLetterRecord> {
+        diesel::insert_into(crate::schema::dead_letter::table)
+            .values(self)
+            .returning(crate::schema::dead_letter::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+}
+
+impl DeadLetter {
+    pub fn create_record<'a>(
+        job: &'a str,
+        payload: &'a str,
+        error: &'a str,
+        created_at: DateTime<Utc>,
+    ) -> CreateDeadLetterRecord<'a> {
+        CreateDeadLetterRecord::new(job, payload, error, created_at)
+    }
+
+    pub async fn read_record(id: i32, conn: &mut Connection) -> QueryResult<DeadLetterRecord> {
+        DeadLetterRecord::read(id, conn).await
+    }
+
+    pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+        DeadLetterRecord::from(self).delete(conn).await
+    }
+}
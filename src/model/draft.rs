@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel::upsert::excluded;
+use diesel_async::RunQueryDsl;
+use serde::de::DeserializeOwned;
+
+use crate::model::Model;
+use crate::schema::draft;
+use crate::Connection;
+
+/// How long a draft is kept after it was last saved.
+pub const DRAFT_LIFETIME: TimeDelta = TimeDelta::days(30);
+
+/// The unsubmitted content of a form, autosaved so it survives the user navigating away.
+///
+/// Drafts are keyed by the user and a name for the form, e.g. `post`. The content is the form's
+/// fields as a JSON object.
+#[derive(Clone, Debug)]
+pub struct Draft {
+    pub id: i32,
+    pub user_id: i32,
+    pub form: String,
+    pub content: String,
+    pub updated_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Draft {
+    /// Save `fields` as `user_id`'s draft of `form`, replacing any previous draft.
+    pub async fn save(
+        user_id: i32,
+        form: &str,
+        fields: &HashMap<String, String>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Self> {
+        let content = serde_json::to_string(fields)
+            .map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))?;
+        let record = Self::create_record(user_id, form, &content, now);
+
+        Ok(diesel::insert_into(draft::table)
+            .values(&record)
+            .on_conflict((draft::user_id, draft::form))
+            .do_update()
+            .set((
+                draft::content.eq(excluded(draft::content)),
+                draft::updated_at.eq(excluded(draft::updated_at)),
+                draft::expires_at.eq(excluded(draft::expires_at)),
+            ))
+            .returning(DraftRecord::as_returning())
+            .get_result(conn)
+            .await?
+            .into())
+    }
+
+    /// `user_id`'s unexpired draft of `form`.
+    pub async fn find(
+        user_id: i32,
+        form: &str,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Option<Self>> {
+        Self::query()
+            .filter(draft::user_id.eq(user_id))
+            .filter(draft::form.eq(form))
+            .filter(draft::expires_at.gt(now))
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// Restore `user_id`'s draft of `form` into the form's type, e.g. to pre-fill it on load.
+    ///
+    /// Drafts that no longer fit the form (e.g. because it has changed) are ignored.
+    pub async fn restore<T: DeserializeOwned>(
+        user_id: i32,
+        form: &str,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Option<T>> {
+        Ok(Self::find(user_id, form, now, conn)
+            .await?
+            .and_then(|draft| serde_json::from_str(&draft.content).ok()))
+    }
+
+    /// Delete `user_id`'s draft of `form`, e.g. once the form has been submitted.
+    pub async fn discard(user_id: i32, form: &str, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(
+            draft::table
+                .filter(draft::user_id.eq(user_id))
+                .filter(draft::form.eq(form)),
+        )
+        .execute(conn)
+        .await
+    }
+
+    pub async fn delete_expired(now: DateTime<Utc>, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(draft::table.filter(draft::expires_at.le(now)))
+            .execute(conn)
+            .await
+    }
+
+    /// The draft's fields.
+    pub fn fields(&self) -> HashMap<String, String> {
+        serde_json::from_str(&self.content).unwrap_or_default()
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn draft_from_clause() -> _ {
+    draft::table
+}
+
+#[diesel::dsl::auto_type]
+fn draft_select_clause() -> _ {
+    let as_select: AsSelect<DraftRecord, Sqlite> = DraftRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for Draft {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = draft_select_clause;
+    type FromClause = draft_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "draft";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        draft_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        draft_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query().filter(draft::id.eq(id)).first(conn).await
+    }
+}
+
+impl Selectable<Sqlite> for Draft {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<Draft as Model>::RowSqlType, Sqlite> for Draft {
+    type Row = (DraftRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<DraftRecord> for Draft {
+    fn from(value: DraftRecord) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            form: value.form,
+            content: value.content,
+            updated_at: value.updated_at,
+            expires_at: value.expires_at,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::draft)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct DraftRecord {
+    pub id: i32,
+    pub user_id: i32,
+    pub form: String,
+    pub content: String,
+    pub updated_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl DraftRecord {
+    pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<DraftRecord> {
+        draft::table.find(id).get_result(conn).await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(draft::table.find(self.id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `Draft` model into `DraftRecord`
+impl From<Draft> for DraftRecord {
+    fn from(value: Draft) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            form: value.form,
+            content: value.content,
+            updated_at: value.updated_at,
+            expires_at: value.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::draft)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CreateDraftRecord<'a> {
+    pub user_id: i32,
+    pub form: &'a str,
+    pub content: &'a str,
+    pub updated_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl<'a> CreateDraftRecord<'a> {
+    /// Create a new `CreateDraftRecord` object, expiring [`DRAFT_LIFETIME`] after `updated_at`.
+    pub fn new(
+        user_id: i32,
+        form: &'a str,
+        content: &'a str,
+        updated_at: DateTime<Utc>,
+    ) -> CreateDraftRecord<'a> {
+        Self {
+            user_id,
+            form,
+            content,
+            updated_at,
+            expires_at: updated_at + DRAFT_LIFETIME,
+        }
+    }
+
+    pub fn with_expires_at(self, expires_at: DateTime<Utc>) -> CreateDraftRecord<'a> {
+        Self { expires_at, ..self }
+    }
+
+    /// Create a new `draft` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<DraftRecord> {
+        diesel::insert_into(crate::schema::draft::table)
+            .values(self)
+            .returning(crate::schema::draft::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+}
+
+impl Draft {
+    pub fn create_record<'a>(
+        user_id: i32,
+        form: &'a str,
+        content: &'a str,
+        updated_at: DateTime<Utc>,
+    ) -> CreateDraftRecord<'a> {
+        CreateDraftRecord::new(user_id, form, content, updated_at)
+    }
+
+    pub async fn read_record(id: i32, conn: &mut Connection) -> QueryResult<DraftRecord> {
+        DraftRecord::read(id, conn).await
+    }
+
+    pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+        DraftRecord::from(self).delete(conn).await
+    }
+}
@@ -5,7 +5,8 @@ use diesel::sqlite::Sqlite;
 use diesel::{OptionalExtension, QueryResult, Selectable};
 use diesel_async::RunQueryDsl;
 
-use crate::model::{Model, UserRecord};
+use crate::form::UniqueConstraint;
+use crate::model::{find_by, Model, UserRecord};
 use crate::schema::email;
 use crate::Connection;
 
@@ -20,6 +21,8 @@ pub struct Email {
     pub verified: bool,
 }
 
+find_by!(Email, email::address: &str);
+
 impl Email {
     pub async fn find_by_user_id(user_id: i32, conn: &mut Connection) -> QueryResult<Option<Self>> {
         Self::query()
@@ -29,17 +32,6 @@ impl Email {
             .optional()
     }
 
-    pub async fn find_by_address(
-        address: &str,
-        conn: &mut Connection,
-    ) -> QueryResult<Option<Self>> {
-        Self::query()
-            .filter(email::address.eq(address))
-            .first(conn)
-            .await
-            .optional()
-    }
-
     pub async fn find_by_address_having_verification(
         address: &str,
         verified: bool,
@@ -73,6 +65,13 @@ impl Model for Email {
     type FromClause = email_from_clause;
     type Query = Select<Self::FromClause, Self::SelectClause>;
 
+    const TABLE_NAME: &'static str = "email";
+    const UNIQUE_CONSTRAINTS: &'static [UniqueConstraint] = &[UniqueConstraint::new(
+        &["address"],
+        "email",
+        "That email address is already in use",
+    )];
+
     fn query() -> Self::Query {
         Self::from_clause().select(Self::select_clause())
     }
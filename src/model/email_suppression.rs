@@ -0,0 +1,240 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel::upsert::excluded;
+use diesel_async::RunQueryDsl;
+
+use crate::model::{find_by, Model};
+use crate::schema::email_suppression;
+use crate::Connection;
+
+crate::db_enum! {
+    /// Why an address is suppressed.
+    pub enum SuppressionReason {
+        /// Email to the address bounced permanently.
+        Bounce = "bounce",
+        /// The recipient reported email from us as spam.
+        Complaint = "complaint",
+    }
+}
+
+/// An email address that's undeliverable, so no more email is sent to it.
+///
+/// Addresses are suppressed by the bounce and complaint webhooks (see
+/// [`crate::mailer::webhook`]), and [`crate::mailer::TrackedMailer`] skips them.
+#[derive(Clone, Debug)]
+pub struct EmailSuppression {
+    pub id: i32,
+    pub address: String,
+    pub reason: SuppressionReason,
+    /// What the provider said about it, e.g. the bounce's diagnostic code.
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+find_by!(EmailSuppression, email_suppression::address: &str);
+
+impl EmailSuppression {
+    /// Suppress `address`, replacing the reason it was suppressed for before.
+    pub async fn suppress(
+        address: &str,
+        reason: SuppressionReason,
+        detail: Option<&str>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Self> {
+        let mut record = Self::create_record(address, reason, now);
+        record.detail = detail;
+
+        Ok(diesel::insert_into(email_suppression::table)
+            .values(record)
+            .on_conflict(email_suppression::address)
+            .do_update()
+            .set((
+                email_suppression::reason.eq(excluded(email_suppression::reason)),
+                email_suppression::detail.eq(excluded(email_suppression::detail)),
+            ))
+            .returning(EmailSuppressionRecord::as_returning())
+            .get_result(conn)
+            .await?
+            .into())
+    }
+
+    /// Stop suppressing `address`, e.g. once the user has fixed their mailbox.
+    pub async fn lift(address: &str, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(email_suppression::table.filter(email_suppression::address.eq(address)))
+            .execute(conn)
+            .await
+    }
+
+    /// Suppressed addresses, most recent first.
+    pub async fn list(conn: &mut Connection, limit: Option<i64>) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .limit(limit.unwrap_or(100))
+            .order_by(email_suppression::id.desc())
+            .load(conn)
+            .await
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn email_suppression_from_clause() -> _ {
+    email_suppression::table
+}
+
+#[diesel::dsl::auto_type]
+fn email_suppression_select_clause() -> _ {
+    let as_select: AsSelect<EmailSuppressionRecord, Sqlite> = EmailSuppressionRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for EmailSuppression {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = email_suppression_select_clause;
+    type FromClause = email_suppression_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "email_suppression";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        email_suppression_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        email_suppression_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query()
+            .filter(email_suppression::id.eq(id))
+            .first(conn)
+            .await
+    }
+}
+
+impl Selectable<Sqlite> for EmailSuppression {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<EmailSuppression as Model>::RowSqlType, Sqlite> for EmailSuppression {
+    type Row = (EmailSuppressionRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<EmailSuppressionRecord> for EmailSuppression {
+    fn from(value: EmailSuppressionRecord) -> Self {
+        Self {
+            id: value.id,
+            address: value.address,
+            reason: value.reason,
+            detail: value.detail,
+            created_at: value.created_at,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::email_suppression)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct EmailSuppressionRecord {
+    pub id: i32,
+    pub address: String,
+    pub reason: SuppressionReason,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl EmailSuppressionRecord {
+    pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<EmailSuppressionRecord> {
+        email_suppression::table.find(id).get_result(conn).await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(email_suppression::table.find(self.id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `EmailSuppression` model into `EmailSuppressionRecord`
+impl From<EmailSuppression> for EmailSuppressionRecord {
+    fn from(value: EmailSuppression) -> Self {
+        Self {
+            id: value.id,
+            address: value.address,
+            reason: value.reason,
+            detail: value.detail,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::email_suppression)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CreateEmailSuppressionRecord<'a> {
+    pub address: &'a str,
+    pub reason: SuppressionReason,
+    pub detail: Option<&'a str>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<'a> CreateEmailSuppressionRecord<'a> {
+    /// Create a new `CreateEmailSuppressionRecord` object
+    pub fn new(
+        address: &'a str,
+        reason: SuppressionReason,
+        created_at: DateTime<Utc>,
+    ) -> CreateEmailSuppressionRecord<'a> {
+        Self {
+            address,
+            reason,
+            detail: None,
+            created_at,
+        }
+    }
+
+    /// Create a new `email_suppression` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<EmailSuppressionRecord> {
+        diesel::insert_into(crate::schema::email_suppression::table)
+            .values(self)
+            .returning(crate::schema::email_suppression::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+}
+
+impl EmailSuppression {
+    pub fn create_record(
+        address: &str,
+        reason: SuppressionReason,
+        created_at: DateTime<Utc>,
+    ) -> CreateEmailSuppressionRecord<'_> {
+        CreateEmailSuppressionRecord::new(address, reason, created_at)
+    }
+
+    pub async fn read_record(
+        id: i32,
+        conn: &mut Connection,
+    ) -> QueryResult<EmailSuppressionRecord> {
+        EmailSuppressionRecord::read(id, conn).await
+    }
+
+    pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+        EmailSuppressionRecord::from(self).delete(conn).await
+    }
+}
@@ -0,0 +1,60 @@
+use crate::actor::RequestActor;
+
+/// What happened to a model in a [`ModelEvent`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelEventKind {
+    Created,
+    Updated,
+    Deleted,
+    /// A scheduled model was published, see [`crate::model::Publish`].
+    Published,
+    /// A trashed model was restored, see [`crate::model::SoftDelete`].
+    Restored,
+}
+
+/// A model was created, updated, deleted, published or restored.
+///
+/// Report these with [`crate::AppContext::on_model_event`], which invalidates the cached fragments
+/// depending on the model.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModelEvent {
+    pub kind: ModelEventKind,
+    /// The model's name, e.g. `post`.
+    pub model: &'static str,
+    /// The id of the model, if the event is about a single one.
+    pub id: Option<i32>,
+    /// Who made the change, when it was made while handling a request, or in a job scoped with
+    /// [`RequestActor::scope`].
+    pub actor: Option<RequestActor>,
+}
+
+impl ModelEvent {
+    fn new(kind: ModelEventKind, model: &'static str, id: i32) -> Self {
+        Self {
+            kind,
+            model,
+            id: Some(id),
+            actor: RequestActor::current(),
+        }
+    }
+
+    pub fn created(model: &'static str, id: i32) -> Self {
+        Self::new(ModelEventKind::Created, model, id)
+    }
+
+    pub fn updated(model: &'static str, id: i32) -> Self {
+        Self::new(ModelEventKind::Updated, model, id)
+    }
+
+    pub fn deleted(model: &'static str, id: i32) -> Self {
+        Self::new(ModelEventKind::Deleted, model, id)
+    }
+
+    pub fn published(model: &'static str, id: i32) -> Self {
+        Self::new(ModelEventKind::Published, model, id)
+    }
+
+    pub fn restored(model: &'static str, id: i32) -> Self {
+        Self::new(ModelEventKind::Restored, model, id)
+    }
+}
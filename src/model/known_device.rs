@@ -0,0 +1,240 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel_async::RunQueryDsl;
+
+use crate::model::Model;
+use crate::schema::known_device;
+use crate::Connection;
+
+/// A device (identified by its user agent) a user has previously signed in from.
+#[derive(Clone, Debug)]
+pub struct KnownDevice {
+    pub id: i32,
+    pub user_id: i32,
+    pub user_agent: String,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+impl KnownDevice {
+    pub async fn list_for_user(user_id: i32, conn: &mut Connection) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .filter(known_device::user_id.eq(user_id))
+            .order_by(known_device::last_seen_at.desc())
+            .load(conn)
+            .await
+    }
+
+    /// Remember that `user_id` signed in from `user_agent`.
+    ///
+    /// Returns `true` when the device hasn't been seen before and the user has signed in from
+    /// somewhere else previously, i.e. when the sign-in is unusual. A user's very first device is
+    /// never considered unusual.
+    pub async fn remember(
+        user_id: i32,
+        user_agent: &str,
+        ip: Option<&str>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<bool> {
+        let updated = diesel::update(
+            known_device::table
+                .filter(known_device::user_id.eq(user_id))
+                .filter(known_device::user_agent.eq(user_agent)),
+        )
+        .set((known_device::ip.eq(ip), known_device::last_seen_at.eq(now)))
+        .execute(conn)
+        .await?;
+
+        if updated > 0 {
+            return Ok(false);
+        }
+
+        let previous: i64 = known_device::table
+            .filter(known_device::user_id.eq(user_id))
+            .count()
+            .get_result(conn)
+            .await?;
+
+        let mut record = Self::create_record(user_id, user_agent, now);
+        if let Some(ip) = ip {
+            record = record.with_ip(ip);
+        }
+        record.save(conn).await?;
+
+        Ok(previous > 0)
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn known_device_from_clause() -> _ {
+    known_device::table
+}
+
+#[diesel::dsl::auto_type]
+fn known_device_select_clause() -> _ {
+    let as_select: AsSelect<KnownDeviceRecord, Sqlite> = KnownDeviceRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for KnownDevice {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = known_device_select_clause;
+    type FromClause = known_device_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "known_device";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        known_device_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        known_device_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query()
+            .filter(known_device::id.eq(id))
+            .first(conn)
+            .await
+    }
+}
+
+impl Selectable<Sqlite> for KnownDevice {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<KnownDevice as Model>::RowSqlType, Sqlite> for KnownDevice {
+    type Row = (KnownDeviceRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<KnownDeviceRecord> for KnownDevice {
+    fn from(value: KnownDeviceRecord) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            user_agent: value.user_agent,
+            ip: value.ip,
+            created_at: value.created_at,
+            last_seen_at: value.last_seen_at,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::known_device)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct KnownDeviceRecord {
+    pub id: i32,
+    pub user_id: i32,
+    pub user_agent: String,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+impl KnownDeviceRecord {
+    pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<KnownDeviceRecord> {
+        known_device::table.find(id).get_result(conn).await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(known_device::table.find(self.id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `KnownDevice` model into `KnownDeviceRecord`
+impl From<KnownDevice> for KnownDeviceRecord {
+    fn from(value: KnownDevice) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            user_agent: value.user_agent,
+            ip: value.ip,
+            created_at: value.created_at,
+            last_seen_at: value.last_seen_at,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::known_device)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CreateKnownDeviceRecord<'a> {
+    pub user_id: i32,
+    pub user_agent: &'a str,
+    pub ip: Option<&'a str>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+impl<'a> CreateKnownDeviceRecord<'a> {
+    /// Create a new `CreateKnownDeviceRecord` object
+    pub fn new(
+        user_id: i32,
+        user_agent: &'a str,
+        created_at: DateTime<Utc>,
+    ) -> CreateKnownDeviceRecord<'a> {
+        Self {
+            user_id,
+            user_agent,
+            ip: None,
+            created_at,
+            last_seen_at: created_at,
+        }
+    }
+
+    pub fn with_ip(self, ip: &'a str) -> CreateKnownDeviceRecord<'a> {
+        Self {
+            ip: Some(ip),
+            ..self
+        }
+    }
+
+    /// Create a new `known_device` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<KnownDeviceRecord> {
+        diesel::insert_into(crate::schema::known_device::table)
+            .values(self)
+            .returning(crate::schema::known_device::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+}
+
+impl KnownDevice {
+    pub fn create_record(
+        user_id: i32,
+        user_agent: &str,
+        created_at: DateTime<Utc>,
+    ) -> CreateKnownDeviceRecord<'_> {
+        CreateKnownDeviceRecord::new(user_id, user_agent, created_at)
+    }
+
+    pub async fn read_record(id: i32, conn: &mut Connection) -> QueryResult<KnownDeviceRecord> {
+        KnownDeviceRecord::read(id, conn).await
+    }
+
+    pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+        KnownDeviceRecord::from(self).delete(conn).await
+    }
+}
@@ -0,0 +1,315 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Text, TimestamptzSqlite};
+use diesel::sqlite::Sqlite;
+use diesel::upsert::excluded;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+
+use crate::model::Model;
+use crate::schema::kv_entry;
+use crate::Connection;
+
+/// An entry in the key-value store, with its value as JSON. See [`crate::kv`] for the API.
+#[derive(Clone, Debug, Serialize)]
+pub struct KvEntry {
+    pub id: i32,
+    pub key: String,
+    /// The entry's value, as JSON.
+    pub value: String,
+    /// When the entry expires, if it does.
+    pub expires_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(QueryableByName)]
+struct Counter {
+    #[diesel(sql_type = BigInt)]
+    value: i64,
+}
+
+impl KvEntry {
+    /// The unexpired entry `key`.
+    pub async fn find(
+        key: &str,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Option<Self>> {
+        Self::query()
+            .filter(kv_entry::key.eq(key))
+            .filter(
+                kv_entry::expires_at
+                    .is_null()
+                    .or(kv_entry::expires_at.gt(now)),
+            )
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// Set the entry `key` to the JSON `value`, replacing the previous value.
+    pub async fn set(
+        key: &str,
+        value: &str,
+        expires_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Self> {
+        Ok(diesel::insert_into(kv_entry::table)
+            .values(Self::create_record(key, value, expires_at, now))
+            .on_conflict(kv_entry::key)
+            .do_update()
+            .set((
+                kv_entry::value.eq(excluded(kv_entry::value)),
+                kv_entry::expires_at.eq(excluded(kv_entry::expires_at)),
+                kv_entry::updated_at.eq(excluded(kv_entry::updated_at)),
+            ))
+            .returning(KvEntryRecord::as_returning())
+            .get_result(conn)
+            .await?
+            .into())
+    }
+
+    /// Remove the entry `key`, returning how many entries were removed.
+    pub async fn unset(key: &str, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(kv_entry::table.filter(kv_entry::key.eq(key)))
+            .execute(conn)
+            .await
+    }
+
+    /// Add `by` to the counter `key`, returning its new value. A counter which doesn't exist or
+    /// has expired starts again from `by`, expiring at `expires_at`.
+    pub async fn increment(
+        key: &str,
+        by: i64,
+        expires_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<i64> {
+        // One statement, so concurrent increments can't lose counts.
+        let counter: Counter = diesel::sql_query(
+            "INSERT INTO kv_entry (key, value, expires_at, updated_at) VALUES (?, ?, ?, ?)
+            ON CONFLICT (key) DO UPDATE SET
+                value = CASE WHEN kv_entry.expires_at <= excluded.updated_at
+                    THEN excluded.value
+                    ELSE CAST(kv_entry.value AS INTEGER) + CAST(excluded.value AS INTEGER) END,
+                expires_at = CASE WHEN kv_entry.expires_at <= excluded.updated_at
+                    THEN excluded.expires_at
+                    ELSE kv_entry.expires_at END,
+                updated_at = excluded.updated_at
+            RETURNING CAST(value AS INTEGER) AS value",
+        )
+        .bind::<Text, _>(key)
+        .bind::<Text, _>(by.to_string())
+        .bind::<Nullable<TimestamptzSqlite>, _>(expires_at)
+        .bind::<TimestamptzSqlite, _>(now)
+        .get_result(conn)
+        .await?;
+
+        Ok(counter.value)
+    }
+
+    /// Store `value` under `key`, unless there's an unexpired entry for it already. Returns
+    /// whether it was stored.
+    pub async fn set_if_absent(
+        key: &str,
+        value: &str,
+        expires_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<bool> {
+        let stored = diesel::sql_query(
+            "INSERT INTO kv_entry (key, value, expires_at, updated_at) VALUES (?, ?, ?, ?)
+            ON CONFLICT (key) DO UPDATE SET
+                value = excluded.value,
+                expires_at = excluded.expires_at,
+                updated_at = excluded.updated_at
+            WHERE kv_entry.expires_at <= excluded.updated_at",
+        )
+        .bind::<Text, _>(key)
+        .bind::<Text, _>(value)
+        .bind::<Nullable<TimestamptzSqlite>, _>(expires_at)
+        .bind::<TimestamptzSqlite, _>(now)
+        .execute(conn)
+        .await?;
+
+        Ok(stored > 0)
+    }
+
+    /// Remove the entry `key` if its value is still `value`, returning whether it was removed.
+    pub async fn unset_if(key: &str, value: &str, conn: &mut Connection) -> QueryResult<bool> {
+        let removed = diesel::delete(
+            kv_entry::table
+                .filter(kv_entry::key.eq(key))
+                .filter(kv_entry::value.eq(value)),
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(removed > 0)
+    }
+
+    pub async fn delete_expired(now: DateTime<Utc>, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(kv_entry::table.filter(kv_entry::expires_at.le(now)))
+            .execute(conn)
+            .await
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn kv_entry_from_clause() -> _ {
+    kv_entry::table
+}
+
+#[diesel::dsl::auto_type]
+fn kv_entry_select_clause() -> _ {
+    let as_select: AsSelect<KvEntryRecord, Sqlite> = KvEntryRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for KvEntry {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = kv_entry_select_clause;
+    type FromClause = kv_entry_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "kv_entry";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        kv_entry_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        kv_entry_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query().filter(kv_entry::id.eq(id)).first(conn).await
+    }
+}
+
+impl Selectable<Sqlite> for KvEntry {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<KvEntry as Model>::RowSqlType, Sqlite> for KvEntry {
+    type Row = (KvEntryRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<KvEntryRecord> for KvEntry {
+    fn from(value: KvEntryRecord) -> Self {
+        Self {
+            id: value.id,
+            key: value.key,
+            value: value.value,
+            expires_at: value.expires_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::kv_entry)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct KvEntryRecord {
+    pub id: i32,
+    pub key: String,
+    pub value: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl KvEntryRecord {
+    pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<KvEntryRecord> {
+        kv_entry::table.find(id).get_result(conn).await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(kv_entry::table.find(self.id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `KvEntry` model into `KvEntryRecord`
+impl From<KvEntry> for KvEntryRecord {
+    fn from(value: KvEntry) -> Self {
+        Self {
+            id: value.id,
+            key: value.key,
+            value: value.value,
+            expires_at: value.expires_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::kv_entry)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CreateKvEntryRecord<'a> {
+    pub key: &'a str,
+    pub value: &'a str,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl<'a> CreateKvEntryRecord<'a> {
+    /// Create a new `CreateKvEntryRecord` object
+    pub fn new(
+        key: &'a str,
+        value: &'a str,
+        expires_at: Option<DateTime<Utc>>,
+        updated_at: DateTime<Utc>,
+    ) -> CreateKvEntryRecord<'a> {
+        Self {
+            key,
+            value,
+            expires_at,
+            updated_at,
+        }
+    }
+
+    /// Create a new `kv_entry` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<KvEntryRecord> {
+        diesel::insert_into(crate::schema::kv_entry::table)
+            .values(self)
+            .returning(crate::schema::kv_entry::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+}
+
+impl KvEntry {
+    pub fn create_record<'a>(
+        key: &'a str,
+        value: &'a str,
+        expires_at: Option<DateTime<Utc>>,
+        updated_at: DateTime<Utc>,
+    ) -> CreateKvEntryRecord<'a> {
+        CreateKvEntryRecord::new(key, value, expires_at, updated_at)
+    }
+
+    pub async fn read_record(id: i32, conn: &mut Connection) -> QueryResult<KvEntryRecord> {
+        KvEntryRecord::read(id, conn).await
+    }
+
+    pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+        KvEntryRecord::from(self).delete(conn).await
+    }
+}
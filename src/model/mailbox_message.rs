@@ -0,0 +1,218 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel_async::RunQueryDsl;
+
+use crate::model::Model;
+use crate::schema::mailbox_message;
+use crate::Connection;
+
+/// An email captured by the [`crate::mailer::Mailbox`] transport.
+#[derive(Clone, Debug)]
+pub struct MailboxMessage {
+    pub id: i32,
+    pub sender: String,
+    pub recipient: String,
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MailboxMessage {
+    pub async fn list(conn: &mut Connection, limit: Option<i64>) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .limit(limit.unwrap_or(100))
+            .order_by(mailbox_message::id.desc())
+            .load(conn)
+            .await
+    }
+
+    /// Links found in the plain text body, e.g. verification links.
+    pub fn links(&self) -> Vec<&str> {
+        self.text
+            .split_whitespace()
+            .filter(|word| word.starts_with("http://") || word.starts_with("https://"))
+            .collect()
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn mailbox_message_from_clause() -> _ {
+    mailbox_message::table
+}
+
+#[diesel::dsl::auto_type]
+fn mailbox_message_select_clause() -> _ {
+    let as_select: AsSelect<MailboxMessageRecord, Sqlite> = MailboxMessageRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for MailboxMessage {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = mailbox_message_select_clause;
+    type FromClause = mailbox_message_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "mailbox_message";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        mailbox_message_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        mailbox_message_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query()
+            .filter(mailbox_message::id.eq(id))
+            .first(conn)
+            .await
+    }
+}
+
+impl Selectable<Sqlite> for MailboxMessage {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<MailboxMessage as Model>::RowSqlType, Sqlite> for MailboxMessage {
+    type Row = (MailboxMessageRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<MailboxMessageRecord> for MailboxMessage {
+    fn from(value: MailboxMessageRecord) -> Self {
+        Self {
+            id: value.id,
+            sender: value.sender,
+            recipient: value.recipient,
+            subject: value.subject,
+            text: value.text,
+            html: value.html,
+            created_at: value.created_at,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::mailbox_message)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct MailboxMessageRecord {
+    pub id: i32,
+    pub sender: String,
+    pub recipient: String,
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MailboxMessageRecord {
+    pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<MailboxMessageRecord> {
+        mailbox_message::table.find(id).get_result(conn).await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(mailbox_message::table.find(self.id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `MailboxMessage` model into `MailboxMessageRecord`
+impl From<MailboxMessage> for MailboxMessageRecord {
+    fn from(value: MailboxMessage) -> Self {
+        Self {
+            id: value.id,
+            sender: value.sender,
+            recipient: value.recipient,
+            subject: value.subject,
+            text: value.text,
+            html: value.html,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::mailbox_message)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CreateMailboxMessageRecord<'a> {
+    pub sender: &'a str,
+    pub recipient: &'a str,
+    pub subject: &'a str,
+    pub text: &'a str,
+    pub html: Option<&'a str>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<'a> CreateMailboxMessageRecord<'a> {
+    /// Create a new `CreateMailboxMessageRecord` object
+    pub fn new(
+        sender: &'a str,
+        recipient: &'a str,
+        subject: &'a str,
+        text: &'a str,
+        created_at: DateTime<Utc>,
+    ) -> CreateMailboxMessageRecord<'a> {
+        Self {
+            sender,
+            recipient,
+            subject,
+            text,
+            html: None,
+            created_at,
+        }
+    }
+
+    pub fn with_html(self, html: &'a str) -> CreateMailboxMessageRecord<'a> {
+        Self {
+            html: Some(html),
+            ..self
+        }
+    }
+
+    /// Create a new `mailbox_message` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<MailboxMessageRecord> {
+        diesel::insert_into(crate::schema::mailbox_message::table)
+            .values(self)
+            .returning(crate::schema::mailbox_message::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+}
+
+impl MailboxMessage {
+    pub fn create_record<'a>(
+        sender: &'a str,
+        recipient: &'a str,
+        subject: &'a str,
+        text: &'a str,
+        created_at: DateTime<Utc>,
+    ) -> CreateMailboxMessageRecord<'a> {
+        CreateMailboxMessageRecord::new(sender, recipient, subject, text, created_at)
+    }
+
+    pub async fn read_record(id: i32, conn: &mut Connection) -> QueryResult<MailboxMessageRecord> {
+        MailboxMessageRecord::read(id, conn).await
+    }
+
+    pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+        MailboxMessageRecord::from(self).delete(conn).await
+    }
+}
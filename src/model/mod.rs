@@ -1,25 +1,75 @@
+use std::marker::PhantomData;
+
 use diesel::query_builder::SelectQuery;
 use diesel::sql_types::Nullable;
 use diesel::sql_types::{Integer, Text};
 use diesel::{define_sql_function, QueryResult};
+use diesel_async::methods::LoadQuery;
+use serde::Serialize;
 
+use crate::form::UniqueConstraint;
 use crate::Connection;
 
+mod announcement;
+mod attachment;
+mod audit_log;
 mod credentials;
+mod dead_letter;
+mod db_enum;
+mod draft;
 mod email;
+mod email_suppression;
+mod event;
+mod known_device;
+mod kv_entry;
+mod mailbox_message;
+mod model_cache;
+mod notification_preferences;
+mod pagination;
 mod permission;
+mod publish;
+mod queued_job;
 mod role;
+mod sent_email;
+mod setting;
+mod soft_delete;
 mod token;
 pub mod unverified_email;
 pub mod user;
+mod user_preference;
+mod version;
 
+pub use announcement::*;
+pub use attachment::*;
+pub use audit_log::*;
 pub use credentials::*;
+pub use dead_letter::*;
+pub use db_enum::*;
+pub use draft::*;
 pub use email::*;
+pub use email_suppression::*;
+pub use event::*;
+pub use known_device::*;
+pub use kv_entry::*;
+pub use mailbox_message::*;
+pub use model_cache::*;
+pub use notification_preferences::*;
+pub use pagination::*;
 pub use permission::*;
+pub use publish::*;
+pub use queued_job::*;
 pub use role::*;
+pub use sent_email::*;
+pub use setting::*;
+pub use soft_delete::*;
 pub use token::*;
 pub use unverified_email::*;
 pub use user::*;
+pub use user_preference::*;
+pub use version::*;
+
+/// What an upsert did, shared with the records generated by `lowboy_record!`.
+pub use lowboy_record::Operation;
 
 #[async_trait::async_trait]
 pub trait Model {
@@ -29,6 +79,20 @@ pub trait Model {
     type FromClause;
     type Query: SelectQuery;
 
+    /// The model's table, e.g. `user_preference`, which is also the name versions, the trash and
+    /// model events know the model by. Models that are a view of another model's table, e.g.
+    /// [`UnverifiedEmail`] of `email`, have a name of their own so they're told apart.
+    ///
+    /// Records generated by `lowboy_record!` have their table's name as
+    /// `<Model>Record::TABLE_NAME`.
+    const TABLE_NAME: &'static str;
+
+    /// Unique constraints to report as form errors, see [`crate::form::UniqueConstraints`].
+    const UNIQUE_CONSTRAINTS: &'static [UniqueConstraint] = &[];
+
+    /// The column cursor based pages are ordered by, see [`Model::page_after`].
+    const PRIMARY_KEY: &'static str = "id";
+
     fn from_clause() -> Self::FromClause;
 
     fn select_clause() -> Self::SelectClause;
@@ -40,7 +104,174 @@ pub trait Model {
     async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self>
     where
         Self: Sized;
+
+    /// [`Model::load`], reusing the model if it was already loaded during the current request,
+    /// see [`ModelCache`].
+    async fn load_cached(id: i32, conn: &mut Connection) -> QueryResult<Self>
+    where
+        Self: Sized + Clone + Send + Sync + 'static,
+    {
+        match ModelCache::current() {
+            Some(cache) => cache.get_or_load(id, Self::load(id, conn)).await,
+            None => Self::load(id, conn).await,
+        }
+    }
+
+    /// The `page`th page, starting from 1, of `per_page` models, along with the total. Use
+    /// [`Paginate`] on [`Model::query`] to paginate a filtered or ordered list.
+    async fn page(page: i64, per_page: i64, conn: &mut Connection) -> QueryResult<Paginated<Self>>
+    where
+        Self: Sized + Send + 'static,
+        Self::Query: Clone + Send + 'static,
+        OffsetPage<Self::Query>: LoadQuery<'static, Connection, Self>,
+        CountRows<Self::Query>: LoadQuery<'static, Connection, i64>,
+    {
+        Self::query().paginate(page, per_page).load_page(conn).await
+    }
+
+    /// The `per_page` models after the one whose primary key is `cursor`, or the first ones if
+    /// there's no cursor, along with the total.
+    async fn page_after(
+        cursor: Option<i32>,
+        per_page: i64,
+        conn: &mut Connection,
+    ) -> QueryResult<Paginated<Self>>
+    where
+        Self: Sized + Send + 'static,
+        Self::Query: Send + 'static,
+        CursorPage<Self::Query>: LoadQuery<'static, Connection, (Self, i64, i32)>,
+    {
+        Self::query()
+            .paginate_after(Self::PRIMARY_KEY, cursor, per_page)
+            .load_page(conn)
+            .await
+    }
+}
+
+/// Models which can be looked up by a slug, e.g. in URLs, see [`crate::extract::LoadSlug`].
+#[async_trait::async_trait]
+pub trait LoadBySlug: Model {
+    async fn load_by_slug(slug: &str, conn: &mut Connection) -> QueryResult<Self>
+    where
+        Self: Sized;
+}
+
+/// An object safe companion to [`Model`], for holding different kinds of models together, e.g. in
+/// admin tooling or a search indexer.
+///
+/// It's implemented for every serializable model, and for [`ModelType`], which stands in for a
+/// model without an instance of it:
+///
+/// ```ignore
+/// let models = [ModelType::<Role>::boxed(), ModelType::<Post>::boxed()];
+/// ```
+#[async_trait::async_trait]
+pub trait AnyModel: Send + Sync {
+    /// The model's table, e.g. `user_preference`.
+    fn table_name(&self) -> &'static str;
+
+    /// The model's name for people, e.g. `User preference`.
+    fn display_name(&self) -> String {
+        let mut name = self.table_name().replace('_', " ");
+        if let Some(first) = name.get_mut(..1) {
+            first.make_ascii_uppercase();
+        }
+        name
+    }
+
+    /// Load the model with `id`, serialized as JSON.
+    async fn load_json(&self, id: i32, conn: &mut Connection) -> QueryResult<serde_json::Value>;
+}
+
+async fn load_json<T: Model + Serialize>(
+    id: i32,
+    conn: &mut Connection,
+) -> QueryResult<serde_json::Value> {
+    let model = T::load(id, conn).await?;
+
+    serde_json::to_value(&model)
+        .map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))
+}
+
+#[async_trait::async_trait]
+impl<T> AnyModel for T
+where
+    T: Model + Serialize + Send + Sync + 'static,
+{
+    fn table_name(&self) -> &'static str {
+        T::TABLE_NAME
+    }
+
+    async fn load_json(&self, id: i32, conn: &mut Connection) -> QueryResult<serde_json::Value> {
+        load_json::<T>(id, conn).await
+    }
+}
+
+/// Stands in for the model `T` as an [`AnyModel`].
+pub struct ModelType<T>(PhantomData<fn() -> T>);
+
+impl<T> ModelType<T>
+where
+    T: Model + Serialize + Send + Sync + 'static,
+{
+    pub fn boxed() -> Box<dyn AnyModel> {
+        Box::new(Self(PhantomData))
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> AnyModel for ModelType<T>
+where
+    T: Model + Serialize + Send + Sync + 'static,
+{
+    fn table_name(&self) -> &'static str {
+        T::TABLE_NAME
+    }
+
+    async fn load_json(&self, id: i32, conn: &mut Connection) -> QueryResult<serde_json::Value> {
+        load_json::<T>(id, conn).await
+    }
+}
+
+/// A model belonging to a user, e.g. a post and its author.
+///
+/// See [`crate::authorize_owner!`] for restricting access to a model to its owner.
+pub trait Owned {
+    /// The id of the user that owns this model.
+    fn owner_id(&self) -> i32;
+}
+
+/// Generate `find_by_<column>` methods for a model's unique columns, which return the model with
+/// the given value, if there is one.
+///
+/// ```ignore
+/// find_by!(Role, role::name: &str);
+/// ```
+macro_rules! find_by {
+    ($model:ident, $($table:ident::$column:ident: $type:ty),+ $(,)?) => {
+        paste::paste! {
+            impl $model {
+                $(
+                    #[doc = "Find the `" $model "` with the given `" $column "`."]
+                    pub async fn [<find_by_ $column>](
+                        $column: $type,
+                        conn: &mut crate::Connection,
+                    ) -> diesel::QueryResult<Option<Self>> {
+                        use diesel::prelude::*;
+                        use diesel_async::RunQueryDsl;
+
+                        <Self as crate::model::Model>::query()
+                            .filter(crate::schema::$table::$column.eq($column))
+                            .first(conn)
+                            .await
+                            .optional()
+                    }
+                )+
+            }
+        }
+    };
 }
+pub(crate) use find_by;
 
 define_sql_function! {
     fn group_concat(val: Text, separator: Text) -> Text;
@@ -0,0 +1,84 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use tokio::sync::OnceCell;
+
+type Entry = Arc<OnceCell<Box<dyn Any + Send + Sync>>>;
+
+tokio::task_local! {
+    static MODEL_CACHE: ModelCache;
+}
+
+/// Memoizes models for the duration of a request, keyed by their type and id, so the auth layer,
+/// extractors and the layout don't load the same user (and its roles and permissions) more than
+/// once.
+///
+/// It's used by [`crate::model::Model::load_cached`], and is available to handlers as an
+/// `Extension<ModelCache>`, e.g. to forget a model after changing it.
+#[derive(Clone, Default)]
+pub struct ModelCache(Arc<Mutex<HashMap<(TypeId, i32), Entry>>>);
+
+impl ModelCache {
+    /// The cache of the request being handled, if there is one.
+    pub fn current() -> Option<Self> {
+        MODEL_CACHE.try_with(Clone::clone).ok()
+    }
+
+    /// The `T` with `id` loaded earlier in the request, or the one `load` loads. Concurrent loads
+    /// of the same `T` wait for the first.
+    pub async fn get_or_load<T, E, F>(&self, id: i32, load: F) -> Result<T, E>
+    where
+        T: Clone + Send + Sync + 'static,
+        F: Future<Output = Result<T, E>>,
+    {
+        let entry = self
+            .entries()
+            .entry((TypeId::of::<T>(), id))
+            .or_default()
+            .clone();
+
+        let model = entry
+            .get_or_try_init(|| async {
+                let model = load.await?;
+                Ok::<_, E>(Box::new(model) as Box<dyn Any + Send + Sync>)
+            })
+            .await?;
+
+        Ok(model
+            .downcast_ref::<T>()
+            .expect("model cache entries are keyed by their type")
+            .clone())
+    }
+
+    /// Forget the `T` with `id`, so it's loaded again.
+    pub fn forget<T: 'static>(&self, id: i32) {
+        self.entries().remove(&(TypeId::of::<T>(), id));
+    }
+
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<(TypeId, i32), Entry>> {
+        self.0
+            .lock()
+            .expect("model cache lock should not be poisoned")
+    }
+}
+
+/// A user loaded along with its roles and permissions, cached apart from the user on its own.
+#[derive(Clone)]
+pub(crate) struct WithRolesAndPermissions<U>(pub U);
+
+/// Give every request its own [`ModelCache`].
+pub(crate) async fn cache_models(mut request: Request, next: Next) -> Response {
+    let cache = ModelCache::default();
+    request.extensions_mut().insert(cache.clone());
+
+    MODEL_CACHE.scope(cache, next.run(request)).await
+}
@@ -0,0 +1,231 @@
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel::upsert::excluded;
+use diesel_async::RunQueryDsl;
+
+use crate::model::Model;
+use crate::schema::{digest_opt_out, notification_preferences};
+use crate::security::{NotificationKind, SecurityNotification};
+use crate::Connection;
+
+/// Which security notifications a user wants to receive.
+#[derive(Clone, Debug)]
+pub struct NotificationPreferences {
+    pub user_id: i32,
+    pub new_device_login: bool,
+    pub failed_login_streak: bool,
+    pub password_changed: bool,
+}
+
+impl NotificationPreferences {
+    /// Every notification is enabled until the user opts out.
+    pub fn new(user_id: i32) -> Self {
+        Self {
+            user_id,
+            new_device_login: true,
+            failed_login_streak: true,
+            password_changed: true,
+        }
+    }
+
+    pub async fn for_user(user_id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Ok(Self::query()
+            .filter(notification_preferences::user_id.eq(user_id))
+            .first(conn)
+            .await
+            .optional()?
+            .unwrap_or_else(|| Self::new(user_id)))
+    }
+
+    pub fn allows(&self, notification: &SecurityNotification) -> bool {
+        self.allows_kind(notification.kind())
+    }
+
+    pub fn allows_kind(&self, kind: NotificationKind) -> bool {
+        match kind {
+            NotificationKind::NewDeviceLogin => self.new_device_login,
+            NotificationKind::FailedLoginStreak => self.failed_login_streak,
+            NotificationKind::PasswordChanged => self.password_changed,
+        }
+    }
+
+    /// Whether `user_id` wants to receive the digest named `digest`. Digests are enabled until the
+    /// user opts out.
+    pub async fn allows_digest(
+        user_id: i32,
+        digest: &str,
+        conn: &mut Connection,
+    ) -> QueryResult<bool> {
+        let opted_out = diesel::select(diesel::dsl::exists(
+            digest_opt_out::table
+                .filter(digest_opt_out::user_id.eq(user_id))
+                .filter(digest_opt_out::digest.eq(digest)),
+        ))
+        .get_result::<bool>(conn)
+        .await?;
+
+        Ok(!opted_out)
+    }
+
+    /// Opt `user_id` in to or out of the digest named `digest`.
+    pub async fn set_digest(
+        user_id: i32,
+        digest: &str,
+        enabled: bool,
+        conn: &mut Connection,
+    ) -> QueryResult<()> {
+        if enabled {
+            diesel::delete(
+                digest_opt_out::table
+                    .filter(digest_opt_out::user_id.eq(user_id))
+                    .filter(digest_opt_out::digest.eq(digest)),
+            )
+            .execute(conn)
+            .await?;
+        } else {
+            diesel::insert_into(digest_opt_out::table)
+                .values((digest_opt_out::user_id.eq(user_id), digest_opt_out::digest.eq(digest)))
+                .on_conflict_do_nothing()
+                .execute(conn)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<NotificationPreferencesRecord> {
+        NotificationPreferencesRecord::from(self.clone())
+            .save(conn)
+            .await
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn notification_preferences_from_clause() -> _ {
+    notification_preferences::table
+}
+
+#[diesel::dsl::auto_type]
+fn notification_preferences_select_clause() -> _ {
+    let as_select: AsSelect<NotificationPreferencesRecord, Sqlite> =
+        NotificationPreferencesRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for NotificationPreferences {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = notification_preferences_select_clause;
+    type FromClause = notification_preferences_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "notification_preferences";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        notification_preferences_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        notification_preferences_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query()
+            .filter(notification_preferences::user_id.eq(id))
+            .first(conn)
+            .await
+    }
+}
+
+impl Selectable<Sqlite> for NotificationPreferences {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<NotificationPreferences as Model>::RowSqlType, Sqlite> for NotificationPreferences {
+    type Row = (NotificationPreferencesRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<NotificationPreferencesRecord> for NotificationPreferences {
+    fn from(value: NotificationPreferencesRecord) -> Self {
+        Self {
+            user_id: value.user_id,
+            new_device_login: value.new_device_login,
+            failed_login_streak: value.failed_login_streak,
+            password_changed: value.password_changed,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable, Insertable)]
+#[diesel(table_name = crate::schema::notification_preferences)]
+#[diesel(primary_key(user_id))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct NotificationPreferencesRecord {
+    pub user_id: i32,
+    pub new_device_login: bool,
+    pub failed_login_streak: bool,
+    pub password_changed: bool,
+}
+
+impl NotificationPreferencesRecord {
+    pub async fn read(
+        user_id: i32,
+        conn: &mut Connection,
+    ) -> QueryResult<NotificationPreferencesRecord> {
+        notification_preferences::table
+            .find(user_id)
+            .get_result(conn)
+            .await
+    }
+
+    /// Create or update the `notification_preferences` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<NotificationPreferencesRecord> {
+        diesel::insert_into(notification_preferences::table)
+            .values(self)
+            .on_conflict(notification_preferences::user_id)
+            .do_update()
+            .set((
+                notification_preferences::new_device_login
+                    .eq(excluded(notification_preferences::new_device_login)),
+                notification_preferences::failed_login_streak
+                    .eq(excluded(notification_preferences::failed_login_streak)),
+                notification_preferences::password_changed
+                    .eq(excluded(notification_preferences::password_changed)),
+            ))
+            .returning(notification_preferences::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(notification_preferences::table.find(self.user_id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `NotificationPreferences` model into `NotificationPreferencesRecord`
+impl From<NotificationPreferences> for NotificationPreferencesRecord {
+    fn from(value: NotificationPreferences) -> Self {
+        Self {
+            user_id: value.user_id,
+            new_device_login: value.new_device_login,
+            failed_login_streak: value.failed_login_streak,
+            password_changed: value.password_changed,
+        }
+    }
+}
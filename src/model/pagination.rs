@@ -0,0 +1,454 @@
+use axum::http::header::LINK;
+use axum::http::{HeaderValue, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use diesel::query_builder::{AstPass, Query, QueryFragment, QueryId};
+use diesel::sql_types::{BigInt, Integer};
+use diesel::sqlite::Sqlite;
+use diesel::QueryResult;
+use diesel_async::methods::LoadQuery;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+
+use crate::Connection;
+
+/// How many items are on a page unless asked otherwise.
+pub const DEFAULT_PER_PAGE: i64 = 20;
+
+/// The most items a page can have.
+pub const MAX_PER_PAGE: i64 = 100;
+
+/// A page of models, and where it is among the rest.
+///
+/// Offset based pages ([`Paginate::paginate`]) have page numbers for rendering pagination links.
+/// Cursor based pages ([`Paginate::paginate_after`]) have the cursor of the next page, for "load
+/// more" links, and stay correct while rows are being added.
+#[derive(Clone, Debug, Serialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    /// How many items there are across every page.
+    pub total: i64,
+    pub per_page: i64,
+    /// The page number, starting from 1, if the page is offset based.
+    pub page: Option<i64>,
+    /// The cursor to load the next page after, if the page is cursor based and there's a next
+    /// page.
+    pub next_cursor: Option<i32>,
+}
+
+impl<T> Paginated<T> {
+    /// How many pages there are.
+    pub fn pages(&self) -> i64 {
+        (self.total + self.per_page - 1) / self.per_page
+    }
+
+    pub fn prev_page(&self) -> Option<i64> {
+        self.page.filter(|page| *page > 1).map(|page| page - 1)
+    }
+
+    pub fn next_page(&self) -> Option<i64> {
+        self.page
+            .filter(|page| *page < self.pages())
+            .map(|page| page + 1)
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.next_page().is_some() || self.next_cursor.is_some()
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Paginated<U> {
+        Paginated {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            per_page: self.per_page,
+            page: self.page,
+            next_cursor: self.next_cursor,
+        }
+    }
+
+    /// Links to the other pages, made by changing the `page` or `after` query parameter of the
+    /// `uri` this page was requested with.
+    pub fn links(&self, uri: &Uri) -> PageLinks {
+        if self.page.is_none() {
+            return PageLinks {
+                first: Some(page_link(uri, None)),
+                next: self
+                    .next_cursor
+                    .map(|cursor| page_link(uri, Some(("after", cursor.into())))),
+                ..Default::default()
+            };
+        }
+
+        let link = |page: i64| page_link(uri, Some(("page", page)));
+        let pages = self.pages();
+
+        PageLinks {
+            first: Some(link(1)),
+            prev: self.prev_page().map(link),
+            next: self.next_page().map(link),
+            last: (pages > 0).then(|| link(pages)),
+        }
+    }
+
+    /// Respond with the page as JSON, see [`PageResponse`].
+    pub fn into_json(self, uri: &Uri) -> PageResponse<T> {
+        let links = self.links(uri);
+
+        PageResponse { page: self, links }
+    }
+}
+
+/// Links to the first, previous, next and last pages of a [`Paginated`], where there are any.
+/// Cursor based pages only link to the first and next pages.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PageLinks {
+    pub first: Option<String>,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+    pub last: Option<String>,
+}
+
+impl PageLinks {
+    /// The links as an RFC 8288 `Link` header value.
+    pub fn header(&self) -> String {
+        [
+            ("first", &self.first),
+            ("prev", &self.prev),
+            ("next", &self.next),
+            ("last", &self.last),
+        ]
+        .into_iter()
+        .filter_map(|(rel, link)| Some(format!(r#"<{}>; rel="{rel}""#, link.as_ref()?)))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+/// `uri`, with its `page` and `after` query parameters replaced with `param`.
+fn page_link(uri: &Uri, param: Option<(&str, i64)>) -> String {
+    let mut query: Vec<(String, String)> = uri
+        .query()
+        .and_then(|query| serde_urlencoded::from_str(query).ok())
+        .unwrap_or_default();
+    query.retain(|(key, _)| key != "page" && key != "after");
+    if let Some((key, value)) = param {
+        query.push((key.to_string(), value.to_string()));
+    }
+
+    match serde_urlencoded::to_string(&query) {
+        Ok(query) if !query.is_empty() => format!("{}?{query}", uri.path()),
+        _ => uri.path().to_string(),
+    }
+}
+
+/// A page as a JSON response, in the envelope lowboy's APIs page with, along with a `Link` header:
+///
+/// ```json
+/// {
+///   "data": [...],
+///   "meta": { "total": 42, "per_page": 20, "page": 2, "pages": 3, "next_cursor": null },
+///   "links": { "first": "/api/posts?page=1", "prev": "/api/posts?page=1", ... }
+/// }
+/// ```
+///
+/// Links keep the request's other query parameters. Nested routers only see the rest of the path,
+/// so pass the [`axum::extract::OriginalUri`]:
+///
+/// ```ignore
+/// async fn list_posts(
+///     OriginalUri(uri): OriginalUri,
+///     Query(query): Query<PageQuery>,
+///     DatabaseConnection(mut conn): DatabaseConnection,
+/// ) -> Result<impl IntoResponse, LowboyError> {
+///     let posts = Post::page(query.page(), query.per_page(), &mut conn).await?;
+///
+///     Ok(posts.into_json(&uri))
+/// }
+/// ```
+pub struct PageResponse<T> {
+    page: Paginated<T>,
+    links: PageLinks,
+}
+
+#[derive(Serialize)]
+struct PageEnvelope<'a, T> {
+    data: &'a [T],
+    meta: PageMeta,
+    links: &'a PageLinks,
+}
+
+#[derive(Serialize)]
+struct PageMeta {
+    total: i64,
+    per_page: i64,
+    page: Option<i64>,
+    pages: i64,
+    next_cursor: Option<i32>,
+}
+
+impl<T: Serialize> IntoResponse for PageResponse<T> {
+    fn into_response(self) -> Response {
+        let envelope = PageEnvelope {
+            data: &self.page.items,
+            meta: PageMeta {
+                total: self.page.total,
+                per_page: self.page.per_page,
+                page: self.page.page,
+                pages: self.page.pages(),
+                next_cursor: self.page.next_cursor,
+            },
+            links: &self.links,
+        };
+        let mut response = Json(envelope).into_response();
+
+        if let Ok(link) = HeaderValue::from_str(&self.links.header()) {
+            if !link.is_empty() {
+                response.headers_mut().insert(LINK, link);
+            }
+        }
+
+        response
+    }
+}
+
+/// The pagination query string of a list page, e.g. `?page=2` or `?after=41&per_page=10`.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub struct PageQuery {
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    pub after: Option<i32>,
+}
+
+impl PageQuery {
+    /// The page number, starting from 1.
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    /// How many items are on a page, up to [`MAX_PER_PAGE`].
+    pub fn per_page(&self) -> i64 {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+}
+
+/// Paginate any select query, e.g. a model's [`crate::model::Model::query`] with a filter:
+///
+/// ```ignore
+/// let page = Post::query()
+///     .filter(post::user_id.eq(user.id))
+///     .order_by(post::id.desc())
+///     .paginate(query.page(), query.per_page())
+///     .load_page::<Post>(&mut conn)
+///     .await?;
+/// ```
+pub trait Paginate: Sized {
+    /// The `page`th page, starting from 1, of `per_page` rows.
+    fn paginate(self, page: i64, per_page: i64) -> OffsetPage<Self>;
+
+    /// The `per_page` rows after the row whose `column`, usually the primary key, is `cursor`, or
+    /// the first rows if there's no cursor. Rows are ordered by `column`, ascending unless
+    /// [`CursorPage::descending`].
+    fn paginate_after(
+        self,
+        column: &'static str,
+        cursor: Option<i32>,
+        per_page: i64,
+    ) -> CursorPage<Self>;
+}
+
+impl<T> Paginate for T {
+    fn paginate(self, page: i64, per_page: i64) -> OffsetPage<Self> {
+        let page = page.max(1);
+        let per_page = per_page.max(1);
+
+        OffsetPage {
+            query: self,
+            page,
+            per_page,
+            offset: (page - 1) * per_page,
+        }
+    }
+
+    fn paginate_after(
+        self,
+        column: &'static str,
+        cursor: Option<i32>,
+        per_page: i64,
+    ) -> CursorPage<Self> {
+        let per_page = per_page.max(1);
+
+        CursorPage {
+            query: self,
+            column,
+            cursor,
+            per_page,
+            // One more row than the page is loaded to tell whether there's a next page.
+            limit: per_page + 1,
+            descending: false,
+        }
+    }
+}
+
+/// A query paginated with [`Paginate::paginate`].
+#[derive(Debug, Clone, Copy, QueryId)]
+pub struct OffsetPage<T> {
+    query: T,
+    page: i64,
+    per_page: i64,
+    offset: i64,
+}
+
+impl<T: Clone + Send + 'static> OffsetPage<T> {
+    /// Load the page, along with the total number of rows.
+    pub async fn load_page<U>(self, conn: &mut Connection) -> QueryResult<Paginated<U>>
+    where
+        Self: LoadQuery<'static, Connection, U>,
+        CountRows<T>: LoadQuery<'static, Connection, i64>,
+        U: Send + 'static,
+    {
+        let page = self.page;
+        let per_page = self.per_page;
+        // Counted separately from the page, so pages past the end still know how many rows
+        // there are.
+        let total = CountRows(self.query.clone()).get_result::<i64>(conn).await?;
+        let items = self.load::<U>(conn).await?;
+
+        Ok(Paginated {
+            items,
+            total,
+            per_page,
+            page: Some(page),
+            next_cursor: None,
+        })
+    }
+}
+
+impl<T: Query> Query for OffsetPage<T> {
+    type SqlType = T::SqlType;
+}
+
+impl<T> QueryFragment<Sqlite> for OffsetPage<T>
+where
+    T: QueryFragment<Sqlite>,
+{
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Sqlite>) -> QueryResult<()> {
+        out.push_sql("SELECT * FROM (");
+        self.query.walk_ast(out.reborrow())?;
+        out.push_sql(") LIMIT ");
+        out.push_bind_param::<BigInt, _>(&self.per_page)?;
+        out.push_sql(" OFFSET ");
+        out.push_bind_param::<BigInt, _>(&self.offset)?;
+        Ok(())
+    }
+}
+
+/// How many rows a query returns, the total of an [`OffsetPage`].
+#[derive(Debug, Clone, Copy, QueryId)]
+pub struct CountRows<T>(T);
+
+impl<T: Query> Query for CountRows<T> {
+    type SqlType = BigInt;
+}
+
+impl<T> QueryFragment<Sqlite> for CountRows<T>
+where
+    T: QueryFragment<Sqlite>,
+{
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Sqlite>) -> QueryResult<()> {
+        out.push_sql("SELECT COUNT(*) FROM (");
+        self.0.walk_ast(out.reborrow())?;
+        out.push_sql(")");
+        Ok(())
+    }
+}
+
+/// A query paginated with [`Paginate::paginate_after`].
+#[derive(Debug, Clone, Copy)]
+pub struct CursorPage<T> {
+    query: T,
+    column: &'static str,
+    cursor: Option<i32>,
+    per_page: i64,
+    limit: i64,
+    descending: bool,
+}
+
+impl<T> CursorPage<T> {
+    /// Order the rows by the cursor column descending, e.g. for newest first.
+    pub fn descending(self) -> Self {
+        Self {
+            descending: true,
+            ..self
+        }
+    }
+}
+
+impl<T: Send + 'static> CursorPage<T> {
+    /// Load the page, along with the total number of rows.
+    pub async fn load_page<U>(self, conn: &mut Connection) -> QueryResult<Paginated<U>>
+    where
+        Self: LoadQuery<'static, Connection, (U, i64, i32)>,
+        U: Send + 'static,
+    {
+        let per_page = self.per_page;
+        let mut rows = self.load::<(U, i64, i32)>(conn).await?;
+
+        let total = rows.first().map_or(0, |(_, total, _)| *total);
+        let has_next = rows.len() as i64 > per_page;
+        rows.truncate(per_page as usize);
+        let next_cursor = rows
+            .last()
+            .filter(|_| has_next)
+            .map(|(_, _, cursor)| *cursor);
+
+        Ok(Paginated {
+            items: rows.into_iter().map(|(item, _, _)| item).collect(),
+            total,
+            per_page,
+            page: None,
+            next_cursor,
+        })
+    }
+}
+
+impl<T: Query> Query for CursorPage<T> {
+    type SqlType = (T::SqlType, BigInt, Integer);
+}
+
+// The SQL depends on the cursor column, whether there's a cursor, and the order, so it isn't
+// cached as a prepared statement.
+impl<T> QueryId for CursorPage<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<T> QueryFragment<Sqlite> for CursorPage<T>
+where
+    T: QueryFragment<Sqlite>,
+{
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Sqlite>) -> QueryResult<()> {
+        // The total is counted before the rows before the cursor are filtered out.
+        out.push_sql("SELECT *, ");
+        out.push_identifier(self.column)?;
+        out.push_sql(" FROM (SELECT *, COUNT(*) OVER () FROM (");
+        self.query.walk_ast(out.reborrow())?;
+        out.push_sql("))");
+
+        if let Some(cursor) = &self.cursor {
+            out.push_sql(" WHERE ");
+            out.push_identifier(self.column)?;
+            out.push_sql(if self.descending { " < " } else { " > " });
+            out.push_bind_param::<Integer, _>(cursor)?;
+        }
+
+        out.push_sql(" ORDER BY ");
+        out.push_identifier(self.column)?;
+        out.push_sql(if self.descending { " DESC" } else { " ASC" });
+        out.push_sql(" LIMIT ");
+        out.push_bind_param::<BigInt, _>(&self.limit)?;
+        Ok(())
+    }
+}
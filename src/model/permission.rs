@@ -1,27 +1,89 @@
 use diesel::dsl::{AsSelect, Select, SqlTypeOf};
 use diesel::prelude::*;
 use diesel::sqlite::Sqlite;
-use diesel::{OptionalExtension, QueryResult, Selectable};
+use diesel::{QueryResult, Selectable};
 use diesel_async::RunQueryDsl;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
 
-use crate::model::Model;
+use crate::model::{find_by, Model};
 use crate::schema::permission;
 use crate::Connection;
 
-#[derive(Clone, Debug, Deserialize, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, Hash, Eq, PartialEq)]
 pub struct Permission {
     pub id: i32,
     pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
 }
 
+find_by!(Permission, permission::name: &str);
+
 impl Permission {
-    pub async fn find_by_name(name: &str, conn: &mut Connection) -> QueryResult<Option<Self>> {
+    pub async fn list(conn: &mut Connection) -> QueryResult<Vec<Self>> {
         Self::query()
-            .filter(permission::name.eq(name))
-            .first(conn)
+            .order_by(permission::name.asc())
+            .load(conn)
             .await
-            .optional()
+    }
+}
+
+/// A permission declared by the app (see [`crate::App::permissions`]) or lowboy itself.
+#[derive(Clone, Copy, Debug)]
+pub struct PermissionDef {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+impl PermissionDef {
+    pub const fn new(name: &'static str, description: &'static str) -> Self {
+        Self { name, description }
+    }
+}
+
+/// The permissions lowboy itself checks for.
+pub const LOWBOY_PERMISSIONS: &[PermissionDef] = &[
+    PermissionDef::new("administer site", "Manage roles, permissions, and users."),
+    PermissionDef::new("profile site", "Capture CPU profiles of the app (debug builds only)."),
+];
+
+impl Permission {
+    /// Add the `catalog` permissions missing from the database and update changed descriptions,
+    /// warning about permissions in the database which aren't declared in the catalog.
+    pub async fn sync(catalog: &[PermissionDef], conn: &mut Connection) -> QueryResult<()> {
+        let existing = Self::list(conn).await?;
+
+        for def in catalog {
+            match existing.iter().find(|permission| permission.name == def.name) {
+                Some(permission) if permission.description.as_deref() == Some(def.description) => {}
+                Some(permission) => {
+                    permission
+                        .update_record()
+                        .with_description(Some(def.description))
+                        .save(conn)
+                        .await?;
+                }
+                None => {
+                    info!("adding permission `{}`", def.name);
+                    Self::create_record(def.name)
+                        .with_description(def.description)
+                        .save(conn)
+                        .await?;
+                }
+            }
+        }
+
+        for permission in existing {
+            if !catalog.iter().any(|def| def.name == permission.name) {
+                warn!(
+                    "permission `{}` isn't declared by the app, it may be left over from a removed feature",
+                    permission.name
+                );
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -43,6 +105,8 @@ impl Model for Permission {
     type FromClause = permission_from_clause;
     type Query = Select<Self::FromClause, Self::SelectClause>;
 
+    const TABLE_NAME: &'static str = "permission";
+
     fn query() -> Self::Query {
         Self::from_clause().select(Self::select_clause())
     }
@@ -84,6 +148,7 @@ impl From<PermissionRecord> for Permission {
         Self {
             id: value.id,
             name: value.name,
+            description: value.description,
         }
     }
 }
@@ -95,6 +160,7 @@ impl From<PermissionRecord> for Permission {
 pub struct PermissionRecord {
     pub id: i32,
     pub name: String,
+    pub description: Option<String>,
 }
 
 impl PermissionRecord {
@@ -123,6 +189,7 @@ impl From<Permission> for PermissionRecord {
         Self {
             id: value.id,
             name: value.name,
+            description: value.description,
         }
     }
 }
@@ -132,12 +199,23 @@ impl From<Permission> for PermissionRecord {
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct CreatePermissionRecord<'a> {
     pub name: &'a str,
+    pub description: Option<&'a str>,
 }
 
 impl<'a> CreatePermissionRecord<'a> {
     /// Create a new `NewPermissionRecord` object
     pub fn new(name: &'a str) -> CreatePermissionRecord<'a> {
-        Self { name }
+        Self {
+            name,
+            description: None,
+        }
+    }
+
+    pub fn with_description(self, description: &'a str) -> CreatePermissionRecord<'a> {
+        Self {
+            description: Some(description),
+            ..self
+        }
     }
 
     /// Create a new `post` in the database
@@ -156,6 +234,7 @@ impl<'a> CreatePermissionRecord<'a> {
 pub struct UpdatePermissionRecord<'a> {
     pub id: i32,
     pub name: Option<&'a str>,
+    pub description: Option<Option<&'a str>>,
 }
 
 impl<'a> UpdatePermissionRecord<'a> {
@@ -170,6 +249,7 @@ impl<'a> UpdatePermissionRecord<'a> {
         Self {
             id: permission.id,
             name: Some(&permission.name),
+            description: Some(permission.description.as_deref()),
         }
     }
 
@@ -177,6 +257,7 @@ impl<'a> UpdatePermissionRecord<'a> {
         Self {
             id: record.id,
             name: Some(&record.name),
+            description: Some(record.description.as_deref()),
         }
     }
 
@@ -187,6 +268,13 @@ impl<'a> UpdatePermissionRecord<'a> {
         }
     }
 
+    pub fn with_description(self, description: Option<&'a str>) -> Self {
+        Self {
+            description: Some(description),
+            ..self
+        }
+    }
+
     pub async fn save(&self, conn: &mut Connection) -> QueryResult<PermissionRecord> {
         diesel::update(self)
             .set(self)
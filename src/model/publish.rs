@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use diesel::QueryResult;
+use serde::Serialize;
+
+use crate::form::{parse_datetime_local, FormErrors};
+use crate::model::Model;
+use crate::Connection;
+
+/// Where a [`Publish`] model is in its workflow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PublicationState {
+    Draft,
+    /// Waiting to be published at its `publish_at`.
+    Scheduled,
+    Published,
+}
+
+/// When to publish a [`Publish`] model, as chosen in a form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Publication {
+    /// Keep (or put) it back in draft.
+    Draft,
+    Now,
+    At(DateTime<Utc>),
+}
+
+impl Publication {
+    /// Read a form's `publish` field, one of `draft`, `now` or `schedule`, and when scheduling its
+    /// `publish_at` field, an `<input type="datetime-local">` in the user's `timezone`.
+    pub fn from_form(
+        publish: &str,
+        publish_at: Option<&str>,
+        timezone: Tz,
+    ) -> Result<Self, FormErrors> {
+        let (field, message) = match publish {
+            "draft" => return Ok(Self::Draft),
+            "now" => return Ok(Self::Now),
+            "schedule" => match publish_at.and_then(|at| parse_datetime_local(at, timezone)) {
+                Some(at) => return Ok(Self::At(at)),
+                None => ("publish_at", "Enter the date and time to publish at"),
+            },
+            _ => ("publish", "Choose when to publish"),
+        };
+
+        let mut errors = FormErrors::new();
+        errors.add(field, message);
+        Err(errors)
+    }
+}
+
+/// An opt-in draft/publish workflow, for models which are written before they're shown, e.g. blog
+/// posts, with publishing scheduled for later.
+///
+/// Publishable models have nullable `published_at` and `publish_at` timestamp columns. A model
+/// without either is a draft, one with only a `publish_at` is scheduled, and one with a
+/// `published_at` is published. Marking them `#[lowboy(published_at)]` and
+/// `#[lowboy(publish_at)]` in `lowboy_record!` implements this trait for the model, with `Publish`
+/// in scope. Scheduled models are published by the [`crate::jobs::PUBLISH_SCHEDULED`] job once
+/// they're due, when they're registered in [`crate::App::publishables`]:
+///
+/// ```ignore
+/// #[apply(lowboy_record!)]
+/// #[derive(Debug, Default, Queryable, Identifiable, Selectable, Insertable)]
+/// #[diesel(table_name = crate::schema::post)]
+/// pub struct Post {
+///     id: i32,
+///     content: String,
+///     #[lowboy(published_at)]
+///     published_at: Option<DateTime<Utc>>,
+///     #[lowboy(publish_at)]
+///     publish_at: Option<DateTime<Utc>>,
+/// }
+///
+/// let publication =
+///     Publication::from_form(&input.publish, input.publish_at.as_deref(), timezone)?;
+/// Post::set_publication(post.id, publication, now, &mut conn).await?;
+/// ```
+///
+/// Leave unpublished models out of the public queries, e.g. with
+/// `post::published_at.is_not_null()` in a query used by the public pages.
+#[async_trait::async_trait]
+pub trait Publish: Model {
+    /// When the model was published, if it was.
+    fn published_at(&self) -> Option<DateTime<Utc>>;
+
+    /// When the model is scheduled to be published, if it is.
+    fn publish_at(&self) -> Option<DateTime<Utc>>;
+
+    fn publication_state(&self) -> PublicationState {
+        match (self.published_at(), self.publish_at()) {
+            (Some(_), _) => PublicationState::Published,
+            (None, Some(_)) => PublicationState::Scheduled,
+            (None, None) => PublicationState::Draft,
+        }
+    }
+
+    /// Publish the model with `id` now, returning how many rows were published.
+    async fn publish(id: i32, now: DateTime<Utc>, conn: &mut Connection) -> QueryResult<usize>;
+
+    /// Schedule the model with `id` to be published at `publish_at`, unpublishing it if it's
+    /// published, returning how many rows were scheduled.
+    async fn schedule(
+        id: i32,
+        publish_at: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<usize>;
+
+    /// Put the model with `id` back in draft, returning how many rows were unpublished.
+    async fn unpublish(id: i32, conn: &mut Connection) -> QueryResult<usize>;
+
+    /// Apply the `publication` chosen for the model with `id`. Times which have already passed
+    /// publish it now.
+    async fn set_publication(
+        id: i32,
+        publication: Publication,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<usize> {
+        match publication {
+            Publication::Draft => Self::unpublish(id, conn).await,
+            Publication::At(at) if at > now => Self::schedule(id, at, conn).await,
+            Publication::Now | Publication::At(_) => Self::publish(id, now, conn).await,
+        }
+    }
+
+    /// Publish the scheduled models which are due, as of when they were scheduled for, returning
+    /// their ids.
+    async fn publish_due(now: DateTime<Utc>, conn: &mut Connection) -> QueryResult<Vec<i32>>;
+}
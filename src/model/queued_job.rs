@@ -0,0 +1,302 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+
+use crate::model::Model;
+use crate::schema::queued_job;
+use crate::Connection;
+
+/// A background job waiting to run, see [`crate::jobs`].
+#[derive(Clone, Debug, Serialize)]
+pub struct QueuedJob {
+    pub id: i32,
+    /// The name the job is registered as, see [`crate::jobs::Job::NAME`].
+    pub job: String,
+    /// The job, serialized to JSON.
+    pub payload: String,
+    /// Only one job with the same key is queued at a time, see [`crate::jobs::Job::unique_key`].
+    pub unique_key: Option<String>,
+    pub run_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    /// How many times the job was started.
+    pub attempts: i32,
+    /// Why the job failed, the last time it was tried.
+    pub last_error: Option<String>,
+    /// A worker is running the job until then, see [`QueuedJob::claim`].
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl QueuedJob {
+    /// Queue `job` to run at `run_at`, unless a job with the same `unique_key` is already queued,
+    /// in which case `None` is returned.
+    pub async fn enqueue(
+        job: &str,
+        payload: &str,
+        unique_key: Option<&str>,
+        run_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Option<Self>> {
+        let mut record = Self::create_record(job, payload, run_at, now);
+        record.unique_key = unique_key;
+
+        // Inserting and checking the key is a single statement, so concurrent enqueues of the same
+        // unique job can't both succeed.
+        Ok(diesel::insert_into(queued_job::table)
+            .values(record)
+            .on_conflict(queued_job::unique_key)
+            .do_nothing()
+            .returning(QueuedJobRecord::as_returning())
+            .get_result(conn)
+            .await
+            .optional()?
+            .map(Self::from))
+    }
+
+    /// Jobs due to run at `now` which no worker is running, the longest overdue first.
+    pub async fn due(
+        now: DateTime<Utc>,
+        limit: i64,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .filter(queued_job::run_at.le(now))
+            .filter(
+                queued_job::locked_until
+                    .is_null()
+                    .or(queued_job::locked_until.le(now)),
+            )
+            .order_by((queued_job::run_at.asc(), queued_job::id.asc()))
+            .limit(limit)
+            .load(conn)
+            .await
+    }
+
+    /// Queued jobs, the next to run first.
+    pub async fn list(conn: &mut Connection, limit: Option<i64>) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .limit(limit.unwrap_or(100))
+            .order_by((queued_job::run_at.asc(), queued_job::id.asc()))
+            .load(conn)
+            .await
+    }
+
+    /// Lock the job with `id` for `lease` to run it, counting the attempt, or `None` if another
+    /// worker already did. If the worker dies while running it, the job runs again once the lease
+    /// is over.
+    pub async fn claim(
+        id: i32,
+        lease: TimeDelta,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Option<Self>> {
+        let unlocked = queued_job::locked_until
+            .is_null()
+            .or(queued_job::locked_until.le(now));
+
+        Ok(diesel::update(queued_job::table.find(id).filter(unlocked))
+            .set((
+                queued_job::locked_until.eq(now + lease),
+                queued_job::attempts.eq(queued_job::attempts + 1),
+            ))
+            .returning(QueuedJobRecord::as_returning())
+            .get_result(conn)
+            .await
+            .optional()?
+            .map(Self::from))
+    }
+
+    /// Unlock the job to run again at `run_at`, after failing with `error`.
+    pub async fn reschedule(
+        &self,
+        run_at: DateTime<Utc>,
+        error: &str,
+        conn: &mut Connection,
+    ) -> QueryResult<usize> {
+        diesel::update(queued_job::table.find(self.id))
+            .set((
+                queued_job::run_at.eq(run_at),
+                queued_job::last_error.eq(error),
+                queued_job::locked_until.eq(None::<DateTime<Utc>>),
+            ))
+            .execute(conn)
+            .await
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn queued_job_from_clause() -> _ {
+    queued_job::table
+}
+
+#[diesel::dsl::auto_type]
+fn queued_job_select_clause() -> _ {
+    let as_select: AsSelect<QueuedJobRecord, Sqlite> = QueuedJobRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for QueuedJob {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = queued_job_select_clause;
+    type FromClause = queued_job_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "queued_job";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        queued_job_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        queued_job_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query()
+            .filter(queued_job::id.eq(id))
+            .first(conn)
+            .await
+    }
+}
+
+impl Selectable<Sqlite> for QueuedJob {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<QueuedJob as Model>::RowSqlType, Sqlite> for QueuedJob {
+    type Row = (QueuedJobRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<QueuedJobRecord> for QueuedJob {
+    fn from(value: QueuedJobRecord) -> Self {
+        Self {
+            id: value.id,
+            job: value.job,
+            payload: value.payload,
+            unique_key: value.unique_key,
+            run_at: value.run_at,
+            created_at: value.created_at,
+            attempts: value.attempts,
+            last_error: value.last_error,
+            locked_until: value.locked_until,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::queued_job)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct QueuedJobRecord {
+    pub id: i32,
+    pub job: String,
+    pub payload: String,
+    pub unique_key: Option<String>,
+    pub run_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl QueuedJobRecord {
+    pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<QueuedJobRecord> {
+        queued_job::table.find(id).get_result(conn).await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(queued_job::table.find(self.id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `QueuedJob` model into `QueuedJobRecord`
+impl From<QueuedJob> for QueuedJobRecord {
+    fn from(value: QueuedJob) -> Self {
+        Self {
+            id: value.id,
+            job: value.job,
+            payload: value.payload,
+            unique_key: value.unique_key,
+            run_at: value.run_at,
+            created_at: value.created_at,
+            attempts: value.attempts,
+            last_error: value.last_error,
+            locked_until: value.locked_until,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::queued_job)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CreateQueuedJobRecord<'a> {
+    pub job: &'a str,
+    pub payload: &'a str,
+    pub unique_key: Option<&'a str>,
+    pub run_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<'a> CreateQueuedJobRecord<'a> {
+    /// Create a new `CreateQueuedJobRecord` object
+    pub fn new(
+        job: &'a str,
+        payload: &'a str,
+        run_at: DateTime<Utc>,
+        created_at: DateTime<Utc>,
+    ) -> CreateQueuedJobRecord<'a> {
+        Self {
+            job,
+            payload,
+            unique_key: None,
+            run_at,
+            created_at,
+        }
+    }
+
+    /// Create a new `queued_job` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<QueuedJobRecord> {
+        diesel::insert_into(crate::schema::queued_job::table)
+            .values(self)
+            .returning(crate::schema::queued_job::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+}
+
+impl QueuedJob {
+    pub fn create_record<'a>(
+        job: &'a str,
+        payload: &'a str,
+        run_at: DateTime<Utc>,
+        created_at: DateTime<Utc>,
+    ) -> CreateQueuedJobRecord<'a> {
+        CreateQueuedJobRecord::new(job, payload, run_at, created_at)
+    }
+
+    pub async fn read_record(id: i32, conn: &mut Connection) -> QueryResult<QueuedJobRecord> {
+        QueuedJobRecord::read(id, conn).await
+    }
+
+    pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+        QueuedJobRecord::from(self).delete(conn).await
+    }
+}
@@ -1,27 +1,49 @@
 use diesel::dsl::{AsSelect, Select, SqlTypeOf};
 use diesel::prelude::*;
 use diesel::sqlite::Sqlite;
-use diesel::{OptionalExtension, QueryResult, Selectable};
+use diesel::sql_types::{Bool, Integer};
+use diesel::{sql_query, QueryResult, Selectable};
 use diesel_async::RunQueryDsl;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::model::Model;
-use crate::schema::{role, user_role};
+use crate::model::{find_by, Model, Permission, PermissionRecord, UserRecord};
+use crate::schema::{permission, role, role_hierarchy, role_permission, user, user_role};
 use crate::Connection;
 
-#[derive(Clone, Debug, Deserialize, Hash, Eq, PartialEq)]
+#[derive(Debug, thiserror::Error)]
+pub enum RoleError {
+    #[error("Role `{parent}` can't inherit `{child}`, as `{child}` already inherits `{parent}`")]
+    Cycle { parent: String, child: String },
+
+    #[error("Role `{0}` is a system role and can't be deleted")]
+    System(String),
+
+    #[error(transparent)]
+    Query(#[from] diesel::result::Error),
+}
+
+#[derive(QueryableByName)]
+struct Includes {
+    #[diesel(sql_type = Bool)]
+    includes: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Hash, Eq, PartialEq)]
 pub struct Role {
     pub id: i32,
     pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// System roles are relied on by lowboy itself, so they can't be deleted.
+    #[serde(default)]
+    pub system: bool,
 }
 
+find_by!(Role, role::name: &str);
+
 impl Role {
-    pub async fn find_by_name(name: &str, conn: &mut Connection) -> QueryResult<Option<Self>> {
-        Self::query()
-            .filter(role::name.eq(name))
-            .first(conn)
-            .await
-            .optional()
+    pub async fn list(conn: &mut Connection) -> QueryResult<Vec<Self>> {
+        Self::query().order_by(role::name.asc()).load(conn).await
     }
 
     pub async fn assign(&self, user_id: i32, conn: &mut Connection) -> QueryResult<usize> {
@@ -48,6 +70,151 @@ impl Role {
         .execute(conn)
         .await
     }
+
+    /// Assign this role to each of `user_ids`, skipping users who already have it.
+    pub async fn assign_many(&self, user_ids: &[i32], conn: &mut Connection) -> QueryResult<usize> {
+        let values: Vec<_> = user_ids
+            .iter()
+            .map(|user_id| {
+                (
+                    user_role::user_id.eq(*user_id),
+                    user_role::role_id.eq(self.id),
+                )
+            })
+            .collect();
+
+        diesel::insert_into(user_role::table)
+            .values(values)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await
+    }
+
+    /// The users directly assigned this role.
+    pub async fn users(&self, conn: &mut Connection) -> QueryResult<Vec<UserRecord>> {
+        user::table
+            .inner_join(user_role::table)
+            .filter(user_role::role_id.eq(self.id))
+            .order_by(user::username.asc())
+            .select(UserRecord::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// The permissions granted directly to this role.
+    pub async fn permissions(&self, conn: &mut Connection) -> QueryResult<Vec<Permission>> {
+        Ok(permission::table
+            .inner_join(role_permission::table)
+            .filter(role_permission::role_id.eq(self.id))
+            .order_by(permission::name.asc())
+            .select(PermissionRecord::as_select())
+            .load(conn)
+            .await?
+            .into_iter()
+            .map(Permission::from)
+            .collect())
+    }
+
+    pub async fn attach(&self, permission: &Permission, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::insert_into(role_permission::table)
+            .values((
+                role_permission::role_id.eq(self.id),
+                role_permission::permission_id.eq(permission.id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await
+    }
+
+    pub async fn detach(&self, permission: &Permission, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(
+            role_permission::table
+                .filter(role_permission::role_id.eq(self.id))
+                .filter(role_permission::permission_id.eq(permission.id)),
+        )
+        .execute(conn)
+        .await
+    }
+
+    /// Delete the role along with its assignments, permissions, and place in the hierarchy.
+    pub async fn remove(self, conn: &mut Connection) -> Result<usize, RoleError> {
+        if self.system {
+            return Err(RoleError::System(self.name));
+        }
+
+        diesel::delete(user_role::table.filter(user_role::role_id.eq(self.id)))
+            .execute(conn)
+            .await?;
+        diesel::delete(role_permission::table.filter(role_permission::role_id.eq(self.id)))
+            .execute(conn)
+            .await?;
+        diesel::delete(
+            role_hierarchy::table.filter(
+                role_hierarchy::parent_id
+                    .eq(self.id)
+                    .or(role_hierarchy::child_id.eq(self.id)),
+            ),
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(self.delete_record(conn).await?)
+    }
+
+    /// Whether this role is, or (transitively) inherits, the role `other_id`.
+    pub async fn includes(&self, other_id: i32, conn: &mut Connection) -> QueryResult<bool> {
+        let query = r#"
+            WITH RECURSIVE included(id) AS (
+                SELECT ?
+                UNION
+                SELECT role_hierarchy.child_id
+                FROM role_hierarchy
+                INNER JOIN included ON role_hierarchy.parent_id = included.id
+            )
+            SELECT EXISTS (SELECT 1 FROM included WHERE id = ?) AS includes
+            "#;
+
+        sql_query(query)
+            .bind::<Integer, _>(self.id)
+            .bind::<Integer, _>(other_id)
+            .get_result::<Includes>(conn)
+            .await
+            .map(|row| row.includes)
+    }
+
+    /// Make this role inherit `child`, granting users with this role `child` and all of its
+    /// permissions, e.g. administrator inherits moderator, which inherits authenticated.
+    pub async fn inherit(
+        &self,
+        child: &Role,
+        conn: &mut Connection,
+    ) -> Result<usize, RoleError> {
+        if child.includes(self.id, conn).await? {
+            return Err(RoleError::Cycle {
+                parent: self.name.clone(),
+                child: child.name.clone(),
+            });
+        }
+
+        Ok(diesel::insert_into(role_hierarchy::table)
+            .values((
+                role_hierarchy::parent_id.eq(self.id),
+                role_hierarchy::child_id.eq(child.id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await?)
+    }
+
+    pub async fn disinherit(&self, child: &Role, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(
+            role_hierarchy::table
+                .filter(role_hierarchy::parent_id.eq(self.id))
+                .filter(role_hierarchy::child_id.eq(child.id)),
+        )
+        .execute(conn)
+        .await
+    }
 }
 
 #[diesel::dsl::auto_type]
@@ -69,6 +236,8 @@ impl Model for Role {
     type FromClause = role_from_clause;
     type Query = Select<Self::FromClause, Self::SelectClause>;
 
+    const TABLE_NAME: &'static str = "role";
+
     fn query() -> Self::Query {
         Self::from_clause().select(Self::select_clause())
     }
@@ -106,6 +275,8 @@ impl From<RoleRecord> for Role {
         Self {
             id: value.id,
             name: value.name,
+            description: value.description,
+            system: value.system,
         }
     }
 }
@@ -117,6 +288,8 @@ impl From<RoleRecord> for Role {
 pub struct RoleRecord {
     pub id: i32,
     pub name: String,
+    pub description: Option<String>,
+    pub system: bool,
 }
 
 impl RoleRecord {
@@ -145,6 +318,8 @@ impl From<Role> for RoleRecord {
         Self {
             id: value.id,
             name: value.name,
+            description: value.description,
+            system: value.system,
         }
     }
 }
@@ -154,12 +329,29 @@ impl From<Role> for RoleRecord {
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct CreateRoleRecord<'a> {
     pub name: &'a str,
+    pub description: Option<&'a str>,
+    pub system: bool,
 }
 
 impl<'a> CreateRoleRecord<'a> {
     /// Create a new `NewRoleRecord` object
     pub fn new(name: &'a str) -> CreateRoleRecord<'a> {
-        Self { name }
+        Self {
+            name,
+            description: None,
+            system: false,
+        }
+    }
+
+    pub fn with_description(self, description: &'a str) -> CreateRoleRecord<'a> {
+        Self {
+            description: Some(description),
+            ..self
+        }
+    }
+
+    pub fn with_system(self, system: bool) -> CreateRoleRecord<'a> {
+        Self { system, ..self }
     }
 
     /// Create a new `post` in the database
@@ -178,6 +370,7 @@ impl<'a> CreateRoleRecord<'a> {
 pub struct UpdateRoleRecord<'a> {
     pub id: i32,
     pub name: Option<&'a str>,
+    pub description: Option<Option<&'a str>>,
 }
 
 impl<'a> UpdateRoleRecord<'a> {
@@ -192,6 +385,7 @@ impl<'a> UpdateRoleRecord<'a> {
         Self {
             id: permission.id,
             name: Some(&permission.name),
+            description: Some(permission.description.as_deref()),
         }
     }
 
@@ -199,6 +393,7 @@ impl<'a> UpdateRoleRecord<'a> {
         Self {
             id: record.id,
             name: Some(&record.name),
+            description: Some(record.description.as_deref()),
         }
     }
 
@@ -209,6 +404,13 @@ impl<'a> UpdateRoleRecord<'a> {
         }
     }
 
+    pub fn with_description(self, description: Option<&'a str>) -> Self {
+        Self {
+            description: Some(description),
+            ..self
+        }
+    }
+
     pub async fn save(&self, conn: &mut Connection) -> QueryResult<RoleRecord> {
         diesel::update(self)
             .set(self)
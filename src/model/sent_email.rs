@@ -0,0 +1,365 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel_async::RunQueryDsl;
+
+use crate::model::Model;
+use crate::schema::sent_email;
+use crate::Connection;
+
+crate::db_enum! {
+    /// Where an email is in its delivery.
+    pub enum SentEmailStatus {
+        /// Handed to the mailer, which hasn't finished sending it.
+        Queued = "queued",
+        Sent = "sent",
+        Failed = "failed",
+        /// Not sent, because the recipient is suppressed (see [`crate::model::EmailSuppression`]).
+        Suppressed = "suppressed",
+        /// Sent, but the provider reported it bounced.
+        Bounced = "bounced",
+        /// Sent, but the recipient reported it as spam.
+        Complained = "complained",
+    }
+}
+
+/// A record of an email lowboy sent, or tried to, for answering questions like "did the
+/// verification email send?".
+#[derive(Clone, Debug)]
+pub struct SentEmail {
+    pub id: i32,
+    /// The recipient's address, e.g. `user@example.com`.
+    pub recipient: String,
+    /// The mail template the email was rendered from, e.g. `verify_email`.
+    pub template: Option<String>,
+    pub subject: String,
+    pub status: SentEmailStatus,
+    /// The id the transport gave the message, e.g. the SMTP relay's queue id.
+    pub provider_message_id: Option<String>,
+    /// Why sending the email failed.
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SentEmail {
+    /// Record that an email is about to be sent.
+    pub async fn queue(
+        recipient: &str,
+        template: Option<&str>,
+        subject: &str,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Self> {
+        let mut record = Self::create_record(recipient, subject, SentEmailStatus::Queued, now);
+        record.template = template;
+
+        Ok(record.save(conn).await?.into())
+    }
+
+    /// Record an email that wasn't sent because `recipient` is suppressed for `reason`.
+    pub async fn suppressed(
+        recipient: &str,
+        template: Option<&str>,
+        subject: &str,
+        reason: &str,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Self> {
+        let mut record = Self::create_record(recipient, subject, SentEmailStatus::Suppressed, now);
+        record.template = template;
+        record.error = Some(reason);
+
+        Ok(record.save(conn).await?.into())
+    }
+
+    /// Record that the email was sent, with the transport's id for it.
+    pub async fn mark_sent(
+        &mut self,
+        provider_message_id: Option<&str>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<()> {
+        diesel::update(sent_email::table.find(self.id))
+            .set((
+                sent_email::status.eq(SentEmailStatus::Sent),
+                sent_email::provider_message_id.eq(provider_message_id),
+                sent_email::updated_at.eq(now),
+            ))
+            .execute(conn)
+            .await?;
+
+        self.status = SentEmailStatus::Sent;
+        self.provider_message_id = provider_message_id.map(str::to_string);
+        self.updated_at = now;
+
+        Ok(())
+    }
+
+    /// Record that sending the email failed with `error`.
+    pub async fn mark_failed(
+        &mut self,
+        error: &str,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<()> {
+        diesel::update(sent_email::table.find(self.id))
+            .set((
+                sent_email::status.eq(SentEmailStatus::Failed),
+                sent_email::error.eq(error),
+                sent_email::updated_at.eq(now),
+            ))
+            .execute(conn)
+            .await?;
+
+        self.status = SentEmailStatus::Failed;
+        self.error = Some(error.to_string());
+        self.updated_at = now;
+
+        Ok(())
+    }
+
+    /// Record that the email the provider knows as `provider_message_id` ended up with `status`,
+    /// e.g. [`SentEmailStatus::Bounced`], returning how many emails were updated.
+    pub async fn mark_by_provider_message_id(
+        provider_message_id: &str,
+        status: SentEmailStatus,
+        error: Option<&str>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<usize> {
+        diesel::update(
+            sent_email::table.filter(sent_email::provider_message_id.eq(provider_message_id)),
+        )
+        .set((
+            sent_email::status.eq(status),
+            sent_email::error.eq(error),
+            sent_email::updated_at.eq(now),
+        ))
+        .execute(conn)
+        .await
+    }
+
+    /// Emails sent to `recipient`, most recent first.
+    pub async fn list_for_recipient(
+        recipient: &str,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .filter(sent_email::recipient.eq(recipient))
+            .order_by(sent_email::id.desc())
+            .load(conn)
+            .await
+    }
+
+    /// The most recent email rendered from `template` sent to `recipient`, e.g. their latest
+    /// `verify_email`.
+    pub async fn latest(
+        recipient: &str,
+        template: &str,
+        conn: &mut Connection,
+    ) -> QueryResult<Option<Self>> {
+        Self::query()
+            .filter(sent_email::recipient.eq(recipient))
+            .filter(sent_email::template.eq(template))
+            .order_by(sent_email::id.desc())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// Emails with `status`, most recent first, e.g. to find the ones that failed.
+    pub async fn list_by_status(
+        status: SentEmailStatus,
+        conn: &mut Connection,
+        limit: Option<i64>,
+    ) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .filter(sent_email::status.eq(status))
+            .limit(limit.unwrap_or(100))
+            .order_by(sent_email::id.desc())
+            .load(conn)
+            .await
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn sent_email_from_clause() -> _ {
+    sent_email::table
+}
+
+#[diesel::dsl::auto_type]
+fn sent_email_select_clause() -> _ {
+    let as_select: AsSelect<SentEmailRecord, Sqlite> = SentEmailRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for SentEmail {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = sent_email_select_clause;
+    type FromClause = sent_email_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "sent_email";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        sent_email_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        sent_email_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query()
+            .filter(sent_email::id.eq(id))
+            .first(conn)
+            .await
+    }
+}
+
+impl Selectable<Sqlite> for SentEmail {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<SentEmail as Model>::RowSqlType, Sqlite> for SentEmail {
+    type Row = (SentEmailRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<SentEmailRecord> for SentEmail {
+    fn from(value: SentEmailRecord) -> Self {
+        Self {
+            id: value.id,
+            recipient: value.recipient,
+            template: value.template,
+            subject: value.subject,
+            status: value.status,
+            provider_message_id: value.provider_message_id,
+            error: value.error,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::sent_email)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SentEmailRecord {
+    pub id: i32,
+    pub recipient: String,
+    pub template: Option<String>,
+    pub subject: String,
+    pub status: SentEmailStatus,
+    pub provider_message_id: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SentEmailRecord {
+    pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<SentEmailRecord> {
+        sent_email::table.find(id).get_result(conn).await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(sent_email::table.find(self.id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `SentEmail` model into `SentEmailRecord`
+impl From<SentEmail> for SentEmailRecord {
+    fn from(value: SentEmail) -> Self {
+        Self {
+            id: value.id,
+            recipient: value.recipient,
+            template: value.template,
+            subject: value.subject,
+            status: value.status,
+            provider_message_id: value.provider_message_id,
+            error: value.error,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::sent_email)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CreateSentEmailRecord<'a> {
+    pub recipient: &'a str,
+    pub template: Option<&'a str>,
+    pub subject: &'a str,
+    pub status: SentEmailStatus,
+    pub provider_message_id: Option<&'a str>,
+    pub error: Option<&'a str>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl<'a> CreateSentEmailRecord<'a> {
+    /// Create a new `CreateSentEmailRecord` object
+    pub fn new(
+        recipient: &'a str,
+        subject: &'a str,
+        status: SentEmailStatus,
+        created_at: DateTime<Utc>,
+    ) -> CreateSentEmailRecord<'a> {
+        Self {
+            recipient,
+            template: None,
+            subject,
+            status,
+            provider_message_id: None,
+            error: None,
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    /// Create a new `sent_email` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<SentEmailRecord> {
+        diesel::insert_into(crate::schema::sent_email::table)
+            .values(self)
+            .returning(crate::schema::sent_email::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+}
+
+impl SentEmail {
+    pub fn create_record<'a>(
+        recipient: &'a str,
+        subject: &'a str,
+        status: SentEmailStatus,
+        created_at: DateTime<Utc>,
+    ) -> CreateSentEmailRecord<'a> {
+        CreateSentEmailRecord::new(recipient, subject, status, created_at)
+    }
+
+    pub async fn read_record(id: i32, conn: &mut Connection) -> QueryResult<SentEmailRecord> {
+        SentEmailRecord::read(id, conn).await
+    }
+
+    pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+        SentEmailRecord::from(self).delete(conn).await
+    }
+}
@@ -0,0 +1,210 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel::upsert::excluded;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+
+use crate::model::Model;
+use crate::schema::setting;
+use crate::Connection;
+
+/// A site-wide setting, stored as JSON. See [`crate::settings`] for the typed API.
+#[derive(Clone, Debug, Serialize)]
+pub struct Setting {
+    pub id: i32,
+    pub key: String,
+    /// The setting's value, as JSON.
+    pub value: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Setting {
+    pub async fn find(key: &str, conn: &mut Connection) -> QueryResult<Option<Self>> {
+        Self::query()
+            .filter(setting::key.eq(key))
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// Every stored setting, by key.
+    pub async fn list(conn: &mut Connection) -> QueryResult<Vec<Self>> {
+        Self::query().order_by(setting::key.asc()).load(conn).await
+    }
+
+    /// Set the setting `key` to the JSON `value`, replacing the previous value.
+    pub async fn set(
+        key: &str,
+        value: &str,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Self> {
+        Ok(diesel::insert_into(setting::table)
+            .values(Self::create_record(key, value, now))
+            .on_conflict(setting::key)
+            .do_update()
+            .set((
+                setting::value.eq(excluded(setting::value)),
+                setting::updated_at.eq(excluded(setting::updated_at)),
+            ))
+            .returning(SettingRecord::as_returning())
+            .get_result(conn)
+            .await?
+            .into())
+    }
+
+    /// Remove the setting `key`, reverting it to the default.
+    pub async fn unset(key: &str, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(setting::table.filter(setting::key.eq(key)))
+            .execute(conn)
+            .await
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn setting_from_clause() -> _ {
+    setting::table
+}
+
+#[diesel::dsl::auto_type]
+fn setting_select_clause() -> _ {
+    let as_select: AsSelect<SettingRecord, Sqlite> = SettingRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for Setting {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = setting_select_clause;
+    type FromClause = setting_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "setting";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        setting_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        setting_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query().filter(setting::id.eq(id)).first(conn).await
+    }
+}
+
+impl Selectable<Sqlite> for Setting {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<Setting as Model>::RowSqlType, Sqlite> for Setting {
+    type Row = (SettingRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<SettingRecord> for Setting {
+    fn from(value: SettingRecord) -> Self {
+        Self {
+            id: value.id,
+            key: value.key,
+            value: value.value,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::setting)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SettingRecord {
+    pub id: i32,
+    pub key: String,
+    pub value: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SettingRecord {
+    pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<SettingRecord> {
+        setting::table.find(id).get_result(conn).await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(setting::table.find(self.id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `Setting` model into `SettingRecord`
+impl From<Setting> for SettingRecord {
+    fn from(value: Setting) -> Self {
+        Self {
+            id: value.id,
+            key: value.key,
+            value: value.value,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::setting)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CreateSettingRecord<'a> {
+    pub key: &'a str,
+    pub value: &'a str,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl<'a> CreateSettingRecord<'a> {
+    /// Create a new `CreateSettingRecord` object
+    pub fn new(key: &'a str, value: &'a str, updated_at: DateTime<Utc>) -> CreateSettingRecord<'a> {
+        Self {
+            key,
+            value,
+            updated_at,
+        }
+    }
+
+    /// Create a new `setting` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<SettingRecord> {
+        diesel::insert_into(crate::schema::setting::table)
+            .values(self)
+            .returning(crate::schema::setting::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+}
+
+impl Setting {
+    pub fn create_record<'a>(
+        key: &'a str,
+        value: &'a str,
+        updated_at: DateTime<Utc>,
+    ) -> CreateSettingRecord<'a> {
+        CreateSettingRecord::new(key, value, updated_at)
+    }
+
+    pub async fn read_record(id: i32, conn: &mut Connection) -> QueryResult<SettingRecord> {
+        SettingRecord::read(id, conn).await
+    }
+
+    pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+        SettingRecord::from(self).delete(conn).await
+    }
+}
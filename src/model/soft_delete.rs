@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use diesel::query_builder::SelectQuery;
+use diesel::QueryResult;
+
+use crate::model::Model;
+use crate::Connection;
+
+/// Opt-in soft deletion, for models whose rows can't be removed, e.g. user content that has to be
+/// kept for compliance.
+///
+/// Soft deleted models have a nullable timestamp column, usually `deleted_at`, which is set
+/// instead of removing the row. Marking it `#[lowboy(soft_delete)]` in `lowboy_record!`
+/// implements this trait for the model, with `SoftDelete` in scope, and makes the model's
+/// `delete_record` trash it rather than delete it. [`Model::query`] should leave trashed rows out,
+/// and [`SoftDelete::with_trashed`] and [`SoftDelete::only_trashed`] bring them back:
+///
+/// ```ignore
+/// #[apply(lowboy_record!)]
+/// #[derive(Debug, Default, Queryable, Identifiable, Selectable, Insertable)]
+/// #[diesel(table_name = crate::schema::post)]
+/// pub struct Post {
+///     id: i32,
+///     content: String,
+///     #[lowboy(soft_delete)]
+///     deleted_at: Option<DateTime<Utc>>,
+/// }
+///
+/// #[diesel::dsl::auto_type]
+/// fn post_from_clause() -> _ {
+///     post::table.filter(post::deleted_at.is_null())
+/// }
+///
+/// post.delete_record(now, &mut conn).await?;
+///
+/// let records: Vec<PostRecord> = Post::only_trashed()
+///     .filter(post::content.like("%draft%"))
+///     .load(&mut conn)
+///     .await?;
+/// ```
+///
+/// Register soft deleted models in [`crate::App::trash`] to list them in the admin's trash, where
+/// they can be restored until they're purged, see [`crate::trash`].
+#[async_trait::async_trait]
+pub trait SoftDelete: Model {
+    /// Every row, trashed or not, selecting the model's record.
+    type WithTrashed: SelectQuery;
+
+    /// The trashed rows, selecting the model's record.
+    type OnlyTrashed: SelectQuery;
+
+    /// Query the model's rows, trashed or not, to be filtered further.
+    fn with_trashed() -> Self::WithTrashed;
+
+    /// Query the model's trashed rows, to be filtered further.
+    fn only_trashed() -> Self::OnlyTrashed;
+
+    /// When the model was trashed, if it was.
+    fn deleted_at(&self) -> Option<DateTime<Utc>>;
+
+    fn is_trashed(&self) -> bool {
+        self.deleted_at().is_some()
+    }
+
+    /// Load the model with `id`, trashed or not.
+    async fn load_with_trashed(id: i32, conn: &mut Connection) -> QueryResult<Self>
+    where
+        Self: Sized;
+
+    /// The trashed models.
+    async fn trashed(conn: &mut Connection) -> QueryResult<Vec<Self>>
+    where
+        Self: Sized;
+
+    /// Trash the model with `id`, returning how many rows were trashed. Trashed models stay
+    /// trashed from when they were first trashed.
+    async fn trash(id: i32, now: DateTime<Utc>, conn: &mut Connection) -> QueryResult<usize>;
+
+    /// Restore the trashed model with `id`, returning how many rows were restored.
+    async fn restore(id: i32, conn: &mut Connection) -> QueryResult<usize>;
+
+    /// Restore the model with `id` if it was trashed after `since`, returning how many rows were
+    /// restored.
+    async fn restore_since(
+        id: i32,
+        since: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<usize>;
+
+    /// Remove the models trashed at or before `trashed_before` for good, returning their ids.
+    async fn purge_trashed(
+        trashed_before: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<i32>>;
+
+    /// Remove the model with `id` for good if it's trashed, returning how many rows were removed.
+    async fn delete_trashed(id: i32, conn: &mut Connection) -> QueryResult<usize>;
+
+    /// Remove the model with `id`, trashed or not, for good.
+    async fn force_delete(id: i32, conn: &mut Connection) -> QueryResult<usize>;
+}
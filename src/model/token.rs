@@ -5,22 +5,37 @@ use diesel::prelude::*;
 use diesel::sqlite::Sqlite;
 use diesel_async::RunQueryDsl;
 
+use crate::clock::Clock;
 use crate::model::{Model, UserRecord};
 use crate::schema::token;
 use crate::Connection;
 
+crate::db_enum! {
+    /// What a token was issued for, so it can't be used for anything else.
+    #[derive(Default)]
+    pub enum TokenPurpose {
+        #[default]
+        EmailVerification = "email_verification",
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Token {
     pub id: i32,
     pub user_id: i32,
     pub secret: String,
     pub expiration: DateTime<Utc>,
+    pub purpose: TokenPurpose,
 }
 
 impl Token {
     pub fn verify(&self, token: &str) -> bool {
         constant_time_eq(self.secret.as_bytes(), token.as_bytes())
     }
+
+    pub fn is_expired(&self, clock: &dyn Clock) -> bool {
+        self.expiration <= clock.now()
+    }
 }
 
 #[diesel::dsl::auto_type]
@@ -41,6 +56,8 @@ impl Model for Token {
     type FromClause = token_from_clause;
     type Query = Select<Self::FromClause, Self::SelectClause>;
 
+    const TABLE_NAME: &'static str = "token";
+
     fn query() -> Self::Query {
         Self::from_clause().select(Self::select_clause())
     }
@@ -81,6 +98,7 @@ impl Queryable<<Token as Model>::RowSqlType, Sqlite> for Token {
             user_id: record.user_id,
             secret: record.secret,
             expiration: record.expiration,
+            purpose: record.purpose,
         })
     }
 }
@@ -95,11 +113,17 @@ pub struct TokenRecord {
     pub user_id: i32,
     pub secret: String,
     pub expiration: DateTime<Utc>,
+    pub purpose: TokenPurpose,
 }
 
 impl TokenRecord {
-    pub fn create(user_id: i32, secret: &str, expiration: DateTime<Utc>) -> CreateTokenRecord {
-        CreateTokenRecord::new(user_id, secret, expiration)
+    pub fn create(
+        user_id: i32,
+        purpose: TokenPurpose,
+        secret: &str,
+        expiration: DateTime<Utc>,
+    ) -> CreateTokenRecord {
+        CreateTokenRecord::new(user_id, purpose, secret, expiration)
     }
 
     pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<TokenRecord> {
@@ -121,6 +145,7 @@ impl From<Token> for TokenRecord {
             user_id: value.user_id,
             secret: value.secret,
             expiration: value.expiration,
+            purpose: value.purpose,
         }
     }
 }
@@ -132,6 +157,7 @@ impl From<TokenRecord> for Token {
             user_id: value.user_id,
             secret: value.secret,
             expiration: value.expiration,
+            purpose: value.purpose,
         }
     }
 }
@@ -143,15 +169,22 @@ pub struct CreateTokenRecord<'a> {
     pub user_id: i32,
     pub secret: &'a str,
     pub expiration: DateTime<Utc>,
+    pub purpose: TokenPurpose,
 }
 
 impl<'a> CreateTokenRecord<'a> {
     /// Create a new `NewTokenRecord` object
-    pub fn new(user_id: i32, secret: &'a str, expiration: DateTime<Utc>) -> CreateTokenRecord<'a> {
+    pub fn new(
+        user_id: i32,
+        purpose: TokenPurpose,
+        secret: &'a str,
+        expiration: DateTime<Utc>,
+    ) -> CreateTokenRecord<'a> {
         Self {
             user_id,
             secret,
             expiration,
+            purpose,
         }
     }
 
@@ -168,10 +201,11 @@ impl<'a> CreateTokenRecord<'a> {
 impl Token {
     pub fn create_record(
         user_id: i32,
+        purpose: TokenPurpose,
         secret: &str,
         expiration: DateTime<Utc>,
     ) -> CreateTokenRecord {
-        CreateTokenRecord::new(user_id, secret, expiration)
+        CreateTokenRecord::new(user_id, purpose, secret, expiration)
     }
 
     pub async fn read_record(id: i32, conn: &mut Connection) -> QueryResult<TokenRecord> {
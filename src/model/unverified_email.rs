@@ -1,16 +1,19 @@
-use chrono::{Duration, Utc};
-use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use chrono::Duration;
+use diesel::dsl::{self, AsSelect, Select, SqlTypeOf};
 use diesel::prelude::*;
 use diesel::sqlite::Sqlite;
 use diesel::QueryResult;
 use diesel_async::scoped_futures::ScopedFutureExt;
 use diesel_async::{AsyncConnection, RunQueryDsl};
-use uuid::Uuid;
 
+use crate::clock::Clock;
+use crate::database::SavepointExt;
 use crate::model::{
-    CreateTokenRecord, Email, EmailRecord, Model, Token, TokenRecord, UpdateEmailRecord,
+    find_by, CreateTokenRecord, Email, EmailRecord, Model, Token, TokenPurpose, TokenRecord,
+    UpdateEmailRecord,
 };
 use crate::schema::{email, token};
+use crate::secret::SecretGenerator;
 use crate::Connection;
 
 use super::Role;
@@ -25,6 +28,9 @@ pub enum Error {
     #[error("There was an error verifying the token")]
     TokenVerification,
 
+    #[error("The verification token has expired")]
+    TokenExpired,
+
     #[error(transparent)]
     VerificationQuery(#[from] diesel::result::Error),
 }
@@ -37,11 +43,20 @@ pub struct UnverifiedEmail {
     pub token: Token,
 }
 
+find_by!(UnverifiedEmail, email::address: &str);
+
 impl UnverifiedEmail {
-    pub async fn new(user_id: i32, address: &str, conn: &mut Connection) -> QueryResult<Self> {
-        let secret = &Uuid::new_v4().to_string();
-        let expiration = Utc::now() + Duration::days(1);
-        let token = TokenRecord::create(user_id, secret, expiration);
+    pub async fn new(
+        user_id: i32,
+        address: &str,
+        clock: &dyn Clock,
+        secrets: &dyn SecretGenerator,
+        conn: &mut Connection,
+    ) -> QueryResult<Self> {
+        let secret = &secrets.generate();
+        let expiration = clock.now() + Duration::days(1);
+        let token =
+            TokenRecord::create(user_id, TokenPurpose::EmailVerification, secret, expiration);
 
         Self::new_with_token(user_id, address, token, conn).await
     }
@@ -52,7 +67,7 @@ impl UnverifiedEmail {
         token: CreateTokenRecord<'a>,
         conn: &mut Connection,
     ) -> QueryResult<Self> {
-        conn.transaction(|conn| {
+        conn.savepoint(|conn| {
             async move {
                 let email = EmailRecord::create(user_id, address).save(conn).await?;
 
@@ -72,22 +87,20 @@ impl UnverifiedEmail {
     // token associated with the user.
     // do we need a join table between them? email_token? unverified_email?
     // Can fix this after.
-    pub async fn find_by_address(
-        address: &str,
+    pub async fn verify(
+        self,
+        token: &str,
+        clock: &dyn Clock,
         conn: &mut Connection,
-    ) -> QueryResult<Option<Self>> {
-        Self::query()
-            .filter(email::address.eq(address))
-            .first(conn)
-            .await
-            .optional()
-    }
-
-    pub async fn verify(self, token: &str, conn: &mut Connection) -> Result<Email> {
+    ) -> Result<Email> {
         if !self.token.verify(token) {
             return Err(Error::TokenVerification);
         }
 
+        if self.token.is_expired(clock) {
+            return Err(Error::TokenExpired);
+        }
+
         conn.transaction(|conn| {
             async move {
                 let email_record = UpdateEmailRecord::new(self.id)
@@ -119,8 +132,11 @@ impl UnverifiedEmail {
 
 #[diesel::dsl::auto_type]
 fn unverified_email_from_clause() -> _ {
+    let email_verification: dsl::Eq<token::purpose, TokenPurpose> =
+        token::purpose.eq(TokenPurpose::EmailVerification);
+
     email::table
-        .inner_join(token::table.on(token::user_id.eq(email::user_id)))
+        .inner_join(token::table.on(token::user_id.eq(email::user_id).and(email_verification)))
         .filter(email::verified.eq(false))
 }
 
@@ -139,6 +155,9 @@ impl Model for UnverifiedEmail {
     type FromClause = unverified_email_from_clause;
     type Query = Select<Self::FromClause, Self::SelectClause>;
 
+    // Its rows are in `email`, but it isn't an `Email`.
+    const TABLE_NAME: &'static str = "unverified_email";
+
     // @TODO we never check token expiration
     fn query() -> Self::Query {
         Self::from_clause().select(Self::select_clause())
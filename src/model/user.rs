@@ -4,18 +4,27 @@ use axum_login::AuthUser;
 use derive_masked::DebugMasked;
 use diesel::dsl::{AsSelect, Select, SqlTypeOf};
 use diesel::prelude::*;
+use diesel::sql_types::{Integer, Text};
 use diesel::sqlite::Sqlite;
-use diesel::{OptionalExtension, QueryResult, Selectable};
+use diesel::{sql_query, OptionalExtension, QueryResult, Selectable};
 use diesel_async::scoped_futures::ScopedFutureExt;
 use diesel_async::{AsyncConnection, RunQueryDsl};
 use gravatar_api::avatars as gravatars;
 use tracing::info;
 
-use crate::model::{json_group_array, permission_record_json, role_record_json};
-use crate::schema::{email, permission, role, role_permission, user, user_role};
+use crate::clock::Clock;
+use crate::database::SavepointExt;
+use crate::form::UniqueConstraint;
+use crate::schema::{
+    draft, email, known_device, notification_preferences, token, user, user_preference, user_role,
+};
+use crate::secret::SecretGenerator;
 use crate::Connection;
 
-use super::{Email, Model, Permission, Role, UnverifiedEmail};
+use super::{Email, Model, Permission, Role, UnverifiedEmail, UpdateEmailRecord};
+
+/// Service accounts are given an address on this domain, which is reserved so it never resolves.
+pub const SERVICE_ACCOUNT_DOMAIN: &str = "service-account.invalid";
 
 #[derive(Clone, Debug)]
 pub struct User {
@@ -24,6 +33,9 @@ pub struct User {
     pub email: Email,
     pub password: Option<String>,
     pub access_token: Option<String>,
+    /// Service accounts are used by automation. They have no password and authenticate with their
+    /// API token (the access token) instead, see [`User::new_service_account`].
+    pub service_account: bool,
     pub roles: Option<HashSet<Role>>,
     pub permissions: Option<HashSet<Permission>>,
 }
@@ -34,19 +46,22 @@ impl User {
         email: &str,
         password: Option<&str>,
         access_token: Option<&str>,
+        clock: &dyn Clock,
+        secrets: &dyn SecretGenerator,
         conn: &mut Connection,
     ) -> QueryResult<Self> {
-        conn.transaction(|conn| {
+        conn.savepoint(|conn| {
             async move {
                 let user = CreateUserRecord {
                     username,
                     password,
                     access_token,
+                    service_account: false,
                 }
                 .save(conn)
                 .await?;
 
-                UnverifiedEmail::new(user.id, email, conn).await?;
+                UnverifiedEmail::new(user.id, email, clock, secrets, conn).await?;
 
                 Role::find_by_name("unverified", conn)
                     .await?
@@ -61,6 +76,182 @@ impl User {
         .await
     }
 
+    /// Prepare the statements used to load users and their roles and permissions, so they're
+    /// cached on the connection.
+    pub(crate) async fn warm_up(conn: &mut Connection) {
+        let _ = <Self as Model>::load(0, conn).await;
+
+        let mut user = Self {
+            id: 0,
+            username: String::new(),
+            email: Email {
+                id: 0,
+                user_id: 0,
+                address: String::new(),
+                verified: false,
+            },
+            password: None,
+            access_token: None,
+            service_account: false,
+            roles: None,
+            permissions: None,
+        };
+        let _ = user.with_roles_and_permissions(conn).await;
+    }
+
+    /// Create a service account, returning it along with its API token.
+    ///
+    /// Service accounts can't log in with a password or OAuth, and don't need their email verified.
+    /// They're given an address on the reserved `.invalid` domain so they still have one, but
+    /// nothing is ever sent to it.
+    pub async fn new_service_account(
+        username: &str,
+        secrets: &dyn SecretGenerator,
+        conn: &mut Connection,
+    ) -> QueryResult<(Self, String)> {
+        let token = secrets.generate();
+
+        let user = conn
+            .transaction(|conn| {
+                let token = &token;
+                async move {
+                    let user = CreateUserRecord::new(username)
+                        .with_access_token(token)
+                        .with_service_account(true)
+                        .save(conn)
+                        .await?;
+
+                    let address = format!("{username}@{SERVICE_ACCOUNT_DOMAIN}");
+                    let email = Email::create_record(user.id, &address).save(conn).await?;
+                    UpdateEmailRecord::from_record(&email)
+                        .with_verified(true)
+                        .save(conn)
+                        .await?;
+
+                    Role::find_by_name("authenticated", conn)
+                        .await?
+                        .expect("authenticated role should exist")
+                        .assign(user.id, conn)
+                        .await?;
+
+                    <Self as Model>::load(user.id, conn).await
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        Ok((user, token))
+    }
+
+    /// Delete a user along with the rows lowboy created for them, e.g. to undo a registration that
+    /// couldn't be completed. Rows the app created for the user must be deleted first.
+    pub async fn discard(self, conn: &mut Connection) -> QueryResult<()> {
+        conn.savepoint(|conn| {
+            async move {
+                let id = self.id;
+
+                diesel::delete(token::table.filter(token::user_id.eq(id)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(email::table.filter(email::user_id.eq(id)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(user_role::table.filter(user_role::user_id.eq(id)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(
+                    notification_preferences::table
+                        .filter(notification_preferences::user_id.eq(id)),
+                )
+                .execute(conn)
+                .await?;
+                diesel::delete(known_device::table.filter(known_device::user_id.eq(id)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(user_preference::table.filter(user_preference::user_id.eq(id)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(draft::table.filter(draft::user_id.eq(id)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(digest_opt_out::table.filter(digest_opt_out::user_id.eq(id)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(user::table.find(id)).execute(conn).await?;
+
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    /// Replace the service account's API token, returning the new one.
+    pub async fn rotate_api_token(
+        &mut self,
+        secrets: &dyn SecretGenerator,
+        conn: &mut Connection,
+    ) -> QueryResult<String> {
+        let token = secrets.generate();
+        self.update_record()
+            .with_access_token(&token)
+            .save(conn)
+            .await?;
+        self.access_token = Some(token.clone());
+
+        Ok(token)
+    }
+
+    /// Find the service account with the API `token`.
+    pub async fn find_service_account_by_token(
+        token: &str,
+        conn: &mut Connection,
+    ) -> QueryResult<Option<Self>> {
+        Self::query()
+            .filter(user::service_account.eq(true))
+            .filter(user::access_token.eq(token))
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    pub async fn list_service_accounts(conn: &mut Connection) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .filter(user::service_account.eq(true))
+            .order(user::username.asc())
+            .load(conn)
+            .await
+    }
+
+    /// The ids of the users with any of `usernames`, ignoring unknown usernames.
+    pub async fn find_ids_by_username(
+        usernames: &[&str],
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<i32>> {
+        user::table
+            .filter(user::username.eq_any(usernames))
+            .select(user::id)
+            .load(conn)
+            .await
+    }
+
+    /// Up to `limit` users with a verified email, ordered by id and starting after `after_id`, for
+    /// emailing every user in batches. Service accounts are skipped.
+    pub async fn list_verified_after(
+        after_id: i32,
+        limit: i64,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<Self>> {
+        Self::query()
+            .filter(user::id.gt(after_id))
+            .filter(user::service_account.eq(false))
+            .filter(email::verified.eq(true))
+            .order(user::id.asc())
+            .limit(limit)
+            .load(conn)
+            .await
+    }
+
     pub async fn find_by_username_having_password(
         username: &str,
         conn: &mut Connection,
@@ -68,12 +259,21 @@ impl User {
         Self::query()
             .filter(user::username.eq(username))
             .filter(user::password.is_not_null())
+            .filter(user::service_account.eq(false))
             .first(conn)
             .await
             .optional()
     }
 }
 
+#[derive(QueryableByName)]
+struct RolesAndPermissions {
+    #[diesel(sql_type = Text)]
+    roles: String,
+    #[diesel(sql_type = Text)]
+    permissions: String,
+}
+
 #[async_trait::async_trait]
 pub trait UserModel: Model
 where
@@ -100,31 +300,48 @@ where
 
     async fn find_by_username(username: &str, conn: &mut Connection) -> QueryResult<Option<Self>>;
 
+    /// Load the user's roles and permissions, including those of the roles their roles inherit.
     async fn with_roles_and_permissions(
         &mut self,
         conn: &mut Connection,
     ) -> QueryResult<&mut Self> {
-        allow_columns_to_appear_in_same_group_by_clause!(
-            permission::id,
-            permission::name,
-            role::id,
-            role::name,
-        );
-
-        let (roles, permissions) = user_role::table
-            .inner_join(role::table.left_join(role_permission::table.left_join(permission::table)))
-            .filter(user_role::user_id.eq(self.id()))
-            .group_by((role::id, role::name, permission::id, permission::name))
-            .select((
-                json_group_array(role_record_json("id", role::id, "name", role::name)),
-                json_group_array(permission_record_json(
-                    "id",
-                    permission::id.nullable(),
-                    "name",
-                    permission::name.nullable(),
-                )),
-            ))
-            .first::<(String, String)>(conn)
+        let query = r#"
+            WITH RECURSIVE effective_role(id) AS (
+                SELECT role_id FROM user_role WHERE user_id = ?
+                UNION
+                SELECT role_hierarchy.child_id
+                FROM role_hierarchy
+                INNER JOIN effective_role ON role_hierarchy.parent_id = effective_role.id
+            )
+            SELECT
+                (
+                    SELECT json_group_array(json_object(
+                        'id', role.id,
+                        'name', role.name,
+                        'description', role.description,
+                        'system', json(iif(role.system, 'true', 'false'))
+                    ))
+                    FROM role
+                    WHERE role.id IN (SELECT id FROM effective_role)
+                ) AS roles,
+                (
+                    SELECT json_group_array(json_object(
+                        'id', permission.id,
+                        'name', permission.name,
+                        'description', permission.description
+                    ))
+                    FROM permission
+                    WHERE permission.id IN (
+                        SELECT permission_id
+                        FROM role_permission
+                        WHERE role_id IN (SELECT id FROM effective_role)
+                    )
+                ) AS permissions
+            "#;
+
+        let RolesAndPermissions { roles, permissions } = sql_query(query)
+            .bind::<Integer, _>(self.id())
+            .get_result(conn)
             .await?;
 
         self.set_roles(serde_json::from_str(&roles).unwrap_or_default())
@@ -133,6 +350,7 @@ where
         Ok(self)
     }
 
+    /// Whether the user has `role`, either directly or through the role hierarchy.
     fn has_role(&self, role: &str) -> bool {
         if self.roles().is_none() {
             info!("attempted to check for role `{role}` on user `{user_id}` before calling UserModel::with_roles_and_permissions()", user_id = self.id());
@@ -205,11 +423,13 @@ impl UserModel for User {
     }
 }
 
+#[doc(hidden)]
 #[diesel::dsl::auto_type]
 pub fn user_from_clause() -> _ {
     user::table.inner_join(email::table)
 }
 
+#[doc(hidden)]
 #[diesel::dsl::auto_type]
 pub fn user_select_clause() -> _ {
     let user_as_select: AsSelect<UserRecord, Sqlite> = UserRecord::as_select();
@@ -225,6 +445,13 @@ impl Model for User {
     type FromClause = user_from_clause;
     type Query = Select<Self::FromClause, Self::SelectClause>;
 
+    const TABLE_NAME: &'static str = "user";
+    const UNIQUE_CONSTRAINTS: &'static [UniqueConstraint] = &[UniqueConstraint::new(
+        &["username"],
+        "username",
+        "That username is already taken",
+    )];
+
     fn query() -> Self::Query {
         Self::from_clause().select(Self::select_clause())
     }
@@ -262,6 +489,7 @@ impl Queryable<<User as Model>::RowSqlType, Sqlite> for User {
             email,
             password: user_record.password,
             access_token: user_record.access_token,
+            service_account: user_record.service_account,
             roles: None,
             permissions: None,
         })
@@ -297,6 +525,7 @@ pub struct UserRecord {
     pub username: String,
     pub password: Option<String>,
     pub access_token: Option<String>,
+    pub service_account: bool,
 }
 
 impl UserRecord {
@@ -327,6 +556,7 @@ impl From<User> for UserRecord {
             username: value.username,
             password: value.password,
             access_token: value.access_token,
+            service_account: value.service_account,
         }
     }
 }
@@ -338,6 +568,7 @@ pub struct CreateUserRecord<'a> {
     pub username: &'a str,
     pub password: Option<&'a str>,
     pub access_token: Option<&'a str>,
+    pub service_account: bool,
 }
 
 impl<'a> CreateUserRecord<'a> {
@@ -362,6 +593,13 @@ impl<'a> CreateUserRecord<'a> {
         }
     }
 
+    pub fn with_service_account(self, service_account: bool) -> CreateUserRecord<'a> {
+        Self {
+            service_account,
+            ..self
+        }
+    }
+
     pub async fn save(&self, conn: &mut Connection) -> QueryResult<UserRecord> {
         diesel::insert_into(crate::schema::user::table)
             .values(self)
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono_tz::Tz;
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel::upsert::excluded;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+
+use crate::model::Model;
+use crate::schema::user_preference;
+use crate::Connection;
+
+pub const THEME_PREFERENCE: &str = "theme";
+pub const LOCALE_PREFERENCE: &str = "locale";
+pub const TIMEZONE_PREFERENCE: &str = "timezone";
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Serialize,
+    PartialEq,
+    Eq,
+    strum::Display,
+    strum::EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Theme {
+    Light,
+    Dark,
+    /// Follow the operating system's preference.
+    #[default]
+    System,
+}
+
+/// A single preference of a user, stored as text.
+#[derive(Clone, Debug)]
+pub struct UserPreference {
+    pub id: i32,
+    pub user_id: i32,
+    pub key: String,
+    pub value: String,
+}
+
+impl UserPreference {
+    /// Set `user_id`'s preference `key` to `value`, replacing the previous value.
+    pub async fn set(
+        user_id: i32,
+        key: &str,
+        value: &str,
+        conn: &mut Connection,
+    ) -> QueryResult<Self> {
+        Ok(diesel::insert_into(user_preference::table)
+            .values(Self::create_record(user_id, key, value))
+            .on_conflict((user_preference::user_id, user_preference::key))
+            .do_update()
+            .set(user_preference::value.eq(excluded(user_preference::value)))
+            .returning(UserPreferenceRecord::as_returning())
+            .get_result(conn)
+            .await?
+            .into())
+    }
+
+    /// Remove `user_id`'s preference `key`, reverting it to the default.
+    pub async fn unset(user_id: i32, key: &str, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(
+            user_preference::table
+                .filter(user_preference::user_id.eq(user_id))
+                .filter(user_preference::key.eq(key)),
+        )
+        .execute(conn)
+        .await
+    }
+}
+
+/// All of a user's preferences, with typed accessors for the ones lowboy knows about.
+///
+/// Apps can store their own preferences with [`UserPreference::set`] and read them with
+/// [`Preferences::get`].
+#[derive(Clone, Debug, Default)]
+pub struct Preferences(HashMap<String, String>);
+
+impl Preferences {
+    pub async fn for_user(user_id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        let preferences = UserPreference::query()
+            .filter(user_preference::user_id.eq(user_id))
+            .load(conn)
+            .await?
+            .into_iter()
+            .map(|preference| (preference.key, preference.value))
+            .collect();
+
+        Ok(Self(preferences))
+    }
+
+    pub fn raw(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// The preference `key` parsed as `T`. Values that no longer parse are ignored.
+    pub fn get<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.raw(key).and_then(|value| value.parse().ok())
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.get(THEME_PREFERENCE).unwrap_or_default()
+    }
+
+    /// The user's locale, e.g. `en-US`, if they chose one.
+    pub fn locale(&self) -> Option<&str> {
+        self.raw(LOCALE_PREFERENCE)
+    }
+
+    /// The user's timezone, UTC unless they chose one.
+    pub fn timezone(&self) -> Tz {
+        self.get(TIMEZONE_PREFERENCE).unwrap_or(Tz::UTC)
+    }
+}
+
+#[diesel::dsl::auto_type]
+fn user_preference_from_clause() -> _ {
+    user_preference::table
+}
+
+#[diesel::dsl::auto_type]
+fn user_preference_select_clause() -> _ {
+    let as_select: AsSelect<UserPreferenceRecord, Sqlite> = UserPreferenceRecord::as_select();
+    (as_select,)
+}
+
+#[async_trait::async_trait]
+impl Model for UserPreference {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = user_preference_select_clause;
+    type FromClause = user_preference_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = "user_preference";
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        user_preference_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        user_preference_select_clause()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        Self::query()
+            .filter(user_preference::id.eq(id))
+            .first(conn)
+            .await
+    }
+}
+
+impl Selectable<Sqlite> for UserPreference {
+    type SelectExpression = <Self as Model>::SelectClause;
+
+    fn construct_selection() -> Self::SelectExpression {
+        Self::select_clause()
+    }
+}
+
+impl Queryable<<UserPreference as Model>::RowSqlType, Sqlite> for UserPreference {
+    type Row = (UserPreferenceRecord,);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(row.0.into())
+    }
+}
+
+impl From<UserPreferenceRecord> for UserPreference {
+    fn from(value: UserPreferenceRecord) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            key: value.key,
+            value: value.value,
+        }
+    }
+}
+
+// @note the rest of this file is to eventually be generated using lowboy_record!
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate::schema::user_preference)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct UserPreferenceRecord {
+    pub id: i32,
+    pub user_id: i32,
+    pub key: String,
+    pub value: String,
+}
+
+impl UserPreferenceRecord {
+    pub async fn read(id: i32, conn: &mut Connection) -> QueryResult<UserPreferenceRecord> {
+        user_preference::table.find(id).get_result(conn).await
+    }
+
+    pub async fn delete(&self, conn: &mut Connection) -> QueryResult<usize> {
+        diesel::delete(user_preference::table.find(self.id))
+            .execute(conn)
+            .await
+    }
+}
+
+/// Convert from a `UserPreference` model into `UserPreferenceRecord`
+impl From<UserPreference> for UserPreferenceRecord {
+    fn from(value: UserPreference) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            key: value.key,
+            value: value.value,
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::user_preference)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CreateUserPreferenceRecord<'a> {
+    pub user_id: i32,
+    pub key: &'a str,
+    pub value: &'a str,
+}
+
+impl<'a> CreateUserPreferenceRecord<'a> {
+    /// Create a new `CreateUserPreferenceRecord` object
+    pub fn new(user_id: i32, key: &'a str, value: &'a str) -> CreateUserPreferenceRecord<'a> {
+        Self {
+            user_id,
+            key,
+            value,
+        }
+    }
+
+    /// Create a new `user_preference` in the database
+    pub async fn save(&self, conn: &mut Connection) -> QueryResult<UserPreferenceRecord> {
+        diesel::insert_into(crate::schema::user_preference::table)
+            .values(self)
+            .returning(crate::schema::user_preference::table::all_columns())
+            .get_result(conn)
+            .await
+    }
+}
+
+impl UserPreference {
+    pub fn create_record<'a>(
+        user_id: i32,
+        key: &'a str,
+        value: &'a str,
+    ) -> CreateUserPreferenceRecord<'a> {
+        CreateUserPreferenceRecord::new(user_id, key, value)
+    }
+
+    pub async fn read_record(id: i32, conn: &mut Connection) -> QueryResult<UserPreferenceRecord> {
+        UserPreferenceRecord::read(id, conn).await
+    }
+
+    pub async fn delete_record(self, conn: &mut Connection) -> QueryResult<usize> {
+        UserPreferenceRecord::from(self).delete(conn).await
+    }
+}
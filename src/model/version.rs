@@ -0,0 +1,458 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::actor::RequestActor;
+use crate::model::Model;
+use crate::schema::model_version;
+use crate::Connection;
+
+crate::db_enum! {
+    /// Why a [`Version`] of a model was recorded.
+    pub enum VersionAction {
+        /// The model was about to be updated.
+        Update = "update",
+        /// The model was about to be deleted.
+        Delete = "delete",
+        /// The model was about to be restored to an earlier version.
+        Restore = "restore",
+    }
+}
+
+/// A snapshot of a [`Versioned`] model, as it was before it was changed.
+#[derive(Clone, Debug, Queryable, Selectable, Serialize)]
+#[diesel(table_name = model_version)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Version {
+    pub id: i32,
+    /// The table of the model the version is of, e.g. `post`.
+    pub model: String,
+    /// The id of the model the version is of.
+    pub record_id: i32,
+    pub action: VersionAction,
+    /// The model serialized as a JSON object.
+    pub data: String,
+    /// The user who made the change, if it was made by one.
+    pub user_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Version {
+    /// The version's snapshot, or `null` if it isn't valid JSON.
+    pub fn value(&self) -> Value {
+        serde_json::from_str(&self.data).unwrap_or_default()
+    }
+}
+
+/// A field which differs between two versions of a model, see [`diff`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    /// The field's value before the change, `null` if it was added.
+    pub before: Value,
+    /// The field's value after the change, `null` if it was removed.
+    pub after: Value,
+}
+
+/// The top-level fields which differ between `before` and `after`, in field order. Values which
+/// aren't objects are compared as a whole, as the field `""`.
+pub fn diff(before: &Value, after: &Value) -> Vec<FieldChange> {
+    let (Value::Object(before), Value::Object(after)) = (before, after) else {
+        if before == after {
+            return vec![];
+        }
+
+        return vec![FieldChange {
+            field: String::new(),
+            before: before.clone(),
+            after: after.clone(),
+        }];
+    };
+
+    let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let was = before.get(field).unwrap_or(&Value::Null);
+            let is = after.get(field).unwrap_or(&Value::Null);
+
+            (was != is).then(|| FieldChange {
+                field: field.clone(),
+                before: was.clone(),
+                after: is.clone(),
+            })
+        })
+        .collect()
+}
+
+fn serialization_error(e: serde_json::Error) -> diesel::result::Error {
+    diesel::result::Error::SerializationError(Box::new(e))
+}
+
+/// The user making the change, who's the real user when an administrator is impersonating
+/// someone.
+fn actor_user_id() -> Option<i32> {
+    RequestActor::current().and_then(|actor| actor.real_user_id.or(actor.effective_user_id))
+}
+
+/// Opt-in record history, for models whose changes should be auditable and undoable, e.g. posts
+/// or anything edited in the admin.
+///
+/// Marking a model `#[lowboy(versioned)]` in `lowboy_record!` implements this trait for it, with
+/// `Versioned` in scope. Its generated `update_record` and `delete_record` then call
+/// [`Versioned::before_update`] and [`Versioned::before_delete`] in the same transaction as the
+/// change, which snapshot the model as JSON, so the version is only kept if the change is:
+///
+/// ```ignore
+/// #[apply(lowboy_record!)]
+/// #[lowboy(versioned)]
+/// #[derive(Debug, Default, Queryable, Identifiable, Selectable, Insertable)]
+/// #[diesel(table_name = crate::schema::post)]
+/// pub struct Post {
+///     id: i32,
+///     title: String,
+/// }
+///
+/// post.title = input.title;
+/// post.update_record(now, &mut conn).await?;
+/// ```
+///
+/// Every model's versions are kept in the `model_version` table, along with the model's table
+/// name, rather than in a table for each model, so versioning a model doesn't need a migration.
+/// Versions are restored by deserializing the snapshot and writing it back with
+/// [`Versioned::write_version`], which recreates the row if it was deleted.
+///
+/// Register versioned models in [`crate::App::versioned_models`] to prune their old versions, see
+/// [`crate::versioning`].
+#[async_trait::async_trait]
+pub trait Versioned: Model + Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// Snapshot the model with `id` as it is now, returning the new version's id, or `None` if
+    /// there's no such model.
+    async fn snapshot(
+        id: i32,
+        action: VersionAction,
+        user_id: Option<i32>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Option<i32>> {
+        let Some(model) = Self::load(id, conn).await.optional()? else {
+            return Ok(None);
+        };
+        let data = serde_json::to_string(&model).map_err(serialization_error)?;
+
+        diesel::insert_into(model_version::table)
+            .values((
+                model_version::model.eq(Self::TABLE_NAME),
+                model_version::record_id.eq(id),
+                model_version::action.eq(action),
+                model_version::data.eq(data),
+                model_version::user_id.eq(user_id),
+                model_version::created_at.eq(now),
+            ))
+            .returning(model_version::id)
+            .get_result(conn)
+            .await
+            .map(Some)
+    }
+
+    /// Snapshot the model with `id` before it's updated, on behalf of the [current
+    /// actor](RequestActor::current)'s user. Called by `lowboy_record!`'s `update_record`.
+    async fn before_update(
+        id: i32,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Option<i32>> {
+        Self::snapshot(id, VersionAction::Update, actor_user_id(), now, conn).await
+    }
+
+    /// Snapshot the model with `id` before it's deleted, on behalf of the [current
+    /// actor](RequestActor::current)'s user. Called by `lowboy_record!`'s `delete_record`.
+    async fn before_delete(
+        id: i32,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Option<i32>> {
+        Self::snapshot(id, VersionAction::Delete, actor_user_id(), now, conn).await
+    }
+
+    /// Write `model`, deserialized from a version's snapshot, back to its row, recreating the row
+    /// if the model was deleted, and return it as saved.
+    async fn write_version(model: Self, conn: &mut Connection) -> QueryResult<Self>;
+
+    /// The versions of the model with `id`, latest first.
+    async fn versions(id: i32, conn: &mut Connection) -> QueryResult<Vec<Version>> {
+        model_version::table
+            .filter(model_version::model.eq(Self::TABLE_NAME))
+            .filter(model_version::record_id.eq(id))
+            .order(model_version::id.desc())
+            .select(Version::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// The version `version_id`, of any model with this type.
+    async fn version(version_id: i32, conn: &mut Connection) -> QueryResult<Version> {
+        model_version::table
+            .filter(model_version::model.eq(Self::TABLE_NAME))
+            .filter(model_version::id.eq(version_id))
+            .select(Version::as_select())
+            .get_result(conn)
+            .await
+    }
+
+    /// What changed after the version `version_id`: the differences between it and the next
+    /// version of the model, or the model as it is now if it's the latest. Fields of a deleted
+    /// model are compared with `null`.
+    async fn changes(version_id: i32, conn: &mut Connection) -> QueryResult<Vec<FieldChange>> {
+        let version = Self::version(version_id, conn).await?;
+
+        let next: Option<Version> = model_version::table
+            .filter(model_version::model.eq(Self::TABLE_NAME))
+            .filter(model_version::record_id.eq(version.record_id))
+            .filter(model_version::id.gt(version.id))
+            .order(model_version::id)
+            .select(Version::as_select())
+            .first(conn)
+            .await
+            .optional()?;
+
+        let after = match next {
+            Some(next) => next.value(),
+            None => match Self::load(version.record_id, conn).await.optional()? {
+                Some(model) => serde_json::to_value(&model).map_err(serialization_error)?,
+                None => Value::Object(Default::default()),
+            },
+        };
+
+        Ok(diff(&version.value(), &after))
+    }
+
+    /// Restore the model to the version `version_id` on behalf of `user_id`, snapshotting it
+    /// first so the restore can be undone, and return it as restored.
+    async fn restore_version(
+        version_id: i32,
+        user_id: Option<i32>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Self> {
+        let version = Self::version(version_id, conn).await?;
+        let model: Self = serde_json::from_str(&version.data)
+            .map_err(|e| diesel::result::Error::DeserializationError(Box::new(e)))?;
+
+        Self::snapshot(version.record_id, VersionAction::Restore, user_id, now, conn).await?;
+        Self::write_version(model, conn).await
+    }
+
+    /// Delete the versions older than `max_age`, and all but the latest `max_versions` of each
+    /// model, returning how many were deleted.
+    async fn prune_versions(
+        max_versions: Option<i64>,
+        max_age: Option<TimeDelta>,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<usize> {
+        let mut pruned = 0;
+
+        if let Some(max_age) = max_age {
+            pruned += diesel::delete(
+                model_version::table
+                    .filter(model_version::model.eq(Self::TABLE_NAME))
+                    .filter(model_version::created_at.lt(now - max_age)),
+            )
+            .execute(conn)
+            .await?;
+        }
+
+        if let Some(max_versions) = max_versions {
+            let versions: Vec<(i32, i32)> = model_version::table
+                .filter(model_version::model.eq(Self::TABLE_NAME))
+                .order((model_version::record_id, model_version::id.desc()))
+                .select((model_version::record_id, model_version::id))
+                .load(conn)
+                .await?;
+
+            let keep = usize::try_from(max_versions).unwrap_or(0);
+            let expired: Vec<i32> = versions
+                .chunk_by(|a, b| a.0 == b.0)
+                .flat_map(|versions| versions.iter().skip(keep).map(|&(_, id)| id))
+                .collect();
+
+            // Kept under SQLite's limit on bound parameters.
+            for ids in expired.chunks(500) {
+                pruned += diesel::delete(model_version::table.filter(model_version::id.eq_any(ids)))
+                    .execute(conn)
+                    .await?;
+            }
+        }
+
+        Ok(pruned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::model::Setting;
+
+    /// A setting, with the versioning it doesn't have in lowboy.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    struct Tracked {
+        id: i32,
+        key: String,
+        value: String,
+        updated_at: DateTime<Utc>,
+    }
+
+    impl From<Setting> for Tracked {
+        fn from(setting: Setting) -> Self {
+            Self {
+                id: setting.id,
+                key: setting.key,
+                value: setting.value,
+                updated_at: setting.updated_at,
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Model for Tracked {
+        type RowSqlType = <Setting as Model>::RowSqlType;
+        type SelectClause = <Setting as Model>::SelectClause;
+        type FromClause = <Setting as Model>::FromClause;
+        type Query = <Setting as Model>::Query;
+
+        const TABLE_NAME: &'static str = "setting";
+
+        fn query() -> Self::Query {
+            Setting::query()
+        }
+
+        fn from_clause() -> Self::FromClause {
+            Setting::from_clause()
+        }
+
+        fn select_clause() -> Self::SelectClause {
+            Setting::select_clause()
+        }
+
+        async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+            Setting::load(id, conn).await.map(Into::into)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Versioned for Tracked {
+        async fn write_version(model: Self, conn: &mut Connection) -> QueryResult<Self> {
+            Setting::set(&model.key, &model.value, model.updated_at, conn)
+                .await
+                .map(Into::into)
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    #[test]
+    fn diff_lists_changed_fields_in_order() {
+        let before = json!({ "title": "Old", "body": "Same", "tags": ["a"] });
+        let after = json!({ "title": "New", "body": "Same", "slug": "new" });
+
+        let fields: Vec<_> = diff(&before, &after)
+            .into_iter()
+            .map(|change| (change.field, change.before, change.after))
+            .collect();
+
+        assert_eq!(
+            fields,
+            vec![
+                ("slug".to_string(), Value::Null, json!("new")),
+                ("tags".to_string(), json!(["a"]), Value::Null),
+                ("title".to_string(), json!("Old"), json!("New")),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_compares_other_values_as_a_whole() {
+        assert!(diff(&json!(1), &json!(1)).is_empty());
+        assert_eq!(
+            diff(&json!(1), &json!({ "a": 1 })),
+            vec![FieldChange {
+                field: String::new(),
+                before: json!(1),
+                after: json!({ "a": 1 }),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn versions_can_be_compared_and_restored() {
+        let database = crate::test::database().await;
+        let mut conn = database.get().await.unwrap();
+        let setting = Setting::set("theme", "\"light\"", now(), &mut conn)
+            .await
+            .unwrap();
+
+        let version = Tracked::before_update(setting.id, now(), &mut conn)
+            .await
+            .unwrap();
+        let version = version.expect("the setting should be snapshotted");
+        Setting::set("theme", "\"dark\"", now(), &mut conn)
+            .await
+            .unwrap();
+
+        let versions = Tracked::versions(setting.id, &mut conn).await.unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].model, "setting");
+        assert_eq!(versions[0].action, VersionAction::Update);
+
+        let changes = Tracked::changes(version, &mut conn).await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "value");
+        assert_eq!(changes[0].before, json!("\"light\""));
+        assert_eq!(changes[0].after, json!("\"dark\""));
+
+        let restored = Tracked::restore_version(version, None, now(), &mut conn)
+            .await
+            .unwrap();
+        assert_eq!(restored.value, "\"light\"");
+        let versions = Tracked::versions(setting.id, &mut conn).await.unwrap();
+        assert_eq!(versions[0].action, VersionAction::Restore);
+    }
+
+    #[tokio::test]
+    async fn prune_keeps_the_latest_versions_of_each_model() {
+        let database = crate::test::database().await;
+        let mut conn = database.get().await.unwrap();
+        let theme = Setting::set("theme", "1", now(), &mut conn).await.unwrap();
+        let locale = Setting::set("locale", "1", now(), &mut conn).await.unwrap();
+
+        for id in [theme.id, theme.id, theme.id, locale.id] {
+            Tracked::before_update(id, now(), &mut conn).await.unwrap();
+        }
+
+        let pruned = Tracked::prune_versions(Some(2), None, now(), &mut conn)
+            .await
+            .unwrap();
+        assert_eq!(pruned, 1);
+        assert_eq!(Tracked::versions(theme.id, &mut conn).await.unwrap().len(), 2);
+        assert_eq!(Tracked::versions(locale.id, &mut conn).await.unwrap().len(), 1);
+
+        let later = now() + TimeDelta::days(2);
+        let max_age = Some(TimeDelta::days(1));
+        let pruned = Tracked::prune_versions(None, max_age, later, &mut conn)
+            .await
+            .unwrap();
+        assert_eq!(pruned, 3);
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::{Arc, LazyLock, RwLock};
+
+use tokio::sync::Semaphore;
+use tokio::task::JoinError;
+
+static PASSWORD_HASHER: LazyLock<PasswordHasher> = LazyLock::new(PasswordHasher::default);
+
+/// Hashes and verifies passwords on the blocking thread pool, a limited number at a time.
+///
+/// Hashing is deliberately slow, so doing it on the async executor would hold up every other task
+/// on the thread, and doing too much of it at once could starve the blocking pool.
+#[derive(Debug)]
+pub struct PasswordHasher {
+    permits: RwLock<Arc<Semaphore>>,
+}
+
+impl Default for PasswordHasher {
+    fn default() -> Self {
+        Self::new(std::thread::available_parallelism().map_or(1, usize::from))
+    }
+}
+
+impl PasswordHasher {
+    /// Hash at most `parallelism` passwords at once.
+    pub fn new(parallelism: usize) -> Self {
+        Self {
+            permits: RwLock::new(Arc::new(Semaphore::new(parallelism.max(1)))),
+        }
+    }
+
+    /// The hasher used by [`crate::context::Context::password_hasher`] unless the app provides
+    /// its own.
+    pub fn global() -> &'static Self {
+        &PASSWORD_HASHER
+    }
+
+    /// Hash at most `parallelism` passwords at once. Hashes already waiting keep the previous
+    /// limit.
+    pub fn set_parallelism(&self, parallelism: usize) {
+        *self.permits.write().expect("lock should not be poisoned") =
+            Arc::new(Semaphore::new(parallelism.max(1)));
+    }
+
+    pub async fn hash(
+        &self,
+        password: impl AsRef<[u8]> + Send + 'static,
+    ) -> Result<String, JoinError> {
+        self.run(move || password_auth::generate_hash(password)).await
+    }
+
+    /// Whether `password` matches `hash`.
+    pub async fn verify(
+        &self,
+        password: impl AsRef<[u8]> + Send + 'static,
+        hash: impl AsRef<str> + Send + 'static,
+    ) -> Result<bool, JoinError> {
+        self.run(move || password_auth::verify_password(password, hash.as_ref()).is_ok())
+            .await
+    }
+
+    async fn run<R, F>(&self, task: F) -> Result<R, JoinError>
+    where
+        R: Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        let permits = self
+            .permits
+            .read()
+            .expect("lock should not be poisoned")
+            .clone();
+        let _permit = permits
+            .acquire_owned()
+            .await
+            .expect("semaphore should not be closed");
+
+        tokio::task::spawn_blocking(task).await
+    }
+}
@@ -0,0 +1,34 @@
+//! The traits, types and macros apps build on, so they can be imported together rather than from
+//! lowboy's modules one by one:
+//!
+//! ```ignore
+//! use lowboy::prelude::*;
+//! ```
+//!
+//! Everything here is part of lowboy's stable API, and the `public_api` test fails if any of it is
+//! removed or renamed. Items outside the prelude are still public where apps need them, e.g.
+//! [`crate::schema`] for joins, but items only lowboy's own macros and routes use are hidden from
+//! the docs and may change without notice.
+
+pub use crate::auth::{
+    LoginForm, LowboyEmailVerificationView, LowboyLoginForm, LowboyLoginView, LowboyRegisterForm,
+    LowboyRegisterView, RegistrationDetails, RegistrationForm,
+};
+pub use crate::bulk::{BulkAction, BulkActions, BulkRequest};
+pub use crate::context::CloneableAppContext;
+pub use crate::error::{LowboyError, LowboyErrorView};
+pub use crate::extract::{
+    AppUser, DatabaseConnection, EnsureAppUser, HxRequest, LoadPath, LoadSlug,
+    TransactionalConnection, ValidatedQuery,
+};
+pub use crate::form::FormErrors;
+pub use crate::jobs::JobQueue;
+pub use crate::model::{
+    LoadBySlug, Model, ModelEvent, Owned, Paginate, Publish, SoftDelete, UserModel, Versioned,
+};
+pub use crate::view::{LayoutContext, LowboyLayout, LowboyView};
+pub use crate::{
+    authorize_owner, db_enum, login_required, lowboy_view, permission_required, view_data, App,
+    AppContext, AuthSession, Config, Connection, Context, Events, Lowboy, LowboyAuth,
+    LowboyContext, ServeOptions,
+};
@@ -0,0 +1,279 @@
+//! Which users are connected to the `/events` stream, for "who's online" features.
+//!
+//! A connection is tracked from when the stream opens until it closes. `presence` events are
+//! broadcast on the event bus when a user's first connection opens (`join`) and their last one
+//! closes (`leave`). Connections which stop being touched, e.g. because the stream was leaked, are
+//! expired by [`crate::jobs::EXPIRE_PRESENCE`].
+//!
+//! Users can only have so many connections open at once, see [`Presence::set_limits`]; opening
+//! another closes their oldest one.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard};
+use std::time::Duration;
+
+use axum::response::sse::Event;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+use tokio::sync::Notify;
+use tracing::info;
+
+use crate::events::Events;
+use crate::metrics::{CloseReason, EventMetrics};
+use crate::model::User;
+
+static PRESENCE: LazyLock<Presence> = LazyLock::new(Presence::default);
+
+/// Connections which haven't been touched for this long are expired.
+pub const STALE_AFTER: TimeDelta = TimeDelta::minutes(2);
+
+struct Entry {
+    user_id: i32,
+    username: String,
+    page: Option<String>,
+    last_seen: DateTime<Utc>,
+    closed: Arc<Notify>,
+}
+
+/// A user who's online, and the pages they have open.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct OnlineUser {
+    pub user_id: i32,
+    pub username: String,
+    /// The pages the user's connections said they're on, if any.
+    pub pages: Vec<String>,
+}
+
+/// The `presence` event broadcast when a user joins or leaves.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PresenceEvent {
+    Join { user_id: i32, username: String },
+    Leave { user_id: i32, username: String },
+}
+
+impl PresenceEvent {
+    fn to_event(&self) -> Event {
+        Event::default()
+            .event("presence")
+            .json_data(self)
+            .expect("a presence event serializes to JSON")
+    }
+}
+
+/// The connected users.
+#[derive(Clone, Default)]
+pub struct Presence {
+    next_id: Arc<AtomicU64>,
+    connections: Arc<Mutex<HashMap<u64, Entry>>>,
+    max_connections: Arc<AtomicUsize>,
+    idle_timeout_secs: Arc<AtomicU64>,
+}
+
+impl Presence {
+    /// The presence used by [`crate::Context::presence`] unless the app provides its own.
+    pub fn global() -> &'static Self {
+        &PRESENCE
+    }
+
+    /// Limit each user to `max_connections` open at once, and close connections which haven't
+    /// been sent an event for `idle_timeout`. `0` and `None` remove the limits.
+    pub fn set_limits(&self, max_connections: usize, idle_timeout: Option<Duration>) {
+        self.max_connections.store(max_connections, Ordering::Relaxed);
+        self.idle_timeout_secs.store(
+            idle_timeout.map_or(0, |timeout| timeout.as_secs()),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// How long a connection can go without being sent an event before it's closed.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        match self.idle_timeout_secs.load(Ordering::Relaxed) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    /// Track a connection of `user`, optionally on `page`, until the returned guard is dropped.
+    /// Broadcasts a join on `events` if it's the user's first connection, and closes their oldest
+    /// connections if they have too many.
+    pub fn connect(
+        &self,
+        user: &User,
+        page: Option<String>,
+        events: Events,
+        now: DateTime<Utc>,
+    ) -> PresenceGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let closed = Arc::new(Notify::new());
+        let (joined, evicted) = {
+            let mut connections = self.connections();
+            let joined = !connections.values().any(|entry| entry.user_id == user.id);
+            connections.insert(
+                id,
+                Entry {
+                    user_id: user.id,
+                    username: user.username.clone(),
+                    page,
+                    last_seen: now,
+                    closed: closed.clone(),
+                },
+            );
+            let evicted = self.evict(&mut connections, user.id);
+            (joined, evicted)
+        };
+        EventMetrics::global().open();
+
+        for entry in evicted {
+            info!("closing the oldest connection of `{}`", entry.username);
+            entry.closed.notify_one();
+            EventMetrics::global().record_closed(CloseReason::Cap);
+        }
+
+        if joined {
+            let event = PresenceEvent::Join {
+                user_id: user.id,
+                username: user.username.clone(),
+            };
+            events.try_send(event.to_event());
+        }
+
+        PresenceGuard {
+            presence: self.clone(),
+            id,
+            events,
+            closed,
+        }
+    }
+
+    /// Remove the oldest connections of the user with `user_id` over the limit.
+    fn evict(&self, connections: &mut HashMap<u64, Entry>, user_id: i32) -> Vec<Entry> {
+        let max_connections = self.max_connections.load(Ordering::Relaxed);
+        if max_connections == 0 {
+            return vec![];
+        }
+
+        let mut ids = connections
+            .iter()
+            .filter(|(_, entry)| entry.user_id == user_id)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        let excess = ids.len().saturating_sub(max_connections);
+
+        ids[..excess]
+            .iter()
+            .filter_map(|id| connections.remove(id))
+            .collect()
+    }
+
+    /// The users who are online, ordered by username.
+    pub fn online_users(&self) -> Vec<OnlineUser> {
+        let mut users: BTreeMap<&str, OnlineUser> = BTreeMap::new();
+        let connections = self.connections();
+
+        for entry in connections.values() {
+            let user = users
+                .entry(entry.username.as_str())
+                .or_insert_with(|| OnlineUser {
+                    user_id: entry.user_id,
+                    username: entry.username.clone(),
+                    pages: vec![],
+                });
+            if let Some(page) = &entry.page {
+                if !user.pages.contains(page) {
+                    user.pages.push(page.clone());
+                }
+            }
+        }
+
+        users.into_values().collect()
+    }
+
+    /// Whether the user with `user_id` is online.
+    pub fn is_online(&self, user_id: i32) -> bool {
+        self.connections()
+            .values()
+            .any(|entry| entry.user_id == user_id)
+    }
+
+    /// Expire the connections which haven't been touched since [`STALE_AFTER`] before `now`,
+    /// broadcasting a leave on `events` for each user who's no longer online.
+    pub fn expire(&self, events: &Events, now: DateTime<Utc>) -> usize {
+        let stale = {
+            let connections = self.connections();
+            connections
+                .iter()
+                .filter(|(_, entry)| now - entry.last_seen > STALE_AFTER)
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>()
+        };
+
+        for id in &stale {
+            self.disconnect(*id, events);
+        }
+
+        stale.len()
+    }
+
+    fn touch(&self, id: u64, now: DateTime<Utc>) {
+        if let Some(entry) = self.connections().get_mut(&id) {
+            entry.last_seen = now;
+        }
+    }
+
+    fn disconnect(&self, id: u64, events: &Events) {
+        let left = {
+            let mut connections = self.connections();
+            let Some(entry) = connections.remove(&id) else {
+                return;
+            };
+            let online = connections
+                .values()
+                .any(|other| other.user_id == entry.user_id);
+            (!online).then_some(entry)
+        };
+
+        if let Some(entry) = left {
+            let event = PresenceEvent::Leave {
+                user_id: entry.user_id,
+                username: entry.username,
+            };
+            events.try_send(event.to_event());
+        }
+    }
+
+    fn connections(&self) -> MutexGuard<'_, HashMap<u64, Entry>> {
+        self.connections
+            .lock()
+            .expect("presence lock should not be poisoned")
+    }
+}
+
+/// Tracks a connection while it's held, see [`Presence::connect`].
+pub struct PresenceGuard {
+    presence: Presence,
+    id: u64,
+    events: Events,
+    closed: Arc<Notify>,
+}
+
+impl PresenceGuard {
+    /// Record that the connection is still alive.
+    pub fn touch(&self, now: DateTime<Utc>) {
+        self.presence.touch(self.id, now);
+    }
+
+    /// Wait until the connection should be closed, because the user opened too many others.
+    pub async fn closed(&self) {
+        self.closed.notified().await;
+    }
+}
+
+impl Drop for PresenceGuard {
+    fn drop(&mut self) {
+        self.presence.disconnect(self.id, &self.events);
+        EventMetrics::global().close();
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Duration;
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use serde::Deserialize;
+
+use crate::clock::Clock;
+use crate::model::{Role, TokenPurpose, UserRecord};
+use crate::password::PasswordHasher;
+use crate::schema::{email, token, user, user_role};
+use crate::secret::SecretGenerator;
+use crate::Connection;
+
+/// Users are inserted in batches of this size, each batch in its own transaction.
+const BATCH_SIZE: usize = 100;
+
+/// A user to create with [`provision_users`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct NewUser {
+    pub username: String,
+    pub email: String,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Whether the email address is already known to be valid. Unverified users are given a
+    /// verification token, but aren't sent a verification email.
+    #[serde(default)]
+    pub verified: bool,
+    /// Roles to assign in addition to `authenticated`/`unverified`.
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl NewUser {
+    pub fn new(username: impl Into<String>, email: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            email: email.into(),
+            password: None,
+            verified: false,
+            roles: vec![],
+        }
+    }
+
+    pub fn with_password(self, password: impl Into<String>) -> Self {
+        Self {
+            password: Some(password.into()),
+            ..self
+        }
+    }
+
+    pub fn with_verified(self, verified: bool) -> Self {
+        Self { verified, ..self }
+    }
+
+    pub fn with_role(mut self, role: impl Into<String>) -> Self {
+        self.roles.push(role.into());
+        self
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProvisionError {
+    #[error("A user with the username `{0}` already exists")]
+    UsernameTaken(String),
+
+    #[error("A user with the email address `{0}` already exists")]
+    EmailTaken(String),
+
+    #[error("The role `{0}` doesn't exist")]
+    UnknownRole(String),
+
+    #[error("Couldn't hash the password: {0}")]
+    PasswordHash(String),
+
+    #[error("Couldn't create the user: {0}")]
+    Insert(String),
+}
+
+/// The outcome of provisioning one user.
+pub type ProvisionResult = Result<UserRecord, ProvisionError>;
+
+struct Pending {
+    index: usize,
+    username: String,
+    email: String,
+    password: Option<String>,
+    verified: bool,
+    role_ids: Vec<i32>,
+}
+
+/// Create `users` in bulk, returning a result for each of them in the same order.
+///
+/// Passwords are hashed in parallel and users are inserted in batches, so this is much faster than
+/// calling [`crate::model::User::new`] for each user. Users that can't be created (e.g. because
+/// their username is taken) are reported without affecting the rest of the batch.
+pub async fn provision_users(
+    users: Vec<NewUser>,
+    clock: &dyn Clock,
+    secrets: &dyn SecretGenerator,
+    hasher: &PasswordHasher,
+    conn: &mut Connection,
+) -> QueryResult<Vec<ProvisionResult>> {
+    let roles: HashMap<String, i32> = Role::list(conn)
+        .await?
+        .into_iter()
+        .map(|role| (role.name, role.id))
+        .collect();
+
+    let mut results: Vec<Option<ProvisionResult>> = users.iter().map(|_| None).collect();
+    let mut seen_usernames = HashSet::new();
+    let mut seen_emails = HashSet::new();
+
+    let mut users = users.into_iter().enumerate().peekable();
+    while users.peek().is_some() {
+        let batch: Vec<_> = users.by_ref().take(BATCH_SIZE).collect();
+
+        let usernames: Vec<&str> = batch.iter().map(|(_, user)| user.username.as_str()).collect();
+        let addresses: Vec<&str> = batch.iter().map(|(_, user)| user.email.as_str()).collect();
+        let taken_usernames: HashSet<String> = user::table
+            .filter(user::username.eq_any(&usernames))
+            .select(user::username)
+            .load(conn)
+            .await?
+            .into_iter()
+            .collect();
+        let taken_emails: HashSet<String> = email::table
+            .filter(email::address.eq_any(&addresses))
+            .select(email::address)
+            .load(conn)
+            .await?
+            .into_iter()
+            .collect();
+
+        let mut pending = vec![];
+        for (index, new_user) in batch {
+            if taken_usernames.contains(&new_user.username)
+                || !seen_usernames.insert(new_user.username.clone())
+            {
+                results[index] = Some(Err(ProvisionError::UsernameTaken(new_user.username)));
+                continue;
+            }
+
+            if taken_emails.contains(&new_user.email) || !seen_emails.insert(new_user.email.clone())
+            {
+                results[index] = Some(Err(ProvisionError::EmailTaken(new_user.email)));
+                continue;
+            }
+
+            let base_role = if new_user.verified {
+                "authenticated"
+            } else {
+                "unverified"
+            };
+            let role_ids = std::iter::once(base_role)
+                .chain(new_user.roles.iter().map(String::as_str))
+                .map(|name| {
+                    roles
+                        .get(name)
+                        .copied()
+                        .ok_or_else(|| ProvisionError::UnknownRole(name.to_string()))
+                })
+                .collect::<Result<HashSet<_>, _>>();
+
+            match role_ids {
+                Ok(role_ids) => pending.push(Pending {
+                    index,
+                    username: new_user.username,
+                    email: new_user.email,
+                    password: new_user.password,
+                    verified: new_user.verified,
+                    role_ids: role_ids.into_iter().collect(),
+                }),
+                Err(e) => results[index] = Some(Err(e)),
+            }
+        }
+
+        // Hashing is deliberately slow, so spread it across the hasher's threads.
+        let hashes = futures::future::join_all(pending.iter_mut().map(|pending| {
+            let password = pending.password.take();
+            async move {
+                match password {
+                    Some(password) => hasher.hash(password).await.map(Some),
+                    None => Ok(None),
+                }
+            }
+        }))
+        .await;
+
+        let mut hashed = vec![];
+        for (mut pending, hash) in pending.into_iter().zip(hashes) {
+            match hash {
+                Ok(hash) => {
+                    pending.password = hash;
+                    hashed.push(pending);
+                }
+                Err(e) => {
+                    results[pending.index] = Some(Err(ProvisionError::PasswordHash(e.to_string())))
+                }
+            }
+        }
+
+        if hashed.is_empty() {
+            continue;
+        }
+
+        match insert_batch(&hashed, clock, secrets, conn).await {
+            Ok(mut records) => {
+                for pending in hashed {
+                    let result = records
+                        .remove(&pending.username)
+                        .ok_or_else(|| ProvisionError::Insert("the user wasn't returned".into()));
+                    results[pending.index] = Some(result);
+                }
+            }
+            Err(e) => {
+                for pending in hashed {
+                    results[pending.index] = Some(Err(ProvisionError::Insert(e.to_string())));
+                }
+            }
+        }
+    }
+
+    Ok(results
+        .into_iter()
+        .map(|result| result.expect("every user should have a result"))
+        .collect())
+}
+
+async fn insert_batch(
+    batch: &[Pending],
+    clock: &dyn Clock,
+    secrets: &dyn SecretGenerator,
+    conn: &mut Connection,
+) -> QueryResult<HashMap<String, UserRecord>> {
+    conn.transaction(|conn| {
+        async move {
+            let users: Vec<_> = batch
+                .iter()
+                .map(|pending| {
+                    (
+                        user::username.eq(&pending.username),
+                        user::password.eq(pending.password.as_deref()),
+                    )
+                })
+                .collect();
+
+            // SQLite doesn't guarantee the order of returned rows, so match them up by username.
+            let records: HashMap<String, UserRecord> = diesel::insert_into(user::table)
+                .values(users)
+                .returning(UserRecord::as_returning())
+                .get_results(conn)
+                .await?
+                .into_iter()
+                .map(|record: UserRecord| (record.username.clone(), record))
+                .collect();
+
+            let user_id = |pending: &Pending| records[&pending.username].id;
+
+            let emails: Vec<_> = batch
+                .iter()
+                .map(|pending| {
+                    (
+                        email::user_id.eq(user_id(pending)),
+                        email::address.eq(&pending.email),
+                        email::verified.eq(pending.verified),
+                    )
+                })
+                .collect();
+            diesel::insert_into(email::table)
+                .values(emails)
+                .execute(conn)
+                .await?;
+
+            let expiration = clock.now() + Duration::days(1);
+            let tokens: Vec<_> = batch
+                .iter()
+                .filter(|pending| !pending.verified)
+                .map(|pending| {
+                    (
+                        token::user_id.eq(user_id(pending)),
+                        token::secret.eq(secrets.generate()),
+                        token::expiration.eq(expiration),
+                        token::purpose.eq(TokenPurpose::EmailVerification),
+                    )
+                })
+                .collect();
+            if !tokens.is_empty() {
+                diesel::insert_into(token::table)
+                    .values(tokens)
+                    .execute(conn)
+                    .await?;
+            }
+
+            let user_roles: Vec<_> = batch
+                .iter()
+                .flat_map(|pending| {
+                    pending.role_ids.iter().map(move |role_id| {
+                        (
+                            user_role::user_id.eq(user_id(pending)),
+                            user_role::role_id.eq(*role_id),
+                        )
+                    })
+                })
+                .collect();
+            diesel::insert_into(user_role::table)
+                .values(user_roles)
+                .execute(conn)
+                .await?;
+
+            Ok(records)
+        }
+        .scope_boxed()
+    })
+    .await
+}
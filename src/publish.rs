@@ -0,0 +1,133 @@
+//! Scheduled publishing of [`Publish`] models.
+//!
+//! Apps register their publishable models in [`crate::App::publishables`], and every minute the
+//! [`crate::jobs::PUBLISH_SCHEDULED`] job publishes the ones which are due. Each model published
+//! is reported to [`crate::AppContext::on_model_event`] as [`ModelEvent::published`], so cached
+//! fragments depending on it are invalidated, and sent to clients as a `published` event:
+//!
+//! ```json
+//! { "model": "post", "id": 5 }
+//! ```
+
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, LazyLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use axum::response::sse::Event;
+use chrono::{DateTime, Utc};
+use diesel::QueryResult;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::context::AppContext;
+use crate::model::{ModelEvent, Publish};
+use crate::{jobs, metrics, Connection};
+
+static PUBLISHABLES: LazyLock<RwLock<Publishables>> = LazyLock::new(Default::default);
+
+#[async_trait::async_trait]
+trait AnyPublishable: Send + Sync {
+    async fn publish_due(
+        &self,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<i32>>;
+}
+
+struct Publisher<M>(PhantomData<fn() -> M>);
+
+#[async_trait::async_trait]
+impl<M: Publish> AnyPublishable for Publisher<M> {
+    async fn publish_due(
+        &self,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<i32>> {
+        M::publish_due(now, conn).await
+    }
+}
+
+/// The registry of models with scheduled publishing, by table name.
+#[derive(Clone, Default)]
+pub struct Publishables {
+    models: BTreeMap<&'static str, Arc<dyn AnyPublishable>>,
+}
+
+impl Publishables {
+    pub fn global() -> RwLockReadGuard<'static, Self> {
+        PUBLISHABLES
+            .read()
+            .expect("publishables lock should not be poisoned")
+    }
+
+    pub fn global_mut() -> RwLockWriteGuard<'static, Self> {
+        PUBLISHABLES
+            .write()
+            .expect("publishables lock should not be poisoned")
+    }
+
+    /// Register the model `M`, so it's published when it's due.
+    pub fn register<M: Publish>(&mut self) -> &mut Self {
+        self.models
+            .insert(M::TABLE_NAME, Arc::new(Publisher::<M>(PhantomData)));
+        self
+    }
+}
+
+/// The `published` event sent when a scheduled model is published.
+#[derive(Clone, Debug, Serialize)]
+pub struct PublishedEvent {
+    pub model: &'static str,
+    pub id: i32,
+}
+
+impl PublishedEvent {
+    fn to_event(&self) -> Event {
+        Event::default()
+            .event("published")
+            .json_data(self)
+            .expect("a published event serializes to JSON")
+    }
+}
+
+/// Publish the registered models which are due, returning how many were published.
+pub async fn publish_scheduled(context: &dyn AppContext) -> anyhow::Result<usize> {
+    // Copied out, so the registry isn't locked while publishing.
+    let models: Vec<_> = Publishables::global()
+        .models
+        .iter()
+        .map(|(&model, publisher)| (model, publisher.clone()))
+        .collect();
+    let now = context.clock().now();
+
+    let mut published = 0;
+    for (model, publisher) in models {
+        let ids = {
+            let mut conn = metrics::checkout(context.database()).await?;
+            publisher.publish_due(now, &mut conn).await?
+        };
+
+        for id in ids {
+            // It's published either way, so one failing handler doesn't hold up the rest.
+            let event = ModelEvent::published(model, id);
+            if let Err(e) = context.on_model_event(&event).await {
+                warn!("couldn't handle publishing {model}({id}): {e}");
+            }
+            context
+                .events()
+                .send(PublishedEvent { model, id }.to_event())
+                .await;
+            published += 1;
+        }
+    }
+
+    Ok(published)
+}
+
+pub(crate) async fn run(context: &dyn AppContext) {
+    match metrics::track_job(jobs::PUBLISH_SCHEDULED.name, publish_scheduled(context)).await {
+        Ok(0) => (),
+        Ok(published) => info!("published {published} scheduled models"),
+        Err(e) => warn!("couldn't publish scheduled models: {e}"),
+    }
+}
@@ -0,0 +1,306 @@
+//! Content scanning for files users upload, e.g. for viruses.
+//!
+//! [`crate::attachment::Attachments`] scans each upload with [`scan_upload`] once it's stored,
+//! which moves flagged files into a quarantine directory, and keeps the returned [`ScanState`] on
+//! the [`crate::model::Attachment`]. [`crate::model::Attachment::download`] checks it with
+//! [`ScanState::ensure_downloadable`] before serving the file. Apps storing uploads themselves do
+//! the same:
+//!
+//! ```ignore
+//! let scanned = scan_upload(&ClamAv::new(), &path, Path::new("uploads/quarantine")).await?;
+//! UpdateUploadRecord::new(upload.id).with_scan_state(scanned.state).save(&mut conn).await?;
+//!
+//! // When it's downloaded:
+//! upload.scan_state.ensure_downloadable()?;
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use axum::http::StatusCode;
+use serde::Deserialize;
+
+use crate::error::LowboyError;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+
+    #[error("scanner failed: {0}")]
+    Scanner(String),
+}
+
+/// What a [`Scanner`] found in a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Clean,
+    /// The file should be quarantined, with what it was flagged for, e.g. the virus signature.
+    Flagged(String),
+}
+
+crate::db_enum! {
+    /// Where an upload is in being scanned, stored alongside it.
+    pub enum ScanState {
+        /// Stored, but not scanned yet.
+        Pending = "pending",
+        Clean = "clean",
+        /// Flagged by the scanner, and moved to quarantine.
+        Quarantined = "quarantined",
+    }
+}
+
+impl ScanState {
+    /// Refuse to serve the upload unless it's been scanned and found clean.
+    pub fn ensure_downloadable(&self) -> std::result::Result<(), LowboyError> {
+        let (status, detail) = match self {
+            Self::Clean => return Ok(()),
+            Self::Pending => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "The file is still being scanned, try again shortly",
+            ),
+            Self::Quarantined => (
+                StatusCode::FORBIDDEN,
+                "The file was flagged as unsafe and can't be downloaded",
+            ),
+        };
+
+        Err(LowboyError::Rejected {
+            status,
+            detail: detail.to_string(),
+        })
+    }
+}
+
+/// An upload after it's been scanned by [`scan_upload`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Scanned {
+    pub state: ScanState,
+    /// Where the upload is now, in the quarantine directory if it was flagged.
+    pub path: PathBuf,
+    /// What the scanner flagged the upload for.
+    pub reason: Option<String>,
+}
+
+/// Scan the upload stored at `path` with `scanner`, moving it into the `quarantine` directory if
+/// it's flagged.
+pub async fn scan_upload(scanner: &dyn Scanner, path: &Path, quarantine: &Path) -> Result<Scanned> {
+    let reason = match scanner.scan(path).await? {
+        Verdict::Clean => {
+            return Ok(Scanned {
+                state: ScanState::Clean,
+                path: path.to_path_buf(),
+                reason: None,
+            })
+        }
+        Verdict::Flagged(reason) => reason,
+    };
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::Scanner(format!("{} isn't a file", path.display())))?;
+    let quarantined = quarantine.join(file_name);
+    tokio::fs::create_dir_all(quarantine).await?;
+    // Renaming fails across filesystems, where the file is copied instead.
+    if tokio::fs::rename(path, &quarantined).await.is_err() {
+        tokio::fs::copy(path, &quarantined).await?;
+        tokio::fs::remove_file(path).await?;
+    }
+    tracing::warn!("quarantined {} ({reason})", quarantined.display());
+
+    Ok(Scanned {
+        state: ScanState::Quarantined,
+        path: quarantined,
+        reason: Some(reason),
+    })
+}
+
+/// Scans uploaded files.
+#[async_trait::async_trait]
+pub trait Scanner: Send + Sync {
+    async fn scan(&self, path: &Path) -> Result<Verdict>;
+}
+
+/// Treats every file as clean, for apps which don't scan uploads.
+pub struct NoopScanner;
+
+#[async_trait::async_trait]
+impl Scanner for NoopScanner {
+    async fn scan(&self, _path: &Path) -> Result<Verdict> {
+        Ok(Verdict::Clean)
+    }
+}
+
+/// Scans files with ClamAV's `clamdscan`, which hands them to a running `clamd`.
+pub struct ClamAv {
+    command: PathBuf,
+}
+
+impl ClamAv {
+    /// Scan with the `clamdscan` found in `PATH`.
+    pub fn new() -> Self {
+        Self::with_command("clamdscan")
+    }
+
+    pub fn with_command(command: impl Into<PathBuf>) -> Self {
+        Self {
+            command: command.into(),
+        }
+    }
+}
+
+impl Default for ClamAv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Scanner for ClamAv {
+    async fn scan(&self, path: &Path) -> Result<Verdict> {
+        let output = tokio::process::Command::new(&self.command)
+            .arg("--no-summary")
+            // Pass the open file to `clamd`, so it doesn't need permission to read the path.
+            .arg("--fdpass")
+            .arg(path)
+            .output()
+            .await?;
+
+        // `clamdscan` exits with 1 when it finds a virus, printing `<path>: <signature> FOUND`.
+        match output.status.code() {
+            Some(0) => Ok(Verdict::Clean),
+            Some(1) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let signature = stdout
+                    .lines()
+                    .find_map(|line| line.strip_suffix(" FOUND"))
+                    .and_then(|line| line.rsplit(": ").next())
+                    .unwrap_or("unknown");
+
+                Ok(Verdict::Flagged(signature.to_string()))
+            }
+            _ => Err(Error::Scanner(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HttpVerdict {
+    clean: bool,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Scans files with an external API, which is sent each file as the body of a `POST` request and
+/// responds with `{ "clean": bool, "reason": "..." }`.
+pub struct HttpScanner {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl HttpScanner {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Scanner for HttpScanner {
+    async fn scan(&self, path: &Path) -> Result<Verdict> {
+        let file = tokio::fs::read(path).await?;
+
+        let verdict: HttpVerdict = self
+            .client
+            .post(&self.url)
+            .body(file)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if verdict.clean {
+            return Ok(Verdict::Clean);
+        }
+
+        Ok(Verdict::Flagged(
+            verdict.reason.unwrap_or_else(|| "unknown".to_string()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flags files whose contents contain `EICAR`.
+    struct ContentScanner;
+
+    #[async_trait::async_trait]
+    impl Scanner for ContentScanner {
+        async fn scan(&self, path: &Path) -> Result<Verdict> {
+            let contents = tokio::fs::read_to_string(path).await?;
+
+            if contents.contains("EICAR") {
+                return Ok(Verdict::Flagged("Eicar-Test-Signature".to_string()));
+            }
+
+            Ok(Verdict::Clean)
+        }
+    }
+
+    async fn upload(contents: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("lowboy-scan-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("upload.txt");
+        tokio::fs::write(&path, contents).await.unwrap();
+
+        (path, dir.join("quarantine"))
+    }
+
+    #[tokio::test]
+    async fn clean_uploads_stay_where_they_are() {
+        let (path, quarantine) = upload("hello").await;
+
+        let scanned = scan_upload(&ContentScanner, &path, &quarantine).await.unwrap();
+        assert_eq!(scanned.state, ScanState::Clean);
+        assert_eq!(scanned.path, path);
+        assert!(path.exists());
+        assert!(scanned.state.ensure_downloadable().is_ok());
+    }
+
+    #[tokio::test]
+    async fn flagged_uploads_are_quarantined_and_refused() {
+        let (path, quarantine) = upload("EICAR").await;
+
+        let scanned = scan_upload(&ContentScanner, &path, &quarantine).await.unwrap();
+        assert_eq!(scanned.state, ScanState::Quarantined);
+        assert_eq!(scanned.reason.as_deref(), Some("Eicar-Test-Signature"));
+        assert_eq!(scanned.path, quarantine.join("upload.txt"));
+        assert!(!path.exists());
+        assert!(scanned.path.exists());
+
+        let refused = scanned.state.ensure_downloadable();
+        assert!(matches!(
+            refused,
+            Err(LowboyError::Rejected { status: StatusCode::FORBIDDEN, .. })
+        ));
+    }
+
+    #[test]
+    fn pending_uploads_are_refused_until_scanned() {
+        assert!(matches!(
+            ScanState::Pending.ensure_downloadable(),
+            Err(LowboyError::Rejected { status: StatusCode::SERVICE_UNAVAILABLE, .. })
+        ));
+    }
+}
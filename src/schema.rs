@@ -6,6 +6,7 @@ diesel::table! {
         username -> Text,
         password -> Nullable<Text>,
         access_token -> Nullable<Text>,
+        service_account -> Bool,
     }
 }
 
@@ -24,6 +25,7 @@ diesel::table! {
         user_id -> Integer,
         secret -> Text,
         expiration -> TimestamptzSqlite,
+        purpose -> Text,
     }
 }
 
@@ -31,6 +33,67 @@ diesel::table! {
     permission (id) {
         id -> Integer,
         name -> Text,
+        description -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    queued_job (id) {
+        id -> Integer,
+        job -> Text,
+        payload -> Text,
+        unique_key -> Nullable<Text>,
+        run_at -> TimestamptzSqlite,
+        created_at -> TimestamptzSqlite,
+        attempts -> Integer,
+        last_error -> Nullable<Text>,
+        locked_until -> Nullable<TimestamptzSqlite>,
+    }
+}
+
+diesel::table! {
+    setting (id) {
+        id -> Integer,
+        key -> Text,
+        value -> Text,
+        updated_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    kv_entry (id) {
+        id -> Integer,
+        key -> Text,
+        value -> Text,
+        expires_at -> Nullable<TimestamptzSqlite>,
+        updated_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    attachment (id) {
+        id -> Integer,
+        user_id -> Nullable<Integer>,
+        file_name -> Text,
+        content_type -> Text,
+        path -> Text,
+        size -> BigInt,
+        scan_state -> Text,
+        scan_reason -> Nullable<Text>,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    announcement (id) {
+        id -> Integer,
+        message -> Text,
+        level -> Text,
+        starts_at -> TimestamptzSqlite,
+        ends_at -> Nullable<TimestamptzSqlite>,
+        role_id -> Nullable<Integer>,
+        announced_at -> Nullable<TimestamptzSqlite>,
+        created_at -> TimestamptzSqlite,
     }
 }
 
@@ -38,6 +101,15 @@ diesel::table! {
     role (id) {
         id -> Integer,
         name -> Text,
+        description -> Nullable<Text>,
+        system -> Bool,
+    }
+}
+
+diesel::table! {
+    role_hierarchy (parent_id, child_id) {
+        parent_id -> Integer,
+        child_id -> Integer,
     }
 }
 
@@ -55,19 +127,161 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    mailbox_message (id) {
+        id -> Integer,
+        sender -> Text,
+        recipient -> Text,
+        subject -> Text,
+        text -> Text,
+        html -> Nullable<Text>,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    notification_preferences (user_id) {
+        user_id -> Integer,
+        new_device_login -> Bool,
+        failed_login_streak -> Bool,
+        password_changed -> Bool,
+    }
+}
+
+diesel::table! {
+    known_device (id) {
+        id -> Integer,
+        user_id -> Integer,
+        user_agent -> Text,
+        ip -> Nullable<Text>,
+        created_at -> TimestamptzSqlite,
+        last_seen_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    audit_log (id) {
+        id -> Integer,
+        request_id -> Text,
+        real_user_id -> Nullable<Integer>,
+        effective_user_id -> Nullable<Integer>,
+        action -> Text,
+        subject -> Nullable<Text>,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    draft (id) {
+        id -> Integer,
+        user_id -> Integer,
+        form -> Text,
+        content -> Text,
+        updated_at -> TimestamptzSqlite,
+        expires_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    user_preference (id) {
+        id -> Integer,
+        user_id -> Integer,
+        key -> Text,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    sent_email (id) {
+        id -> Integer,
+        recipient -> Text,
+        template -> Nullable<Text>,
+        subject -> Text,
+        status -> Text,
+        provider_message_id -> Nullable<Text>,
+        error -> Nullable<Text>,
+        created_at -> TimestamptzSqlite,
+        updated_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    email_suppression (id) {
+        id -> Integer,
+        address -> Text,
+        reason -> Text,
+        detail -> Nullable<Text>,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    dead_letter (id) {
+        id -> Integer,
+        job -> Text,
+        payload -> Text,
+        error -> Text,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    digest_opt_out (user_id, digest) {
+        user_id -> Integer,
+        digest -> Text,
+    }
+}
+
+diesel::table! {
+    model_version (id) {
+        id -> Integer,
+        model -> Text,
+        record_id -> Integer,
+        action -> Text,
+        data -> Text,
+        user_id -> Nullable<Integer>,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::joinable!(announcement -> role (role_id));
+diesel::joinable!(attachment -> user (user_id));
+diesel::joinable!(digest_opt_out -> user (user_id));
 diesel::joinable!(email -> user (user_id));
+diesel::joinable!(draft -> user (user_id));
 diesel::joinable!(token -> user (user_id));
+diesel::joinable!(notification_preferences -> user (user_id));
+diesel::joinable!(known_device -> user (user_id));
+diesel::joinable!(model_version -> user (user_id));
 diesel::joinable!(role_permission -> permission (permission_id));
 diesel::joinable!(role_permission -> role (role_id));
+diesel::joinable!(user_preference -> user (user_id));
 diesel::joinable!(user_role -> user (user_id));
 diesel::joinable!(user_role -> role (role_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    announcement,
+    attachment,
+    audit_log,
+    dead_letter,
+    digest_opt_out,
+    draft,
     email,
+    email_suppression,
     user,
+    mailbox_message,
+    notification_preferences,
+    known_device,
+    kv_entry,
+    model_version,
     permission,
+    queued_job,
     role,
+    role_hierarchy,
     role_permission,
+    sent_email,
+    setting,
     token,
+    user_preference,
     user_role,
 );
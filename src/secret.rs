@@ -0,0 +1,57 @@
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use dyn_clone::DynClone;
+use uuid::Uuid;
+
+/// A source of random secrets, such as verification tokens.
+///
+/// Secrets are requested from the context's generator rather than generated inline, so tests can
+/// use a [`SequentialSecretGenerator`] and make assertions on the emails and links that contain
+/// them.
+pub trait SecretGenerator: Debug + DynClone + Send + Sync {
+    fn generate(&self) -> String;
+}
+dyn_clone::clone_trait_object!(SecretGenerator);
+
+/// Generates random v4 UUID secrets.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UuidSecretGenerator;
+
+impl SecretGenerator for UuidSecretGenerator {
+    fn generate(&self) -> String {
+        Uuid::new_v4().to_string()
+    }
+}
+
+/// Generates predictable secrets (`{prefix}-1`, `{prefix}-2`, ...) for tests.
+///
+/// Clones share the same counter.
+#[derive(Clone, Debug)]
+pub struct SequentialSecretGenerator {
+    prefix: String,
+    counter: Arc<AtomicU64>,
+}
+
+impl SequentialSecretGenerator {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            counter: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+impl Default for SequentialSecretGenerator {
+    fn default() -> Self {
+        Self::new("secret")
+    }
+}
+
+impl SecretGenerator for SequentialSecretGenerator {
+    fn generate(&self) -> String {
+        let next = self.counter.fetch_add(1, Ordering::SeqCst) + 1;
+        format!("{prefix}-{next}", prefix = self.prefix)
+    }
+}
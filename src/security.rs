@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::model::User;
+
+/// Consecutive failed logins before an account is temporarily locked and its owner notified.
+pub const FAILED_LOGIN_THRESHOLD: u32 = 5;
+
+/// How long an account stays locked once [`FAILED_LOGIN_THRESHOLD`] is reached.
+pub const LOCKOUT: TimeDelta = TimeDelta::minutes(15);
+
+static LOGIN_ATTEMPTS: LazyLock<LoginAttempts> = LazyLock::new(LoginAttempts::default);
+
+crate::db_enum! {
+    /// The kinds of [`SecurityNotification`].
+    pub enum NotificationKind {
+        NewDeviceLogin = "new_device_login",
+        FailedLoginStreak = "failed_login_streak",
+        PasswordChanged = "password_changed",
+    }
+}
+
+/// Security related events a user can be emailed about.
+#[derive(Clone, Debug)]
+pub enum SecurityNotification {
+    /// A successful sign-in from a device the user hasn't used before.
+    NewDeviceLogin {
+        user_agent: String,
+        ip: Option<String>,
+    },
+    /// The account was locked after too many failed sign-in attempts.
+    FailedLoginStreak {
+        attempts: u32,
+    },
+    PasswordChanged,
+}
+
+impl SecurityNotification {
+    pub fn kind(&self) -> NotificationKind {
+        match self {
+            Self::NewDeviceLogin { .. } => NotificationKind::NewDeviceLogin,
+            Self::FailedLoginStreak { .. } => NotificationKind::FailedLoginStreak,
+            Self::PasswordChanged => NotificationKind::PasswordChanged,
+        }
+    }
+
+    pub fn subject(&self) -> &'static str {
+        match self {
+            Self::NewDeviceLogin { .. } => "New sign-in to your account",
+            Self::FailedLoginStreak { .. } => "Your account has been temporarily locked",
+            Self::PasswordChanged => "Your password was changed",
+        }
+    }
+
+    pub fn text(&self, user: &User) -> String {
+        let body = match self {
+            Self::NewDeviceLogin { user_agent, ip } => format!(
+                "Your account was just signed in to from a new device:\n\n{user_agent}\n{ip}\n\nIf this wasn't you, change your password immediately.",
+                ip = ip.as_deref().unwrap_or("unknown address"),
+            ),
+            Self::FailedLoginStreak { attempts } => format!(
+                "There were {attempts} failed attempts to sign in to your account, so it has been locked for {minutes} minutes.\n\nIf this wasn't you, consider changing your password.",
+                minutes = LOCKOUT.num_minutes(),
+            ),
+            Self::PasswordChanged => {
+                "The password for your account was just changed.\n\nIf this wasn't you, contact support immediately.".to_string()
+            }
+        };
+
+        format!("Hi {username},\n\n{body}", username = user.username)
+    }
+}
+
+/// Tracks consecutive failed logins per user, for locking out brute force attempts.
+#[derive(Debug, Default)]
+pub struct LoginAttempts(Mutex<HashMap<i32, (u32, DateTime<Utc>)>>);
+
+impl LoginAttempts {
+    pub fn global() -> &'static Self {
+        &LOGIN_ATTEMPTS
+    }
+
+    pub fn is_locked(&self, user_id: i32, now: DateTime<Utc>) -> bool {
+        self.locked_for(user_id, now).is_some()
+    }
+
+    /// How much longer the user is locked out for, if they are.
+    pub fn locked_for(&self, user_id: i32, now: DateTime<Utc>) -> Option<TimeDelta> {
+        self.0
+            .lock()
+            .expect("login attempts lock should not be poisoned")
+            .get(&user_id)
+            .filter(|(attempts, _)| *attempts >= FAILED_LOGIN_THRESHOLD)
+            .map(|(_, last)| LOCKOUT - (now - *last))
+            .filter(|remaining| *remaining > TimeDelta::zero())
+    }
+
+    /// Record a failed login, returning the length of the current streak.
+    pub fn record_failure(&self, user_id: i32, now: DateTime<Utc>) -> u32 {
+        let mut attempts = self
+            .0
+            .lock()
+            .expect("login attempts lock should not be poisoned");
+        let (count, last) = attempts.entry(user_id).or_insert((0, now));
+
+        // A streak that ended in an expired lockout starts over.
+        if *count >= FAILED_LOGIN_THRESHOLD && now - *last >= LOCKOUT {
+            *count = 0;
+        }
+
+        *count += 1;
+        *last = now;
+
+        *count
+    }
+
+    pub fn clear(&self, user_id: i32) {
+        self.0
+            .lock()
+            .expect("login attempts lock should not be poisoned")
+            .remove(&user_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+    }
+
+    fn fail(attempts: &LoginAttempts, times: u32, at: DateTime<Utc>) {
+        for _ in 0..times {
+            attempts.record_failure(1, at);
+        }
+    }
+
+    #[test]
+    fn locked_for_counts_down_from_the_last_failure() {
+        let attempts = LoginAttempts::default();
+        fail(&attempts, FAILED_LOGIN_THRESHOLD - 1, now());
+        assert_eq!(attempts.locked_for(1, now()), None);
+
+        fail(&attempts, 1, now());
+        assert_eq!(attempts.locked_for(1, now()), Some(LOCKOUT));
+
+        let later = now() + TimeDelta::minutes(5);
+        assert_eq!(attempts.locked_for(1, later), Some(LOCKOUT - TimeDelta::minutes(5)));
+        assert!(attempts.is_locked(1, now() + LOCKOUT - TimeDelta::seconds(1)));
+        assert_eq!(attempts.locked_for(1, now() + LOCKOUT), None);
+        assert_eq!(attempts.locked_for(2, now()), None);
+    }
+
+    #[test]
+    fn a_failure_after_the_lockout_starts_a_new_streak() {
+        let attempts = LoginAttempts::default();
+        fail(&attempts, FAILED_LOGIN_THRESHOLD, now());
+
+        assert_eq!(attempts.record_failure(1, now() + LOCKOUT), 1);
+        assert_eq!(attempts.locked_for(1, now() + LOCKOUT), None);
+    }
+
+    #[test]
+    fn clear_ends_the_lockout() {
+        let attempts = LoginAttempts::default();
+        fail(&attempts, FAILED_LOGIN_THRESHOLD, now());
+
+        attempts.clear(1);
+        assert_eq!(attempts.locked_for(1, now()), None);
+    }
+}
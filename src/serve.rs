@@ -0,0 +1,133 @@
+//! Options for the built-in layers [`crate::Lowboy::serve_with`] wraps the app in.
+//!
+//! Everything is enabled by default. Apps that don't need a layer can opt out of it, e.g. a pure
+//! JSON API doesn't need flash messages, HTML error pages or live reloading:
+//!
+//! ```ignore
+//! let options = ServeOptions::default()
+//!     .messages(false)
+//!     .error_page(false)
+//!     .livereload(false);
+//!
+//! Lowboy::boot().await?.serve_with::<Api>(options).await?;
+//! ```
+//!
+//! Handlers relying on a disabled layer fail, e.g. extracting [`axum_messages::Messages`] without
+//! the messages layer. Lowboy's own routes keep working without it: they take
+//! [`crate::extract::Flash`], and their messages are dropped.
+//!
+//! The order of the layers is fixed, layers can only be turned on or off.
+
+use chrono::TimeDelta;
+
+/// The session cookie's settings.
+#[derive(Clone, Debug)]
+pub struct SessionOptions {
+    pub cookie_name: String,
+    /// Only send the cookie over HTTPS. Defaults to whether the app's `public_url` is HTTPS, which
+    /// it is when tls is configured.
+    pub secure: Option<bool>,
+    /// Sessions expire after being inactive this long.
+    pub expiry: TimeDelta,
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        Self {
+            cookie_name: "id".to_string(),
+            secure: None,
+            expiry: TimeDelta::days(1),
+        }
+    }
+}
+
+/// Which of lowboy's built-in layers to use, see the [module docs](self).
+#[derive(Clone, Debug)]
+pub struct ServeOptions {
+    pub(crate) messages: bool,
+    pub(crate) error_page: bool,
+    pub(crate) catch_panic: bool,
+    pub(crate) api_tokens: bool,
+    pub(crate) locale_detection: bool,
+    pub(crate) session_tracking: bool,
+    pub(crate) livereload: bool,
+    pub(crate) session: SessionOptions,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self {
+            messages: true,
+            error_page: true,
+            catch_panic: true,
+            api_tokens: true,
+            locale_detection: true,
+            session_tracking: true,
+            livereload: true,
+            session: SessionOptions::default(),
+        }
+    }
+}
+
+impl ServeOptions {
+    /// Flash messages, see [`axum_messages`].
+    pub fn messages(self, enabled: bool) -> Self {
+        Self {
+            messages: enabled,
+            ..self
+        }
+    }
+
+    /// Render errors with the app's error view. Without it, errors are bare status codes.
+    pub fn error_page(self, enabled: bool) -> Self {
+        Self {
+            error_page: enabled,
+            ..self
+        }
+    }
+
+    /// Turn panics in handlers into internal server errors, rather than dropping the connection.
+    pub fn catch_panic(self, enabled: bool) -> Self {
+        Self {
+            catch_panic: enabled,
+            ..self
+        }
+    }
+
+    /// Authenticate service accounts with `Authorization: Bearer` tokens.
+    pub fn api_tokens(self, enabled: bool) -> Self {
+        Self {
+            api_tokens: enabled,
+            ..self
+        }
+    }
+
+    /// Detect each request's locale and timezone, see [`crate::locale`].
+    pub fn locale_detection(self, enabled: bool) -> Self {
+        Self {
+            locale_detection: enabled,
+            ..self
+        }
+    }
+
+    /// Track the devices sessions are used from, see [`crate::session`].
+    pub fn session_tracking(self, enabled: bool) -> Self {
+        Self {
+            session_tracking: enabled,
+            ..self
+        }
+    }
+
+    /// Reload the page when static files change. Only debug builds with the `livereload` feature
+    /// live reload.
+    pub fn livereload(self, enabled: bool) -> Self {
+        Self {
+            livereload: enabled,
+            ..self
+        }
+    }
+
+    pub fn session(self, session: SessionOptions) -> Self {
+        Self { session, ..self }
+    }
+}
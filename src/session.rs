@@ -0,0 +1,217 @@
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum_extra::headers::UserAgent;
+use axum_extra::TypedHeader;
+use chrono::{DateTime, TimeDelta, Utc};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text};
+use diesel_async::pooled_connection::deadpool::Pool;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use tracing::{info, warn};
+
+use crate::context::CloneableAppContext;
+use crate::diesel_sqlite_session_store::{tower_sessions, TowerSession};
+use crate::{jobs, metrics, AuthSession, Connection};
+
+pub(crate) const SESSION_DEVICE_KEY: &str = "lowboy.device";
+
+/// How stale `last_seen` may get before it's refreshed. Refreshing it writes the session back to
+/// the database, so it isn't done on every request.
+const LAST_SEEN_RESOLUTION: TimeDelta = TimeDelta::minutes(1);
+
+/// The device a session belongs to, tracked in the session data by [`track`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SessionDevice {
+    pub user_id: Option<i32>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub last_seen: i64,
+}
+
+/// A live session, for listing and revoking sessions on a user's behalf.
+#[derive(Clone, Debug)]
+pub struct ActiveSession {
+    pub id: String,
+    pub user_id: Option<i32>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub last_seen: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl ActiveSession {
+    /// Every unexpired session, most recently seen first.
+    pub async fn list(now: DateTime<Utc>, conn: &mut Connection) -> QueryResult<Vec<Self>> {
+        Ok(tower_sessions::table
+            .filter(tower_sessions::expiry_date.gt(now.timestamp()))
+            .order_by(tower_sessions::last_seen.desc())
+            .select(TowerSession::as_select())
+            .load(conn)
+            .await?
+            .into_iter()
+            .map(Self::from)
+            .collect())
+    }
+
+    /// Every unexpired session `user_id` is logged in to, most recently seen first.
+    pub async fn list_for_user(
+        user_id: i32,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<Vec<Self>> {
+        Ok(tower_sessions::table
+            .filter(tower_sessions::user_id.eq(user_id))
+            .filter(tower_sessions::expiry_date.gt(now.timestamp()))
+            .order_by(tower_sessions::last_seen.desc())
+            .select(TowerSession::as_select())
+            .load(conn)
+            .await?
+            .into_iter()
+            .map(Self::from)
+            .collect())
+    }
+
+    /// Log `user_id` out of the session `id`, returning whether the session existed.
+    pub async fn revoke(id: &str, user_id: i32, conn: &mut Connection) -> QueryResult<bool> {
+        let deleted = diesel::delete(
+            tower_sessions::table
+                .filter(tower_sessions::id.eq(id))
+                .filter(tower_sessions::user_id.eq(user_id)),
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(deleted > 0)
+    }
+}
+
+impl From<TowerSession> for ActiveSession {
+    fn from(value: TowerSession) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            user_agent: value.user_agent,
+            ip: value.ip,
+            last_seen: value
+                .last_seen
+                .and_then(|last_seen| DateTime::from_timestamp(last_seen, 0)),
+            expires_at: DateTime::from_timestamp(value.expiry_date, 0).unwrap_or_default(),
+        }
+    }
+}
+
+/// How much data a session is storing, see [`SessionSize::largest`].
+#[derive(Clone, Debug, QueryableByName)]
+pub struct SessionSize {
+    #[diesel(sql_type = Text)]
+    pub id: String,
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub user_id: Option<i32>,
+    /// The size of the session's encoded data, in bytes.
+    #[diesel(sql_type = BigInt)]
+    pub size: i64,
+}
+
+impl SessionSize {
+    /// The `limit` sessions storing the most data, largest first.
+    pub async fn largest(limit: i64, conn: &mut Connection) -> QueryResult<Vec<Self>> {
+        let query = r#"
+            SELECT id, user_id, length(data) AS size
+            FROM tower_sessions
+            ORDER BY size DESC
+            LIMIT ?
+            "#;
+
+        sql_query(query).bind::<BigInt, _>(limit).load(conn).await
+    }
+}
+
+/// Log the largest sessions, warning about those over `max_size` bytes.
+///
+/// This is run periodically by [`crate::Lowboy::serve`], so session bloat (e.g. forms being
+/// round-tripped through the session) is noticed before it becomes a problem.
+pub async fn report_largest(database: &Pool<Connection>, max_size: usize) {
+    let sessions = async {
+        let mut conn = metrics::checkout(database).await?;
+        Ok::<_, anyhow::Error>(SessionSize::largest(5, &mut conn).await?)
+    };
+
+    let sessions = match metrics::track_job(jobs::REPORT_LARGEST_SESSIONS.name, sessions).await {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            warn!("couldn't report the largest sessions: {e}");
+            return;
+        }
+    };
+
+    for session in sessions {
+        if session.size as usize > max_size {
+            warn!(
+                session_id = %session.id,
+                user_id = session.user_id,
+                size = session.size,
+                max_size,
+                "session data exceeds the maximum size"
+            );
+        } else {
+            info!(
+                session_id = %session.id,
+                user_id = session.user_id,
+                size = session.size,
+                "large session"
+            );
+        }
+    }
+}
+
+/// Keep track of the user, user agent, and address each session is used from.
+pub async fn track<AC: CloneableAppContext>(
+    State(context): State<AC>,
+    auth_session: AuthSession,
+    session: Session,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let now = context.clock().now();
+    let device = SessionDevice {
+        user_id: auth_session.user.map(|user| user.id),
+        user_agent: user_agent.map(|TypedHeader(user_agent)| user_agent.to_string()),
+        ip: connect_info.map(|ConnectInfo(addr)| addr.ip().to_string()),
+        last_seen: now.timestamp(),
+    };
+
+    let previous = match session.get::<SessionDevice>(SESSION_DEVICE_KEY).await {
+        Ok(previous) => previous,
+        Err(e) => {
+            warn!("couldn't load the session device: {e}");
+            None
+        }
+    };
+
+    let changed = match previous {
+        Some(previous) => {
+            previous.user_id != device.user_id
+                || previous.user_agent != device.user_agent
+                || previous.ip != device.ip
+                || device.last_seen - previous.last_seen >= LAST_SEEN_RESOLUTION.num_seconds()
+        }
+        // Don't create sessions for visitors who don't otherwise have one.
+        None => session.id().is_some() || device.user_id.is_some(),
+    };
+
+    if changed {
+        if let Err(e) = session.insert(SESSION_DEVICE_KEY, &device).await {
+            warn!("couldn't track the session device: {e}");
+        }
+    }
+
+    next.run(request).await
+}
@@ -0,0 +1,338 @@
+//! Site-wide settings stored in the database, so they can be changed from the admin without
+//! redeploying.
+//!
+//! Each setting is a type implementing [`TypedSetting`], read and written through
+//! [`crate::Context::settings`]:
+//!
+//! ```ignore
+//! #[derive(Default, Deserialize, Serialize)]
+//! pub struct PostsPerPage(pub i64);
+//!
+//! impl TypedSetting for PostsPerPage {
+//!     const KEY: &'static str = "posts_per_page";
+//!     const LABEL: &'static str = "Posts per page";
+//! }
+//!
+//! let PostsPerPage(per_page) = context.settings().get::<PostsPerPage>().await?;
+//! ```
+//!
+//! Settings registered with [`crate::app::App::settings`] are editable in the admin. Values are
+//! cached for [`CACHE_TTL`], so changes made by other processes can take that long to be seen.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{LazyLock, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use diesel_async::pooled_connection::deadpool::Pool;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::clock::Clock;
+use crate::metrics;
+use crate::model::Setting;
+use crate::Connection;
+
+type Result<T> = std::result::Result<T, Error>;
+
+static SETTINGS_CACHE: LazyLock<SettingsCache> = LazyLock::new(SettingsCache::default);
+static SETTING_DEFINITIONS: LazyLock<RwLock<SettingDefinitions>> =
+    LazyLock::new(Default::default);
+
+/// How long setting values are cached for.
+pub const CACHE_TTL: TimeDelta = TimeDelta::seconds(60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error(transparent)]
+    Pool(#[from] deadpool::managed::PoolError<diesel_async::pooled_connection::PoolError>),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// A setting, stored as JSON under [`TypedSetting::KEY`]. Its [`Default`] is used until it's set.
+pub trait TypedSetting: Serialize + DeserializeOwned + Default + Send + Sync + 'static {
+    /// The key the setting is stored under, e.g. `site_name`.
+    const KEY: &'static str;
+
+    /// What the setting is called in the admin.
+    const LABEL: &'static str;
+
+    /// What the setting does, shown in the admin.
+    const DESCRIPTION: &'static str = "";
+}
+
+/// The name of the site, used instead of [`crate::app::App::app_title`] when set.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SiteName(pub Option<String>);
+
+impl TypedSetting for SiteName {
+    const KEY: &'static str = "site_name";
+    const LABEL: &'static str = "Site name";
+    const DESCRIPTION: &'static str = "Shown in page titles. Leave empty to use the app's name.";
+}
+
+/// Where users can get help, shown in the layout when set.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SupportEmail(pub Option<String>);
+
+impl TypedSetting for SupportEmail {
+    const KEY: &'static str = "support_email";
+    const LABEL: &'static str = "Support email";
+    const DESCRIPTION: &'static str = "Where users can get help.";
+}
+
+/// Whether new users can register. Existing users can still log in when it's closed.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct RegistrationOpen(pub bool);
+
+impl Default for RegistrationOpen {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+impl TypedSetting for RegistrationOpen {
+    const KEY: &'static str = "registration_open";
+    const LABEL: &'static str = "Registration open";
+    const DESCRIPTION: &'static str = "Whether new users can register.";
+}
+
+/// A registered setting, without its type, so the admin can list and edit it.
+#[derive(Clone, Copy, Debug)]
+pub struct SettingDefinition {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub description: &'static str,
+    default: fn() -> Value,
+    parse: fn(&str) -> serde_json::Result<String>,
+}
+
+impl SettingDefinition {
+    fn of<S: TypedSetting>() -> Self {
+        Self {
+            key: S::KEY,
+            label: S::LABEL,
+            description: S::DESCRIPTION,
+            default: || serde_json::to_value(S::default()).unwrap_or_default(),
+            parse: parse::<S>,
+        }
+    }
+
+    /// The default value, as JSON.
+    pub fn default_value(&self) -> Value {
+        (self.default)()
+    }
+
+    /// Whether the setting is on or off, rather than text.
+    pub fn is_bool(&self) -> bool {
+        self.default_value().is_boolean()
+    }
+
+    /// Parse a value entered in the admin into the setting's JSON.
+    pub fn parse(&self, input: &str) -> serde_json::Result<String> {
+        (self.parse)(input)
+    }
+
+    /// The JSON `value`, or the default, as it's entered in the admin.
+    pub fn display(&self, value: Option<&str>) -> String {
+        let value = value
+            .and_then(|value| serde_json::from_str(value).ok())
+            .unwrap_or_else(|| self.default_value());
+
+        match value {
+            Value::Null => String::new(),
+            Value::String(value) => value,
+            value => value.to_string(),
+        }
+    }
+}
+
+/// Parse `input` as JSON, or as a string if it isn't JSON of the setting's type, so text settings
+/// don't need quoting. Empty input is `null`.
+fn parse<S: TypedSetting>(input: &str) -> serde_json::Result<String> {
+    let input = input.trim();
+    let value = match input {
+        "" => Value::Null,
+        input => serde_json::from_str(input).unwrap_or_else(|_| Value::String(input.to_string())),
+    };
+
+    let setting = serde_json::from_value::<S>(value.clone()).or_else(|error| match value {
+        Value::String(_) => Err(error),
+        _ => serde_json::from_value::<S>(Value::String(input.to_string())),
+    })?;
+
+    serde_json::to_string(&setting)
+}
+
+/// The registry of settings editable in the admin, by key.
+#[derive(Clone)]
+pub struct SettingDefinitions(BTreeMap<&'static str, SettingDefinition>);
+
+impl Default for SettingDefinitions {
+    fn default() -> Self {
+        let mut definitions = Self(BTreeMap::new());
+        definitions
+            .register::<SiteName>()
+            .register::<SupportEmail>()
+            .register::<RegistrationOpen>();
+        definitions
+    }
+}
+
+impl SettingDefinitions {
+    pub fn global() -> RwLockReadGuard<'static, Self> {
+        SETTING_DEFINITIONS
+            .read()
+            .expect("setting definitions lock should not be poisoned")
+    }
+
+    pub fn global_mut() -> RwLockWriteGuard<'static, Self> {
+        SETTING_DEFINITIONS
+            .write()
+            .expect("setting definitions lock should not be poisoned")
+    }
+
+    /// Register the setting `S`, so it's editable in the admin.
+    pub fn register<S: TypedSetting>(&mut self) -> &mut Self {
+        self.0.insert(S::KEY, SettingDefinition::of::<S>());
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<SettingDefinition> {
+        self.0.get(key).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SettingDefinition> {
+        self.0.values()
+    }
+}
+
+struct Entry {
+    /// The stored JSON, or `None` if the setting isn't set.
+    value: Option<String>,
+    expires_at: DateTime<Utc>,
+}
+
+/// The cache of setting values behind [`Settings`].
+#[derive(Default)]
+pub struct SettingsCache(Mutex<HashMap<String, Entry>>);
+
+impl SettingsCache {
+    /// The cache used by [`crate::Context::settings`].
+    pub fn global() -> &'static Self {
+        &SETTINGS_CACHE
+    }
+
+    fn get(&self, key: &str, now: DateTime<Utc>) -> Option<Option<String>> {
+        self.entries()
+            .get(key)
+            .filter(|entry| entry.expires_at > now)
+            .map(|entry| entry.value.clone())
+    }
+
+    fn insert(&self, key: &str, value: Option<String>, now: DateTime<Utc>) {
+        self.entries().insert(
+            key.to_string(),
+            Entry {
+                value,
+                expires_at: now + CACHE_TTL,
+            },
+        );
+    }
+
+    pub fn invalidate(&self, key: &str) {
+        self.entries().remove(key);
+    }
+
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<String, Entry>> {
+        self.0
+            .lock()
+            .expect("settings cache lock should not be poisoned")
+    }
+}
+
+/// Reads and writes settings, see the [module docs](self).
+pub struct Settings<'a> {
+    database: &'a Pool<Connection>,
+    cache: &'a SettingsCache,
+    clock: &'a dyn Clock,
+}
+
+impl<'a> Settings<'a> {
+    pub fn new(
+        database: &'a Pool<Connection>,
+        cache: &'a SettingsCache,
+        clock: &'a dyn Clock,
+    ) -> Self {
+        Self {
+            database,
+            cache,
+            clock,
+        }
+    }
+
+    /// The setting `S`, or its default if it isn't set or its stored value no longer
+    /// deserializes.
+    pub async fn get<S: TypedSetting>(&self) -> Result<S> {
+        let Some(value) = self.raw(S::KEY).await? else {
+            return Ok(S::default());
+        };
+
+        Ok(serde_json::from_str(&value).unwrap_or_else(|error| {
+            tracing::warn!("setting `{}` is invalid, using the default: {error}", S::KEY);
+            S::default()
+        }))
+    }
+
+    pub async fn set<S: TypedSetting>(&self, setting: &S) -> Result<()> {
+        self.set_raw(S::KEY, &serde_json::to_string(setting)?).await
+    }
+
+    /// Revert the setting `S` to its default.
+    pub async fn reset<S: TypedSetting>(&self) -> Result<()> {
+        self.reset_raw(S::KEY).await
+    }
+
+    /// The stored JSON of the setting `key`, if it's set.
+    pub async fn raw(&self, key: &str) -> Result<Option<String>> {
+        let now = self.clock.now();
+        if let Some(value) = self.cache.get(key, now) {
+            return Ok(value);
+        }
+
+        let mut conn = metrics::checkout(self.database).await?;
+        let value = Setting::find(key, &mut conn)
+            .await?
+            .map(|setting| setting.value);
+        self.cache.insert(key, value.clone(), now);
+
+        Ok(value)
+    }
+
+    /// Store `value`, which should be the JSON of the setting `key`.
+    pub async fn set_raw(&self, key: &str, value: &str) -> Result<()> {
+        let mut conn = metrics::checkout(self.database).await?;
+        Setting::set(key, value, self.clock.now(), &mut conn).await?;
+        self.cache.invalidate(key);
+
+        Ok(())
+    }
+
+    /// Revert the setting `key` to its default.
+    pub async fn reset_raw(&self, key: &str) -> Result<()> {
+        let mut conn = metrics::checkout(self.database).await?;
+        Setting::unset(key, &mut conn).await?;
+        self.cache.invalidate(key);
+
+        Ok(())
+    }
+}
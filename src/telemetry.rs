@@ -0,0 +1,139 @@
+//! Opt-in reports of lowboy's own failures, to help its maintainers find the deployment problems
+//! apps commonly run into.
+//!
+//! Telemetry is off unless an endpoint is configured:
+//!
+//! ```yaml
+//! telemetry:
+//!   endpoint: https://telemetry.example.com/lowboy
+//! ```
+//!
+//! Only two things are reported, each as a JSON [`Report`] `POST`ed to the endpoint:
+//!
+//! - panics in lowboy's own code, with where they happened, but not their message, which can
+//!   include request data
+//! - failures running lowboy's migrations at boot, with the migration error
+//!
+//! Reports include lowboy's version, the rustc it was built with, and the OS and architecture.
+//! They never include the app's name, config, requests or anything about its users. Panics in the
+//! app's code aren't reported.
+
+use std::panic::{self, PanicHookInfo};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Reports taking longer than this to send are dropped.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Config {
+    /// Where reports are `POST`ed.
+    pub endpoint: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportKind {
+    Panic,
+    MigrationFailure,
+}
+
+/// A failure reported to the telemetry endpoint, see the [module docs](self).
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub kind: ReportKind,
+    /// Where in lowboy a panic happened, or the migration error.
+    pub detail: String,
+    pub lowboy_version: &'static str,
+    pub lowboy_git_sha: &'static str,
+    pub rustc_version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+}
+
+impl Report {
+    fn new(kind: ReportKind, detail: String) -> Self {
+        Self {
+            kind,
+            detail,
+            lowboy_version: env!("CARGO_PKG_VERSION"),
+            lowboy_git_sha: env!("VERGEN_GIT_SHA"),
+            rustc_version: env!("VERGEN_RUSTC_SEMVER"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+        }
+    }
+
+    pub fn migration_failure(error: &impl std::fmt::Display) -> Self {
+        Self::new(ReportKind::MigrationFailure, error.to_string())
+    }
+
+    /// A report of the panic, if it happened in lowboy's code.
+    fn panic(info: &PanicHookInfo<'_>) -> Option<Self> {
+        let location = info.location()?;
+        if !is_lowboy_source(location.file()) {
+            return None;
+        }
+
+        Some(Self::new(
+            ReportKind::Panic,
+            format!("{}:{}:{}", location.file(), location.line(), location.column()),
+        ))
+    }
+}
+
+/// Whether `file` is one of lowboy's source files, going by where this one is.
+fn is_lowboy_source(file: &str) -> bool {
+    let source = file!()
+        .strip_suffix("telemetry.rs")
+        .unwrap_or(file!());
+
+    file.starts_with(source)
+}
+
+/// Turn on telemetry with `config`, reporting panics in lowboy's code from now on. Does nothing
+/// without a config, or once telemetry is already on.
+pub(crate) fn install(config: Option<Config>) {
+    let Some(config) = config else {
+        return;
+    };
+    if CONFIG.set(config).is_err() {
+        return;
+    }
+
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Some(report) = Report::panic(info) {
+            // Panic hooks can't wait, so the report is sent in the background, if there's a
+            // runtime to send it from.
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn(send(report));
+            }
+        }
+
+        previous_hook(info);
+    }));
+}
+
+/// Send `report`, if telemetry is on.
+pub(crate) async fn send(report: Report) {
+    let Some(config) = CONFIG.get() else {
+        return;
+    };
+
+    let sent = reqwest::Client::new()
+        .post(&config.endpoint)
+        .timeout(TIMEOUT)
+        .json(&report)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status);
+
+    if let Err(e) = sent {
+        tracing::debug!("couldn't send telemetry report: {e}");
+    }
+}
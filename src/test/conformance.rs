@@ -0,0 +1,456 @@
+//! A happy-path conformance suite for lowboy applications.
+//!
+//! Apps customize the registration/login forms and views, which makes it easy to break the auth
+//! flow without noticing. Running the suite against an [`App`](crate::App) boots it against a
+//! throwaway database and a mocked OAuth provider, then walks through registration, email
+//! verification, password and OAuth login (with the `oauth` feature), logout, and access to
+//! authenticated routes:
+//!
+//! ```ignore
+//! #[tokio::test]
+//! async fn conformance() {
+//!     lowboy::test::conformance::run::<MyApp, MyContext>(Options::default())
+//!         .await
+//!         .unwrap();
+//! }
+//! ```
+use axum::http::StatusCode;
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use base64::prelude::*;
+#[cfg(feature = "oauth")]
+use oauth2::url::Url;
+use serde_json::json;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::{location, TestClient};
+use crate::auth::{IdentityProvider, IdentityProviderConfig};
+use crate::context::CloneableAppContext;
+use crate::model::{UnverifiedEmail, User, UserModel as _};
+use crate::{
+    app, Config, EventOverflow, JournalMode, Lowboy, OversizedSession, PoolRecycling, SchemaCheck,
+    Synchronous, TempStore,
+};
+
+type Result<T> = std::result::Result<T, Error>;
+
+const MOCK_ACCESS_TOKEN: &str = "conformance-access-token";
+const MOCK_USERNAME: &str = "conformance-oauth";
+const MOCK_EMAIL: &str = "conformance-oauth@example.com";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Lowboy(#[from] crate::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Pool(#[from] deadpool::managed::PoolError<diesel_async::pooled_connection::PoolError>),
+
+    #[error(transparent)]
+    Diesel(#[from] diesel::result::Error),
+
+    #[error("{step}: {message}")]
+    Step {
+        step: &'static str,
+        message: String,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// A route which requires authentication.
+    pub protected_path: String,
+    /// A route which requires a permission the conformance users won't have.
+    pub forbidden_path: Option<String>,
+    pub username: String,
+    pub email: String,
+    pub password: String,
+    /// Additional fields required by the app's registration form.
+    pub registration_fields: Vec<(String, String)>,
+    /// Additional fields required by the app's login form.
+    pub login_fields: Vec<(String, String)>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            protected_path: "/".into(),
+            forbidden_path: None,
+            username: "conformance".into(),
+            email: "conformance@example.com".into(),
+            password: "conformance-password".into(),
+            registration_fields: vec![],
+            login_fields: vec![],
+        }
+    }
+}
+
+impl Options {
+    pub fn with_protected_path(mut self, path: impl Into<String>) -> Self {
+        self.protected_path = path.into();
+        self
+    }
+
+    pub fn with_forbidden_path(mut self, path: impl Into<String>) -> Self {
+        self.forbidden_path = Some(path.into());
+        self
+    }
+
+    pub fn with_registration_field(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.registration_fields.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_login_field(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.login_fields.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// Run the conformance suite against `App`, returning the first step that failed.
+pub async fn run<App: app::App<AC>, AC: CloneableAppContext>(options: Options) -> Result<()> {
+    let (provider_url, provider) = mock_provider().await?;
+    let database = std::env::temp_dir().join(format!("lowboy-conformance-{}.db", Uuid::new_v4()));
+
+    let mut github = IdentityProviderConfig::new(
+        IdentityProvider::GitHub,
+        "conformance",
+        "conformance",
+        format!("{provider_url}/authorize"),
+        format!("{provider_url}/token"),
+    );
+    github.userinfo_url = Some(format!("{provider_url}/user"));
+
+    let session_key: Vec<u8> = (0..4).flat_map(|_| Uuid::new_v4().into_bytes()).collect();
+    let config = Config {
+        listen_addr: [127, 0, 0, 1].into(),
+        listen_port: 3000,
+        public_url: None,
+        tls: None,
+        grpc_port: None,
+        static_dir: "static".into(),
+        database_url: database.to_string_lossy().into_owned(),
+        database_pool_size: 4,
+        database_pool_wait_timeout: None,
+        database_pool_create_timeout: None,
+        database_pool_recycle_timeout: None,
+        database_pool_recycling: PoolRecycling::default(),
+        database_statement_cache: true,
+        database_warm_up: false,
+        database_pool_slow_checkout: 100,
+        database_journal_mode: JournalMode::default(),
+        database_synchronous: Synchronous::default(),
+        database_busy_timeout: 30000,
+        database_cache_size: None,
+        database_temp_store: TempStore::default(),
+        database_mmap_size: None,
+        database_attach: vec![],
+        database_schema_check: SchemaCheck::Abort,
+        password_hash_parallelism: None,
+        default_locale: None,
+        default_timezone: "UTC".to_string(),
+        session_max_size: 65536,
+        session_oversized: OversizedSession::default(),
+        session_key: BASE64_STANDARD.encode(session_key),
+        session_cookie_domain: None,
+        session_cookie_path: "/".to_string(),
+        // The suite submits forms as soon as they're shown.
+        bot_min_fill_time: 0,
+        captcha: None,
+        oauth_providers: vec![github],
+        mailer: None,
+        telemetry: None,
+        minify_html: false,
+        events_capacity: 32,
+        events_overflow: EventOverflow::default(),
+        events_send_timeout: 1000,
+        jobs_workers: 4,
+        jobs_poll_interval: 1000,
+        events_max_connections: 5,
+        events_idle_timeout: 3600,
+        json_body_limit: 1048576,
+        request_watchdog: 30,
+        profiling: false,
+        metrics: false,
+        version_endpoint: false,
+        version_header: false,
+        startup_banner: false,
+    };
+
+    let result = async {
+        let lowboy = Lowboy::<AC>::boot_with_config(config).await?;
+        let router = lowboy
+            .router::<App>()
+            .await?
+            .with_state(lowboy.context().clone());
+
+        let mut suite = Suite {
+            client: TestClient::new(router),
+            context: lowboy.context(),
+            options: &options,
+        };
+
+        suite.anonymous_access().await?;
+        suite.register().await?;
+        suite.verify_email().await?;
+        suite.password_login().await?;
+        suite.logout().await?;
+
+        #[cfg(feature = "oauth")]
+        {
+            suite.oauth_login().await?;
+            suite.logout().await?;
+        }
+
+        Ok::<_, Error>(())
+    }
+    .await;
+
+    provider.abort();
+    for suffix in ["", "-wal", "-shm"] {
+        let mut path = database.clone().into_os_string();
+        path.push(suffix);
+        let _ = std::fs::remove_file(path);
+    }
+
+    result
+}
+
+struct Suite<'a, AC: CloneableAppContext> {
+    client: TestClient,
+    context: &'a AC,
+    options: &'a Options,
+}
+
+impl<AC: CloneableAppContext> Suite<'_, AC> {
+    async fn anonymous_access(&mut self) -> Result<()> {
+        const STEP: &str = "anonymous access";
+
+        let response = self.client.get(&self.options.protected_path).await;
+        expect_login_redirect(STEP, &response)
+    }
+
+    async fn register(&mut self) -> Result<()> {
+        const STEP: &str = "register";
+
+        let response = self.client.get("/register").await;
+        expect_status(STEP, &response, StatusCode::OK)?;
+
+        let mut fields = vec![
+            ("username".to_string(), self.options.username.clone()),
+            ("email".to_string(), self.options.email.clone()),
+            ("password".to_string(), self.options.password.clone()),
+        ];
+        fields.extend(self.options.registration_fields.iter().cloned());
+
+        let response = self.client.post_form("/register", &fields).await;
+        expect_login_redirect(STEP, &response)?;
+
+        let mut conn = self.context.database().get().await?;
+        if User::find_by_username(&self.options.username, &mut conn)
+            .await?
+            .is_none()
+        {
+            return Err(failure(STEP, "the registered user was not created"));
+        }
+
+        Ok(())
+    }
+
+    async fn verify_email(&mut self) -> Result<()> {
+        const STEP: &str = "verify email";
+
+        let mut conn = self.context.database().get().await?;
+        let Some(email) = UnverifiedEmail::find_by_address(&self.options.email, &mut conn).await?
+        else {
+            return Err(failure(STEP, "no verification token was created"));
+        };
+
+        let path = format!(
+            "/email/{address}/verify/{token}",
+            address = email.address,
+            token = email.token.secret
+        );
+        let response = self.client.get(&path).await;
+        expect_login_redirect(STEP, &response)?;
+
+        let verified = User::find_by_username(&self.options.username, &mut conn)
+            .await?
+            .is_some_and(|user| user.email.verified);
+        if !verified {
+            return Err(failure(STEP, "the email address was not marked as verified"));
+        }
+
+        Ok(())
+    }
+
+    async fn password_login(&mut self) -> Result<()> {
+        const STEP: &str = "password login";
+
+        let response = self.client.get("/login").await;
+        expect_status(STEP, &response, StatusCode::OK)?;
+
+        let mut fields = vec![
+            ("username".to_string(), self.options.username.clone()),
+            ("password".to_string(), self.options.password.clone()),
+        ];
+        fields.extend(self.options.login_fields.iter().cloned());
+
+        let response = self.client.post_form("/login", &fields).await;
+        let location = expect_redirect(STEP, &response)?;
+        if location.starts_with("/login") {
+            return Err(failure(STEP, "valid credentials were rejected"));
+        }
+
+        self.authenticated_access(STEP).await
+    }
+
+    #[cfg(feature = "oauth")]
+    async fn oauth_login(&mut self) -> Result<()> {
+        const STEP: &str = "oauth login";
+
+        let mut fields = vec![
+            ("username".to_string(), String::new()),
+            ("password".to_string(), String::new()),
+        ];
+        fields.extend(self.options.login_fields.iter().cloned());
+
+        let response = self.client.post_form("/login/oauth/github", &fields).await;
+        let authorize_url = expect_redirect(STEP, &response)?;
+        let state = Url::parse(&authorize_url)
+            .ok()
+            .and_then(|url| {
+                url.query_pairs()
+                    .find(|(name, _)| name == "state")
+                    .map(|(_, state)| state.into_owned())
+            })
+            .ok_or_else(|| failure(STEP, "the authorization url is missing the csrf state"))?;
+
+        let callback = format!(
+            "/login/oauth/github/callback?intermediary_redirect=false&code=conformance&state={state}"
+        );
+        let response = self.client.get(&callback).await;
+        let authenticate = expect_redirect(STEP, &response)?;
+
+        let response = self.client.get(&authenticate).await;
+        let location = expect_redirect(STEP, &response)?;
+        if location.starts_with("/login") {
+            return Err(failure(STEP, "the oauth login was rejected"));
+        }
+
+        let mut conn = self.context.database().get().await?;
+        if User::find_by_username(MOCK_USERNAME, &mut conn)
+            .await?
+            .is_none()
+        {
+            return Err(failure(STEP, "the oauth user was not created"));
+        }
+
+        self.authenticated_access(STEP).await
+    }
+
+    async fn logout(&mut self) -> Result<()> {
+        const STEP: &str = "logout";
+
+        let response = self.client.get("/logout").await;
+        expect_redirect(STEP, &response)?;
+
+        let response = self.client.get(&self.options.protected_path).await;
+        expect_login_redirect(STEP, &response)
+    }
+
+    async fn authenticated_access(&mut self, step: &'static str) -> Result<()> {
+        let response = self.client.get(&self.options.protected_path).await;
+        expect_status(step, &response, StatusCode::OK)?;
+
+        if let Some(ref path) = self.options.forbidden_path {
+            let response = self.client.get(path).await;
+            expect_status(step, &response, StatusCode::FORBIDDEN)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Serve just enough of an OAuth provider to complete the code exchange and user info lookup.
+async fn mock_provider() -> Result<(String, JoinHandle<()>)> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+
+    let router = Router::new()
+        .route(
+            "/token",
+            post(|| async {
+                Json(json!({
+                    "access_token": MOCK_ACCESS_TOKEN,
+                    "token_type": "bearer",
+                }))
+            }),
+        )
+        .route(
+            "/user",
+            get(|| async {
+                Json(json!({
+                    "login": MOCK_USERNAME,
+                    "email": MOCK_EMAIL,
+                    "avatar_url": "",
+                    "name": "Conformance",
+                }))
+            }),
+        );
+
+    let server = tokio::spawn(async move {
+        let _ = axum::serve(listener, router).await;
+    });
+
+    Ok((url, server))
+}
+
+fn failure(step: &'static str, message: impl Into<String>) -> Error {
+    Error::Step {
+        step,
+        message: message.into(),
+    }
+}
+
+fn expect_status(step: &'static str, response: &Response, status: StatusCode) -> Result<()> {
+    if response.status() != status {
+        return Err(failure(
+            step,
+            format!("expected {status}, got {}", response.status()),
+        ));
+    }
+
+    Ok(())
+}
+
+fn expect_redirect(step: &'static str, response: &Response) -> Result<String> {
+    match location(response) {
+        Some(location) if response.status().is_redirection() => Ok(location.to_string()),
+        _ => Err(failure(
+            step,
+            format!("expected a redirect, got {}", response.status()),
+        )),
+    }
+}
+
+fn expect_login_redirect(step: &'static str, response: &Response) -> Result<()> {
+    let location = expect_redirect(step, response)?;
+    if !location.starts_with("/login") {
+        return Err(failure(
+            step,
+            format!("expected a redirect to /login, got {location}"),
+        ));
+    }
+
+    Ok(())
+}
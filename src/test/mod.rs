@@ -0,0 +1,166 @@
+//! Utilities for testing lowboy applications.
+use std::collections::BTreeMap;
+
+use axum::body::Body;
+use axum::http::header::{CONTENT_TYPE, COOKIE, LOCATION, SET_COOKIE};
+use axum::http::{request, Method, Request};
+use axum::response::Response;
+use axum::Router;
+#[cfg(test)]
+use diesel_async::pooled_connection::deadpool::Pool;
+use tower::ServiceExt as _;
+
+#[cfg(test)]
+use crate::Connection;
+
+pub mod conformance;
+
+/// A minimal client that drives a [`Router`] in-process, keeping track of cookies between
+/// requests so sessions behave the way they do in a browser.
+#[derive(Clone)]
+pub struct TestClient {
+    router: Router,
+    cookies: BTreeMap<String, String>,
+}
+
+impl TestClient {
+    pub fn new(router: Router) -> Self {
+        Self {
+            router,
+            cookies: BTreeMap::new(),
+        }
+    }
+
+    pub async fn get(&mut self, uri: &str) -> Response {
+        let request = Request::builder().method(Method::GET).uri(uri);
+
+        self.send(request, Body::empty()).await
+    }
+
+    pub async fn post_form<K, V>(&mut self, uri: &str, fields: &[(K, V)]) -> Response
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let body = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(fields.iter().map(|(k, v)| (k.as_ref(), v.as_ref())))
+            .finish();
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded");
+
+        self.send(request, Body::from(body)).await
+    }
+
+    /// Forget all cookies, as if the browser was closed.
+    pub fn clear_cookies(&mut self) {
+        self.cookies.clear();
+    }
+
+    async fn send(&mut self, request: request::Builder, body: Body) -> Response {
+        let request = if self.cookies.is_empty() {
+            request
+        } else {
+            let cookies = self
+                .cookies
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join("; ");
+            request.header(COOKIE, cookies)
+        };
+
+        let request = request.body(body).expect("request should be valid");
+        let response = self
+            .router
+            .clone()
+            .oneshot(request)
+            .await
+            .unwrap_or_else(|never| match never {});
+
+        self.store_cookies(&response);
+
+        response
+    }
+
+    fn store_cookies(&mut self, response: &Response) {
+        for header in response.headers().get_all(SET_COOKIE) {
+            let Ok(header) = header.to_str() else {
+                continue;
+            };
+            let mut attributes = header.split(';').map(str::trim);
+            let Some((name, value)) = attributes.next().and_then(|pair| pair.split_once('=')) else {
+                continue;
+            };
+
+            let removed = attributes.any(|attribute| attribute.eq_ignore_ascii_case("max-age=0"));
+            if removed {
+                self.cookies.remove(name);
+            } else {
+                self.cookies.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+}
+
+/// The `Location` header of a redirect response.
+pub fn location(response: &Response) -> Option<&str> {
+    response
+        .headers()
+        .get(LOCATION)
+        .and_then(|location| location.to_str().ok())
+}
+
+/// A fresh database with lowboy's migrations run, for lowboy's own tests. It's a file in the
+/// temporary directory, rather than in memory, so every connection in the pool sees the same data.
+#[cfg(test)]
+pub(crate) async fn database() -> Pool<Connection> {
+    use diesel::Connection as _;
+    use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
+    use diesel_async::{AsyncConnection as _, SimpleAsyncConnection as _};
+    use diesel_migrations::MigrationHarness as _;
+    use futures::FutureExt as _;
+
+    let path = std::env::temp_dir().join(format!("lowboy-test-{}.db", uuid::Uuid::new_v4()));
+    let url = path.display().to_string();
+
+    let mut conn = diesel::SqliteConnection::establish(&url).expect("test database should open");
+    conn.run_pending_migrations(crate::MIGRATIONS)
+        .expect("test database should migrate");
+
+    let mut manager_config = ManagerConfig::default();
+    manager_config.custom_setup = Box::new(|url| {
+        async move {
+            let mut conn = Connection::establish(url).await?;
+            conn.batch_execute("PRAGMA busy_timeout = 5000; PRAGMA foreign_keys = ON;")
+                .await
+                .map_err(diesel::ConnectionError::CouldntSetupConfiguration)?;
+            Ok(conn)
+        }
+        .boxed()
+    });
+    let manager = AsyncDieselConnectionManager::new_with_config(url, manager_config);
+
+    Pool::builder(manager)
+        .max_size(4)
+        .build()
+        .expect("test database pool should build")
+}
+
+/// A [`crate::context::LowboyContext`] on a fresh [`database`], for lowboy's own tests.
+#[cfg(test)]
+pub(crate) async fn context() -> crate::context::LowboyContext {
+    use std::time::Duration;
+
+    use crate::config::EventOverflow;
+    use crate::context::{AppContext as _, LowboyContext};
+    use crate::jobs::Scheduler;
+    use crate::Events;
+
+    let database = database().await;
+    let events = Events::new(16, EventOverflow::DropNew, Duration::from_secs(1));
+    let scheduler = Scheduler::start().await.unwrap();
+
+    LowboyContext::create(database, events, scheduler, None).unwrap()
+}
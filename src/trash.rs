@@ -0,0 +1,262 @@
+//! The trash, where [`SoftDelete`] models wait to be restored or purged.
+//!
+//! Apps register their soft deleted models in [`crate::App::trash`], each with how long it's kept
+//! in the trash. Trashed models are listed in the admin at `/admin/trash`, where they can be
+//! restored until they expire, and every night the [`crate::jobs::PURGE_TRASH`] job removes the
+//! expired ones for good:
+//!
+//! ```ignore
+//! fn trash(trash: &mut Trash) {
+//!     trash
+//!         .register::<Post>(DEFAULT_RETENTION)
+//!         .register::<Comment>(TimeDelta::days(7));
+//! }
+//! ```
+//!
+//! Restoring and purging are reported to [`crate::AppContext::on_model_event`] as
+//! [`ModelEvent::restored`] and [`ModelEvent::deleted`].
+
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, LazyLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use diesel::QueryResult;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::context::AppContext;
+use crate::model::{AnyModel as _, ModelEvent, SoftDelete};
+use crate::{jobs, metrics, Connection};
+
+static TRASH: LazyLock<RwLock<Trash>> = LazyLock::new(Default::default);
+
+/// How long trashed models are kept, unless they're registered with their own retention.
+pub const DEFAULT_RETENTION: TimeDelta = TimeDelta::days(30);
+
+/// A trashed model, as it's listed in the trash.
+#[derive(Clone, Debug, Serialize)]
+pub struct TrashedItem {
+    /// The model's table, e.g. `post`.
+    pub model: &'static str,
+    /// The model's name for people, e.g. `Post`.
+    pub display_name: String,
+    pub id: i32,
+    /// The model serialized as a JSON object.
+    pub data: Value,
+    pub deleted_at: DateTime<Utc>,
+    /// When the model is purged, and can no longer be restored.
+    pub expires_at: DateTime<Utc>,
+}
+
+#[async_trait::async_trait]
+trait AnyTrashable: Send + Sync {
+    fn retention(&self) -> TimeDelta;
+
+    async fn trashed(&self, conn: &mut Connection) -> QueryResult<Vec<TrashedItem>>;
+
+    async fn restore(
+        &self,
+        id: i32,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<usize>;
+
+    async fn delete(&self, id: i32, conn: &mut Connection) -> QueryResult<usize>;
+
+    async fn purge(&self, now: DateTime<Utc>, conn: &mut Connection) -> QueryResult<Vec<i32>>;
+}
+
+struct Trashable<M> {
+    retention: TimeDelta,
+    model: PhantomData<fn() -> M>,
+}
+
+#[async_trait::async_trait]
+impl<M> AnyTrashable for Trashable<M>
+where
+    M: SoftDelete + Serialize + Send + Sync + 'static,
+{
+    fn retention(&self) -> TimeDelta {
+        self.retention
+    }
+
+    async fn trashed(&self, conn: &mut Connection) -> QueryResult<Vec<TrashedItem>> {
+        let models = M::trashed(conn).await?;
+
+        let mut items = Vec::with_capacity(models.len());
+        for model in models {
+            let Some(deleted_at) = model.deleted_at() else {
+                continue;
+            };
+            let data = serde_json::to_value(&model)
+                .map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))?;
+            let Some(id) = data
+                .get(M::PRIMARY_KEY)
+                .and_then(Value::as_i64)
+                .and_then(|id| i32::try_from(id).ok())
+            else {
+                continue;
+            };
+
+            items.push(TrashedItem {
+                model: M::TABLE_NAME,
+                display_name: model.display_name(),
+                id,
+                data,
+                deleted_at,
+                expires_at: deleted_at + self.retention,
+            });
+        }
+
+        Ok(items)
+    }
+
+    async fn restore(
+        &self,
+        id: i32,
+        now: DateTime<Utc>,
+        conn: &mut Connection,
+    ) -> QueryResult<usize> {
+        M::restore_since(id, now - self.retention, conn).await
+    }
+
+    async fn delete(&self, id: i32, conn: &mut Connection) -> QueryResult<usize> {
+        M::delete_trashed(id, conn).await
+    }
+
+    async fn purge(&self, now: DateTime<Utc>, conn: &mut Connection) -> QueryResult<Vec<i32>> {
+        M::purge_trashed(now - self.retention, conn).await
+    }
+}
+
+/// The registry of [`SoftDelete`] models kept in the trash, by table name.
+#[derive(Clone, Default)]
+pub struct Trash {
+    models: BTreeMap<&'static str, Arc<dyn AnyTrashable>>,
+}
+
+impl Trash {
+    pub fn global() -> RwLockReadGuard<'static, Self> {
+        TRASH.read().expect("trash lock should not be poisoned")
+    }
+
+    pub fn global_mut() -> RwLockWriteGuard<'static, Self> {
+        TRASH.write().expect("trash lock should not be poisoned")
+    }
+
+    /// Register the model `M`, keeping it in the trash for `retention` before it's purged.
+    pub fn register<M>(&mut self, retention: TimeDelta) -> &mut Self
+    where
+        M: SoftDelete + Serialize + Send + Sync + 'static,
+    {
+        let trashable = Trashable::<M> {
+            retention,
+            model: PhantomData,
+        };
+        self.models.insert(M::TABLE_NAME, Arc::new(trashable));
+        self
+    }
+
+    /// How long the model `model` is kept in the trash, if it's registered.
+    pub fn retention(&self, model: &str) -> Option<TimeDelta> {
+        self.models.get(model).map(|trashable| trashable.retention())
+    }
+
+    fn get(model: &str) -> Option<(&'static str, Arc<dyn AnyTrashable>)> {
+        Self::global()
+            .models
+            .get_key_value(model)
+            .map(|(&model, trashable)| (model, trashable.clone()))
+    }
+
+    /// Copied out, so the registry isn't locked while they're queried.
+    fn models() -> Vec<(&'static str, Arc<dyn AnyTrashable>)> {
+        Self::global()
+            .models
+            .iter()
+            .map(|(&model, trashable)| (model, trashable.clone()))
+            .collect()
+    }
+}
+
+/// Every registered model in the trash, latest trashed first.
+pub async fn list(conn: &mut Connection) -> QueryResult<Vec<TrashedItem>> {
+    let mut items = vec![];
+    for (_, trashable) in Trash::models() {
+        items.extend(trashable.trashed(conn).await?);
+    }
+    items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+
+    Ok(items)
+}
+
+/// Restore the trashed `model` with `id`, returning whether it was restored. Models that aren't
+/// trashed, or whose retention has passed, aren't.
+pub async fn restore(context: &dyn AppContext, model: &str, id: i32) -> anyhow::Result<bool> {
+    let Some((model, trashable)) = Trash::get(model) else {
+        return Ok(false);
+    };
+
+    let restored = {
+        let mut conn = metrics::checkout(context.database()).await?;
+        trashable.restore(id, context.clock().now(), &mut conn).await? > 0
+    };
+    if restored {
+        context.on_model_event(&ModelEvent::restored(model, id)).await?;
+    }
+
+    Ok(restored)
+}
+
+/// Remove the trashed `model` with `id` for good, without waiting for it to expire, returning
+/// whether it was removed.
+pub async fn delete(context: &dyn AppContext, model: &str, id: i32) -> anyhow::Result<bool> {
+    let Some((model, trashable)) = Trash::get(model) else {
+        return Ok(false);
+    };
+
+    let deleted = {
+        let mut conn = metrics::checkout(context.database()).await?;
+        trashable.delete(id, &mut conn).await? > 0
+    };
+    if deleted {
+        context.on_model_event(&ModelEvent::deleted(model, id)).await?;
+    }
+
+    Ok(deleted)
+}
+
+/// Remove the registered models whose retention has passed for good, returning how many were
+/// removed.
+pub async fn purge_expired(context: &dyn AppContext) -> anyhow::Result<usize> {
+    let now = context.clock().now();
+
+    let mut purged = 0;
+    for (model, trashable) in Trash::models() {
+        let ids = {
+            let mut conn = metrics::checkout(context.database()).await?;
+            trashable.purge(now, &mut conn).await?
+        };
+
+        for id in ids {
+            // It's purged either way, so one failing handler doesn't hold up the rest.
+            let event = ModelEvent::deleted(model, id);
+            if let Err(e) = context.on_model_event(&event).await {
+                warn!("couldn't handle purging {model}({id}): {e}");
+            }
+            purged += 1;
+        }
+    }
+
+    Ok(purged)
+}
+
+pub(crate) async fn run(context: &dyn AppContext) {
+    match metrics::track_job(jobs::PURGE_TRASH.name, purge_expired(context)).await {
+        Ok(0) => (),
+        Ok(purged) => info!("purged {purged} expired models from the trash"),
+        Err(e) => warn!("couldn't purge the trash: {e}"),
+    }
+}
@@ -0,0 +1,123 @@
+//! Pruning the history of [`Versioned`] models.
+//!
+//! Apps register their versioned models in [`crate::App::versioned_models`], each with how much of
+//! its history to keep, and every night the [`crate::jobs::PRUNE_VERSIONS`] job deletes the
+//! versions outside of it:
+//!
+//! ```ignore
+//! fn versioned_models(models: &mut VersionedModels) {
+//!     models
+//!         .register::<Post>(Retention::default().max_versions(50))
+//!         .register::<Page>(Retention::default().max_age(TimeDelta::days(365)));
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, LazyLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use diesel::QueryResult;
+use tracing::{info, warn};
+
+use crate::context::AppContext;
+use crate::model::Versioned;
+use crate::{jobs, metrics, Connection};
+
+static VERSIONED_MODELS: LazyLock<RwLock<VersionedModels>> = LazyLock::new(Default::default);
+
+/// How much of a model's history is kept. By default every version is kept.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Retention {
+    /// How many of each model's latest versions are kept.
+    pub max_versions: Option<i64>,
+    /// How long versions are kept.
+    pub max_age: Option<TimeDelta>,
+}
+
+impl Retention {
+    pub fn max_versions(self, max_versions: i64) -> Self {
+        Self {
+            max_versions: Some(max_versions),
+            ..self
+        }
+    }
+
+    pub fn max_age(self, max_age: TimeDelta) -> Self {
+        Self {
+            max_age: Some(max_age),
+            ..self
+        }
+    }
+}
+
+#[async_trait::async_trait]
+trait AnyVersioned: Send + Sync {
+    async fn prune(&self, now: DateTime<Utc>, conn: &mut Connection) -> QueryResult<usize>;
+}
+
+struct Pruner<M> {
+    retention: Retention,
+    model: PhantomData<fn() -> M>,
+}
+
+#[async_trait::async_trait]
+impl<M: Versioned> AnyVersioned for Pruner<M> {
+    async fn prune(&self, now: DateTime<Utc>, conn: &mut Connection) -> QueryResult<usize> {
+        M::prune_versions(self.retention.max_versions, self.retention.max_age, now, conn).await
+    }
+}
+
+/// The registry of [`Versioned`] models, by table name.
+#[derive(Clone, Default)]
+pub struct VersionedModels {
+    models: BTreeMap<&'static str, Arc<dyn AnyVersioned>>,
+}
+
+impl VersionedModels {
+    pub fn global() -> RwLockReadGuard<'static, Self> {
+        VERSIONED_MODELS
+            .read()
+            .expect("versioned models lock should not be poisoned")
+    }
+
+    pub fn global_mut() -> RwLockWriteGuard<'static, Self> {
+        VERSIONED_MODELS
+            .write()
+            .expect("versioned models lock should not be poisoned")
+    }
+
+    /// Register the model `M`, keeping its history according to `retention`.
+    pub fn register<M: Versioned>(&mut self, retention: Retention) -> &mut Self {
+        let pruner = Pruner::<M> {
+            retention,
+            model: PhantomData,
+        };
+        self.models.insert(M::TABLE_NAME, Arc::new(pruner));
+        self
+    }
+}
+
+/// Delete the registered models' versions outside of their retention, returning how many were
+/// deleted.
+pub async fn prune(context: &dyn AppContext) -> anyhow::Result<usize> {
+    // Copied out, so the registry isn't locked while pruning.
+    let models: Vec<_> = VersionedModels::global().models.values().cloned().collect();
+    let now = context.clock().now();
+
+    let mut conn = metrics::checkout(context.database()).await?;
+    let mut pruned = 0;
+    for model in models {
+        pruned += model.prune(now, &mut conn).await?;
+    }
+
+    Ok(pruned)
+}
+
+pub(crate) async fn run(context: &dyn AppContext) {
+    match metrics::track_job(jobs::PRUNE_VERSIONS.name, prune(context)).await {
+        Ok(0) => (),
+        Ok(pruned) => info!("pruned {pruned} versions"),
+        Err(e) => warn!("couldn't prune versions: {e}"),
+    }
+}
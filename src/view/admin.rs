@@ -0,0 +1,116 @@
+use chrono::{DateTime, Utc};
+use rinja::Template;
+use serde::Serialize;
+
+use crate::model::{
+    Announcement, AnnouncementLevel, DeadLetter, Permission, QueuedJob, Role, User, UserRecord,
+};
+use crate::trash::TrashedItem;
+use crate::view::filters;
+
+#[derive(Clone, Template)]
+#[template(path = "admin/roles.html")]
+pub struct AdminRoles {
+    pub roles: Vec<Role>,
+}
+
+#[derive(Clone, Template)]
+#[template(path = "admin/role.html")]
+pub struct AdminRole {
+    pub role: Role,
+    /// Every permission, and whether it's granted to the role.
+    pub permissions: Vec<(Permission, bool)>,
+    pub users: Vec<UserRecord>,
+}
+
+#[derive(Clone, Template)]
+#[template(path = "admin/service-accounts.html")]
+pub struct AdminServiceAccounts {
+    pub service_accounts: Vec<User>,
+    /// A newly created or rotated API token, and the username of the account it belongs to.
+    pub token: Option<(String, String)>,
+}
+
+#[derive(Clone, Template)]
+#[template(path = "admin/dead-letters.html")]
+pub struct AdminDeadLetters {
+    pub dead_letters: Vec<DeadLetter>,
+}
+
+/// A scheduled job, and when it'll run next.
+#[derive(Clone, Debug, Serialize)]
+pub struct AdminScheduledJob {
+    pub name: String,
+    pub schedule: String,
+    pub next_runs: Vec<DateTime<Utc>>,
+}
+
+#[derive(Clone, Template)]
+#[template(path = "admin/jobs.html")]
+pub struct AdminJobs {
+    pub scheduled: Vec<AdminScheduledJob>,
+    pub queued: Vec<QueuedJob>,
+}
+
+/// A registered setting, and its current value as it's entered in the admin.
+#[derive(Clone, Debug, Serialize)]
+pub struct AdminSetting {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub description: &'static str,
+    pub value: String,
+    /// Whether the setting is on or off, rather than text.
+    pub is_bool: bool,
+    /// Whether the setting has been changed from its default.
+    pub is_set: bool,
+}
+
+#[derive(Clone, Template)]
+#[template(path = "admin/settings.html")]
+pub struct AdminSettings {
+    pub settings: Vec<AdminSetting>,
+}
+
+#[derive(Clone, Template)]
+#[template(path = "admin/announcements.html")]
+pub struct AdminAnnouncements {
+    pub announcements: Vec<Announcement>,
+    /// The roles announcements can be shown to.
+    pub roles: Vec<Role>,
+    pub now: DateTime<Utc>,
+}
+
+impl AdminAnnouncements {
+    pub fn levels(&self) -> &'static [AnnouncementLevel] {
+        AnnouncementLevel::ALL
+    }
+
+    /// Who `announcement` is shown to.
+    pub fn audience(&self, announcement: &Announcement) -> &str {
+        let Some(role_id) = announcement.role_id else {
+            return "Everyone";
+        };
+
+        self.roles
+            .iter()
+            .find(|role| role.id == role_id)
+            .map_or("Unknown role", |role| role.name.as_str())
+    }
+
+    pub fn status(&self, announcement: &Announcement) -> &'static str {
+        if announcement.is_active(self.now) {
+            "Active"
+        } else if announcement.starts_at > self.now {
+            "Scheduled"
+        } else {
+            "Ended"
+        }
+    }
+}
+
+#[derive(Clone, Template)]
+#[template(path = "admin/trash.html")]
+pub struct AdminTrash {
+    /// The trashed models, latest trashed first.
+    pub items: Vec<TrashedItem>,
+}
@@ -0,0 +1,413 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, LazyLock, RwLock, RwLockWriteGuard};
+
+use axum_messages::{Level, Message};
+use rinja::Template;
+use tracing::warn;
+
+use crate::controller::icons::MANIFEST_PATH;
+use crate::form::FormErrors;
+use crate::model::Announcement;
+
+static COMPONENTS: LazyLock<RwLock<Components>> = LazyLock::new(Default::default);
+
+/// A reusable partial, rendered from typed props.
+///
+/// Components are registered by name in [`Components`], so templates can render them without
+/// knowing which implementation the app chose:
+///
+/// ```html
+/// {{ lowboy::view::component("pagination", pagination)|safe }}
+/// ```
+pub trait Component: Send + Sync + 'static {
+    type Props: 'static;
+
+    fn render(&self, props: &Self::Props) -> rinja::Result<String>;
+}
+
+/// Renders props which are templates themselves, as lowboy's built-in components do.
+pub struct TemplateComponent<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for TemplateComponent<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Template + 'static> Component for TemplateComponent<T> {
+    type Props = T;
+
+    fn render(&self, props: &T) -> rinja::Result<String> {
+        props.render()
+    }
+}
+
+trait AnyComponent: Send + Sync {
+    /// Render the component, or `None` if `props` aren't the component's props.
+    fn render_any(&self, props: &dyn Any) -> Option<rinja::Result<String>>;
+}
+
+impl<C: Component> AnyComponent for C {
+    fn render_any(&self, props: &dyn Any) -> Option<rinja::Result<String>> {
+        props
+            .downcast_ref::<C::Props>()
+            .map(|props| self.render(props))
+    }
+}
+
+/// The registry of components, by name.
+///
+/// Lowboy registers `pagination`, `messages`, `field_errors`, `nav`, `progress_bar`,
+/// `connection_banner`, `announcements`, `icon_links`, `provider_buttons` and `bulk_results`. Apps
+/// add their own (or replace lowboy's, keeping the props) in [`crate::App::components`].
+#[derive(Clone)]
+pub struct Components(HashMap<String, Arc<dyn AnyComponent>>);
+
+impl Default for Components {
+    fn default() -> Self {
+        let mut components = Self(HashMap::new());
+        components
+            .register("pagination", TemplateComponent::<Pagination>::default())
+            .register("messages", TemplateComponent::<FlashMessages>::default())
+            .register("field_errors", TemplateComponent::<FieldErrors>::default())
+            .register("nav", TemplateComponent::<Nav>::default())
+            .register("progress_bar", TemplateComponent::<ProgressBar>::default())
+            .register(
+                "connection_banner",
+                TemplateComponent::<ConnectionBanner>::default(),
+            )
+            .register("announcements", TemplateComponent::<Announcements>::default())
+            .register("icon_links", TemplateComponent::<IconLinks>::default())
+            .register(
+                "provider_buttons",
+                TemplateComponent::<ProviderButtons>::default(),
+            )
+            .register("bulk_results", TemplateComponent::<BulkResults>::default());
+        components
+    }
+}
+
+impl Components {
+    /// The components used by [`component`].
+    pub fn global_mut() -> RwLockWriteGuard<'static, Self> {
+        COMPONENTS
+            .write()
+            .expect("components lock should not be poisoned")
+    }
+
+    /// Register `component` as `name`, replacing any component already registered with that name.
+    pub fn register(&mut self, name: impl Into<String>, component: impl Component) -> &mut Self {
+        self.0.insert(name.into(), Arc::new(component));
+        self
+    }
+
+    /// Render the component registered as `name`.
+    ///
+    /// Templates can't handle errors, so unknown components, props of the wrong type and
+    /// rendering errors are logged and render nothing.
+    pub fn render<P: 'static>(&self, name: &str, props: &P) -> String {
+        let Some(component) = self.0.get(name) else {
+            warn!("no component is registered as `{name}`");
+            return String::new();
+        };
+
+        match component.render_any(props) {
+            Some(Ok(html)) => html,
+            Some(Err(e)) => {
+                warn!("couldn't render component `{name}`: {e}");
+                String::new()
+            }
+            None => {
+                warn!(
+                    "component `{name}` was rendered with the wrong props: {}",
+                    std::any::type_name::<P>()
+                );
+                String::new()
+            }
+        }
+    }
+}
+
+/// Render the globally registered component `name`, e.g. from a template.
+pub fn component<P: 'static>(name: &str, props: &P) -> String {
+    COMPONENTS
+        .read()
+        .expect("components lock should not be poisoned")
+        .render(name, props)
+}
+
+/// Links to the previous and next pages, and the pages around the current one.
+#[derive(Clone, Debug, Template)]
+#[template(path = "components/pagination.html")]
+pub struct Pagination {
+    /// The current page, starting at 1.
+    pub page: u32,
+    pub total_pages: u32,
+    /// The path pages are linked to, with `page` added to its query.
+    pub path: String,
+}
+
+impl Pagination {
+    /// How many pages to link to on either side of the current one.
+    const WINDOW: u32 = 2;
+
+    pub fn new(page: u32, total_pages: u32, path: impl Into<String>) -> Self {
+        Self {
+            page,
+            total_pages,
+            path: path.into(),
+        }
+    }
+
+    pub fn href(&self, page: &u32) -> String {
+        let separator = if self.path.contains('?') { '&' } else { '?' };
+        format!("{}{separator}page={page}", self.path)
+    }
+
+    pub fn pages(&self) -> std::ops::RangeInclusive<u32> {
+        let first = self.page.saturating_sub(Self::WINDOW).max(1);
+        let last = (self.page + Self::WINDOW).min(self.total_pages);
+        first..=last
+    }
+}
+
+/// The flash messages added with [`axum_messages::Messages`].
+#[derive(Clone, Debug, Template)]
+#[template(path = "components/messages.html")]
+pub struct FlashMessages {
+    pub messages: Vec<Message>,
+}
+
+impl FlashMessages {
+    pub fn new(messages: Vec<Message>) -> Self {
+        Self { messages }
+    }
+
+    pub fn level(&self, message: &Message) -> &'static str {
+        match message.level {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Success => "success",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        }
+    }
+}
+
+/// The error messages for a single form field.
+#[derive(Clone, Debug, Template)]
+#[template(path = "components/field-errors.html")]
+pub struct FieldErrors {
+    pub name: String,
+    pub messages: Vec<String>,
+}
+
+impl FieldErrors {
+    pub fn new(errors: &FormErrors, name: impl Into<String>) -> Self {
+        let name = name.into();
+        Self {
+            messages: errors.messages(&name).to_vec(),
+            name,
+        }
+    }
+}
+
+/// A navigation menu.
+#[derive(Clone, Debug, Default, Template)]
+#[template(path = "components/nav.html")]
+pub struct Nav {
+    pub links: Vec<NavLink>,
+}
+
+impl Nav {
+    /// Add a link, marked active if it's `current_path`.
+    pub fn with_link(
+        mut self,
+        label: impl Into<String>,
+        href: impl Into<String>,
+        current_path: &str,
+    ) -> Self {
+        let href = href.into();
+        self.links.push(NavLink {
+            label: label.into(),
+            active: href == current_path,
+            href,
+        });
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NavLink {
+    pub label: String,
+    pub href: String,
+    pub active: bool,
+}
+
+/// A progress bar shown at the top of the page while htmx navigates.
+///
+/// Include it in the layout along with the lowboy client and its stylesheet, which drive and style
+/// it:
+///
+/// ```html
+/// {{ lowboy::view::component("progress_bar", lowboy::view::ProgressBar)|safe }}
+/// ```
+#[derive(Clone, Debug, Default, Template)]
+#[template(path = "components/progress-bar.html")]
+pub struct ProgressBar;
+
+/// A banner shown while the connection to the server's event stream is lost, whether it's managed
+/// by the lowboy client or the htmx SSE extension.
+#[derive(Clone, Debug, Template)]
+#[template(path = "components/connection-banner.html")]
+pub struct ConnectionBanner {
+    pub message: String,
+}
+
+impl ConnectionBanner {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl Default for ConnectionBanner {
+    fn default() -> Self {
+        Self::new("Connection lost, reconnecting…")
+    }
+}
+
+/// The active announcements shown to the user, see [`crate::model::Announcement`].
+///
+/// Include it in the layout with the announcements given to
+/// [`LowboyLayout::set_announcements`](super::LowboyLayout::set_announcements). It's reloaded from
+/// `/announcements` when a new announcement becomes active, by the lowboy client or the htmx SSE
+/// extension:
+///
+/// ```html
+/// {{ lowboy::view::component("announcements", announcements)|safe }}
+/// ```
+#[derive(Clone, Debug, Default, Template)]
+#[template(path = "components/announcements.html")]
+pub struct Announcements {
+    pub announcements: Vec<Announcement>,
+}
+
+impl Announcements {
+    pub fn new(announcements: Vec<Announcement>) -> Self {
+        Self { announcements }
+    }
+}
+
+/// The favicon, apple touch icon, web app manifest and theme color tags, for the layout's head.
+///
+/// ```html
+/// {{ lowboy::view::component("icon_links", lowboy::view::IconLinks::default())|safe }}
+/// ```
+#[derive(Clone, Debug, Template)]
+#[template(path = "components/icon-links.html")]
+pub struct IconLinks {
+    pub manifest_path: &'static str,
+    pub theme_color: Option<String>,
+}
+
+impl IconLinks {
+    pub fn with_theme_color(self, theme_color: impl Into<String>) -> Self {
+        Self {
+            theme_color: Some(theme_color.into()),
+            ..self
+        }
+    }
+}
+
+impl Default for IconLinks {
+    fn default() -> Self {
+        Self {
+            manifest_path: MANIFEST_PATH,
+            theme_color: None,
+        }
+    }
+}
+
+/// A "Sign in with" button for one of the configured OAuth providers.
+#[derive(Clone, Debug)]
+pub struct ProviderButton {
+    /// The provider's name in the OAuth routes, e.g. `github`.
+    pub name: String,
+    /// e.g. `GitHub`.
+    pub label: String,
+    /// The provider's icon, which stylesheets can pick with `[data-provider]`.
+    pub icon: String,
+}
+
+/// The sign in buttons of the configured OAuth providers, for the login and register forms.
+///
+/// ```html
+/// {{ lowboy::view::component("provider_buttons", providers)|safe }}
+/// ```
+#[derive(Clone, Debug, Default, Template)]
+#[template(path = "components/provider-buttons.html")]
+pub struct ProviderButtons {
+    pub providers: Vec<ProviderButton>,
+}
+
+impl ProviderButtons {
+    pub fn new(providers: Vec<ProviderButton>) -> Self {
+        Self { providers }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+}
+
+/// A record a bulk action couldn't be applied to, and why.
+#[derive(Clone, Debug)]
+pub struct BulkFailure {
+    pub id: i32,
+    pub reason: String,
+}
+
+/// The outcome of applying a bulk action to the selected records, see [`crate::bulk`].
+///
+/// It's rendered in place of the list page's `#lowboy-bulk-results` element, with the ids that
+/// succeeded in `data-succeeded` so the page can update their rows.
+#[derive(Clone, Debug, Template)]
+#[template(path = "components/bulk-results.html")]
+pub struct BulkResults {
+    /// The action's label, e.g. `Delete`.
+    pub action: String,
+    pub succeeded: Vec<i32>,
+    pub failed: Vec<BulkFailure>,
+}
+
+impl BulkResults {
+    pub fn new(action: impl Into<String>) -> Self {
+        Self {
+            action: action.into(),
+            succeeded: vec![],
+            failed: vec![],
+        }
+    }
+
+    /// e.g. `Delete: 3 succeeded, 1 failed.`
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{}: {} succeeded", self.action, self.succeeded.len());
+        if !self.failed.is_empty() {
+            summary.push_str(&format!(", {} failed", self.failed.len()));
+        }
+        summary.push('.');
+        summary
+    }
+
+    pub fn succeeded_ids(&self) -> String {
+        self.succeeded
+            .iter()
+            .map(i32::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
@@ -0,0 +1,142 @@
+use std::cell::RefCell;
+
+use chrono::{DateTime, Locale, Utc};
+use chrono_tz::Tz;
+
+thread_local! {
+    static RENDER_CONTEXT: RefCell<Option<RenderContext>> = const { RefCell::new(None) };
+}
+
+/// Who a page is being rendered for, so the date [`filters`] can show times in their timezone and
+/// locale without every view carrying them around.
+///
+/// Lowboy renders every view within the logged in user's context. Views rendered elsewhere (e.g.
+/// htmx fragments) can be wrapped with [`RenderContext::scope`], and otherwise fall back to UTC.
+#[derive(Clone, Debug)]
+pub struct RenderContext {
+    pub timezone: Tz,
+    pub locale: Option<String>,
+    /// The current time, for relative times.
+    pub now: DateTime<Utc>,
+}
+
+impl RenderContext {
+    /// Run `render` with this as the current render context.
+    pub fn scope<R>(self, render: impl FnOnce() -> R) -> R {
+        struct Restore(Option<RenderContext>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                RENDER_CONTEXT.set(self.0.take());
+            }
+        }
+
+        let _restore = Restore(RENDER_CONTEXT.replace(Some(self)));
+        render()
+    }
+
+    fn current() -> Option<Self> {
+        RENDER_CONTEXT.with_borrow(Clone::clone)
+    }
+
+    fn locale(&self) -> Option<Locale> {
+        self.locale
+            .as_deref()
+            .and_then(|locale| Locale::try_from(locale.replace('-', "_").as_str()).ok())
+    }
+}
+
+/// Format `datetime` in the user's `timezone`, e.g. `2025-01-07 09:30 EST`.
+pub fn format_datetime(datetime: &DateTime<Utc>, timezone: &Tz) -> String {
+    datetime
+        .with_timezone(timezone)
+        .format("%Y-%m-%d %H:%M %Z")
+        .to_string()
+}
+
+/// Format the date of `datetime` in the user's `timezone`, e.g. `2025-01-07`.
+pub fn format_date(datetime: &DateTime<Utc>, timezone: &Tz) -> String {
+    datetime
+        .with_timezone(timezone)
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// How long ago `datetime` was, e.g. `5 minutes ago`, or how long until it if it's in the future.
+pub fn humanize_age(datetime: &DateTime<Utc>, now: &DateTime<Utc>) -> String {
+    let age = *now - *datetime;
+    let seconds = age.num_seconds().abs();
+
+    if seconds < 60 {
+        return "just now".to_string();
+    }
+
+    let (count, unit) = match seconds {
+        s if s < 60 * 60 => (s / 60, "minute"),
+        s if s < 60 * 60 * 24 => (s / (60 * 60), "hour"),
+        s if s < 60 * 60 * 24 * 30 => (s / (60 * 60 * 24), "day"),
+        s if s < 60 * 60 * 24 * 365 => (s / (60 * 60 * 24 * 30), "month"),
+        s => (s / (60 * 60 * 24 * 365), "year"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+
+    if age.num_seconds() < 0 {
+        format!("in {count} {unit}{plural}")
+    } else {
+        format!("{count} {unit}{plural} ago")
+    }
+}
+
+/// Rinja filters for showing timestamps to the user, in the current [`RenderContext`].
+///
+/// Bring them into scope in the module deriving the template:
+///
+/// ```ignore
+/// use lowboy::view::filters;
+/// ```
+///
+/// ```html
+/// <time>{{ post.created_at|format_datetime }}</time> ({{ post.created_at|humanize_age }})
+/// ```
+pub mod filters {
+    use chrono::{DateTime, Utc};
+    use chrono_tz::Tz;
+
+    use super::RenderContext;
+
+    pub fn format_datetime(datetime: &DateTime<Utc>) -> rinja::Result<String> {
+        let Some(context) = RenderContext::current() else {
+            return Ok(super::format_datetime(datetime, &Tz::UTC));
+        };
+
+        Ok(match context.locale() {
+            Some(locale) => datetime
+                .with_timezone(&context.timezone)
+                .format_localized("%x %X %Z", locale)
+                .to_string(),
+            None => super::format_datetime(datetime, &context.timezone),
+        })
+    }
+
+    pub fn format_date(datetime: &DateTime<Utc>) -> rinja::Result<String> {
+        let Some(context) = RenderContext::current() else {
+            return Ok(super::format_date(datetime, &Tz::UTC));
+        };
+
+        Ok(match context.locale() {
+            Some(locale) => datetime
+                .with_timezone(&context.timezone)
+                .format_localized("%x", locale)
+                .to_string(),
+            None => super::format_date(datetime, &context.timezone),
+        })
+    }
+
+    pub fn humanize_age(datetime: &DateTime<Utc>) -> rinja::Result<String> {
+        let now = RenderContext::current()
+            .map(|context| context.now)
+            .unwrap_or_else(Utc::now);
+
+        Ok(super::humanize_age(datetime, &now))
+    }
+}
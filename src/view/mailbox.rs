@@ -0,0 +1,26 @@
+use rinja::Template;
+
+use crate::mailer::RenderedMail;
+use crate::model::MailboxMessage;
+
+#[derive(Clone, Template)]
+#[template(path = "dev/mailbox.html")]
+pub struct Mailbox {
+    pub messages: Vec<MailboxMessage>,
+    /// The mail templates with previews.
+    pub previews: Vec<String>,
+}
+
+#[derive(Clone, Template)]
+#[template(path = "dev/mailbox-message.html")]
+pub struct MailboxMessageView {
+    pub message: MailboxMessage,
+}
+
+/// A mail template rendered with its preview props.
+#[derive(Clone, Template)]
+#[template(path = "dev/mail-preview.html")]
+pub struct MailPreview {
+    pub name: String,
+    pub mail: RenderedMail,
+}
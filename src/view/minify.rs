@@ -0,0 +1,136 @@
+use axum::body::{to_bytes, Body};
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+/// Elements whose contents are whitespace sensitive, and are left untouched.
+const RAW_TAGS: [&str; 4] = ["pre", "textarea", "script", "style"];
+
+/// Collapse runs of whitespace in `html`, keeping a newline if the run had one.
+///
+/// Browsers collapse whitespace the same way when rendering, so this is safe for everything but
+/// whitespace sensitive elements (`RAW_TAGS`) and quoted attribute values, which are copied as-is.
+pub fn minify_html(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    // Whether `rest` is inside a start or end tag, where quotes start attribute values.
+    let mut in_tag = false;
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(tag) = raw_tag(rest) {
+            let end = closing_tag(rest, tag).unwrap_or(rest.len());
+            output.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if in_tag && (c == '"' || c == '\'') {
+            let end = rest[1..].find(c).map_or(rest.len(), |end| end + 2);
+            output.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if c.is_ascii_whitespace() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_whitespace())
+                .unwrap_or(rest.len());
+            output.push(if rest[..len].contains('\n') { '\n' } else { ' ' });
+            rest = &rest[len..];
+        } else {
+            if c == '<' {
+                in_tag = rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/');
+            } else if c == '>' {
+                in_tag = false;
+            }
+            output.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    output
+}
+
+fn raw_tag(html: &str) -> Option<&'static str> {
+    let name = html.strip_prefix('<')?;
+
+    RAW_TAGS.into_iter().find(|tag| is_tag_name(name, tag))
+}
+
+/// Whether `html` starts with the tag name `tag`, e.g. `pre>` or `pre class=".."` for `pre`.
+fn is_tag_name(html: &str, tag: &str) -> bool {
+    html.get(..tag.len())
+        .is_some_and(|name| name.eq_ignore_ascii_case(tag))
+        && html[tag.len()..].starts_with(|c: char| c == '>' || c == '/' || c.is_ascii_whitespace())
+}
+
+fn closing_tag(html: &str, tag: &str) -> Option<usize> {
+    let mut from = 0;
+
+    while let Some(index) = html[from..].find("</") {
+        let start = from + index;
+        if is_tag_name(&html[start + 2..], tag) {
+            return Some(start);
+        }
+        from = start + 2;
+    }
+
+    None
+}
+
+/// Minify rendered HTML responses, see [`minify_html`].
+pub async fn minify(response: Response) -> Response {
+    let is_html = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"));
+    if !is_html {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(body) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let Ok(html) = std::str::from_utf8(&body) else {
+        return Response::from_parts(parts, Body::from(body));
+    };
+
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(minify_html(html)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whitespace_between_elements_is_collapsed() {
+        let html = "<ul>\n    <li>a</li>   <li>b</li>\n</ul>";
+
+        assert_eq!(minify_html(html), "<ul>\n<li>a</li> <li>b</li>\n</ul>");
+    }
+
+    #[test]
+    fn attribute_values_are_left_untouched() {
+        let html = r#"<input  value="two  spaces"   title='a
+  b'>  <p>"quoted  text"</p>"#;
+
+        assert_eq!(
+            minify_html(html),
+            "<input value=\"two  spaces\" title='a\n  b'> <p>\"quoted text\"</p>"
+        );
+    }
+
+    #[test]
+    fn whitespace_sensitive_elements_are_left_untouched() {
+        let html = "<pre>  a\n    b  </pre>  <textarea name=\"body\">\n  c   d\n</textarea>";
+
+        assert_eq!(
+            minify_html(html),
+            "<pre>  a\n    b  </pre> <textarea name=\"body\">\n  c   d\n</textarea>"
+        );
+    }
+
+    #[test]
+    fn raw_elements_end_at_their_own_closing_tag() {
+        let html = "<pre>a  </prefix>  b</pre>  c";
+
+        assert_eq!(minify_html(html), "<pre>a  </prefix>  b</pre> c");
+    }
+}
@@ -10,10 +10,26 @@ use dyn_clone::DynClone;
 use crate::auth::AuthSession;
 use crate::context::CloneableAppContext;
 use crate::error::{ErrorWrapper, LowboyError, LowboyErrorView};
-use crate::model::{Model, UserModel};
-use crate::{app, lowboy_view};
+use crate::extract::load_app_user;
+use crate::locale::RequestLocale;
+use crate::model::{Announcement, Preferences, UserModel};
+use crate::settings::{SiteName, SupportEmail};
+use crate::{app, controller, lowboy_view, metrics};
 
-pub async fn error_page<App: app::App<AC>, AC: CloneableAppContext>(
+#[cfg(feature = "admin")]
+pub mod admin;
+mod component;
+mod format;
+pub mod mailbox;
+mod minify;
+pub mod preferences;
+pub mod session;
+
+pub use component::*;
+pub use format::*;
+pub use minify::*;
+
+pub(crate) async fn error_page<App: app::App<AC>, AC: CloneableAppContext>(
     State(state): State<AC>,
     auth_session: Option<AuthSession>,
     messages: Option<Messages>,
@@ -57,22 +73,18 @@ pub async fn error_page<App: app::App<AC>, AC: CloneableAppContext>(
     }
 }
 
-pub async fn render_view<App: app::App<AC>, AC: CloneableAppContext>(
+pub(crate) async fn render_view<App: app::App<AC>, AC: CloneableAppContext>(
     State(context): State<AC>,
     auth_session: Option<AuthSession>,
     messages: Option<Messages>,
     response: Response,
 ) -> Result<impl IntoResponse, LowboyError> {
     if let Some(ViewBox(view)) = response.extensions().get::<ViewBox>() {
-        let mut conn = context.database().get().await?;
-        let user = if let Some(AuthSession {
-            user: Some(user), ..
-        }) = auth_session
-        {
-            Some(<App::User as Model>::load(user.id, &mut conn).await?)
-        } else {
-            None
-        };
+        let user = load_app_user::<App, AC>(
+            auth_session.and_then(|session| session.user).map(|user| user.id),
+            context.database(),
+        )
+        .await?;
 
         // @TODO display an error message on every page telling the user their email has not been
         // verified. It shouldn't really be _here_, but just need to make note.
@@ -83,15 +95,68 @@ pub async fn render_view<App: app::App<AC>, AC: CloneableAppContext>(
             "lowboy_version".to_string(),
             env!("VERGEN_GIT_SHA").to_string(),
         );
-        layout_context.insert("app_title".to_string(), App::app_title().to_string());
+        let settings = context.settings();
+        let SiteName(site_name) = settings.get::<SiteName>().await?;
+        layout_context.insert(
+            "app_title".to_string(),
+            site_name.unwrap_or_else(|| App::app_title().to_string()),
+        );
+        if let SupportEmail(Some(support_email)) = settings.get::<SupportEmail>().await? {
+            layout_context.insert("support_email".to_string(), support_email);
+        }
+        layout_context.insert(
+            "lowboy_client_js".to_string(),
+            controller::CLIENT_PATH.to_string(),
+        );
+        layout_context.insert(
+            "lowboy_client_css".to_string(),
+            controller::STYLES_PATH.to_string(),
+        );
+
+        // Expose the user's display preferences, so the layout can e.g. apply their theme.
+        let preferences = match user {
+            Some(ref user) => {
+                let mut conn = metrics::checkout(context.database()).await?;
+                Preferences::for_user(user.id(), &mut conn).await?
+            }
+            None => Preferences::default(),
+        };
+        // Prefer the locale resolved for the request, which falls back to the session, browser
+        // and configured defaults.
+        let locale = response
+            .extensions()
+            .get::<RequestLocale>()
+            .cloned()
+            .unwrap_or_else(|| RequestLocale {
+                locale: preferences.locale().map(str::to_string),
+                timezone: preferences.timezone(),
+            });
+        layout_context.insert("theme".to_string(), preferences.theme().to_string());
+        layout_context.insert("timezone".to_string(), locale.timezone.to_string());
+        if let Some(ref locale) = locale.locale {
+            layout_context.insert("locale".to_string(), locale.clone());
+        }
 
         if let Some(LayoutContext(data)) = response.extensions().get::<LayoutContext>() {
             layout_context.append(&mut data.clone());
         }
 
+        // The announcements for everyone, and for the user's roles.
+        let announcements = {
+            let mut conn = metrics::checkout(context.database()).await?;
+            Announcement::active_for(
+                user.as_ref().map(|user| user.id()),
+                context.clock().now(),
+                &mut conn,
+            )
+            .await?
+        };
+
+        let render_context = locale.render_context(context.clock().now());
+
         // @perf consider switching to .render() over .to_string()
         // @see https://rinja.readthedocs.io/en/stable/performance.html
-        Ok(Html(
+        let html = render_context.scope(|| {
             App::layout(&context)
                 .set_messages(
                     messages
@@ -100,20 +165,29 @@ pub async fn render_view<App: app::App<AC>, AC: CloneableAppContext>(
                 )
                 .set_content(view.to_string())
                 .set_user(user)
+                .set_announcements(Announcements::new(announcements))
                 .set_context(layout_context)
-                .to_string(),
-        )
-        .into_response())
+                .to_string()
+        });
+
+        Ok(Html(html).into_response())
     } else {
         Ok(response)
     }
 }
 
+#[allow(unused_variables)]
 pub trait LowboyLayout<T: UserModel>: ToString + Default {
     fn set_messages(&mut self, messages: Vec<Message>) -> &mut Self;
     fn set_content(&mut self, content: impl LowboyView) -> &mut Self;
     fn set_context(&mut self, context: LayoutContext) -> &mut Self;
     fn set_user(&mut self, user: Option<T>) -> &mut Self;
+
+    /// The active announcements for the user, to show with the [`Announcements`] component.
+    /// They're ignored unless the layout keeps them.
+    fn set_announcements(&mut self, announcements: Announcements) -> &mut Self {
+        self
+    }
 }
 
 pub trait LowboyView: ToString + DynClone + Send + Sync {}
@@ -121,9 +195,11 @@ dyn_clone::clone_trait_object!(LowboyView);
 
 impl<T: ToString + Clone + Send + Sync> LowboyView for T {}
 
+#[doc(hidden)]
 #[derive(Clone)]
 pub struct View<T: LowboyView>(pub T);
 
+#[doc(hidden)]
 #[derive(Clone)]
 pub struct ViewBox(pub Box<dyn LowboyView>);
 
@@ -155,6 +231,7 @@ impl std::ops::Deref for LayoutContext {
     }
 }
 
+#[doc(hidden)]
 #[derive(Clone)]
 pub struct ViewWithContext<T: LowboyView>(pub T, pub LayoutContext);
 
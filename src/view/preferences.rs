@@ -0,0 +1,33 @@
+use chrono_tz::{Tz, TZ_VARIANTS};
+use rinja::Template;
+
+use crate::model::{NotificationPreferences, Theme};
+
+#[derive(Clone, Template)]
+#[template(path = "account/preferences.html")]
+pub struct AccountPreferences {
+    pub theme: Theme,
+    pub locale: String,
+    pub timezone: Tz,
+    pub notifications: NotificationPreferences,
+    /// Users who signed up with OAuth don't have a password to confirm before changing it.
+    pub has_password: bool,
+}
+
+impl AccountPreferences {
+    pub fn themes(&self) -> [Theme; 3] {
+        [Theme::System, Theme::Light, Theme::Dark]
+    }
+
+    pub fn timezones(&self) -> &'static [Tz] {
+        &TZ_VARIANTS
+    }
+
+    pub fn is_theme(&self, theme: &Theme) -> bool {
+        self.theme == *theme
+    }
+
+    pub fn is_timezone(&self, timezone: &Tz) -> bool {
+        self.timezone == *timezone
+    }
+}
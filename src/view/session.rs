@@ -0,0 +1,17 @@
+use rinja::Template;
+
+use crate::session::ActiveSession;
+use crate::view::filters;
+
+#[derive(Clone, Template)]
+#[template(path = "account/sessions.html")]
+pub struct ActiveSessions {
+    pub sessions: Vec<ActiveSession>,
+    pub current: Option<String>,
+}
+
+impl ActiveSessions {
+    pub fn is_current(&self, session: &ActiveSession) -> bool {
+        self.current.as_ref() == Some(&session.id)
+    }
+}
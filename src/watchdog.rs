@@ -0,0 +1,86 @@
+//! Logs requests which are taking too long to complete, to diagnose hangs, e.g. a handler waiting
+//! on a locked SQLite connection or an external API which never responds.
+//!
+//! Requests still running after `request_watchdog` seconds are logged with their route and user,
+//! in the request's span so the log includes its request id, and logged again each time as long
+//! again passes. Once they complete, how long they took is logged too.
+
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use tokio::task::JoinHandle;
+use tracing::{warn, Span};
+
+use crate::AuthSession;
+
+/// Aborts the watchdog when the request completes, or is dropped, e.g. when the client goes away.
+struct Watchdog(JoinHandle<()>);
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Watch each request, logging the ones still running after `threshold`.
+pub(crate) async fn watch(
+    State(threshold): State<Duration>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| request.uri().path(), MatchedPath::as_str)
+        .to_string();
+    let user_id = request
+        .extensions()
+        .get::<AuthSession>()
+        .and_then(|auth_session| auth_session.user.as_ref())
+        .map(|user| user.id);
+
+    let span = Span::current();
+    let started = Instant::now();
+
+    let watchdog = {
+        let (method, route, span) = (method.clone(), route.clone(), span.clone());
+        Watchdog(tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + threshold,
+                threshold,
+            );
+            loop {
+                interval.tick().await;
+                warn!(
+                    parent: &span,
+                    %method,
+                    %route,
+                    user_id,
+                    "request still running after {:.1}s",
+                    started.elapsed().as_secs_f64()
+                );
+            }
+        }))
+    };
+
+    let response = next.run(request).await;
+    drop(watchdog);
+
+    let elapsed = started.elapsed();
+    if elapsed >= threshold {
+        warn!(
+            parent: &span,
+            %method,
+            %route,
+            user_id,
+            status = response.status().as_u16(),
+            "slow request completed after {:.1}s",
+            elapsed.as_secs_f64()
+        );
+    }
+
+    response
+}
@@ -0,0 +1,382 @@
+//! Multi-step forms, e.g. onboarding or a long registration, where each step is its own form and
+//! the user can go back and forth between them before finishing.
+//!
+//! A [`Wizard`] is the data collected across all of its steps, and each [`Step`] is a form which
+//! fills in part of it. Progress is kept in a [`Store`], either the session or, to survive logging
+//! out or switching devices, the [key-value store](crate::kv):
+//!
+//! ```ignore
+//! #[derive(Default, Serialize, Deserialize)]
+//! struct Onboarding {
+//!     profile: Option<ProfileStep>,
+//!     interests: Option<InterestsStep>,
+//! }
+//!
+//! impl Wizard for Onboarding {
+//!     const NAME: &'static str = "onboarding";
+//!     const PATH: &'static str = "/onboarding";
+//!     const STEPS: &'static [&'static str] = &[ProfileStep::NAME, InterestsStep::NAME];
+//!
+//!     async fn complete<AC: CloneableAppContext>(self, context: &AC, user: Option<&User>) -> ... {
+//!         // Save the profile and interests.
+//!     }
+//! }
+//!
+//! async fn submit_profile(..., Form(input): Form<ProfileStep>) -> Result<Response, LowboyError> {
+//!     let store = Store::Session(&session);
+//!     let mut progress = store.load::<Onboarding>().await?;
+//!
+//!     let next = match progress.submit(input) {
+//!         Ok(next) => next,
+//!         Err(errors) => return Ok(/* render the step with `errors` */),
+//!     };
+//!     // Save the step before finishing too, so it isn't lost if completing fails.
+//!     store.save(&progress).await?;
+//!
+//!     match next {
+//!         Some(next) => Ok(hx.redirect(&Onboarding::path(next))),
+//!         None => {
+//!             store.finish(progress, &context, auth_session.user.as_ref()).await?;
+//!             Ok(hx.redirect("/"))
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::collections::BTreeSet;
+use std::future::Future;
+
+use axum::response::Response;
+use chrono::TimeDelta;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use validator::Validate;
+
+use crate::context::CloneableAppContext;
+use crate::error::LowboyError;
+use crate::extract::HxRequest;
+use crate::form::FormErrors;
+use crate::kv::Kv;
+use crate::model::User;
+
+/// How long unfinished progress is kept in the key-value store.
+const EXPIRY_DAYS: i64 = 30;
+
+/// The data collected by a multi-step form, see the [module docs](self).
+pub trait Wizard: Default + Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// Identifies the wizard's progress in its [`Store`].
+    const NAME: &'static str;
+
+    /// Where the wizard's steps are routed, each at `{PATH}/{step}`.
+    const PATH: &'static str;
+
+    /// The names of the steps, in the order they're filled in.
+    const STEPS: &'static [&'static str];
+
+    /// Called with the collected data once every step has been submitted.
+    fn complete<AC: CloneableAppContext>(
+        self,
+        context: &AC,
+        user: Option<&User>,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// The path of `step`.
+    fn path(step: &str) -> String {
+        format!("{}/{step}", Self::PATH)
+    }
+}
+
+/// One of a [`Wizard`]'s steps, a form which is validated on its own when it's submitted.
+pub trait Step<W: Wizard>: Serialize + DeserializeOwned + Validate + Send {
+    /// The step's name, which must be one of [`Wizard::STEPS`].
+    const NAME: &'static str;
+
+    /// The step as it was last submitted, to fill in its form when the user comes back to it.
+    fn get(wizard: &W) -> Option<Self>;
+
+    /// Store the submitted step in the wizard's data.
+    fn set(self, wizard: &mut W);
+}
+
+/// How far through a [`Wizard`] the user is, and what they've filled in so far.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Progress<W> {
+    data: W,
+    submitted: BTreeSet<String>,
+}
+
+impl<W: Wizard> Progress<W> {
+    pub fn data(&self) -> &W {
+        &self.data
+    }
+
+    /// The step as it was last submitted.
+    pub fn form<S: Step<W>>(&self) -> Option<S> {
+        S::get(&self.data)
+    }
+
+    pub fn is_submitted(&self, step: &str) -> bool {
+        self.submitted.contains(step)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        W::STEPS.iter().all(|step| self.is_submitted(step))
+    }
+
+    /// The first step which hasn't been submitted yet, or the last step if they all have.
+    pub fn current(&self) -> &'static str {
+        W::STEPS
+            .iter()
+            .find(|step| !self.is_submitted(step))
+            .or(W::STEPS.last())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Whether `step` can be visited, i.e. it's been submitted or is the current step, so steps
+    /// can't be skipped.
+    pub fn can_visit(&self, step: &str) -> bool {
+        position::<W>(step).is_some_and(|index| {
+            position::<W>(self.current()).is_some_and(|current| index <= current)
+        })
+    }
+
+    /// The step after `step`.
+    pub fn next(&self, step: &str) -> Option<&'static str> {
+        position::<W>(step).and_then(|index| W::STEPS.get(index + 1).copied())
+    }
+
+    /// The step before `step`.
+    pub fn previous(&self, step: &str) -> Option<&'static str> {
+        position::<W>(step)
+            .and_then(|index| index.checked_sub(1))
+            .and_then(|index| W::STEPS.get(index).copied())
+    }
+
+    /// The path of the step before `step`, for its form's back link.
+    pub fn back_path(&self, step: &str) -> Option<String> {
+        self.previous(step).map(W::path)
+    }
+
+    /// Redirect to the current step, unless step `S` can be visited.
+    pub fn redirect_unless_visitable<S: Step<W>>(&self, hx: &HxRequest) -> Option<Response> {
+        (!self.can_visit(S::NAME)).then(|| hx.redirect(&W::path(self.current())))
+    }
+
+    /// Validate and store the submitted step `S`, returning the step to go to next, or `None`
+    /// once every step has been submitted and the wizard can be [finished](Store::finish).
+    pub fn submit<S: Step<W>>(&mut self, input: S) -> Result<Option<&'static str>, FormErrors> {
+        input.validate()?;
+
+        input.set(&mut self.data);
+        self.submitted.insert(S::NAME.to_string());
+
+        Ok(match self.next(S::NAME) {
+            Some(next) => Some(next),
+            None if self.is_complete() => None,
+            None => Some(self.current()),
+        })
+    }
+}
+
+fn position<W: Wizard>(step: &str) -> Option<usize> {
+    W::STEPS.iter().position(|name| *name == step)
+}
+
+/// Where a wizard's [`Progress`] is kept between steps.
+pub enum Store<'a> {
+    /// Kept in the session, so it's lost when the session ends.
+    Session(&'a Session),
+    /// Kept in the key-value store for the user, for 30 days after their last step.
+    Database { kv: Kv<'a>, user_id: i32 },
+}
+
+impl Store<'_> {
+    /// The progress through wizard `W`, starting from the beginning if there's none.
+    pub async fn load<W: Wizard>(&self) -> Result<Progress<W>, LowboyError> {
+        let progress = match self {
+            Self::Session(session) => session.get(&session_key::<W>()).await?,
+            Self::Database { kv, user_id } => kv.get(&kv_key::<W>(*user_id)).await?,
+        };
+
+        Ok(progress.unwrap_or_default())
+    }
+
+    pub async fn save<W: Wizard>(&self, progress: &Progress<W>) -> Result<(), LowboyError> {
+        match self {
+            Self::Session(session) => session.insert(&session_key::<W>(), progress).await?,
+            Self::Database { kv, user_id } => {
+                kv.set(
+                    &kv_key::<W>(*user_id),
+                    progress,
+                    Some(TimeDelta::days(EXPIRY_DAYS)),
+                )
+                .await?
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Forget the progress through wizard `W`, e.g. when the user starts over.
+    pub async fn clear<W: Wizard>(&self) -> Result<(), LowboyError> {
+        match self {
+            Self::Session(session) => {
+                session.remove_value(&session_key::<W>()).await?;
+            }
+            Self::Database { kv, user_id } => {
+                kv.delete(&kv_key::<W>(*user_id)).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Call [`Wizard::complete`] with the collected data, then forget the progress. If completing
+    /// fails the progress is kept, so the user can try again, as long as it was
+    /// [saved](Store::save) with the final step first.
+    pub async fn finish<W: Wizard, AC: CloneableAppContext>(
+        &self,
+        progress: Progress<W>,
+        context: &AC,
+        user: Option<&User>,
+    ) -> Result<(), LowboyError> {
+        progress.data.complete(context, user).await?;
+
+        self.clear::<W>().await
+    }
+}
+
+fn session_key<W: Wizard>() -> String {
+    format!("lowboy.wizard.{}", W::NAME)
+}
+
+fn kv_key<W: Wizard>(user_id: i32) -> String {
+    format!("wizard:{}:{user_id}", W::NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tower_sessions::MemoryStore;
+
+    use super::*;
+
+    #[derive(Default, Serialize, Deserialize)]
+    struct Signup {
+        name: Option<NameStep>,
+        plan: Option<PlanStep>,
+    }
+
+    impl Wizard for Signup {
+        const NAME: &'static str = "signup";
+        const PATH: &'static str = "/signup";
+        const STEPS: &'static [&'static str] = &[NameStep::NAME, PlanStep::NAME];
+
+        async fn complete<AC: CloneableAppContext>(
+            self,
+            _context: &AC,
+            _user: Option<&User>,
+        ) -> anyhow::Result<()> {
+            match self.plan {
+                Some(PlanStep { ref plan }) if plan == "unavailable" => {
+                    anyhow::bail!("the plan is unavailable")
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Validate)]
+    struct NameStep {
+        #[validate(length(min = 1))]
+        name: String,
+    }
+
+    impl Step<Signup> for NameStep {
+        const NAME: &'static str = "name";
+
+        fn get(wizard: &Signup) -> Option<Self> {
+            wizard.name.clone()
+        }
+
+        fn set(self, wizard: &mut Signup) {
+            wizard.name = Some(self);
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Validate)]
+    struct PlanStep {
+        plan: String,
+    }
+
+    impl Step<Signup> for PlanStep {
+        const NAME: &'static str = "plan";
+
+        fn get(wizard: &Signup) -> Option<Self> {
+            wizard.plan.clone()
+        }
+
+        fn set(self, wizard: &mut Signup) {
+            wizard.plan = Some(self);
+        }
+    }
+
+    fn name(name: &str) -> NameStep {
+        NameStep {
+            name: name.to_string(),
+        }
+    }
+
+    fn plan(plan: &str) -> PlanStep {
+        PlanStep {
+            plan: plan.to_string(),
+        }
+    }
+
+    #[test]
+    fn steps_are_submitted_in_order() {
+        let mut progress = Progress::<Signup>::default();
+        assert_eq!(progress.current(), "name");
+        assert!(!progress.can_visit("plan"));
+
+        assert!(progress.submit(name("")).is_err());
+        assert!(!progress.is_submitted("name"));
+
+        assert_eq!(progress.submit(name("marc")).unwrap(), Some("plan"));
+        assert_eq!(progress.current(), "plan");
+        assert!(progress.can_visit("name"));
+        assert!(progress.can_visit("plan"));
+        assert_eq!(progress.back_path("plan").as_deref(), Some("/signup/name"));
+        assert_eq!(progress.form::<NameStep>(), Some(name("marc")));
+
+        assert_eq!(progress.submit(plan("free")).unwrap(), None);
+        assert!(progress.is_complete());
+    }
+
+    #[tokio::test]
+    async fn failing_to_finish_keeps_the_saved_final_step() {
+        let session = Session::new(None, Arc::new(MemoryStore::default()), None);
+        let store = Store::Session(&session);
+        let context = crate::test::context().await;
+
+        let mut progress = store.load::<Signup>().await.unwrap();
+        progress.submit(name("marc")).unwrap();
+        assert_eq!(progress.submit(plan("unavailable")).unwrap(), None);
+        store.save(&progress).await.unwrap();
+        assert!(store.finish(progress, &context, None).await.is_err());
+
+        let mut progress = store.load::<Signup>().await.unwrap();
+        assert!(progress.is_complete());
+        assert_eq!(progress.form::<PlanStep>(), Some(plan("unavailable")));
+
+        // Trying again with another plan finishes, and forgets the progress.
+        assert_eq!(progress.submit(plan("free")).unwrap(), None);
+        store.save(&progress).await.unwrap();
+        store.finish(progress, &context, None).await.unwrap();
+        let progress = store.load::<Signup>().await.unwrap();
+        assert!(!progress.is_submitted("name"));
+    }
+}
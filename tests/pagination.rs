@@ -0,0 +1,128 @@
+//! Offset and cursor pagination, as implemented for any select query by `Paginate`.
+
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use lowboy::model::Paginate;
+use lowboy::Connection;
+
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        item (id) {
+            id -> Integer,
+            name -> Text,
+        }
+    }
+}
+
+use schema::item;
+
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::item)]
+#[diesel(check_for_backend(Sqlite))]
+pub struct Item {
+    pub id: i32,
+    pub name: String,
+}
+
+/// A connection with `count` items, named `item 1` onwards.
+async fn connection(count: i32) -> Connection {
+    let mut conn = Connection::establish(":memory:").await.unwrap();
+    diesel::sql_query("CREATE TABLE item (id INTEGER PRIMARY KEY NOT NULL, name TEXT NOT NULL)")
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    for id in 1..=count {
+        diesel::insert_into(item::table)
+            .values((item::id.eq(id), item::name.eq(format!("item {id}"))))
+            .execute(&mut conn)
+            .await
+            .unwrap();
+    }
+
+    conn
+}
+
+fn ids(items: &[Item]) -> Vec<i32> {
+    items.iter().map(|item| item.id).collect()
+}
+
+#[tokio::test]
+async fn paginate_loads_the_page_and_the_total() {
+    let mut conn = connection(5).await;
+
+    let page = item::table
+        .select(Item::as_select())
+        .order_by(item::id.asc())
+        .paginate(2, 2)
+        .load_page::<Item>(&mut conn)
+        .await
+        .unwrap();
+
+    assert_eq!(ids(&page.items), vec![3, 4]);
+    assert_eq!(page.total, 5);
+    assert_eq!(page.pages(), 3);
+    assert_eq!(page.prev_page(), Some(1));
+    assert_eq!(page.next_page(), Some(3));
+}
+
+#[tokio::test]
+async fn pages_past_the_end_still_have_the_total() {
+    let mut conn = connection(5).await;
+
+    let page = item::table
+        .select(Item::as_select())
+        .paginate(4, 2)
+        .load_page::<Item>(&mut conn)
+        .await
+        .unwrap();
+
+    assert!(page.items.is_empty());
+    assert_eq!(page.total, 5);
+    assert_eq!(page.next_page(), None);
+}
+
+#[tokio::test]
+async fn the_total_only_counts_rows_the_query_returns() {
+    let mut conn = connection(5).await;
+
+    let page = item::table
+        .select(Item::as_select())
+        .filter(item::id.gt(2))
+        .paginate(1, 2)
+        .load_page::<Item>(&mut conn)
+        .await
+        .unwrap();
+
+    assert_eq!(ids(&page.items), vec![3, 4]);
+    assert_eq!(page.total, 3);
+}
+
+#[tokio::test]
+async fn paginate_after_loads_the_rows_after_the_cursor() {
+    let mut conn = connection(5).await;
+
+    let page = item::table
+        .select(Item::as_select())
+        .paginate_after("id", Some(2), 2)
+        .load_page::<Item>(&mut conn)
+        .await
+        .unwrap();
+
+    assert_eq!(ids(&page.items), vec![3, 4]);
+    assert_eq!(page.total, 5);
+    assert_eq!(page.next_cursor, Some(4));
+
+    let last = item::table
+        .select(Item::as_select())
+        .paginate_after("id", Some(4), 2)
+        .load_page::<Item>(&mut conn)
+        .await
+        .unwrap();
+
+    assert_eq!(ids(&last.items), vec![5]);
+    assert_eq!(last.next_cursor, None);
+}
@@ -0,0 +1,47 @@
+//! Guards lowboy's public API against accidental breakage.
+//!
+//! The whole public API, as rustdoc sees it, is snapshotted in `tests/public_api.txt`. Building
+//! rustdoc JSON needs a nightly toolchain, so the test is ignored by default, run it with:
+//!
+//! ```sh
+//! just public-api
+//! ```
+//!
+//! When a change to the API is intended, update the snapshot with:
+//!
+//! ```sh
+//! UPDATE_EXPECT=1 just public-api
+//! ```
+
+// Fails to compile if anything is removed from, or renamed in, the prelude.
+#[allow(unused_imports)]
+use lowboy::prelude::{
+    authorize_owner, db_enum, login_required, lowboy_view, permission_required, view_data, App,
+    AppContext, AppUser, AuthSession, BulkAction, BulkActions, BulkRequest, CloneableAppContext,
+    Config, Connection, Context, DatabaseConnection, EnsureAppUser, Events, FormErrors, HxRequest,
+    JobQueue, LayoutContext, LoadBySlug, LoadPath, LoadSlug, LoginForm, Lowboy, LowboyAuth,
+    LowboyContext, LowboyEmailVerificationView, LowboyError, LowboyErrorView, LowboyLayout,
+    LowboyLoginForm, LowboyLoginView, LowboyRegisterForm, LowboyRegisterView, LowboyView, Model,
+    ModelEvent, Owned, Paginate, Publish, RegistrationDetails, RegistrationForm, ServeOptions,
+    SoftDelete, TransactionalConnection, UserModel, ValidatedQuery, Versioned,
+};
+
+#[test]
+#[ignore = "needs a nightly toolchain, run it with `just public-api`"]
+fn public_api_matches_snapshot() {
+    rustup_toolchain::install(public_api::MINIMUM_NIGHTLY_RUST_VERSION)
+        .expect("the nightly toolchain should install");
+
+    let rustdoc_json = rustdoc_json::Builder::default()
+        .toolchain(public_api::MINIMUM_NIGHTLY_RUST_VERSION)
+        .manifest_path(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+        .all_features(true)
+        .build()
+        .expect("rustdoc JSON should build");
+
+    let api = public_api::Builder::from_rustdoc_json(rustdoc_json)
+        .build()
+        .expect("the public API should be read from the rustdoc JSON");
+
+    expect_test::expect_file!["public_api.txt"].assert_eq(&api.to_string());
+}
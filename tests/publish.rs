@@ -0,0 +1,150 @@
+//! Publishing, as implemented for models by `#[lowboy(published_at)]` and
+//! `#[lowboy(publish_at)]`.
+#![allow(dead_code)]
+
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use lowboy::model::{Model, Publication, PublicationState, Publish};
+use lowboy::Connection;
+use lowboy_record::prelude::*;
+
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        article (id) {
+            id -> Integer,
+            title -> Text,
+            published_at -> Nullable<TimestamptzSqlite>,
+            publish_at -> Nullable<TimestamptzSqlite>,
+        }
+    }
+}
+
+use schema::article;
+
+#[apply(lowboy_record!)]
+#[derive(Debug, Default, Queryable, Identifiable, Selectable, Insertable)]
+#[diesel(table_name = crate::schema::article)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Article {
+    pub id: i32,
+    pub title: String,
+    #[lowboy(published_at)]
+    pub published_at: Option<DateTime<Utc>>,
+    #[lowboy(publish_at)]
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+#[async_trait::async_trait]
+impl Model for Article {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = AsSelect<ArticleRecord, Sqlite>;
+    type FromClause = article::table;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = ArticleRecord::TABLE_NAME;
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        article::table
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        ArticleRecord::as_select()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        let record = Self::query().filter(article::id.eq(id)).first(conn).await?;
+
+        Self::from_record(&record, conn).await
+    }
+}
+
+async fn connection() -> Connection {
+    let mut conn = Connection::establish(":memory:").await.unwrap();
+    diesel::sql_query(
+        "CREATE TABLE article (
+            id INTEGER PRIMARY KEY NOT NULL,
+            title TEXT NOT NULL,
+            published_at TIMESTAMP,
+            publish_at TIMESTAMP
+        )",
+    )
+    .execute(&mut conn)
+    .await
+    .unwrap();
+
+    conn
+}
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+}
+
+async fn create(title: &str, conn: &mut Connection) -> i32 {
+    Article::new_record(title).create(conn).await.unwrap().id
+}
+
+async fn set_publication(id: i32, publication: Publication, conn: &mut Connection) {
+    Article::set_publication(id, publication, now(), conn)
+        .await
+        .unwrap();
+}
+
+async fn state(id: i32, conn: &mut Connection) -> PublicationState {
+    Article::load(id, conn).await.unwrap().publication_state()
+}
+
+#[tokio::test]
+async fn set_publication_moves_the_model_through_its_workflow() {
+    let mut conn = connection().await;
+    let id = create("title", &mut conn).await;
+    assert_eq!(state(id, &mut conn).await, PublicationState::Draft);
+
+    let later = Publication::At(now() + TimeDelta::hours(1));
+    set_publication(id, later, &mut conn).await;
+    assert_eq!(state(id, &mut conn).await, PublicationState::Scheduled);
+
+    // Times which have already passed publish it now.
+    let earlier = Publication::At(now() - TimeDelta::hours(1));
+    set_publication(id, earlier, &mut conn).await;
+    let published = Article::load(id, &mut conn).await.unwrap();
+    assert_eq!(published.publication_state(), PublicationState::Published);
+    assert_eq!(published.published_at, Some(now()));
+    assert_eq!(published.publish_at, None);
+
+    set_publication(id, Publication::Draft, &mut conn).await;
+    assert_eq!(state(id, &mut conn).await, PublicationState::Draft);
+}
+
+#[tokio::test]
+async fn publish_due_publishes_scheduled_models_as_of_when_they_were_due() {
+    let mut conn = connection().await;
+    let due = create("due", &mut conn).await;
+    let pending = create("pending", &mut conn).await;
+    let draft = create("draft", &mut conn).await;
+    let due_at = now() - TimeDelta::minutes(5);
+    Article::schedule(due, due_at, &mut conn).await.unwrap();
+    let later = now() + TimeDelta::minutes(5);
+    Article::schedule(pending, later, &mut conn).await.unwrap();
+
+    let published = Article::publish_due(now(), &mut conn).await.unwrap();
+    assert_eq!(published, vec![due]);
+
+    let article = Article::load(due, &mut conn).await.unwrap();
+    assert_eq!(article.published_at, Some(due_at));
+    assert_eq!(article.publish_at, None);
+    assert_eq!(state(pending, &mut conn).await, PublicationState::Scheduled);
+    assert_eq!(state(draft, &mut conn).await, PublicationState::Draft);
+
+    // Nothing is published twice.
+    let published = Article::publish_due(now(), &mut conn).await.unwrap();
+    assert!(published.is_empty());
+}
@@ -0,0 +1,213 @@
+//! Soft deletion, as implemented for models by `#[lowboy(soft_delete)]`.
+#![allow(dead_code)]
+
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::result::Error;
+use diesel::sqlite::Sqlite;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use lowboy::model::{Model, SoftDelete};
+use lowboy::Connection;
+use lowboy_record::prelude::*;
+
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        note (id) {
+            id -> Integer,
+            content -> Text,
+            deleted_at -> Nullable<TimestamptzSqlite>,
+        }
+    }
+}
+
+use schema::note;
+
+#[apply(lowboy_record!)]
+#[derive(Debug, Default, Queryable, Identifiable, Selectable, Insertable)]
+#[diesel(table_name = crate::schema::note)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Note {
+    pub id: i32,
+    pub content: String,
+    #[lowboy(soft_delete)]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[diesel::dsl::auto_type]
+fn note_from_clause() -> _ {
+    note::table.filter(note::deleted_at.is_null())
+}
+
+#[async_trait::async_trait]
+impl Model for Note {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = AsSelect<NoteRecord, Sqlite>;
+    type FromClause = note_from_clause;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = NoteRecord::TABLE_NAME;
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        note_from_clause()
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        NoteRecord::as_select()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        let record = Self::query().filter(note::id.eq(id)).first(conn).await?;
+
+        Self::from_record(&record, conn).await
+    }
+}
+
+async fn connection() -> Connection {
+    let mut conn = Connection::establish(":memory:").await.unwrap();
+    diesel::sql_query(
+        "CREATE TABLE note (
+            id INTEGER PRIMARY KEY NOT NULL,
+            content TEXT NOT NULL,
+            deleted_at TIMESTAMP
+        )",
+    )
+    .execute(&mut conn)
+    .await
+    .unwrap();
+
+    conn
+}
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+}
+
+#[tokio::test]
+async fn trash_hides_the_model_until_its_restored() {
+    let mut conn = connection().await;
+    let note = Note::new_record("note").create(&mut conn).await.unwrap();
+
+    assert_eq!(Note::trash(note.id, now(), &mut conn).await.unwrap(), 1);
+    assert!(matches!(Note::load(note.id, &mut conn).await, Err(Error::NotFound)));
+
+    let trashed = Note::load_with_trashed(note.id, &mut conn).await.unwrap();
+    assert!(trashed.is_trashed());
+    assert_eq!(trashed.deleted_at(), Some(now()));
+
+    // Trashing it again keeps when it was first trashed.
+    let later = now() + TimeDelta::hours(1);
+    assert_eq!(Note::trash(note.id, later, &mut conn).await.unwrap(), 0);
+    let trashed = Note::trashed(&mut conn).await.unwrap();
+    assert_eq!(trashed.len(), 1);
+    assert_eq!(trashed[0].deleted_at, Some(now()));
+
+    assert_eq!(Note::restore(note.id, &mut conn).await.unwrap(), 1);
+    assert_eq!(Note::restore(note.id, &mut conn).await.unwrap(), 0);
+    assert!(!Note::load(note.id, &mut conn).await.unwrap().is_trashed());
+    assert!(Note::trashed(&mut conn).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn restore_since_leaves_models_trashed_before_it() {
+    let mut conn = connection().await;
+    let id = Note::new_record("note").create(&mut conn).await.unwrap().id;
+    Note::trash(id, now(), &mut conn).await.unwrap();
+
+    let after = now() + TimeDelta::seconds(1);
+    let restored = Note::restore_since(id, after, &mut conn).await.unwrap();
+    assert_eq!(restored, 0);
+    let note = Note::load_with_trashed(id, &mut conn).await.unwrap();
+    assert!(note.is_trashed());
+
+    let before = now() - TimeDelta::seconds(1);
+    let restored = Note::restore_since(id, before, &mut conn).await.unwrap();
+    assert_eq!(restored, 1);
+    assert!(Note::load(id, &mut conn).await.is_ok());
+}
+
+#[tokio::test]
+async fn force_delete_removes_the_model_trashed_or_not() {
+    let mut conn = connection().await;
+    let kept = Note::new_record("kept").create(&mut conn).await.unwrap();
+    let trashed = Note::new_record("trashed").create(&mut conn).await.unwrap();
+    Note::trash(trashed.id, now(), &mut conn).await.unwrap();
+
+    assert_eq!(Note::force_delete(kept.id, &mut conn).await.unwrap(), 1);
+    assert_eq!(Note::force_delete(trashed.id, &mut conn).await.unwrap(), 1);
+    assert!(matches!(
+        Note::load_with_trashed(kept.id, &mut conn).await,
+        Err(Error::NotFound)
+    ));
+    assert!(matches!(
+        Note::load_with_trashed(trashed.id, &mut conn).await,
+        Err(Error::NotFound)
+    ));
+}
+
+#[tokio::test]
+async fn delete_trashed_and_purge_only_remove_trashed_models() {
+    let mut conn = connection().await;
+    let kept = Note::new_record("kept").create(&mut conn).await.unwrap();
+    let old = Note::new_record("old").create(&mut conn).await.unwrap();
+    let recent = Note::new_record("recent").create(&mut conn).await.unwrap();
+    let long_ago = now() - TimeDelta::days(31);
+    Note::trash(old.id, long_ago, &mut conn).await.unwrap();
+    Note::trash(recent.id, now(), &mut conn).await.unwrap();
+
+    assert_eq!(Note::delete_trashed(kept.id, &mut conn).await.unwrap(), 0);
+
+    let cutoff = now() - TimeDelta::days(30);
+    let purged = Note::purge_trashed(cutoff, &mut conn).await.unwrap();
+    assert_eq!(purged, vec![old.id]);
+
+    assert_eq!(Note::delete_trashed(recent.id, &mut conn).await.unwrap(), 1);
+    assert!(Note::trashed(&mut conn).await.unwrap().is_empty());
+    assert!(Note::load(kept.id, &mut conn).await.is_ok());
+}
+
+#[tokio::test]
+async fn delete_record_trashes_the_model() {
+    let mut conn = connection().await;
+    let id = Note::new_record("note").create(&mut conn).await.unwrap().id;
+    let note = Note::load(id, &mut conn).await.unwrap();
+
+    assert_eq!(note.delete_record(now(), &mut conn).await.unwrap(), 1);
+    assert!(matches!(Note::load(id, &mut conn).await, Err(Error::NotFound)));
+    let note = Note::load_with_trashed(id, &mut conn).await.unwrap();
+    assert_eq!(note.deleted_at, Some(now()));
+}
+
+#[tokio::test]
+async fn trashed_queries_can_be_filtered_further() {
+    let mut conn = connection().await;
+    let kept = Note::new_record("kept").create(&mut conn).await.unwrap();
+    let first = Note::new_record("first").create(&mut conn).await.unwrap();
+    let second = Note::new_record("second").create(&mut conn).await.unwrap();
+    Note::trash(first.id, now(), &mut conn).await.unwrap();
+    Note::trash(second.id, now(), &mut conn).await.unwrap();
+
+    let ids: Vec<i32> = Note::with_trashed()
+        .order_by(note::id.asc())
+        .load::<NoteRecord>(&mut conn)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|record| record.id)
+        .collect();
+    assert_eq!(ids, vec![kept.id, first.id, second.id]);
+
+    let trashed: Vec<NoteRecord> = Note::only_trashed()
+        .filter(note::content.eq("second"))
+        .load(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(trashed.len(), 1);
+    assert_eq!(trashed[0].id, second.id);
+}
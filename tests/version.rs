@@ -0,0 +1,167 @@
+//! Versioning, as implemented for models by `#[lowboy(versioned)]`.
+#![allow(dead_code)]
+
+use chrono::{DateTime, TimeZone, Utc};
+use diesel::dsl::{AsSelect, Select, SqlTypeOf};
+use diesel::prelude::*;
+use diesel::result::Error;
+use diesel::sqlite::Sqlite;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use lowboy::actor::RequestActor;
+use lowboy::model::{Model, VersionAction, Versioned};
+use lowboy::Connection;
+use lowboy_record::prelude::*;
+
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        page (id) {
+            id -> Integer,
+            title -> Text,
+            body -> Nullable<Text>,
+        }
+    }
+}
+
+use schema::page;
+
+#[apply(lowboy_record!)]
+#[lowboy(versioned)]
+#[derive(Debug, Default, Queryable, Identifiable, Selectable, Insertable)]
+#[diesel(table_name = crate::schema::page)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Page {
+    pub id: i32,
+    pub title: String,
+    pub body: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Model for Page {
+    type RowSqlType = SqlTypeOf<Self::SelectClause>;
+    type SelectClause = AsSelect<PageRecord, Sqlite>;
+    type FromClause = page::table;
+    type Query = Select<Self::FromClause, Self::SelectClause>;
+
+    const TABLE_NAME: &'static str = PageRecord::TABLE_NAME;
+
+    fn query() -> Self::Query {
+        Self::from_clause().select(Self::select_clause())
+    }
+
+    fn from_clause() -> Self::FromClause {
+        page::table
+    }
+
+    fn select_clause() -> Self::SelectClause {
+        PageRecord::as_select()
+    }
+
+    async fn load(id: i32, conn: &mut Connection) -> QueryResult<Self> {
+        let record = Self::query().filter(page::id.eq(id)).first(conn).await?;
+
+        Self::from_record(&record, conn).await
+    }
+}
+
+async fn connection() -> Connection {
+    let mut conn = Connection::establish(":memory:").await.unwrap();
+    diesel::sql_query(
+        "CREATE TABLE page (
+            id INTEGER PRIMARY KEY NOT NULL,
+            title TEXT NOT NULL,
+            body TEXT
+        )",
+    )
+    .execute(&mut conn)
+    .await
+    .unwrap();
+    diesel::sql_query(
+        "CREATE TABLE model_version (
+            id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+            model TEXT NOT NULL,
+            record_id INTEGER NOT NULL,
+            action TEXT NOT NULL,
+            data TEXT NOT NULL,
+            user_id INTEGER,
+            created_at DATETIME NOT NULL
+        )",
+    )
+    .execute(&mut conn)
+    .await
+    .unwrap();
+
+    conn
+}
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+}
+
+#[tokio::test]
+async fn update_record_snapshots_the_model_first() {
+    let mut conn = connection().await;
+    let id = Page::new_record("Draft")
+        .with_body(Some("Hello"))
+        .create(&mut conn)
+        .await
+        .unwrap()
+        .id;
+
+    let mut page = Page::load(id, &mut conn).await.unwrap();
+    page.title = "Hello".to_string();
+    page.body = None;
+    let record = page.update_record(now(), &mut conn).await.unwrap();
+    assert_eq!(record.title, "Hello");
+    assert_eq!(record.body, None);
+
+    let versions = Page::versions(id, &mut conn).await.unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].model, "page");
+    assert_eq!(versions[0].action, VersionAction::Update);
+    assert_eq!(versions[0].user_id, None);
+    assert_eq!(versions[0].created_at, now());
+    assert_eq!(versions[0].value()["title"], "Draft");
+    assert_eq!(versions[0].value()["body"], "Hello");
+
+    let changes = Page::changes(versions[0].id, &mut conn).await.unwrap();
+    let fields: Vec<_> = changes.into_iter().map(|change| change.field).collect();
+    assert_eq!(fields, vec!["body", "title"]);
+}
+
+#[tokio::test]
+async fn versions_are_attributed_to_the_current_actor() {
+    let mut conn = connection().await;
+    let id = Page::new_record("Draft").create(&mut conn).await.unwrap().id;
+    let page = Page::load(id, &mut conn).await.unwrap();
+
+    RequestActor::on_behalf_of(7)
+        .scope(page.update_record(now(), &mut conn))
+        .await
+        .unwrap();
+
+    let versions = Page::versions(id, &mut conn).await.unwrap();
+    assert_eq!(versions[0].user_id, Some(7));
+}
+
+#[tokio::test]
+async fn deleted_models_can_be_restored_from_their_last_version() {
+    let mut conn = connection().await;
+    let id = Page::new_record("Gone").create(&mut conn).await.unwrap().id;
+
+    let page = Page::load(id, &mut conn).await.unwrap();
+    assert_eq!(page.delete_record(now(), &mut conn).await.unwrap(), 1);
+    assert!(matches!(Page::load(id, &mut conn).await, Err(Error::NotFound)));
+
+    let versions = Page::versions(id, &mut conn).await.unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].action, VersionAction::Delete);
+
+    let restored = Page::restore_version(versions[0].id, None, now(), &mut conn)
+        .await
+        .unwrap();
+    assert_eq!(restored.id, id);
+    assert_eq!(restored.title, "Gone");
+    assert_eq!(Page::load(id, &mut conn).await.unwrap().title, "Gone");
+}